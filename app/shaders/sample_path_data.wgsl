@@ -0,0 +1,87 @@
+// Bins the data of one path into one row of the 1D viewer's data
+// buffer: each bin gets the mean of the values of the path's nodes in
+// its pangenome range, weighted by overlap, or -inf if the path has no
+// nodes in the range. Matches `sampling::sample_data_into_buffer`.
+
+struct Params {
+  view_start: u32,
+  bin_size: u32,
+  used_bins: u32,
+  bin_count: u32,
+  // index of the row's first bin, past the buffer's prefix
+  row_offset: u32,
+  node_count: u32,
+  _pad0: u32,
+  _pad1: u32,
+}
+
+// the nodes of the path, sorted by pangenome position
+struct PathNode {
+  start: u32,
+  end: u32,
+  value: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> nodes: array<PathNode>;
+// starts with the [u32; 2] prefix of the slot cache data buffer
+@group(0) @binding(2) var<storage, read_write> data: array<f32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+  let bin = id.x;
+  if (bin >= params.bin_count) {
+    return;
+  }
+
+  let out_ix = 2u + params.row_offset + bin;
+
+  if (bin >= params.used_bins) {
+    data[out_ix] = 0.0;
+    return;
+  }
+
+  let s = params.view_start + bin * params.bin_size;
+  let e = s + params.bin_size;
+
+  // the first node that ends after the start of the bin
+  var lo = 0u;
+  var hi = params.node_count;
+  loop {
+    if (lo >= hi) {
+      break;
+    }
+    let mid = (lo + hi) / 2u;
+    if (nodes[mid].end <= s) {
+      lo = mid + 1u;
+    } else {
+      hi = mid;
+    }
+  }
+
+  var sum_len = 0u;
+  var sum_val = 0.0;
+
+  var i = lo;
+  loop {
+    if (i >= params.node_count) {
+      break;
+    }
+    let node = nodes[i];
+    if (node.start >= e) {
+      break;
+    }
+
+    let len = min(node.end, e) - max(node.start, s);
+    sum_len += len;
+    sum_val += node.value * f32(len);
+    i += 1u;
+  }
+
+  if (sum_len > 0u) {
+    data[out_ix] = sum_val / f32(sum_len);
+  } else {
+    // negative infinity marks empty bins
+    data[out_ix] = bitcast<f32>(0xff800000u);
+  }
+}
@@ -4,21 +4,83 @@ use std::sync::Arc;
 
 use waragraph_core::graph::{Bp, PathId, PathIndex};
 
+pub mod filter;
+pub mod progress;
+pub mod search;
+pub mod style;
+
+use progress::{AnnotationLoadProgress, ProgressReader};
+use style::AnnotationStyle;
+
+/// The set that annotations created in the viewer are added to
+pub const USER_SET_NAME: &str = "Regions";
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Annotation {
     pub path: PathId,
     pub range: std::ops::Range<Bp>,
     pub label: Arc<String>,
     pub color: Option<egui::Color32>,
+    /// The parts of `range` that are drawn thick, e.g. the exons of a
+    /// BED12 record; if empty, all of `range` is
+    pub blocks: Vec<std::ops::Range<Bp>>,
+    /// The record extended past the end of its path, and has been
+    /// clamped to it
+    pub out_of_bounds: bool,
+    /// Columns and attributes of the record, e.g. `type` and `ID` of
+    /// a GFF record, or `strand` and `itemRgb` of a BED record
+    pub attributes: Vec<(String, String)>,
+    /// The color of the label text, from the BED `itemRgb` column or
+    /// shaded by the score; the theme's label color if `None`
+    pub label_color: Option<egui::Color32>,
+}
+
+impl Annotation {
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find_map(|(k, v)| (k == key).then_some(v.as_str()))
+    }
+
+    /// Clamps the range and blocks to `0..path_len`; records that
+    /// start past the end are pinned to the last base pair of the
+    /// path. Sets and returns `out_of_bounds`.
+    fn fit_to_path(&mut self, path_len: Bp) -> bool {
+        let end = path_len.0;
+        let clamp = |r: &std::ops::Range<Bp>| {
+            let start = r.start.0.min(end.saturating_sub(1));
+            Bp(start)..Bp(r.end.0.clamp(start, end))
+        };
+
+        self.out_of_bounds = self.range.end > path_len;
+
+        if self.out_of_bounds {
+            self.range = clamp(&self.range);
+            self.blocks = self
+                .blocks
+                .iter()
+                .filter(|b| b.start < path_len)
+                .map(clamp)
+                .collect();
+        }
+
+        self.out_of_bounds
+    }
 }
 
+#[derive(Clone)]
 pub struct AnnotationSet {
     pub name: String,
     pub annotations: Vec<Annotation>,
     pub path_annotations: HashMap<PathId, Vec<usize>>,
+    /// The number of records that extended past the end of their
+    /// path
+    pub out_of_bounds: usize,
+    /// The file the set was loaded from
+    pub source: Option<std::path::PathBuf>,
 }
 
-fn annotation_set_name(
+pub(crate) fn annotation_set_name(
     file_path: impl AsRef<std::path::Path>,
     name: Option<&str>,
 ) -> String {
@@ -39,110 +101,300 @@ impl AnnotationSet {
         self.annotations.get(annot_id.0)
     }
 
+    /// Writes the annotations as BED9, with the path names as the
+    /// sequence names, and the annotation colors, if any, as
+    /// `itemRgb`.
+    pub fn write_bed(
+        &self,
+        graph: &PathIndex,
+        mut out: impl std::io::Write,
+    ) -> std::io::Result<()> {
+        for annot in self.annotations.iter() {
+            let Some(path_name) = graph.path_names.get_by_left(&annot.path)
+            else {
+                continue;
+            };
+
+            let (start, end) = (annot.range.start.0, annot.range.end.0);
+            let rgb = annot
+                .color
+                .map(|c| format!("{},{},{}", c.r(), c.g(), c.b()))
+                .unwrap_or_else(|| "0".to_string());
+
+            write!(out, "{path_name}\t{start}\t{end}\t{}", annot.label)?;
+            writeln!(out, "\t0\t.\t{start}\t{end}\t{rgb}")?;
+        }
+        Ok(())
+    }
+
     pub fn from_bed(
         graph: &PathIndex,
         name: Option<&str>,
         path_name_map: impl Fn(&str) -> String,
         bed_path: impl AsRef<std::path::Path>,
     ) -> Result<Self> {
-        use noodles::bed;
+        Self::from_bed_with_progress(graph, name, path_name_map, bed_path, None)
+    }
+
+    fn from_bed_with_progress(
+        graph: &PathIndex,
+        name: Option<&str>,
+        path_name_map: impl Fn(&str) -> String,
+        bed_path: impl AsRef<std::path::Path>,
+        progress: Option<&AnnotationLoadProgress>,
+    ) -> Result<Self> {
         use std::fs::File;
-        use std::io::BufReader;
+        use std::io::{BufRead, BufReader};
 
         let name = annotation_set_name(&bed_path, name);
+        let source = bed_path.as_ref().to_path_buf();
 
-        let mut reader = File::open(bed_path)
-            .map(BufReader::new)
-            .map(bed::Reader::new)?;
+        let file = File::open(bed_path)?;
+        if let Some(progress) = progress {
+            progress.set_file_len(file.metadata()?.len());
+        }
+        let reader = BufReader::new(ProgressReader {
+            inner: file,
+            progress,
+        });
 
         let mut annotations = Vec::new();
         let mut path_annotations: HashMap<_, Vec<_>> = HashMap::new();
+        let mut out_of_bounds = 0;
 
-        for result in reader.records::<4>() {
-            match result {
-                Ok(record) => {
-                    if let Some(name) = record.name() {
-                        let seqid = &record.reference_sequence_name();
+        // the scores of the annotations without `itemRgb`, for shading
+        let mut scores: Vec<(usize, f32)> = Vec::new();
 
-                        let start = record.start_position().get();
-                        let end = record.end_position().get();
+        for (line_ix, line) in reader.lines().enumerate() {
+            let line = line?;
 
-                        let start_bp = Bp(start as u64);
-                        let end_bp = Bp(end as u64);
-                        let range = start_bp..end_bp;
+            if let Some(progress) = progress {
+                progress.check_cancelled()?;
+            }
 
-                        let path_name = path_name_map(seqid);
+            let record = match parse_bed_line(&line) {
+                Ok(Some(record)) => record,
+                Ok(None) => continue,
+                Err(err) => {
+                    log::error!(
+                        "Error parsing BED record on line {}: {err}",
+                        line_ix + 1
+                    );
+                    continue;
+                }
+            };
 
-                        let path_id = graph.path_names.get_by_right(&path_name);
+            let Some(name) = record.name else {
+                continue;
+            };
 
-                        let path_id = if let Some(path) = path_id {
-                            *path
-                        } else {
-                            continue;
-                        };
+            let path_name = path_name_map(record.seqid);
 
-                        let a_id = annotations.len();
+            let path_id = graph.path_names.get_by_right(&path_name);
 
-                        let (label, color) = if let Some((name, color_str)) =
-                            name.rsplit_once(' ')
-                        {
-                            // if `color_str` is a hex-encoded color string #RRGGBB, use that
-                            (
-                                Arc::new(name.to_string()),
-                                parse_color(&color_str),
-                            )
-                        } else {
-                            let [r, g, b] =
-                                crate::color::util::hashed_rgb(&name);
-                            let color = egui::Color32::from_rgb(r, g, b);
-                            (Arc::new(name.to_string()), Some(color))
-                        };
+            let path_id = if let Some(path) = path_id {
+                *path
+            } else {
+                continue;
+            };
 
-                        let annot = Annotation {
-                            path: path_id,
-                            range,
-                            label,
-                            color,
-                        };
+            let a_id = annotations.len();
 
-                        annotations.push(annot);
-                        path_annotations.entry(path_id).or_default().push(a_id);
-                    }
+            let (label, color) =
+                if let Some((name, color_str)) = name.rsplit_once(' ') {
+                    // if `color_str` is a hex-encoded color string #RRGGBB, use that
+                    (Arc::new(name.to_string()), parse_color(&color_str))
+                } else {
+                    let [r, g, b] = crate::color::util::hashed_rgb(&name);
+                    let color = egui::Color32::from_rgb(r, g, b);
+                    (Arc::new(name.to_string()), Some(color))
+                };
+
+            let item_rgb = record
+                .item_rgb
+                .map(|[r, g, b]| egui::Color32::from_rgb(r, g, b));
+
+            // the itemRgb column takes precedence over the above
+            let color = item_rgb.or(color);
+
+            if item_rgb.is_none() {
+                if let Some(score) =
+                    record.score.and_then(|s| s.parse::<f32>().ok())
+                {
+                    scores.push((a_id, score));
                 }
-                Err(err) => {
-                    log::error!("Error parsing GFF record: {err}");
+            }
+
+            let to_bp = |r: std::ops::Range<u64>| Bp(r.start)..Bp(r.end);
+
+            let mut attributes = vec![("name".to_string(), label.to_string())];
+            let optional = [
+                ("score", record.score.map(str::to_string)),
+                ("strand", record.strand.map(str::to_string)),
+                (
+                    "itemRgb",
+                    record.item_rgb.map(|[r, g, b]| format!("{r},{g},{b}")),
+                ),
+            ];
+            attributes.extend(
+                optional
+                    .into_iter()
+                    .filter_map(|(k, v)| Some((k.to_string(), v?))),
+            );
+
+            let mut annot = Annotation {
+                path: path_id,
+                range: to_bp(record.range),
+                label,
+                color,
+                blocks: record.blocks.into_iter().map(to_bp).collect(),
+                out_of_bounds: false,
+                attributes,
+                label_color: item_rgb,
+            };
+
+            if let Some(path_len) = graph.path_len(path_id) {
+                if annot.fit_to_path(path_len) {
+                    out_of_bounds += 1;
                 }
             }
+
+            if let Some(progress) = progress {
+                progress.push(&annot);
+            }
+
+            annotations.push(annot);
+            path_annotations.entry(path_id).or_default().push(a_id);
+        }
+
+        // files where all records have the same score (often 0) don't
+        // use it, so they're left unshaded
+        let (min, max) = scores
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &(_, s)| {
+                (min.min(s), max.max(s))
+            });
+
+        if min < max {
+            for (a_id, score) in scores {
+                let shade = score_shade(score, min..max);
+                annotations[a_id].label_color = Some(shade);
+            }
         }
 
         Ok(Self {
             name,
             annotations,
             path_annotations,
+            out_of_bounds,
+            source: Some(source),
         })
     }
 
+    /// Loads a BED or GFF file, depending on the extension; the
+    /// labels of GFF records are taken from the `gff_attr` attribute.
+    pub fn from_file(
+        graph: &PathIndex,
+        gff_attr: &str,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Self> {
+        Self::from_file_with_progress(graph, gff_attr, path, None)
+    }
+
+    /// Like `from_file`, reporting the progress, and handing over the
+    /// annotations in batches as they're parsed; BED score shading is
+    /// only applied to the returned set.
+    pub fn from_file_with_progress(
+        graph: &PathIndex,
+        gff_attr: &str,
+        path: impl AsRef<std::path::Path>,
+        progress: Option<&AnnotationLoadProgress>,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let ext = path.extension().and_then(|ext| ext.to_str());
+
+        match ext {
+            Some("bed") => Self::from_bed_with_progress(
+                graph,
+                None,
+                |name| name.to_string(),
+                path,
+                progress,
+            ),
+            Some("gff") => {
+                // TODO the name and record functions should be configurable
+                Self::from_gff_with_progress(
+                    graph,
+                    None,
+                    |name| name.to_string(),
+                    // |name| format!("S288C.{name}"),
+                    // |name| format!("SGDref#1#{name}"),
+                    |record| {
+                        let attrs = record.attributes();
+                        let label = attrs.iter().find_map(|entry| {
+                            (entry.key() == gff_attr).then_some(entry.value())
+                        })?;
+
+                        Some(label.to_string())
+                    },
+                    path,
+                    progress,
+                )
+            }
+            _ => Err(anyhow!("Unknown annotation file extension `{ext:?}`")),
+        }
+    }
+
     pub fn from_gff(
         graph: &PathIndex,
         name: Option<&str>,
         path_name_map: impl Fn(&str) -> String,
         record_label: impl Fn(&noodles::gff::Record) -> Option<String>,
         gff_path: impl AsRef<std::path::Path>,
+    ) -> Result<Self> {
+        Self::from_gff_with_progress(
+            graph,
+            name,
+            path_name_map,
+            record_label,
+            gff_path,
+            None,
+        )
+    }
+
+    fn from_gff_with_progress(
+        graph: &PathIndex,
+        name: Option<&str>,
+        path_name_map: impl Fn(&str) -> String,
+        record_label: impl Fn(&noodles::gff::Record) -> Option<String>,
+        gff_path: impl AsRef<std::path::Path>,
+        progress: Option<&AnnotationLoadProgress>,
     ) -> Result<Self> {
         use noodles::gff;
         use std::fs::File;
         use std::io::BufReader;
 
         let name = annotation_set_name(&gff_path, name);
+        let source = gff_path.as_ref().to_path_buf();
 
-        let mut reader = File::open(gff_path)
-            .map(BufReader::new)
-            .map(gff::Reader::new)?;
+        let file = File::open(gff_path)?;
+        if let Some(progress) = progress {
+            progress.set_file_len(file.metadata()?.len());
+        }
+        let mut reader = gff::Reader::new(BufReader::new(ProgressReader {
+            inner: file,
+            progress,
+        }));
 
         let mut annotations = Vec::new();
         let mut path_annotations: HashMap<_, Vec<_>> = HashMap::new();
+        let mut out_of_bounds = 0;
 
         for result in reader.records() {
+            if let Some(progress) = progress {
+                progress.check_cancelled()?;
+            }
+
             match result {
                 Ok(record) => {
                     if let Some(label) = record_label(&record) {
@@ -170,13 +422,39 @@ impl AnnotationSet {
                         let [r, g, b] = crate::color::util::hashed_rgb(&label);
                         let color = egui::Color32::from_rgb(r, g, b);
 
-                        let annot = Annotation {
+                        let mut attributes = vec![
+                            ("type".to_string(), record.ty().to_string()),
+                            ("source".to_string(), record.source().to_string()),
+                            ("strand".to_string(), record.strand().to_string()),
+                        ];
+                        attributes.extend(record.attributes().iter().map(
+                            |entry| {
+                                let key = entry.key().to_string();
+                                (key, entry.value().to_string())
+                            },
+                        ));
+
+                        let mut annot = Annotation {
                             path: path_id,
                             range,
                             label: Arc::new(label.to_string()),
                             color: None,
+                            blocks: Vec::new(),
+                            out_of_bounds: false,
+                            attributes,
+                            label_color: None,
                         };
 
+                        if let Some(path_len) = graph.path_len(path_id) {
+                            if annot.fit_to_path(path_len) {
+                                out_of_bounds += 1;
+                            }
+                        }
+
+                        if let Some(progress) = progress {
+                            progress.push(&annot);
+                        }
+
                         annotations.push(annot);
                         path_annotations.entry(path_id).or_default().push(a_id);
                     }
@@ -191,6 +469,8 @@ impl AnnotationSet {
             name,
             annotations,
             path_annotations,
+            out_of_bounds,
+            source: Some(source),
         })
     }
 }
@@ -210,6 +490,11 @@ pub struct GlobalAnnotationId {
 pub struct AnnotationStore {
     pub annotation_sets: BTreeMap<AnnotationSetId, Arc<AnnotationSet>>,
     next_set_id: AnnotationSetId,
+
+    styles: HashMap<AnnotationSetId, AnnotationStyle>,
+    // incremented when a style or a set's annotations change, so
+    // that views can be rebuilt
+    style_generation: u64,
 }
 
 impl std::default::Default for AnnotationStore {
@@ -217,25 +502,219 @@ impl std::default::Default for AnnotationStore {
         Self {
             annotation_sets: BTreeMap::default(),
             next_set_id: AnnotationSetId(0),
+            styles: HashMap::default(),
+            style_generation: 0,
         }
     }
 }
 
 impl AnnotationStore {
-    pub fn get(&self, id: GlobalAnnotationId) -> &Annotation {
+    /// The annotation, or `None` if it has been removed.
+    pub fn get(&self, id: GlobalAnnotationId) -> Option<&Annotation> {
         self.annotation_sets
             .get(&id.set_id)
             .and_then(|set| set.get(id.annot_id))
-            .unwrap()
     }
 
+    /// Adds the set, along with the style rules saved for its file.
     pub fn insert_set(&mut self, set: AnnotationSet) -> AnnotationSetId {
         let set_id = self.next_set_id;
         self.next_set_id = AnnotationSetId(set_id.0 + 1);
+
+        if let Some(source) = set.source.as_ref() {
+            match AnnotationStyle::load_for(source) {
+                Ok(Some(style)) => {
+                    self.styles.insert(set_id, style);
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    log::error!("Error loading styles for {source:?}: {e:?}")
+                }
+            }
+        }
+
         self.annotation_sets.insert(set_id, Arc::new(set));
         set_id
     }
 
+    /// Appends annotations to the set, e.g. the batches of a file
+    /// that's still being loaded.
+    pub fn extend_set(
+        &mut self,
+        set_id: AnnotationSetId,
+        annots: impl IntoIterator<Item = Annotation>,
+    ) {
+        let Some(set) = self.annotation_sets.get_mut(&set_id) else {
+            return;
+        };
+        let set = Arc::make_mut(set);
+
+        for annot in annots {
+            if annot.out_of_bounds {
+                set.out_of_bounds += 1;
+            }
+            let ix = set.annotations.len();
+            set.path_annotations.entry(annot.path).or_default().push(ix);
+            set.annotations.push(annot);
+        }
+
+        self.style_generation += 1;
+    }
+
+    /// Replaces the annotations of the set, keeping its ID and style.
+    pub fn replace_set(&mut self, set_id: AnnotationSetId, set: AnnotationSet) {
+        if let Some(old) = self.annotation_sets.get_mut(&set_id) {
+            *old = Arc::new(set);
+            self.style_generation += 1;
+        }
+    }
+
+    pub fn remove_set(&mut self, set_id: AnnotationSetId) {
+        if self.annotation_sets.remove(&set_id).is_some() {
+            self.styles.remove(&set_id);
+            self.style_generation += 1;
+        }
+    }
+
+    /// Adds the annotation to the set named `set_name` that wasn't
+    /// loaded from a file, creating the set if there's none.
+    pub fn add_annotation(
+        &mut self,
+        set_name: &str,
+        annot: Annotation,
+    ) -> GlobalAnnotationId {
+        let existing = self.annotation_sets.iter().find_map(|(id, set)| {
+            (set.name == set_name && set.source.is_none()).then_some(*id)
+        });
+
+        let set_id = existing.unwrap_or_else(|| {
+            self.insert_set(AnnotationSet {
+                name: set_name.to_string(),
+                annotations: Vec::new(),
+                path_annotations: HashMap::default(),
+                out_of_bounds: 0,
+                source: None,
+            })
+        });
+
+        let set = self.annotation_sets.get_mut(&set_id).unwrap();
+        let set = Arc::make_mut(set);
+
+        let ix = set.annotations.len();
+        set.path_annotations.entry(annot.path).or_default().push(ix);
+        set.annotations.push(annot);
+
+        self.style_generation += 1;
+
+        GlobalAnnotationId {
+            set_id,
+            annot_id: AnnotationId(ix),
+        }
+    }
+
+    /// Applies `edit` to the annotation, e.g. to change its label or
+    /// color; its path can't be changed, as the set is indexed by it.
+    /// Returns false if there's no such annotation.
+    pub fn edit_annotation(
+        &mut self,
+        id: GlobalAnnotationId,
+        edit: impl FnOnce(&mut Annotation),
+    ) -> bool {
+        let Some(set) = self.annotation_sets.get_mut(&id.set_id) else {
+            return false;
+        };
+        let Some(annot) = Arc::make_mut(set).annotations.get_mut(id.annot_id.0)
+        else {
+            return false;
+        };
+
+        let path = annot.path;
+        edit(annot);
+        annot.path = path;
+
+        self.style_generation += 1;
+        true
+    }
+
+    /// Removes the annotation; the following annotations of its set
+    /// move down one ID.
+    pub fn remove_annotation(&mut self, id: GlobalAnnotationId) {
+        let Some(set) = self.annotation_sets.get_mut(&id.set_id) else {
+            return;
+        };
+        let set = Arc::make_mut(set);
+
+        if id.annot_id.0 >= set.annotations.len() {
+            return;
+        }
+
+        set.annotations.remove(id.annot_id.0);
+
+        set.path_annotations.clear();
+        for (ix, annot) in set.annotations.iter().enumerate() {
+            set.path_annotations.entry(annot.path).or_default().push(ix);
+        }
+
+        self.style_generation += 1;
+    }
+
+    /// The sets that weren't loaded from a file, i.e. those created in
+    /// the viewer.
+    pub fn user_sets(
+        &self,
+    ) -> impl Iterator<Item = (AnnotationSetId, &Arc<AnnotationSet>)> {
+        self.annotation_sets
+            .iter()
+            .filter(|(_, set)| set.source.is_none())
+            .map(|(id, set)| (*id, set))
+    }
+
+    pub fn style(&self, set_id: AnnotationSetId) -> Option<&AnnotationStyle> {
+        self.styles.get(&set_id)
+    }
+
+    pub fn style_generation(&self) -> u64 {
+        self.style_generation
+    }
+
+    /// Replaces the style rules of the set, and saves them next to
+    /// its file.
+    pub fn set_style(
+        &mut self,
+        set_id: AnnotationSetId,
+        style: AnnotationStyle,
+    ) {
+        let source = self
+            .annotation_sets
+            .get(&set_id)
+            .and_then(|set| set.source.as_ref());
+
+        if let Some(source) = source {
+            if let Err(e) = style.save_for(source) {
+                log::error!("Error saving styles for {source:?}: {e:?}");
+            }
+        }
+
+        self.styles.insert(set_id, style);
+        self.style_generation += 1;
+    }
+
+    /// The color of the annotation, from the first style rule of its
+    /// set that matches, or the color given in the file.
+    pub fn annotation_color(
+        &self,
+        set_id: AnnotationSetId,
+        annot: &Annotation,
+    ) -> Option<egui::Color32> {
+        self.style(set_id)
+            .and_then(|style| style.color(annot))
+            .or(annot.color)
+    }
+
+    pub fn get_color(&self, id: GlobalAnnotationId) -> Option<egui::Color32> {
+        self.annotation_color(id.set_id, self.get(id)?)
+    }
+
     pub fn get_sets_for_path<'a>(
         &'a self,
         path: PathId,
@@ -257,6 +736,143 @@ impl AnnotationStore {
     }
 }
 
+/// Maps a BED score to a gray, from dim for the lowest score in
+/// `range` to white for the highest, like the UCSC browser's
+/// `useScore` shading, but inverted for dark backgrounds. Scores are
+/// clamped to 0-1000, the range defined by the BED format.
+fn score_shade(score: f32, range: std::ops::Range<f32>) -> egui::Color32 {
+    const MIN_GRAY: f32 = 80.0;
+
+    let min = range.start.clamp(0.0, 1000.0);
+    let max = range.end.clamp(0.0, 1000.0);
+    let score = score.clamp(min, max);
+
+    let t = if max > min {
+        (score - min) / (max - min)
+    } else {
+        1.0
+    };
+
+    let gray = MIN_GRAY + (255.0 - MIN_GRAY) * t;
+    egui::Color32::from_gray(gray.round() as u8)
+}
+
+/// The columns of a BED record that are used for annotations: the
+/// first four, and the `itemRgb` and block columns of BED9 and BED12.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BedRecord<'a> {
+    seqid: &'a str,
+    range: std::ops::Range<u64>,
+    name: Option<&'a str>,
+    score: Option<&'a str>,
+    strand: Option<&'a str>,
+    item_rgb: Option<[u8; 3]>,
+    /// The blocks of a BED12 record, as absolute ranges
+    blocks: Vec<std::ops::Range<u64>>,
+}
+
+/// Parses a tab-separated BED line; returns `None` for empty,
+/// comment, `track` and `browser` lines.
+fn parse_bed_line(line: &str) -> Result<Option<BedRecord<'_>>> {
+    let line = line.trim_end();
+
+    if line.is_empty()
+        || line.starts_with('#')
+        || line.starts_with("track")
+        || line.starts_with("browser")
+    {
+        return Ok(None);
+    }
+
+    let fields = line.split('\t').collect::<Vec<_>>();
+
+    if fields.len() < 3 {
+        anyhow::bail!("Expected at least 3 columns, found {}", fields.len());
+    }
+
+    let parse_u64 = |s: &str| {
+        s.trim()
+            .parse::<u64>()
+            .map_err(|e| anyhow!("Error parsing {s:?}: {e}"))
+    };
+
+    let parse_list = |s: &str| {
+        s.split(',')
+            .filter(|v| !v.trim().is_empty())
+            .map(parse_u64)
+            .collect::<Result<Vec<_>>>()
+    };
+
+    let start = parse_u64(fields[1])?;
+    let end = parse_u64(fields[2])?;
+
+    if end < start {
+        anyhow::bail!("Record ends ({end}) before it starts ({start})");
+    }
+
+    let name = fields.get(3).copied().filter(|n| !n.is_empty());
+
+    // "." means no score or strand
+    let optional = |ix: usize| {
+        fields
+            .get(ix)
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty() && *s != ".")
+    };
+    let score = optional(4);
+    let strand = optional(5);
+
+    // "0" means no color
+    let item_rgb = match fields.get(8).map(|s| s.trim()) {
+        None | Some("") | Some("0") | Some(".") => None,
+        Some(rgb) => {
+            let channels = parse_list(rgb)?
+                .into_iter()
+                .map(u8::try_from)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let [r, g, b] = channels[..] else {
+                anyhow::bail!("Expected itemRgb as R,G,B, found {rgb:?}");
+            };
+            Some([r, g, b])
+        }
+    };
+
+    let blocks = if fields.len() >= 12 {
+        let count = parse_u64(fields[9])? as usize;
+        let sizes = parse_list(fields[10])?;
+        let starts = parse_list(fields[11])?;
+
+        if sizes.len() != count || starts.len() != count {
+            anyhow::bail!(
+                "Expected {count} blocks, found {} sizes and {} starts",
+                sizes.len(),
+                starts.len()
+            );
+        }
+
+        starts
+            .into_iter()
+            .zip(sizes)
+            .map(|(s, len)| {
+                let b_start = (start + s).min(end);
+                b_start..(b_start + len).min(end)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(Some(BedRecord {
+        seqid: fields[0],
+        range: start..end,
+        name,
+        score,
+        strand,
+        item_rgb,
+        blocks,
+    }))
+}
+
 fn parse_color(color_str: &str) -> Option<egui::Color32> {
     use btoi::btou_radix;
 
@@ -274,3 +890,184 @@ fn parse_color(color_str: &str) -> Option<egui::Color32> {
 
     Some(egui::Color32::from_rgb(r, g, b))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bed_columns_are_optional() {
+        let bed4 = parse_bed_line("chr1\t10\t20\tgene").unwrap().unwrap();
+        assert_eq!(bed4.seqid, "chr1");
+        assert_eq!(bed4.range, 10..20);
+        assert_eq!(bed4.name, Some("gene"));
+        assert_eq!(bed4.item_rgb, None);
+        assert!(bed4.blocks.is_empty());
+
+        let bed9 = "chr1\t10\t20\tgene\t0\t+\t10\t20\t255,0,128";
+        let bed9 = parse_bed_line(bed9).unwrap().unwrap();
+        assert_eq!(bed9.item_rgb, Some([255, 0, 128]));
+        assert_eq!(bed9.score, Some("0"));
+        assert_eq!(bed9.strand, Some("+"));
+
+        let bed12 = "chr1\t100\t200\ttx\t0\t-\t100\t200\t0\t3\t\
+                     10,20,5,\t0,40,95,";
+        let bed12 = parse_bed_line(bed12).unwrap().unwrap();
+        assert_eq!(bed12.item_rgb, None);
+        assert_eq!(bed12.blocks, vec![100..110, 140..160, 195..200]);
+
+        assert!(parse_bed_line("track name=genes").unwrap().is_none());
+        assert!(parse_bed_line("chr1\t20\t10\tgene").is_err());
+        assert!(
+            parse_bed_line("chr1\t1\t9\tx\t0\t+\t1\t9\t0\t2\t1,\t0,").is_err()
+        );
+    }
+
+    #[test]
+    fn added_annotations_share_a_set() {
+        let annot = |start, end| Annotation {
+            path: PathId::from(0u32),
+            range: Bp(start)..Bp(end),
+            label: Arc::new("region".to_string()),
+            color: None,
+            blocks: Vec::new(),
+            out_of_bounds: false,
+            attributes: Vec::new(),
+            label_color: None,
+        };
+
+        let mut store = AnnotationStore::default();
+        let generation = store.style_generation();
+
+        let a = store.add_annotation("Regions", annot(0, 10));
+        let b = store.add_annotation("Regions", annot(20, 30));
+
+        assert_eq!(a.set_id, b.set_id);
+        assert_eq!(store.get(b).unwrap().range, Bp(20)..Bp(30));
+        assert_eq!(store.annotation_sets.len(), 1);
+        assert!(store.style_generation() > generation);
+
+        let set = &store.annotation_sets[&a.set_id];
+        assert_eq!(set.path_annotations[&PathId::from(0u32)], vec![0, 1]);
+    }
+
+    #[test]
+    fn edit_and_remove_annotations() {
+        let annot = |start, end| Annotation {
+            path: PathId::from(0u32),
+            range: Bp(start)..Bp(end),
+            label: Arc::new("region".to_string()),
+            color: None,
+            blocks: Vec::new(),
+            out_of_bounds: false,
+            attributes: Vec::new(),
+            label_color: None,
+        };
+
+        let mut store = AnnotationStore::default();
+        let a = store.add_annotation("Regions", annot(0, 10));
+        let b = store.add_annotation("Regions", annot(20, 30));
+        let c = store.add_annotation("Regions", annot(40, 50));
+
+        let red = egui::Color32::from_rgb(255, 0, 0);
+        assert!(store.edit_annotation(c, |annot| {
+            annot.label = Arc::new("repeat".to_string());
+            annot.color = Some(red);
+        }));
+
+        store.remove_annotation(b);
+        assert!(store.get(c).is_none());
+
+        let c = GlobalAnnotationId {
+            set_id: c.set_id,
+            annot_id: AnnotationId(1),
+        };
+        assert_eq!(store.get(c).unwrap().label.as_str(), "repeat");
+
+        let set = &store.annotation_sets[&a.set_id];
+        assert_eq!(set.path_annotations[&PathId::from(0u32)], vec![0, 1]);
+    }
+
+    #[test]
+    fn sets_grow_in_batches() {
+        let annot = |start, end| Annotation {
+            path: PathId::from(0u32),
+            range: Bp(start)..Bp(end),
+            label: Arc::new("region".to_string()),
+            color: None,
+            blocks: Vec::new(),
+            out_of_bounds: false,
+            attributes: Vec::new(),
+            label_color: None,
+        };
+
+        let progress = progress::AnnotationLoadProgress::default();
+        progress.push(&annot(0, 10));
+        progress.push(&annot(20, 30));
+        assert_eq!(progress.records(), 2);
+
+        let mut store = AnnotationStore::default();
+        let set_id = store.insert_set(AnnotationSet {
+            name: "genes.bed".to_string(),
+            annotations: Vec::new(),
+            path_annotations: HashMap::new(),
+            out_of_bounds: 0,
+            source: None,
+        });
+
+        let generation = store.style_generation();
+        store.extend_set(set_id, progress.take_batch());
+        assert!(progress.take_batch().is_empty());
+        assert!(store.style_generation() > generation);
+
+        let set = &store.annotation_sets[&set_id];
+        assert_eq!(set.path_annotations[&PathId::from(0u32)], vec![0, 1]);
+
+        store.remove_set(set_id);
+        assert!(store.annotation_sets.is_empty());
+    }
+
+    #[test]
+    fn score_shading() {
+        let gray = |s| score_shade(s, 100.0..500.0);
+
+        assert_eq!(gray(100.0), egui::Color32::from_gray(80));
+        assert_eq!(gray(500.0), egui::Color32::WHITE);
+        assert_eq!(gray(300.0), egui::Color32::from_gray(168));
+
+        // clamped to the range, and to the BED score range
+        assert_eq!(gray(0.0), egui::Color32::from_gray(80));
+        assert_eq!(score_shade(2000.0, 0.0..1000.0), egui::Color32::WHITE);
+        assert_eq!(score_shade(2000.0, 0.0..4000.0), egui::Color32::WHITE);
+    }
+
+    #[test]
+    fn out_of_bounds_records_are_clamped() {
+        let annot =
+            |range: std::ops::Range<u64>, blocks: &[(u64, u64)]| Annotation {
+                path: PathId::from(0),
+                range: Bp(range.start)..Bp(range.end),
+                label: Arc::new("a".to_string()),
+                color: None,
+                blocks: blocks.iter().map(|&(s, e)| Bp(s)..Bp(e)).collect(),
+                out_of_bounds: false,
+                attributes: Vec::new(),
+                label_color: None,
+            };
+
+        let mut inside = annot(10..100, &[]);
+        assert!(!inside.fit_to_path(Bp(100)));
+        assert_eq!(inside.range, Bp(10)..Bp(100));
+
+        let mut overhang = annot(50..150, &[(50, 60), (90, 120), (130, 150)]);
+        assert!(overhang.fit_to_path(Bp(100)));
+        assert!(overhang.out_of_bounds);
+        assert_eq!(overhang.range, Bp(50)..Bp(100));
+        assert_eq!(overhang.blocks, vec![Bp(50)..Bp(60), Bp(90)..Bp(100)]);
+
+        // records entirely past the end are pinned to the last bp
+        let mut beyond = annot(200..300, &[]);
+        assert!(beyond.fit_to_path(Bp(100)));
+        assert_eq!(beyond.range, Bp(99)..Bp(100));
+    }
+}
@@ -0,0 +1,226 @@
+//! Filter expressions over annotation records, e.g.
+//! `len > 5000 && name ~ 'LINE'`, evaluated with rhai.
+//!
+//! Each record has the variables `name` (its label), `start`, `end`
+//! and `len` (in path coordinates), `path` (the path name), and `attr`,
+//! a map of its columns and attributes. Attributes whose keys are
+//! identifiers are also variables themselves, e.g. `type == 'gene'`.
+//! Numeric attribute values are numbers. `text ~ pattern` is true if
+//! the regex matches somewhere in the text.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use rhai::EvalAltResult;
+
+use super::Annotation;
+
+/// The variables every record has; attributes with these keys are
+/// only available through `attr`.
+const RECORD_FIELDS: [&str; 6] =
+    ["name", "start", "end", "len", "path", "attr"];
+
+pub struct AnnotationFilter {
+    source: String,
+    engine: rhai::Engine,
+    ast: rhai::AST,
+}
+
+impl std::fmt::Debug for AnnotationFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AnnotationFilter")
+            .field(&self.source)
+            .finish()
+    }
+}
+
+impl PartialEq for AnnotationFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl AnnotationFilter {
+    /// Compiles a filter expression. Strings can be in single or
+    /// double quotes.
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut engine = rhai::Engine::new();
+
+        engine
+            .register_custom_operator("~", 110)
+            .map_err(|e| anyhow!(e))?;
+
+        let regexes: Arc<Mutex<HashMap<String, regex::Regex>>> =
+            Default::default();
+        engine.register_fn(
+            "~",
+            move |text: &str,
+                  pattern: &str|
+                  -> Result<bool, Box<EvalAltResult>> {
+                let mut regexes = regexes.lock().unwrap();
+                if !regexes.contains_key(pattern) {
+                    let regex = regex::Regex::new(pattern)
+                        .map_err(|e| e.to_string())?;
+                    regexes.insert(pattern.to_string(), regex);
+                }
+                Ok(regexes[pattern].is_match(text))
+            },
+        );
+        // missing attributes never match
+        engine.register_fn("~", |_: (), _: &str| false);
+
+        let ast = engine.compile_expression(quote_strings(source))?;
+
+        Ok(Self {
+            source: source.to_string(),
+            engine,
+            ast,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the record, on the path named `path_name`, passes the
+    /// filter. Records the expression fails on, e.g. by using an
+    /// attribute they don't have, don't pass.
+    pub fn matches(&self, annot: &Annotation, path_name: &str) -> bool {
+        let mut scope = record_scope(annot, path_name);
+        self.engine
+            .eval_ast_with_scope::<bool>(&mut scope, &self.ast)
+            .unwrap_or(false)
+    }
+}
+
+fn record_scope(annot: &Annotation, path_name: &str) -> rhai::Scope<'static> {
+    let mut scope = rhai::Scope::new();
+    let mut attr = rhai::Map::new();
+
+    for (key, value) in annot.attributes.iter() {
+        let value = attribute_value(value);
+        if is_identifier(key) && !RECORD_FIELDS.contains(&key.as_str()) {
+            scope.push_dynamic(key.as_str(), value.clone());
+        }
+        attr.insert(key.as_str().into(), value);
+    }
+
+    let start = annot.range.start.0 as i64;
+    let end = annot.range.end.0 as i64;
+
+    scope
+        .push("name", annot.label.to_string())
+        .push("start", start)
+        .push("end", end)
+        .push("len", end - start)
+        .push("path", path_name.to_string())
+        .push("attr", attr);
+
+    scope
+}
+
+fn attribute_value(value: &str) -> rhai::Dynamic {
+    if let Ok(v) = value.parse::<i64>() {
+        v.into()
+    } else if let Some(v) =
+        value.parse::<rhai::FLOAT>().ok().filter(|v| v.is_finite())
+    {
+        v.into()
+    } else {
+        value.to_string().into()
+    }
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replaces single quotes around strings with double quotes, as
+/// single quotes are character literals in rhai.
+fn quote_strings(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut quote = None;
+    let mut escaped = false;
+
+    for c in source.chars() {
+        match (quote, c) {
+            _ if escaped => {
+                escaped = false;
+                out.push(c);
+            }
+            (Some(_), '\\') => {
+                escaped = true;
+                out.push(c);
+            }
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                out.push('"');
+            }
+            (Some(q), c) if c == q => {
+                quote = None;
+                out.push('"');
+            }
+            (Some('\''), '"') => out.push_str("\\\""),
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use waragraph_core::graph::{Bp, PathId};
+
+    #[test]
+    fn filter_by_fields_and_attributes() {
+        let annot = |label: &str, len: u64, ty: &str| Annotation {
+            path: PathId::from(0),
+            range: Bp(100)..Bp(100 + len),
+            label: Arc::new(label.to_string()),
+            color: None,
+            blocks: Vec::new(),
+            out_of_bounds: false,
+            attributes: vec![
+                ("type".to_string(), ty.to_string()),
+                ("score".to_string(), "800".to_string()),
+            ],
+            label_color: None,
+        };
+
+        let line = annot("L1PA2_LINE", 6000, "repeat");
+        let alu = annot("AluY", 300, "repeat");
+        let gene = annot("BRCA1", 8000, "gene");
+
+        let filter =
+            AnnotationFilter::parse("len > 5000 && name ~ 'LINE'").unwrap();
+        assert!(filter.matches(&line, "chr1"));
+        assert!(!filter.matches(&alu, "chr1"));
+        assert!(!filter.matches(&gene, "chr1"));
+
+        let filter =
+            AnnotationFilter::parse("type == \"gene\" && score >= 500")
+                .unwrap();
+        assert!(filter.matches(&gene, "chr1"));
+        assert!(!filter.matches(&line, "chr1"));
+
+        let filter = AnnotationFilter::parse("path == 'chr2'").unwrap();
+        assert!(filter.matches(&alu, "chr2"));
+        assert!(!filter.matches(&alu, "chr1"));
+
+        // records without the attribute don't match, rather than fail
+        let filter = AnnotationFilter::parse("attr.ID ~ 'gene'").unwrap();
+        assert!(!filter.matches(&gene, "chr1"));
+        let filter = AnnotationFilter::parse("ID == 'x'").unwrap();
+        assert!(!filter.matches(&gene, "chr1"));
+
+        assert!(AnnotationFilter::parse("len >").is_err());
+    }
+}
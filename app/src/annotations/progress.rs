@@ -0,0 +1,85 @@
+//! Progress reporting while an annotation file is loaded in the
+//! background, with the annotations parsed so far handed over in
+//! batches, so they can be shown before the file is done.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use super::Annotation;
+
+/// Shared between the thread loading an annotation file, via
+/// `AnnotationSet::from_file_with_progress`, and whatever shows the
+/// progress, which can also cancel the loading.
+#[derive(Default)]
+pub struct AnnotationLoadProgress {
+    file_len: AtomicU64,
+    bytes_read: AtomicU64,
+    records: AtomicUsize,
+    cancelled: AtomicBool,
+    batch: Mutex<Vec<Annotation>>,
+}
+
+impl AnnotationLoadProgress {
+    /// The fraction of the file that has been read.
+    pub fn fraction(&self) -> f32 {
+        let len = self.file_len.load(Ordering::Relaxed);
+        let read = self.bytes_read.load(Ordering::Relaxed);
+        if len == 0 {
+            0.0
+        } else {
+            (read as f64 / len as f64).min(1.0) as f32
+        }
+    }
+
+    /// The number of annotations parsed so far.
+    pub fn records(&self) -> usize {
+        self.records.load(Ordering::Relaxed)
+    }
+
+    /// Stops the loading at the next record; the loading function
+    /// then returns an error.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// The annotations parsed since the last call, in file order.
+    pub fn take_batch(&self) -> Vec<Annotation> {
+        std::mem::take(&mut self.batch.lock().unwrap())
+    }
+
+    pub(super) fn check_cancelled(&self) -> anyhow::Result<()> {
+        if self.is_cancelled() {
+            anyhow::bail!("Annotation loading cancelled");
+        }
+        Ok(())
+    }
+
+    pub(super) fn set_file_len(&self, len: u64) {
+        self.file_len.store(len, Ordering::Relaxed);
+    }
+
+    pub(super) fn push(&self, annot: &Annotation) {
+        self.records.fetch_add(1, Ordering::Relaxed);
+        self.batch.lock().unwrap().push(annot.clone());
+    }
+}
+
+/// Counts the bytes read from a file, for the progress.
+pub(super) struct ProgressReader<'a, R> {
+    pub(super) inner: R,
+    pub(super) progress: Option<&'a AnnotationLoadProgress>,
+}
+
+impl<'a, R: std::io::Read> std::io::Read for ProgressReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(progress) = self.progress {
+            progress.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,152 @@
+//! Searching annotations by label, across all loaded sets.
+
+use anyhow::Result;
+
+use super::{AnnotationId, AnnotationStore, GlobalAnnotationId};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    #[default]
+    Substring,
+    Regex,
+}
+
+/// The labels of all annotations in a store, lowercased for
+/// case-insensitive matching. Rebuilt by `update` when sets are added
+/// or their annotations change.
+#[derive(Default)]
+pub struct AnnotationSearchIndex {
+    // the set count and style generation of the store when built
+    built_from: (usize, u64),
+    labels: Vec<(GlobalAnnotationId, String)>,
+}
+
+impl AnnotationSearchIndex {
+    pub fn new(store: &AnnotationStore) -> Self {
+        let mut index = Self::default();
+        index.rebuild(store);
+        index
+    }
+
+    /// Rebuilds the index if the store has changed since it was built,
+    /// returning `true` if it did.
+    pub fn update(&mut self, store: &AnnotationStore) -> bool {
+        let outdated = Self::store_key(store) != self.built_from;
+        if outdated {
+            self.rebuild(store);
+        }
+        outdated
+    }
+
+    fn store_key(store: &AnnotationStore) -> (usize, u64) {
+        (store.annotation_sets.len(), store.style_generation())
+    }
+
+    fn rebuild(&mut self, store: &AnnotationStore) {
+        self.built_from = Self::store_key(store);
+        self.labels.clear();
+
+        for (set_id, set) in store.annotation_sets.iter() {
+            for (ix, annot) in set.annotations.iter().enumerate() {
+                let id = GlobalAnnotationId {
+                    set_id: *set_id,
+                    annot_id: AnnotationId(ix),
+                };
+                self.labels.push((id, annot.label.to_lowercase()));
+            }
+        }
+    }
+
+    /// Returns up to `limit` annotations whose labels contain the
+    /// query, or match it as a regex, ignoring case. Fails if the
+    /// regex is invalid.
+    pub fn search(
+        &self,
+        query: &str,
+        mode: SearchMode,
+        limit: usize,
+    ) -> Result<Vec<GlobalAnnotationId>> {
+        let matches = match mode {
+            SearchMode::Substring => {
+                let query = query.to_lowercase();
+                self.collect(limit, |label| label.contains(&query))
+            }
+            SearchMode::Regex => {
+                let regex = regex::RegexBuilder::new(query)
+                    .case_insensitive(true)
+                    .build()?;
+                self.collect(limit, |label| regex.is_match(label))
+            }
+        };
+
+        Ok(matches)
+    }
+
+    fn collect(
+        &self,
+        limit: usize,
+        is_match: impl Fn(&str) -> bool,
+    ) -> Vec<GlobalAnnotationId> {
+        self.labels
+            .iter()
+            .filter(|(_, label)| is_match(label))
+            .map(|(id, _)| *id)
+            .take(limit)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::{Annotation, AnnotationSet};
+    use std::sync::Arc;
+    use waragraph_core::graph::{Bp, PathId};
+
+    #[test]
+    fn substring_and_regex_search() {
+        let annotations = ["BRCA1", "brca2", "TP53", "TP53-AS1"]
+            .into_iter()
+            .map(|label| Annotation {
+                path: PathId::from(0),
+                range: Bp(0)..Bp(10),
+                label: Arc::new(label.to_string()),
+                color: None,
+                blocks: Vec::new(),
+                out_of_bounds: false,
+                attributes: Vec::new(),
+                label_color: None,
+            })
+            .collect();
+
+        let mut store = AnnotationStore::default();
+        let set_id = store.insert_set(AnnotationSet {
+            name: "genes".to_string(),
+            annotations,
+            path_annotations: Default::default(),
+            out_of_bounds: 0,
+            source: None,
+        });
+
+        let index = AnnotationSearchIndex::new(&store);
+
+        let ids = |ixs: &[usize]| {
+            ixs.iter()
+                .map(|&ix| GlobalAnnotationId {
+                    set_id,
+                    annot_id: AnnotationId(ix),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let search =
+            |query, mode, limit| index.search(query, mode, limit).unwrap();
+
+        assert_eq!(search("brca", SearchMode::Substring, 10), ids(&[0, 1]));
+        assert_eq!(search("tp53", SearchMode::Substring, 1), ids(&[2]));
+        assert_eq!(search("^tp53$", SearchMode::Regex, 10), ids(&[2]));
+        assert_eq!(search("[12]$", SearchMode::Regex, 10), ids(&[0, 1, 3]));
+
+        assert!(index.search("(", SearchMode::Regex, 10).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! Rules for coloring the annotations of a set by the value of an
+//! attribute, e.g. the GFF `type` column; saved next to the
+//! annotation file as `<file>.styles.tsv`.
+
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+
+use super::{parse_color, Annotation};
+
+// written in the value column of rules that match any value
+pub const ANY_VALUE: &str = "*";
+
+// written in the color column of rules that color by the value's hash
+const HASHED: &str = "hash";
+
+/// Colors the annotations whose `attribute` has the given `value`, or
+/// any value if it's `*`. Without a color, each distinct value gets
+/// a color derived from its hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyleRule {
+    pub attribute: String,
+    pub value: String,
+    pub color: Option<egui::Color32>,
+}
+
+impl StyleRule {
+    fn color_for(&self, annot: &Annotation) -> Option<egui::Color32> {
+        let value = annot.attribute(&self.attribute)?;
+
+        if self.value != ANY_VALUE && self.value != value {
+            return None;
+        }
+
+        let color = self.color.unwrap_or_else(|| {
+            let [r, g, b] = crate::color::util::hashed_rgb(value);
+            egui::Color32::from_rgb(r, g, b)
+        });
+
+        Some(color)
+    }
+}
+
+/// The style rules of an annotation set; the first matching rule
+/// decides the color of an annotation.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AnnotationStyle {
+    pub rules: Vec<StyleRule>,
+}
+
+impl AnnotationStyle {
+    pub fn file_for(annotation_path: &Path) -> PathBuf {
+        let mut file = annotation_path.as_os_str().to_owned();
+        file.push(".styles.tsv");
+        PathBuf::from(file)
+    }
+
+    pub fn color(&self, annot: &Annotation) -> Option<egui::Color32> {
+        self.rules.iter().find_map(|rule| rule.color_for(annot))
+    }
+
+    /// Parses rules from TSV with the columns attribute, value (`*`
+    /// for any), and color (`#RRGGBB`, or `hash`); lines starting
+    /// with `#` are skipped.
+    pub fn read_tsv(text: &str) -> Result<Self> {
+        let mut rules = Vec::new();
+
+        for (line_ix, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<_>>();
+            let [attribute, value, color] = fields[..] else {
+                anyhow::bail!(
+                    "Expected 3 columns on line {}, found {}",
+                    line_ix + 1,
+                    fields.len()
+                );
+            };
+
+            let color = if color.trim() == HASHED {
+                None
+            } else {
+                let color = parse_color(color).ok_or_else(|| {
+                    anyhow!("Invalid color {color:?} on line {}", line_ix + 1)
+                })?;
+                Some(color)
+            };
+
+            rules.push(StyleRule {
+                attribute: attribute.to_string(),
+                value: value.to_string(),
+                color,
+            });
+        }
+
+        Ok(Self { rules })
+    }
+
+    pub fn write_tsv(&self, mut out: impl Write) -> Result<()> {
+        writeln!(out, "#attribute\tvalue\tcolor")?;
+
+        for rule in &self.rules {
+            let color = if let Some(c) = rule.color {
+                format!("#{:02X}{:02X}{:02X}", c.r(), c.g(), c.b())
+            } else {
+                HASHED.to_string()
+            };
+
+            writeln!(out, "{}\t{}\t{color}", rule.attribute, rule.value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads the rules saved for the annotation file, if any.
+    pub fn load_for(annotation_path: &Path) -> Result<Option<Self>> {
+        let file = Self::file_for(annotation_path);
+        if !file.exists() {
+            return Ok(None);
+        }
+        let style = Self::read_tsv(&std::fs::read_to_string(file)?)?;
+        Ok(Some(style))
+    }
+
+    pub fn save_for(&self, annotation_path: &Path) -> Result<()> {
+        let file = std::fs::File::create(Self::file_for(annotation_path))?;
+        let mut out = std::io::BufWriter::new(file);
+        self.write_tsv(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use waragraph_core::graph::{Bp, PathId};
+
+    fn annot(attributes: &[(&str, &str)]) -> Annotation {
+        Annotation {
+            path: PathId::from(0),
+            range: Bp(0)..Bp(10),
+            label: Arc::new("a".to_string()),
+            color: None,
+            blocks: Vec::new(),
+            out_of_bounds: false,
+            label_color: None,
+            attributes: attributes
+                .iter()
+                .map(|&(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_colors() {
+        let style = AnnotationStyle {
+            rules: vec![
+                StyleRule {
+                    attribute: "type".into(),
+                    value: "exon".into(),
+                    color: Some(egui::Color32::RED),
+                },
+                StyleRule {
+                    attribute: "type".into(),
+                    value: ANY_VALUE.into(),
+                    color: None,
+                },
+            ],
+        };
+
+        let exon = annot(&[("type", "exon")]);
+        let gene = annot(&[("type", "gene")]);
+        let other = annot(&[("strand", "+")]);
+
+        assert_eq!(style.color(&exon), Some(egui::Color32::RED));
+        let [r, g, b] = crate::color::util::hashed_rgb("gene");
+        assert_eq!(style.color(&gene), Some(egui::Color32::from_rgb(r, g, b)));
+        assert_eq!(style.color(&other), None);
+
+        let mut tsv = Vec::new();
+        style.write_tsv(&mut tsv).unwrap();
+        let tsv = String::from_utf8(tsv).unwrap();
+        assert_eq!(AnnotationStyle::read_tsv(&tsv).unwrap(), style);
+    }
+}
@@ -4,33 +4,89 @@ use tokio::{
     runtime::Runtime,
     sync::{mpsc, RwLock},
 };
-use waragraph_core::graph::{Bp, PathId};
+use waragraph_core::graph::coords::CoordSystem;
+use waragraph_core::graph::reference::ReferenceGuess;
+use waragraph_core::graph::{Bp, Node, PathId};
 use winit::{
     event::{ElementState, Event, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
     window::WindowId,
 };
 
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::Arc,
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 
 use crate::{
-    annotations::{AnnotationSet, AnnotationStore},
-    color::{ColorSchemeId, ColorStore},
+    annotations::{AnnotationStore, GlobalAnnotationId},
+    color::{paths::PathColors, ColorSchemeId, ColorStore},
     context::{widget::ContextInspector, ContextState},
-    viewer_1d::Viewer1D,
+    viewer_1d::{bookmarks::BookmarkStore, Viewer1D},
     viewer_2d::Viewer2D,
 };
 
 mod window;
 
+pub mod adapter;
+
+pub mod args;
+
+pub mod add_sample;
+
+pub mod annotation_load;
+
+pub mod bedgraph;
+
+pub mod config;
+
+pub mod consensus;
+
+pub mod export_paths;
+
+pub mod gpu_recovery;
+
+pub mod graph_stats;
+
+pub mod layout;
+
+pub mod liftover;
+
+pub mod loading;
+
+pub mod node_export;
+
+pub mod view_state;
+
+pub mod notify;
+
+pub mod odgi;
+
+pub mod provenance;
+
+pub mod qc;
+
+pub mod selection;
+
+pub mod session;
+
 pub mod settings_menu;
 
 pub mod workspace;
 
 pub mod resource;
 
+pub mod safe_mode;
+
+pub mod save_graph;
+
+pub mod script;
+
+pub use args::{parse_args, Args, ArgsError};
+pub use view_state::{ViewLink, ViewState};
 pub use window::AppWindowState;
 
 use self::{
@@ -56,7 +112,96 @@ pub struct SharedState {
     // tsv_path: Option<Arc<RwLock<PathBuf>>>,
     pub data_color_schemes: Arc<RwLock<HashMap<String, ColorSchemeId>>>,
 
+    pub provenance: Arc<RwLock<provenance::ProvenanceLog>>,
+
+    pub export_paths: Arc<RwLock<export_paths::ExportPaths>>,
+
+    // nodes selected in any of the viewers
+    pub selection: Arc<RwLock<selection::SelectionStore>>,
+
+    pub theme: Arc<AtomicCell<crate::gui::theme::Theme>>,
+
+    pub zoom_thresholds: Arc<AtomicCell<crate::gui::zoom::ZoomThresholds>>,
+    pub performance:
+        Arc<AtomicCell<crate::gui::performance::PerformanceSettings>>,
+
+    /// Convention for the positions shown to and entered by the user
+    pub coords: Arc<AtomicCell<CoordSystem>>,
+    /// Reference path to show positions on, instead of pangenome
+    /// offsets
+    pub reference_coords: Arc<AtomicCell<Option<PathId>>>,
+    /// The reference path guessed when the graph was loaded, which
+    /// `reference_coords` starts as
+    pub detected_reference: Option<ReferenceGuess>,
+
+    pub bookmarks: Arc<RwLock<BookmarkStore>>,
+
+    pub path_colors: Arc<RwLock<PathColors>>,
+
+    pub tour: Arc<RwLock<crate::gui::tour::GuidedTour>>,
+
+    pub measure: Arc<RwLock<crate::gui::measure::MeasureTool>>,
+
+    /// Tooltips pinned as floating cards, in either viewer
+    pub pinned: Arc<RwLock<crate::gui::pinned::PinnedCards>>,
+
+    /// Config values the viewers and scripts share; each viewer adds
+    /// the schema of its keys
+    pub config: config::ConfigMap,
+
     pub app_msg_send: tokio::sync::mpsc::Sender<AppMsg>,
+
+    /// Started with `--safe-mode`
+    pub safe_mode: bool,
+}
+
+impl SharedState {
+    /// Sets the color scheme, by name, used for a data source.
+    pub fn set_data_color_scheme(
+        &self,
+        state: &raving_wgpu::State,
+        data_key: &str,
+        scheme: &str,
+    ) -> Result<()> {
+        let mut colors = self.colors.blocking_write();
+        let id = colors.get_color_scheme_id(scheme).ok_or_else(|| {
+            anyhow::anyhow!("Unknown color scheme `{scheme}`")
+        })?;
+
+        let mut schemes = self.data_color_schemes.blocking_write();
+        anyhow::ensure!(
+            schemes.contains_key(data_key),
+            "Unknown data `{data_key}`"
+        );
+
+        if colors.get_color_scheme_texture(id).is_none() {
+            colors.create_color_scheme_texture(state, scheme);
+        }
+
+        schemes.insert(data_key.to_string(), id);
+        Ok(())
+    }
+
+    /// The names of the color schemes used for the data sources.
+    pub fn data_color_scheme_names(&self) -> BTreeMap<String, String> {
+        let colors = self.colors.blocking_read();
+        self.data_color_schemes
+            .blocking_read()
+            .iter()
+            .map(|(data_key, id)| {
+                (data_key.clone(), colors.get_scheme_name(*id).to_string())
+            })
+            .collect()
+    }
+
+    /// The name of the detected reference path, or an empty string.
+    pub fn detected_reference_name(&self) -> String {
+        self.detected_reference
+            .as_ref()
+            .and_then(|guess| self.graph.path_names.get_by_left(&guess.path))
+            .cloned()
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -83,11 +228,50 @@ pub struct App {
     settings: SettingsWindow,
     settings_window_tgt: Option<WindowId>,
 
+    // set by the wgpu error handler
+    gpu_error: Arc<AtomicCell<bool>>,
+    gpu_rebuilds: Vec<std::time::Instant>,
+
+    // hash of the GFA, computed in the background for view links
+    graph_hash: Arc<RwLock<Option<String>>>,
+    view_link_widget: Arc<RwLock<view_state::ViewLinkWidget>>,
+    // view link from the command line, applied when the event loop starts
+    initial_view_link: Option<ViewLink>,
+    // pangenome range of `--region`, shown in the 1D view on start
+    initial_range: Option<std::ops::Range<Bp>>,
+    // script from the command line, started with the event loop
+    initial_script: Option<PathBuf>,
+    // `--config` file, read once the viewers are open, and its watcher
+    config_path: Option<PathBuf>,
+    config_watcher: Option<config::file::ConfigWatcher>,
+
+    // the graphs in the session; only the active one is loaded
+    session: Arc<RwLock<session::Session>>,
+    // the command line options passed on when restarting with another
+    // graph
+    restart_args: Vec<std::ffi::OsString>,
+    // set when switching graphs, to exit once the new process is started
+    exit_requested: bool,
+    // holds the GFA converted from an odgi graph until the app exits
+    og_conversion: Option<odgi::ConversionDir>,
+    // `--gff-attr`, for the widgets that load annotations
+    gff_attr: String,
+    // `--export-image` and the size to render at; the 1D viewer's
+    // window is hidden, and the app exits once the image is written
+    export_image: Option<(PathBuf, [u32; 2])>,
+    export_done: Option<crossbeam::channel::Receiver<PathBuf>>,
+
+    notifications: Arc<RwLock<notify::Notifications>>,
+
     app_msg_recv: tokio::sync::mpsc::Receiver<AppMsg>,
 }
 
 impl App {
-    pub fn init(state: &raving_wgpu::State, args: Args) -> Result<Self> {
+    pub fn init(
+        state: &raving_wgpu::State,
+        args: Args,
+        path_index: waragraph_core::graph::PathIndex,
+    ) -> Result<Self> {
         let runtime = tokio::runtime::Builder::new_multi_thread()
             .worker_threads(4)
             .enable_all()
@@ -96,7 +280,33 @@ impl App {
 
         let tokio_rt = Arc::new(runtime);
 
-        let path_index = waragraph_core::graph::PathIndex::from_gfa(&args.gfa)?;
+        let gpu_error = Arc::new(AtomicCell::new(false));
+        gpu_recovery::set_error_handler(&state.device, &gpu_error);
+
+        let initial_view_link =
+            args.view_link.as_deref().map(ViewLink::parse).transpose()?;
+        let restart_args = args.restart_args();
+
+        let mut path_index = path_index;
+        let added_paths =
+            add_sample::add_aligned_paths(&mut path_index, &args.aligned);
+
+        let detected_reference = args::detect_reference(&path_index, &args);
+
+        let initial_range = args::region_pangenome_range(
+            &path_index,
+            &args,
+            detected_reference.as_ref().map(|guess| guess.path),
+        )?;
+
+        let session = Arc::new(RwLock::new(session::Session::for_args(&args)));
+
+        let provenance: Arc<RwLock<provenance::ProvenanceLog>> =
+            Default::default();
+
+        let graph_hash =
+            provenance::hash_graph(tokio_rt.handle(), &provenance, &args);
+
         let path_index = Arc::new(path_index);
 
         let (app_msg_send, app_msg_recv) = mpsc::channel::<AppMsg>(256);
@@ -114,10 +324,71 @@ impl App {
             app_windows.widget_state.clone(),
         );
 
+        crate::util::gpu_memory::set_budget(
+            args.gpu_memory_budget_mb.map(|mb| mb * 1024 * 1024),
+        );
+
+        settings.register_widget(
+            "Diagnostics",
+            "Adapters",
+            Arc::new(RwLock::new(adapter::AdapterDiagnosticsWidget::new(
+                args.adapter.clone(),
+            ))),
+        );
+
+        settings.register_widget(
+            "Diagnostics",
+            "GPU Memory",
+            Arc::new(RwLock::new(
+                crate::util::gpu_memory::GpuMemoryWidget::new(&state.device),
+            )),
+        );
+
+        let view_link_widget =
+            Arc::new(RwLock::new(view_state::ViewLinkWidget::default()));
+
+        settings.register_widget(
+            "General",
+            "View Link",
+            view_link_widget.clone(),
+        );
+
         let shared = {
+            let export_paths = Arc::new(RwLock::new(
+                export_paths::ExportPaths::new(args.graph_path()),
+            ));
+
+            let bookmarks = {
+                let file = BookmarkStore::file_for_graph(args.graph_path());
+
+                let store = BookmarkStore::open(file).unwrap_or_else(|e| {
+                    log::error!("Error loading bookmarks: {e:?}");
+                    BookmarkStore::default()
+                });
+
+                Arc::new(RwLock::new(store))
+            };
+
+            let path_colors = PathColors::load_for(args.graph_path())
+                .unwrap_or_else(|e| {
+                    log::error!("Error loading path colors: {e:?}");
+                    PathColors::default()
+                });
+            let path_colors = Arc::new(RwLock::new(path_colors));
+
+            let graph_path = args.graph_path().to_path_buf();
+            let og_path =
+                args.og.clone().or_else(|| odgi::cached_og(&args.gfa));
+
             let workspace = Arc::new(RwLock::new(Workspace {
-                gfa_path: args.gfa,
-                tsv_path: args.tsv,
+                gfa_path: args.gfa.clone(),
+                graph_path,
+                tsv_path: args.tsv.clone(),
+                og_path,
+                added_paths,
+
+                odgi_layout_job: None,
+                builtin_layout_job: None,
             }));
 
             {
@@ -125,7 +396,17 @@ impl App {
                 settings.register_widget("General", "Graph & Layout", ws);
             }
 
-            let graph_data_cache = Arc::new(GraphDataCache::init(&path_index));
+            let mut graph_data_cache = GraphDataCache::init(&path_index);
+
+            bedgraph::load_tracks(
+                &path_index,
+                &mut graph_data_cache,
+                &args.bedgraphs,
+                &provenance,
+                tokio_rt.handle(),
+            );
+
+            let graph_data_cache = Arc::new(graph_data_cache);
 
             let colors = Arc::new(RwLock::new(ColorStore::init(state)));
 
@@ -144,70 +425,25 @@ impl App {
 
                 add_entry("depth", "spectral");
                 add_entry("strand", "black_red");
-            }
-
-            let mut annotations = AnnotationStore::default();
-
-            for annot_path in args.annotations.iter() {
-                if let Some(ext) = annot_path.extension() {
-                    let result = if ext == "bed" {
-                        AnnotationSet::from_bed(
-                            &path_index,
-                            None,
-                            |name| name.to_string(),
-                            annot_path,
-                        )
-                    } else if ext == "gff" {
-                        let attr = args
-                            .gff_attr
-                            .as_ref()
-                            .map(|s| s.as_str())
-                            .unwrap_or("Name");
-
-                        // TODO the name and record functions should be configurable
-                        AnnotationSet::from_gff(
-                            &path_index,
-                            None,
-                            |name| name.to_string(),
-                            // |name| format!("S288C.{name}"),
-                            // |name| format!("SGDref#1#{name}"),
-                            |record| {
-                                let attrs = record.attributes();
-                                let label = attrs.iter().find_map(|entry| {
-                                    (entry.key() == attr)
-                                        .then_some(entry.value())
-                                })?;
-
-                                Some(label.to_string())
-                            },
-                            annot_path,
-                        )
-                    } else {
-                        log::error!("Unknown annotation file extension `{ext:?}`, ignoring");
-                        continue;
-                    };
-
-                    match result {
-                        Ok(set) => {
-                            log::warn!(
-                                "loaded annotation set with {} annotations",
-                                set.annotations.len()
-                            );
 
-                            annotations.insert_set(set);
-                        }
-                        Err(e) => {
-                            log::error!(
-                                "Error loading annotation file {:?}: {e:?}",
-                                annot_path.as_os_str()
-                            );
-                        }
-                    }
+                for key in graph_data_cache.tracks() {
+                    add_entry(key, "spectral");
                 }
             }
 
-            let annotations: Arc<RwLock<AnnotationStore>> =
-                Arc::new(RwLock::new(annotations));
+            // in safe mode, they're loaded from the safe mode panel
+            let annotations = if args.safe_mode {
+                AnnotationStore::default()
+            } else {
+                annotation_load::load_files(
+                    &path_index,
+                    args.gff_attr.as_deref().unwrap_or("Name"),
+                    &args.annotations,
+                    &provenance,
+                    tokio_rt.handle(),
+                )
+            };
+            let annotations = Arc::new(RwLock::new(annotations));
 
             SharedState {
                 graph: path_index,
@@ -222,10 +458,62 @@ impl App {
 
                 workspace,
 
+                provenance,
+
+                export_paths,
+
+                selection: Default::default(),
+
+                theme: Default::default(),
+
+                zoom_thresholds: Default::default(),
+                performance: Default::default(),
+
+                coords: Arc::new(AtomicCell::new(args.coords)),
+                reference_coords: Arc::new(AtomicCell::new(
+                    detected_reference.as_ref().map(|guess| guess.path),
+                )),
+                detected_reference,
+
+                bookmarks,
+
+                path_colors,
+
+                tour: Default::default(),
+
+                measure: Default::default(),
+
+                pinned: Default::default(),
+
+                config: Default::default(),
+
                 app_msg_send,
+
+                safe_mode: args.safe_mode,
             }
         };
 
+        session.blocking_read().restore(state, &shared);
+
+        layout::request_initial_layout(&shared, &args);
+
+        if args.tour {
+            crate::gui::tour::start_tour(&shared);
+        }
+
+        if args.safe_mode {
+            let widget = safe_mode::SafeModeWidget::new(
+                &shared,
+                args.annotations.clone(),
+                args.gff_attr.as_deref().unwrap_or("Name"),
+            );
+            settings.register_widget(
+                "Diagnostics",
+                "Safe Mode",
+                Arc::new(RwLock::new(widget)),
+            );
+        }
+
         let context_state = ContextState::default();
 
         let context_inspector = ContextInspector::with_default_widgets(&shared);
@@ -236,7 +524,10 @@ impl App {
             context_inspector.settings_widget().clone(),
         );
 
-        Ok(Self {
+        let notifications =
+            Arc::new(RwLock::new(notify::Notifications::default()));
+
+        let mut app = Self {
             tokio_rt,
             shared,
 
@@ -252,12 +543,224 @@ impl App {
             settings_window_tgt: None,
 
             app_msg_recv,
-        })
+
+            notifications,
+
+            gpu_error,
+            gpu_rebuilds: Vec::new(),
+
+            graph_hash,
+            view_link_widget,
+            initial_view_link,
+            initial_range,
+            initial_script: args.script,
+            config_path: args.config,
+            config_watcher: None,
+
+            session,
+            restart_args,
+            exit_requested: false,
+            og_conversion: args.og_conversion,
+            gff_attr: args.gff_attr.unwrap_or_else(|| "Name".to_string()),
+            export_image: args.export_image.map(|path| {
+                let width = args.render_width.unwrap_or(1280) as u32;
+                let height = args.render_height.unwrap_or(800) as u32;
+                (path, [width, height])
+            }),
+            export_done: None,
+        };
+
+        app.register_graph_widgets();
+
+        app.settings.register_widget(
+            "General",
+            "Notifications",
+            app.notifications.clone(),
+        );
+
+        Ok(app)
+    }
+
+    /// Registers the settings widgets that use the shared state; they're
+    /// registered again, replacing the old ones, when the graph is
+    /// replaced.
+    fn register_graph_widgets(&mut self) {
+        self.settings.register_widget(
+            "Diagnostics",
+            "Graph QC",
+            Arc::new(RwLock::new(qc::QcReportWidget::new(&self.shared))),
+        );
+
+        self.settings.register_widget(
+            "Diagnostics",
+            "Graph Statistics",
+            Arc::new(RwLock::new(graph_stats::GraphStatsWidget::new(
+                &self.shared,
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Graphs",
+            Arc::new(RwLock::new(session::SessionWidget::new(
+                self.session.clone(),
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Add Sample",
+            Arc::new(RwLock::new(add_sample::AddSampleWidget::new(
+                &self.shared,
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Consensus Path",
+            Arc::new(RwLock::new(consensus::ConsensusWidget::new(
+                &self.shared,
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Save Graph",
+            Arc::new(RwLock::new(save_graph::SaveGraphWidget::new(
+                &self.shared,
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Export",
+            Arc::new(RwLock::new(export_paths::ExportPathsWidget::new(
+                self.shared.export_paths.clone(),
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Display",
+            Arc::new(RwLock::new(crate::gui::theme::ThemeWidget::new(
+                self.shared.theme.clone(),
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Node ID Liftover",
+            Arc::new(RwLock::new(liftover::LiftoverWidget::new(&self.shared))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Coordinates",
+            Arc::new(RwLock::new(crate::gui::coords::CoordSystemWidget::new(
+                &self.shared,
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Zoom Levels",
+            Arc::new(RwLock::new(crate::gui::zoom::ZoomThresholdsWidget::new(
+                self.shared.zoom_thresholds.clone(),
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Performance",
+            Arc::new(RwLock::new(
+                crate::gui::performance::PerformanceWidget::new(
+                    self.shared.performance.clone(),
+                    self.shared.zoom_thresholds.clone(),
+                ),
+            )),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Path Colors",
+            Arc::new(RwLock::new(crate::color::widget::PathColorsWidget::new(
+                &self.shared,
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Load Annotations",
+            Arc::new(RwLock::new(annotation_load::AnnotationLoadWidget::new(
+                &self.shared,
+                &self.gff_attr,
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Annotation Search",
+            Arc::new(RwLock::new(
+                crate::gui::annotations::AnnotationSearchWidget::new(
+                    &self.shared,
+                ),
+            )),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Annotation Styles",
+            Arc::new(RwLock::new(
+                crate::gui::annotations::AnnotationStyleWidget::new(
+                    self.shared.annotations.clone(),
+                ),
+            )),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "User Annotations",
+            Arc::new(RwLock::new(
+                crate::gui::annotations::UserAnnotationsWidget::new(
+                    &self.shared,
+                ),
+            )),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Measure",
+            Arc::new(RwLock::new(crate::gui::measure::MeasureWidget::new(
+                &self.shared,
+            ))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Scripting",
+            Arc::new(RwLock::new(script::ScriptWidget::new(&self.shared))),
+        );
+
+        self.settings.register_widget(
+            "General",
+            "Guided Tour",
+            Arc::new(RwLock::new(crate::gui::tour::GuidedTourWidget::new(
+                &self.shared,
+            ))),
+        );
+
+        self.settings.register_widget(
+            "Diagnostics",
+            "Provenance",
+            Arc::new(RwLock::new(provenance::ProvenanceWidget::new(
+                &self.shared,
+            ))),
+        );
     }
 
     pub fn init_custom_window(
         &mut self,
-        event_loop: &EventLoop<()>,
+        event_loop: &EventLoopWindowTarget<()>,
         state: &raving_wgpu::State,
         id: &str,
         title: Option<&str>,
@@ -283,8 +786,9 @@ impl App {
         state: &raving_wgpu::State,
     ) -> Result<()> {
         let title = "Waragraph 1D";
+        let hidden_size = self.export_image.as_ref().map(|(_, size)| *size);
 
-        let app = AppWindowState::init(event_loop, state, title, |window| {
+        let constructor = |window: &WindowState| -> Result<Box<dyn AppWindow>> {
             let dims: [u32; 2] = window.window.inner_size().into();
 
             let mut app = Viewer1D::init(
@@ -297,7 +801,19 @@ impl App {
             )?;
 
             Ok(Box::new(app))
-        })?;
+        };
+
+        let app = if let Some(size) = hidden_size {
+            AppWindowState::init_hidden(
+                event_loop,
+                state,
+                title,
+                size,
+                constructor,
+            )?
+        } else {
+            AppWindowState::init(event_loop, state, title, constructor)?
+        };
 
         let winid = app.window.window.id();
 
@@ -327,7 +843,7 @@ impl App {
                 state,
                 &window,
                 self.shared.graph.clone(),
-                tsv,
+                tsv.clone(),
                 &self.shared,
                 &mut self.settings,
             )?;
@@ -337,22 +853,119 @@ impl App {
 
         let winid = app.window.window.id();
 
+        // the viewer is recreated with the same layout when the windows
+        // are rebuilt after GPU errors
+        let log = &self.shared.provenance;
+        if !log.blocking_read().contains_path(&tsv) {
+            provenance::ProvenanceLog::record_file_hashed(
+                log,
+                self.tokio_rt.handle(),
+                provenance::EventKind::Load,
+                "Layout (TSV)",
+                &tsv,
+            );
+        }
+
         self.app_windows.apps.insert(AppType::Viewer2D, app);
         self.app_windows.windows.insert(winid, AppType::Viewer2D);
 
         Ok(())
     }
 
+    /// Recreates the open (or asleep) viewers, keeping their
+    /// navigation state, so that they pick up a new device or graph.
+    fn reinit_viewer_windows(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<()>,
+        state: &raving_wgpu::State,
+    ) -> Result<()> {
+        for app_ty in [AppType::Viewer1D, AppType::Viewer2D] {
+            let view_state =
+                if let Some(app) = self.app_windows.apps.remove(&app_ty) {
+                    let winid = app.window.window.id();
+                    self.app_windows.windows.remove(&winid);
+                    if self.settings_window_tgt == Some(winid) {
+                        self.settings_window_tgt = None;
+                    }
+                    app.app.view_state()
+                } else if let Some(asleep) =
+                    self.app_windows.sleeping.remove(&app_ty)
+                {
+                    asleep.app.view_state()
+                } else {
+                    continue;
+                };
+
+            match app_ty {
+                AppType::Viewer1D => self.init_viewer_1d(event_loop, state)?,
+                AppType::Viewer2D => self.init_viewer_2d(event_loop, state)?,
+                AppType::Custom(_) => unreachable!(),
+            }
+
+            if let (Some(view_state), Some(app)) =
+                (view_state, self.app_windows.apps.get_mut(&app_ty))
+            {
+                app.app.restore_view_state(&view_state);
+            }
+        }
+
+        self.app_windows.update_widget_state();
+
+        Ok(())
+    }
+
     pub fn run(
         mut self,
         event_loop: EventLoop<()>,
-        state: raving_wgpu::State,
+        mut state: raving_wgpu::State,
     ) -> Result<()> {
         let mut is_ready = false;
         let mut prev_frame_t = std::time::Instant::now();
 
         self.app_windows.update_widget_state();
 
+        if let Some(link) = self.initial_view_link.take() {
+            self.open_view_link(&state, &link)?;
+        }
+
+        if let Some(range) = self.initial_range.take() {
+            if let Some(app) = self.app_windows.app_mut(&AppType::Viewer1D) {
+                app.set_view_range(range);
+            }
+        }
+
+        if let Some(path) = self.config_path.take() {
+            let cfg = &self.shared.config;
+            match config::file::ConfigWatcher::new(cfg, &path) {
+                Ok(watcher) => self.config_watcher = Some(watcher),
+                Err(e) => {
+                    log::error!("Error loading config file {path:?}: {e:?}")
+                }
+            }
+        }
+
+        if let Some(path) = self.initial_script.take() {
+            script::spawn_script_file(
+                self.tokio_rt.handle(),
+                &self.shared,
+                path,
+            );
+        }
+
+        if let Some((path, _)) = self.export_image.clone() {
+            let app = self
+                .app_windows
+                .app_mut(&AppType::Viewer1D)
+                .context("The 1D viewer isn't open")?;
+
+            let (done_send, done_recv) = crossbeam::channel::bounded(1);
+            anyhow::ensure!(
+                app.request_image_export(Some(path), done_send),
+                "The 1D viewer can't export images"
+            );
+            self.export_done = Some(done_recv);
+        }
+
         {
             // upload color buffers -- should obviously be handled better,
             // rather than just once at the start!
@@ -389,9 +1002,8 @@ impl App {
                                     ElementState::Pressed
                                 );
 
-                                if let Some(Key::Escape) = input.virtual_keycode
-                                {
-                                    if pressed {
+                                match input.virtual_keycode {
+                                    Some(Key::Escape) if pressed => {
                                         if let Err(e) =
                                             self.shared.app_msg_send.try_send(
                                                 AppMsg::ToggleSettingsWindow {
@@ -402,30 +1014,38 @@ impl App {
                                             log::error!("{e:?}");
                                         }
                                     }
+                                    Some(Key::F6) if pressed => {
+                                        self.app_windows.focus_next(*window_id);
+                                    }
+                                    _ => (),
                                 }
                             }
                             WindowEvent::CloseRequested => {
                                 *control_flow = ControlFlow::Exit
                             }
-                            WindowEvent::Resized(phys_size) => {
+                            WindowEvent::Resized(_) => {
                                 if is_ready {
-                                    app.resize(&state);
-                                    app.app
-                                        .on_resize(
-                                            &state,
-                                            app.window.size.into(),
-                                            (*phys_size).into(),
-                                        )
-                                        .unwrap();
+                                    if let Err(e) = app.resize(&state) {
+                                        log::error!("Resize error: {e:?}");
+                                        self.gpu_error.store(true);
+                                    }
                                 }
                             }
                             WindowEvent::ScaleFactorChanged {
-                                new_inner_size,
+                                scale_factor,
                                 ..
                             } => {
-                                if is_ready {
-                                    app.resize(&state);
-                                }
+                                // egui picks up the new scale factor in
+                                // `on_event`, and the new size arrives
+                                // as a `Resized` event; if the size
+                                // doesn't change, only the UI needs to
+                                // be laid out and drawn again
+                                log::debug!(
+                                    "Scale factor of {:?} changed to \
+                                     {scale_factor}",
+                                    app.title
+                                );
+                                app.window.window.request_redraw();
                             }
                             _ => {}
                         }
@@ -440,14 +1060,35 @@ impl App {
                     let app_type = app_type.unwrap();
 
                     let app = self.app_windows.apps.get_mut(app_type).unwrap();
-                    app.render(&state).unwrap();
+                    if let Err(e) = app.render(&state) {
+                        log::error!("Render error: {e:?}");
+                        self.gpu_error.store(true);
+                    }
                 }
                 Event::MainEventsCleared => {
                     let dt = prev_frame_t.elapsed().as_secs_f32();
                     prev_frame_t = std::time::Instant::now();
 
+                    // the frame rate limit of the performance preset
+                    let frame_time =
+                        self.shared.performance.load().frame_time();
+                    *control_flow = match frame_time {
+                        Some(t) => ControlFlow::WaitUntil(prev_frame_t + t),
+                        None => ControlFlow::Poll,
+                    };
+
                     self.context_state.start_frame();
 
+                    if self.gpu_error.take() {
+                        if let Err(e) = self
+                            .recover_from_gpu_error(event_loop_tgt, &mut state)
+                        {
+                            log::error!("{e:?}");
+                            *control_flow = ControlFlow::Exit;
+                            return;
+                        }
+                    }
+
                     while let Ok(msg) = self.app_msg_recv.try_recv() {
                         if let Err(e) =
                             self.process_msg(event_loop_tgt, &state, msg)
@@ -456,6 +1097,26 @@ impl App {
                         }
                     }
 
+                    if self.exit_requested {
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+
+                    // the sender is dropped if the export failed
+                    if let Some(done) = self.export_done.as_ref() {
+                        match done.try_recv() {
+                            Ok(_) => {
+                                *control_flow = ControlFlow::Exit;
+                                return;
+                            }
+                            Err(crossbeam::channel::TryRecvError::Empty) => {}
+                            Err(_) => {
+                                *control_flow = ControlFlow::ExitWithCode(1);
+                                return;
+                            }
+                        }
+                    }
+
                     // TODO: don't really like just having this here,
                     // but good enough for now
                     self.app_windows.update_widget_state();
@@ -477,6 +1138,10 @@ impl App {
                             self.settings.show(app.egui.ctx());
                         }
 
+                        self.notifications
+                            .blocking_write()
+                            .show(app.egui.ctx(), &self.shared.app_msg_send);
+
                         if context_inspector_tgts.contains(app_type) {
                             egui::Window::new("Context Inspector")
                                 .default_pos([100.0, 100.0])
@@ -486,9 +1151,23 @@ impl App {
                                 });
                         }
 
-                        app.window.window.request_redraw();
+                        if self.export_image.is_some() {
+                            // hidden windows don't get redraw events
+                            // on every platform
+                            if let Err(e) = app.render(&state) {
+                                log::error!("Render error: {e:?}");
+                                self.gpu_error.store(true);
+                            }
+                        } else {
+                            app.window.window.request_redraw();
+                        }
                     }
                 }
+                Event::LoopDestroyed => {
+                    // the event loop doesn't drop the app on every
+                    // platform, so the converted GFA is removed here
+                    self.og_conversion.take();
+                }
 
                 _ => {}
             },
@@ -516,6 +1195,12 @@ impl App {
                     }
                 }
             }
+            AppMsg::RunOdgiLayout => {
+                self.run_odgi_layout();
+            }
+            AppMsg::RunBuiltinLayout => {
+                self.run_builtin_layout();
+            }
             AppMsg::OpenSettingsWindow { src } => {
                 if self.settings_window_tgt.is_none() {
                     self.settings_window_tgt = Some(src);
@@ -534,6 +1219,96 @@ impl App {
                 self.app_windows
                     .handle_window_delta(event_loop, state, delta)?;
             }
+            AppMsg::CopyViewLink => {
+                let link = self.create_view_link().to_uri();
+                self.view_link_widget.blocking_write().set_link(link);
+            }
+            AppMsg::OpenViewLink(link) => {
+                let link = ViewLink::parse(&link)?;
+                self.open_view_link(state, &link)?;
+            }
+            AppMsg::ExportSession(path) => {
+                self.sync_session();
+                self.save_session(&path)?;
+            }
+            AppMsg::SwitchGraph(ix) => {
+                self.switch_graph(ix)?;
+            }
+            AppMsg::OpenSession(path) => {
+                self.open_session(&path)?;
+            }
+            AppMsg::Script(cmd) => {
+                self.apply_script_cmd(state, cmd)?;
+            }
+            AppMsg::GotoAnnotation(id) => {
+                let annotations = self.shared.annotations.clone();
+                let annotations = annotations.blocking_read();
+                let Some(annotation) = annotations.get(id) else {
+                    return Ok(());
+                };
+
+                for app_ty in [AppType::Viewer1D, AppType::Viewer2D] {
+                    if let Some(app) = self.app_windows.app_mut(&app_ty) {
+                        app.goto_region(
+                            Some(annotation.path),
+                            annotation.range.clone(),
+                        );
+                    }
+                }
+            }
+            AppMsg::CenterNode2D(node) => {
+                if let Some(app) = self.app_windows.app_mut(&AppType::Viewer2D)
+                {
+                    app.goto_node(node);
+                }
+            }
+            AppMsg::GotoNode(node) => {
+                for app_ty in [AppType::Viewer1D, AppType::Viewer2D] {
+                    if let Some(app) = self.app_windows.app_mut(&app_ty) {
+                        app.goto_node(node);
+                    }
+                }
+            }
+            AppMsg::GotoRegion { path, range } => {
+                for app_ty in [AppType::Viewer1D, AppType::Viewer2D] {
+                    if let Some(app) = self.app_windows.app_mut(&app_ty) {
+                        app.goto_region(path, range.clone());
+                    }
+                }
+            }
+            AppMsg::AddPaths(gaf) => {
+                self.add_paths(event_loop, state, gaf)?;
+            }
+            AppMsg::JobDone(job) => {
+                self.notifications.blocking_write().push(job);
+            }
+            AppMsg::FocusWindow(app_ty) => {
+                if let Some(app) = self.app_windows.apps.get(&app_ty) {
+                    app.window.window.focus_window();
+                }
+            }
+            AppMsg::OpenPresenceMatrix => {
+                use crate::viewer_1d::presence_matrix::PresenceMatrix;
+
+                let app_ty = AppType::Custom(PresenceMatrix::TITLE.into());
+
+                if self.app_windows.sleeping.contains_key(&app_ty) {
+                    self.app_windows.handle_window_delta(
+                        event_loop,
+                        state,
+                        WindowDelta::Open(app_ty),
+                    )?;
+                } else if !self.app_windows.apps.contains_key(&app_ty) {
+                    let shared = self.shared.clone();
+                    self.init_custom_window(
+                        event_loop,
+                        state,
+                        PresenceMatrix::TITLE,
+                        None,
+                        |_window| Ok(Box::new(PresenceMatrix::new(&shared))),
+                    )?;
+                }
+            }
         }
 
         Ok(())
@@ -571,58 +1346,83 @@ pub trait AppWindow {
         swapchain_view: &wgpu::TextureView,
         encoder: &mut wgpu::CommandEncoder,
     ) -> anyhow::Result<()>;
-}
-
-#[derive(Debug)]
-pub struct Args {
-    pub gfa: PathBuf,
-    pub tsv: Option<PathBuf>,
-
-    pub annotations: Vec<PathBuf>,
-    pub gff_attr: Option<String>,
-    // pub annotations: Option<PathBuf>,
-}
-
-pub fn parse_args() -> std::result::Result<Args, pico_args::Error> {
-    let mut pargs = pico_args::Arguments::from_env();
-
-    // let init_range = pargs.opt_value_from_fn("--range", parse_range)?;
-
-    let mut annotations = Vec::new();
-
-    let bed = pargs.opt_value_from_os_str("--bed", parse_path)?;
-    if let Some(bed) = bed {
-        annotations.push(bed);
-    }
-
-    let gff = pargs.opt_value_from_os_str("--gff", parse_path)?;
-    if let Some(gff) = gff {
-        annotations.push(gff);
+    /// Returns the window's current navigation state, if it has one
+    /// that can be restored with `restore_view_state`.
+    fn view_state(&self) -> Option<ViewState> {
+        None
     }
 
-    let gff_attr = pargs.opt_value_from_str("--gff-attr")?;
+    fn restore_view_state(&mut self, _view_state: &ViewState) {}
 
-    let args = Args {
-        gfa: pargs.free_from_os_str(parse_path)?,
-        tsv: pargs.opt_free_from_os_str(parse_path)?,
+    /// Moves the view to the range of the path, or of the pangenome
+    /// if `path` is `None`, if the window has a view of the graph.
+    fn goto_region(
+        &mut self,
+        _path: Option<PathId>,
+        _range: std::ops::Range<Bp>,
+    ) {
+    }
 
-        annotations,
-        gff_attr,
-        // init_range,
-    };
+    /// Moves the view to the node, if the window has a view of the
+    /// graph.
+    fn goto_node(&mut self, _node: Node) {}
 
-    Ok(args)
-}
+    /// Shows exactly the pangenome range, if the window has a 1D view
+    fn set_view_range(&mut self, _range: std::ops::Range<Bp>) {}
 
-fn parse_path(s: &std::ffi::OsStr) -> Result<std::path::PathBuf, &'static str> {
-    Ok(s.into())
+    /// Exports an image of the window's view, to `path` or a path
+    /// generated from the export settings, sending the path on `done`
+    /// once it's written; returns `false` if not supported.
+    fn request_image_export(
+        &mut self,
+        _path: Option<PathBuf>,
+        _done: crossbeam::channel::Sender<PathBuf>,
+    ) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum AppMsg {
     InitViewer1D,
     InitViewer2D,
-    OpenSettingsWindow { src: WindowId },
-    ToggleSettingsWindow { src: WindowId },
+    RunOdgiLayout,
+    RunBuiltinLayout,
+    OpenSettingsWindow {
+        src: WindowId,
+    },
+    ToggleSettingsWindow {
+        src: WindowId,
+    },
     WindowDelta(WindowDelta),
+    CopyViewLink,
+    OpenViewLink(String),
+    ExportSession(PathBuf),
+    /// Restarts the app with the graph at the given index in the session
+    SwitchGraph(usize),
+    /// Restarts the app with the session in the file
+    OpenSession(PathBuf),
+    Script(script::ScriptCmd),
+    /// Moves the views of all viewers to the annotation
+    GotoAnnotation(GlobalAnnotationId),
+    /// Centers the 2D view on the node, keeping its zoom level
+    CenterNode2D(Node),
+    /// Moves the views of all viewers to the node
+    GotoNode(Node),
+    /// Moves the views of all viewers to the range of the path, or of
+    /// the pangenome if `path` is `None`
+    GotoRegion {
+        path: Option<PathId>,
+        range: std::ops::Range<Bp>,
+    },
+    /// Adds a path for each sequence aligned in the GAF to the graph,
+    /// and reloads the viewers with them
+    AddPaths(PathBuf),
+    /// Shows a notification for a finished background job
+    JobDone(notify::JobDone),
+    /// Raises the window of the app, if it's open
+    FocusWindow(AppType),
+    /// Opens the path presence matrix window, creating it the first
+    /// time
+    OpenPresenceMatrix,
 }
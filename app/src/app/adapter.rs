@@ -0,0 +1,281 @@
+//! Backend and adapter selection, and adapter diagnostics.
+//!
+//! The device itself is created by `raving_wgpu`, so the selection is
+//! passed on using wgpu's standard `WGPU_BACKEND`, `WGPU_ADAPTER_NAME`,
+//! and `WGPU_POWER_PREF` environment variables, which must be set
+//! before initialization.
+
+use anyhow::Result;
+
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AdapterOptions {
+    /// Comma-separated list of backends, e.g. `vulkan,gl`
+    pub backend: Option<String>,
+    /// Case-insensitive substring of the adapter name
+    pub adapter_name: Option<String>,
+    /// `low` or `high`
+    pub power_preference: Option<String>,
+}
+
+impl AdapterOptions {
+    pub fn from_args(
+        pargs: &mut pico_args::Arguments,
+    ) -> std::result::Result<Self, pico_args::Error> {
+        Ok(Self {
+            backend: pargs.opt_value_from_str("--backend")?,
+            adapter_name: pargs.opt_value_from_str("--adapter")?,
+            power_preference: pargs.opt_value_from_str("--power-pref")?,
+        })
+    }
+
+    pub fn backends(&self) -> Result<wgpu::Backends> {
+        let Some(backend) = self.backend.as_ref() else {
+            return Ok(wgpu::Backends::all());
+        };
+
+        let mut backends = wgpu::Backends::empty();
+
+        for name in backend.split(',') {
+            backends |= match name.trim().to_ascii_lowercase().as_str() {
+                "vulkan" | "vk" => wgpu::Backends::VULKAN,
+                "metal" | "mtl" => wgpu::Backends::METAL,
+                "dx12" | "d3d12" => wgpu::Backends::DX12,
+                "dx11" | "d3d11" => wgpu::Backends::DX11,
+                "gl" | "opengl" | "gles" => wgpu::Backends::GL,
+                other => anyhow::bail!(
+                    "Unknown backend `{other}`, \
+                     expected one of vulkan, metal, dx12, dx11, gl"
+                ),
+            };
+        }
+
+        Ok(backends)
+    }
+
+    pub fn power_preference(&self) -> Result<wgpu::PowerPreference> {
+        match self.power_preference.as_deref() {
+            None => Ok(wgpu::PowerPreference::HighPerformance),
+            Some("low") => Ok(wgpu::PowerPreference::LowPower),
+            Some("high") => Ok(wgpu::PowerPreference::HighPerformance),
+            Some(other) => {
+                anyhow::bail!(
+                    "Unknown power preference `{other}`, expected low or high"
+                )
+            }
+        }
+    }
+
+    pub fn matches(&self, info: &wgpu::AdapterInfo) -> bool {
+        let backend_ok = self
+            .backends()
+            .map(|b| b.contains(info.backend.into()))
+            .unwrap_or(false);
+
+        let name_ok = self
+            .adapter_name
+            .as_ref()
+            .map(|name| {
+                info.name
+                    .to_ascii_lowercase()
+                    .contains(&name.to_ascii_lowercase())
+            })
+            .unwrap_or(true);
+
+        backend_ok && name_ok
+    }
+
+    /// Checks the options and exports them as `WGPU_*` environment
+    /// variables; fails if no available adapter matches.
+    pub fn apply(&self) -> Result<()> {
+        let backends = self.backends()?;
+        self.power_preference()?;
+
+        if self.backend.is_some() || self.adapter_name.is_some() {
+            let adapters = enumerate_adapters(backends);
+
+            if !adapters.iter().any(|a| self.matches(&a.info)) {
+                let available = adapters
+                    .iter()
+                    .map(|a| format!("{} ({:?})", a.info.name, a.info.backend))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                anyhow::bail!(
+                    "No adapter matches the selection; available: {available}"
+                );
+            }
+        }
+
+        if let Some(backend) = self.backend.as_ref() {
+            std::env::set_var("WGPU_BACKEND", backend);
+        }
+
+        if let Some(name) = self.adapter_name.as_ref() {
+            std::env::set_var("WGPU_ADAPTER_NAME", name);
+        }
+
+        if let Some(pref) = self.power_preference.as_ref() {
+            std::env::set_var("WGPU_POWER_PREF", pref);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AdapterDiagnostics {
+    pub info: wgpu::AdapterInfo,
+    pub limits: wgpu::Limits,
+    pub features: wgpu::Features,
+}
+
+pub fn enumerate_adapters(backends: wgpu::Backends) -> Vec<AdapterDiagnostics> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    });
+
+    instance
+        .enumerate_adapters(backends)
+        .map(|adapter| AdapterDiagnostics {
+            info: adapter.get_info(),
+            limits: adapter.limits(),
+            features: adapter.features(),
+        })
+        .collect()
+}
+
+/// Prints the available adapters and their main limits, for
+/// `--list-adapters`.
+pub fn print_adapters() {
+    let adapters = enumerate_adapters(wgpu::Backends::all());
+
+    if adapters.is_empty() {
+        println!("No adapters found");
+    }
+
+    for (ix, adapter) in adapters.iter().enumerate() {
+        let info = &adapter.info;
+        println!(
+            "{ix}: {} ({:?}, {:?})",
+            info.name, info.backend, info.device_type
+        );
+        println!("    driver: {} {}", info.driver, info.driver_info);
+        for (name, value) in limit_rows(&adapter.limits) {
+            println!("    {name}: {value}");
+        }
+    }
+}
+
+fn limit_rows(limits: &wgpu::Limits) -> [(&'static str, String); 4] {
+    use crate::util::gpu_memory::format_bytes;
+
+    [
+        ("max buffer size", format_bytes(limits.max_buffer_size)),
+        (
+            "max storage binding",
+            format_bytes(limits.max_storage_buffer_binding_size as u64),
+        ),
+        (
+            "max texture size",
+            limits.max_texture_dimension_2d.to_string(),
+        ),
+        (
+            "max compute workgroup size",
+            limits.max_compute_invocations_per_workgroup.to_string(),
+        ),
+    ]
+}
+
+/// Settings window panel listing the available adapters.
+pub struct AdapterDiagnosticsWidget {
+    options: AdapterOptions,
+    adapters: Option<Vec<AdapterDiagnostics>>,
+}
+
+impl AdapterDiagnosticsWidget {
+    pub fn new(options: AdapterOptions) -> Self {
+        Self {
+            options,
+            adapters: None,
+        }
+    }
+}
+
+impl SettingsWidget for AdapterDiagnosticsWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let adapters = self
+            .adapters
+            .get_or_insert_with(|| enumerate_adapters(wgpu::Backends::all()));
+
+        let resp = ui.vertical(|ui| {
+            let backend = self.options.backend.as_deref().unwrap_or("any");
+            let name = self.options.adapter_name.as_deref().unwrap_or("any");
+            ui.label(format!("Selected backend: {backend}, adapter: {name}"));
+
+            for (ix, adapter) in adapters.iter().enumerate() {
+                let info = &adapter.info;
+
+                let mut title = format!(
+                    "{} ({:?}, {:?})",
+                    info.name, info.backend, info.device_type
+                );
+
+                if self.options.matches(info) {
+                    title.push_str(" *");
+                }
+
+                egui::CollapsingHeader::new(title)
+                    .id_source(("adapter_diagnostics", ix))
+                    .show(ui, |ui| {
+                        ui.label(format!(
+                            "Driver: {} {}",
+                            info.driver, info.driver_info
+                        ));
+
+                        for (name, value) in limit_rows(&adapter.limits) {
+                            ui.label(format!("{name}: {value}"));
+                        }
+
+                        ui.label(format!("Features: {:?}", adapter.features));
+                    });
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adapter_backend_parsing() {
+        let opts = AdapterOptions {
+            backend: Some("vulkan, GL".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            opts.backends().unwrap(),
+            wgpu::Backends::VULKAN | wgpu::Backends::GL
+        );
+
+        let opts = AdapterOptions {
+            backend: Some("glide".to_string()),
+            ..Default::default()
+        };
+
+        assert!(opts.backends().is_err());
+    }
+}
@@ -0,0 +1,356 @@
+//! The "Add Sample" panel: aligns the sequences in a FASTA file to the
+//! graph with GraphAligner, or takes a GAF of alignments made
+//! elsewhere, and adds a path to the loaded graph for each aligned
+//! sequence (see `waragraph_core::graph::gaf`).
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use tokio::process::Command;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::{gaf, PathId, PathIndex};
+use winit::event_loop::EventLoopWindowTarget;
+
+use crate::context::widget::ContextInspector;
+
+use super::resource::GraphDataCache;
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{App, AppMsg, SharedState};
+
+/// Adds the paths in the GAF files given on the command line to the
+/// graph, returning the added paths by file; files that fail are
+/// logged and skipped.
+pub fn add_aligned_paths(
+    graph: &mut PathIndex,
+    gafs: &[PathBuf],
+) -> Vec<(PathBuf, Vec<PathId>)> {
+    let mut added_paths = Vec::new();
+
+    for gaf in gafs {
+        match graph.add_gaf_paths(gaf) {
+            Ok(added) => {
+                log::warn!("Added {} aligned paths from {gaf:?}", added.len());
+                added_paths.push((gaf.clone(), added));
+            }
+            Err(e) => log::error!("Error adding paths from {gaf:?}: {e:?}"),
+        }
+    }
+
+    added_paths
+}
+
+/// Looks for a `GraphAligner` executable in the directories in `PATH`.
+pub fn find_graph_aligner() -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&paths).find_map(|dir| {
+        let aligner = dir.join("GraphAligner");
+        aligner.is_file().then_some(aligner)
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum AddSampleStatus {
+    Aligning,
+    Reading,
+    Ready { gaf: PathBuf, paths: usize },
+    Failed(String),
+}
+
+pub struct AddSampleWidget {
+    shared: SharedState,
+
+    aligner: Option<PathBuf>,
+    /// GraphAligner can't read GBZ graphs
+    gbz: bool,
+
+    fasta_recv: Option<oneshot::Receiver<PathBuf>>,
+    gaf_recv: Option<oneshot::Receiver<PathBuf>>,
+
+    status: Option<Arc<Mutex<AddSampleStatus>>>,
+}
+
+impl AddSampleWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            shared: shared.clone(),
+            aligner: find_graph_aligner(),
+            gbz: waragraph_core::graph::gbz::is_gbz_file(
+                shared.workspace.blocking_read().gfa_path(),
+            ),
+            fasta_recv: None,
+            gaf_recv: None,
+            status: None,
+        }
+    }
+
+    fn start(
+        &mut self,
+        rt: &tokio::runtime::Handle,
+        fasta: Option<PathBuf>,
+        gaf: PathBuf,
+    ) {
+        let status = Arc::new(Mutex::new(if fasta.is_some() {
+            AddSampleStatus::Aligning
+        } else {
+            AddSampleStatus::Reading
+        }));
+        self.status = Some(status.clone());
+
+        let graph = self.shared.graph.clone();
+        let gfa = self.shared.workspace.blocking_read().gfa_path().clone();
+        let aligner = self.aligner.clone();
+
+        rt.spawn(async move {
+            let result = async {
+                if let (Some(fasta), Some(aligner)) = (fasta, aligner) {
+                    run_graph_aligner(&aligner, &gfa, &fasta, &gaf).await?;
+                    *status.lock().unwrap() = AddSampleStatus::Reading;
+                }
+
+                let gaf_ = gaf.clone();
+                let paths = tokio::task::spawn_blocking(move || {
+                    count_gaf_paths(&graph, &gaf_)
+                })
+                .await??;
+
+                anyhow::Ok(paths)
+            }
+            .await;
+
+            *status.lock().unwrap() = match result {
+                Ok(paths) => AddSampleStatus::Ready { gaf, paths },
+                Err(e) => {
+                    log::error!("Error adding sample: {e:?}");
+                    AddSampleStatus::Failed(e.to_string())
+                }
+            };
+        });
+    }
+
+    fn poll_channels(&mut self, settings_ctx: &SettingsUiContext) {
+        let rt = &settings_ctx.tokio_handle;
+
+        if let Some(mut recv) = self.fasta_recv.take() {
+            match recv.try_recv() {
+                Ok(fasta) => {
+                    let gaf = fasta.with_extension("gaf");
+                    self.start(rt, Some(fasta), gaf);
+                }
+                Err(TryRecvError::Empty) => self.fasta_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.gaf_recv.take() {
+            match recv.try_recv() {
+                Ok(gaf) => self.start(rt, None, gaf),
+                Err(TryRecvError::Empty) => self.gaf_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+/// The number of paths the GAF would add to the graph.
+fn count_gaf_paths(graph: &PathIndex, gaf_path: &Path) -> Result<usize> {
+    let gaf = std::fs::File::open(gaf_path).map(std::io::BufReader::new)?;
+    let paths = gaf::read_gaf_paths(graph, gaf)?;
+
+    let new = paths
+        .iter()
+        .filter(|(name, _)| !graph.path_names.contains_right(name))
+        .count();
+
+    anyhow::ensure!(new > 0, "No new aligned sequences in {gaf_path:?}");
+    Ok(new)
+}
+
+async fn run_graph_aligner(
+    aligner: &Path,
+    gfa: &Path,
+    fasta: &Path,
+    gaf: &Path,
+) -> Result<()> {
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .to_string();
+
+    log::warn!("Aligning {fasta:?} to the graph with GraphAligner");
+
+    let output = Command::new(aligner)
+        .arg("-g")
+        .arg(gfa)
+        .arg("-f")
+        .arg(fasta)
+        .arg("-a")
+        .arg(gaf)
+        .args(["-x", "vg", "-t", &threads])
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last_line = stderr
+            .lines()
+            .rfind(|l| !l.trim().is_empty())
+            .unwrap_or_default();
+        anyhow::bail!("GraphAligner failed: {}", last_line.trim());
+    }
+
+    Ok(())
+}
+
+impl SettingsWidget for AddSampleWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels(settings_ctx);
+
+        let status = self.status.as_ref().map(|s| s.lock().unwrap().clone());
+        let busy = matches!(
+            status,
+            Some(AddSampleStatus::Aligning | AddSampleStatus::Reading)
+        );
+
+        let resp = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                let align = ui
+                    .add_enabled(
+                        self.aligner.is_some() && !self.gbz && !busy,
+                        egui::Button::new("Align FASTA"),
+                    )
+                    .on_hover_text(
+                        "Aligns the sequences to the graph with GraphAligner, \
+                         writing the alignments next to the FASTA",
+                    )
+                    .on_disabled_hover_text(if self.gbz {
+                        "GraphAligner can't align to GBZ graphs"
+                    } else {
+                        "GraphAligner not found in PATH"
+                    });
+
+                if align.clicked() {
+                    let mut files = egui_file::FileDialog::open_file(None);
+                    files.open();
+
+                    let id = egui::Id::new("AddSampleWidget_fasta");
+                    let recv = settings_ctx.with_file_dialog_oneshot(id, files);
+                    self.fasta_recv = Some(recv);
+                }
+
+                if ui
+                    .add_enabled(!busy, egui::Button::new("Load GAF"))
+                    .on_hover_text("Uses alignments made elsewhere")
+                    .clicked()
+                {
+                    let mut files = egui_file::FileDialog::open_file(None);
+                    files.open();
+
+                    let id = egui::Id::new("AddSampleWidget_gaf");
+                    let recv = settings_ctx.with_file_dialog_oneshot(id, files);
+                    self.gaf_recv = Some(recv);
+                }
+            });
+
+            match status {
+                None => {}
+                Some(AddSampleStatus::Aligning) => {
+                    ui.horizontal(|ui| {
+                        ui.label("Aligning...");
+                        ui.spinner();
+                    });
+                }
+                Some(AddSampleStatus::Reading) => {
+                    ui.horizontal(|ui| {
+                        ui.label("Reading alignments...");
+                        ui.spinner();
+                    });
+                }
+                Some(AddSampleStatus::Ready { gaf, paths }) => {
+                    ui.label(format!("{paths} aligned sequences"));
+
+                    if ui
+                        .button("Add paths")
+                        .on_hover_text(
+                            "Adds a path for each aligned sequence, keeping \
+                             the current views",
+                        )
+                        .clicked()
+                    {
+                        settings_ctx.send_app_msg_task(AppMsg::AddPaths(gaf));
+                    }
+                }
+                Some(AddSampleStatus::Failed(err)) => {
+                    ui.label(format!("Error: {err}"));
+                }
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+impl App {
+    /// Adds the paths aligned in `gaf` to a copy of the graph, which
+    /// then replaces the shared graph; the widgets and viewers that
+    /// hold the old one are recreated, and the GAF is stored in the
+    /// session so that the paths are added again when it's reloaded.
+    pub(super) fn add_paths(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<()>,
+        state: &raving_wgpu::State,
+        gaf: PathBuf,
+    ) -> Result<()> {
+        let mut graph = PathIndex::clone(&self.shared.graph);
+        let added = graph.add_gaf_paths(&gaf)?;
+
+        if added.is_empty() {
+            log::warn!("No new aligned paths in {gaf:?}");
+            return Ok(());
+        }
+        log::warn!("Added {} aligned paths from {gaf:?}", added.len());
+
+        let graph = Arc::new(graph);
+        self.shared.graph_data_cache = Arc::new(GraphDataCache::with_graph(
+            &self.shared.graph_data_cache,
+            &graph,
+        ));
+        self.shared.graph = graph;
+
+        self.shared
+            .workspace
+            .blocking_write()
+            .added_paths
+            .push((gaf.clone(), added));
+
+        {
+            let mut session = self.session.blocking_write();
+            let entry = session.active_graph_mut();
+            if !entry.aligned.contains(&gaf) {
+                entry.aligned.push(gaf);
+            }
+        }
+
+        self.context_inspector =
+            ContextInspector::with_default_widgets(&self.shared);
+        self.settings.register_widget(
+            "Context",
+            "Context Inspector",
+            self.context_inspector.settings_widget().clone(),
+        );
+        self.register_graph_widgets();
+
+        self.reinit_viewer_windows(event_loop, state)
+    }
+}
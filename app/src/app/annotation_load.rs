@@ -0,0 +1,358 @@
+//! Loading annotation files in the background, so that large BED and
+//! GFF files don't block the GUI. The annotations show up in the
+//! viewers in batches while the file is read, and the loading can be
+//! cancelled.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use tokio::sync::RwLock;
+use waragraph_core::graph::PathIndex;
+
+use crate::annotations::progress::AnnotationLoadProgress;
+use crate::annotations::{annotation_set_name, AnnotationSet, AnnotationStore};
+
+use super::notify::JobDone;
+use super::provenance::{self, EventKind, ProvenanceLog};
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{AppMsg, SharedState};
+
+/// How often the annotations parsed so far are added to the store.
+const BATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationLoadStatus {
+    Loading,
+    Done { annotations: usize },
+    Cancelled,
+    Failed(String),
+}
+
+/// Handle to an annotation file being loaded in the background; the
+/// status can be polled from the GUI.
+#[derive(Clone)]
+pub struct AnnotationLoadJob {
+    path: PathBuf,
+    progress: Arc<AnnotationLoadProgress>,
+    status: Arc<Mutex<AnnotationLoadStatus>>,
+}
+
+impl AnnotationLoadJob {
+    pub fn status(&self) -> AnnotationLoadStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn cancel(&self) {
+        self.progress.cancel();
+    }
+
+    /// Loads the file on a blocking thread. An empty set is added to
+    /// the store right away, and the annotations are appended to it
+    /// as they're parsed; when done, the set is replaced by the
+    /// complete one. If the loading fails or is cancelled, the set is
+    /// removed.
+    ///
+    /// Must not be called from within the tokio runtime.
+    pub fn spawn(
+        rt: &tokio::runtime::Handle,
+        shared: &SharedState,
+        path: PathBuf,
+        gff_attr: &str,
+    ) -> Self {
+        let progress = Arc::new(AnnotationLoadProgress::default());
+        let status = Arc::new(Mutex::new(AnnotationLoadStatus::Loading));
+
+        let job = Self {
+            path: path.clone(),
+            progress: progress.clone(),
+            status: status.clone(),
+        };
+
+        let set = AnnotationSet {
+            name: annotation_set_name(&path, None),
+            annotations: Vec::new(),
+            path_annotations: Default::default(),
+            out_of_bounds: 0,
+            source: Some(path.clone()),
+        };
+        let set_id = shared.annotations.blocking_write().insert_set(set);
+
+        let graph = shared.graph.clone();
+        let annotations = shared.annotations.clone();
+        let provenance = shared.provenance.clone();
+        let app_msg_send = shared.app_msg_send.clone();
+        let gff_attr = gff_attr.to_string();
+        let handle = rt.clone();
+
+        let started = Instant::now();
+
+        rt.spawn(async move {
+            let mut task = {
+                let progress = progress.clone();
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || {
+                    AnnotationSet::from_file_with_progress(
+                        &graph,
+                        &gff_attr,
+                        &path,
+                        Some(&progress),
+                    )
+                })
+            };
+
+            let result = loop {
+                tokio::select! {
+                    result = &mut task => {
+                        break result
+                            .map_err(anyhow::Error::from)
+                            .and_then(|r| r);
+                    }
+                    _ = tokio::time::sleep(BATCH_INTERVAL) => {
+                        let batch = progress.take_batch();
+                        if !batch.is_empty() {
+                            annotations.write().await.extend_set(set_id, batch);
+                        }
+                    }
+                }
+            };
+
+            let done = match result {
+                Ok(set) => {
+                    let count = set.annotations.len();
+                    log::warn!(
+                        "loaded annotation set with {count} annotations \
+                         ({} out of path bounds)",
+                        set.out_of_bounds,
+                    );
+
+                    annotations.write().await.replace_set(set_id, set);
+                    *status.lock().unwrap() =
+                        AnnotationLoadStatus::Done { annotations: count };
+
+                    provenance::ProvenanceLog::record_file_hashed(
+                        &provenance,
+                        &handle,
+                        EventKind::Load,
+                        "Annotations",
+                        &path,
+                    );
+
+                    let mut done = JobDone::finished("Annotations", started);
+                    done.detail = Some(format!(
+                        "{count} annotations from {}",
+                        path.display()
+                    ));
+                    Some(done)
+                }
+                Err(_) if progress.is_cancelled() => {
+                    log::warn!("Cancelled loading {path:?}");
+                    annotations.write().await.remove_set(set_id);
+                    *status.lock().unwrap() = AnnotationLoadStatus::Cancelled;
+                    None
+                }
+                Err(e) => {
+                    log::error!(
+                        "Error loading annotation file {path:?}: {e:?}"
+                    );
+                    annotations.write().await.remove_set(set_id);
+                    *status.lock().unwrap() =
+                        AnnotationLoadStatus::Failed(e.to_string());
+                    Some(JobDone::failed("Annotations", started, &e))
+                }
+            };
+
+            if let Some(done) = done {
+                if let Err(e) = app_msg_send.send(AppMsg::JobDone(done)).await {
+                    log::error!("{e:?}");
+                }
+            }
+        });
+
+        job
+    }
+
+    /// Shows the file name and the progress, with a button to cancel
+    /// the loading.
+    pub fn show(&self, ui: &mut egui::Ui) {
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default();
+
+        ui.horizontal(|ui| {
+            ui.label(name);
+
+            match self.status() {
+                AnnotationLoadStatus::Loading => {
+                    let records = self.progress.records();
+                    ui.add(
+                        egui::ProgressBar::new(self.progress.fraction())
+                            .text(format!("{records} annotations"))
+                            .desired_width(160.0)
+                            .animate(true),
+                    );
+                    if ui.button("Cancel").clicked() {
+                        self.cancel();
+                    }
+                }
+                AnnotationLoadStatus::Done { annotations } => {
+                    ui.label(format!("{annotations} annotations"));
+                }
+                AnnotationLoadStatus::Cancelled => {
+                    ui.label("Cancelled");
+                }
+                AnnotationLoadStatus::Failed(err) => {
+                    ui.label(format!("Error: {err}"));
+                }
+            }
+        });
+    }
+}
+
+/// Loads the annotation files given on the command line, logging the
+/// ones that fail.
+pub fn load_files(
+    graph: &PathIndex,
+    gff_attr: &str,
+    paths: &[PathBuf],
+    provenance: &Arc<RwLock<ProvenanceLog>>,
+    tokio_handle: &tokio::runtime::Handle,
+) -> AnnotationStore {
+    let mut annotations = AnnotationStore::default();
+
+    for annot_path in paths {
+        match AnnotationSet::from_file(graph, gff_attr, annot_path) {
+            Ok(set) => {
+                log::warn!(
+                    "loaded annotation set with {} annotations \
+                     ({} out of path bounds)",
+                    set.annotations.len(),
+                    set.out_of_bounds,
+                );
+
+                annotations.insert_set(set);
+
+                ProvenanceLog::record_file_hashed(
+                    provenance,
+                    tokio_handle,
+                    EventKind::Load,
+                    "Annotations",
+                    annot_path,
+                );
+            }
+            Err(e) => {
+                log::error!(
+                    "Error loading annotation file {:?}: {e:?}",
+                    annot_path.as_os_str()
+                );
+            }
+        }
+    }
+
+    annotations
+}
+
+/// Loads BED and GFF files picked from a file dialog.
+pub struct AnnotationLoadWidget {
+    shared: SharedState,
+    gff_attr: String,
+
+    jobs: Vec<AnnotationLoadJob>,
+    path_recv: Option<oneshot::Receiver<PathBuf>>,
+}
+
+impl AnnotationLoadWidget {
+    pub fn new(shared: &SharedState, gff_attr: &str) -> Self {
+        Self {
+            shared: shared.clone(),
+            gff_attr: gff_attr.to_string(),
+            jobs: Vec::new(),
+            path_recv: None,
+        }
+    }
+
+    fn poll_path(&mut self, settings_ctx: &SettingsUiContext) {
+        let Some(mut recv) = self.path_recv.take() else {
+            return;
+        };
+
+        match recv.try_recv() {
+            Ok(path) => {
+                let job = AnnotationLoadJob::spawn(
+                    &settings_ctx.tokio_handle,
+                    &self.shared,
+                    path,
+                    &self.gff_attr,
+                );
+                self.jobs.push(job);
+            }
+            Err(TryRecvError::Empty) => self.path_recv = Some(recv),
+            Err(TryRecvError::Closed) => {}
+        }
+    }
+}
+
+impl SettingsWidget for AnnotationLoadWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_path(settings_ctx);
+
+        let resp = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Load annotations")
+                    .on_hover_text("Loads a BED or GFF file in the background")
+                    .clicked()
+                {
+                    let mut files = egui_file::FileDialog::open_file(None);
+                    files.open();
+
+                    let id = egui::Id::new("AnnotationLoadWidget_file");
+                    let recv = settings_ctx.with_file_dialog_oneshot(id, files);
+                    self.path_recv = Some(recv);
+                }
+
+                ui.label("GFF label attribute");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.gff_attr)
+                        .desired_width(80.0),
+                );
+            });
+
+            for job in self.jobs.iter() {
+                job.show(ui);
+            }
+
+            let finished = self
+                .jobs
+                .iter()
+                .any(|job| job.status() != AnnotationLoadStatus::Loading);
+
+            if finished && ui.button("Clear finished").clicked() {
+                self.jobs.retain(|job| {
+                    job.status() == AnnotationLoadStatus::Loading
+                });
+            }
+
+            if self
+                .jobs
+                .iter()
+                .any(|job| job.status() == AnnotationLoadStatus::Loading)
+            {
+                ui.ctx().request_repaint_after(BATCH_INTERVAL);
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
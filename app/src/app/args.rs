@@ -0,0 +1,402 @@
+//! Command line arguments, and the startup options derived from
+//! them: the reference path and the initial region.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use waragraph_core::graph::coords::CoordSystem;
+use waragraph_core::graph::reference::ReferenceGuess;
+use waragraph_core::graph::{Bp, PathId};
+
+use super::{adapter, odgi, session};
+
+#[derive(Debug)]
+pub struct Args {
+    pub gfa: PathBuf,
+    pub tsv: Option<PathBuf>,
+    /// The odgi graph `gfa` was converted from, if one was given
+    pub og: Option<PathBuf>,
+    /// The temporary directory holding `gfa`, if it was converted from
+    /// `og`; removed when dropped
+    pub og_conversion: Option<odgi::ConversionDir>,
+
+    pub annotations: Vec<PathBuf>,
+    pub gff_attr: Option<String>,
+    /// GAF files of sequences aligned to the graph, added as paths
+    pub aligned: Vec<PathBuf>,
+    /// bedGraph files of values over path ranges, loaded as data
+    /// tracks of the 1D viewer
+    pub bedgraphs: Vec<PathBuf>,
+    // pub annotations: Option<PathBuf>,
+    pub odgi_layout: bool,
+    /// Don't compute a layout if none is provided
+    pub no_layout: bool,
+
+    pub gpu_memory_budget_mb: Option<u64>,
+
+    pub adapter: adapter::AdapterOptions,
+    pub list_adapters: bool,
+
+    pub software_render: bool,
+    pub render_data: Option<String>,
+    pub render_width: Option<usize>,
+    pub output: Option<PathBuf>,
+    /// Renders the 1D view offscreen to this PNG, with the labels in
+    /// an SVG next to it, and exits, without showing any windows
+    pub export_image: Option<PathBuf>,
+    pub render_height: Option<usize>,
+    pub locus_grid: Option<PathBuf>,
+    pub grid_columns: Option<usize>,
+
+    pub find_regions: bool,
+    pub reference: Option<String>,
+
+    pub nonref_report: Option<PathBuf>,
+    pub min_length: u64,
+
+    pub view_link: Option<String>,
+
+    /// Rhai script to run once the viewers are open
+    pub script: Option<PathBuf>,
+
+    /// TOML file of viewer settings, reloaded when it changes
+    pub config: Option<PathBuf>,
+
+    /// Set by `--session <file>`, which then provides the GFA, layout,
+    /// annotations and view, instead of the positional arguments
+    pub session: Option<session::Session>,
+    pub session_path: Option<PathBuf>,
+
+    /// Set by the `qc` subcommand
+    pub qc: bool,
+
+    /// Set by the `view1d` subcommand, which opens only the 1D viewer
+    /// and doesn't compute a layout, and by `--export-image`
+    pub view1d: bool,
+
+    /// `[path:]start-end` to show in the 1D view on start, with the
+    /// same syntax as the "Go to" box
+    pub region: Option<String>,
+
+    /// Initial coordinate convention; `--one-based` for 1-based, closed
+    /// positions
+    pub coords: CoordSystem,
+
+    /// Start the guided tour, with the bundled example graph if no
+    /// GFA is given
+    pub tour: bool,
+
+    /// Start with the GPU-heavy features disabled, to be enabled one
+    /// at a time from the "Safe Mode" panel
+    pub safe_mode: bool,
+}
+
+impl Args {
+    /// The graph file as given, which is the odgi graph if `gfa` was
+    /// converted from one.
+    pub fn graph_path(&self) -> &Path {
+        self.og.as_deref().unwrap_or(&self.gfa)
+    }
+
+    /// The options that don't depend on the graph, to pass on when
+    /// restarting with another graph in the session. The files loaded
+    /// for the graph are in the session, and the view, region, script
+    /// and tour only apply at the first start.
+    pub fn restart_args(&self) -> Vec<std::ffi::OsString> {
+        let mut out: Vec<std::ffi::OsString> = Vec::new();
+
+        let mut opt = |name: &str, val: Option<std::ffi::OsString>| {
+            if let Some(val) = val {
+                out.push(name.into());
+                out.push(val);
+            }
+        };
+
+        let adapter = &self.adapter;
+        opt("--backend", adapter.backend.clone().map(Into::into));
+        opt("--adapter", adapter.adapter_name.clone().map(Into::into));
+        opt(
+            "--power-pref",
+            adapter.power_preference.clone().map(Into::into),
+        );
+        opt(
+            "--gpu-memory-budget",
+            self.gpu_memory_budget_mb.map(|mb| mb.to_string().into()),
+        );
+        opt("--gff-attr", self.gff_attr.clone().map(Into::into));
+        opt("--reference", self.reference.clone().map(Into::into));
+        opt("--config", self.config.clone().map(Into::into));
+
+        let flags = [
+            ("--odgi-layout", self.odgi_layout),
+            ("--no-layout", self.no_layout),
+            ("--one-based", self.coords == CoordSystem::OneBased),
+            ("--safe-mode", self.safe_mode),
+        ];
+        out.extend(
+            flags
+                .iter()
+                .filter(|(_, on)| *on)
+                .map(|(flag, _)| std::ffi::OsString::from(flag)),
+        );
+
+        if self.view1d {
+            out.insert(0, "view1d".into());
+        }
+
+        out
+    }
+}
+
+#[derive(Debug)]
+pub enum ArgsError {
+    /// Missing or invalid arguments, for which the usage is printed
+    Usage(pico_args::Error),
+    /// A file named by the arguments, e.g. the session, couldn't be
+    /// loaded
+    Load(anyhow::Error),
+}
+
+impl From<pico_args::Error> for ArgsError {
+    fn from(e: pico_args::Error) -> Self {
+        Self::Usage(e)
+    }
+}
+
+impl std::fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Usage(e) => write!(f, "{e}"),
+            Self::Load(e) => write!(f, "{e:#}"),
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+pub fn parse_args() -> std::result::Result<Args, ArgsError> {
+    let mut raw_args = std::env::args_os().skip(1).collect::<Vec<_>>();
+
+    let qc = raw_args.first().map(|arg| arg == "qc").unwrap_or(false);
+    if qc {
+        raw_args.remove(0);
+    }
+
+    let view1d = raw_args.first().map(|arg| arg == "view1d").unwrap_or(false);
+    if view1d {
+        raw_args.remove(0);
+    }
+
+    let mut pargs = pico_args::Arguments::from_vec(raw_args);
+
+    let region = pargs.opt_value_from_str("--region")?;
+
+    // both may be given more than once
+    let mut annotations = pargs.values_from_os_str("--bed", parse_path)?;
+    annotations.extend(pargs.values_from_os_str("--gff", parse_path)?);
+
+    let bedgraphs = pargs.values_from_os_str("--bedgraph", parse_path)?;
+    let mut aligned = pargs.values_from_os_str("--gaf", parse_path)?;
+
+    let gff_attr = pargs.opt_value_from_str("--gff-attr")?;
+
+    let odgi_layout = pargs.contains("--odgi-layout");
+    let no_layout = pargs.contains("--no-layout");
+
+    let gpu_memory_budget_mb =
+        pargs.opt_value_from_str("--gpu-memory-budget")?;
+
+    let adapter = adapter::AdapterOptions::from_args(&mut pargs)?;
+    let list_adapters = pargs.contains("--list-adapters");
+
+    let software_render = pargs.contains("--software-render");
+    let render_data = pargs.opt_value_from_str("--data")?;
+    let render_width = pargs.opt_value_from_str("--width")?;
+    let output = pargs.opt_value_from_os_str("--output", parse_path)?;
+    let export_image =
+        pargs.opt_value_from_os_str("--export-image", parse_path)?;
+    let render_height = pargs.opt_value_from_str("--height")?;
+    let view1d = view1d || export_image.is_some();
+    let locus_grid = pargs.opt_value_from_os_str("--locus-grid", parse_path)?;
+    let grid_columns = pargs.opt_value_from_str("--grid-columns")?;
+
+    let find_regions = pargs.contains("--find-regions");
+    let reference = pargs.opt_value_from_str("--reference")?;
+
+    let nonref_report =
+        pargs.opt_value_from_os_str("--nonref-report", parse_path)?;
+    let min_length = pargs.opt_value_from_str("--min-length")?.unwrap_or(50);
+
+    let mut view_link = pargs.opt_value_from_str("--view")?;
+
+    let script = pargs.opt_value_from_os_str("--script", parse_path)?;
+    let config = pargs.opt_value_from_os_str("--config", parse_path)?;
+
+    let tour = pargs.contains("--tour");
+    let safe_mode = pargs.contains("--safe-mode");
+
+    let coords = if pargs.contains("--one-based") {
+        CoordSystem::OneBased
+    } else {
+        CoordSystem::ZeroBased
+    };
+
+    let session_path = pargs.opt_value_from_os_str("--session", parse_path)?;
+    let session = session_path
+        .as_ref()
+        .map(|path| {
+            session::Session::load(path)
+                .with_context(|| format!("Error loading session {path:?}"))
+                .map_err(ArgsError::Load)
+        })
+        .transpose()?;
+
+    // a copy written for this process by a graph switch
+    if let Some(path) = session_path.as_ref() {
+        if session::is_temp_session_path(path) {
+            if let Err(e) = std::fs::remove_file(path) {
+                log::warn!("Error removing temporary session {path:?}: {e}");
+            }
+        }
+    }
+
+    let (gfa, tsv) = if let Some(session) = session.as_ref() {
+        let graph = session.active_graph();
+        annotations.extend(graph.annotations.iter().cloned());
+        aligned.extend(graph.aligned.iter().cloned());
+        if view_link.is_none() {
+            view_link = graph.view_link.clone();
+        }
+        (graph.gfa.clone(), graph.layout.clone())
+    } else if tour {
+        match pargs.opt_free_from_os_str(parse_path)? {
+            Some(gfa) => (gfa, pargs.opt_free_from_os_str(parse_path)?),
+            None => {
+                let (gfa, tsv) = crate::gui::tour::write_example_graph()
+                    .context("Error writing the example graph")
+                    .map_err(ArgsError::Load)?;
+                (gfa, Some(tsv))
+            }
+        }
+    } else {
+        (
+            pargs.free_from_os_str(parse_path)?,
+            pargs.opt_free_from_os_str(parse_path)?,
+        )
+    };
+
+    let args = Args {
+        gfa,
+        tsv,
+        og: None,
+        og_conversion: None,
+
+        annotations,
+        gff_attr,
+        aligned,
+        bedgraphs,
+        odgi_layout,
+        no_layout,
+        gpu_memory_budget_mb,
+        adapter,
+        list_adapters,
+        software_render,
+        render_data,
+        render_width,
+        output,
+        export_image,
+        render_height,
+        locus_grid,
+        grid_columns,
+        find_regions,
+        reference,
+        nonref_report,
+        min_length,
+        view_link,
+        script,
+        config,
+        session,
+        session_path,
+        qc,
+        view1d,
+        region,
+        coords,
+        tour,
+        safe_mode,
+    };
+
+    Ok(args)
+}
+
+/// The reference path named with `--reference`, if it's in the
+/// graph, or else the guess from `reference::guess_reference`.
+pub(super) fn detect_reference(
+    graph: &waragraph_core::graph::PathIndex,
+    args: &Args,
+) -> Option<ReferenceGuess> {
+    use waragraph_core::graph::reference::{self, ReferenceReason};
+
+    let named = args
+        .reference
+        .as_deref()
+        .and_then(|names| names.split(',').next())
+        .and_then(|name| graph.path_names.get_by_right(name.trim()));
+
+    if let Some(path) = named {
+        return Some(ReferenceGuess {
+            path: *path,
+            reason: ReferenceReason::Named,
+        });
+    }
+
+    let rank0_names = if waragraph_core::graph::gbz::is_gbz_file(&args.gfa) {
+        Default::default()
+    } else {
+        reference::rgfa_rank0_names(&args.gfa).unwrap_or_else(|e| {
+            log::error!("Error reading rGFA tags: {e:?}");
+            Default::default()
+        })
+    };
+
+    let guess = reference::guess_reference(graph, &rank0_names)?;
+
+    if let Some(name) = graph.path_names.get_by_left(&guess.path) {
+        log::warn!("Using `{name}` as the reference path ({})", guess.reason);
+    }
+
+    Some(guess)
+}
+
+/// The pangenome range of the `--region`, if any, parsed as in the
+/// "Go to" box; nodes are shown with their full range.
+pub(super) fn region_pangenome_range(
+    graph: &waragraph_core::graph::PathIndex,
+    args: &Args,
+    reference: Option<PathId>,
+) -> Result<Option<std::ops::Range<Bp>>> {
+    use crate::viewer_1d::control::{self, GotoTarget};
+
+    let Some(region) = args.region.as_deref() else {
+        return Ok(None);
+    };
+
+    let target = control::parse_goto(graph, args.coords, reference, region)
+        .map_err(|e| anyhow::anyhow!("Invalid --region `{region}`: {e}"))?;
+
+    let range = match target {
+        GotoTarget::Node(node) => graph.node_pangenome_range(node),
+        GotoTarget::Range { path: None, range } => range,
+        GotoTarget::Range {
+            path: Some(path),
+            range,
+        } => control::path_range_to_pangenome(graph, path, range).ok_or_else(
+            || anyhow::anyhow!("--region `{region}` doesn't cover any nodes"),
+        )?,
+    };
+
+    Ok(Some(range))
+}
+
+fn parse_path(s: &std::ffi::OsStr) -> Result<std::path::PathBuf, &'static str> {
+    Ok(s.into())
+}
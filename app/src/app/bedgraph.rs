@@ -0,0 +1,296 @@
+//! bedGraph tracks: values over ranges of paths, such as coverage,
+//! turned into per-node values so they can be shown like the other
+//! quantitative data of the 1D viewer.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::RwLock;
+use waragraph_core::graph::{PathId, PathIndex};
+
+use super::provenance::{EventKind, ProvenanceLog};
+use super::resource::{GraphDataCache, GraphDataSourceFn, PathDataSourceFn};
+
+pub struct BedGraphTrack {
+    /// The file stem, used as the data key
+    pub name: String,
+    /// For each path with records, the value of each node on the path
+    /// in node order, as the path data sources produce
+    path_values: HashMap<PathId, Vec<f32>>,
+    /// The mean of the path values of each node, over the paths that
+    /// have records
+    node_values: Vec<f32>,
+    path_node_counts: Vec<usize>,
+}
+
+impl BedGraphTrack {
+    /// Reads a bedGraph file with path names as the chromosome
+    /// column. The value of a node on a path is the mean over the
+    /// node's bases, counting bases without a record as 0, so the
+    /// records of a path are expected not to overlap, as in the
+    /// output of `bedtools genomecov -bg`.
+    pub fn from_file(graph: &PathIndex, path: &Path) -> Result<Self> {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| anyhow!("Invalid bedGraph file name {path:?}"))?
+            .to_string();
+
+        let reader = std::fs::File::open(path).map(BufReader::new)?;
+        Self::from_reader(graph, name, reader)
+    }
+
+    /// Like `from_file`, for bedGraph records in `reader`, with `name`
+    /// as the track name.
+    pub fn from_reader(
+        graph: &PathIndex,
+        name: String,
+        reader: impl BufRead,
+    ) -> Result<Self> {
+        let mut records: HashMap<PathId, Vec<(u64, u64, f32)>> = HashMap::new();
+        let mut unknown_paths = 0;
+
+        for (line_ix, line) in reader.lines().enumerate() {
+            let line = line?;
+
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("track")
+                || line.starts_with("browser")
+            {
+                continue;
+            }
+
+            let Some((path_name, start, end, value)) = parse_line(&line) else {
+                log::error!(
+                    "Error parsing bedGraph record on line {}",
+                    line_ix + 1
+                );
+                continue;
+            };
+
+            let Some(path) = graph.path_names.get_by_right(path_name) else {
+                unknown_paths += 1;
+                continue;
+            };
+
+            records.entry(*path).or_default().push((start, end, value));
+        }
+
+        if unknown_paths > 0 {
+            log::warn!(
+                "Skipped {unknown_paths} bedGraph records on paths not in \
+                 the graph"
+            );
+        }
+
+        let mut path_values = HashMap::new();
+        let mut node_sums = vec![(0f32, 0u32); graph.node_count];
+
+        for (path, mut records) in records {
+            records.sort_by_key(|(start, _, _)| *start);
+            let values = path_node_values(graph, path, &records);
+
+            let nodes = graph.path_node_sets[path.ix()].iter();
+            for (node, value) in nodes.zip(values.iter()) {
+                let (sum, count) = &mut node_sums[node as usize];
+                *sum += value;
+                *count += 1;
+            }
+
+            path_values.insert(path, values);
+        }
+
+        let node_values = node_sums
+            .into_iter()
+            .map(
+                |(sum, count)| if count > 0 { sum / count as f32 } else { 0.0 },
+            )
+            .collect();
+
+        let path_node_counts = graph
+            .path_node_sets
+            .iter()
+            .map(|nodes| nodes.len() as usize)
+            .collect();
+
+        Ok(Self {
+            name,
+            path_values,
+            node_values,
+            path_node_counts,
+        })
+    }
+
+    /// Adds the track to the cache as a data source, under its name
+    /// or, if that's taken, the name with a number appended; returns
+    /// the data key.
+    pub fn add_to_cache(self, cache: &mut GraphDataCache) -> String {
+        let mut key = self.name.clone();
+        let mut n = 2;
+        while cache.graph_data_source_names().any(|name| name == key) {
+            key = format!("{}_{n}", self.name);
+            n += 1;
+        }
+
+        let track = Arc::new(self);
+
+        let graph_source: GraphDataSourceFn<f32> = {
+            let track = track.clone();
+            Arc::new(move || Ok(track.node_values.clone()))
+        };
+
+        let path_source: PathDataSourceFn<f32> = Arc::new(move |path| {
+            if let Some(values) = track.path_values.get(&path) {
+                return Ok(values.clone());
+            }
+
+            // paths without records are all 0; paths added to the graph
+            // after the track was loaded have no values
+            let Some(&count) = track.path_node_counts.get(path.ix()) else {
+                anyhow::bail!("No bedGraph values for path {}", path.ix());
+            };
+            Ok(vec![0.0; count])
+        });
+
+        cache.insert_track(&key, graph_source, path_source);
+        key
+    }
+}
+
+/// Parses `path start end value`, separated by tabs or spaces.
+/// Loads the bedGraph files given on the command line into the
+/// cache, logging the ones that fail.
+pub fn load_tracks(
+    graph: &PathIndex,
+    cache: &mut GraphDataCache,
+    paths: &[PathBuf],
+    provenance: &Arc<RwLock<ProvenanceLog>>,
+    tokio_handle: &tokio::runtime::Handle,
+) {
+    for bg_path in paths {
+        match BedGraphTrack::from_file(graph, bg_path) {
+            Ok(track) => {
+                let key = track.add_to_cache(cache);
+                log::warn!("loaded bedGraph track `{key}`");
+
+                ProvenanceLog::record_file_hashed(
+                    provenance,
+                    tokio_handle,
+                    EventKind::Load,
+                    "bedGraph",
+                    bg_path,
+                );
+            }
+            Err(e) => {
+                log::error!(
+                    "Error loading bedGraph file {:?}: {e:?}",
+                    bg_path.as_os_str()
+                );
+            }
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Option<(&str, u64, u64, f32)> {
+    let mut fields = line.split_whitespace();
+    let path_name = fields.next()?;
+    let start = fields.next()?.parse().ok()?;
+    let end = fields.next()?.parse().ok()?;
+    let value = fields.next()?.parse().ok()?;
+    (start < end).then_some((path_name, start, end, value))
+}
+
+/// The length-weighted mean value of each node on the path, in node
+/// order, from the records of the path sorted by start.
+fn path_node_values(
+    graph: &PathIndex,
+    path: PathId,
+    records: &[(u64, u64, f32)],
+) -> Vec<f32> {
+    // the sum of value * overlap, and the bases, of each node, over
+    // all of the path's visits to it
+    let mut sums: HashMap<u32, (f64, u64)> = HashMap::new();
+
+    let mut offset = 0u64;
+    let mut first_record = 0;
+
+    for step in graph.path_steps[path.ix()].iter() {
+        let node = step.node();
+        let step_start = offset;
+        let step_end = offset + graph.node_length(node).0;
+        offset = step_end;
+
+        while first_record < records.len()
+            && records[first_record].1 <= step_start
+        {
+            first_record += 1;
+        }
+
+        let (sum, len) = sums.entry(node.ix() as u32).or_default();
+        *len += step_end - step_start;
+
+        for &(start, end, value) in records[first_record..].iter() {
+            if start >= step_end {
+                break;
+            }
+            let overlap =
+                end.min(step_end).saturating_sub(start.max(step_start));
+            *sum += value as f64 * overlap as f64;
+        }
+    }
+
+    graph.path_node_sets[path.ix()]
+        .iter()
+        .map(|node| match sums.get(&node) {
+            Some((sum, len)) if *len > 0 => (sum / *len as f64) as f32,
+            _ => 0.0,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_values_are_length_weighted() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tAA
+S\t3\tAAAA
+P\tchr1\t1+,2+,3+\t*
+P\tchr2\t1+,3+\t*
+";
+        let bedgraph = "\
+track type=bedGraph
+chr1\t0\t2\t4
+chr1\t4\t6\t1.5
+chr1\t6\t10\t2
+chr3\t0\t10\t1
+";
+
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+        let track = BedGraphTrack::from_reader(
+            &graph,
+            "coverage".to_string(),
+            bedgraph.as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(track.name, "coverage");
+
+        let chr1 = *graph.path_names.get_by_right("chr1").unwrap();
+        let chr2 = *graph.path_names.get_by_right("chr2").unwrap();
+
+        // half of node 1 is at 4, and the rest uncovered
+        assert_eq!(track.path_values[&chr1], vec![2.0, 1.5, 2.0]);
+        assert!(!track.path_values.contains_key(&chr2));
+        assert_eq!(track.node_values, vec![2.0, 1.5, 2.0]);
+        assert_eq!(track.path_node_counts, vec![3, 2]);
+    }
+}
@@ -0,0 +1,388 @@
+//! Typed config keys shared by the viewers and the script API. Each
+//! viewer declares its keys, with defaults and ranges, in a
+//! `ConfigSchema`; values set from scripts are checked against it,
+//! and the viewers pick up the keys changed since they last looked.
+
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, RwLock};
+
+use crossbeam::atomic::AtomicCell;
+use rhai::{Dynamic, ImmutableString};
+
+pub mod file;
+
+/// The type, default value, and allowed range of a config key
+#[derive(Debug, Clone)]
+pub struct ConfigKey {
+    pub default: Dynamic,
+    /// Inclusive range of numeric values
+    pub range: Option<RangeInclusive<f64>>,
+}
+
+/// Declares the keys a `ConfigMap` may hold; values of the wrong type
+/// or out of range are rejected by `ConfigMap::set`.
+#[derive(Debug, Default, Clone)]
+pub struct ConfigSchema {
+    keys: BTreeMap<ImmutableString, ConfigKey>,
+}
+
+#[derive(Debug, Default)]
+pub struct ConfigSchemaBuilder {
+    schema: ConfigSchema,
+}
+
+impl ConfigSchemaBuilder {
+    fn key(
+        mut self,
+        key: &str,
+        default: Dynamic,
+        range: Option<RangeInclusive<f64>>,
+    ) -> Self {
+        self.schema
+            .keys
+            .insert(key.into(), ConfigKey { default, range });
+        self
+    }
+
+    pub fn int(
+        self,
+        key: &str,
+        default: i64,
+        range: RangeInclusive<i64>,
+    ) -> Self {
+        let range = (*range.start() as f64)..=(*range.end() as f64);
+        self.key(key, Dynamic::from_int(default), Some(range))
+    }
+
+    /// Integers are accepted too, and stored as floats
+    pub fn float(
+        self,
+        key: &str,
+        default: f32,
+        range: RangeInclusive<f32>,
+    ) -> Self {
+        let range = (*range.start() as f64)..=(*range.end() as f64);
+        self.key(key, Dynamic::from_float(default), Some(range))
+    }
+
+    pub fn bool(self, key: &str, default: bool) -> Self {
+        self.key(key, Dynamic::from_bool(default), None)
+    }
+
+    pub fn string(self, key: &str, default: &str) -> Self {
+        self.key(key, Dynamic::from(ImmutableString::from(default)), None)
+    }
+
+    pub fn build(self) -> ConfigSchema {
+        self.schema
+    }
+}
+
+impl ConfigSchema {
+    pub fn builder() -> ConfigSchemaBuilder {
+        ConfigSchemaBuilder::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ConfigKey> {
+        self.keys.get(key)
+    }
+
+    /// Checks `val` against the schema of `key`, returning the value
+    /// to store, with integers converted to floats for float keys.
+    pub fn validate(&self, key: &str, val: Dynamic) -> Result<Dynamic, String> {
+        let expected = self
+            .keys
+            .get(key)
+            .ok_or_else(|| format!("Unknown config key `{key}`"))?;
+
+        let default = &expected.default;
+
+        let val = if default.is::<rhai::FLOAT>() && val.is::<rhai::INT>() {
+            Dynamic::from_float(val.as_int().unwrap() as rhai::FLOAT)
+        } else {
+            val
+        };
+
+        if val.type_id() != default.type_id() {
+            return Err(format!(
+                "`{key}` must be {}, not {}",
+                default.type_name(),
+                val.type_name()
+            ));
+        }
+
+        if let Some(range) = expected.range.as_ref() {
+            let num = val
+                .as_int()
+                .map(|v| v as f64)
+                .or_else(|_| val.as_float().map(|v| v as f64))
+                .unwrap_or_default();
+
+            if !range.contains(&num) {
+                return Err(format!(
+                    "`{key}` must be between {} and {}, not {num}",
+                    range.start(),
+                    range.end(),
+                ));
+            }
+        }
+
+        Ok(val)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ConfigInner {
+    values: BTreeMap<ImmutableString, Dynamic>,
+    /// The generation each key was last set in
+    versions: BTreeMap<ImmutableString, u64>,
+    generation: u64,
+    schema: ConfigSchema,
+    /// Values for keys that aren't in the schema yet
+    pending: BTreeMap<ImmutableString, Dynamic>,
+}
+
+impl ConfigInner {
+    fn insert(
+        &mut self,
+        key: ImmutableString,
+        val: Dynamic,
+    ) -> Option<Dynamic> {
+        self.generation += 1;
+        self.versions.insert(key.clone(), self.generation);
+        self.values.insert(key, val)
+    }
+}
+
+/// Config values, shared by the viewers and scripts; cloning shares
+/// the map.
+#[derive(Debug, Default, Clone)]
+pub struct ConfigMap {
+    inner: Arc<RwLock<ConfigInner>>,
+}
+
+impl ConfigMap {
+    /// Adds the keys of `schema`, keeping any valid values already
+    /// set, and filling in the defaults otherwise.
+    pub fn extend_schema(&self, schema: ConfigSchema) {
+        let mut inner = self.inner.write().unwrap();
+
+        for (key, cfg_key) in schema.keys {
+            inner.schema.keys.insert(key.clone(), cfg_key.clone());
+
+            let val = inner
+                .pending
+                .remove(&key)
+                .or_else(|| inner.values.get(&key).cloned());
+
+            let val = match val {
+                Some(val) => match inner.schema.validate(&key, val) {
+                    Ok(val) => val,
+                    Err(e) => {
+                        log::error!("Invalid config value: {e}");
+                        cfg_key.default.clone()
+                    }
+                },
+                None => cfg_key.default.clone(),
+            };
+
+            inner.insert(key, val);
+        }
+    }
+
+    /// Sets `key` to `val`, if it passes the schema, returning the
+    /// previous value.
+    pub fn set(
+        &self,
+        key: &str,
+        val: Dynamic,
+    ) -> Result<Option<Dynamic>, String> {
+        let mut inner = self.inner.write().unwrap();
+        let val = inner.schema.validate(key, val)?;
+        Ok(inner.insert(key.into(), val))
+    }
+
+    /// Like `set`, except that a value for a key that isn't in the
+    /// schema yet is kept, and checked when the key is added, e.g.
+    /// when the 2D viewer is opened after a config file is read.
+    pub fn set_or_defer(&self, key: &str, val: Dynamic) -> Result<(), String> {
+        let mut inner = self.inner.write().unwrap();
+
+        if inner.schema.get(key).is_none() {
+            inner.pending.insert(key.into(), val);
+            return Ok(());
+        }
+
+        let val = inner.schema.validate(key, val)?;
+        inner.insert(key.into(), val);
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<Dynamic> {
+        self.inner.read().unwrap().values.get(key).cloned()
+    }
+
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        self.get(key)?.as_int().ok()
+    }
+
+    /// Integer values are converted
+    pub fn get_f32(&self, key: &str) -> Option<f32> {
+        let val = self.get(key)?;
+        val.as_float()
+            .ok()
+            .or_else(|| val.as_int().ok().map(|v| v as f32))
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key)?.as_bool().ok()
+    }
+
+    pub fn get_string(&self, key: &str) -> Option<ImmutableString> {
+        self.get(key)?.into_immutable_string().ok()
+    }
+
+    /// Increases every time a value is set
+    pub fn generation(&self) -> u64 {
+        self.inner.read().unwrap().generation
+    }
+
+    /// The keys set after `generation`
+    pub fn changed_since(&self, generation: u64) -> Vec<ImmutableString> {
+        let inner = self.inner.read().unwrap();
+        inner
+            .versions
+            .iter()
+            .filter(|(_, v)| **v > generation)
+            .map(|(k, _)| k.clone())
+            .collect()
+    }
+
+    /// Copies the keys changed since `generation` into their cells,
+    /// then stores the cells that were changed elsewhere, e.g. in the
+    /// settings window, so that scripts read the current values.
+    /// Returns the generation to pass next time.
+    pub fn sync_cells(
+        &self,
+        cells: &[(&str, ConfigCell)],
+        generation: u64,
+    ) -> u64 {
+        let changed = self.changed_since(generation);
+
+        for (key, cell) in cells {
+            if !changed.iter().any(|k| k == key) {
+                continue;
+            }
+            match cell {
+                ConfigCell::Bool(c) => {
+                    if let Some(v) = self.get_bool(key) {
+                        c.store(v);
+                    }
+                }
+                ConfigCell::Int(c) => {
+                    if let Some(v) = self.get_i64(key) {
+                        c.store(v as usize);
+                    }
+                }
+                ConfigCell::Float(c) => {
+                    if let Some(v) = self.get_f32(key) {
+                        c.store(v);
+                    }
+                }
+            }
+        }
+
+        for (key, cell) in cells {
+            let (current, val) = match cell {
+                ConfigCell::Bool(c) => {
+                    (self.get_bool(key) == Some(c.load()), c.load().into())
+                }
+                ConfigCell::Int(c) => (
+                    self.get_i64(key) == Some(c.load() as i64),
+                    Dynamic::from_int(c.load() as i64),
+                ),
+                ConfigCell::Float(c) => {
+                    (self.get_f32(key) == Some(c.load()), c.load().into())
+                }
+            };
+
+            if !current {
+                if let Err(e) = self.set(key, val) {
+                    log::error!("Error storing config value: {e}");
+                }
+            }
+        }
+
+        self.generation()
+    }
+}
+
+/// A settings cell of a viewer, backed by a config key
+#[derive(Debug, Clone)]
+pub enum ConfigCell {
+    Bool(Arc<AtomicCell<bool>>),
+    Int(Arc<AtomicCell<usize>>),
+    Float(Arc<AtomicCell<f32>>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_validation() {
+        let cfg = ConfigMap::default();
+
+        let schema = ConfigSchema::builder()
+            .int("layout.padding", 2, 0..=64)
+            .float("scale", 1.0, 0.5..=4.0)
+            .bool("labels", true)
+            .build();
+        cfg.extend_schema(schema);
+
+        assert_eq!(cfg.get_i64("layout.padding"), Some(2));
+        assert_eq!(cfg.get_bool("labels"), Some(true));
+
+        assert!(cfg.set("layout.padding", Dynamic::from_int(65)).is_err());
+        assert!(cfg.set("labels", Dynamic::from_int(1)).is_err());
+        assert!(cfg.set("unknown", Dynamic::from_int(1)).is_err());
+
+        cfg.set("scale", Dynamic::from_int(2)).unwrap();
+        assert_eq!(cfg.get_f32("scale"), Some(2.0));
+        assert!(cfg.get("scale").unwrap().is::<rhai::FLOAT>());
+
+        assert_eq!(cfg.get_i64("layout.padding"), Some(2));
+    }
+
+    #[test]
+    fn cells_follow_changed_keys() {
+        let cfg = ConfigMap::default();
+        cfg.extend_schema(
+            ConfigSchema::builder()
+                .bool("labels", true)
+                .float("step", 0.1, 0.0..=1.0)
+                .build(),
+        );
+
+        let labels = Arc::new(AtomicCell::new(true));
+        let step = Arc::new(AtomicCell::new(0.1));
+        let cells = [
+            ("labels", ConfigCell::Bool(labels.clone())),
+            ("step", ConfigCell::Float(step.clone())),
+        ];
+
+        let gen = cfg.sync_cells(&cells, 0);
+
+        cfg.set("labels", false.into()).unwrap();
+        // changed in the UI
+        step.store(0.5);
+
+        let gen = cfg.sync_cells(&cells, gen);
+        assert!(!labels.load());
+        assert_eq!(step.load(), 0.5);
+        assert_eq!(cfg.get_f32("step"), Some(0.5));
+
+        assert!(cfg.changed_since(gen).is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+//! Config files: TOML values for the `ConfigMap`, applied at startup
+//! and again whenever the file is saved, so that the viewer settings
+//! can be tweaked while the viewers are running.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use rhai::Dynamic;
+
+use super::ConfigMap;
+
+/// Reads the TOML file at `path` into `config`; see `apply_toml`.
+pub fn apply_toml_file(config: &ConfigMap, path: &Path) -> Result<usize> {
+    let text = std::fs::read_to_string(path)?;
+    apply_toml(config, &text)
+}
+
+/// Parses `text` as TOML and sets the values in `config`. Tables are
+/// flattened into `.`-separated keys, so `[viewer_2d]` with
+/// `show_minimap = false` sets `viewer_2d.show_minimap`. Values
+/// rejected by the schema are logged and skipped; returns the number
+/// of values set.
+pub fn apply_toml(config: &ConfigMap, text: &str) -> Result<usize> {
+    let table = match text.parse::<toml::Value>()? {
+        toml::Value::Table(table) => table,
+        _ => bail!("Config file must be a TOML table"),
+    };
+
+    let mut values = Vec::new();
+    flatten_table("", table, &mut values);
+
+    let mut count = 0;
+
+    for (key, val) in values {
+        match config.set_or_defer(&key, val) {
+            Ok(_) => count += 1,
+            Err(e) => log::error!("Config file: {e}"),
+        }
+    }
+
+    Ok(count)
+}
+
+fn flatten_table(
+    prefix: &str,
+    table: toml::value::Table,
+    out: &mut Vec<(String, Dynamic)>,
+) {
+    for (key, val) in table {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        match val {
+            toml::Value::Table(table) => flatten_table(&key, table, out),
+            val => out.push((key, toml_to_dynamic(val))),
+        }
+    }
+}
+
+fn toml_to_dynamic(val: toml::Value) -> Dynamic {
+    match val {
+        toml::Value::String(s) => Dynamic::from(rhai::ImmutableString::from(s)),
+        toml::Value::Integer(i) => Dynamic::from_int(i),
+        toml::Value::Float(f) => Dynamic::from_float(f as rhai::FLOAT),
+        toml::Value::Boolean(b) => Dynamic::from_bool(b),
+        toml::Value::Datetime(d) => {
+            Dynamic::from(rhai::ImmutableString::from(d.to_string()))
+        }
+        toml::Value::Array(vals) => {
+            Dynamic::from_array(vals.into_iter().map(toml_to_dynamic).collect())
+        }
+        toml::Value::Table(table) => Dynamic::from_map(
+            table
+                .into_iter()
+                .map(|(k, v)| (k.into(), toml_to_dynamic(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Applies a config file, and again whenever it's written to; the
+/// viewers pick up the changed keys on their next update.
+pub struct ConfigWatcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    pub fn new(config: &ConfigMap, path: &Path) -> Result<Self> {
+        let path = path.canonicalize()?;
+
+        let count = apply_toml_file(config, &path)?;
+        log::info!("Applied {count} values from config file {path:?}");
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("Invalid config file path {path:?}"))?
+            .to_owned();
+
+        let mut watcher = {
+            let config = config.clone();
+            let path = path.clone();
+
+            notify::recommended_watcher(
+                move |event: notify::Result<notify::Event>| {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(e) => {
+                            log::error!("Config file watcher error: {e:?}");
+                            return;
+                        }
+                    };
+
+                    // editors often replace the file rather than write
+                    // to it, so the directory is watched
+                    let is_write = matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_)
+                    );
+                    let is_config = event
+                        .paths
+                        .iter()
+                        .any(|p| p.file_name() == Some(file_name.as_os_str()));
+
+                    if !is_write || !is_config {
+                        return;
+                    }
+
+                    match apply_toml_file(&config, &path) {
+                        Ok(count) => log::info!(
+                            "Reloaded {count} values from config file {path:?}"
+                        ),
+                        Err(e) => log::error!(
+                            "Error reloading config file {path:?}: {e:?}"
+                        ),
+                    }
+                },
+            )?
+        };
+
+        let dir = path
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid config file path {path:?}"))?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::app::config::ConfigSchema;
+
+    #[test]
+    fn toml_tables_are_flattened() {
+        let config = ConfigMap::default();
+        config.extend_schema(
+            ConfigSchema::builder()
+                .int("viewer_1d.max_annotation_rows", 5, 1..=16)
+                .float("viewer_2d.key_pan_step", 0.1, 0.02..=0.5)
+                .bool("viewer_2d.show_minimap", true)
+                .build(),
+        );
+
+        let path = std::env::temp_dir()
+            .join(format!("waragraph_config_{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "[viewer_1d]\n\
+             max_annotation_rows = 100\n\
+             [viewer_2d]\n\
+             key_pan_step = 0.25\n\
+             show_minimap = false\n\
+             [viewer_3d]\n\
+             fov = 90\n",
+        )
+        .unwrap();
+
+        let count = apply_toml_file(&config, &path);
+        let watcher = ConfigWatcher::new(&config, &path);
+        let _ = std::fs::remove_file(&path);
+
+        // the annotation rows are out of range
+        assert_eq!(count.unwrap(), 3);
+        assert!(watcher.is_ok());
+        assert_eq!(config.get_i64("viewer_1d.max_annotation_rows"), Some(5));
+        assert_eq!(config.get_f32("viewer_2d.key_pan_step"), Some(0.25));
+        assert_eq!(config.get_bool("viewer_2d.show_minimap"), Some(false));
+
+        // kept until a viewer declares the key
+        assert!(config.get("viewer_3d.fov").is_none());
+        config.extend_schema(
+            ConfigSchema::builder()
+                .float("viewer_3d.fov", 60.0, 10.0..=120.0)
+                .build(),
+        );
+        assert_eq!(config.get_f32("viewer_3d.fov"), Some(90.0));
+
+        assert!(apply_toml(&config, "viewer_2d = 1\n[").is_err());
+    }
+}
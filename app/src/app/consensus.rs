@@ -0,0 +1,291 @@
+//! The "Consensus Path" panel: builds the walk through the selected
+//! region that's best supported by the paths (see
+//! `PathIndex::consensus_walk`), to export its sequence, or to add it
+//! to the graph as a path, e.g. when curating representative alleles.
+
+use std::io::prelude::*;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::{Bp, OrientedNode};
+
+use crate::gui::coords::PosFormat;
+
+use super::provenance::EventKind;
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{AppMsg, SharedState};
+
+struct Consensus {
+    name: String,
+    region: String,
+    walk: Vec<OrientedNode>,
+    len: u64,
+}
+
+pub struct ConsensusWidget {
+    shared: SharedState,
+
+    consensus: Option<Arc<Consensus>>,
+    consensus_recv: Option<oneshot::Receiver<Consensus>>,
+
+    fasta_recv: Option<oneshot::Receiver<PathBuf>>,
+    gaf_recv: Option<oneshot::Receiver<PathBuf>>,
+
+    status: Option<String>,
+}
+
+impl ConsensusWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            shared: shared.clone(),
+            consensus: None,
+            consensus_recv: None,
+            fasta_recv: None,
+            gaf_recv: None,
+            status: None,
+        }
+    }
+
+    fn spawn_build(
+        &mut self,
+        tokio_handle: &tokio::runtime::Handle,
+        range: Range<Bp>,
+        region: String,
+    ) {
+        let shared = self.shared.clone();
+        let (send, recv) = oneshot::channel();
+
+        tokio_handle.spawn_blocking(move || {
+            let graph = &shared.graph;
+            let walk = graph.consensus_walk(range.clone());
+            let len = walk.iter().map(|s| graph.node_length(s.node()).0).sum();
+
+            let _ = send.send(Consensus {
+                name: format!("consensus_{}-{}", range.start.0, range.end.0),
+                region,
+                walk,
+                len,
+            });
+        });
+
+        self.consensus_recv = Some(recv);
+        self.status = None;
+    }
+
+    fn poll_channels(&mut self, settings_ctx: &SettingsUiContext) {
+        if let Some(mut recv) = self.consensus_recv.take() {
+            match recv.try_recv() {
+                Ok(consensus) => self.consensus = Some(Arc::new(consensus)),
+                Err(TryRecvError::Empty) => self.consensus_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        let Some(consensus) = self.consensus.clone() else {
+            return;
+        };
+
+        if let Some(mut recv) = self.fasta_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    let result = write_fasta(&self.shared, &consensus, &path);
+                    self.status = Some(match result {
+                        Ok(()) => format!("Wrote {}", path.display()),
+                        Err(e) => {
+                            log::error!("Error writing consensus: {e:?}");
+                            format!("Error: {e}")
+                        }
+                    });
+                }
+                Err(TryRecvError::Empty) => self.fasta_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.gaf_recv.take() {
+            match recv.try_recv() {
+                Ok(gaf) => match write_gaf(&self.shared, &consensus, &gaf) {
+                    Ok(()) => {
+                        settings_ctx.send_app_msg_task(AppMsg::AddPaths(gaf));
+                    }
+                    Err(e) => {
+                        log::error!("Error writing consensus: {e:?}");
+                        self.status = Some(format!("Error: {e}"));
+                    }
+                },
+                Err(TryRecvError::Empty) => self.gaf_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+/// Writes the sequence of the consensus walk as FASTA, named after the
+/// region, and records the export.
+fn write_fasta(
+    shared: &SharedState,
+    consensus: &Consensus,
+    path: &Path,
+) -> Result<()> {
+    let seq = shared.graph.walk_sequence(&consensus.walk);
+
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(out, ">{} {}", consensus.name, consensus.region)?;
+    for line in seq.chunks(80) {
+        out.write_all(line)?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+
+    shared.provenance.blocking_write().record_path(
+        EventKind::Export,
+        "Consensus (FASTA)",
+        path,
+    );
+
+    Ok(())
+}
+
+/// Writes the consensus walk as a single GAF alignment, which is then
+/// added to the graph as a path.
+fn write_gaf(
+    shared: &SharedState,
+    consensus: &Consensus,
+    path: &Path,
+) -> Result<()> {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    shared
+        .graph
+        .write_gaf_walk(&consensus.name, &consensus.walk, &mut out)?;
+    out.flush()?;
+
+    shared.provenance.blocking_write().record_path(
+        EventKind::Export,
+        "Consensus (GAF)",
+        path,
+    );
+
+    Ok(())
+}
+
+impl SettingsWidget for ConsensusWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels(settings_ctx);
+
+        let range = self
+            .shared
+            .selection
+            .blocking_read()
+            .ranges()
+            .last()
+            .cloned();
+        let format = PosFormat::load(&self.shared);
+        let region = range
+            .as_ref()
+            .map(|range| format.range(&self.shared.graph, range));
+
+        let resp = ui.vertical(|ui| {
+            match region.as_ref() {
+                Some(region) => ui.label(format!("Selected region: {region}")),
+                None => {
+                    ui.label("Shift-drag in the 1D view to select a region")
+                }
+            };
+
+            ui.horizontal(|ui| {
+                let building = self.consensus_recv.is_some();
+
+                if ui
+                    .add_enabled(
+                        range.is_some() && !building,
+                        egui::Button::new("Build consensus"),
+                    )
+                    .on_hover_text(
+                        "Finds the walk through the region that covers the \
+                         most sequence shared by the most paths",
+                    )
+                    .clicked()
+                {
+                    if let Some((range, region)) = range.zip(region) {
+                        let rt = &settings_ctx.tokio_handle;
+                        self.spawn_build(rt, range, region);
+                    }
+                }
+
+                if building {
+                    ui.spinner();
+                }
+            });
+
+            let Some(consensus) = self.consensus.clone() else {
+                return;
+            };
+
+            ui.separator();
+
+            ui.label(format!(
+                "{}: {} nodes, {} bp",
+                consensus.name,
+                consensus.walk.len(),
+                consensus.len
+            ));
+
+            if consensus.walk.is_empty() {
+                return;
+            }
+
+            let busy = self.fasta_recv.is_some() || self.gaf_recv.is_some();
+
+            ui.horizontal(|ui| {
+                let export_path = |ext: &str| {
+                    self.shared.export_paths.blocking_read().export_path(
+                        "consensus",
+                        Some(&consensus.region),
+                        ext,
+                    )
+                };
+
+                if ui
+                    .add_enabled(!busy, egui::Button::new("Export FASTA"))
+                    .clicked()
+                {
+                    let id = egui::Id::new("ConsensusWidget_fasta");
+                    let path = export_path("fa");
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.fasta_recv = Some(recv);
+                }
+
+                if ui
+                    .add_enabled(!busy, egui::Button::new("Add as path"))
+                    .on_hover_text(
+                        "Saves the walk as a GAF alignment, and adds it to \
+                         the graph as a path, keeping the current views",
+                    )
+                    .clicked()
+                {
+                    let id = egui::Id::new("ConsensusWidget_gaf");
+                    let path = export_path("gaf");
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.gaf_recv = Some(recv);
+                }
+            });
+
+            if let Some(status) = self.status.as_ref() {
+                ui.label(status);
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
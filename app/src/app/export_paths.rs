@@ -0,0 +1,337 @@
+//! Default directories and file name templates for exports, so that
+//! reports and images can be saved without a file dialog.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::sync::{oneshot, RwLock};
+
+use super::provenance::format_timestamp;
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+
+pub struct ExportPaths {
+    /// Exports are written here without asking, if set
+    pub directory: Option<PathBuf>,
+    /// Images are written here if set, otherwise to `directory`
+    pub image_directory: Option<PathBuf>,
+    /// File name template, without the extension
+    pub template: String,
+
+    graph_name: String,
+}
+
+impl ExportPaths {
+    pub const DEFAULT_TEMPLATE: &'static str = "{graph}_{kind}_{region}_{time}";
+
+    pub fn new(gfa_path: &Path) -> Self {
+        let graph_name = gfa_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "graph".to_string());
+
+        Self {
+            directory: None,
+            image_directory: None,
+            template: Self::DEFAULT_TEMPLATE.to_string(),
+            graph_name,
+        }
+    }
+
+    /// The file name for an export, without the directory;
+    /// `region` is `"all"` if not given.
+    pub fn file_name(
+        &self,
+        kind: &str,
+        region: Option<&str>,
+        extension: &str,
+        timestamp: u64,
+    ) -> String {
+        // e.g. 2023-11-14_221320
+        let time = format_timestamp(timestamp)
+            .replace(' ', "_")
+            .replace(':', "");
+
+        let name = expand_template(
+            &self.template,
+            &[
+                ("graph", &self.graph_name),
+                ("kind", kind),
+                ("region", region.unwrap_or("all")),
+                ("time", &time),
+            ],
+        );
+
+        let name = if name.is_empty() { kind } else { name.as_str() };
+
+        format!("{name}.{extension}")
+    }
+
+    /// Path for exporting a report or other data, if an export
+    /// directory is set.
+    pub fn export_path(
+        &self,
+        kind: &str,
+        region: Option<&str>,
+        extension: &str,
+    ) -> Option<PathBuf> {
+        let dir = self.directory.as_ref()?;
+        Some(self.path_in(dir, kind, region, extension))
+    }
+
+    /// Path for exporting an image, if an image or export directory
+    /// is set.
+    pub fn image_path(
+        &self,
+        kind: &str,
+        region: Option<&str>,
+        extension: &str,
+    ) -> Option<PathBuf> {
+        let dir = self.image_directory.as_ref().or(self.directory.as_ref())?;
+        Some(self.path_in(dir, kind, region, extension))
+    }
+
+    /// Path for a screenshot: as `image_path`, but in the current
+    /// directory if no directory is set.
+    pub fn screenshot_path(&self, region: &str) -> PathBuf {
+        let dir = self
+            .image_directory
+            .as_ref()
+            .or(self.directory.as_ref())
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from("."));
+        self.path_in(&dir, "screenshot", Some(region), "png")
+    }
+
+    /// Creates `dir` if needed, and adds a numbered suffix to the
+    /// file name if it already exists, so that nothing is overwritten.
+    fn path_in(
+        &self,
+        dir: &Path,
+        kind: &str,
+        region: Option<&str>,
+        extension: &str,
+    ) -> PathBuf {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            log::error!("Error creating export directory {dir:?}: {e:?}");
+        }
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let path = dir.join(self.file_name(kind, region, extension, timestamp));
+
+        let mut candidate = path.clone();
+        let mut suffix = 2;
+
+        while candidate.exists() {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            candidate =
+                path.with_file_name(format!("{stem}-{suffix}.{extension}"));
+            suffix += 1;
+        }
+
+        candidate
+    }
+}
+
+/// Replaces each `{key}` in `template` with the corresponding value,
+/// with anything other than letters, digits, `-`, `_` and `.`
+/// replaced by `_`, so that the values can't add directories. Unknown
+/// placeholders are kept as they are.
+pub fn expand_template(template: &str, fields: &[(&str, &str)]) -> String {
+    let sanitize = |value: &str| {
+        value
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>()
+    };
+
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        let Some(close) = rest.find('}') else {
+            break;
+        };
+
+        let key = &rest[1..close];
+
+        if let Some((_, value)) = fields.iter().find(|(k, _)| *k == key) {
+            out.push_str(&sanitize(value));
+        } else {
+            out.push_str(&rest[..=close]);
+        }
+
+        rest = &rest[close + 1..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Settings window panel for the export directories and file name
+/// template.
+pub struct ExportPathsWidget {
+    paths: Arc<RwLock<ExportPaths>>,
+
+    // edited as text; empty means that a file dialog is used
+    directory_text: String,
+    image_directory_text: String,
+
+    dir_recv: Option<oneshot::Receiver<PathBuf>>,
+    image_dir_recv: Option<oneshot::Receiver<PathBuf>>,
+}
+
+impl ExportPathsWidget {
+    pub fn new(paths: Arc<RwLock<ExportPaths>>) -> Self {
+        let (directory_text, image_directory_text) = {
+            let paths = paths.blocking_read();
+            let text = |p: Option<&PathBuf>| {
+                p.map(|p| p.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            };
+            (
+                text(paths.directory.as_ref()),
+                text(paths.image_directory.as_ref()),
+            )
+        };
+
+        Self {
+            paths,
+            directory_text,
+            image_directory_text,
+            dir_recv: None,
+            image_dir_recv: None,
+        }
+    }
+}
+
+impl SettingsWidget for ExportPathsWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        use oneshot::error::TryRecvError;
+
+        for (recv, text) in [
+            (&mut self.dir_recv, &mut self.directory_text),
+            (&mut self.image_dir_recv, &mut self.image_directory_text),
+        ] {
+            if let Some(mut r) = recv.take() {
+                match r.try_recv() {
+                    Ok(path) => *text = path.to_string_lossy().into_owned(),
+                    Err(TryRecvError::Empty) => *recv = Some(r),
+                    Err(TryRecvError::Closed) => {}
+                }
+            }
+        }
+
+        let mut paths = self.paths.blocking_write();
+
+        let resp = ui.vertical(|ui| {
+            ui.label(
+                "Exports are saved to these directories without asking; \
+                 leave empty to choose each file",
+            );
+
+            egui::Grid::new("ExportPathsWidget-grid").show(ui, |ui| {
+                for (label, text, recv, id) in [
+                    (
+                        "Export directory",
+                        &mut self.directory_text,
+                        &mut self.dir_recv,
+                        "ExportPathsWidget_dir",
+                    ),
+                    (
+                        "Image directory",
+                        &mut self.image_directory_text,
+                        &mut self.image_dir_recv,
+                        "ExportPathsWidget_image_dir",
+                    ),
+                ] {
+                    ui.label(label);
+                    ui.text_edit_singleline(text);
+
+                    if ui.button("Browse").clicked() {
+                        let mut files =
+                            egui_file::FileDialog::select_folder(None);
+                        files.open();
+
+                        let id = egui::Id::new(id);
+                        *recv = Some(
+                            settings_ctx.with_file_dialog_oneshot(id, files),
+                        );
+                    }
+                    ui.end_row();
+                }
+
+                ui.label("File name");
+                ui.text_edit_singleline(&mut paths.template);
+                if ui.button("Reset").clicked() {
+                    paths.template = ExportPaths::DEFAULT_TEMPLATE.to_string();
+                }
+                ui.end_row();
+            });
+
+            ui.label(
+                "Placeholders: {graph}, {kind}, {region} (\"all\" for \
+                 whole-graph exports) and {time}",
+            );
+
+            let example = paths.file_name("view", Some("1000-2000"), "png", 0);
+            ui.label(format!("Example: {example}"));
+        });
+
+        let to_dir = |text: &str| {
+            let text = text.trim();
+            (!text.is_empty()).then(|| PathBuf::from(text))
+        };
+
+        paths.directory = to_dir(&self.directory_text);
+        paths.image_directory = to_dir(&self.image_directory_text);
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_expand_sanitized() {
+        let fields = [("graph", "yeast"), ("kind", "qc"), ("region", "a/b c")];
+
+        assert_eq!(
+            expand_template("{graph}_{kind}_{region}", &fields),
+            "yeast_qc_a_b_c"
+        );
+        assert_eq!(
+            expand_template("{graph}-{other}", &fields),
+            "yeast-{other}"
+        );
+        assert_eq!(expand_template("x_{graph", &fields), "x_{graph");
+
+        let paths = ExportPaths::new(Path::new("data/yeast.gfa"));
+        assert_eq!(
+            paths.file_name("view", Some("10-20"), "png", 1_700_000_000),
+            "yeast_view_10-20_2023-11-14_221320.png"
+        );
+    }
+}
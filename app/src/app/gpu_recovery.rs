@@ -0,0 +1,151 @@
+//! Recovery from GPU errors: wgpu panics on errors by default, so
+//! errors are instead flagged, and on the next frame the viewer
+//! windows are rebuilt, on a new device if the old one was lost.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use crossbeam::atomic::AtomicCell;
+use winit::event_loop::EventLoopWindowTarget;
+
+use super::{App, AppType};
+
+/// Flags `gpu_error` on any uncaptured error on the device.
+pub fn set_error_handler(
+    device: &wgpu::Device,
+    gpu_error: &Arc<AtomicCell<bool>>,
+) {
+    let gpu_error = gpu_error.clone();
+    device.on_uncaptured_error(Box::new(move |err| {
+        log::error!("GPU error: {err}");
+        gpu_error.store(true);
+    }));
+}
+
+/// Whether the device can no longer create resources, which is how a
+/// lost device shows up.
+pub fn device_is_lost(device: &wgpu::Device) -> bool {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+    let _probe = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Device loss probe"),
+        size: 4,
+        usage: wgpu::BufferUsages::UNIFORM,
+        mapped_at_creation: false,
+    });
+
+    pollster::block_on(device.pop_error_scope()).is_some()
+}
+
+impl App {
+    /// Handles a flagged GPU error. If the device was lost, a new one
+    /// is requested from the adapter, with the same features and
+    /// limits, and the windows are moved to it; then the viewer
+    /// windows are rebuilt, uploading their buffers again.
+    pub(super) fn recover_from_gpu_error(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<()>,
+        state: &mut raving_wgpu::State,
+    ) -> Result<()> {
+        if device_is_lost(&state.device) {
+            log::warn!("The GPU device was lost, requesting a new one");
+            self.recreate_device(event_loop, state)?;
+        }
+
+        self.rebuild_viewer_windows(event_loop, state)
+    }
+
+    fn recreate_device(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<()>,
+        state: &mut raving_wgpu::State,
+    ) -> Result<()> {
+        let desc = wgpu::DeviceDescriptor {
+            label: None,
+            features: state.device.features(),
+            limits: state.device.limits(),
+        };
+
+        let (device, queue) =
+            pollster::block_on(state.adapter.request_device(&desc, None))
+                .context("Error recreating the GPU device")?;
+
+        set_error_handler(&device, &self.gpu_error);
+
+        state.device = device;
+        state.queue = queue;
+
+        // the windows that only draw egui keep their apps, but need
+        // new surfaces and egui renderers; the viewers are recreated
+        // by `rebuild_viewer_windows`
+        let custom = self
+            .app_windows
+            .apps
+            .keys()
+            .filter(|app_ty| matches!(app_ty, AppType::Custom(_)))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for app_ty in custom {
+            let app = self.app_windows.apps.remove(&app_ty).unwrap();
+
+            let old_id = app.window.window.id();
+            self.app_windows.windows.remove(&old_id);
+            if self.settings_window_tgt == Some(old_id) {
+                self.settings_window_tgt = None;
+            }
+
+            let app = app.recreate(event_loop, state)?;
+            let winid = app.window.window.id();
+            self.app_windows.apps.insert(app_ty.clone(), app);
+            self.app_windows.windows.insert(winid, app_ty);
+        }
+
+        // closed ones are opened from scratch the next time
+        self.app_windows
+            .sleeping
+            .retain(|app_ty, _| !matches!(app_ty, AppType::Custom(_)));
+
+        Ok(())
+    }
+
+    /// Rebuilds the viewer windows, and the GPU resources they and
+    /// the color store use, keeping the views' navigation state. This
+    /// handles lost surfaces, errors that leave resources in a bad
+    /// state, and, after [`App::recover_from_gpu_error`] has replaced
+    /// the device, moves the viewers to the new one.
+    ///
+    /// If the errors keep coming back, this returns an error, and the
+    /// app exits.
+    pub fn rebuild_viewer_windows(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<()>,
+        state: &raving_wgpu::State,
+    ) -> Result<()> {
+        // give up if the errors keep coming back
+        const MAX_REBUILDS: usize = 3;
+        const REBUILD_WINDOW: std::time::Duration =
+            std::time::Duration::from_secs(60);
+
+        let now = std::time::Instant::now();
+        self.gpu_rebuilds
+            .retain(|t| now.duration_since(*t) < REBUILD_WINDOW);
+
+        if self.gpu_rebuilds.len() >= MAX_REBUILDS {
+            anyhow::bail!(
+                "GPU errors persist after rebuilding the windows \
+                 {MAX_REBUILDS} times"
+            );
+        }
+        self.gpu_rebuilds.push(now);
+
+        log::warn!("Rebuilding the viewer windows after a GPU error");
+
+        self.shared
+            .colors
+            .blocking_write()
+            .recreate_gpu_resources(state)?;
+
+        self.reinit_viewer_windows(event_loop, state)
+    }
+}
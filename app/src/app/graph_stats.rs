@@ -0,0 +1,227 @@
+use std::sync::Arc;
+
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::stats::{GraphStats, Histogram, NodeMeasure};
+
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::SharedState;
+
+/// Bins with more nodes than this can't be selected, as the viewers
+/// draw a highlight for each selected node
+const MAX_SELECTED_NODES: usize = 100_000;
+
+const HISTOGRAM_HEIGHT: f32 = 80.0;
+
+/// Settings window panel with counts and distributions for the loaded
+/// graph. Clicking a histogram bar selects the nodes in the bin, which
+/// highlights them in the viewers.
+pub struct GraphStatsWidget {
+    shared: SharedState,
+
+    stats_recv: Option<oneshot::Receiver<GraphStats>>,
+    stats: Option<Arc<GraphStats>>,
+
+    // the last clicked bar
+    selected_bin: Option<(NodeMeasure, usize)>,
+}
+
+impl GraphStatsWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            shared: shared.clone(),
+            stats_recv: None,
+            stats: None,
+            selected_bin: None,
+        }
+    }
+
+    fn poll_stats(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        if self.stats.is_none() && self.stats_recv.is_none() {
+            let graph = self.shared.graph.clone();
+            let (send, recv) = oneshot::channel();
+
+            tokio_handle.spawn_blocking(move || {
+                let _ = send.send(GraphStats::compute(&graph));
+            });
+
+            self.stats_recv = Some(recv);
+        }
+
+        if let Some(mut recv) = self.stats_recv.take() {
+            match recv.try_recv() {
+                Ok(stats) => self.stats = Some(Arc::new(stats)),
+                Err(TryRecvError::Empty) => self.stats_recv = Some(recv),
+                Err(TryRecvError::Closed) => {
+                    log::error!("Graph statistics task failed");
+                }
+            }
+        }
+    }
+
+    fn select_bin(
+        &mut self,
+        stats: &GraphStats,
+        measure: NodeMeasure,
+        bin_ix: usize,
+    ) {
+        let nodes = stats.nodes_in_bin(&self.shared.graph, measure, bin_ix);
+        self.shared.selection.blocking_write().select_all(nodes);
+        self.selected_bin = Some((measure, bin_ix));
+    }
+}
+
+impl SettingsWidget for GraphStatsWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_stats(&settings_ctx.tokio_handle);
+
+        let resp = ui.vertical(|ui| {
+            let Some(stats) = self.stats.clone() else {
+                ui.spinner();
+                return;
+            };
+
+            egui::Grid::new("GraphStatsWidget-summary")
+                .striped(true)
+                .show(ui, |ui| {
+                    for (label, value) in [
+                        ("Nodes", stats.node_count.to_string()),
+                        ("Edges", stats.edge_count.to_string()),
+                        ("Paths", stats.path_count.to_string()),
+                        (
+                            "Total length",
+                            format!("{} bp", stats.total_length.0),
+                        ),
+                    ] {
+                        ui.label(label);
+                        ui.label(value);
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+            ui.label("Click a bar to select the nodes in it");
+
+            for (measure, title, unit) in [
+                (NodeMeasure::Length, "Node length", " bp"),
+                (NodeMeasure::Degree, "Node degree", ""),
+            ] {
+                ui.label(title);
+
+                let selected = self
+                    .selected_bin
+                    .filter(|(m, _)| *m == measure)
+                    .map(|(_, ix)| ix);
+
+                let clicked = histogram_bars(
+                    ui,
+                    stats.histogram(measure),
+                    selected,
+                    unit,
+                );
+
+                if let Some(bin_ix) = clicked {
+                    self.select_bin(&stats, measure, bin_ix);
+                }
+            }
+
+            if ui
+                .add_enabled(
+                    self.selected_bin.is_some(),
+                    egui::Button::new("Clear selection"),
+                )
+                .clicked()
+            {
+                self.shared.selection.blocking_write().clear();
+                self.selected_bin = None;
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+/// Draws the histogram as a row of bars, returning the index of the
+/// bar that was clicked, if any.
+fn histogram_bars(
+    ui: &mut egui::Ui,
+    hist: &Histogram,
+    selected: Option<usize>,
+    unit: &str,
+) -> Option<usize> {
+    let width = ui.available_width().max(100.0);
+    let (rect, resp) = ui.allocate_exact_size(
+        egui::vec2(width, HISTOGRAM_HEIGHT),
+        egui::Sense::click(),
+    );
+
+    let bin_count = hist.bins.len();
+    let max_count = hist.max_count();
+    if bin_count == 0 || max_count == 0 {
+        return None;
+    }
+
+    let visuals = ui.visuals();
+    let bar_width = rect.width() / bin_count as f32;
+
+    let hovered_ix = resp.hover_pos().map(|pos| {
+        (((pos.x - rect.left()) / bar_width) as usize).min(bin_count - 1)
+    });
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+
+    for (ix, bin) in hist.bins.iter().enumerate() {
+        // log scale, so that small bins are still visible next to the
+        // largest
+        let height = ((bin.count as f32).ln_1p() / (max_count as f32).ln_1p())
+            * rect.height();
+
+        let left = rect.left() + ix as f32 * bar_width;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(left + 1.0, rect.bottom() - height),
+            egui::pos2(left + bar_width - 1.0, rect.bottom()),
+        );
+
+        let color = if selected == Some(ix) {
+            visuals.selection.bg_fill
+        } else if hovered_ix == Some(ix) {
+            visuals.widgets.hovered.fg_stroke.color
+        } else {
+            visuals.widgets.inactive.fg_stroke.color
+        };
+
+        painter.rect_filled(bar, 0.0, color);
+    }
+
+    let bin = hovered_ix.map(|ix| (ix, hist.bins[ix]));
+
+    if let Some((_, bin)) = bin {
+        let range = if bin.min == bin.max {
+            format!("{}{unit}", bin.min)
+        } else {
+            format!("{}-{}{unit}", bin.min, bin.max)
+        };
+
+        let mut text = format!("{range}: {} nodes", bin.count);
+        if bin.count > MAX_SELECTED_NODES {
+            text.push_str("\nToo many nodes to select");
+        }
+
+        resp.clone().on_hover_text_at_pointer(text);
+    }
+
+    if resp.clicked() {
+        bin.filter(|(_, bin)| bin.count > 0 && bin.count <= MAX_SELECTED_NODES)
+            .map(|(ix, _)| ix)
+    } else {
+        None
+    }
+}
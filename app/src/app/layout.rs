@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::Result;
+use tokio::sync::RwLock;
+use waragraph_core::graph::PathIndex;
+
+use crate::viewer_2d::layout::{NodePositions, PathSgdLayout};
+
+use super::notify::{JobAction, JobDone};
+use super::{workspace::Workspace, App, AppMsg, AppType, Args, SharedState};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuiltinLayoutStatus {
+    Running { progress: f32 },
+    Done { lay_path: PathBuf },
+    Failed(String),
+}
+
+impl App {
+    /// Starts computing a layout with the built-in layout engine, if
+    /// a layout isn't already loaded or being computed. The 2D viewer
+    /// is opened when the layout is done.
+    pub fn run_builtin_layout(&mut self) {
+        let mut workspace = self.shared.workspace.blocking_write();
+
+        if workspace.tsv_path.is_some() {
+            return;
+        }
+
+        let running = workspace
+            .builtin_layout_job
+            .as_ref()
+            .map(|job| !matches!(job.status(), BuiltinLayoutStatus::Failed(_)));
+
+        if running == Some(true) {
+            return;
+        }
+
+        let job = BuiltinLayoutJob::spawn(
+            self.tokio_rt.handle(),
+            self.shared.graph.clone(),
+            workspace.graph_path.clone(),
+            self.shared.workspace.clone(),
+            self.shared.app_msg_send.clone(),
+        );
+
+        workspace.builtin_layout_job = Some(job);
+    }
+}
+
+/// Starts a layout on startup if the graph was loaded without one,
+/// with odgi if `--odgi-layout` was given, and otherwise with the
+/// built-in engine, unless only the 1D viewer is used.
+pub fn request_initial_layout(shared: &SharedState, args: &Args) {
+    if shared.workspace.blocking_read().tsv_path().is_some() || args.safe_mode {
+        return;
+    }
+
+    let msg = if args.odgi_layout {
+        AppMsg::RunOdgiLayout
+    } else if !args.no_layout && !args.view1d {
+        AppMsg::RunBuiltinLayout
+    } else {
+        return;
+    };
+
+    if let Err(e) = shared.app_msg_send.try_send(msg) {
+        log::error!("{e:?}");
+    }
+}
+
+/// Handle to a layout computed by the built-in path-guided SGD
+/// layout engine in the background, used when there's no layout
+/// file; the status can be polled from the GUI.
+#[derive(Clone)]
+pub struct BuiltinLayoutJob {
+    status: Arc<Mutex<BuiltinLayoutStatus>>,
+}
+
+impl BuiltinLayoutJob {
+    pub fn status(&self) -> BuiltinLayoutStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Computes the layout on a blocking thread and writes it as a
+    /// layout table next to the graph file (or in the temporary
+    /// directory, if that fails). When done, it's stored in the workspace and
+    /// the 2D viewer is initialized.
+    ///
+    /// If a layout from a previous run exists, and is newer than the
+    /// graph file, it's used directly.
+    pub fn spawn(
+        rt: &tokio::runtime::Handle,
+        graph: Arc<PathIndex>,
+        graph_path: PathBuf,
+        workspace: Arc<RwLock<Workspace>>,
+        app_msg_send: tokio::sync::mpsc::Sender<AppMsg>,
+    ) -> Self {
+        let status = Arc::new(Mutex::new(BuiltinLayoutStatus::Running {
+            progress: 0.0,
+        }));
+
+        let job = Self {
+            status: status.clone(),
+        };
+
+        let started = Instant::now();
+
+        rt.spawn(async move {
+            let result = {
+                let status = status.clone();
+                tokio::task::spawn_blocking(move || {
+                    run_builtin_layout(&graph, &graph_path, &status)
+                })
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|r| r)
+            };
+
+            match result {
+                Ok(lay_path) => {
+                    log::warn!("Built-in layout finished: {lay_path:?}");
+
+                    {
+                        let mut ws = workspace.write().await;
+                        if ws.tsv_path.is_none() {
+                            ws.tsv_path = Some(lay_path.clone());
+                        }
+                    }
+
+                    *status.lock().unwrap() =
+                        BuiltinLayoutStatus::Done { lay_path };
+
+                    let done = JobDone::finished("Layout", started)
+                        .with_action(JobAction::Msg {
+                            label: "Show 2D view".to_string(),
+                            msg: Box::new(AppMsg::FocusWindow(
+                                AppType::Viewer2D,
+                            )),
+                        });
+
+                    for msg in [AppMsg::InitViewer2D, AppMsg::JobDone(done)] {
+                        if let Err(e) = app_msg_send.send(msg).await {
+                            log::error!("{e:?}");
+                        }
+                    }
+                }
+                Err(e) => {
+                    log::error!("Error computing layout: {e:?}");
+                    *status.lock().unwrap() =
+                        BuiltinLayoutStatus::Failed(e.to_string());
+
+                    let done = JobDone::failed("Layout", started, &e);
+                    if let Err(e) =
+                        app_msg_send.send(AppMsg::JobDone(done)).await
+                    {
+                        log::error!("{e:?}");
+                    }
+                }
+            }
+        });
+
+        job
+    }
+}
+
+fn layout_output_path(graph_path: &Path) -> PathBuf {
+    graph_path.with_extension("layout.tsv")
+}
+
+fn run_builtin_layout(
+    graph: &PathIndex,
+    graph_path: &Path,
+    status: &Mutex<BuiltinLayoutStatus>,
+) -> Result<PathBuf> {
+    let lay_path = layout_output_path(graph_path);
+
+    let modified = |p: &Path| std::fs::metadata(p)?.modified();
+    if let (Ok(lay), Ok(graph)) = (modified(&lay_path), modified(graph_path)) {
+        if lay >= graph {
+            log::warn!("Using existing layout {lay_path:?}");
+            return Ok(lay_path);
+        }
+    }
+
+    let positions =
+        NodePositions::from_path_sgd(graph, &PathSgdLayout::default(), |p| {
+            *status.lock().unwrap() =
+                BuiltinLayoutStatus::Running { progress: p };
+        });
+
+    let write = |path: &Path| -> Result<()> {
+        let file = std::fs::File::create(path)?;
+        positions.write_layout_tsv(std::io::BufWriter::new(file))
+    };
+
+    match write(&lay_path) {
+        Ok(()) => Ok(lay_path),
+        Err(e) => {
+            log::warn!(
+                "Couldn't write layout to {lay_path:?} ({e}), \
+                 using the temporary directory"
+            );
+
+            let file_name = lay_path.file_name().unwrap_or_default();
+            let tmp_path = std::env::temp_dir().join(file_name);
+            write(&tmp_path)?;
+            Ok(tmp_path)
+        }
+    }
+}
@@ -0,0 +1,466 @@
+//! Carrying sessions, selections and node lists over to a rebuilt
+//! graph, using a node ID liftover file (see
+//! `waragraph_core::graph::liftover`).
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::liftover::NodeLiftover;
+use waragraph_core::graph::{Bp, PathIndex};
+
+use super::notify::JobDone;
+use super::session::Session;
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{AppMsg, SharedState, ViewLink, ViewState};
+
+/// Counts of what was carried over by `lift_session`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LiftSummary {
+    pub bookmarks: usize,
+    pub dropped_bookmarks: usize,
+    pub selected_nodes: usize,
+}
+
+/// Remaps the active graph of the session, which refers to `old`, to
+/// the graph `new` loaded from `new_gfa`. Bookmarks on paths are kept
+/// as they are, since path coordinates don't depend on the nodes;
+/// pangenome bookmarks, the 1D view and the selection are lifted.
+/// The 2D view is dropped, since the layout is for the old nodes.
+pub fn lift_session(
+    session: &mut Session,
+    liftover: &NodeLiftover,
+    old: &PathIndex,
+    new: &PathIndex,
+    new_gfa: &Path,
+    new_layout: Option<&Path>,
+) -> LiftSummary {
+    let mut summary = LiftSummary::default();
+
+    let entry = session.active_graph_mut();
+    entry.gfa = new_gfa.to_path_buf();
+    entry.layout = new_layout.map(Path::to_path_buf);
+
+    entry.bookmarks.retain_mut(|bookmark| {
+        if bookmark.path.is_some() {
+            summary.bookmarks += 1;
+            return true;
+        }
+
+        let range = bookmark.range.clone();
+        match liftover.lift_pangenome_range(old, new, range) {
+            Some(range) => {
+                bookmark.range = range;
+                summary.bookmarks += 1;
+                true
+            }
+            None => {
+                summary.dropped_bookmarks += 1;
+                false
+            }
+        }
+    });
+
+    let mut selection = entry
+        .selection
+        .iter()
+        .flat_map(|&id| liftover.lift_node(id, new))
+        .map(|node| new.segment_id(node))
+        .collect::<Vec<_>>();
+    selection.sort_unstable();
+    selection.dedup();
+    summary.selected_nodes = selection.len();
+    entry.selection = selection;
+
+    let link = entry
+        .view_link
+        .as_deref()
+        .and_then(|link| ViewLink::parse(link).ok());
+
+    entry.view_link = link.map(|link| {
+        let views = link
+            .views
+            .into_iter()
+            .filter_map(|view| match view {
+                ViewState::View1D {
+                    range,
+                    data_key,
+                    path_offset,
+                    annotation_slots,
+                } => {
+                    let range = Bp(range.start)..Bp(range.end);
+                    let range =
+                        liftover.lift_pangenome_range(old, new, range)?;
+                    Some(ViewState::View1D {
+                        range: range.start.0..range.end.0,
+                        data_key,
+                        path_offset,
+                        annotation_slots,
+                    })
+                }
+                ViewState::View2D { .. } => None,
+            })
+            .collect();
+
+        ViewLink {
+            graph_hash: None,
+            views,
+            path_rows: link.path_rows,
+            settings: link.settings,
+        }
+        .to_uri()
+    });
+
+    summary
+}
+
+/// Parses a list of GFA segment IDs, separated by whitespace or
+/// commas; lines starting with `#` are skipped.
+pub fn parse_node_list(text: &str) -> Result<Vec<u64>> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            id.parse::<u64>()
+                .map_err(|_| anyhow!("Invalid segment ID `{id}`"))
+        })
+        .collect()
+}
+
+/// Loads the session and its (old) graph, lifts it to the current
+/// graph, and writes it next to the original as `<name>.lifted.json`.
+fn lift_session_file(
+    shared: &SharedState,
+    liftover: &NodeLiftover,
+    session_path: &Path,
+) -> Result<(PathBuf, LiftSummary)> {
+    let mut session = Session::load(session_path)?;
+
+    let old_gfa = session.active_graph().gfa.clone();
+    let old = PathIndex::from_file(&old_gfa)
+        .map_err(|e| anyhow!("Error loading old graph {old_gfa:?}: {e}"))?;
+
+    let (new_gfa, new_layout) = {
+        let workspace = shared.workspace.blocking_read();
+        (
+            workspace.graph_path().clone(),
+            workspace.tsv_path().cloned(),
+        )
+    };
+
+    let summary = lift_session(
+        &mut session,
+        liftover,
+        &old,
+        &shared.graph,
+        &new_gfa,
+        new_layout.as_deref(),
+    );
+
+    let out_path = session_path.with_extension("lifted.json");
+    session.save(&out_path, &shared.provenance.blocking_read())?;
+
+    Ok((out_path, summary))
+}
+
+/// Settings window panel for loading a liftover file, and using it to
+/// open sessions and import node lists made for an earlier build of
+/// the graph.
+pub struct LiftoverWidget {
+    shared: SharedState,
+
+    liftover: Option<(PathBuf, Arc<NodeLiftover>)>,
+    // remap imported node lists with the liftover
+    lift_node_lists: bool,
+
+    liftover_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    liftover_recv: Option<oneshot::Receiver<Result<(PathBuf, NodeLiftover)>>>,
+    node_list_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    session_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    session_recv: Option<oneshot::Receiver<Result<(PathBuf, LiftSummary)>>>,
+
+    status: Option<String>,
+    error: Option<String>,
+}
+
+impl LiftoverWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            shared: shared.clone(),
+            liftover: None,
+            lift_node_lists: true,
+            liftover_path_recv: None,
+            liftover_recv: None,
+            node_list_path_recv: None,
+            session_path_recv: None,
+            session_recv: None,
+            status: None,
+            error: None,
+        }
+    }
+
+    fn import_node_list(&self, path: &Path) -> Result<usize> {
+        let ids = parse_node_list(&std::fs::read_to_string(path)?)?;
+        let graph = &self.shared.graph;
+
+        let nodes = match self.liftover.as_ref() {
+            Some((_, liftover)) if self.lift_node_lists => ids
+                .iter()
+                .flat_map(|&id| liftover.lift_node(id, graph))
+                .collect::<Vec<_>>(),
+            _ => ids
+                .iter()
+                .filter_map(|&id| graph.segment_node(id))
+                .collect(),
+        };
+
+        let count = nodes.len();
+        self.shared.selection.blocking_write().select_all(nodes);
+        Ok(count)
+    }
+
+    fn poll_channels(&mut self, settings_ctx: &SettingsUiContext) {
+        if let Some(mut recv) = self.liftover_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    let (send, recv) = oneshot::channel();
+                    settings_ctx.tokio_handle.spawn_blocking(move || {
+                        let result = NodeLiftover::from_file(&path)
+                            .map(|liftover| (path, liftover))
+                            .map_err(anyhow::Error::from);
+                        let _ = send.send(result);
+                    });
+                    self.liftover_recv = Some(recv);
+                }
+                Err(TryRecvError::Empty) => {
+                    self.liftover_path_recv = Some(recv)
+                }
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.liftover_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok((path, liftover))) => {
+                    self.liftover = Some((path, Arc::new(liftover)));
+                    self.error = None;
+                }
+                Ok(Err(e)) => {
+                    log::error!("Error loading liftover: {e:?}");
+                    self.error = Some(e.to_string());
+                }
+                Err(TryRecvError::Empty) => self.liftover_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.node_list_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => match self.import_node_list(&path) {
+                    Ok(count) => {
+                        self.status = Some(format!("Selected {count} nodes"));
+                        self.error = None;
+                    }
+                    Err(e) => {
+                        log::error!("Error importing node list: {e:?}");
+                        self.error = Some(e.to_string());
+                    }
+                },
+                Err(TryRecvError::Empty) => {
+                    self.node_list_path_recv = Some(recv)
+                }
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.session_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    if let Some((_, liftover)) = self.liftover.as_ref() {
+                        let shared = self.shared.clone();
+                        let liftover = liftover.clone();
+                        let (send, recv) = oneshot::channel();
+                        let started = Instant::now();
+                        settings_ctx.tokio_handle.spawn_blocking(move || {
+                            let result =
+                                lift_session_file(&shared, &liftover, &path);
+
+                            let done = match &result {
+                                Ok((path, _)) => JobDone::finished(
+                                    "Session liftover",
+                                    started,
+                                )
+                                .with_detail(path.display().to_string()),
+                                Err(e) => JobDone::failed(
+                                    "Session liftover",
+                                    started,
+                                    e,
+                                ),
+                            };
+                            let msg = AppMsg::JobDone(done);
+                            if let Err(e) =
+                                shared.app_msg_send.blocking_send(msg)
+                            {
+                                log::error!("{e:?}");
+                            }
+
+                            let _ = send.send(result);
+                        });
+                        self.session_recv = Some(recv);
+                    }
+                }
+                Err(TryRecvError::Empty) => self.session_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.session_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok((path, summary))) => {
+                    log::warn!(
+                        "Wrote remapped session to {path:?}: {} bookmarks \
+                         ({} dropped), {} selected nodes",
+                        summary.bookmarks,
+                        summary.dropped_bookmarks,
+                        summary.selected_nodes
+                    );
+                    settings_ctx.send_app_msg_task(AppMsg::OpenSession(path));
+                }
+                Ok(Err(e)) => {
+                    log::error!("Error remapping session: {e:?}");
+                    self.error = Some(e.to_string());
+                }
+                Err(TryRecvError::Empty) => self.session_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+impl SettingsWidget for LiftoverWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels(settings_ctx);
+
+        let resp = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Load liftover")
+                    .on_hover_text(
+                        "TSV of old segment ID, new segment IDs \
+                         (comma-separated, for split nodes), and the \
+                         offset in the new segment (for merged nodes)",
+                    )
+                    .clicked()
+                {
+                    let mut files = egui_file::FileDialog::open_file(None);
+                    files.open();
+
+                    let id = egui::Id::new("LiftoverWidget_liftover");
+                    let recv = settings_ctx.with_file_dialog_oneshot(id, files);
+                    self.liftover_path_recv = Some(recv);
+                }
+
+                if self.liftover_recv.is_some() || self.session_recv.is_some() {
+                    ui.spinner();
+                }
+            });
+
+            match self.liftover.as_ref() {
+                Some((path, liftover)) => {
+                    let name = path
+                        .file_name()
+                        .map(|n| n.to_string_lossy())
+                        .unwrap_or_default();
+                    ui.label(format!(
+                        "{name}: {} remapped segments",
+                        liftover.len()
+                    ));
+                }
+                None => {
+                    ui.label("No liftover loaded");
+                }
+            }
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Import node list")
+                    .on_hover_text(
+                        "Selects the nodes with the segment IDs in the file",
+                    )
+                    .clicked()
+                {
+                    let mut files = egui_file::FileDialog::open_file(None);
+                    files.open();
+
+                    let id = egui::Id::new("LiftoverWidget_node_list");
+                    let recv = settings_ctx.with_file_dialog_oneshot(id, files);
+                    self.node_list_path_recv = Some(recv);
+                }
+
+                ui.add_enabled(
+                    self.liftover.is_some(),
+                    egui::Checkbox::new(
+                        &mut self.lift_node_lists,
+                        "Remap with the liftover",
+                    ),
+                );
+            });
+
+            let remap = ui
+                .add_enabled(
+                    self.liftover.is_some() && self.session_recv.is_none(),
+                    egui::Button::new("Open session for the old graph"),
+                )
+                .on_hover_text(
+                    "Remaps the session's bookmarks, 1D view and selection \
+                     to this graph, saves it as <name>.lifted.json, and \
+                     opens it",
+                );
+
+            if remap.clicked() {
+                let mut files = egui_file::FileDialog::open_file(None);
+                files.open();
+
+                let id = egui::Id::new("LiftoverWidget_session");
+                let recv = settings_ctx.with_file_dialog_oneshot(id, files);
+                self.session_path_recv = Some(recv);
+            }
+
+            if let Some(status) = self.status.as_ref() {
+                ui.label(status);
+            }
+
+            if let Some(err) = self.error.as_ref() {
+                ui.label(format!("Error: {err}"));
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_lists() {
+        let ids = parse_node_list("# nodes\n1 2,3\n\n 40\t5\n").unwrap();
+        assert_eq!(ids, vec![1, 2, 3, 40, 5]);
+
+        assert!(parse_node_list("1 two\n").is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! The window shown while the graph is loaded, with a coarse preview
+//! of what's been parsed so far: path count and lengths, and the path
+//! coverage across the pangenome. Loading can be cancelled from the
+//! window, or by closing it.
+
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use raving_wgpu::{gui::EguiCtx, WindowState};
+use waragraph_core::graph::progress::{LoadProgress, LoadSnapshot, LoadStage};
+use waragraph_core::graph::PathIndex;
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    platform::run_return::EventLoopExtRunReturn,
+    window::WindowBuilder,
+};
+
+const FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Loads the graph on a separate thread, showing the preview until
+/// it's done. Returns `None` if loading was cancelled or the window
+/// was closed first.
+pub fn load_graph_with_preview(
+    event_loop: &mut EventLoop<()>,
+    state: &raving_wgpu::State,
+    graph_path: &Path,
+) -> Result<Option<PathIndex>> {
+    let progress = Arc::new(LoadProgress::default());
+
+    let (result_send, result_recv) = mpsc::channel();
+
+    {
+        let progress = progress.clone();
+        let graph_path = graph_path.to_path_buf();
+        std::thread::spawn(move || {
+            let result = PathIndex::from_file_with_progress(
+                &graph_path,
+                Some(&progress),
+            );
+            let _ = result_send.send(result);
+        });
+    }
+
+    let file_name = graph_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let window = WindowBuilder::new()
+        .with_title(format!("Waragraph - loading {file_name}"))
+        .with_inner_size(winit::dpi::LogicalSize::new(640.0, 420.0))
+        .build(event_loop)?;
+
+    let mut window = state.prepare_window(window)?;
+    let mut egui_ctx =
+        EguiCtx::init(state, window.surface_format, event_loop, None);
+
+    let mut result = None;
+    let started = Instant::now();
+
+    event_loop.run_return(|event, _tgt, control_flow| {
+        *control_flow = ControlFlow::WaitUntil(Instant::now() + FRAME_INTERVAL);
+
+        match &event {
+            Event::WindowEvent { event, .. } => {
+                let _ = egui_ctx.on_event(event);
+
+                match event {
+                    WindowEvent::CloseRequested => {
+                        progress.cancel();
+                        *control_flow = ControlFlow::Exit;
+                    }
+                    WindowEvent::Resized(_)
+                    | WindowEvent::ScaleFactorChanged { .. } => {
+                        window.resize(&state.device);
+                    }
+                    _ => (),
+                }
+            }
+            Event::MainEventsCleared => {
+                match result_recv.try_recv() {
+                    Ok(loaded) => {
+                        result = Some(loaded.map_err(anyhow::Error::from));
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        result =
+                            Some(Err(anyhow::anyhow!("Error loading graph")));
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    Err(mpsc::TryRecvError::Empty) => (),
+                }
+
+                let snapshot = progress.snapshot();
+
+                egui_ctx.begin_frame(&window.window);
+                let mut cancel = false;
+                egui::CentralPanel::default().show(egui_ctx.ctx(), |ui| {
+                    show_preview(ui, &file_name, &snapshot, started.elapsed());
+
+                    ui.separator();
+                    cancel = ui.button("Cancel").clicked();
+                });
+                egui_ctx.end_frame(&window.window);
+
+                if cancel {
+                    // the loading thread stops at the next line it
+                    // reads; GBZ files are loaded in one step, and
+                    // finish in the background
+                    log::warn!("Graph loading cancelled");
+                    progress.cancel();
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+
+                window.window.request_redraw();
+            }
+            Event::RedrawRequested(_) => {
+                if let Err(e) = render(state, &mut window, &mut egui_ctx) {
+                    log::error!("Error rendering loading preview: {e:?}");
+                }
+            }
+            _ => (),
+        }
+    });
+
+    result.transpose()
+}
+
+fn render(
+    state: &raving_wgpu::State,
+    window: &mut WindowState,
+    egui_ctx: &mut EguiCtx,
+) -> Result<()> {
+    let output = match window.surface.get_current_texture() {
+        Ok(output) => output,
+        Err(wgpu::SurfaceError::OutOfMemory) => {
+            anyhow::bail!("Out of memory acquiring surface texture");
+        }
+        Err(_) => {
+            window.resize(&state.device);
+            return Ok(());
+        }
+    };
+
+    let mut encoder =
+        state
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Loading preview"),
+            });
+
+    let output_view = output
+        .texture
+        .create_view(&wgpu::TextureViewDescriptor::default());
+
+    egui_ctx.render(state, window, &output_view, &mut encoder);
+
+    state.queue.submit(Some(encoder.finish()));
+    output.present();
+
+    Ok(())
+}
+
+fn show_preview(
+    ui: &mut egui::Ui,
+    file_name: &str,
+    snapshot: &LoadSnapshot,
+    elapsed: Duration,
+) {
+    ui.heading(format!("Loading {file_name}"));
+
+    let stage_ix = match snapshot.stage {
+        LoadStage::Segments => 1,
+        LoadStage::Links => 2,
+        LoadStage::Paths | LoadStage::Done => 3,
+    };
+    let fraction = if snapshot.file_len > 0 {
+        snapshot.bytes_read as f32 / snapshot.file_len as f32
+    } else {
+        0.0
+    };
+
+    ui.add(
+        egui::ProgressBar::new(fraction)
+            .text(format!("{} ({stage_ix}/3)", snapshot.stage.label())),
+    );
+
+    egui::Grid::new("LoadingPreview-counts").show(ui, |ui| {
+        ui.label("Read");
+        ui.label(format!(
+            "{:.1} of {:.1} MB, {}s",
+            snapshot.bytes_read as f64 / 1e6,
+            snapshot.file_len as f64 / 1e6,
+            elapsed.as_secs()
+        ));
+        ui.end_row();
+
+        ui.label("Segments");
+        ui.label(snapshot.segments.to_string());
+        ui.end_row();
+
+        ui.label("Sequence");
+        ui.label(format!("{} bp", snapshot.sequence_len));
+        ui.end_row();
+
+        ui.label("Links");
+        ui.label(snapshot.edges.to_string());
+        ui.end_row();
+
+        ui.label("Paths");
+        ui.label(snapshot.paths.len().to_string());
+        ui.end_row();
+    });
+
+    if !snapshot.coverage.is_empty() {
+        ui.separator();
+        ui.label("Path coverage across the pangenome");
+        show_coverage(ui, &snapshot.coverage);
+    }
+
+    if !snapshot.paths.is_empty() {
+        ui.separator();
+        show_path_lengths(ui, &snapshot.paths);
+    }
+}
+
+fn show_coverage(ui: &mut egui::Ui, coverage: &[u64]) {
+    let width = ui.available_width();
+    let (rect, _) =
+        ui.allocate_exact_size(egui::vec2(width, 48.0), egui::Sense::hover());
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+    let max = coverage.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let bin_width = rect.width() / coverage.len() as f32;
+    let color = ui.visuals().selection.bg_fill;
+
+    for (ix, &count) in coverage.iter().enumerate() {
+        let height = rect.height() * count as f32 / max;
+        let x = rect.left() + ix as f32 * bin_width;
+        let bin = egui::Rect::from_min_max(
+            egui::pos2(x, rect.bottom() - height),
+            egui::pos2(x + bin_width, rect.bottom()),
+        );
+        painter.rect_filled(bin, 0.0, color);
+    }
+}
+
+fn show_path_lengths(ui: &mut egui::Ui, paths: &[(String, u64)]) {
+    let longest = paths.iter().map(|(_, len)| *len).max().unwrap_or(0);
+    let color = ui.visuals().selection.bg_fill;
+    let row_height = ui.text_style_height(&egui::TextStyle::Body);
+
+    egui::ScrollArea::vertical()
+        .stick_to_bottom(true)
+        .show_rows(ui, row_height, paths.len(), |ui, rows| {
+            for (name, len) in &paths[rows] {
+                ui.horizontal(|ui| {
+                    let (rect, resp) = ui.allocate_exact_size(
+                        egui::vec2(200.0, row_height),
+                        egui::Sense::hover(),
+                    );
+
+                    let fraction = *len as f32 / longest.max(1) as f32;
+                    let mut bar = rect;
+                    bar.set_width(rect.width() * fraction);
+                    ui.painter().rect_filled(bar, 0.0, color);
+
+                    resp.on_hover_text(format!("{len} bp"));
+                    ui.label(name);
+                });
+            }
+        });
+}
@@ -0,0 +1,306 @@
+//! Exporting the nodes in the 1D view range, or in the 2D viewport,
+//! as a BED file on the pangenome, for use with command-line tools.
+
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Result;
+use crossbeam::atomic::AtomicCell;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use tokio::sync::RwLock;
+use ultraviolet::Vec2;
+use waragraph_core::graph::{Bp, Node, PathIndex};
+
+use crate::viewer_2d::layout::NodePositions;
+
+use super::notify::{JobAction, JobDone};
+use super::provenance::EventKind;
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{AppMsg, SharedState};
+
+/// The nodes overlapping the pangenome range, in pangenome order.
+pub fn nodes_in_range(
+    graph: &PathIndex,
+    range: std::ops::Range<Bp>,
+) -> Vec<Node> {
+    let end = range.end.min(graph.pangenome_len());
+    if range.start >= end {
+        return Vec::new();
+    }
+
+    let nodes = graph.pos_range_nodes(range.start.0..end.0 - 1);
+    (nodes.start().ix()..=nodes.end().ix())
+        .map(Node::from)
+        .collect()
+}
+
+/// Writes one line per node, with the pangenome as the chromosome:
+/// the node's 0-based, end-exclusive pangenome range, its segment ID
+/// as the name, and its length.
+pub fn write_node_bed(
+    graph: &PathIndex,
+    nodes: &[Node],
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    writeln!(out, "#chrom\tstart\tend\tnode\tlength")?;
+
+    for &node in nodes {
+        let (offset, len) = graph.node_offset_length(node);
+        let start = offset.0;
+        let end = start + len.0;
+        let id = graph.segment_id(node);
+        writeln!(out, "pangenome\t{start}\t{end}\t{id}\t{}", len.0)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the nodes to `path` as BED, and records the export.
+pub fn export_node_bed(
+    shared: &SharedState,
+    nodes: &[Node],
+    path: &Path,
+) -> Result<()> {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write_node_bed(&shared.graph, nodes, &mut out)?;
+    out.flush()?;
+
+    log::warn!("Wrote {} nodes to {path:?}", nodes.len());
+
+    shared.provenance.blocking_write().record_path(
+        EventKind::Export,
+        "Node BED",
+        path,
+    );
+
+    Ok(())
+}
+
+/// Where the exported nodes are taken from.
+pub enum NodeRegion {
+    /// The pangenome range of the 1D view
+    View1D(Arc<AtomicCell<(u64, u64)>>),
+    /// The layout-space bounds of the 2D view, and the node positions
+    /// it shows
+    View2D {
+        viewport: Arc<AtomicCell<(Vec2, Vec2)>>,
+        positions: Arc<RwLock<Arc<NodePositions>>>,
+    },
+}
+
+impl NodeRegion {
+    fn nodes(&self, graph: &PathIndex) -> Vec<Node> {
+        match self {
+            NodeRegion::View1D(range) => {
+                let (start, end) = range.load();
+                nodes_in_range(graph, Bp(start)..Bp(end))
+            }
+            NodeRegion::View2D {
+                viewport,
+                positions,
+            } => {
+                let (min, max) = viewport.load();
+                positions.blocking_read().nodes_in_rect(min, max)
+            }
+        }
+    }
+}
+
+/// Settings window panel for exporting the nodes in a viewer's view.
+pub struct NodeExportWidget {
+    shared: SharedState,
+    region: Arc<NodeRegion>,
+
+    export_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    result_recv: Option<oneshot::Receiver<Result<(PathBuf, usize)>>>,
+    status: Option<String>,
+}
+
+impl NodeExportWidget {
+    pub fn new(shared: &SharedState, region: NodeRegion) -> Self {
+        Self {
+            shared: shared.clone(),
+            region: Arc::new(region),
+            export_path_recv: None,
+            result_recv: None,
+            status: None,
+        }
+    }
+
+    fn spawn_export(
+        &mut self,
+        tokio_handle: &tokio::runtime::Handle,
+        path: PathBuf,
+    ) {
+        let shared = self.shared.clone();
+        let region = self.region.clone();
+
+        let (send, recv) = oneshot::channel();
+        let started = Instant::now();
+
+        tokio_handle.spawn_blocking(move || {
+            let nodes = region.nodes(&shared.graph);
+
+            let result = export_node_bed(&shared, &nodes, &path)
+                .map(|_| (path, nodes.len()));
+
+            let done = match &result {
+                Ok((path, count)) => JobDone::finished("Node export", started)
+                    .with_detail(format!("{count} nodes to {}", path.display()))
+                    .with_action(JobAction::CopyPath(path.clone())),
+                Err(e) => JobDone::failed("Node export", started, e),
+            };
+            if let Err(e) =
+                shared.app_msg_send.blocking_send(AppMsg::JobDone(done))
+            {
+                log::error!("{e:?}");
+            }
+
+            let _ = send.send(result);
+        });
+
+        self.result_recv = Some(recv);
+        self.status = None;
+    }
+
+    fn poll_channels(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        if let Some(mut recv) = self.export_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => self.spawn_export(tokio_handle, path),
+                Err(TryRecvError::Empty) => self.export_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.result_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok((path, count))) => {
+                    self.status = Some(format!(
+                        "Wrote {count} nodes to {}",
+                        path.display()
+                    ));
+                }
+                Ok(Err(e)) => {
+                    log::error!("Error exporting nodes: {e:?}");
+                    self.status = Some(format!("Error: {e}"));
+                }
+                Err(TryRecvError::Empty) => self.result_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+impl SettingsWidget for NodeExportWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels(&settings_ctx.tokio_handle);
+
+        let region = match self.region.as_ref() {
+            NodeRegion::View1D(range) => {
+                let (start, end) = range.load();
+                let coords = self.shared.coords.load();
+                coords.format_range(&(Bp(start)..Bp(end)))
+            }
+            NodeRegion::View2D { .. } => "viewport".to_string(),
+        };
+
+        let resp = ui.vertical(|ui| {
+            let source = match self.region.as_ref() {
+                NodeRegion::View1D(_) => format!("1D view range ({region})"),
+                NodeRegion::View2D { .. } => "2D viewport".to_string(),
+            };
+            ui.label(format!("Nodes in the {source}"));
+
+            ui.weak(
+                "One line per node: `pangenome`, the node's 0-based \
+                 pangenome start and end, its segment ID, and its \
+                 length.",
+            );
+
+            let busy =
+                self.export_path_recv.is_some() || self.result_recv.is_some();
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!busy, egui::Button::new("Export BED"))
+                    .clicked()
+                {
+                    let path = self
+                        .shared
+                        .export_paths
+                        .blocking_read()
+                        .export_path("nodes", Some(&region), "bed");
+
+                    let id = egui::Id::new("NodeExportWidget_export").with(
+                        matches!(*self.region, NodeRegion::View2D { .. }),
+                    );
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.export_path_recv = Some(recv);
+                }
+
+                if self.result_recv.is_some() {
+                    ui.spinner();
+                }
+            });
+
+            if let Some(status) = self.status.as_ref() {
+                ui.label(status);
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GFA_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../test/data/A-3105.fa.353ea42.34ee7b1.1576367.smooth.fix.gfa"
+    );
+
+    #[test]
+    fn node_bed_covers_range() {
+        let graph = PathIndex::from_gfa(GFA_PATH).unwrap();
+
+        let first = Node::from(3u32);
+        let last = Node::from(5u32);
+        let start = graph.node_offset(first).0;
+        let end = graph.node_offset(last).0 + 1;
+
+        let nodes = nodes_in_range(&graph, Bp(start)..Bp(end));
+        assert_eq!(nodes, vec![first, Node::from(4u32), last]);
+
+        // ending at a node's start doesn't include it
+        let end = graph.node_offset(last).0;
+        let nodes = nodes_in_range(&graph, Bp(start)..Bp(end));
+        assert_eq!(nodes.last(), Some(&Node::from(4u32)));
+        assert!(nodes_in_range(&graph, Bp(end)..Bp(end)).is_empty());
+
+        let mut bed = Vec::new();
+        write_node_bed(&graph, &nodes, &mut bed).unwrap();
+        let bed = String::from_utf8(bed).unwrap();
+        let lines = bed.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+
+        let fields = lines[1].split('\t').collect::<Vec<_>>();
+        let range = graph.node_pangenome_range(first);
+        assert_eq!(fields[0], "pangenome");
+        assert_eq!(fields[1], range.start.0.to_string());
+        assert_eq!(fields[2], range.end.0.to_string());
+        assert_eq!(fields[3], graph.segment_id(first).to_string());
+        assert_eq!(fields[4], (range.end.0 - range.start.0).to_string());
+    }
+}
@@ -0,0 +1,341 @@
+//! Notifications for finished background jobs, such as layouts and
+//! exports, shown as toasts in the corner of the app windows, and
+//! optionally as system notifications.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::AppMsg;
+
+/// How long a toast stays up, unless closed
+const TOAST_SECS: u64 = 10;
+const HISTORY_LEN: usize = 32;
+
+/// What the button on a notification does
+#[derive(Debug, Clone)]
+pub enum JobAction {
+    /// Sends the message to the app, e.g. to show the result
+    Msg { label: String, msg: Box<AppMsg> },
+    /// Copies the path of a written file to the clipboard
+    CopyPath(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+pub struct JobDone {
+    pub title: String,
+    pub detail: Option<String>,
+    pub elapsed: Duration,
+    pub failed: bool,
+    pub action: Option<JobAction>,
+}
+
+impl JobDone {
+    pub fn finished(title: impl Into<String>, started: Instant) -> Self {
+        Self {
+            title: title.into(),
+            detail: None,
+            elapsed: started.elapsed(),
+            failed: false,
+            action: None,
+        }
+    }
+
+    pub fn failed(
+        title: impl Into<String>,
+        started: Instant,
+        error: impl std::fmt::Display,
+    ) -> Self {
+        Self {
+            detail: Some(error.to_string()),
+            failed: true,
+            ..Self::finished(title, started)
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_action(mut self, action: JobAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    fn summary(&self) -> String {
+        let elapsed = format_elapsed(self.elapsed);
+        if self.failed {
+            format!("Failed after {elapsed}")
+        } else {
+            format!("Finished in {elapsed}")
+        }
+    }
+}
+
+/// Formats the duration as seconds with one decimal, or as minutes
+/// and seconds if longer than a minute.
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64();
+    if secs < 60.0 {
+        format!("{secs:.1} s")
+    } else {
+        let secs = elapsed.as_secs();
+        format!("{} min {} s", secs / 60, secs % 60)
+    }
+}
+
+struct Toast {
+    id: usize,
+    job: JobDone,
+    shown_at: Instant,
+}
+
+/// The finished jobs, shown as toasts until they expire or are
+/// closed, and listed in the settings window.
+pub struct Notifications {
+    toasts: Vec<Toast>,
+    history: Vec<(std::time::SystemTime, JobDone)>,
+    next_id: usize,
+
+    /// Jobs that finish faster than this don't show a toast
+    pub min_elapsed_secs: f32,
+    /// Also send a system notification, via `notify-send` or
+    /// `osascript`
+    pub system: bool,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            toasts: Vec::new(),
+            history: Vec::new(),
+            next_id: 0,
+            min_elapsed_secs: 2.0,
+            system: false,
+        }
+    }
+}
+
+impl Notifications {
+    pub fn push(&mut self, job: JobDone) {
+        if job.failed {
+            log::error!("{}: {}", job.title, job.summary());
+        } else {
+            log::warn!("{}: {}", job.title, job.summary());
+        }
+
+        self.history
+            .push((std::time::SystemTime::now(), job.clone()));
+        if self.history.len() > HISTORY_LEN {
+            self.history.remove(0);
+        }
+
+        // failures are always shown
+        if !job.failed && job.elapsed.as_secs_f32() < self.min_elapsed_secs {
+            return;
+        }
+
+        if self.system {
+            let body = match job.detail.as_ref() {
+                Some(detail) => format!("{}\n{detail}", job.summary()),
+                None => job.summary(),
+            };
+            system_notification(&job.title, &body);
+        }
+
+        self.toasts.push(Toast {
+            id: self.next_id,
+            job,
+            shown_at: Instant::now(),
+        });
+        self.next_id += 1;
+    }
+
+    /// Draws the toasts in the bottom right corner, removing those
+    /// that are closed or have expired.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        app_msg_send: &tokio::sync::mpsc::Sender<AppMsg>,
+    ) {
+        let expiry = Duration::from_secs(TOAST_SECS);
+        self.toasts
+            .retain(|toast| toast.shown_at.elapsed() < expiry);
+
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let mut closed = Vec::new();
+
+        egui::Area::new("job_notifications")
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for toast in self.toasts.iter() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(320.0);
+
+                        ui.horizontal(|ui| {
+                            ui.strong(&toast.job.title);
+                            if ui.small_button("✕").clicked() {
+                                closed.push(toast.id);
+                            }
+                        });
+
+                        let summary = toast.job.summary();
+                        if toast.job.failed {
+                            ui.colored_label(
+                                ui.visuals().error_fg_color,
+                                summary,
+                            );
+                        } else {
+                            ui.label(summary);
+                        }
+
+                        if let Some(detail) = toast.job.detail.as_ref() {
+                            ui.weak(detail);
+                        }
+
+                        if let Some(action) = toast.job.action.as_ref() {
+                            if action_button(ui, action, app_msg_send) {
+                                closed.push(toast.id);
+                            }
+                        }
+                    });
+                }
+            });
+
+        self.toasts.retain(|toast| !closed.contains(&toast.id));
+    }
+}
+
+/// Shows the button for the action, and applies it when clicked;
+/// returns true if clicked.
+fn action_button(
+    ui: &mut egui::Ui,
+    action: &JobAction,
+    app_msg_send: &tokio::sync::mpsc::Sender<AppMsg>,
+) -> bool {
+    match action {
+        JobAction::Msg { label, msg } => {
+            let clicked = ui.button(label).clicked();
+            if clicked {
+                if let Err(e) = app_msg_send.try_send(msg.as_ref().clone()) {
+                    log::error!("Error applying notification action: {e:?}");
+                }
+            }
+            clicked
+        }
+        JobAction::CopyPath(path) => {
+            let clicked = ui.button("Copy path").clicked();
+            if clicked {
+                let text = path.display().to_string();
+                ui.output_mut(|o| o.copied_text = text);
+            }
+            clicked
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn system_notification_cmd(title: &str, body: &str) -> std::process::Command {
+    let mut cmd = std::process::Command::new("notify-send");
+    cmd.args(["--app-name", "waragraph", title, body]);
+    cmd
+}
+
+#[cfg(target_os = "macos")]
+fn system_notification_cmd(title: &str, body: &str) -> std::process::Command {
+    let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let script = format!(
+        "display notification \"{}\" with title \"{}\"",
+        quote(body),
+        quote(title)
+    );
+    let mut cmd = std::process::Command::new("osascript");
+    cmd.args(["-e", &script]);
+    cmd
+}
+
+fn system_notification(title: &str, body: &str) {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        let mut cmd = system_notification_cmd(title, body);
+        // waited on in a thread so the process is reaped
+        std::thread::spawn(move || {
+            if let Err(e) = cmd.status() {
+                log::error!("Error sending system notification: {e:?}");
+            }
+        });
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = (title, body);
+        log::warn!("System notifications aren't supported on this platform");
+    }
+}
+
+impl SettingsWidget for Notifications {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let resp = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Notify for jobs longer than");
+                ui.add(
+                    egui::DragValue::new(&mut self.min_elapsed_secs)
+                        .clamp_range(0.0..=600.0)
+                        .speed(0.5)
+                        .suffix(" s"),
+                );
+            });
+
+            ui.checkbox(&mut self.system, "System notifications")
+                .on_hover_text(
+                    "Also notify through the desktop, with notify-send \
+                     on Linux or osascript on macOS",
+                );
+
+            ui.separator();
+
+            if self.history.is_empty() {
+                ui.weak("No finished jobs");
+            }
+
+            egui::Grid::new("NotificationHistory").striped(true).show(
+                ui,
+                |ui| {
+                    for (time, job) in self.history.iter().rev() {
+                        let ago = time.elapsed().unwrap_or_default();
+                        ui.label(format!("{} ago", format_elapsed(ago)));
+                        ui.label(&job.title);
+                        ui.label(job.summary());
+                        ui.end_row();
+                    }
+                },
+            );
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elapsed_formatting() {
+        assert_eq!(format_elapsed(Duration::from_millis(2340)), "2.3 s");
+        assert_eq!(format_elapsed(Duration::from_secs(192)), "3 min 12 s");
+    }
+}
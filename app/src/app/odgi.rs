@@ -0,0 +1,500 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::Result;
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+use tokio::sync::RwLock;
+
+use super::notify::{JobAction, JobDone};
+use super::{workspace::Workspace, App, AppMsg, AppType, Args};
+
+/// Looks for an `odgi` executable in the directories in `PATH`.
+pub fn find_odgi() -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&paths).find_map(|dir| {
+        let odgi = dir.join("odgi");
+        odgi.is_file().then_some(odgi)
+    })
+}
+
+/// Whether the file is an odgi graph, going by its extension.
+pub fn is_og_file(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("og"))
+}
+
+/// Whether `path` exists and was modified no earlier than `src`.
+fn is_newer_than(path: &Path, src: &Path) -> bool {
+    let modified = |p: &Path| std::fs::metadata(p)?.modified();
+    match (modified(path), modified(src)) {
+        (Ok(a), Ok(b)) => a >= b,
+        _ => false,
+    }
+}
+
+/// The odgi graph built by a previous `odgi layout` run on the GFA,
+/// if it's still up to date.
+pub fn cached_og(gfa_path: &Path) -> Option<PathBuf> {
+    let (og_path, _) = layout_output_paths(gfa_path);
+    is_newer_than(&og_path, gfa_path).then_some(og_path)
+}
+
+/// Runs `odgi`, writing its stdout to `out_path`; the output file is
+/// removed if the command fails.
+fn run_odgi_to_file(
+    odgi: &Path,
+    args: &[&std::ffi::OsStr],
+    out_path: &Path,
+) -> Result<()> {
+    let out = std::fs::File::create(out_path)?;
+
+    let output = std::process::Command::new(odgi)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(out)
+        .output()?;
+
+    if !output.status.success() {
+        let _ = std::fs::remove_file(out_path);
+        anyhow::bail!(
+            "odgi {} failed: {}",
+            args[0].to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// A directory for files converted with odgi, unique to the process;
+/// it's removed, with its contents, when dropped.
+#[derive(Debug)]
+pub struct ConversionDir {
+    path: PathBuf,
+}
+
+impl ConversionDir {
+    pub fn new() -> Result<Self> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let n = COUNT.fetch_add(1, Ordering::Relaxed);
+        let pid = std::process::id();
+        let path =
+            std::env::temp_dir().join(format!("waragraph-odgi-{pid}-{n}"));
+
+        std::fs::create_dir(&path)?;
+
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ConversionDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            log::warn!("Error removing {:?}: {e}", self.path);
+        }
+    }
+}
+
+/// Converts an odgi graph to GFA with `odgi view`, writing it in
+/// `dir`.
+pub fn og_to_gfa(
+    odgi: &Path,
+    og_path: &Path,
+    dir: &ConversionDir,
+) -> Result<PathBuf> {
+    let stem = og_path
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or("graph".into());
+    let gfa_path = dir.path().join(format!("{stem}.gfa"));
+
+    log::warn!("Converting {og_path:?} to GFA");
+    run_odgi_to_file(
+        odgi,
+        &[
+            "view".as_ref(),
+            "-i".as_ref(),
+            og_path.as_ref(),
+            "-g".as_ref(),
+        ],
+        &gfa_path,
+    )?;
+
+    Ok(gfa_path)
+}
+
+/// Converts a binary layout written by `odgi layout -o` to TSV with
+/// `odgi draw`, which also needs the graph the layout was computed
+/// for. The TSV is written in `dir`.
+pub fn lay_to_tsv(
+    odgi: &Path,
+    og_path: &Path,
+    lay_path: &Path,
+    dir: &ConversionDir,
+) -> Result<PathBuf> {
+    let tsv_path = dir.path().join("layout.tsv");
+    // odgi draw insists on drawing the layout as well
+    let png_path = dir.path().join("layout.png");
+
+    let output = std::process::Command::new(odgi)
+        .arg("draw")
+        .arg("-i")
+        .arg(og_path)
+        .arg("-c")
+        .arg(lay_path)
+        .arg("-T")
+        .arg(&tsv_path)
+        .arg("-p")
+        .arg(&png_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "odgi draw failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(tsv_path)
+}
+
+/// If the graph given on the command line is an odgi graph, replaces
+/// it with its GFA conversion, which everything else reads, and keeps
+/// the original in `args.og` for loading odgi layouts. The conversion
+/// is removed when `args.og_conversion` is dropped.
+pub fn resolve_og_args(args: &mut Args) -> Result<()> {
+    if !is_og_file(&args.gfa) {
+        return Ok(());
+    }
+
+    let Some(odgi) = find_odgi() else {
+        anyhow::bail!(
+            "Loading odgi graphs requires odgi in PATH, or convert {} \
+             with `odgi view -g`",
+            args.gfa.display()
+        );
+    };
+
+    let dir = ConversionDir::new()?;
+    let gfa = og_to_gfa(&odgi, &args.gfa, &dir)?;
+    args.og = Some(std::mem::replace(&mut args.gfa, gfa));
+    args.og_conversion = Some(dir);
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OdgiLayoutStatus {
+    Building,
+    Layout { progress: f32 },
+    Done { tsv_path: PathBuf },
+    Failed(String),
+}
+
+impl App {
+    /// Starts `odgi layout` in the background, if a layout isn't
+    /// already loaded or being computed. The 2D viewer is opened when
+    /// the layout is done.
+    pub fn run_odgi_layout(&mut self) {
+        let mut workspace = self.shared.workspace.blocking_write();
+
+        if workspace.tsv_path.is_some() {
+            return;
+        }
+
+        let running = workspace
+            .odgi_layout_job
+            .as_ref()
+            .map(|job| !matches!(job.status(), OdgiLayoutStatus::Failed(_)));
+
+        if running == Some(true) {
+            return;
+        }
+
+        if waragraph_core::graph::gbz::is_gbz_file(&workspace.gfa_path) {
+            log::warn!("odgi layout requires a GFA, using the built-in layout");
+            drop(workspace);
+            self.run_builtin_layout();
+            return;
+        }
+
+        let Some(odgi_path) = find_odgi() else {
+            log::warn!("odgi not found in PATH, using the built-in layout");
+            drop(workspace);
+            self.run_builtin_layout();
+            return;
+        };
+
+        let job = OdgiLayoutJob::spawn(
+            self.tokio_rt.handle(),
+            odgi_path,
+            workspace.graph_path.clone(),
+            workspace.og_path.clone(),
+            self.shared.workspace.clone(),
+            self.shared.app_msg_send.clone(),
+        );
+
+        workspace.odgi_layout_job = Some(job);
+    }
+}
+
+/// Handle to an `odgi layout` run in the background; the status can
+/// be polled from the GUI.
+#[derive(Clone)]
+pub struct OdgiLayoutJob {
+    status: Arc<Mutex<OdgiLayoutStatus>>,
+}
+
+impl OdgiLayoutJob {
+    pub fn status(&self) -> OdgiLayoutStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Runs `odgi build` and `odgi layout` on the workspace GFA,
+    /// writing the layout TSV next to the GFA. When the layout is
+    /// done, it's stored in the workspace and the 2D viewer is
+    /// initialized.
+    ///
+    /// If a layout TSV from a previous run exists, and is newer than
+    /// the GFA, it's used directly. If `og_path` is given, that graph
+    /// is laid out instead of building one from the GFA, and
+    /// `gfa_path` may be the odgi graph itself, only naming the layout.
+    pub fn spawn(
+        rt: &tokio::runtime::Handle,
+        odgi: PathBuf,
+        gfa_path: PathBuf,
+        og_path: Option<PathBuf>,
+        workspace: Arc<RwLock<Workspace>>,
+        app_msg_send: tokio::sync::mpsc::Sender<AppMsg>,
+    ) -> Self {
+        let status = Arc::new(Mutex::new(OdgiLayoutStatus::Building));
+
+        let job = Self {
+            status: status.clone(),
+        };
+
+        let started = Instant::now();
+
+        rt.spawn(async move {
+            let build_og = og_path.is_none();
+            let og_path =
+                og_path.unwrap_or_else(|| layout_output_paths(&gfa_path).0);
+            let result =
+                run_odgi_layout(&odgi, &gfa_path, &og_path, build_og, &status)
+                    .await;
+
+            match result {
+                Ok(tsv_path) => {
+                    log::warn!("odgi layout finished: {tsv_path:?}");
+
+                    {
+                        let mut ws = workspace.write().await;
+                        if ws.tsv_path.is_none() {
+                            ws.tsv_path = Some(tsv_path.clone());
+                        }
+                        ws.og_path.get_or_insert(og_path);
+                    }
+
+                    *status.lock().unwrap() =
+                        OdgiLayoutStatus::Done { tsv_path };
+
+                    let done = JobDone::finished("odgi layout", started)
+                        .with_action(JobAction::Msg {
+                            label: "Show 2D view".to_string(),
+                            msg: Box::new(AppMsg::FocusWindow(
+                                AppType::Viewer2D,
+                            )),
+                        });
+
+                    for msg in [AppMsg::InitViewer2D, AppMsg::JobDone(done)] {
+                        if let Err(e) = app_msg_send.send(msg).await {
+                            log::error!("{e:?}");
+                        }
+                    }
+                }
+                Err(e) => {
+                    log::error!("Error running odgi layout: {e:?}");
+                    *status.lock().unwrap() =
+                        OdgiLayoutStatus::Failed(e.to_string());
+
+                    let done = JobDone::failed("odgi layout", started, &e);
+                    if let Err(e) =
+                        app_msg_send.send(AppMsg::JobDone(done)).await
+                    {
+                        log::error!("{e:?}");
+                    }
+                }
+            }
+        });
+
+        job
+    }
+}
+
+fn layout_output_paths(gfa_path: &Path) -> (PathBuf, PathBuf) {
+    let og_path = gfa_path.with_extension("og");
+    let tsv_path = gfa_path.with_extension("odgi.layout.tsv");
+    (og_path, tsv_path)
+}
+
+async fn run_odgi_layout(
+    odgi: &Path,
+    gfa_path: &Path,
+    og_path: &Path,
+    build_og: bool,
+    status: &Mutex<OdgiLayoutStatus>,
+) -> Result<PathBuf> {
+    let (_, tsv_path) = layout_output_paths(gfa_path);
+
+    let is_newer_than_gfa = |path: &Path| is_newer_than(path, gfa_path);
+
+    if is_newer_than_gfa(&tsv_path) {
+        log::warn!("Using existing odgi layout {tsv_path:?}");
+        return Ok(tsv_path);
+    }
+
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .to_string();
+
+    if build_og && !is_newer_than_gfa(og_path) {
+        *status.lock().unwrap() = OdgiLayoutStatus::Building;
+
+        let output = Command::new(odgi)
+            .arg("build")
+            .arg("-g")
+            .arg(gfa_path)
+            .arg("-o")
+            .arg(og_path)
+            .args(["-t", &threads])
+            .stdin(Stdio::null())
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "odgi build failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+    }
+
+    *status.lock().unwrap() = OdgiLayoutStatus::Layout { progress: 0.0 };
+
+    let mut child = Command::new(odgi)
+        .arg("layout")
+        .arg("-i")
+        .arg(og_path)
+        .arg("-T")
+        .arg(&tsv_path)
+        .args(["-t", &threads])
+        .arg("-P")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // odgi reports progress on stderr, overwriting the line using
+    // carriage returns, so the output is read in chunks rather than
+    // lines, keeping the tail for error reporting
+    let mut stderr = child.stderr.take().unwrap();
+    let mut stderr_tail = String::new();
+    let mut buf = [0u8; 4096];
+
+    loop {
+        let n = stderr.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+
+        let chunk = String::from_utf8_lossy(&buf[..n]);
+
+        if let Some(progress) = chunk.rsplit('\r').find_map(parse_progress) {
+            *status.lock().unwrap() = OdgiLayoutStatus::Layout { progress };
+        }
+
+        stderr_tail.push_str(&chunk);
+        if stderr_tail.len() > 4096 {
+            let cut = stderr_tail.len() - 4096;
+            let cut = (cut..stderr_tail.len())
+                .find(|&i| stderr_tail.is_char_boundary(i))
+                .unwrap_or(stderr_tail.len());
+            stderr_tail.drain(..cut);
+        }
+    }
+
+    let exit = child.wait().await?;
+
+    if !exit.success() {
+        let last_line = stderr_tail
+            .rsplit(|c| c == '\n' || c == '\r')
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or_default();
+        anyhow::bail!("odgi layout failed: {}", last_line.trim());
+    }
+
+    Ok(tsv_path)
+}
+
+/// Parses the last percentage (e.g. `42.50%`) in a progress line,
+/// returning it as a fraction in `0.0..=1.0`.
+fn parse_progress(line: &str) -> Option<f32> {
+    let end = line.rfind('%')?;
+    let start = line[..end]
+        .rfind(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+
+    let pct = line[start..end].parse::<f32>().ok()?;
+    Some((pct / 100.0).clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn odgi_progress_parsing() {
+        let line = "[odgi::layout] 2D path-guided SGD: 50.00% @ 1.2e+06/s";
+        assert_eq!(parse_progress(line), Some(0.5));
+        assert_eq!(parse_progress("100%"), Some(1.0));
+        assert_eq!(parse_progress("no progress here"), None);
+    }
+
+    #[test]
+    fn odgi_graph_extension() {
+        assert!(is_og_file("data/graph.og"));
+        assert!(is_og_file("graph.OG"));
+        assert!(!is_og_file("graph.gfa"));
+        assert!(!is_og_file("graph.odgi.gfa"));
+    }
+
+    #[test]
+    fn conversion_dir_is_removed() -> Result<()> {
+        let dir = ConversionDir::new()?;
+        let other = ConversionDir::new()?;
+        assert_ne!(dir.path(), other.path());
+
+        let path = dir.path().to_path_buf();
+        std::fs::write(path.join("graph.gfa"), "H\tVN:Z:1.0\n")?;
+
+        drop(dir);
+        assert!(!path.exists());
+
+        Ok(())
+    }
+}
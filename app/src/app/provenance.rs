@@ -0,0 +1,379 @@
+//! Append-only log of the data loaded into, and exported from, the
+//! app, so that analyses done through the GUI can be traced back to
+//! their inputs.
+
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::Serialize;
+use tokio::sync::{oneshot, RwLock};
+
+use super::export_paths::ExportPaths;
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{AppMsg, Args, SharedState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Load,
+    Filter,
+    Export,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Load => "load",
+            EventKind::Filter => "filter",
+            EventKind::Export => "export",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProvenanceEvent {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub kind: EventKind,
+    pub description: String,
+    pub path: Option<PathBuf>,
+    /// SHA-256 of the file at `path`, as hex
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ProvenanceLog {
+    events: Vec<ProvenanceEvent>,
+}
+
+impl ProvenanceLog {
+    pub fn events(&self) -> &[ProvenanceEvent] {
+        &self.events
+    }
+
+    /// True if an event involving the file has been recorded.
+    pub fn contains_path(&self, path: &Path) -> bool {
+        self.events.iter().any(|e| e.path.as_deref() == Some(path))
+    }
+
+    pub fn record(&mut self, kind: EventKind, description: impl ToString) {
+        self.push(kind, description.to_string(), None, None);
+    }
+
+    /// Records an event involving a file, without hashing it.
+    pub fn record_path(
+        &mut self,
+        kind: EventKind,
+        description: impl ToString,
+        path: &Path,
+    ) {
+        let path = Some(path.to_path_buf());
+        self.push(kind, description.to_string(), path, None);
+    }
+
+    /// Records an event involving a file that has already been
+    /// hashed.
+    pub fn record_hashed(
+        &mut self,
+        kind: EventKind,
+        description: impl ToString,
+        path: &Path,
+        sha256: Option<String>,
+    ) {
+        let path = Some(path.to_path_buf());
+        self.push(kind, description.to_string(), path, sha256);
+    }
+
+    fn push(
+        &mut self,
+        kind: EventKind,
+        description: String,
+        path: Option<PathBuf>,
+        sha256: Option<String>,
+    ) {
+        self.events.push(ProvenanceEvent {
+            timestamp: unix_now(),
+            kind,
+            description,
+            path,
+            sha256,
+        });
+    }
+
+    /// Hashes the file on a blocking thread, and records the event
+    /// when done; if the file can't be read, it's recorded without
+    /// a hash.
+    pub fn record_file_hashed(
+        log: &Arc<RwLock<Self>>,
+        tokio_handle: &tokio::runtime::Handle,
+        kind: EventKind,
+        description: impl ToString,
+        path: &Path,
+    ) {
+        let log = log.clone();
+        let description = description.to_string();
+        let path = path.to_path_buf();
+        let timestamp = unix_now();
+
+        tokio_handle.spawn_blocking(move || {
+            let sha256 = match sha256_file(&path) {
+                Ok(hash) => Some(hash),
+                Err(e) => {
+                    log::error!("Error hashing {path:?}: {e:?}");
+                    None
+                }
+            };
+
+            log.blocking_write().events.push(ProvenanceEvent {
+                timestamp,
+                kind,
+                description,
+                path: Some(path),
+                sha256,
+            });
+        });
+    }
+}
+
+/// An event as written to session files
+#[derive(Serialize)]
+struct EventRecord<'a> {
+    time: String,
+    timestamp: u64,
+    kind: &'static str,
+    description: &'a str,
+    path: Option<&'a Path>,
+    sha256: Option<&'a str>,
+}
+
+impl Serialize for ProvenanceLog {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.events.iter().map(|event| EventRecord {
+            time: format_timestamp(event.timestamp),
+            timestamp: event.timestamp,
+            kind: event.kind.as_str(),
+            description: &event.description,
+            path: event.path.as_deref(),
+            sha256: event.sha256.as_deref(),
+        }))
+    }
+}
+
+/// Hashes the graph file on a blocking thread, and records its Load
+/// event when done. The graph's SHA-256 identifies it in the log and
+/// in view links; the returned lock is held until the hash is done,
+/// so anything that needs it before then waits.
+pub fn hash_graph(
+    tokio_handle: &tokio::runtime::Handle,
+    log: &Arc<RwLock<ProvenanceLog>>,
+    args: &Args,
+) -> Arc<RwLock<Option<String>>> {
+    let graph_hash = Arc::new(RwLock::new(None));
+
+    let mut hash = graph_hash.clone().try_write_owned().unwrap();
+    let log = log.clone();
+    let graph_path = args.graph_path().to_path_buf();
+    let description = if waragraph_core::graph::gbz::is_gbz_file(&args.gfa) {
+        "Graph (GBZ)"
+    } else if args.og.is_some() {
+        "Graph (odgi)"
+    } else {
+        "Graph (GFA)"
+    };
+
+    tokio_handle.spawn_blocking(move || {
+        match sha256_file(&graph_path) {
+            Ok(h) => *hash = Some(h),
+            Err(e) => log::error!("Error hashing {graph_path:?}: {e:?}"),
+        }
+
+        log.blocking_write().record_hashed(
+            EventKind::Load,
+            description,
+            &graph_path,
+            hash.clone(),
+        );
+    });
+
+    graph_hash
+}
+
+pub fn sha256_file(path: impl AsRef<Path>) -> Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 16];
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    let hash = hasher.finalize();
+    Ok(hash.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Seconds since the Unix epoch, now.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS` UTC.
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let secs = timestamp % 86_400;
+
+    // days to civil date, from Howard Hinnant's `civil_from_days`
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
+
+/// Settings window panel listing the provenance log, with a button
+/// for exporting the session.
+pub struct ProvenanceWidget {
+    log: Arc<RwLock<ProvenanceLog>>,
+    export_paths: Arc<RwLock<ExportPaths>>,
+
+    session_path_recv: Option<oneshot::Receiver<PathBuf>>,
+}
+
+impl ProvenanceWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            log: shared.provenance.clone(),
+            export_paths: shared.export_paths.clone(),
+            session_path_recv: None,
+        }
+    }
+}
+
+impl SettingsWidget for ProvenanceWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        use oneshot::error::TryRecvError;
+
+        if let Some(mut recv) = self.session_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    settings_ctx.send_app_msg_task(AppMsg::ExportSession(path));
+                }
+                Err(TryRecvError::Empty) => self.session_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        let resp = ui.vertical(|ui| {
+            if ui
+                .button("Export session")
+                .on_hover_text(
+                    "Saves the graphs, their views and this log as JSON",
+                )
+                .clicked()
+            {
+                let path = self
+                    .export_paths
+                    .blocking_read()
+                    .export_path("session", None, "json");
+
+                let id = egui::Id::new("ProvenanceWidget_session");
+                let recv = settings_ctx.save_file_oneshot(id, path);
+                self.session_path_recv = Some(recv);
+            }
+
+            let log = self.log.blocking_read();
+
+            egui::ScrollArea::vertical()
+                .max_height(400.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("ProvenanceWidget-events")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label("Time (UTC)");
+                            ui.label("Event");
+                            ui.label("Description");
+                            ui.label("SHA-256");
+                            ui.end_row();
+
+                            for event in log.events() {
+                                ui.label(format_timestamp(event.timestamp));
+                                ui.label(event.kind.as_str());
+
+                                let label = ui.label(&event.description);
+                                if let Some(path) = event.path.as_ref() {
+                                    label.on_hover_text(path.to_string_lossy());
+                                }
+
+                                if let Some(hash) = event.sha256.as_ref() {
+                                    ui.monospace(&hash[..12])
+                                        .on_hover_text(hash);
+                                } else {
+                                    ui.label("");
+                                }
+
+                                ui.end_row();
+                            }
+                        });
+                });
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_utc_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13:20");
+    }
+
+    #[test]
+    fn log_json_is_escaped() {
+        let mut log = ProvenanceLog::default();
+        log.record(EventKind::Filter, "min length \"50\"");
+        log.record_path(EventKind::Export, "image", Path::new("a.png"));
+
+        let json = serde_json::to_string(&log).unwrap();
+
+        assert!(json.contains(r#""description":"min length \"50\"""#));
+        assert!(json.contains(r#""path":"a.png","sha256":null}"#));
+        assert_eq!(json.matches("\"kind\"").count(), 2);
+    }
+}
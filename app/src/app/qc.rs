@@ -0,0 +1,283 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::qc::{duplicate_segment_names, GraphQc};
+use waragraph_core::graph::PathIndex;
+
+use crate::viewer_1d::nonref_report::parse_path_list;
+
+use super::provenance::EventKind;
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{Args, SharedState};
+
+/// Computes the QC summary; `references` is a comma-separated list of
+/// path names.
+pub fn generate_report(
+    graph: &PathIndex,
+    gfa_path: &Path,
+    references: &str,
+) -> Result<GraphQc> {
+    let references = parse_path_list(graph, references)?;
+
+    let mut qc = GraphQc::compute(graph, &references);
+    qc.duplicate_segment_names = duplicate_segment_names(gfa_path)?;
+
+    Ok(qc)
+}
+
+/// Writes the report as HTML if the file extension is `html` or
+/// `htm`, and as JSON otherwise.
+pub fn export_report(qc: &GraphQc, path: &Path) -> Result<()> {
+    let is_html = path
+        .extension()
+        .map(|ext| ext == "html" || ext == "htm")
+        .unwrap_or(false);
+
+    let out = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+    if is_html {
+        qc.write_html(out)?;
+    } else {
+        qc.write_json(out)?;
+    }
+
+    log::warn!("Wrote QC report to {path:?}");
+
+    Ok(())
+}
+
+/// Entry point for `waragraph qc <gfa>`. The report is printed to
+/// stdout as JSON, unless `--output` is given.
+pub fn run(args: &Args) -> Result<()> {
+    // check for duplicates first, as the index can't be built if
+    // segment names aren't unique
+    let duplicates = duplicate_segment_names(&args.gfa)?;
+
+    if !duplicates.is_empty() {
+        log::error!(
+            "{} duplicate segment names, e.g. `{}`",
+            duplicates.len(),
+            duplicates[0]
+        );
+    }
+
+    let graph = match PathIndex::from_file(&args.gfa) {
+        Ok(graph) => graph,
+        Err(e) if !duplicates.is_empty() => {
+            log::error!("Skipping the graph statistics: {e}");
+            return write_report(args, &GraphQc::unindexed(duplicates));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let references = args
+        .reference
+        .as_deref()
+        .map(|names| parse_path_list(&graph, names))
+        .transpose()?
+        .unwrap_or_default();
+
+    let mut qc = GraphQc::compute(&graph, &references);
+    qc.duplicate_segment_names = duplicates;
+
+    write_report(args, &qc)
+}
+
+fn write_report(args: &Args, qc: &GraphQc) -> Result<()> {
+    if let Some(output) = args.output.as_ref() {
+        export_report(qc, output)
+    } else {
+        qc.write_json(std::io::stdout().lock())?;
+        Ok(())
+    }
+}
+
+/// Settings window panel showing the QC summary of the loaded graph.
+pub struct QcReportWidget {
+    shared: SharedState,
+
+    references: String,
+
+    report_recv: Option<oneshot::Receiver<Result<GraphQc>>>,
+    report: Option<Arc<GraphQc>>,
+    export_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    error: Option<String>,
+}
+
+impl QcReportWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            shared: shared.clone(),
+
+            references: shared.detected_reference_name(),
+
+            report_recv: None,
+            report: None,
+            export_path_recv: None,
+            error: None,
+        }
+    }
+
+    fn spawn_report(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        let graph = self.shared.graph.clone();
+        let gfa_path = self.shared.workspace.blocking_read().gfa_path().clone();
+        let references = self.references.clone();
+
+        let (send, recv) = oneshot::channel();
+
+        tokio_handle.spawn_blocking(move || {
+            let result = generate_report(&graph, &gfa_path, &references);
+            let _ = send.send(result);
+        });
+
+        self.report_recv = Some(recv);
+        self.error = None;
+    }
+
+    fn poll_channels(&mut self) {
+        if let Some(mut recv) = self.report_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok(report)) => self.report = Some(Arc::new(report)),
+                Ok(Err(e)) => self.error = Some(e.to_string()),
+                Err(TryRecvError::Empty) => self.report_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.export_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    if let Some(report) = self.report.as_ref() {
+                        if let Err(e) = export_report(report, &path) {
+                            log::error!("Error exporting QC report: {e:?}");
+                            self.error = Some(e.to_string());
+                        } else {
+                            self.shared
+                                .provenance
+                                .blocking_write()
+                                .record_path(
+                                    EventKind::Export,
+                                    "Graph QC report",
+                                    &path,
+                                );
+                        }
+                    }
+                }
+                Err(TryRecvError::Empty) => self.export_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+impl SettingsWidget for QcReportWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels();
+
+        let resp = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Reference paths:");
+                ui.text_edit_singleline(&mut self.references)
+                    .on_hover_text("Comma-separated path names (optional)");
+            });
+
+            ui.horizontal(|ui| {
+                let running = self.report_recv.is_some();
+
+                if ui
+                    .add_enabled(!running, egui::Button::new("Generate"))
+                    .clicked()
+                {
+                    self.spawn_report(&settings_ctx.tokio_handle);
+                }
+
+                if running {
+                    ui.spinner();
+                }
+
+                let export = ui
+                    .add_enabled(
+                        self.report.is_some(),
+                        egui::Button::new("Export"),
+                    )
+                    .on_hover_text(
+                        "Saved as HTML if the file name ends with .html, \
+                         and as JSON otherwise",
+                    );
+
+                if export.clicked() {
+                    let path = self
+                        .shared
+                        .export_paths
+                        .blocking_read()
+                        .export_path("qc", None, "html");
+
+                    let id = egui::Id::new("QcReportWidget_export");
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.export_path_recv = Some(recv);
+                }
+            });
+
+            if let Some(err) = self.error.as_ref() {
+                ui.label(format!("Error: {err}"));
+            }
+
+            let Some(report) = self.report.as_ref() else {
+                return;
+            };
+
+            egui::Grid::new("QcReportWidget-summary")
+                .striped(true)
+                .show(ui, |ui| {
+                    for (label, value) in report.summary_rows() {
+                        ui.label(label);
+                        ui.label(value);
+                        ui.end_row();
+                    }
+
+                    for (label, stats) in [
+                        ("Node length", &report.node_lengths),
+                        ("Path length", &report.path_lengths),
+                    ] {
+                        ui.label(label);
+                        ui.label(format!(
+                            "{}-{} bp, mean {:.1}, N50 {}",
+                            stats.min, stats.max, stats.mean, stats.n50
+                        ));
+                        ui.end_row();
+                    }
+
+                    if let Some(largest) = report.components.first() {
+                        ui.label("Largest component");
+                        ui.label(format!(
+                            "{} nodes, {} bp",
+                            largest.nodes, largest.length.0
+                        ));
+                        ui.end_row();
+                    }
+
+                    for cov in &report.reference_coverage {
+                        ui.label(format!("Coverage by {}", cov.name));
+                        ui.label(format!(
+                            "{} bp ({:.2}%)",
+                            cov.covered.0,
+                            cov.fraction * 100.0
+                        ));
+                        ui.end_row();
+                    }
+                });
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
@@ -149,6 +149,19 @@ impl GraphDataSources {
             graph_f32.insert(name, Arc::new(ctor));
         }
 
+        // graph node multiplicity
+        {
+            let name = "multiplicity".to_string();
+            let graph = graph.clone();
+            let ctor = move || {
+                Ok((0..graph.node_count)
+                    .map(|ix| graph.node_multiplicity(Node::from(ix)))
+                    .collect())
+            };
+
+            graph_f32.insert(name, Arc::new(ctor));
+        }
+
         // path depth
         {
             let name = "depth".to_string();
@@ -166,6 +179,22 @@ impl GraphDataSources {
             path_f32.insert(name, Arc::new(ctor));
         }
 
+        // node multiplicity on path, i.e. the average number of
+        // traversals per path of each node the path visits
+        {
+            let name = "multiplicity".to_string();
+            let graph = graph.clone();
+            let ctor = move |path: PathId| {
+                let path_data = graph.path_node_sets[path.ix()]
+                    .iter()
+                    .map(|node| graph.node_multiplicity(Node::from(node)))
+                    .collect::<Vec<_>>();
+                Ok(path_data)
+            };
+
+            path_f32.insert(name, Arc::new(ctor));
+        }
+
         // path strand
         {
             let name = "strand".to_string();
@@ -206,6 +235,9 @@ impl GraphDataSources {
 
 pub struct GraphDataCache {
     graph: Arc<PathIndex>,
+    // the keys of the sources loaded from files, such as bedGraph
+    // tracks, in the order they were added
+    tracks: Vec<String>,
     graph_f32: RwLock<HashMap<String, Arc<GraphData<f32, FStats>>>>,
     path_f32:
         RwLock<HashMap<(String, PathId), Arc<GraphPathData<f32, FStats>>>>,
@@ -224,12 +256,53 @@ impl GraphDataCache {
 
         Self {
             graph,
+            tracks: Vec::new(),
             graph_f32,
             path_f32,
             sources,
         }
     }
 
+    /// A cache for `graph`, which has the same nodes as the cache's
+    /// graph but may have more paths, keeping the sources added with
+    /// `insert_track`.
+    pub fn with_graph(&self, graph: &Arc<PathIndex>) -> Self {
+        let mut cache = Self::init(graph);
+
+        for key in self.tracks.iter() {
+            let graph_source = self.sources.graph_f32.get(key).cloned();
+            let path_source = self.sources.path_f32.get(key).cloned();
+
+            if let (Some(graph_source), Some(path_source)) =
+                (graph_source, path_source)
+            {
+                cache.insert_track(key, graph_source, path_source);
+            }
+        }
+
+        cache
+    }
+
+    /// Adds a data source with both node and path values under `key`,
+    /// replacing any source with the same key.
+    pub fn insert_track(
+        &mut self,
+        key: &str,
+        graph_source: GraphDataSourceFn<f32>,
+        path_source: PathDataSourceFn<f32>,
+    ) {
+        self.sources.graph_f32.insert(key.to_string(), graph_source);
+        self.sources.path_f32.insert(key.to_string(), path_source);
+        if !self.tracks.iter().any(|k| k == key) {
+            self.tracks.push(key.to_string());
+        }
+    }
+
+    /// The keys of the sources added with `insert_track`
+    pub fn tracks(&self) -> &[String] {
+        &self.tracks
+    }
+
     pub fn graph_data_source_names<'a>(
         &'a self,
     ) -> impl Iterator<Item = &'a str> + 'a {
@@ -0,0 +1,160 @@
+//! `--safe-mode`: starts without the annotations, the 2D path lines,
+//! GPU sampling of the 1D path data, and the 2D window, so that a
+//! graph can still be opened on a flaky driver. The "Safe Mode" panel
+//! turns the features back on one at a time, to find the one that
+//! causes problems.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use super::annotation_load::AnnotationLoadJob;
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{AppMsg, SharedState};
+
+pub struct SafeModeWidget {
+    shared: SharedState,
+
+    // annotation files from the command line, loaded on request
+    annotations: Vec<PathBuf>,
+    gff_attr: String,
+    annotation_jobs: Vec<AnnotationLoadJob>,
+    // the paths drawn in the 2D view when the path colors were loaded
+    paths_2d: BTreeSet<String>,
+
+    enabled: Vec<&'static str>,
+}
+
+impl SafeModeWidget {
+    /// Takes the shown 2D paths out of the path colors, to be
+    /// restored from the panel.
+    pub fn new(
+        shared: &SharedState,
+        annotations: Vec<PathBuf>,
+        gff_attr: &str,
+    ) -> Self {
+        let paths_2d = {
+            let mut path_colors = shared.path_colors.blocking_write();
+            let paths = std::mem::take(&mut path_colors.shown_2d);
+            path_colors.mark_changed();
+            paths
+        };
+
+        log::warn!(
+            "Safe mode: annotations, 2D path lines, GPU sampling and \
+             the 2D view are disabled; enable them from the \"Safe Mode\" \
+             panel in the \"Diagnostics\" settings tab"
+        );
+
+        Self {
+            shared: shared.clone(),
+            annotations,
+            gff_attr: gff_attr.to_string(),
+            annotation_jobs: Vec::new(),
+            paths_2d,
+            enabled: Vec::new(),
+        }
+    }
+
+    fn load_annotations(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        for path in std::mem::take(&mut self.annotations) {
+            let job = AnnotationLoadJob::spawn(
+                tokio_handle,
+                &self.shared,
+                path,
+                &self.gff_attr,
+            );
+            self.annotation_jobs.push(job);
+        }
+    }
+
+    fn enable_button(
+        &mut self,
+        ui: &mut egui::Ui,
+        name: &'static str,
+        hover: &str,
+    ) -> bool {
+        let enabled = self.enabled.contains(&name);
+        let label = if enabled {
+            format!("{name} (enabled)")
+        } else {
+            format!("Enable {name}")
+        };
+
+        let clicked = ui
+            .add_enabled(!enabled, egui::Button::new(label))
+            .on_hover_text(hover)
+            .clicked();
+
+        if clicked {
+            log::warn!("Safe mode: enabling {name}");
+            self.enabled.push(name);
+        }
+
+        clicked
+    }
+}
+
+impl SettingsWidget for SafeModeWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let resp = ui.vertical(|ui| {
+            ui.label(
+                "Started with --safe-mode. Enable the features one at a \
+                 time, and check the log for GPU errors after each.",
+            );
+
+            if self.enable_button(
+                ui,
+                "annotations",
+                &format!(
+                    "Loads the {} annotation files from the command line",
+                    self.annotations.len()
+                ),
+            ) {
+                self.load_annotations(&settings_ctx.tokio_handle);
+            }
+
+            for job in self.annotation_jobs.iter() {
+                job.show(ui);
+            }
+
+            if self.enable_button(
+                ui,
+                "2D path lines",
+                "Draws the paths that were shown in the 2D view",
+            ) {
+                let mut path_colors = self.shared.path_colors.blocking_write();
+                path_colors.shown_2d.append(&mut self.paths_2d);
+                path_colors.mark_changed();
+            }
+
+            if self.enable_button(
+                ui,
+                "2D view",
+                "Opens the 2D view, computing a layout if there is none",
+            ) {
+                let has_layout =
+                    self.shared.workspace.blocking_read().tsv_path().is_some();
+                settings_ctx.send_app_msg_task(if has_layout {
+                    AppMsg::InitViewer2D
+                } else {
+                    AppMsg::RunBuiltinLayout
+                });
+            }
+
+            ui.label(
+                "GPU sampling of the 1D path data is turned on with \
+                 \"Sample path data on the GPU\" in the \"1D Viewer\" tab.",
+            );
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
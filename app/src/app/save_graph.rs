@@ -0,0 +1,235 @@
+//! Saving the loaded graph as GFA, including the paths that were added
+//! to it after loading, with a header noting where it came from.
+
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::Result;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+
+use super::notify::{JobAction, JobDone};
+use super::provenance::{self, EventKind};
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{AppMsg, SharedState};
+
+/// The lines of the GFA header comment: when and by what the graph
+/// was saved, the source graph, with its hash if it has been hashed,
+/// and the paths that were added, by file.
+pub fn provenance_comments(shared: &SharedState) -> Vec<String> {
+    let workspace = shared.workspace.blocking_read();
+    let provenance = shared.provenance.blocking_read();
+
+    let mut lines = vec![format!(
+        "Saved by Waragraph {} at {} UTC",
+        env!("CARGO_PKG_VERSION"),
+        provenance::format_timestamp(provenance::unix_now())
+    )];
+
+    let source = workspace.gfa_path();
+    let hash = provenance
+        .events()
+        .iter()
+        .find(|e| e.path.as_ref() == Some(source))
+        .and_then(|e| e.sha256.as_ref());
+
+    lines.push(match hash {
+        Some(hash) => {
+            format!("Source graph: {} (SHA-256 {hash})", source.display())
+        }
+        None => format!("Source graph: {}", source.display()),
+    });
+
+    for (gaf, paths) in workspace.added_paths() {
+        lines.push(format!(
+            "Added {} paths from alignments in {}",
+            paths.len(),
+            gaf.display()
+        ));
+
+        for path in paths {
+            if let Some(name) = shared.graph.path_names.get_by_left(path) {
+                lines.push(format!("  {name}"));
+            }
+        }
+    }
+
+    lines
+}
+
+/// Writes the graph to `path` as GFA, and records the export.
+pub fn save_graph(
+    shared: &SharedState,
+    walks: bool,
+    path: &Path,
+) -> Result<()> {
+    let comments = provenance_comments(shared);
+
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    shared.graph.write_gfa(&comments, walks, &mut out)?;
+    out.flush()?;
+
+    log::warn!(
+        "Wrote graph with {} paths to {path:?}",
+        shared.graph.path_names.len()
+    );
+
+    shared.provenance.blocking_write().record_path(
+        EventKind::Export,
+        "Graph (GFA)",
+        path,
+    );
+
+    Ok(())
+}
+
+/// Settings window panel for saving the graph, with any added paths,
+/// as GFA.
+pub struct SaveGraphWidget {
+    shared: SharedState,
+    walks: bool,
+
+    export_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    result_recv: Option<oneshot::Receiver<Result<PathBuf>>>,
+    status: Option<String>,
+}
+
+impl SaveGraphWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            shared: shared.clone(),
+            walks: false,
+            export_path_recv: None,
+            result_recv: None,
+            status: None,
+        }
+    }
+
+    fn spawn_save(
+        &mut self,
+        tokio_handle: &tokio::runtime::Handle,
+        path: PathBuf,
+    ) {
+        let shared = self.shared.clone();
+        let walks = self.walks;
+
+        let (send, recv) = oneshot::channel();
+        let started = Instant::now();
+
+        tokio_handle.spawn_blocking(move || {
+            let result = save_graph(&shared, walks, &path).map(|_| path);
+
+            let done = match &result {
+                Ok(path) => JobDone::finished("Graph save", started)
+                    .with_detail(path.display().to_string())
+                    .with_action(JobAction::CopyPath(path.clone())),
+                Err(e) => JobDone::failed("Graph save", started, e),
+            };
+            if let Err(e) =
+                shared.app_msg_send.blocking_send(AppMsg::JobDone(done))
+            {
+                log::error!("{e:?}");
+            }
+
+            let _ = send.send(result);
+        });
+
+        self.result_recv = Some(recv);
+        self.status = None;
+    }
+
+    fn poll_channels(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        if let Some(mut recv) = self.export_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => self.spawn_save(tokio_handle, path),
+                Err(TryRecvError::Empty) => self.export_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.result_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok(path)) => {
+                    self.status = Some(format!("Wrote {}", path.display()));
+                }
+                Ok(Err(e)) => {
+                    log::error!("Error saving graph: {e:?}");
+                    self.status = Some(format!("Error: {e}"));
+                }
+                Err(TryRecvError::Empty) => self.result_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+impl SettingsWidget for SaveGraphWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels(&settings_ctx.tokio_handle);
+
+        let added = self
+            .shared
+            .workspace
+            .blocking_read()
+            .added_paths()
+            .iter()
+            .map(|(_, paths)| paths.len())
+            .sum::<usize>();
+
+        let resp = ui.vertical(|ui| {
+            if added > 0 {
+                ui.label(format!("{added} paths added since loading"));
+            } else {
+                ui.label("No paths added since loading");
+            }
+
+            ui.checkbox(&mut self.walks, "Write PanSN paths as W lines")
+                .on_hover_text(
+                    "Paths named `sample#haplotype#contig` are written \
+                     as walks; Waragraph only reads P lines",
+                );
+
+            let busy =
+                self.export_path_recv.is_some() || self.result_recv.is_some();
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!busy, egui::Button::new("Save GFA"))
+                    .on_hover_text(
+                        "Writes the segments, links and all paths, with \
+                         where the graph came from in the header",
+                    )
+                    .clicked()
+                {
+                    let path = self
+                        .shared
+                        .export_paths
+                        .blocking_read()
+                        .export_path("graph", None, "gfa");
+
+                    let id = egui::Id::new("SaveGraphWidget_export");
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.export_path_recv = Some(recv);
+                }
+
+                if self.result_recv.is_some() {
+                    ui.spinner();
+                }
+            });
+
+            if let Some(status) = self.status.as_ref() {
+                ui.label(status);
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
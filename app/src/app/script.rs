@@ -0,0 +1,660 @@
+//! Rhai scripting API for driving the viewers, e.g. to generate
+//! figures for a list of regions. Scripts run on a blocking thread;
+//! commands that need the viewer windows are sent to the app as
+//! `AppMsg::Script`, and applied in order.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use rhai::EvalAltResult;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::subgraph::Subgraph;
+use waragraph_core::graph::{Bp, Node, PathId};
+
+use crate::annotations::AnnotationSet;
+use crate::viewer_1d::control::path_range_to_pangenome;
+use crate::viewer_1d::subgraph_export::export_subgraph;
+
+use super::provenance::{self, EventKind};
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{App, AppMsg, AppType, SharedState, ViewState};
+
+type ScriptResult<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+#[derive(Debug, Clone)]
+pub enum ScriptCmd {
+    /// Show exactly the given pangenome range in the 1D viewer
+    SetRange1D(std::ops::Range<u64>),
+    /// Switch the data visualized in the 1D viewer
+    SetData1D(String),
+    /// Scroll the 1D path list so that the path at this index is at
+    /// the top
+    ScrollToPath1D(usize),
+    SetColorScheme {
+        data_key: String,
+        scheme: String,
+    },
+    /// Move the views of both viewers to the node
+    GotoNode(Node),
+    /// Move the 2D view to the nodes in the range of the path
+    GotoRegion2D {
+        path: PathId,
+        range: std::ops::Range<Bp>,
+    },
+    /// Export the 1D view, to `path` or to a path generated from the
+    /// export settings; the path is sent on `done` when it's written
+    Screenshot1D {
+        path: Option<PathBuf>,
+        done: crossbeam::channel::Sender<PathBuf>,
+    },
+}
+
+impl App {
+    pub(super) fn apply_script_cmd(
+        &mut self,
+        state: &raving_wgpu::State,
+        cmd: ScriptCmd,
+    ) -> Result<()> {
+        match cmd {
+            ScriptCmd::SetRange1D(new_range) => {
+                self.update_view_1d(|range, _, _| *range = new_range)
+            }
+            ScriptCmd::SetData1D(key) => {
+                self.update_view_1d(|_, data_key, _| *data_key = key)
+            }
+            ScriptCmd::ScrollToPath1D(ix) => {
+                self.update_view_1d(|_, _, path_offset| *path_offset = ix)
+            }
+            ScriptCmd::SetColorScheme { data_key, scheme } => {
+                self.shared.set_data_color_scheme(state, &data_key, &scheme)
+            }
+            ScriptCmd::GotoNode(node) => {
+                for app_ty in [AppType::Viewer1D, AppType::Viewer2D] {
+                    if let Some(app) = self.app_windows.app_mut(&app_ty) {
+                        app.goto_node(node);
+                    }
+                }
+                Ok(())
+            }
+            ScriptCmd::GotoRegion2D { path, range } => {
+                let app = self
+                    .app_windows
+                    .app_mut(&AppType::Viewer2D)
+                    .ok_or_else(|| anyhow!("No 2D viewer"))?;
+                app.goto_region(Some(path), range);
+                Ok(())
+            }
+            ScriptCmd::Screenshot1D { path, done } => {
+                let app = self
+                    .app_windows
+                    .apps
+                    .get_mut(&AppType::Viewer1D)
+                    .ok_or_else(|| {
+                        anyhow!("The 1D viewer window isn't open")
+                    })?;
+
+                anyhow::ensure!(
+                    app.app.request_image_export(path, done),
+                    "The 1D viewer can't export images"
+                );
+                Ok(())
+            }
+        }
+    }
+
+    fn update_view_1d(
+        &mut self,
+        f: impl FnOnce(&mut std::ops::Range<u64>, &mut String, &mut usize),
+    ) -> Result<()> {
+        let app = self
+            .app_windows
+            .app_mut(&AppType::Viewer1D)
+            .ok_or_else(|| anyhow!("No 1D viewer"))?;
+
+        let Some(ViewState::View1D {
+            mut range,
+            mut data_key,
+            mut path_offset,
+            annotation_slots,
+        }) = app.view_state()
+        else {
+            anyhow::bail!("No 1D view state");
+        };
+
+        f(&mut range, &mut data_key, &mut path_offset);
+
+        app.restore_view_state(&ViewState::View1D {
+            range,
+            data_key,
+            path_offset,
+            annotation_slots,
+        });
+
+        Ok(())
+    }
+}
+
+/// Creates an engine with the waragraph API:
+///
+/// - `pangenome_len()`, `path_names()`, `path_len(path)`
+/// - `set_range(start, end)`: show a pangenome range in the 1D viewer
+/// - `goto_path(path, start, end)`: show a range of a path
+/// - `set_data(key)`: visualize the data `key` in the 1D viewer
+/// - `scroll_to_path(path)`
+/// - `goto_node(node)`: move both viewers to the node
+/// - `center_2d(path, start, end)`: fly the 2D view to a range of a path
+/// - `select_path(path)`: select the path and its nodes,
+///   `deselect_path(path)`, `select_range(start, end)`: select a
+///   pangenome range, `clear_selection()`
+/// - `selected_paths()`, `selected_nodes()`
+/// - `set_solo(path, bool)`, `set_mute(path, bool)`,
+///   `clear_solo_mute()`: solo and mute paths in the 1D path list;
+///   muting takes precedence. `shown_paths()` lists the paths in it
+/// - `load_annotations(file)`, `load_annotations(file, gff_attr)`
+/// - `set_color_scheme(data_key, scheme)`, `set_high_contrast(bool)`
+/// - `screenshot()`, `screenshot(file)`: export the 1D view, waiting
+///   until it's written; returns the path
+/// - `export_subgraph(file)`, `export_subgraph(file, start, end)`:
+///   write the subgraph of the selection, or of a pangenome range, as
+///   GFA; returns the number of nodes
+/// - `get_config(key)`, `set_config(key, value)`: read and change the
+///   viewer settings, e.g. `viewer_2d.show_minimap`; values of the
+///   wrong type or out of range are errors
+pub fn create_engine(shared: &SharedState) -> rhai::Engine {
+    let mut engine = rhai::Engine::new();
+
+    engine.on_print(|s| log::warn!("[script] {s}"));
+
+    let s = shared.clone();
+    engine
+        .register_fn("pangenome_len", move || s.graph.pangenome_len().0 as i64);
+
+    let s = shared.clone();
+    engine.register_fn("path_names", move || {
+        let graph = &s.graph;
+        (0..graph.path_names.len())
+            .filter_map(|ix| {
+                graph.path_names.get_by_left(&PathId::from(ix as u32))
+            })
+            .map(|name| rhai::Dynamic::from(name.clone()))
+            .collect::<rhai::Array>()
+    });
+
+    let s = shared.clone();
+    engine.register_fn("path_len", move |name: &str| -> ScriptResult<i64> {
+        let path = path_id(&s, name)?;
+        let len = s.graph.path_len(path).unwrap_or_default();
+        Ok(len.0 as i64)
+    });
+
+    let s = shared.clone();
+    engine.register_fn(
+        "set_range",
+        move |start: i64, end: i64| -> ScriptResult<()> {
+            let range = bp(start)?..bp(end)?;
+            if range.is_empty() || range.end > s.graph.pangenome_len().0 {
+                return Err(format!("Invalid range {start}-{end}").into());
+            }
+            send(&s, ScriptCmd::SetRange1D(range))
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn(
+        "goto_path",
+        move |name: &str, start: i64, end: i64| -> ScriptResult<()> {
+            let path = path_id(&s, name)?;
+            let range = Bp(bp(start)?)..Bp(bp(end)?);
+            let range = path_range_to_pangenome(&s.graph, path, range)
+                .ok_or_else(|| {
+                    format!("Range {start}-{end} isn't on path `{name}`")
+                })?;
+            send(&s, ScriptCmd::SetRange1D(range.start.0..range.end.0))
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn("set_data", move |key: &str| -> ScriptResult<()> {
+        send(&s, ScriptCmd::SetData1D(key.to_string()))
+    });
+
+    let s = shared.clone();
+    engine.register_fn(
+        "scroll_to_path",
+        move |name: &str| -> ScriptResult<()> {
+            let path = path_id(&s, name)?;
+            send(&s, ScriptCmd::ScrollToPath1D(path.ix()))
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn("goto_node", move |node: i64| -> ScriptResult<()> {
+        let node = usize::try_from(node)
+            .ok()
+            .filter(|&n| n < s.graph.node_count)
+            .ok_or_else(|| format!("Invalid node {node}"))?;
+        send(&s, ScriptCmd::GotoNode(Node::from(node)))
+    });
+
+    let s = shared.clone();
+    engine.register_fn(
+        "center_2d",
+        move |name: &str, start: i64, end: i64| -> ScriptResult<()> {
+            let path = path_id(&s, name)?;
+            let range = Bp(bp(start)?)..Bp(bp(end)?);
+            send(&s, ScriptCmd::GotoRegion2D { path, range })
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn("select_path", move |name: &str| -> ScriptResult<()> {
+        let path = path_id(&s, name)?;
+        let mut selection = s.selection.blocking_write();
+        selection.set_path_selected(path, true);
+        for step in s.graph.path_steps[path.ix()].iter() {
+            if !selection.contains(step.node()) {
+                selection.toggle(step.node());
+            }
+        }
+        Ok(())
+    });
+
+    let s = shared.clone();
+    engine.register_fn(
+        "deselect_path",
+        move |name: &str| -> ScriptResult<()> {
+            let path = path_id(&s, name)?;
+            s.selection.blocking_write().set_path_selected(path, false);
+            Ok(())
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn(
+        "select_range",
+        move |start: i64, end: i64| -> ScriptResult<()> {
+            let range = Bp(bp(start)?)..Bp(bp(end)?);
+            if range.is_empty() || range.end > s.graph.pangenome_len() {
+                return Err(format!("Invalid range {start}-{end}").into());
+            }
+            s.selection.blocking_write().add_range(range);
+            Ok(())
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn("selected_paths", move || {
+        let selection = s.selection.blocking_read();
+        selection
+            .paths()
+            .filter_map(|path| s.graph.path_names.get_by_left(&path))
+            .map(|name| rhai::Dynamic::from(name.clone()))
+            .collect::<rhai::Array>()
+    });
+
+    let s = shared.clone();
+    engine.register_fn("selected_nodes", move || {
+        let selection = s.selection.blocking_read();
+        selection
+            .nodes()
+            .map(|node| rhai::Dynamic::from(node.ix() as i64))
+            .collect::<rhai::Array>()
+    });
+
+    let s = shared.clone();
+    engine.register_fn("clear_selection", move || {
+        s.selection.blocking_write().clear();
+    });
+
+    let s = shared.clone();
+    engine.register_fn(
+        "set_solo",
+        move |name: &str, soloed: bool| -> ScriptResult<()> {
+            path_id(&s, name)?;
+            let mut path_colors = s.path_colors.blocking_write();
+            path_colors.set_soloed(name, soloed);
+            path_colors.mark_changed();
+            Ok(())
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn(
+        "set_mute",
+        move |name: &str, muted: bool| -> ScriptResult<()> {
+            path_id(&s, name)?;
+            let mut path_colors = s.path_colors.blocking_write();
+            path_colors.set_muted(name, muted);
+            path_colors.mark_changed();
+            Ok(())
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn("clear_solo_mute", move || {
+        let mut path_colors = s.path_colors.blocking_write();
+        path_colors.clear_solo_mute();
+        path_colors.mark_changed();
+    });
+
+    let s = shared.clone();
+    engine.register_fn("shown_paths", move || {
+        let path_colors = s.path_colors.blocking_read();
+        s.graph
+            .path_names
+            .right_values()
+            .filter(|name| path_colors.shown_1d(name))
+            .map(|name| rhai::Dynamic::from(name.clone()))
+            .collect::<rhai::Array>()
+    });
+
+    let s = shared.clone();
+    engine.register_fn("load_annotations", move |file: &str| {
+        load_annotations(&s, Path::new(file), "Name")
+    });
+
+    let s = shared.clone();
+    engine.register_fn(
+        "load_annotations",
+        move |file: &str, gff_attr: &str| {
+            load_annotations(&s, Path::new(file), gff_attr)
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn(
+        "set_color_scheme",
+        move |data_key: &str, scheme: &str| -> ScriptResult<()> {
+            let cmd = ScriptCmd::SetColorScheme {
+                data_key: data_key.to_string(),
+                scheme: scheme.to_string(),
+            };
+            send(&s, cmd)
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn("set_high_contrast", move |high_contrast: bool| {
+        let mut theme = s.theme.load();
+        theme.high_contrast = high_contrast;
+        s.theme.store(theme);
+    });
+
+    let s = shared.clone();
+    engine.register_fn("screenshot", move || screenshot(&s, None));
+
+    let s = shared.clone();
+    engine.register_fn("screenshot", move |file: &str| {
+        screenshot(&s, Some(PathBuf::from(file)))
+    });
+
+    let s = shared.clone();
+    engine.register_fn(
+        "export_subgraph",
+        move |file: &str| -> ScriptResult<i64> {
+            let subgraph = {
+                let selection = s.selection.blocking_read();
+                if selection.is_empty() {
+                    return Err("No nodes are selected".into());
+                }
+                Subgraph::from_nodes(&s.graph, selection.nodes())
+            };
+            write_subgraph(&s, &subgraph, file)
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn(
+        "export_subgraph",
+        move |file: &str, start: i64, end: i64| -> ScriptResult<i64> {
+            let range = Bp(bp(start)?)..Bp(bp(end)?);
+            if range.is_empty() || range.end > s.graph.pangenome_len() {
+                return Err(format!("Invalid range {start}-{end}").into());
+            }
+            let subgraph = Subgraph::from_pangenome_range(&s.graph, range);
+            write_subgraph(&s, &subgraph, file)
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn(
+        "get_config",
+        move |key: &str| -> ScriptResult<rhai::Dynamic> {
+            s.config
+                .get(key)
+                .ok_or_else(|| format!("Unknown config key `{key}`").into())
+        },
+    );
+
+    let s = shared.clone();
+    engine.register_fn(
+        "set_config",
+        move |key: &str, val: rhai::Dynamic| -> ScriptResult<()> {
+            s.config.set(key, val)?;
+            Ok(())
+        },
+    );
+
+    engine
+}
+
+/// Runs the script; call from a blocking thread, since the functions
+/// wait on the app.
+pub fn run_script(shared: &SharedState, script: &str) -> Result<()> {
+    let engine = create_engine(shared);
+    engine.run(script).map_err(|e| anyhow!("{e}"))
+}
+
+/// Runs the script file in the background, e.g. for `--script`.
+pub fn spawn_script_file(
+    tokio_handle: &tokio::runtime::Handle,
+    shared: &SharedState,
+    path: PathBuf,
+) {
+    provenance::ProvenanceLog::record_file_hashed(
+        &shared.provenance,
+        tokio_handle,
+        EventKind::Load,
+        "Script",
+        &path,
+    );
+
+    let shared = shared.clone();
+    tokio_handle.spawn_blocking(move || {
+        let result = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|script| run_script(&shared, &script));
+
+        match result {
+            Ok(()) => log::warn!("Finished script {path:?}"),
+            Err(e) => log::error!("Error running script {path:?}: {e}"),
+        }
+    });
+}
+
+fn bp(v: i64) -> ScriptResult<u64> {
+    u64::try_from(v).map_err(|_| format!("Invalid position {v}").into())
+}
+
+fn path_id(shared: &SharedState, name: &str) -> ScriptResult<PathId> {
+    shared
+        .graph
+        .path_names
+        .get_by_right(name)
+        .copied()
+        .ok_or_else(|| format!("Unknown path `{name}`").into())
+}
+
+fn send(shared: &SharedState, cmd: ScriptCmd) -> ScriptResult<()> {
+    shared
+        .app_msg_send
+        .blocking_send(AppMsg::Script(cmd))
+        .map_err(|e| e.to_string().into())
+}
+
+fn load_annotations(
+    shared: &SharedState,
+    file: &Path,
+    gff_attr: &str,
+) -> ScriptResult<i64> {
+    let set = AnnotationSet::from_file(&shared.graph, gff_attr, file)
+        .map_err(|e| format!("Error loading {file:?}: {e}"))?;
+
+    let count = set.annotations.len();
+    log::warn!(
+        "loaded annotation set with {count} annotations \
+         ({} out of path bounds)",
+        set.out_of_bounds
+    );
+
+    shared.annotations.blocking_write().insert_set(set);
+
+    provenance::ProvenanceLog::record_file_hashed(
+        &shared.provenance,
+        &tokio::runtime::Handle::current(),
+        EventKind::Load,
+        "Annotations",
+        file,
+    );
+
+    Ok(count as i64)
+}
+
+fn write_subgraph(
+    shared: &SharedState,
+    subgraph: &Subgraph,
+    file: &str,
+) -> ScriptResult<i64> {
+    export_subgraph(shared, subgraph, Path::new(file))
+        .map_err(|e| format!("Error writing {file}: {e}"))?;
+    Ok(subgraph.node_count() as i64)
+}
+
+fn screenshot(
+    shared: &SharedState,
+    path: Option<PathBuf>,
+) -> ScriptResult<String> {
+    let (done, recv) = crossbeam::channel::bounded(1);
+    send(shared, ScriptCmd::Screenshot1D { path, done })?;
+
+    // the export waits for the data of the current view to be sampled
+    let path = recv.recv_timeout(Duration::from_secs(120)).map_err(|e| {
+        format!(
+            "Screenshot failed ({e}); without an export directory, \
+                 pass a file name"
+        )
+    })?;
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Settings window panel for running scripts.
+pub struct ScriptWidget {
+    shared: SharedState,
+
+    script: String,
+
+    run_recv: Option<oneshot::Receiver<Result<()>>>,
+    open_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    error: Option<String>,
+}
+
+impl ScriptWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            shared: shared.clone(),
+            script: String::new(),
+            run_recv: None,
+            open_path_recv: None,
+            error: None,
+        }
+    }
+
+    fn spawn_script(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        let shared = self.shared.clone();
+        let script = self.script.clone();
+
+        let (send, recv) = oneshot::channel();
+
+        tokio_handle.spawn_blocking(move || {
+            let _ = send.send(run_script(&shared, &script));
+        });
+
+        self.run_recv = Some(recv);
+        self.error = None;
+    }
+
+    fn poll_channels(&mut self) {
+        if let Some(mut recv) = self.run_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => self.error = Some(e.to_string()),
+                Err(TryRecvError::Empty) => self.run_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.open_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => match std::fs::read_to_string(&path) {
+                    Ok(script) => self.script = script,
+                    Err(e) => self.error = Some(format!("{path:?}: {e}")),
+                },
+                Err(TryRecvError::Empty) => self.open_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+impl SettingsWidget for ScriptWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels();
+
+        let resp = ui.vertical(|ui| {
+            ui.add(
+                egui::TextEdit::multiline(&mut self.script)
+                    .code_editor()
+                    .desired_rows(12)
+                    .hint_text("set_range(0, 10000);\nscreenshot();"),
+            );
+
+            ui.horizontal(|ui| {
+                let running = self.run_recv.is_some();
+
+                if ui.add_enabled(!running, egui::Button::new("Run")).clicked()
+                {
+                    self.spawn_script(&settings_ctx.tokio_handle);
+                }
+
+                if ui.button("Open").clicked() {
+                    let mut files = egui_file::FileDialog::open_file(None);
+                    files.open();
+
+                    let id = egui::Id::new("ScriptWidget_open");
+                    let recv = settings_ctx.with_file_dialog_oneshot(id, files);
+                    self.open_path_recv = Some(recv);
+                }
+
+                if running {
+                    ui.spinner();
+                }
+            });
+
+            if let Some(err) = self.error.as_ref() {
+                ui.label(format!("Error: {err}"));
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Selection shared by the viewer windows and scripts: nodes, paths,
+//! and pangenome ranges selected in one view are highlighted in the
+//! others.
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use waragraph_core::graph::{Bp, Node, PathId};
+
+#[derive(Debug, Default, Clone)]
+pub struct SelectionStore {
+    nodes: BTreeSet<Node>,
+    // the most recently selected node, which the 2D viewer shows the
+    // details of
+    primary: Option<Node>,
+
+    paths: BTreeSet<PathId>,
+    ranges: Vec<Range<Bp>>,
+
+    generation: u64,
+}
+
+impl SelectionStore {
+    /// Incremented on every change, so that views can tell when to
+    /// rebuild what they draw from the selection.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = Node> + '_ {
+        self.nodes.iter().copied()
+    }
+
+    pub fn primary(&self) -> Option<Node> {
+        self.primary
+    }
+
+    pub fn contains(&self, node: Node) -> bool {
+        self.nodes.contains(&node)
+    }
+
+    /// `true` if no nodes are selected, whether or not there are
+    /// selected paths or ranges.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Replaces the selection with `node`, or clears it if `None`.
+    pub fn select_only(&mut self, node: Option<Node>) {
+        self.nodes.clear();
+        self.nodes.extend(node);
+        self.primary = node;
+        self.generation += 1;
+    }
+
+    /// Adds the node to the selection, or removes it if it's already
+    /// selected.
+    pub fn toggle(&mut self, node: Node) {
+        if self.nodes.remove(&node) {
+            self.deselected(node);
+        } else {
+            self.nodes.insert(node);
+            self.primary = Some(node);
+        }
+        self.generation += 1;
+    }
+
+    pub fn deselect(&mut self, node: Node) {
+        if self.nodes.remove(&node) {
+            self.deselected(node);
+            self.generation += 1;
+        }
+    }
+
+    /// Replaces the selection with the nodes; the last one becomes
+    /// the primary node.
+    pub fn select_all(&mut self, nodes: impl IntoIterator<Item = Node>) {
+        self.nodes.clear();
+        self.primary = None;
+        for node in nodes {
+            self.nodes.insert(node);
+            self.primary = Some(node);
+        }
+        self.generation += 1;
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = PathId> + '_ {
+        self.paths.iter().copied()
+    }
+
+    pub fn contains_path(&self, path: PathId) -> bool {
+        self.paths.contains(&path)
+    }
+
+    pub fn set_path_selected(&mut self, path: PathId, selected: bool) {
+        let changed = if selected {
+            self.paths.insert(path)
+        } else {
+            self.paths.remove(&path)
+        };
+
+        if changed {
+            self.generation += 1;
+        }
+    }
+
+    pub fn toggle_path(&mut self, path: PathId) {
+        let selected = self.contains_path(path);
+        self.set_path_selected(path, !selected);
+    }
+
+    /// The selected pangenome ranges, in the order they were added.
+    pub fn ranges(&self) -> &[Range<Bp>] {
+        &self.ranges
+    }
+
+    pub fn add_range(&mut self, range: Range<Bp>) {
+        if !range.is_empty() && !self.ranges.contains(&range) {
+            self.ranges.push(range);
+            self.generation += 1;
+        }
+    }
+
+    pub fn remove_range(&mut self, range: &Range<Bp>) {
+        let len = self.ranges.len();
+        self.ranges.retain(|r| r != range);
+        if self.ranges.len() != len {
+            self.generation += 1;
+        }
+    }
+
+    /// Replaces the selected ranges with `range`, or clears them if
+    /// `None`.
+    pub fn select_range_only(&mut self, range: Option<Range<Bp>>) {
+        self.ranges.clear();
+        self.ranges.extend(range.filter(|r| !r.is_empty()));
+        self.generation += 1;
+    }
+
+    /// Clears the selected nodes, paths, and ranges.
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.ranges.clear();
+        self.select_only(None);
+    }
+
+    fn deselected(&mut self, node: Node) {
+        if self.primary == Some(node) {
+            self.primary = self.nodes.iter().next_back().copied();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_keeps_primary_selected() {
+        let mut sel = SelectionStore::default();
+
+        sel.select_only(Some(Node::from(3u32)));
+        sel.toggle(Node::from(1u32));
+        sel.toggle(Node::from(5u32));
+        assert_eq!(sel.primary(), Some(Node::from(5u32)));
+        assert_eq!(sel.nodes().count(), 3);
+
+        sel.toggle(Node::from(5u32));
+        assert!(!sel.contains(Node::from(5u32)));
+        assert_eq!(sel.primary(), Some(Node::from(3u32)));
+
+        sel.deselect(Node::from(3u32));
+        sel.deselect(Node::from(1u32));
+        assert!(sel.is_empty());
+        assert_eq!(sel.primary(), None);
+
+        sel.toggle(Node::from(2u32));
+        sel.select_only(None);
+        assert!(sel.is_empty());
+        assert_eq!(sel.primary(), None);
+    }
+
+    #[test]
+    fn paths_and_ranges_bump_generation() {
+        let mut sel = SelectionStore::default();
+        let path = PathId::from(1u32);
+
+        sel.toggle_path(path);
+        assert!(sel.contains_path(path));
+        let gen = sel.generation();
+
+        // no change
+        sel.set_path_selected(path, true);
+        sel.add_range(Bp(10)..Bp(10));
+        assert_eq!(sel.generation(), gen);
+
+        sel.add_range(Bp(10)..Bp(20));
+        sel.add_range(Bp(10)..Bp(20));
+        assert_eq!(sel.ranges(), &[Bp(10)..Bp(20)]);
+        assert_eq!(sel.generation(), gen + 1);
+
+        sel.select_only(Some(Node::from(3u32)));
+        sel.clear();
+        assert!(sel.is_empty());
+        assert_eq!(sel.paths().count(), 0);
+        assert!(sel.ranges().is_empty());
+        assert!(sel.generation() > gen + 1);
+    }
+}
@@ -0,0 +1,581 @@
+//! Session files, which list one or more graphs, each with its layout,
+//! annotation files, views, color schemes and bookmarks, along with
+//! the display settings and the provenance log.
+//!
+//! Only one graph is loaded at a time; switching to another graph in
+//! the session restarts the app with that graph.
+
+use std::collections::BTreeMap;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, RwLock};
+
+use crate::viewer_1d::bookmarks::Bookmark;
+
+use super::provenance::{EventKind, ProvenanceLog};
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{App, AppMsg, Args, SharedState};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphEntry {
+    pub gfa: PathBuf,
+    #[serde(default)]
+    pub layout: Option<PathBuf>,
+    #[serde(default)]
+    pub annotations: Vec<PathBuf>,
+    /// GAF files of sequences aligned to the graph, each added as a
+    /// path when the graph is loaded
+    #[serde(default)]
+    pub aligned: Vec<PathBuf>,
+    /// The views of the graph, as a `waragraph://view?...` link
+    #[serde(default)]
+    pub view_link: Option<String>,
+    /// The color scheme used for each data source, by name
+    #[serde(default)]
+    pub color_schemes: BTreeMap<String, String>,
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    /// The selected nodes, by GFA segment ID
+    #[serde(default)]
+    pub selection: Vec<u64>,
+    /// The paths soloed and muted in the 1D path list, by name
+    #[serde(default)]
+    pub soloed_paths: Vec<String>,
+    #[serde(default)]
+    pub muted_paths: Vec<String>,
+}
+
+impl GraphEntry {
+    pub fn new(gfa: PathBuf) -> Self {
+        Self {
+            gfa,
+            layout: None,
+            annotations: Vec::new(),
+            aligned: Vec::new(),
+            view_link: None,
+            color_schemes: BTreeMap::new(),
+            bookmarks: Vec::new(),
+            selection: Vec::new(),
+            soloed_paths: Vec::new(),
+            muted_paths: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.gfa
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.gfa.to_string_lossy().into_owned())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub graphs: Vec<GraphEntry>,
+    /// Index of the graph that is loaded
+    #[serde(default)]
+    pub active: usize,
+    #[serde(default)]
+    pub high_contrast: bool,
+}
+
+/// A session as written to a file, with the provenance log
+#[derive(Serialize)]
+struct SessionFile<'a> {
+    #[serde(flatten)]
+    session: &'a Session,
+    /// The active graph's views, as written by earlier versions
+    view_link: Option<&'a str>,
+    provenance: &'a ProvenanceLog,
+}
+
+impl Session {
+    pub fn single(graph: GraphEntry) -> Self {
+        Self {
+            graphs: vec![graph],
+            active: 0,
+            high_contrast: false,
+        }
+    }
+
+    /// The session given with `--session`, or one with just the
+    /// graph, with the files given on the command line.
+    pub fn for_args(args: &Args) -> Self {
+        let mut session = args.session.clone().unwrap_or_else(|| {
+            Self::single(GraphEntry::new(args.graph_path().to_path_buf()))
+        });
+        let entry = session.active_graph_mut();
+        entry.layout = args.tsv.clone();
+        entry.annotations = args.annotations.clone();
+        entry.aligned = args.aligned.clone();
+        session
+    }
+
+    pub fn active_graph(&self) -> &GraphEntry {
+        &self.graphs[self.active]
+    }
+
+    pub fn active_graph_mut(&mut self) -> &mut GraphEntry {
+        &mut self.graphs[self.active]
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut session: Self = serde_json::from_str(text)?;
+
+        anyhow::ensure!(
+            !session.graphs.is_empty(),
+            "Session doesn't list any graphs"
+        );
+        session.active = session.active.min(session.graphs.len() - 1);
+
+        Ok(session)
+    }
+
+    pub fn write(
+        &self,
+        mut out: impl Write,
+        provenance: &ProvenanceLog,
+    ) -> std::io::Result<()> {
+        let file = SessionFile {
+            session: self,
+            view_link: self.active_graph().view_link.as_deref(),
+            provenance,
+        };
+        serde_json::to_writer_pretty(&mut out, &file)?;
+        writeln!(out)
+    }
+
+    pub fn save(
+        &self,
+        path: impl AsRef<Path>,
+        provenance: &ProvenanceLog,
+    ) -> Result<()> {
+        let file = std::fs::File::create(path)?;
+        let mut out = std::io::BufWriter::new(file);
+        self.write(&mut out, provenance)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Applies the active graph's color schemes, bookmarks, selection
+    /// and soloed and muted paths, and the display settings, to the
+    /// newly loaded graph.
+    pub fn restore(&self, state: &raving_wgpu::State, shared: &SharedState) {
+        let entry = self.active_graph();
+
+        for (data_key, scheme) in entry.color_schemes.iter() {
+            if let Err(e) =
+                shared.set_data_color_scheme(state, data_key, scheme)
+            {
+                log::error!("Error restoring color scheme: {e:?}");
+            }
+        }
+
+        let mut bookmarks = shared.bookmarks.blocking_write();
+        for bookmark in entry.bookmarks.iter() {
+            if !bookmarks.bookmarks().contains(bookmark) {
+                bookmarks.add(bookmark.clone());
+            }
+        }
+
+        shared.selection.blocking_write().select_all(
+            entry
+                .selection
+                .iter()
+                .filter_map(|&id| shared.graph.segment_node(id)),
+        );
+
+        {
+            let mut path_colors = shared.path_colors.blocking_write();
+            for name in entry.soloed_paths.iter() {
+                path_colors.set_soloed(name, true);
+            }
+            for name in entry.muted_paths.iter() {
+                path_colors.set_muted(name, true);
+            }
+            path_colors.mark_changed();
+        }
+
+        shared.theme.store(crate::gui::theme::Theme {
+            high_contrast: self.high_contrast,
+        });
+    }
+}
+
+impl App {
+    /// Restarts the app with the graph at index `ix` in the session,
+    /// after storing the current graph's state in the session.
+    pub(super) fn switch_graph(&mut self, ix: usize) -> Result<()> {
+        // the user's session file is only written by "Save session"
+        let path = temp_session_path();
+
+        self.sync_session();
+
+        let gfa = {
+            let mut session = self.session.blocking_write();
+            let Some(graph) = session.graphs.get(ix) else {
+                anyhow::bail!("No graph {ix} in session");
+            };
+            let gfa = graph.gfa.clone();
+            session.active = ix;
+            gfa
+        };
+
+        self.save_session(&path)?;
+
+        log::warn!("Switching to graph {gfa:?}");
+        self.restart_with_session(&path)
+    }
+
+    /// Restarts the app with the session in the file.
+    pub(super) fn open_session(&mut self, path: &Path) -> Result<()> {
+        // fail early, rather than in the new process
+        let session = Session::load(path)?;
+        let gfa = &session.active_graph().gfa;
+
+        log::warn!("Opening session {path:?} with graph {gfa:?}");
+        self.restart_with_session(path)
+    }
+
+    /// Stores the current views, layout, annotation files, color
+    /// schemes and bookmarks of the loaded graph in the session.
+    pub(super) fn sync_session(&self) {
+        let link = self.create_view_link().to_uri();
+        let layout = self.shared.workspace.blocking_read().tsv_path.clone();
+
+        let annotations = self
+            .shared
+            .annotations
+            .blocking_read()
+            .annotation_sets
+            .values()
+            .filter_map(|set| set.source.clone())
+            .collect::<Vec<_>>();
+
+        let color_schemes = self.shared.data_color_scheme_names();
+
+        let bookmarks =
+            self.shared.bookmarks.blocking_read().bookmarks().to_vec();
+
+        let selection = self
+            .shared
+            .selection
+            .blocking_read()
+            .nodes()
+            .map(|node| self.shared.graph.segment_id(node))
+            .collect::<Vec<_>>();
+
+        let (soloed_paths, muted_paths) = {
+            let path_colors = self.shared.path_colors.blocking_read();
+            let names = |names: &std::collections::BTreeSet<String>| {
+                names.iter().cloned().collect::<Vec<_>>()
+            };
+            (names(&path_colors.soloed), names(&path_colors.muted))
+        };
+
+        let mut session = self.session.blocking_write();
+        session.high_contrast = self.shared.theme.load().high_contrast;
+
+        let entry = session.active_graph_mut();
+        entry.view_link = Some(link);
+        entry.layout = layout;
+        entry.annotations = annotations;
+        entry.color_schemes = color_schemes;
+        entry.bookmarks = bookmarks;
+        entry.selection = selection;
+        entry.soloed_paths = soloed_paths;
+        entry.muted_paths = muted_paths;
+    }
+
+    /// Starts a new process with the session in the file, and the
+    /// options this one was started with, and exits this one.
+    fn restart_with_session(&mut self, path: &Path) -> Result<()> {
+        std::process::Command::new(std::env::current_exe()?)
+            .args(&self.restart_args)
+            .arg("--session")
+            .arg(path)
+            .spawn()?;
+
+        self.exit_requested = true;
+        Ok(())
+    }
+
+    pub(super) fn save_session(&self, path: &Path) -> Result<()> {
+        let mut log = self.shared.provenance.blocking_write();
+        log.record_path(EventKind::Export, "Session", path);
+
+        self.session.blocking_read().save(path, &log)?;
+        log::warn!("Wrote session to {path:?}");
+
+        Ok(())
+    }
+}
+
+const TEMP_SESSION_PREFIX: &str = "waragraph-session-";
+
+/// A new file in the temporary directory, for the session a switch
+/// to another graph restarts with.
+pub fn temp_session_path() -> PathBuf {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let n = COUNT.fetch_add(1, Ordering::Relaxed);
+    let pid = std::process::id();
+    std::env::temp_dir().join(format!("{TEMP_SESSION_PREFIX}{pid}-{n}.json"))
+}
+
+/// Whether `path` was created by `temp_session_path`, in which case
+/// the restarted process removes it once the session is loaded.
+pub fn is_temp_session_path(path: &Path) -> bool {
+    let in_temp_dir = path.parent() == Some(std::env::temp_dir().as_path());
+    let is_temp_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map_or(false, |name| name.starts_with(TEMP_SESSION_PREFIX));
+    in_temp_dir && is_temp_name
+}
+
+/// Settings window panel listing the graphs in the session, for
+/// switching between them, and for saving and opening sessions.
+pub struct SessionWidget {
+    session: Arc<RwLock<Session>>,
+
+    gfa_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    save_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    open_path_recv: Option<oneshot::Receiver<PathBuf>>,
+}
+
+impl SessionWidget {
+    pub fn new(session: Arc<RwLock<Session>>) -> Self {
+        Self {
+            session,
+            gfa_path_recv: None,
+            save_path_recv: None,
+            open_path_recv: None,
+        }
+    }
+}
+
+impl SettingsWidget for SessionWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        use oneshot::error::TryRecvError;
+
+        let mut session = self.session.blocking_write();
+
+        if let Some(mut recv) = self.gfa_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => session.graphs.push(GraphEntry::new(path)),
+                Err(TryRecvError::Empty) => self.gfa_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.save_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    settings_ctx.send_app_msg_task(AppMsg::ExportSession(path))
+                }
+                Err(TryRecvError::Empty) => self.save_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.open_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    settings_ctx.send_app_msg_task(AppMsg::OpenSession(path))
+                }
+                Err(TryRecvError::Empty) => self.open_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        let resp = ui.vertical(|ui| {
+            let mut remove = None;
+
+            egui::Grid::new("SessionWidget-graphs").striped(true).show(
+                ui,
+                |ui| {
+                    for (i, graph) in session.graphs.iter().enumerate() {
+                        ui.label(graph.name())
+                            .on_hover_text(graph.gfa.to_string_lossy());
+
+                        if i == session.active {
+                            ui.label("(loaded)");
+                            ui.label("");
+                        } else {
+                            if ui
+                                .button("Switch")
+                                .on_hover_text(
+                                    "Restarts with this graph, keeping \
+                                     the views of the current one",
+                                )
+                                .clicked()
+                            {
+                                settings_ctx
+                                    .send_app_msg_task(AppMsg::SwitchGraph(i));
+                            }
+
+                            if ui.button("Remove").clicked() {
+                                remove = Some(i);
+                            }
+                        }
+                        ui.end_row();
+                    }
+                },
+            );
+
+            if let Some(i) = remove {
+                session.graphs.remove(i);
+                if session.active > i {
+                    session.active -= 1;
+                }
+            }
+
+            if ui.button("Add graph").clicked() {
+                let mut files = egui_file::FileDialog::open_file(None);
+                files.open();
+
+                let id = egui::Id::new("SessionWidget_add");
+                let recv = settings_ctx.with_file_dialog_oneshot(id, files);
+                self.gfa_path_recv = Some(recv);
+            }
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Save session")
+                    .on_hover_text(
+                        "Saves the graphs, views, color schemes, \
+                         bookmarks and display settings as JSON",
+                    )
+                    .clicked()
+                {
+                    let id = egui::Id::new("SessionWidget_save");
+                    let recv = settings_ctx.save_file_oneshot(id, None);
+                    self.save_path_recv = Some(recv);
+                }
+
+                if ui
+                    .button("Open session")
+                    .on_hover_text("Restarts with the session in the file")
+                    .clicked()
+                {
+                    let mut files = egui_file::FileDialog::open_file(None);
+                    files.open();
+
+                    let id = egui::Id::new("SessionWidget_open");
+                    let recv = settings_ctx.with_file_dialog_oneshot(id, files);
+                    self.open_path_recv = Some(recv);
+                }
+            });
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::provenance::EventKind;
+    use waragraph_core::graph::Bp;
+
+    #[test]
+    fn session_roundtrip() {
+        let mut first = GraphEntry::new("/data/a \"one\".gfa".into());
+        first.layout = Some("/data/a.tsv".into());
+        first.annotations = vec!["genes.bed".into(), "repeats.gff".into()];
+        first.aligned = vec!["sample.gaf".into()];
+        first.view_link = Some("waragraph://view?1d=0-100&paths=2".into());
+        first.color_schemes = [
+            ("depth".into(), "spectral".into()),
+            ("strand".into(), "black_red".into()),
+        ]
+        .into();
+        first.bookmarks = vec![
+            Bookmark {
+                name: "gene \"A\"".into(),
+                path: Some("chr1".into()),
+                range: Bp(10)..Bp(200),
+            },
+            Bookmark {
+                name: "all".into(),
+                path: None,
+                range: Bp(0)..Bp(1000),
+            },
+        ];
+        first.selection = vec![3, 17, 4096];
+        first.soloed_paths = vec!["HG002#1#chr20".into(), "a \"b\"".into()];
+        first.muted_paths = vec!["grch38".into()];
+
+        let session = Session {
+            graphs: vec![first, GraphEntry::new("b.gfa".into())],
+            active: 1,
+            high_contrast: true,
+        };
+
+        let mut log = ProvenanceLog::default();
+        log.record(EventKind::Filter, "something");
+
+        let mut out = Vec::new();
+        session.write(&mut out, &log).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        let parsed = Session::parse(&text).unwrap();
+        assert_eq!(parsed, session);
+    }
+
+    #[test]
+    fn sessions_need_graphs() {
+        assert!(Session::parse("{\"graphs\": []}").is_err());
+        assert!(Session::parse("{\"view_link\": null}").is_err());
+
+        let session = Session::parse(
+            "{\"graphs\": [{\"gfa\": \"x.gfa\"}], \"active\": 4}",
+        )
+        .unwrap();
+        assert_eq!(session.active, 0);
+        assert_eq!(session.active_graph().gfa, PathBuf::from("x.gfa"));
+    }
+
+    #[test]
+    fn deeply_nested_sessions_are_rejected() {
+        let text = format!(
+            "{{\"graphs\": [{{\"gfa\": \"x.gfa\", \"layout\": {}}}]}}",
+            "[".repeat(100_000)
+        );
+        assert!(Session::parse(&text).is_err());
+    }
+
+    #[test]
+    fn temp_sessions_are_recognized() {
+        let path = temp_session_path();
+        assert_ne!(path, temp_session_path());
+        assert!(is_temp_session_path(&path));
+
+        let name = path.file_name().unwrap();
+        assert!(!is_temp_session_path(Path::new(name)));
+        assert!(!is_temp_session_path(
+            &std::env::temp_dir().join("session.json")
+        ));
+    }
+}
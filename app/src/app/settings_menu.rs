@@ -56,6 +56,9 @@ impl SettingsWindow {
         }
     }
 
+    /// Adds the widget to the tab, replacing any widget with the same
+    /// name, so that widgets can be registered again when the viewers
+    /// or the graph are reloaded.
     pub fn register_widget(
         &mut self,
         tab_name: &str,
@@ -67,6 +70,11 @@ impl SettingsWindow {
             .entry(tab_name.into())
             .or_insert_with(|| SettingsWindowTab::new(name));
 
+        if let Some(h) = tab.handlers.iter_mut().find(|h| h.name == name) {
+            h.widget = widget;
+            return;
+        }
+
         let h = SettingsHandler {
             name: name.to_string(),
             widget,
@@ -101,6 +109,9 @@ impl SettingsWindow {
                 |ui| {
                     ui.vertical(|ui| {
                         ui.set_min_width(128.0);
+
+                        let mut buttons = Vec::new();
+
                         for tab_name in self.tabs.keys() {
                             let active =
                                 Some(tab_name) == self.active_tab.as_ref();
@@ -112,7 +123,11 @@ impl SettingsWindow {
                             if button.clicked() {
                                 self.active_tab = Some(tab_name.to_string());
                             }
+
+                            buttons.push(button);
                         }
+
+                        crate::gui::util::arrow_key_focus(ui, &buttons);
                     });
 
                     egui::ScrollArea::vertical()
@@ -226,6 +241,25 @@ impl SettingsUiContext {
 
         recv
     }
+
+    /// Opens a save file dialog, unless `path` is already given (e.g.
+    /// generated from the export settings), in which case it's used
+    /// without asking.
+    pub fn save_file_oneshot(
+        &self,
+        id: egui::Id,
+        path: Option<PathBuf>,
+    ) -> oneshot::Receiver<PathBuf> {
+        if let Some(path) = path {
+            let (send, recv) = oneshot::channel::<PathBuf>();
+            let _ = send.send(path);
+            return recv;
+        }
+
+        let mut files = egui_file::FileDialog::save_file(None);
+        files.open();
+        self.with_file_dialog_oneshot(id, files)
+    }
 }
 
 pub struct SettingsUiResponse {
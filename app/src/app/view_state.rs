@@ -0,0 +1,564 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use waragraph_core::graph::coords::CoordSystem;
+
+use super::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use super::{App, AppMsg, AppType};
+
+/// The navigation state of a viewer window, i.e. what part of the
+/// graph it's looking at. Used to restore the view when a window
+/// has to be recreated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViewState {
+    View1D {
+        /// Pangenome range, in bp
+        range: std::ops::Range<u64>,
+        /// Key of the data source being visualized
+        data_key: String,
+        /// Index of the first visible path in the path list
+        path_offset: usize,
+        /// Names of the annotation sets shown as slots, top to
+        /// bottom; `None` leaves the slots as they are
+        annotation_slots: Option<Vec<String>>,
+    },
+    /// Center and size of the view, in layout coordinates
+    View2D { center: [f32; 2], size: [f32; 2] },
+}
+
+const VIEW_LINK_PREFIX: &str = "waragraph://view?";
+
+/// The paths in the 1D path list, by name, as set in the path list
+/// and "Path Colors" panels.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PathRows {
+    pub hidden: Vec<String>,
+    pub soloed: Vec<String>,
+    pub muted: Vec<String>,
+}
+
+/// The display settings a view link carries; `None` leaves the
+/// setting as it is.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ViewSettings {
+    pub coords: Option<CoordSystem>,
+    /// Name of the path positions are shown on, or empty for
+    /// pangenome offsets
+    pub reference: Option<String>,
+    pub high_contrast: Option<bool>,
+    /// Color scheme names, by data source
+    pub color_schemes: BTreeMap<String, String>,
+}
+
+/// A shareable encoding of the views of all viewer windows, the
+/// visible paths and annotation slots, and the display settings, as
+/// a `waragraph://view?...` URI, e.g.
+///
+/// `waragraph://view?graph=1f2e..&1d=1000-2000&data=depth&paths=4`
+///
+/// The graph is identified by the SHA-256 of its file, as in the
+/// provenance log, so that a link isn't applied to a different graph.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ViewLink {
+    pub graph_hash: Option<String>,
+    pub views: Vec<ViewState>,
+    pub path_rows: Option<PathRows>,
+    pub settings: ViewSettings,
+}
+
+impl ViewLink {
+    pub fn to_uri(&self) -> String {
+        let mut params = Vec::new();
+
+        if let Some(hash) = self.graph_hash.as_ref() {
+            params.push(format!("graph={hash}"));
+        }
+
+        for view in &self.views {
+            match view {
+                ViewState::View1D {
+                    range,
+                    data_key,
+                    path_offset,
+                    annotation_slots,
+                } => {
+                    params.push(format!("1d={}-{}", range.start, range.end));
+                    params.push(format!("data={}", percent_encode(data_key)));
+                    params.push(format!("paths={path_offset}"));
+                    if let Some(slots) = annotation_slots {
+                        params.push(format!("annots={}", encode_list(slots)));
+                    }
+                }
+                ViewState::View2D { center, size } => {
+                    let [x, y] = center;
+                    let [w, h] = size;
+                    params.push(format!("2d={x},{y},{w},{h}"));
+                }
+            }
+        }
+
+        if let Some(rows) = self.path_rows.as_ref() {
+            params.push(format!("hidden={}", encode_list(&rows.hidden)));
+            params.push(format!("solo={}", encode_list(&rows.soloed)));
+            params.push(format!("mute={}", encode_list(&rows.muted)));
+        }
+
+        let settings = &self.settings;
+        if let Some(coords) = settings.coords {
+            params.push(format!("coords={}", coords.short_name()));
+        }
+        if let Some(reference) = settings.reference.as_ref() {
+            params.push(format!("ref={}", percent_encode(reference)));
+        }
+        if let Some(high_contrast) = settings.high_contrast {
+            params.push(format!("contrast={}", high_contrast as u8));
+        }
+        for (data_key, scheme) in &settings.color_schemes {
+            params.push(format!(
+                "scheme.{}={}",
+                percent_encode(data_key),
+                percent_encode(scheme)
+            ));
+        }
+
+        format!("{VIEW_LINK_PREFIX}{}", params.join("&"))
+    }
+
+    /// Parses a link created by `to_uri`; the `waragraph://view?`
+    /// prefix is optional.
+    pub fn parse(link: &str) -> Result<Self> {
+        let link = link.trim();
+        let query = link.strip_prefix(VIEW_LINK_PREFIX).unwrap_or(link);
+
+        let mut graph_hash = None;
+        let mut range_1d = None;
+        let mut data_key = None;
+        let mut path_offset = 0;
+        let mut annotation_slots = None;
+        let mut view_2d = None;
+        let mut path_rows: Option<PathRows> = None;
+        let mut settings = ViewSettings::default();
+
+        for param in query.split('&').filter(|p| !p.is_empty()) {
+            let Some((key, value)) = param.split_once('=') else {
+                anyhow::bail!("Malformed view link parameter `{param}`");
+            };
+
+            match key {
+                "graph" => {
+                    anyhow::ensure!(
+                        !value.is_empty()
+                            && value.bytes().all(|b| b.is_ascii_hexdigit()),
+                        "Malformed graph hash `{value}`"
+                    );
+                    graph_hash = Some(value.to_ascii_lowercase());
+                }
+                "1d" => {
+                    let (start, end) = value
+                        .split_once('-')
+                        .ok_or_else(|| anyhow::anyhow!("Malformed 1D range"))?;
+                    let range = start.parse::<u64>()?..end.parse::<u64>()?;
+                    anyhow::ensure!(range.start < range.end, "Empty 1D range");
+                    range_1d = Some(range);
+                }
+                "data" => {
+                    data_key = Some(percent_decode(value)?);
+                }
+                "paths" => {
+                    path_offset = value.parse()?;
+                }
+                "annots" => {
+                    annotation_slots = Some(decode_list(value)?);
+                }
+                "hidden" => {
+                    path_rows.get_or_insert_with(Default::default).hidden =
+                        decode_list(value)?;
+                }
+                "solo" => {
+                    path_rows.get_or_insert_with(Default::default).soloed =
+                        decode_list(value)?;
+                }
+                "mute" => {
+                    path_rows.get_or_insert_with(Default::default).muted =
+                        decode_list(value)?;
+                }
+                "coords" => {
+                    let coords = CoordSystem::ALL
+                        .into_iter()
+                        .find(|c| c.short_name() == value)
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("Unknown coordinates `{value}`")
+                        })?;
+                    settings.coords = Some(coords);
+                }
+                "ref" => {
+                    settings.reference = Some(percent_decode(value)?);
+                }
+                "contrast" => {
+                    settings.high_contrast = Some(match value {
+                        "0" => false,
+                        "1" => true,
+                        _ => anyhow::bail!("Malformed contrast `{value}`"),
+                    });
+                }
+                "2d" => {
+                    let vals = value
+                        .split(',')
+                        .map(|v| v.parse::<f32>())
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    anyhow::ensure!(
+                        vals.len() == 4 && vals.iter().all(|v| v.is_finite()),
+                        "Malformed 2D view"
+                    );
+                    anyhow::ensure!(
+                        vals[2] > 0.0 && vals[3] > 0.0,
+                        "Empty 2D view"
+                    );
+
+                    view_2d = Some(ViewState::View2D {
+                        center: [vals[0], vals[1]],
+                        size: [vals[2], vals[3]],
+                    });
+                }
+                _ => {
+                    if let Some(data_key) = key.strip_prefix("scheme.") {
+                        settings.color_schemes.insert(
+                            percent_decode(data_key)?,
+                            percent_decode(value)?,
+                        );
+                    } else {
+                        // ignore unknown parameters, so that links from
+                        // newer versions can still be used
+                        log::warn!("Ignoring view link parameter `{key}`");
+                    }
+                }
+            }
+        }
+
+        let mut views = Vec::new();
+
+        if let Some(range) = range_1d {
+            views.push(ViewState::View1D {
+                range,
+                data_key: data_key.unwrap_or_else(|| "depth".to_string()),
+                path_offset,
+                annotation_slots,
+            });
+        }
+
+        views.extend(view_2d);
+
+        anyhow::ensure!(!views.is_empty(), "View link doesn't contain a view");
+
+        Ok(Self {
+            graph_hash,
+            views,
+            path_rows,
+            settings,
+        })
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+
+    out
+}
+
+/// Comma-separated, with each item percent-encoded, which escapes any
+/// commas in the items.
+fn encode_list(items: &[String]) -> String {
+    let items = items.iter().map(|s| percent_encode(s)).collect::<Vec<_>>();
+    items.join(",")
+}
+
+fn decode_list(s: &str) -> Result<Vec<String>> {
+    s.split(',')
+        .filter(|item| !item.is_empty())
+        .map(percent_decode)
+        .collect()
+}
+
+fn percent_decode(s: &str) -> Result<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next(), iter.next()];
+            let [Some(h), Some(l)] = hex else {
+                anyhow::bail!("Truncated percent-encoding in `{s}`");
+            };
+            let hex = std::str::from_utf8(&[h, l])?.to_string();
+            bytes.push(u8::from_str_radix(&hex, 16)?);
+        } else {
+            bytes.push(b);
+        }
+    }
+
+    Ok(String::from_utf8(bytes)?)
+}
+
+impl App {
+    /// Creates a view link for the current views of the viewer
+    /// windows, whether they're open or asleep, with the paths in
+    /// the 1D path list and the display settings.
+    pub fn create_view_link(&self) -> ViewLink {
+        let views = [AppType::Viewer1D, AppType::Viewer2D]
+            .iter()
+            .filter_map(|app_ty| {
+                if let Some(app) = self.app_windows.apps.get(app_ty) {
+                    app.app.view_state()
+                } else {
+                    self.app_windows.sleeping.get(app_ty)?.app.view_state()
+                }
+            })
+            .collect();
+
+        let path_rows = {
+            let path_colors = self.shared.path_colors.blocking_read();
+            let names = |names: &BTreeSet<String>| {
+                names.iter().cloned().collect::<Vec<_>>()
+            };
+            PathRows {
+                hidden: names(&path_colors.hidden_1d),
+                soloed: names(&path_colors.soloed),
+                muted: names(&path_colors.muted),
+            }
+        };
+
+        let reference = self
+            .shared
+            .reference_coords
+            .load()
+            .and_then(|path| self.shared.graph.path_names.get_by_left(&path))
+            .cloned()
+            .unwrap_or_default();
+
+        let settings = ViewSettings {
+            coords: Some(self.shared.coords.load()),
+            reference: Some(reference),
+            high_contrast: Some(self.shared.theme.load().high_contrast),
+            color_schemes: self.shared.data_color_scheme_names(),
+        };
+
+        ViewLink {
+            graph_hash: self.graph_hash.blocking_read().clone(),
+            views,
+            path_rows: Some(path_rows),
+            settings,
+        }
+    }
+
+    /// Applies the views in the link to the corresponding viewer
+    /// windows, and the paths and settings it has to the shared
+    /// state; fails if the link was created for a different graph.
+    pub fn open_view_link(
+        &mut self,
+        state: &raving_wgpu::State,
+        link: &ViewLink,
+    ) -> Result<()> {
+        let graph_hash = self.graph_hash.blocking_read().clone();
+
+        if let (Some(ours), Some(theirs)) = (graph_hash, &link.graph_hash) {
+            anyhow::ensure!(
+                &ours == theirs,
+                "View link is for a different graph \
+                 (hash {theirs}, expected {ours})"
+            );
+        }
+
+        if let Some(rows) = link.path_rows.as_ref() {
+            let mut path_colors = self.shared.path_colors.blocking_write();
+            let names = |names: &[String]| names.iter().cloned().collect();
+            path_colors.hidden_1d = names(&rows.hidden);
+            path_colors.soloed = names(&rows.soloed);
+            path_colors.muted = names(&rows.muted);
+            path_colors.mark_changed();
+        }
+
+        let settings = &link.settings;
+
+        if let Some(coords) = settings.coords {
+            self.shared.coords.store(coords);
+        }
+
+        if let Some(name) = settings.reference.as_ref() {
+            let path = self.shared.graph.path_names.get_by_right(name);
+            if name.is_empty() || path.is_some() {
+                self.shared.reference_coords.store(path.copied());
+            } else {
+                log::warn!("No reference path `{name}` for view link");
+            }
+        }
+
+        if let Some(high_contrast) = settings.high_contrast {
+            self.shared
+                .theme
+                .store(crate::gui::theme::Theme { high_contrast });
+        }
+
+        for (data_key, scheme) in settings.color_schemes.iter() {
+            if let Err(e) =
+                self.shared.set_data_color_scheme(state, data_key, scheme)
+            {
+                log::warn!("Error applying view link color scheme: {e:?}");
+            }
+        }
+
+        for view in &link.views {
+            let app_ty = match view {
+                ViewState::View1D { .. } => AppType::Viewer1D,
+                ViewState::View2D { .. } => AppType::Viewer2D,
+            };
+
+            if let Some(app) = self.app_windows.app_mut(&app_ty) {
+                app.restore_view_state(view);
+            } else {
+                log::warn!("No {app_ty:?} window for view link, ignoring");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Settings window panel for creating and opening view links.
+#[derive(Default)]
+pub struct ViewLinkWidget {
+    link_text: String,
+    copy_pending: bool,
+}
+
+impl ViewLinkWidget {
+    /// Sets the link shown in the panel; it's copied to the
+    /// clipboard the next time the panel is shown.
+    pub fn set_link(&mut self, link: String) {
+        self.link_text = link;
+        self.copy_pending = true;
+    }
+}
+
+impl SettingsWidget for ViewLinkWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        if self.copy_pending {
+            self.copy_pending = false;
+            let link = self.link_text.clone();
+            ui.output_mut(|o| o.copied_text = link);
+        }
+
+        let resp = ui.vertical(|ui| {
+            ui.add_sized(
+                [ui.available_size().x, 0f32],
+                egui::TextEdit::singleline(&mut self.link_text)
+                    .hint_text("waragraph://view?..."),
+            );
+
+            ui.horizontal(|ui| {
+                if ui.button("Copy view link").clicked() {
+                    settings_ctx.send_app_msg_task(AppMsg::CopyViewLink);
+                }
+
+                let open = ui.add_enabled(
+                    !self.link_text.trim().is_empty(),
+                    egui::Button::new("Open link"),
+                );
+
+                if open.clicked() {
+                    settings_ctx.send_app_msg_task(AppMsg::OpenViewLink(
+                        self.link_text.clone(),
+                    ));
+                }
+            });
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_link_roundtrip() {
+        let names = |names: &[&str]| {
+            names.iter().map(|s| s.to_string()).collect::<Vec<_>>()
+        };
+
+        let link = ViewLink {
+            graph_hash: Some("9f86d081884c7d65".to_string()),
+            views: vec![
+                ViewState::View1D {
+                    range: 1000..2500,
+                    data_key: "my data&more".to_string(),
+                    path_offset: 3,
+                    annotation_slots: Some(names(&["genes,v2", "repeats"])),
+                },
+                ViewState::View2D {
+                    center: [10.5, -3.0],
+                    size: [200.0, 150.25],
+                },
+            ],
+            path_rows: Some(PathRows {
+                hidden: names(&["HG002#1", "chr1"]),
+                soloed: Vec::new(),
+                muted: names(&["a b"]),
+            }),
+            settings: ViewSettings {
+                coords: Some(CoordSystem::OneBased),
+                reference: Some("grch38#chr1".to_string()),
+                high_contrast: Some(true),
+                color_schemes: [("depth".to_string(), "spectral".to_string())]
+                    .into_iter()
+                    .collect(),
+            },
+        };
+
+        let uri = link.to_uri();
+        assert!(uri.starts_with("waragraph://view?graph=9f86d081884c7d65"));
+        assert!(uri.contains("annots=genes%2Cv2,repeats"));
+        assert_eq!(ViewLink::parse(&uri).unwrap(), link);
+
+        // the prefix is optional, and links without the tracks or
+        // settings leave them as they are
+        let plain = ViewLink::parse("1d=5-10").unwrap();
+        assert_eq!(plain.graph_hash, None);
+        assert_eq!(plain.views.len(), 1);
+        assert_eq!(plain.path_rows, None);
+        assert_eq!(plain.settings, ViewSettings::default());
+        assert!(matches!(
+            plain.views[0],
+            ViewState::View1D {
+                annotation_slots: None,
+                ..
+            }
+        ));
+
+        let empty = ViewLink::parse("1d=5-10&hidden=&solo=&mute=").unwrap();
+        assert_eq!(empty.path_rows, Some(PathRows::default()));
+
+        assert!(ViewLink::parse("1d=10-5").is_err());
+        assert!(ViewLink::parse("2d=0,0,1").is_err());
+        assert!(ViewLink::parse("graph=xyz&1d=5-10").is_err());
+        assert!(ViewLink::parse("graph=abc").is_err());
+        assert!(ViewLink::parse("1d=5-10&coords=2-based").is_err());
+    }
+}
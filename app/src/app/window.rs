@@ -41,11 +41,41 @@ impl AppWindowState {
         let window =
             WindowBuilder::new().with_title(title).build(event_loop)?;
 
+        Self::init_with_window(event_loop, state, title, window, constructor)
+    }
+
+    /// As `init`, with a window of the given size that's never shown,
+    /// for rendering without a display of the app, e.g. batch export.
+    pub(super) fn init_hidden(
+        event_loop: &EventLoopWindowTarget<()>,
+        state: &raving_wgpu::State,
+        title: &str,
+        [width, height]: [u32; 2],
+        constructor: impl FnOnce(&WindowState) -> anyhow::Result<Box<dyn AppWindow>>,
+    ) -> anyhow::Result<Self> {
+        let window = WindowBuilder::new()
+            .with_title(title)
+            .with_inner_size(winit::dpi::PhysicalSize::new(width, height))
+            .with_visible(false)
+            .build(event_loop)?;
+
+        Self::init_with_window(event_loop, state, title, window, constructor)
+    }
+
+    fn init_with_window(
+        event_loop: &EventLoopWindowTarget<()>,
+        state: &raving_wgpu::State,
+        title: &str,
+        window: winit::window::Window,
+        constructor: impl FnOnce(&WindowState) -> anyhow::Result<Box<dyn AppWindow>>,
+    ) -> anyhow::Result<Self> {
         let win_state = state.prepare_window(window)?;
 
         let egui_ctx =
             EguiCtx::init(&state, win_state.surface_format, &event_loop, None);
 
+        crate::gui::util::apply_focus_visuals(egui_ctx.ctx());
+
         let app = constructor(&win_state)?;
 
         Ok(Self {
@@ -56,8 +86,33 @@ impl AppWindowState {
         })
     }
 
-    pub(super) fn resize(&mut self, state: &raving_wgpu::State) {
+    /// Replaces the window, and its surface and egui renderer, with
+    /// new ones on the current device, keeping the app. Only for apps
+    /// that don't hold GPU resources of their own.
+    pub(super) fn recreate(
+        self,
+        event_loop: &EventLoopWindowTarget<()>,
+        state: &raving_wgpu::State,
+    ) -> anyhow::Result<Self> {
+        let app = self.app;
+        Self::init(event_loop, state, &self.title, move |_| Ok(app))
+    }
+
+    /// Reconfigures the surface to the window's current size, and
+    /// lets the app resize its own buffers if the size changed.
+    pub(super) fn resize(
+        &mut self,
+        state: &raving_wgpu::State,
+    ) -> anyhow::Result<()> {
+        let old_size: [u32; 2] = self.window.size.into();
         self.window.resize(&state.device);
+        let new_size: [u32; 2] = self.window.window.inner_size().into();
+
+        if old_size != new_size && new_size[0] > 0 && new_size[1] > 0 {
+            self.app.on_resize(state, old_size, new_size)?;
+        }
+
+        Ok(())
     }
 
     pub(super) fn on_event<'a>(&mut self, event: &WindowEvent<'a>) -> bool {
@@ -96,7 +151,29 @@ impl AppWindowState {
         let egui_ctx = &mut self.egui;
         let window = &mut self.window;
 
-        if let Ok(output) = window.surface.get_current_texture() {
+        let output = match window.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(wgpu::SurfaceError::Timeout) => {
+                // skip this frame
+                return Ok(());
+            }
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                anyhow::bail!("Out of memory acquiring surface texture");
+            }
+            Err(_) => {
+                // lost or outdated, e.g. after a scale factor change
+                // the window wasn't resized for yet
+                let old_size: [u32; 2] = window.size.into();
+                window.resize(&state.device);
+                let new_size: [u32; 2] = window.window.inner_size().into();
+                if old_size != new_size {
+                    app.on_resize(state, old_size, new_size)?;
+                }
+                return Ok(());
+            }
+        };
+
+        {
             let mut encoder = state.device.create_command_encoder(
                 &wgpu::CommandEncoderDescriptor {
                     label: Some(&self.title),
@@ -115,8 +192,6 @@ impl AppWindowState {
 
             state.queue.submit(Some(encoder.finish()));
             output.present();
-        } else {
-            window.resize(&state.device);
         }
 
         Ok(())
@@ -215,6 +290,40 @@ pub struct AppWindows {
 }
 
 impl AppWindows {
+    /// Gives the keyboard focus to the next open window after
+    /// `current`, ordered by app type and wrapping around.
+    pub(super) fn focus_next(&self, current: WindowId) {
+        let Some(current) = self.windows.get(&current) else {
+            return;
+        };
+
+        let mut app_types = self.apps.keys().collect::<Vec<_>>();
+        app_types.sort();
+
+        let Some(ix) = app_types.iter().position(|&ty| ty == current) else {
+            return;
+        };
+
+        let next = app_types[(ix + 1) % app_types.len()];
+
+        if let Some(app) = self.apps.get(next) {
+            app.window.window.focus_window();
+        }
+    }
+
+    /// The app of the given type, whether its window is open or
+    /// asleep.
+    pub(super) fn app_mut(
+        &mut self,
+        app_ty: &AppType,
+    ) -> Option<&mut dyn AppWindow> {
+        if let Some(app) = self.apps.get_mut(app_ty) {
+            Some(app.app.as_mut())
+        } else {
+            Some(self.sleeping.get_mut(app_ty)?.app.as_mut())
+        }
+    }
+
     pub(super) fn update_widget_state(&self) {
         let mut state = self.widget_state.blocking_write();
         self.windows.clone_into(&mut state.window_app_map);
@@ -8,15 +8,30 @@ use tokio::{
     sync::oneshot::{self, error::TryRecvError},
     task::JoinHandle,
 };
+use waragraph_core::graph::PathId;
 
 use super::{
+    layout::{BuiltinLayoutJob, BuiltinLayoutStatus},
+    odgi::OdgiLayoutStatus,
     settings_menu::{SettingsUiContext, SettingsUiResponse, SettingsWidget},
     AppMsg,
 };
 
 pub struct Workspace {
     pub(super) gfa_path: PathBuf,
+    /// The graph file as opened; the odgi graph if `gfa_path` was
+    /// converted from one, which then only lasts as long as the app.
+    /// Files derived from the graph, like layouts, go next to this one
+    pub(super) graph_path: PathBuf,
     pub(super) tsv_path: Option<PathBuf>,
+    /// The graph in odgi format, used for loading odgi layouts
+    pub(super) og_path: Option<PathBuf>,
+    /// Paths added to the graph after loading it, by the GAF file
+    /// they were aligned in
+    pub(super) added_paths: Vec<(PathBuf, Vec<PathId>)>,
+
+    pub(super) odgi_layout_job: Option<OdgiLayoutJob>,
+    pub(super) builtin_layout_job: Option<BuiltinLayoutJob>,
 }
 
 impl Workspace {
@@ -24,9 +39,21 @@ impl Workspace {
         &self.gfa_path
     }
 
+    pub fn graph_path(&self) -> &PathBuf {
+        &self.graph_path
+    }
+
     pub fn tsv_path(&self) -> Option<&PathBuf> {
         self.tsv_path.as_ref()
     }
+
+    pub fn og_path(&self) -> Option<&PathBuf> {
+        self.og_path.as_ref()
+    }
+
+    pub fn added_paths(&self) -> &[(PathBuf, Vec<PathId>)] {
+        &self.added_paths
+    }
 }
 
 impl SettingsWidget for Workspace {
@@ -62,9 +89,9 @@ impl SettingsWidget for Workspace {
         };
 
         let resp = ui.horizontal_wrapped(|ui| {
-            ui.label("GFA:");
+            ui.label("Graph:");
             let mut gfa_buf =
-                self.gfa_path.clone().to_string_lossy().to_string();
+                self.graph_path.clone().to_string_lossy().to_string();
             ui.add_enabled(false, egui::TextEdit::singleline(&mut gfa_buf));
 
             ui.end_row();
@@ -86,7 +113,7 @@ impl SettingsWidget for Workspace {
                             if let Some(ext) =
                                 p.extension().map(|e| e.to_ascii_lowercase())
                             {
-                                ext == "tsv"
+                                ext == "tsv" || ext == "lay"
                             } else {
                                 false
                             }
@@ -96,6 +123,10 @@ impl SettingsWidget for Workspace {
                 let recv = settings_ctx.with_file_dialog_oneshot(id, files);
                 state.tsv_path_recv.store(Some(recv));
             }
+
+            ui.end_row();
+
+            self.show_layout_jobs(ui, settings_ctx);
         });
 
         state.store(ui.ctx(), id);
@@ -106,6 +137,72 @@ impl SettingsWidget for Workspace {
     }
 }
 
+impl Workspace {
+    fn show_layout_jobs(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) {
+        if let Some(job) = self.builtin_layout_job.as_ref() {
+            match job.status() {
+                BuiltinLayoutStatus::Running { progress } => {
+                    ui.label("Computing layout:");
+                    ui.add(
+                        egui::ProgressBar::new(progress)
+                            .show_percentage()
+                            .animate(true),
+                    );
+                }
+                BuiltinLayoutStatus::Done { .. } => {}
+                BuiltinLayoutStatus::Failed(err) => {
+                    ui.label(format!("Layout failed: {err}"));
+                    if ui.button("Retry").clicked() {
+                        self.builtin_layout_job = None;
+                    }
+                }
+            }
+        } else if let Some(job) = self.odgi_layout_job.as_ref() {
+            match job.status() {
+                OdgiLayoutStatus::Building => {
+                    ui.label("Building odgi graph...");
+                    ui.spinner();
+                }
+                OdgiLayoutStatus::Layout { progress } => {
+                    ui.label("Running odgi layout:");
+                    ui.add(
+                        egui::ProgressBar::new(progress)
+                            .show_percentage()
+                            .animate(true),
+                    );
+                }
+                OdgiLayoutStatus::Done { .. } => {}
+                OdgiLayoutStatus::Failed(err) => {
+                    ui.label(format!("odgi layout failed: {err}"));
+                    if ui.button("Retry").clicked() {
+                        self.odgi_layout_job = None;
+                    }
+                }
+            }
+        } else if self.tsv_path.is_none() {
+            if ui
+                .button("Compute layout")
+                .on_hover_text("Uses the built-in path-guided SGD layout")
+                .clicked()
+            {
+                settings_ctx.send_app_msg_task(AppMsg::RunBuiltinLayout);
+            }
+
+            if super::odgi::find_odgi().is_some() {
+                if ui.button("Run odgi layout").clicked() {
+                    settings_ctx.send_app_msg_task(AppMsg::RunOdgiLayout);
+                }
+            } else {
+                ui.label("odgi not found in PATH");
+            }
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct WorkspaceWidgetState {
     // tsv_path_future: Arc<AtomicCell<Option<JoinHandle<Option<PathBuf>>>>>,
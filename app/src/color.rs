@@ -7,6 +7,7 @@ use wgpu::{
     BufferUsages,
 };
 
+pub mod paths;
 pub mod util;
 pub mod widget;
 
@@ -79,6 +80,41 @@ fn create_nearest_sampler(device: &wgpu::Device) -> wgpu::Sampler {
     device.create_sampler(&sampler_desc)
 }
 
+/// The color schemes that are always available, as (name, colors).
+pub fn builtin_color_schemes() -> Vec<(&'static str, Vec<[f32; 4]>)> {
+    let rgba = |r: u8, g: u8, b: u8| {
+        let max = u8::MAX as f32;
+        [r as f32 / max, g as f32 / max, b as f32 / max, 1.0]
+    };
+
+    let spectral_colors = [
+        rgba(158, 1, 66),
+        rgba(213, 62, 79),
+        rgba(244, 109, 67),
+        rgba(253, 174, 97),
+        rgba(254, 224, 139),
+        rgba(255, 255, 191),
+        rgba(230, 245, 152),
+        rgba(171, 221, 164),
+        rgba(102, 194, 165),
+        rgba(50, 136, 189),
+        rgba(94, 79, 162),
+    ];
+    let mut spectral = vec![rgba(128, 128, 128), rgba(196, 196, 196)];
+
+    spectral.extend(spectral_colors);
+
+    let black_red = (0..8)
+        .map(|i: i32| {
+            // for i = 8 this is 255, which is what we want
+            let r = ((i * 64 - 1) / 2).max(0);
+            rgba(r as u8, 0, 0)
+        })
+        .collect();
+
+    vec![("spectral", spectral), ("black_red", black_red)]
+}
+
 impl ColorStore {
     pub fn get_color_scheme_id(&self, name: &str) -> Option<ColorSchemeId> {
         self.scheme_name_map.get_by_left(name).copied()
@@ -110,37 +146,9 @@ impl ColorStore {
             // egui_textures: HashMap::default(),
         };
 
-        let rgba = |r: u8, g: u8, b: u8| {
-            let max = u8::MAX as f32;
-            [r as f32 / max, g as f32 / max, b as f32 / max, 1.0]
-        };
-
-        let spectral_colors = [
-            rgba(158, 1, 66),
-            rgba(213, 62, 79),
-            rgba(244, 109, 67),
-            rgba(253, 174, 97),
-            rgba(254, 224, 139),
-            rgba(255, 255, 191),
-            rgba(230, 245, 152),
-            rgba(171, 221, 164),
-            rgba(102, 194, 165),
-            rgba(50, 136, 189),
-            rgba(94, 79, 162),
-        ];
-        let mut spectral = vec![rgba(128, 128, 128), rgba(196, 196, 196)];
-
-        spectral.extend(spectral_colors);
-
-        result.add_color_scheme("spectral", spectral);
-
-        let black_red = (0..8).map(|i: i32| {
-            // for i = 8 this is 255, which is what we want
-            let r = ((i * 64 - 1) / 2).max(0);
-            rgba(r as u8, 0, 0)
-        });
-
-        result.add_color_scheme("black_red", black_red);
+        for (name, colors) in builtin_color_schemes() {
+            result.add_color_scheme(name, colors);
+        }
 
         result
     }
@@ -201,6 +209,12 @@ impl ColorStore {
             bytemuck::cast_slice(&pixel_data),
         );
 
+        crate::util::gpu_memory::record_texture(
+            &label,
+            [size.width, size.height],
+            format.block_size(None).unwrap_or(4),
+        );
+
         let label = format!("Texture View - Color Scheme {scheme_name}");
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor {
@@ -233,6 +247,31 @@ impl ColorStore {
         Some(buf.clone())
     }
 
+    /// Recreates the GPU samplers, textures, and buffers for all
+    /// color schemes that had them, e.g. after a GPU error.
+    pub fn recreate_gpu_resources(
+        &mut self,
+        state: &raving_wgpu::State,
+    ) -> anyhow::Result<()> {
+        self.linear_sampler = Arc::new(create_linear_sampler(&state.device));
+        self.nearest_sampler = Arc::new(create_nearest_sampler(&state.device));
+
+        let with_textures = self
+            .scheme_textures
+            .keys()
+            .map(|&id| self.get_scheme_name(id).to_string())
+            .collect::<Vec<_>>();
+
+        self.scheme_textures.clear();
+        self.scheme_buffers.clear();
+
+        for name in with_textures {
+            self.create_color_scheme_texture(state, &name);
+        }
+
+        self.upload_color_schemes_to_gpu(state)
+    }
+
     pub fn upload_color_schemes_to_gpu(
         &mut self,
         state: &raving_wgpu::State,
@@ -269,6 +308,28 @@ impl ColorStore {
         Ok(())
     }
 
+    /// Replaces the colors of a scheme, recreating its texture and
+    /// GPU buffer if it had them.
+    pub fn set_color_scheme_colors(
+        &mut self,
+        state: &raving_wgpu::State,
+        id: ColorSchemeId,
+        colors: impl IntoIterator<Item = [f32; 4]>,
+    ) -> anyhow::Result<()> {
+        self.color_schemes[id.0].colors = colors.into_iter().collect();
+
+        if self.scheme_textures.contains_key(&id) {
+            let name = self.get_scheme_name(id).to_string();
+            self.create_color_scheme_texture(state, &name);
+        }
+
+        if self.scheme_buffers.remove(&id).is_some() {
+            self.upload_color_schemes_to_gpu(state)?;
+        }
+
+        Ok(())
+    }
+
     pub fn add_color_scheme(
         &mut self,
         name: &str,
@@ -288,6 +349,19 @@ impl ColorStore {
     }
 }
 
+/// Returns the color at `t` in `[0, 1]` of a sequence of colors,
+/// matching the nearest sampler used when rendering on the GPU.
+pub fn color_at(colors: &[[f32; 4]], t: f32) -> [f32; 4] {
+    let len = colors.len();
+    if len == 0 {
+        return [1.0; 4];
+    }
+
+    let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+    let ix = ((t * len as f32) as usize).min(len - 1);
+    colors[ix]
+}
+
 /// A `ColorScheme` is a sequence of colors
 pub struct ColorScheme {
     pub id: ColorSchemeId,
@@ -295,6 +369,11 @@ pub struct ColorScheme {
 }
 
 impl ColorScheme {
+    /// Returns the color at `t` in `[0, 1]`, see [`color_at`].
+    pub fn color_at(&self, t: f32) -> [f32; 4] {
+        color_at(&self.colors, t)
+    }
+
     pub fn required_buffer_size(&self) -> usize {
         let elem_count = self.colors.len();
         let elem_size = std::mem::size_of::<[f32; 4]>();
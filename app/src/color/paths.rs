@@ -0,0 +1,347 @@
+//! The color of each path, shared by the 1D viewer (the `path_name`
+//! visualization mode) and the path overlays of the 2D viewer.
+//!
+//! Colors come from a palette, either hashed from the path name or
+//! categorical by path index, with per-path overrides. The overrides,
+//! and which paths are drawn in the 2D view and left out of the 1D
+//! view, are kept next to the graph in `<gfa>.path_colors.tsv`. The
+//! solo and mute toggles of the 1D path list are kept in the session.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use egui::Color32;
+use waragraph_core::graph::{PathId, PathIndex};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PathPalette {
+    /// Hashed from the path name, so a path has the same color in
+    /// every graph
+    #[default]
+    Hash,
+    /// Cycles through a categorical palette, in path order
+    Categorical,
+}
+
+impl PathPalette {
+    pub const ALL: [PathPalette; 2] =
+        [PathPalette::Hash, PathPalette::Categorical];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PathPalette::Hash => "hash",
+            PathPalette::Categorical => "categorical",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PathColors {
+    pub palette: PathPalette,
+    /// Colors set by the user, by path name
+    pub overrides: BTreeMap<String, Color32>,
+    /// Paths drawn on top of the 2D view
+    pub shown_2d: BTreeSet<String>,
+    /// Paths left out of the 1D path list
+    pub hidden_1d: BTreeSet<String>,
+    /// If any paths are soloed, only they are in the 1D path list
+    pub soloed: BTreeSet<String>,
+    /// Paths left out of the 1D path list, even if soloed
+    pub muted: BTreeSet<String>,
+
+    // bumped on every change, so that the viewers know to update
+    generation: u64,
+}
+
+impl PathColors {
+    /// The path colors of a graph are kept in `<gfa>.path_colors.tsv`.
+    pub fn file_for_graph(gfa_path: &Path) -> PathBuf {
+        let mut file = gfa_path.as_os_str().to_owned();
+        file.push(".path_colors.tsv");
+        PathBuf::from(file)
+    }
+
+    /// Loads the path colors of the graph, if the file exists.
+    pub fn load_for(gfa_path: &Path) -> Result<Self> {
+        let file = Self::file_for_graph(gfa_path);
+        if file.exists() {
+            Self::parse(&std::fs::read_to_string(file)?)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn save_for(&self, gfa_path: &Path) -> Result<()> {
+        let file = std::fs::File::create(Self::file_for_graph(gfa_path))?;
+        let mut out = std::io::BufWriter::new(file);
+        self.write(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Must be called after changing the palette, overrides, or
+    /// shown paths.
+    pub fn mark_changed(&mut self) {
+        self.generation += 1;
+    }
+
+    pub fn set_shown_2d(&mut self, name: &str, shown: bool) {
+        if shown {
+            self.shown_2d.insert(name.to_string());
+        } else {
+            self.shown_2d.remove(name);
+        }
+    }
+
+    pub fn set_shown_1d(&mut self, name: &str, shown: bool) {
+        if shown {
+            self.hidden_1d.remove(name);
+        } else {
+            self.hidden_1d.insert(name.to_string());
+        }
+    }
+
+    pub fn set_soloed(&mut self, name: &str, soloed: bool) {
+        if soloed {
+            self.soloed.insert(name.to_string());
+        } else {
+            self.soloed.remove(name);
+        }
+    }
+
+    pub fn set_muted(&mut self, name: &str, muted: bool) {
+        if muted {
+            self.muted.insert(name.to_string());
+        } else {
+            self.muted.remove(name);
+        }
+    }
+
+    pub fn clear_solo_mute(&mut self) {
+        self.soloed.clear();
+        self.muted.clear();
+    }
+
+    /// Whether the path is in the 1D path list: it's not hidden or
+    /// muted, and either no paths are soloed, or it is. Muting takes
+    /// precedence over soloing.
+    pub fn shown_1d(&self, name: &str) -> bool {
+        !self.hidden_1d.contains(name)
+            && !self.muted.contains(name)
+            && (self.soloed.is_empty() || self.soloed.contains(name))
+    }
+
+    /// The paths left out of the 1D path list, by ID, whether hidden,
+    /// muted, or not soloed.
+    pub fn hidden_1d_ids(&self, graph: &PathIndex) -> HashSet<PathId> {
+        graph
+            .path_names
+            .iter()
+            .filter(|(_, name)| !self.shown_1d(name))
+            .map(|(path, _)| *path)
+            .collect()
+    }
+
+    pub fn palette_color(&self, path: PathId, name: &str) -> Color32 {
+        match self.palette {
+            PathPalette::Hash => {
+                let [r, g, b] = super::util::path_name_hash_color(name);
+                Color32::from_rgb(
+                    (r * 255.0) as u8,
+                    (g * 255.0) as u8,
+                    (b * 255.0) as u8,
+                )
+            }
+            PathPalette::Categorical => {
+                let palette = &colorous::TABLEAU10;
+                let c = palette[path.ix() % palette.len()];
+                Color32::from_rgb(c.r, c.g, c.b)
+            }
+        }
+    }
+
+    pub fn color(&self, path: PathId, name: &str) -> Color32 {
+        self.overrides
+            .get(name)
+            .copied()
+            .unwrap_or_else(|| self.palette_color(path, name))
+    }
+
+    /// The color of each path in the graph, in path order.
+    pub fn colors(&self, graph: &PathIndex) -> Vec<[f32; 4]> {
+        graph
+            .path_names
+            .iter()
+            .map(|(path, name)| {
+                let [r, g, b, a] = self.color(*path, name).to_array();
+                [r, g, b, a].map(|c| c as f32 / 255.0)
+            })
+            .collect()
+    }
+
+    /// Parses the `#palette` line and rows of path name, color (as
+    /// `#rrggbb`, or `.` for the palette color), whether the path is
+    /// drawn in the 2D view (`1` or `0`), and, optionally, whether
+    /// it's shown in the 1D view.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut colors = Self::default();
+
+        for (line_ix, line) in text.lines().enumerate() {
+            let err = |msg: &str| anyhow!("Line {}: {msg}", line_ix + 1);
+
+            if let Some(palette) = line.strip_prefix("#palette\t") {
+                colors.palette = PathPalette::from_name(palette.trim())
+                    .ok_or_else(|| err("unknown palette"))?;
+                continue;
+            }
+
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<_>>();
+            let (name, color, shown, shown_1d) = match fields[..] {
+                [name, color, shown] => (name, color, shown, "1"),
+                [name, color, shown, shown_1d] => {
+                    (name, color, shown, shown_1d)
+                }
+                _ => return Err(err("expected 3 or 4 columns")),
+            };
+
+            if color != "." {
+                let color = parse_hex_color(color)
+                    .ok_or_else(|| err("invalid color"))?;
+                colors.overrides.insert(name.to_string(), color);
+            }
+
+            if shown == "1" {
+                colors.shown_2d.insert(name.to_string());
+            }
+
+            if shown_1d == "0" {
+                colors.hidden_1d.insert(name.to_string());
+            }
+        }
+
+        Ok(colors)
+    }
+
+    pub fn write(&self, mut out: impl Write) -> std::io::Result<()> {
+        writeln!(out, "#palette\t{}", self.palette.name())?;
+        writeln!(out, "#path\tcolor\tshow_2d\tshow_1d")?;
+
+        let names = self
+            .overrides
+            .keys()
+            .chain(self.shown_2d.iter())
+            .chain(self.hidden_1d.iter())
+            .collect::<BTreeSet<_>>();
+
+        for name in names {
+            let color = self
+                .overrides
+                .get(name)
+                .map(|c| format!("#{:02x}{:02x}{:02x}", c.r(), c.g(), c.b()))
+                .unwrap_or_else(|| ".".to_string());
+            let shown = u8::from(self.shown_2d.contains(name));
+            let shown_1d = u8::from(!self.hidden_1d.contains(name));
+
+            writeln!(out, "{name}\t{color}\t{shown}\t{shown_1d}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_hex_color(text: &str) -> Option<Color32> {
+    let hex = text.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |ix: usize| u8::from_str_radix(hex.get(ix..ix + 2)?, 16).ok();
+    Some(Color32::from_rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_colors_roundtrip() {
+        let mut colors = PathColors {
+            palette: PathPalette::Categorical,
+            ..Default::default()
+        };
+        colors
+            .overrides
+            .insert("HG002#1#chr20".into(), Color32::from_rgb(255, 0, 16));
+        colors.shown_2d.insert("HG002#1#chr20".into());
+        colors.shown_2d.insert("grch38".into());
+        colors.hidden_1d.insert("HG002#2#chr20".into());
+
+        let mut out = Vec::new();
+        colors.write(&mut out).unwrap();
+        let parsed = PathColors::parse(&String::from_utf8(out).unwrap());
+        assert_eq!(parsed.unwrap(), colors);
+
+        assert!(PathColors::parse("#palette\tplaid\n").is_err());
+        assert!(PathColors::parse("a\tred\t0\n").is_err());
+
+        // files from before the 1D column are still read
+        let parsed = PathColors::parse("a\t.\t1\n").unwrap();
+        assert!(parsed.shown_2d.contains("a"));
+        assert!(parsed.hidden_1d.is_empty());
+    }
+
+    #[test]
+    fn solo_and_mute() {
+        let mut colors = PathColors::default();
+        colors.hidden_1d.insert("c".into());
+        assert!(colors.shown_1d("a") && colors.shown_1d("b"));
+
+        colors.set_soloed("a", true);
+        colors.set_soloed("c", true);
+        assert!(colors.shown_1d("a"));
+        assert!(!colors.shown_1d("b"));
+        // soloing doesn't override the 1D toggle
+        assert!(!colors.shown_1d("c"));
+
+        // muting overrides soloing
+        colors.set_muted("a", true);
+        assert!(!colors.shown_1d("a"));
+
+        colors.set_soloed("c", false);
+        colors.set_soloed("a", false);
+        assert!(colors.shown_1d("b"));
+        assert!(!colors.shown_1d("a"));
+
+        colors.clear_solo_mute();
+        assert!(colors.shown_1d("a"));
+    }
+
+    #[test]
+    fn overrides_take_precedence() {
+        let mut colors = PathColors {
+            palette: PathPalette::Categorical,
+            ..Default::default()
+        };
+
+        let first = colors.color(PathId::from(0u32), "a");
+        let wrapped =
+            colors.color(PathId::from(colorous::TABLEAU10.len() as u32), "b");
+        assert_eq!(first, wrapped);
+
+        colors.overrides.insert("a".into(), Color32::WHITE);
+        assert_eq!(colors.color(PathId::from(0u32), "a"), Color32::WHITE);
+    }
+}
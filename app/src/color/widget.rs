@@ -345,3 +345,149 @@ impl<'a> egui::Widget for ColorMapWidget<'a> {
         self.show(ui)
     }
 }
+
+/// Settings for the colors of the paths in the 1D `path_name` mode
+/// and the 2D path overlays.
+pub struct PathColorsWidget {
+    graph: Arc<waragraph_core::graph::PathIndex>,
+    path_colors: Arc<RwLock<super::paths::PathColors>>,
+    workspace: Arc<RwLock<crate::app::workspace::Workspace>>,
+
+    filter: String,
+    error: Option<String>,
+}
+
+impl PathColorsWidget {
+    pub fn new(shared: &crate::app::SharedState) -> Self {
+        Self {
+            graph: shared.graph.clone(),
+            path_colors: shared.path_colors.clone(),
+            workspace: shared.workspace.clone(),
+            filter: String::new(),
+            error: None,
+        }
+    }
+}
+
+impl SettingsWidget for PathColorsWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        use super::paths::PathPalette;
+
+        let resp = ui.vertical(|ui| {
+            let mut path_colors = self.path_colors.blocking_write();
+            let mut changed = false;
+
+            ui.horizontal(|ui| {
+                ui.label("Palette");
+
+                let mut palette = path_colors.palette;
+                egui::ComboBox::from_id_source("PathColorsWidget-palette")
+                    .selected_text(palette.name())
+                    .show_ui(ui, |ui| {
+                        for p in PathPalette::ALL {
+                            ui.selectable_value(&mut palette, p, p.name());
+                        }
+                    });
+
+                if palette != path_colors.palette {
+                    path_colors.palette = palette;
+                    changed = true;
+                }
+
+                if ui
+                    .button("Save")
+                    .on_hover_text(
+                        "Saves the colors and shown paths next to the graph",
+                    )
+                    .clicked()
+                {
+                    let gfa_path =
+                        self.workspace.blocking_read().gfa_path().clone();
+                    match path_colors.save_for(&gfa_path) {
+                        Ok(()) => self.error = None,
+                        Err(e) => {
+                            log::error!("Error saving path colors: {e:?}");
+                            self.error = Some(e.to_string());
+                        }
+                    }
+                }
+            });
+
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter)
+                    .hint_text("Filter paths"),
+            );
+
+            if let Some(err) = self.error.as_ref() {
+                ui.label(format!("Error: {err}"));
+            }
+
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("PathColorsWidget-paths")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (path, name) in self.graph.path_names.iter() {
+                                if !name.contains(self.filter.as_str()) {
+                                    continue;
+                                }
+
+                                ui.label(name);
+
+                                let mut color = path_colors.color(*path, name);
+                                if ui
+                                    .color_edit_button_srgba(&mut color)
+                                    .changed()
+                                {
+                                    path_colors
+                                        .overrides
+                                        .insert(name.clone(), color);
+                                    changed = true;
+                                }
+
+                                let overridden =
+                                    path_colors.overrides.contains_key(name);
+                                if ui
+                                    .add_enabled(
+                                        overridden,
+                                        egui::Button::new("Reset").small(),
+                                    )
+                                    .clicked()
+                                {
+                                    path_colors.overrides.remove(name);
+                                    changed = true;
+                                }
+
+                                let mut shown =
+                                    path_colors.shown_2d.contains(name);
+                                if ui
+                                    .checkbox(&mut shown, "2D")
+                                    .on_hover_text(
+                                        "Draw the path in the 2D view",
+                                    )
+                                    .changed()
+                                {
+                                    path_colors.set_shown_2d(name, shown);
+                                    changed = true;
+                                }
+
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            if changed {
+                path_colors.mark_changed();
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
@@ -12,7 +12,16 @@ use taffy::{error::TaffyError, prelude::*};
 use ultraviolet::Vec2;
 
 pub mod annotations;
+pub mod coords;
+pub mod layers;
+pub mod measure;
+pub mod path_list;
+pub mod performance;
+pub mod pinned;
+pub mod theme;
+pub mod tour;
 pub mod util;
+pub mod zoom;
 
 /*
 Each row can contain its own inline grid layout, subject to parameters shared
@@ -1,12 +1,26 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use egui::scroll_area::ScrollAreaOutput;
+use tokio::sync::oneshot::{self, error::TryRecvError};
 use tokio::sync::RwLock;
+use waragraph_core::graph::{Bp, PathId, PathIndex};
 
+use crate::annotations::search::{AnnotationSearchIndex, SearchMode};
+use crate::annotations::style::{AnnotationStyle, StyleRule, ANY_VALUE};
 use crate::annotations::{
-    Annotation, AnnotationId, AnnotationStore, GlobalAnnotationId,
+    Annotation, AnnotationId, AnnotationSetId, AnnotationStore,
+    GlobalAnnotationId, USER_SET_NAME,
 };
+use crate::app::provenance::EventKind;
+use crate::app::selection::SelectionStore;
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::{AppMsg, SharedState};
+use crate::gui::coords::PosFormat;
 
 pub(crate) struct AnnotationListWidget {
     annotation_store: Arc<RwLock<AnnotationStore>>,
@@ -64,6 +78,15 @@ impl AnnotationListWidget {
             .annotation_sets
             .clone();
 
+        for set in annotation_sets.values() {
+            if set.out_of_bounds > 0 {
+                ui.label(format!(
+                    "{}: {} records past the end of their paths",
+                    set.name, set.out_of_bounds
+                ));
+            }
+        }
+
         let filter_entry = ui.text_edit_singleline(&mut self.filter_string);
 
         if filter_entry.changed() {
@@ -89,3 +112,678 @@ impl AnnotationListWidget {
         );
     }
 }
+
+/// Settings window panel for editing the rules that color the
+/// annotations of a set by attribute value.
+pub(crate) struct AnnotationStyleWidget {
+    annotation_store: Arc<RwLock<AnnotationStore>>,
+
+    set_id: Option<AnnotationSetId>,
+    // the attribute keys present in the selected set
+    attribute_keys: Vec<String>,
+    rules: Vec<StyleRule>,
+}
+
+impl AnnotationStyleWidget {
+    pub fn new(annotation_store: Arc<RwLock<AnnotationStore>>) -> Self {
+        Self {
+            annotation_store,
+            set_id: None,
+            attribute_keys: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+
+    fn select_set(&mut self, set_id: AnnotationSetId) {
+        let store = self.annotation_store.blocking_read();
+
+        let keys = store
+            .annotation_sets
+            .get(&set_id)
+            .into_iter()
+            .flat_map(|set| set.annotations.iter())
+            .flat_map(|annot| annot.attributes.iter().map(|(k, _)| k))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        self.set_id = Some(set_id);
+        self.attribute_keys = keys.into_iter().collect();
+        self.rules = store
+            .style(set_id)
+            .map(|style| style.rules.clone())
+            .unwrap_or_default();
+    }
+}
+
+impl SettingsWidget for AnnotationStyleWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let sets = self
+            .annotation_store
+            .blocking_read()
+            .annotation_sets
+            .iter()
+            .map(|(id, set)| (*id, set.name.clone()))
+            .collect::<Vec<_>>();
+
+        let mut response = ui.label("Color annotations by attribute value");
+
+        if sets.is_empty() {
+            ui.label("No annotations loaded");
+            return SettingsUiResponse { response };
+        }
+
+        let selected_name = self
+            .set_id
+            .and_then(|id| sets.iter().find(|(s, _)| *s == id))
+            .map(|(_, name)| name.as_str())
+            .unwrap_or("Select annotations");
+
+        let mut new_set = None;
+
+        egui::ComboBox::from_label("Annotations")
+            .selected_text(selected_name)
+            .show_ui(ui, |ui| {
+                for (id, name) in sets.iter() {
+                    if ui
+                        .selectable_label(self.set_id == Some(*id), name)
+                        .clicked()
+                    {
+                        new_set = Some(*id);
+                    }
+                }
+            });
+
+        if let Some(set_id) = new_set {
+            self.select_set(set_id);
+        }
+
+        let Some(set_id) = self.set_id else {
+            return SettingsUiResponse { response };
+        };
+
+        let mut to_remove = None;
+
+        egui::Grid::new("annotation_style_rules").show(ui, |ui| {
+            ui.label("Attribute");
+            ui.label("Value");
+            ui.label("Color");
+            ui.end_row();
+
+            for (ix, rule) in self.rules.iter_mut().enumerate() {
+                egui::ComboBox::from_id_source(("style_rule_attr", ix))
+                    .selected_text(&rule.attribute)
+                    .show_ui(ui, |ui| {
+                        for key in self.attribute_keys.iter() {
+                            ui.selectable_value(
+                                &mut rule.attribute,
+                                key.clone(),
+                                key,
+                            );
+                        }
+                    });
+
+                ui.add(
+                    egui::TextEdit::singleline(&mut rule.value)
+                        .desired_width(100.0),
+                )
+                .on_hover_text("* matches any value");
+
+                ui.horizontal(|ui| {
+                    let mut hashed = rule.color.is_none();
+                    if ui
+                        .checkbox(&mut hashed, "By value")
+                        .on_hover_text("Pick a color per distinct value")
+                        .changed()
+                    {
+                        rule.color =
+                            (!hashed).then_some(egui::Color32::from_gray(200));
+                    }
+
+                    if let Some(color) = rule.color.as_mut() {
+                        ui.color_edit_button_srgba(color);
+                    }
+                });
+
+                if ui.button("Remove").clicked() {
+                    to_remove = Some(ix);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(ix) = to_remove {
+            self.rules.remove(ix);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add rule").clicked() {
+                let attribute = self
+                    .attribute_keys
+                    .iter()
+                    .find(|k| *k == "type")
+                    .or(self.attribute_keys.first())
+                    .cloned()
+                    .unwrap_or_default();
+
+                self.rules.push(StyleRule {
+                    attribute,
+                    value: ANY_VALUE.to_string(),
+                    color: None,
+                });
+            }
+
+            let apply = ui
+                .button("Apply")
+                .on_hover_text("Saves the rules next to the annotation file");
+
+            if apply.clicked() {
+                let style = AnnotationStyle {
+                    rules: self.rules.clone(),
+                };
+                self.annotation_store
+                    .blocking_write()
+                    .set_style(set_id, style);
+            }
+
+            response = response.union(apply);
+        });
+
+        SettingsUiResponse { response }
+    }
+}
+
+// the most matches that are listed
+const MAX_SEARCH_RESULTS: usize = 500;
+
+/// Settings window panel for searching annotations by label; clicking
+/// a match moves the viewers to the annotation.
+pub(crate) struct AnnotationSearchWidget {
+    shared: SharedState,
+    index: AnnotationSearchIndex,
+
+    query: String,
+    mode: SearchMode,
+
+    results: Vec<GlobalAnnotationId>,
+    error: Option<String>,
+}
+
+impl AnnotationSearchWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        let index =
+            AnnotationSearchIndex::new(&shared.annotations.blocking_read());
+
+        Self {
+            shared: shared.clone(),
+            index,
+            query: String::new(),
+            mode: SearchMode::default(),
+            results: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn search(&mut self) {
+        self.results.clear();
+        self.error = None;
+
+        if self.query.is_empty() {
+            return;
+        }
+
+        match self
+            .index
+            .search(&self.query, self.mode, MAX_SEARCH_RESULTS)
+        {
+            Ok(results) => self.results = results,
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+}
+
+impl SettingsWidget for AnnotationSearchWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let mut changed =
+            self.index.update(&self.shared.annotations.blocking_read());
+
+        let resp = ui.horizontal(|ui| {
+            let entry = ui.add(
+                egui::TextEdit::singleline(&mut self.query)
+                    .hint_text("Search annotations"),
+            );
+            let substr =
+                ui.radio_value(&mut self.mode, SearchMode::Substring, "Text");
+            let regex =
+                ui.radio_value(&mut self.mode, SearchMode::Regex, "Regex");
+
+            changed |= entry.changed() || substr.changed() || regex.changed();
+
+            entry
+        });
+
+        if changed {
+            self.search();
+        }
+
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::RED, error);
+        } else if !self.query.is_empty() {
+            if self.results.len() == MAX_SEARCH_RESULTS {
+                ui.label(format!("First {MAX_SEARCH_RESULTS} matches"));
+            } else {
+                ui.label(format!("{} matches", self.results.len()));
+            }
+        }
+
+        let annotations = self.shared.annotations.blocking_read();
+        let graph = &self.shared.graph;
+        let fmt = PosFormat::load(&self.shared);
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+
+        egui::ScrollArea::vertical().max_height(400.0).show_rows(
+            ui,
+            row_height,
+            self.results.len(),
+            |ui, range| {
+                for &id in &self.results[range] {
+                    let Some(set) = annotations.annotation_sets.get(&id.set_id)
+                    else {
+                        continue;
+                    };
+                    let Some(annot) = set.get(id.annot_id) else {
+                        continue;
+                    };
+
+                    ui.horizontal(|ui| {
+                        if ui.link(annot.label.as_str()).clicked() {
+                            let msg = AppMsg::GotoAnnotation(id);
+                            if let Err(e) =
+                                self.shared.app_msg_send.try_send(msg)
+                            {
+                                log::error!("{e:?}");
+                            }
+                        }
+
+                        ui.weak(format!(
+                            "{} {}",
+                            set.name,
+                            fmt.path_range(graph, annot.path, &annot.range)
+                        ));
+                    });
+                }
+            },
+        );
+
+        SettingsUiResponse {
+            response: resp.inner,
+        }
+    }
+}
+
+/// The part of `path` from its first to its last step on a selected
+/// node or in a selected range, or `None` if the path doesn't visit
+/// the selection.
+fn selection_path_range(
+    graph: &PathIndex,
+    path: PathId,
+    selection: &SelectionStore,
+) -> Option<Range<Bp>> {
+    let steps = graph.path_steps.get(path.ix())?;
+
+    let mut range: Option<Range<u64>> = None;
+    let mut offset = 0u64;
+
+    for step in steps {
+        let len = graph.node_length(step.node()).0;
+
+        let in_range = || {
+            let node_range = graph.node_pangenome_range(step.node());
+            selection
+                .ranges()
+                .iter()
+                .any(|r| r.start < node_range.end && node_range.start < r.end)
+        };
+
+        if selection.contains(step.node()) || in_range() {
+            let r = range.get_or_insert(offset..offset);
+            r.end = offset + len;
+        }
+
+        offset += len;
+    }
+
+    range.map(|r| Bp(r.start)..Bp(r.end))
+}
+
+/// Settings window panel for the annotations created in the viewer:
+/// annotating the selected nodes, editing labels and colors, and
+/// exporting a set as BED.
+pub(crate) struct UserAnnotationsWidget {
+    shared: SharedState,
+
+    set_id: Option<AnnotationSetId>,
+
+    label: String,
+    // `None` to annotate the selected paths, or every path through
+    // the selection if none are selected
+    path: Option<PathId>,
+
+    export_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    error: Option<String>,
+}
+
+impl UserAnnotationsWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            shared: shared.clone(),
+            set_id: None,
+            label: "region".to_string(),
+            path: None,
+            export_path_recv: None,
+            error: None,
+        }
+    }
+
+    fn annotate_selection(&mut self) {
+        let graph = &self.shared.graph;
+        let selection = self.shared.selection.blocking_read();
+
+        let paths = match self.path {
+            Some(path) => vec![path],
+            None if selection.paths().next().is_some() => {
+                selection.paths().collect()
+            }
+            None => graph.path_names.left_values().copied().collect(),
+        };
+
+        let mut store = self.shared.annotations.blocking_write();
+        let label = Arc::new(self.label.clone());
+
+        for path in paths {
+            let Some(range) = selection_path_range(graph, path, &selection)
+            else {
+                continue;
+            };
+
+            let annot = Annotation {
+                path,
+                range,
+                label: label.clone(),
+                color: None,
+                blocks: Vec::new(),
+                out_of_bounds: false,
+                attributes: Vec::new(),
+                label_color: None,
+            };
+
+            let id = store.add_annotation(USER_SET_NAME, annot);
+            self.set_id = Some(id.set_id);
+        }
+    }
+
+    fn export(
+        &self,
+        set_id: AnnotationSetId,
+        path: &Path,
+    ) -> anyhow::Result<()> {
+        let store = self.shared.annotations.blocking_read();
+        let set = store
+            .annotation_sets
+            .get(&set_id)
+            .ok_or_else(|| anyhow::anyhow!("The annotation set was removed"))?;
+
+        let out = std::io::BufWriter::new(std::fs::File::create(path)?);
+        set.write_bed(&self.shared.graph, out)?;
+
+        log::warn!("Wrote {} annotations to {path:?}", set.annotations.len());
+
+        Ok(())
+    }
+
+    fn poll_export(&mut self) {
+        let Some(mut recv) = self.export_path_recv.take() else {
+            return;
+        };
+
+        match recv.try_recv() {
+            Ok(path) => {
+                let Some(set_id) = self.set_id else {
+                    return;
+                };
+                if let Err(e) = self.export(set_id, &path) {
+                    log::error!("Error exporting annotations: {e:?}");
+                    self.error = Some(e.to_string());
+                } else {
+                    self.shared.provenance.blocking_write().record_path(
+                        EventKind::Export,
+                        "User annotations",
+                        &path,
+                    );
+                }
+            }
+            Err(TryRecvError::Empty) => self.export_path_recv = Some(recv),
+            Err(TryRecvError::Closed) => {}
+        }
+    }
+}
+
+impl SettingsWidget for UserAnnotationsWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_export();
+
+        let graph = self.shared.graph.clone();
+        let fmt = PosFormat::load(&self.shared);
+
+        let (selected, selected_ranges, paths_selected) = {
+            let selection = self.shared.selection.blocking_read();
+            (
+                selection.nodes().count(),
+                selection.ranges().len(),
+                selection.paths().next().is_some(),
+            )
+        };
+
+        let mut response = ui.label(format!(
+            "{selected} nodes and {selected_ranges} ranges selected"
+        ));
+
+        ui.horizontal(|ui| {
+            ui.label("Label");
+            ui.text_edit_singleline(&mut self.label);
+        });
+
+        let all_paths = if paths_selected {
+            "Selected paths"
+        } else {
+            "All paths"
+        };
+
+        let path_name = self
+            .path
+            .and_then(|p| graph.path_names.get_by_left(&p))
+            .map(|n| n.as_str())
+            .unwrap_or(all_paths);
+
+        egui::ComboBox::from_label("On path")
+            .selected_text(path_name)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.path, None, all_paths);
+                for (path, name) in graph.path_names.iter() {
+                    ui.selectable_value(&mut self.path, Some(*path), name);
+                }
+            });
+
+        let annotate = ui
+            .add_enabled(
+                selected + selected_ranges > 0,
+                egui::Button::new("Annotate selection"),
+            )
+            .on_hover_text(
+                "Adds an annotation spanning the selected nodes and \
+                 ranges on each path that visits them; select nodes in \
+                 the 2D view, or shift-drag in the 1D view to select a \
+                 region",
+            );
+
+        if annotate.clicked() {
+            self.annotate_selection();
+        }
+        response = response.union(annotate);
+
+        ui.separator();
+
+        let sets = self
+            .shared
+            .annotations
+            .blocking_read()
+            .user_sets()
+            .map(|(id, set)| (id, set.name.clone()))
+            .collect::<Vec<_>>();
+
+        if self.set_id.is_none() {
+            self.set_id = sets.first().map(|(id, _)| *id);
+        }
+
+        let Some(set_id) = self.set_id else {
+            ui.label("No annotations created yet");
+            return SettingsUiResponse { response };
+        };
+
+        let set_name = sets
+            .iter()
+            .find(|(id, _)| *id == set_id)
+            .map(|(_, name)| name.as_str())
+            .unwrap_or(USER_SET_NAME);
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Annotations")
+                .selected_text(set_name)
+                .show_ui(ui, |ui| {
+                    for (id, name) in sets.iter() {
+                        ui.selectable_value(&mut self.set_id, Some(*id), name);
+                    }
+                });
+
+            let export = ui.add_enabled(
+                self.export_path_recv.is_none(),
+                egui::Button::new("Export BED"),
+            );
+
+            if export.clicked() {
+                let path = self
+                    .shared
+                    .export_paths
+                    .blocking_read()
+                    .export_path("annotations", None, "bed");
+
+                let id = egui::Id::new("UserAnnotationsWidget_export");
+                let recv = settings_ctx.save_file_oneshot(id, path);
+                self.export_path_recv = Some(recv);
+                self.error = None;
+            }
+        });
+
+        if let Some(err) = self.error.as_ref() {
+            ui.label(format!("Error: {err}"));
+        }
+
+        let annots = self
+            .shared
+            .annotations
+            .blocking_read()
+            .annotation_sets
+            .get(&set_id)
+            .map(|set| set.annotations.clone())
+            .unwrap_or_default();
+
+        let mut edits = Vec::new();
+        let mut to_remove = None;
+
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .show(ui, |ui| {
+                egui::Grid::new("UserAnnotationsWidget-annotations")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (ix, annot) in annots.iter().enumerate() {
+                            let id = GlobalAnnotationId {
+                                set_id,
+                                annot_id: AnnotationId(ix),
+                            };
+
+                            let mut label = annot.label.to_string();
+                            let label_edit = ui.add(
+                                egui::TextEdit::singleline(&mut label)
+                                    .desired_width(120.0),
+                            );
+
+                            let mut color = annot.color;
+                            let mut colored = color.is_some();
+                            ui.horizontal(|ui| {
+                                if ui.checkbox(&mut colored, "").changed() {
+                                    color = colored.then_some(
+                                        egui::Color32::from_gray(200),
+                                    );
+                                }
+                                if let Some(color) = color.as_mut() {
+                                    ui.color_edit_button_srgba(color);
+                                }
+                            });
+
+                            if label_edit.changed() || color != annot.color {
+                                edits.push((id, label, color));
+                            }
+
+                            let range = fmt.path_range(
+                                &graph,
+                                annot.path,
+                                &annot.range,
+                            );
+                            if ui.link(range).clicked() {
+                                let msg = AppMsg::GotoAnnotation(id);
+                                if let Err(e) =
+                                    self.shared.app_msg_send.try_send(msg)
+                                {
+                                    log::error!("{e:?}");
+                                }
+                            }
+
+                            if ui.button("Remove").clicked() {
+                                to_remove = Some(id);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if !edits.is_empty() || to_remove.is_some() {
+            let mut store = self.shared.annotations.blocking_write();
+
+            for (id, label, color) in edits {
+                store.edit_annotation(id, |annot| {
+                    annot.label = Arc::new(label);
+                    annot.color = color;
+                });
+            }
+
+            if let Some(id) = to_remove {
+                store.remove_annotation(id);
+            }
+        }
+
+        SettingsUiResponse { response }
+    }
+}
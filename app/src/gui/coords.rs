@@ -0,0 +1,243 @@
+//! Settings panel for the coordinate convention used for the
+//! positions shown in the viewers and entered by the user, and for
+//! showing pangenome positions relative to a reference path.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use crossbeam::atomic::AtomicCell;
+use waragraph_core::graph::coords::{project_to_path, CoordSystem};
+use waragraph_core::graph::reference::ReferenceGuess;
+use waragraph_core::graph::{Bp, PathId, PathIndex};
+
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::SharedState;
+use crate::viewer_1d::control::path_range_to_pangenome;
+
+/// How pangenome positions are shown: in a coordinate convention,
+/// and either as they are or projected onto a reference path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PosFormat {
+    pub coords: CoordSystem,
+    pub reference: Option<PathId>,
+}
+
+impl PosFormat {
+    pub fn load(shared: &SharedState) -> Self {
+        Self {
+            coords: shared.coords.load(),
+            reference: shared.reference_coords.load(),
+        }
+    }
+
+    fn ref_name<'a>(&self, graph: &'a PathIndex, path: PathId) -> &'a str {
+        graph
+            .path_names
+            .get_by_left(&path)
+            .map(|n| n.as_str())
+            .unwrap_or("reference")
+    }
+
+    /// Names the convention, and the reference path if any.
+    pub fn label(&self, graph: &PathIndex) -> String {
+        match self.reference {
+            Some(path) => format!(
+                "{}, on {}",
+                self.coords.short_name(),
+                self.ref_name(graph, path)
+            ),
+            None => self.coords.short_name().to_string(),
+        }
+    }
+
+    /// The base at `pos`; on a reference, as `path:pos`, with a `~`
+    /// if the reference doesn't visit the base.
+    pub fn pos(&self, graph: &PathIndex, pos: Bp) -> String {
+        let Some(path) = self.reference else {
+            return self.coords.display_pos(pos).to_string();
+        };
+
+        let name = self.ref_name(graph, path);
+        match project_to_path(graph, path, pos) {
+            Some(p) => {
+                let approx = if p.exact { "" } else { "~" };
+                format!("{name}:{approx}{}", self.coords.display_pos(p.pos))
+            }
+            None => format!("{name}:NA"),
+        }
+    }
+
+    /// The end of a range ending at `end`, which is the same in both
+    /// conventions.
+    pub fn end(&self, graph: &PathIndex, end: Bp) -> String {
+        let Some(path) = self.reference else {
+            return end.0.to_string();
+        };
+
+        match project_to_path(graph, path, Bp(end.0.saturating_sub(1))) {
+            Some(p) if p.exact => (p.pos.0 + 1).to_string(),
+            Some(p) => format!("~{}", p.pos.0),
+            None => "NA".to_string(),
+        }
+    }
+
+    /// A pangenome range as `start-end`; on a reference, as
+    /// `path:start-end`, with a `~` if an end isn't on the path.
+    pub fn range(&self, graph: &PathIndex, range: &Range<Bp>) -> String {
+        let Some(path) = self.reference else {
+            return self.coords.format_range(range);
+        };
+
+        let name = self.ref_name(graph, path);
+        let start = project_to_path(graph, path, range.start);
+        let last = project_to_path(graph, path, Bp(range.end.0.max(1) - 1));
+
+        let Some((start, last)) = start.zip(last) else {
+            return format!("{name}:NA");
+        };
+
+        let approx = if start.exact && last.exact { "" } else { "~" };
+        // the reference may run through the range in reverse
+        let (a, b) = (start.pos.min(last.pos), start.pos.max(last.pos));
+        let end = if last.exact {
+            Bp(b.0 + 1)
+        } else {
+            b.max(Bp(a.0 + 1))
+        };
+
+        format!("{name}:{approx}{}", self.coords.format_range(&(a..end)))
+    }
+
+    /// A range of a path, e.g. an annotation, as `path:start-end`; on
+    /// a reference, projected onto it through the pangenome.
+    pub fn path_range(
+        &self,
+        graph: &PathIndex,
+        path: PathId,
+        range: &Range<Bp>,
+    ) -> String {
+        if self.reference.is_some() && self.reference != Some(path) {
+            if let Some(pan) =
+                path_range_to_pangenome(graph, path, range.clone())
+            {
+                return self.range(graph, &pan);
+            }
+        }
+
+        let name = self.ref_name(graph, path);
+        format!("{name}:{}", self.coords.format_range(range))
+    }
+}
+
+pub struct CoordSystemWidget {
+    graph: Arc<PathIndex>,
+    coords: Arc<AtomicCell<CoordSystem>>,
+    reference: Arc<AtomicCell<Option<PathId>>>,
+    detected: Option<ReferenceGuess>,
+}
+
+impl CoordSystemWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            graph: shared.graph.clone(),
+            coords: shared.coords.clone(),
+            reference: shared.reference_coords.clone(),
+            detected: shared.detected_reference.clone(),
+        }
+    }
+}
+
+impl SettingsWidget for CoordSystemWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let mut coords = self.coords.load();
+        let mut reference = self.reference.load();
+
+        let resp = ui.vertical(|ui| {
+            for c in CoordSystem::ALL {
+                ui.radio_value(&mut coords, c, c.name());
+            }
+
+            ui.label(
+                "Used for the view range and cursor position, the \
+                 position fields, region lists, and exported tables, \
+                 which name the convention in a comment.",
+            );
+            ui.weak(
+                "BED files are always read as 0-based and GFF as \
+                 1-based, and scripts and view links always use \
+                 0-based, half-open ranges.",
+            );
+
+            ui.separator();
+
+            let detected = self.detected.as_ref().map(|guess| guess.path);
+            let path_label = |path: PathId| {
+                let name = self
+                    .graph
+                    .path_names
+                    .get_by_left(&path)
+                    .map(|name| name.as_str())
+                    .unwrap_or_default();
+                if Some(path) == detected {
+                    format!("{name} (detected)")
+                } else {
+                    name.to_string()
+                }
+            };
+
+            let selected = reference
+                .map(path_label)
+                .unwrap_or_else(|| "Pangenome".to_string());
+
+            egui::ComboBox::from_label("Show positions on")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut reference, None, "Pangenome");
+                    let mut paths = self
+                        .graph
+                        .path_names
+                        .left_values()
+                        .copied()
+                        .collect::<Vec<_>>();
+                    paths.sort();
+                    for path in paths {
+                        let label = path_label(path);
+                        ui.selectable_value(&mut reference, Some(path), label);
+                    }
+                });
+
+            if let Some(guess) = self.detected.as_ref() {
+                ui.horizontal(|ui| {
+                    let name = self.graph.path_names.get_by_left(&guess.path);
+                    let name = name.map(|n| n.as_str()).unwrap_or_default();
+                    ui.weak(format!("Detected {name}: {}", guess.reason));
+                    if reference != Some(guess.path)
+                        && ui.small_button("Use").clicked()
+                    {
+                        reference = Some(guess.path);
+                    }
+                });
+            }
+
+            ui.weak(
+                "On a reference path, the 1D ruler, the tooltips, and \
+                 annotation ranges show positions on the path; bases \
+                 the path skips are marked with ~ and shown at the end \
+                 of the path's previous node.",
+            );
+        });
+
+        self.coords.store(coords);
+        self.reference.store(reference);
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Drawing order of the shapes the viewers draw on top of the graph.
+//!
+//! Each viewer's render graph has a single draw node (the path slots
+//! in the 1D view, the nodes in the 2D view), which always runs before
+//! egui. Everything else is drawn as egui shapes, tagged with a
+//! [`Layer`], and painted sorted by layer, so shapes composite in the
+//! same order no matter which part of the viewer added them first.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    /// Bands spanning the view, e.g. selected ranges
+    Background,
+    /// Quantitative tracks, their axes and legends
+    DataTracks,
+    /// Shapes drawn over the graph itself, e.g. the 2D path overlay
+    Nodes,
+    /// Selection and hover highlights
+    Highlights,
+    /// Annotation spans and labels
+    Annotations,
+    /// Names, messages, spinners and other UI drawn in the view
+    Ui,
+}
+
+#[derive(Default)]
+pub struct LayeredShapes {
+    shapes: Vec<(Layer, egui::Shape)>,
+}
+
+impl LayeredShapes {
+    pub fn push(&mut self, layer: Layer, shape: egui::Shape) {
+        self.shapes.push((layer, shape));
+    }
+
+    pub fn extend(
+        &mut self,
+        layer: Layer,
+        shapes: impl IntoIterator<Item = egui::Shape>,
+    ) {
+        self.shapes.extend(shapes.into_iter().map(|s| (layer, s)));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// The shapes in drawing order: sorted by layer, and in the order
+    /// they were added within each layer.
+    pub fn into_sorted(mut self) -> Vec<egui::Shape> {
+        self.shapes.sort_by_key(|(layer, _)| *layer);
+        self.shapes.into_iter().map(|(_, shape)| shape).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dot(x: f32) -> egui::Shape {
+        egui::Shape::circle_filled(egui::pos2(x, 0.0), 1.0, egui::Color32::RED)
+    }
+
+    fn xs(shapes: Vec<egui::Shape>) -> Vec<f32> {
+        shapes
+            .into_iter()
+            .map(|s| s.visual_bounding_rect().center().x)
+            .collect()
+    }
+
+    #[test]
+    fn sorted_by_layer_then_insertion() {
+        let mut shapes = LayeredShapes::default();
+        shapes.push(Layer::Ui, dot(5.0));
+        shapes.push(Layer::Highlights, dot(3.0));
+        shapes.push(Layer::Background, dot(0.0));
+        shapes.extend(Layer::Highlights, [dot(4.0)]);
+        shapes.push(Layer::DataTracks, dot(1.0));
+        shapes.push(Layer::DataTracks, dot(2.0));
+
+        assert_eq!(
+            xs(shapes.into_sorted()),
+            vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]
+        );
+    }
+}
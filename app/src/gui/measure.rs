@@ -0,0 +1,271 @@
+//! Measure mode: click two points, in either viewer, to show the
+//! distance between them along the pangenome, along a path, and, for
+//! points picked in the 2D view, in the layout.
+
+use std::sync::Arc;
+
+use crossbeam::atomic::AtomicCell;
+use tokio::sync::RwLock;
+use waragraph_core::graph::coords::{project_to_path, CoordSystem};
+use waragraph_core::graph::{Bp, PathId, PathIndex};
+
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::SharedState;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeasurePoint {
+    /// The clicked base, in pangenome coordinates
+    pub pos: Bp,
+    /// Set for points picked in the 2D view
+    pub layout_pos: Option<[f32; 2]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub pangenome: u64,
+    /// `None` if either point is on a node the path doesn't visit
+    pub path: Option<u64>,
+    /// `None` unless both points were picked in the 2D view
+    pub layout: Option<f32>,
+}
+
+#[derive(Debug, Default)]
+pub struct MeasureTool {
+    /// While active, clicks in the viewers add points instead of
+    /// changing the selection
+    pub active: bool,
+    points: Vec<MeasurePoint>,
+    pub path: Option<PathId>,
+}
+
+impl MeasureTool {
+    pub fn points(&self) -> &[MeasurePoint] {
+        &self.points
+    }
+
+    /// Adds a point, starting a new measurement if there already are
+    /// two.
+    pub fn add_point(&mut self, point: MeasurePoint) {
+        if self.points.len() >= 2 {
+            self.points.clear();
+        }
+        self.points.push(point);
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    pub fn measurement(&self, graph: &PathIndex) -> Option<Measurement> {
+        let [a, b] = self.points.as_slice() else {
+            return None;
+        };
+
+        let path = self.path.and_then(|path| {
+            let a = path_pos_at(graph, path, a.pos)?;
+            let b = path_pos_at(graph, path, b.pos)?;
+            Some(a.0.abs_diff(b.0))
+        });
+
+        let layout = a.layout_pos.zip(b.layout_pos).map(|(a, b)| {
+            let dx = b[0] - a[0];
+            let dy = b[1] - a[1];
+            (dx * dx + dy * dy).sqrt()
+        });
+
+        Some(Measurement {
+            pangenome: a.pos.0.abs_diff(b.pos.0),
+            path,
+            layout,
+        })
+    }
+}
+
+/// The position along `path` of the base at pangenome position `pos`,
+/// using the path's first visit to the node.
+pub fn path_pos_at(graph: &PathIndex, path: PathId, pos: Bp) -> Option<Bp> {
+    project_to_path(graph, path, pos)
+        .filter(|p| p.exact)
+        .map(|p| p.pos)
+}
+
+impl Measurement {
+    /// Tab-separated, for copying into a spreadsheet.
+    pub fn to_text(
+        &self,
+        a: &MeasurePoint,
+        b: &MeasurePoint,
+        coords: CoordSystem,
+        path_name: Option<&str>,
+    ) -> String {
+        let mut text = format!(
+            "from\t{}\nto\t{}\npangenome_bp\t{}\n",
+            coords.display_pos(a.pos),
+            coords.display_pos(b.pos),
+            self.pangenome
+        );
+
+        if let Some(name) = path_name {
+            let dist = self
+                .path
+                .map(|d| d.to_string())
+                .unwrap_or_else(|| "NA".to_string());
+            text.push_str(&format!("path\t{name}\npath_bp\t{dist}\n"));
+        }
+
+        if let Some(dist) = self.layout {
+            text.push_str(&format!("layout\t{dist:.2}\n"));
+        }
+
+        text
+    }
+}
+
+/// Settings window panel for measure mode and its results.
+pub struct MeasureWidget {
+    graph: Arc<PathIndex>,
+    measure: Arc<RwLock<MeasureTool>>,
+    coords: Arc<AtomicCell<CoordSystem>>,
+}
+
+impl MeasureWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            graph: shared.graph.clone(),
+            measure: shared.measure.clone(),
+            coords: shared.coords.clone(),
+        }
+    }
+}
+
+impl SettingsWidget for MeasureWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let mut measure = self.measure.blocking_write();
+        let coords = self.coords.load();
+        let graph = &self.graph;
+
+        let path_name =
+            |path: PathId| graph.path_names.get_by_left(&path).cloned();
+
+        let resp = ui.vertical(|ui| {
+            ui.checkbox(&mut measure.active, "Measure mode")
+                .on_hover_text(
+                    "Click two points in the 1D or 2D view; the selection \
+                     is left as it is while this is on",
+                );
+
+            let selected = measure
+                .path
+                .and_then(path_name)
+                .unwrap_or_else(|| "None".to_string());
+
+            egui::ComboBox::from_label("Path")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut measure.path, None, "None");
+                    for (path, name) in graph.path_names.iter() {
+                        ui.selectable_value(
+                            &mut measure.path,
+                            Some(*path),
+                            name,
+                        );
+                    }
+                });
+
+            ui.separator();
+
+            egui::Grid::new("MeasureWidget-points").show(ui, |ui| {
+                for (label, point) in ["From", "To"]
+                    .into_iter()
+                    .zip(measure.points().iter().map(Some).chain([None; 2]))
+                {
+                    ui.label(label);
+                    match point {
+                        Some(p) => {
+                            ui.label(format!("{}", coords.display_pos(p.pos)))
+                        }
+                        None => ui.weak("click a point"),
+                    };
+                    ui.end_row();
+                }
+            });
+
+            let Some(m) = measure.measurement(graph) else {
+                if ui.button("Clear").clicked() {
+                    measure.clear();
+                }
+                return;
+            };
+
+            egui::Grid::new("MeasureWidget-result").striped(true).show(
+                ui,
+                |ui| {
+                    ui.label("Pangenome");
+                    ui.label(format!("{} bp", m.pangenome));
+                    ui.end_row();
+
+                    if measure.path.is_some() {
+                        ui.label("Along path");
+                        match m.path {
+                            Some(d) => ui.label(format!("{d} bp")),
+                            None => ui.weak("not on the path"),
+                        };
+                        ui.end_row();
+                    }
+
+                    if let Some(d) = m.layout {
+                        ui.label("Layout");
+                        ui.label(format!("{d:.2} units"));
+                        ui.end_row();
+                    }
+                },
+            );
+
+            ui.horizontal(|ui| {
+                if ui.button("Copy").clicked() {
+                    let [a, b] = measure.points() else {
+                        unreachable!();
+                    };
+                    let name = measure.path.and_then(path_name);
+                    let text = m.to_text(a, b, coords, name.as_deref());
+                    ui.output_mut(|o| o.copied_text = text);
+                }
+
+                if ui.button("Clear").clicked() {
+                    measure.clear();
+                }
+            });
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_start_over_after_two() {
+        let point = |pos| MeasurePoint {
+            pos: Bp(pos),
+            layout_pos: None,
+        };
+
+        let mut measure = MeasureTool::default();
+        measure.add_point(point(10));
+        measure.add_point(point(40));
+        assert_eq!(measure.points(), &[point(10), point(40)]);
+
+        measure.add_point(point(5));
+        assert_eq!(measure.points(), &[point(5)]);
+    }
+}
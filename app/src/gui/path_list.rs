@@ -0,0 +1,406 @@
+//! The path list in the side panels of the viewers: every path in the
+//! graph, with a fuzzy filter, toggles for showing each path in the 1D
+//! path list and as a line in the 2D view, and solo and mute toggles
+//! for the 1D path list, for a path or all paths of a sample. Clicking
+//! a path name selects the path.
+
+use std::sync::Arc;
+
+use egui::collapsing_header::CollapsingState;
+use tokio::sync::RwLock;
+use waragraph_core::graph::{PathId, PathIndex};
+
+use crate::app::selection::SelectionStore;
+use crate::color::paths::PathColors;
+
+/// Scores how well `query` matches `text`, if all of its characters
+/// appear in `text` in order, ignoring case; higher is better.
+/// Consecutive characters, and matches at the start of the text or
+/// of a `#`, `_`, `.` or `:` separated part, score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut prev_match: Option<usize> = None;
+    let mut text_chars = text.char_indices();
+    let mut prev_char: Option<char> = None;
+
+    for q in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            let (ix, c) = text_chars.next()?;
+            let before = prev_char.replace(c);
+
+            if !c.to_lowercase().eq(std::iter::once(q)) {
+                continue;
+            }
+
+            score += 1;
+            if prev_match.is_some_and(|p| p + 1 == ix) {
+                score += 4;
+            }
+            if before.map_or(true, |b| matches!(b, '#' | '_' | '.' | ':')) {
+                score += 3;
+            }
+            prev_match = Some(ix);
+            break;
+        }
+    }
+
+    // shorter names rank higher among equal matches
+    Some(score * 1000 - text.len().min(999) as i32)
+}
+
+/// The sample part of a PanSN path name (`sample#haplotype#contig`),
+/// or the whole name if it has no `#`.
+pub fn sample_name(path_name: &str) -> &str {
+    path_name.split('#').next().unwrap_or(path_name)
+}
+
+pub struct PathListWidget {
+    graph: Arc<PathIndex>,
+    path_colors: Arc<RwLock<PathColors>>,
+    selection: Arc<RwLock<SelectionStore>>,
+
+    filter: String,
+    group_by_sample: bool,
+
+    // the paths matching the filter, best first, with the filter
+    // they were found with
+    matches: Option<(String, Arc<Vec<PathId>>)>,
+}
+
+impl PathListWidget {
+    pub fn new(shared: &crate::app::SharedState) -> Self {
+        Self {
+            graph: shared.graph.clone(),
+            path_colors: shared.path_colors.clone(),
+            selection: shared.selection.clone(),
+            filter: String::new(),
+            group_by_sample: false,
+            matches: None,
+        }
+    }
+
+    fn matching_paths(&mut self) -> Arc<Vec<PathId>> {
+        if let Some((filter, paths)) = self.matches.as_ref() {
+            if filter == &self.filter {
+                return paths.clone();
+            }
+        }
+
+        let query = self.filter.trim();
+
+        let paths = if query.is_empty() {
+            let mut paths = self
+                .graph
+                .path_names
+                .left_values()
+                .copied()
+                .collect::<Vec<_>>();
+            paths.sort();
+            paths
+        } else {
+            let mut scored = self
+                .graph
+                .path_names
+                .iter()
+                .filter_map(|(path, name)| {
+                    Some((fuzzy_score(query, name)?, *path))
+                })
+                .collect::<Vec<_>>();
+            scored.sort_by(|(a, pa), (b, pb)| b.cmp(a).then(pa.cmp(pb)));
+            scored.into_iter().map(|(_, path)| path).collect()
+        };
+
+        let paths = Arc::new(paths);
+        self.matches = Some((self.filter.clone(), paths.clone()));
+        paths
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let paths = self.matching_paths();
+
+        ui.add(
+            egui::TextEdit::singleline(&mut self.filter)
+                .hint_text("Filter paths"),
+        );
+
+        let mut path_colors = self.path_colors.blocking_write();
+        let mut selection = self.selection.blocking_write();
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.group_by_sample, "Group by sample")
+                .on_hover_text(
+                    "Groups the paths by the part of the name before the \
+                     first `#`",
+                );
+
+            let shown = ui
+                .small_button("Show all")
+                .on_hover_text("Shows the listed paths in the 1D view");
+            let hidden = ui
+                .small_button("Hide all")
+                .on_hover_text("Hides the listed paths from the 1D view");
+
+            if shown.clicked() || hidden.clicked() {
+                for path in paths.iter() {
+                    if let Some(name) = self.graph.path_names.get_by_left(path)
+                    {
+                        path_colors.set_shown_1d(name, shown.clicked());
+                    }
+                }
+                changed = true;
+            }
+
+            let any_solo_mute =
+                !path_colors.soloed.is_empty() || !path_colors.muted.is_empty();
+            if ui
+                .add_enabled(
+                    any_solo_mute,
+                    egui::Button::new("Clear solo/mute").small(),
+                )
+                .clicked()
+            {
+                path_colors.clear_solo_mute();
+                changed = true;
+            }
+        });
+
+        ui.label(format!(
+            "{} of {} paths",
+            paths.len(),
+            self.graph.path_names.len()
+        ));
+
+        let scroll_area = egui::ScrollArea::vertical()
+            .id_source("PathListWidget-scroll")
+            .max_height(300.0);
+
+        if !self.group_by_sample {
+            let row_height = ui.spacing().interact_size.y;
+
+            scroll_area.show_rows(ui, row_height, paths.len(), |ui, range| {
+                for path in paths[range].iter() {
+                    changed |= path_row(
+                        ui,
+                        &self.graph,
+                        &mut path_colors,
+                        &mut selection,
+                        *path,
+                    );
+                }
+            });
+        } else {
+            scroll_area.show(ui, |ui| {
+                let mut groups: Vec<(&str, Vec<PathId>)> = Vec::new();
+
+                for path in paths.iter() {
+                    let Some(name) = self.graph.path_names.get_by_left(path)
+                    else {
+                        continue;
+                    };
+                    let sample = sample_name(name);
+
+                    match groups.iter_mut().find(|(s, _)| *s == sample) {
+                        Some((_, group)) => group.push(*path),
+                        None => groups.push((sample, vec![*path])),
+                    }
+                }
+
+                for (sample, group) in groups {
+                    let id = ui.make_persistent_id(("PathListGroup", sample));
+                    CollapsingState::load_with_default_open(
+                        ui.ctx(),
+                        id,
+                        false,
+                    )
+                    .show_header(ui, |ui| {
+                        changed |= group_header(
+                            ui,
+                            &self.graph,
+                            &mut path_colors,
+                            sample,
+                            &group,
+                        );
+                    })
+                    .body(|ui| {
+                        for path in group.iter() {
+                            changed |= path_row(
+                                ui,
+                                &self.graph,
+                                &mut path_colors,
+                                &mut selection,
+                                *path,
+                            );
+                        }
+                    });
+                }
+            });
+        }
+
+        if changed {
+            path_colors.mark_changed();
+        }
+    }
+}
+
+/// The 1D and 2D toggles, solo and mute toggles, and name of a path,
+/// with the name toggling the path's selection; returns true if the
+/// path's visibility changed.
+fn path_row(
+    ui: &mut egui::Ui,
+    graph: &PathIndex,
+    path_colors: &mut PathColors,
+    selection: &mut SelectionStore,
+    path: PathId,
+) -> bool {
+    let Some(name) = graph.path_names.get_by_left(&path) else {
+        return false;
+    };
+
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        let mut shown_1d = !path_colors.hidden_1d.contains(name);
+        if ui
+            .checkbox(&mut shown_1d, "1D")
+            .on_hover_text("Show the path in the 1D path list")
+            .changed()
+        {
+            path_colors.set_shown_1d(name, shown_1d);
+            changed = true;
+        }
+
+        let mut shown_2d = path_colors.shown_2d.contains(name);
+        if ui
+            .checkbox(&mut shown_2d, "2D")
+            .on_hover_text("Draw the path in the 2D view")
+            .changed()
+        {
+            path_colors.set_shown_2d(name, shown_2d);
+            changed = true;
+        }
+
+        changed |= solo_mute_toggles(ui, path_colors, &[name]);
+
+        let mut text = egui::RichText::new(name.as_str())
+            .color(path_colors.color(path, name));
+        if !path_colors.shown_1d(name) {
+            text = text.strikethrough();
+        }
+        if ui
+            .selectable_label(selection.contains_path(path), text)
+            .on_hover_text("Click to select the path in all views")
+            .clicked()
+        {
+            selection.toggle_path(path);
+        }
+    });
+
+    changed
+}
+
+/// The toggles of a group of paths, which are checked if all paths in
+/// the group are shown; returns true if the visibility changed.
+fn group_header(
+    ui: &mut egui::Ui,
+    graph: &PathIndex,
+    path_colors: &mut PathColors,
+    sample: &str,
+    group: &[PathId],
+) -> bool {
+    let names = group
+        .iter()
+        .filter_map(|path| graph.path_names.get_by_left(path))
+        .collect::<Vec<_>>();
+
+    let mut changed = false;
+
+    let mut shown_1d = names
+        .iter()
+        .all(|name| !path_colors.hidden_1d.contains(*name));
+    if ui.checkbox(&mut shown_1d, "1D").changed() {
+        for name in names.iter() {
+            path_colors.set_shown_1d(name, shown_1d);
+        }
+        changed = true;
+    }
+
+    let mut shown_2d = names
+        .iter()
+        .all(|name| path_colors.shown_2d.contains(*name));
+    if ui.checkbox(&mut shown_2d, "2D").changed() {
+        for name in names.iter() {
+            path_colors.set_shown_2d(name, shown_2d);
+        }
+        changed = true;
+    }
+
+    changed |= solo_mute_toggles(ui, path_colors, &names);
+
+    ui.label(format!("{sample} ({})", group.len()));
+
+    changed
+}
+
+/// The solo and mute toggles of one or more paths, which are on if
+/// they're on for all of them; returns true if either changed.
+fn solo_mute_toggles(
+    ui: &mut egui::Ui,
+    path_colors: &mut PathColors,
+    names: &[&String],
+) -> bool {
+    let mut changed = false;
+
+    let soloed = names.iter().all(|name| path_colors.soloed.contains(*name));
+    if ui
+        .selectable_label(soloed, "S")
+        .on_hover_text(
+            "Solo: while any paths are soloed, only they are shown in the \
+             1D path list",
+        )
+        .clicked()
+    {
+        for name in names {
+            path_colors.set_soloed(name, !soloed);
+        }
+        changed = true;
+    }
+
+    let muted = names.iter().all(|name| path_colors.muted.contains(*name));
+    if ui
+        .selectable_label(muted, "M")
+        .on_hover_text("Mute: hide from the 1D path list, even if soloed")
+        .clicked()
+    {
+        for name in names {
+            path_colors.set_muted(name, !muted);
+        }
+        changed = true;
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matching() {
+        assert!(fuzzy_score("hg2c20", "HG002#1#chr20").is_some());
+        assert!(fuzzy_score("chr21", "HG002#1#chr20").is_none());
+        assert!(fuzzy_score("", "anything").is_some());
+
+        // consecutive and part-start matches rank first
+        let contiguous = fuzzy_score("chr2", "HG002#1#chr20").unwrap();
+        let scattered = fuzzy_score("chr2", "c_h_r_2").unwrap();
+        assert!(contiguous > scattered);
+
+        let prefix = fuzzy_score("hg", "HG002#1#chr20").unwrap();
+        let inner = fuzzy_score("hg", "xhgx#1#chr20").unwrap();
+        assert!(prefix > inner);
+
+        assert_eq!(sample_name("HG002#1#chr20"), "HG002");
+        assert_eq!(sample_name("grch38"), "grch38");
+    }
+}
@@ -0,0 +1,251 @@
+//! Performance presets: one choice that trades drawing quality for
+//! speed across the viewers, for weaker hardware. A preset sets the
+//! zoom levels annotation labels and 2D path lines are drawn at (see
+//! `zoom`), how many rows of annotation labels the 1D view stacks, how
+//! many bins the 1D view samples paths into, and the frame rate limit.
+
+use std::sync::Arc;
+
+use crossbeam::atomic::AtomicCell;
+
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+
+use super::zoom::{ZoomLayer, ZoomRange, ZoomThresholds};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PerformancePreset {
+    #[default]
+    Quality,
+    Balanced,
+    Fast,
+}
+
+impl PerformancePreset {
+    pub const ALL: [PerformancePreset; 3] = [
+        PerformancePreset::Quality,
+        PerformancePreset::Balanced,
+        PerformancePreset::Fast,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PerformancePreset::Quality => "Quality",
+            PerformancePreset::Balanced => "Balanced",
+            PerformancePreset::Fast => "Fast",
+        }
+    }
+
+    pub fn settings(&self) -> PerformanceSettings {
+        match self {
+            PerformancePreset::Quality => PerformanceSettings {
+                preset: *self,
+                bin_count: 1024,
+                max_label_rows: None,
+                max_fps: None,
+            },
+            PerformancePreset::Balanced => PerformanceSettings {
+                preset: *self,
+                bin_count: 1024,
+                max_label_rows: Some(3),
+                max_fps: Some(60),
+            },
+            PerformancePreset::Fast => PerformanceSettings {
+                preset: *self,
+                bin_count: 512,
+                max_label_rows: Some(1),
+                max_fps: Some(30),
+            },
+        }
+    }
+
+    /// The zoom range the preset draws the layer at; the layers not
+    /// listed in `LIMITED_LAYERS` are left as they are.
+    pub fn zoom_range(&self, layer: ZoomLayer) -> ZoomRange {
+        let max_bp_per_px = match (self, layer) {
+            (PerformancePreset::Balanced, ZoomLayer::Labels) => 10_000.0,
+            (PerformancePreset::Fast, ZoomLayer::Labels) => 1_000.0,
+            (PerformancePreset::Fast, ZoomLayer::PathLines) => 10_000.0,
+            _ => return layer.default_range(),
+        };
+
+        ZoomRange {
+            min_bp_per_px: 0.0,
+            max_bp_per_px,
+        }
+    }
+
+    const LIMITED_LAYERS: [ZoomLayer; 2] =
+        [ZoomLayer::Labels, ZoomLayer::PathLines];
+
+    /// Sets the zoom ranges of the layers the preset limits.
+    pub fn apply_zoom(&self, thresholds: &mut ZoomThresholds) {
+        for layer in Self::LIMITED_LAYERS {
+            thresholds.set_range(layer, self.zoom_range(layer));
+        }
+    }
+}
+
+/// The settings of the current preset, read by the viewers every
+/// frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerformanceSettings {
+    pub preset: PerformancePreset,
+    /// Bins each path is sampled into in the 1D view
+    pub bin_count: usize,
+    /// Most rows of annotation labels stacked in each 1D slot, on top
+    /// of the 1D viewer's own setting
+    pub max_label_rows: Option<usize>,
+    /// `None` draws as often as possible
+    pub max_fps: Option<u32>,
+}
+
+impl std::default::Default for PerformanceSettings {
+    fn default() -> Self {
+        PerformancePreset::default().settings()
+    }
+}
+
+impl PerformanceSettings {
+    /// The time between frames, if the frame rate is limited.
+    pub fn frame_time(&self) -> Option<std::time::Duration> {
+        let fps = self.max_fps.filter(|&fps| fps > 0)?;
+        Some(std::time::Duration::from_secs_f64(1.0 / fps as f64))
+    }
+}
+
+/// Settings window panel to pick a preset, with a table of what each
+/// one sets.
+pub struct PerformanceWidget {
+    performance: Arc<AtomicCell<PerformanceSettings>>,
+    thresholds: Arc<AtomicCell<ZoomThresholds>>,
+}
+
+impl PerformanceWidget {
+    pub fn new(
+        performance: Arc<AtomicCell<PerformanceSettings>>,
+        thresholds: Arc<AtomicCell<ZoomThresholds>>,
+    ) -> Self {
+        Self {
+            performance,
+            thresholds,
+        }
+    }
+}
+
+fn format_max_bp_per_px(range: ZoomRange) -> String {
+    if range.max_bp_per_px.is_finite() {
+        format!("below {} bp/px", range.max_bp_per_px)
+    } else {
+        "always".to_string()
+    }
+}
+
+impl SettingsWidget for PerformanceWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let current = self.performance.load().preset;
+
+        let resp = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                for preset in PerformancePreset::ALL {
+                    if ui
+                        .selectable_label(preset == current, preset.name())
+                        .clicked()
+                        && preset != current
+                    {
+                        self.performance.store(preset.settings());
+
+                        let mut thresholds = self.thresholds.load();
+                        preset.apply_zoom(&mut thresholds);
+                        self.thresholds.store(thresholds);
+                    }
+                }
+            });
+
+            ui.weak(
+                "Use \"Balanced\" or \"Fast\" if the views are slow to \
+                 respond; the zoom levels can still be changed in \
+                 \"Zoom Levels\" afterward.",
+            );
+
+            egui::Grid::new("PerformanceWidget-presets")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("");
+                    for preset in PerformancePreset::ALL {
+                        if preset == current {
+                            ui.strong(preset.name());
+                        } else {
+                            ui.label(preset.name());
+                        }
+                    }
+                    ui.end_row();
+
+                    ui.label("1D bins per path");
+                    for preset in PerformancePreset::ALL {
+                        ui.label(preset.settings().bin_count.to_string());
+                    }
+                    ui.end_row();
+
+                    ui.label("1D label rows");
+                    for preset in PerformancePreset::ALL {
+                        ui.label(match preset.settings().max_label_rows {
+                            Some(rows) => format!("up to {rows}"),
+                            None => "as set".to_string(),
+                        });
+                    }
+                    ui.end_row();
+
+                    for layer in PerformancePreset::LIMITED_LAYERS {
+                        ui.label(layer.name());
+                        for preset in PerformancePreset::ALL {
+                            let range = preset.zoom_range(layer);
+                            ui.label(format_max_bp_per_px(range));
+                        }
+                        ui.end_row();
+                    }
+
+                    ui.label("Frame rate");
+                    for preset in PerformancePreset::ALL {
+                        ui.label(match preset.settings().max_fps {
+                            Some(fps) => format!("up to {fps} fps"),
+                            None => "unlimited".to_string(),
+                        });
+                    }
+                    ui.end_row();
+                });
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_limit_layers() {
+        let mut thresholds = ZoomThresholds::default();
+
+        PerformancePreset::Fast.apply_zoom(&mut thresholds);
+        assert!(!thresholds.visible(ZoomLayer::Labels, 5_000.0));
+        assert!(!thresholds.visible(ZoomLayer::PathLines, 1e5));
+        // other layers aren't changed
+        assert!(thresholds.visible(ZoomLayer::Nodes, 1e6));
+
+        PerformancePreset::Quality.apply_zoom(&mut thresholds);
+        assert_eq!(thresholds, ZoomThresholds::default());
+
+        let fast = PerformancePreset::Fast.settings();
+        assert!(fast.frame_time().unwrap().as_millis() >= 33);
+        assert_eq!(PerformanceSettings::default().frame_time(), None);
+    }
+}
@@ -0,0 +1,256 @@
+//! Pinned tooltips: pressing P while a tooltip is shown turns it into
+//! a floating card that stays open, with its content kept up to date,
+//! so several nodes, positions or annotations can be compared.
+
+use waragraph_core::graph::{Bp, Node, PathId};
+
+use crate::annotations::GlobalAnnotationId;
+use crate::app::{AppMsg, AppType, SharedState};
+use crate::gui::coords::PosFormat;
+use crate::viewer_1d::sequence_panel;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinnedItem {
+    Node(Node),
+    PathPos { path: PathId, pos: Bp },
+    Annotation(GlobalAnnotationId),
+}
+
+#[derive(Debug, Clone)]
+struct PinnedCard {
+    id: usize,
+    item: PinnedItem,
+    /// Cards are drawn in the viewer they were pinned in
+    viewer: AppType,
+    pos: egui::Pos2,
+}
+
+#[derive(Debug, Default)]
+pub struct PinnedCards {
+    cards: Vec<PinnedCard>,
+    next_id: usize,
+}
+
+/// True if the pin key was pressed this frame, and isn't being typed
+/// into a text field.
+pub fn pin_key_pressed(ctx: &egui::Context) -> bool {
+    !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(egui::Key::P))
+}
+
+impl PinnedCards {
+    /// Pins a card for the item at the cursor position `pos`, unless
+    /// the item is already pinned in the viewer.
+    pub fn pin(&mut self, viewer: AppType, item: PinnedItem, pos: egui::Pos2) {
+        if self
+            .cards
+            .iter()
+            .any(|card| card.viewer == viewer && card.item == item)
+        {
+            return;
+        }
+
+        self.cards.push(PinnedCard {
+            id: self.next_id,
+            item,
+            viewer,
+            pos,
+        });
+        self.next_id += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cards.clear();
+    }
+
+    /// Draws the cards pinned in `viewer`, removing those that are
+    /// closed.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        shared: &SharedState,
+        viewer: &AppType,
+    ) {
+        let mut closed = Vec::new();
+
+        for card in self.cards.iter().filter(|c| &c.viewer == viewer) {
+            let mut open = true;
+
+            egui::Window::new(card_title(shared, &card.item))
+                .id(egui::Id::new(("PinnedCard", card.id)))
+                .open(&mut open)
+                .default_pos(card.pos)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    card_contents(ui, shared, &card.item);
+
+                    if ui.button("Jump").clicked() {
+                        let msg = jump_msg(shared, &card.item);
+                        if let Err(e) = shared.app_msg_send.try_send(msg) {
+                            log::error!("Error jumping to pinned item: {e:?}");
+                        }
+                    }
+                });
+
+            if !open {
+                closed.push(card.id);
+            }
+        }
+
+        self.cards.retain(|card| !closed.contains(&card.id));
+    }
+}
+
+fn path_name(shared: &SharedState, path: PathId) -> &str {
+    shared
+        .graph
+        .path_names
+        .get_by_left(&path)
+        .map(|n| n.as_str())
+        .unwrap_or("ERROR")
+}
+
+fn card_title(shared: &SharedState, item: &PinnedItem) -> String {
+    match item {
+        PinnedItem::Node(node) => format!("Node {}", node.ix()),
+        PinnedItem::PathPos { path, pos } => {
+            let pos = shared.coords.load().display_pos(*pos);
+            format!("{}:{pos}", path_name(shared, *path))
+        }
+        PinnedItem::Annotation(id) => {
+            let annotations = shared.annotations.blocking_read();
+            annotations
+                .annotation_sets
+                .get(&id.set_id)
+                .and_then(|set| set.get(id.annot_id))
+                .map(|a| a.label.to_string())
+                .unwrap_or_else(|| "Annotation".to_string())
+        }
+    }
+}
+
+fn card_contents(ui: &mut egui::Ui, shared: &SharedState, item: &PinnedItem) {
+    let graph = &shared.graph;
+    let coords = shared.coords.load();
+
+    match *item {
+        PinnedItem::Node(node) => {
+            let len = graph.node_length(node);
+            let paths = graph
+                .path_node_sets
+                .iter()
+                .filter(|set| set.contains(node.ix() as u32))
+                .count();
+
+            ui.label(format!("Length {} bp", len.0));
+            ui.label(format!("On {paths} paths"));
+
+            if shared.selection.blocking_read().contains(node) {
+                ui.label("Selected");
+            }
+
+            let seq = graph.node_sequence(node);
+            let mut text =
+                String::from_utf8_lossy(&seq[..seq.len().min(32)]).into_owned();
+            if seq.len() > 32 {
+                text.push('…');
+            }
+            ui.monospace(text);
+        }
+        PinnedItem::PathPos { path, pos } => {
+            ui.label(format!("Path {}", path_name(shared, path)));
+            ui.label(format!(
+                "Pos {} bp ({})",
+                coords.display_pos(pos),
+                coords.short_name()
+            ));
+
+            if let Some(step) = graph.step_at_pos(path, pos.0) {
+                ui.label(format!("Node {}", step.node().ix()));
+            }
+
+            let seq = graph.path_sequence(
+                path,
+                Bp(pos.0.saturating_sub(10))..Bp(pos.0 + 11),
+            );
+            if let Some(seq) = seq {
+                let center = pos.0.min(10) as usize;
+                ui.monospace(sequence_panel::sequence_snippet(
+                    &seq, center, 10,
+                ));
+            }
+        }
+        PinnedItem::Annotation(id) => {
+            let annotations = shared.annotations.blocking_read();
+            let Some((set, annot)) =
+                annotations.annotation_sets.get(&id.set_id).and_then(|set| {
+                    let annot = set.get(id.annot_id)?;
+                    Some((set, annot))
+                })
+            else {
+                ui.weak("The annotation has been removed");
+                return;
+            };
+
+            ui.label(format!("Set {}", set.name));
+            ui.label(format!("Path {}", path_name(shared, annot.path)));
+            ui.label(format!(
+                "Range {} ({})",
+                coords.format_range(&annot.range),
+                coords.short_name()
+            ));
+
+            let fmt = PosFormat::load(shared);
+            if fmt.reference.is_some_and(|r| r != annot.path) {
+                let range =
+                    fmt.path_range(&shared.graph, annot.path, &annot.range);
+                ui.label(format!("Ref {range}"));
+            }
+        }
+    }
+}
+
+fn jump_msg(shared: &SharedState, item: &PinnedItem) -> AppMsg {
+    match *item {
+        PinnedItem::Node(node) => AppMsg::GotoNode(node),
+        PinnedItem::PathPos { path, pos } => {
+            let end = shared
+                .graph
+                .path_len(path)
+                .map(|len| len.0)
+                .unwrap_or(pos.0 + 100)
+                .min(pos.0 + 100);
+            AppMsg::GotoRegion {
+                path: Some(path),
+                range: Bp(pos.0.saturating_sub(100))..Bp(end.max(pos.0 + 1)),
+            }
+        }
+        PinnedItem::Annotation(id) => AppMsg::GotoAnnotation(id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinning_twice_keeps_one_card() {
+        let mut cards = PinnedCards::default();
+        let pos = egui::pos2(0.0, 0.0);
+
+        cards.pin(AppType::Viewer1D, PinnedItem::Node(Node::from(3u32)), pos);
+        cards.pin(AppType::Viewer1D, PinnedItem::Node(Node::from(3u32)), pos);
+        cards.pin(AppType::Viewer2D, PinnedItem::Node(Node::from(3u32)), pos);
+        assert_eq!(cards.len(), 2);
+
+        cards.clear();
+        assert!(cards.is_empty());
+    }
+}
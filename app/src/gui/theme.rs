@@ -0,0 +1,148 @@
+//! Colors and outlines for the viewers' overlays (labels, selection
+//! and hover highlights), with a high-contrast variant for projectors
+//! and low-vision users.
+
+use std::sync::Arc;
+
+use crossbeam::atomic::AtomicCell;
+use egui::{Color32, Stroke};
+
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub high_contrast: bool,
+}
+
+impl Theme {
+    const HALO_OFFSETS: [[f32; 2]; 8] = [
+        [-1.0, -1.0],
+        [0.0, -1.0],
+        [1.0, -1.0],
+        [-1.0, 0.0],
+        [1.0, 0.0],
+        [-1.0, 1.0],
+        [0.0, 1.0],
+        [1.0, 1.0],
+    ];
+
+    pub fn label_color(&self) -> Color32 {
+        Color32::WHITE
+    }
+
+    pub fn selection_color(&self) -> Color32 {
+        if self.high_contrast {
+            Color32::from_rgb(0, 255, 255)
+        } else {
+            Color32::YELLOW
+        }
+    }
+
+    pub fn hover_color(&self) -> Color32 {
+        if self.high_contrast {
+            Color32::from_rgb(255, 0, 255)
+        } else {
+            Color32::RED
+        }
+    }
+
+    /// Fill for regions covered by the selection in the 1D viewer.
+    pub fn selection_fill(&self) -> Color32 {
+        let [r, g, b, _] = self.selection_color().to_array();
+        let alpha = if self.high_contrast { 96 } else { 48 };
+        Color32::from_rgba_unmultiplied(r, g, b, alpha)
+    }
+
+    /// Scales the width of highlight strokes.
+    pub fn stroke_width(&self, width: f32) -> f32 {
+        if self.high_contrast {
+            width * 1.5
+        } else {
+            width
+        }
+    }
+
+    /// Dark outline drawn beneath highlight strokes, in high-contrast
+    /// mode, so that they stand out against light nodes as well.
+    pub fn outline(&self, stroke: Stroke) -> Option<Stroke> {
+        self.high_contrast
+            .then(|| Stroke::new(stroke.width + 3.0, Color32::BLACK))
+    }
+
+    /// The stroke for a highlight, preceded by its outline if any.
+    pub fn outlined_strokes(&self, width: f32, color: Color32) -> Vec<Stroke> {
+        let stroke = Stroke::new(self.stroke_width(width), color);
+        self.outline(stroke).into_iter().chain([stroke]).collect()
+    }
+
+    /// A text shape, drawn on top of a dark halo in high-contrast
+    /// mode.
+    pub fn text_shape(
+        &self,
+        fonts: &egui::text::Fonts,
+        pos: egui::Pos2,
+        anchor: egui::Align2,
+        text: impl ToString,
+        font: egui::FontId,
+        color: Color32,
+    ) -> egui::Shape {
+        let galley = fonts.layout_no_wrap(text.to_string(), font, color);
+        let rect =
+            anchor.anchor_rect(egui::Rect::from_min_size(pos, galley.size()));
+
+        if !self.high_contrast {
+            return egui::Shape::galley(rect.min, galley);
+        }
+
+        let halo = Self::HALO_OFFSETS.iter().map(|&[x, y]| {
+            let mut shape = egui::epaint::TextShape::new(
+                rect.min + egui::vec2(x, y) * 1.5,
+                galley.clone(),
+            );
+            shape.override_text_color = Some(Color32::BLACK);
+            egui::Shape::Text(shape)
+        });
+
+        let shapes = halo
+            .chain([egui::Shape::galley(rect.min, galley.clone())])
+            .collect();
+
+        egui::Shape::Vec(shapes)
+    }
+}
+
+/// Settings window panel for toggling high-contrast mode.
+pub struct ThemeWidget {
+    theme: Arc<AtomicCell<Theme>>,
+}
+
+impl ThemeWidget {
+    pub fn new(theme: Arc<AtomicCell<Theme>>) -> Self {
+        Self { theme }
+    }
+}
+
+impl SettingsWidget for ThemeWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let mut theme = self.theme.load();
+
+        let resp = ui
+            .checkbox(&mut theme.high_contrast, "High contrast")
+            .on_hover_text(
+                "Outlines labels and highlights, and uses brighter \
+                 selection colors",
+            );
+
+        if resp.changed() {
+            self.theme.store(theme);
+        }
+
+        SettingsUiResponse { response: resp }
+    }
+}
@@ -0,0 +1,358 @@
+//! Guided tour: a window over the 1D viewer that walks through
+//! navigation, data tracks, selection and export, one step at a time.
+//!
+//! Each step's "Show me" button runs a short script with the same
+//! functions as `--script`, so the tour exercises the real commands,
+//! and a step is marked done once the app state shows the user has
+//! done it themselves.
+
+use std::ops::Range;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+
+use crate::app::provenance::EventKind;
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::{script, SharedState};
+
+const EXAMPLE_GFA: &[u8] = include_bytes!(
+    "../../../test/data/A-3105.fa.353ea42.34ee7b1.1576367.smooth.fix.gfa"
+);
+const EXAMPLE_LAYOUT: &[u8] =
+    include_bytes!("../../../test/data/A-3105.layout.tsv");
+
+/// The directory the example graph is written to, and the tour's
+/// exports go to.
+pub fn tour_dir() -> PathBuf {
+    std::env::temp_dir().join("waragraph-tour")
+}
+
+/// Writes the bundled example graph and its layout to the tour
+/// directory, returning their paths.
+pub fn write_example_graph() -> std::io::Result<(PathBuf, PathBuf)> {
+    let dir = tour_dir();
+    std::fs::create_dir_all(&dir)?;
+
+    let gfa = dir.join("A-3105.gfa");
+    let tsv = dir.join("A-3105.layout.tsv");
+    std::fs::write(&gfa, EXAMPLE_GFA)?;
+    std::fs::write(&tsv, EXAMPLE_LAYOUT)?;
+
+    Ok((gfa, tsv))
+}
+
+/// Starts the tour from the first step. If no export directory is
+/// set, exports go to the tour directory, so that the export step
+/// doesn't need a file dialog.
+pub fn start_tour(shared: &SharedState) {
+    shared
+        .export_paths
+        .blocking_write()
+        .directory
+        .get_or_insert_with(|| tour_dir().join("exports"));
+
+    shared.tour.blocking_write().start();
+}
+
+/// How the tour notices that a step has been done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepCheck {
+    Manual,
+    ViewMoved,
+    SelectionNonEmpty,
+    Exported,
+}
+
+struct TourStep {
+    title: &'static str,
+    text: &'static str,
+    /// Run by "Show me"
+    script: Option<&'static str>,
+    check: StepCheck,
+}
+
+const STEPS: &[TourStep] = &[
+    TourStep {
+        title: "Welcome",
+        text: "This tour walks through the main parts of Waragraph. \
+               Each step describes something to try; \"Show me\" does \
+               it for you, using the same commands as `--script`.",
+        script: None,
+        check: StepCheck::Manual,
+    },
+    TourStep {
+        title: "Navigating the 1D view",
+        text: "Each row is a path, laid out along the pangenome. Drag \
+               to pan, and scroll to zoom in and out. Try zooming in \
+               on a region.",
+        script: Some(
+            "let len = pangenome_len();\n\
+             set_range(len / 4, len / 4 + len / 50);",
+        ),
+        check: StepCheck::ViewMoved,
+    },
+    TourStep {
+        title: "Data tracks",
+        text: "The rows are colored by a data source, such as depth or \
+               strand. Switch between them in the 1D Viewer tab of the \
+               settings window (Escape), and scroll the path names to \
+               find a path.",
+        script: Some(
+            "set_data(\"strand\");\n\
+             let names = path_names();\n\
+             scroll_to_path(names[names.len() - 1]);",
+        ),
+        check: StepCheck::Manual,
+    },
+    TourStep {
+        title: "Selecting nodes",
+        text: "Click a node in the 2D view to select it, and \
+               shift-click to add to the selection. The selection is \
+               highlighted in both viewers.",
+        script: Some("select_path(path_names()[0]);"),
+        check: StepCheck::SelectionNonEmpty,
+    },
+    TourStep {
+        title: "The 2D view",
+        text: "The 2D view shows the graph layout. Right click a node \
+               in the 1D view to pan the 2D view to it, or center it \
+               on a region of a path from a script.",
+        script: Some(
+            "let p = path_names()[0];\n\
+             center_2d(p, 0, path_len(p) / 10);",
+        ),
+        check: StepCheck::Manual,
+    },
+    TourStep {
+        title: "Exporting",
+        text: "Export the 1D view as PNG and SVG from the Export Image \
+               panel of the 1D Viewer tab. Exports go to the directory \
+               set in the Export panel of the General tab.",
+        script: Some("screenshot();"),
+        check: StepCheck::Exported,
+    },
+    TourStep {
+        title: "Done",
+        text: "That's the tour! The Scripting panel in the General tab \
+               runs the same commands used here, and the tour can be \
+               started again from the Guided Tour panel.",
+        script: None,
+        check: StepCheck::Manual,
+    },
+];
+
+/// The app state when a step was entered, to tell when it's done.
+#[derive(Debug, Clone)]
+struct StepBaseline {
+    view: Range<u64>,
+    exports: usize,
+}
+
+#[derive(Default)]
+pub struct GuidedTour {
+    pub active: bool,
+    step: usize,
+
+    baseline: Option<StepBaseline>,
+    done: bool,
+
+    script_recv: Option<oneshot::Receiver<Result<()>>>,
+    error: Option<String>,
+}
+
+impl GuidedTour {
+    fn start(&mut self) {
+        self.active = true;
+        self.go_to(0);
+    }
+
+    fn go_to(&mut self, step: usize) {
+        self.step = step.min(STEPS.len() - 1);
+        self.baseline = None;
+        self.done = false;
+        self.error = None;
+    }
+
+    fn export_count(shared: &SharedState) -> usize {
+        shared
+            .provenance
+            .blocking_read()
+            .events()
+            .iter()
+            .filter(|ev| ev.kind == EventKind::Export)
+            .count()
+    }
+
+    fn update_done(&mut self, shared: &SharedState, view: &Range<u64>) {
+        let Some(baseline) = self.baseline.as_ref() else {
+            self.baseline = Some(StepBaseline {
+                view: view.clone(),
+                exports: Self::export_count(shared),
+            });
+            return;
+        };
+
+        if self.done {
+            return;
+        }
+
+        self.done = match STEPS[self.step].check {
+            StepCheck::Manual => false,
+            StepCheck::ViewMoved => *view != baseline.view,
+            StepCheck::SelectionNonEmpty => {
+                !shared.selection.blocking_read().is_empty()
+            }
+            StepCheck::Exported => {
+                Self::export_count(shared) > baseline.exports
+            }
+        };
+    }
+
+    fn run_step_script(
+        &mut self,
+        shared: &SharedState,
+        tokio_handle: &tokio::runtime::Handle,
+        script: &'static str,
+    ) {
+        let shared = shared.clone();
+        let (send, recv) = oneshot::channel();
+
+        tokio_handle.spawn_blocking(move || {
+            let _ = send.send(script::run_script(&shared, script));
+        });
+
+        self.script_recv = Some(recv);
+        self.error = None;
+    }
+
+    fn poll_script(&mut self) {
+        if let Some(mut recv) = self.script_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => self.error = Some(e.to_string()),
+                Err(TryRecvError::Empty) => self.script_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+
+    /// Draws the tour window, if the tour is active; `view` is the
+    /// current range of the 1D view.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        shared: &SharedState,
+        tokio_handle: &tokio::runtime::Handle,
+        view: Range<u64>,
+    ) {
+        if !self.active {
+            return;
+        }
+
+        self.poll_script();
+        self.update_done(shared, &view);
+
+        let step = &STEPS[self.step];
+        let mut open = true;
+
+        egui::Window::new("Guided tour")
+            .id(egui::Id::new("GuidedTour"))
+            .open(&mut open)
+            .anchor(egui::Align2::RIGHT_TOP, [-10.0, 40.0])
+            .collapsible(false)
+            .resizable(false)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                ui.label(format!("Step {} of {}", self.step + 1, STEPS.len()));
+                ui.heading(step.title);
+                ui.label(step.text);
+
+                if self.done {
+                    ui.colored_label(
+                        ui.visuals().selection.bg_fill,
+                        "✔ Done, on to the next step",
+                    );
+                }
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(self.step > 0, egui::Button::new("Back"))
+                        .clicked()
+                    {
+                        self.go_to(self.step - 1);
+                    }
+
+                    if let Some(script) = step.script {
+                        let running = self.script_recv.is_some();
+                        if ui
+                            .add_enabled(!running, egui::Button::new("Show me"))
+                            .on_hover_text(script)
+                            .clicked()
+                        {
+                            self.run_step_script(shared, tokio_handle, script);
+                        }
+                    }
+
+                    if self.step + 1 < STEPS.len() {
+                        if ui.button("Next").clicked() {
+                            self.go_to(self.step + 1);
+                        }
+                    } else if ui.button("Finish").clicked() {
+                        self.active = false;
+                    }
+                });
+            });
+
+        if !open {
+            self.active = false;
+        }
+    }
+}
+
+/// Settings window panel for starting the tour.
+pub struct GuidedTourWidget {
+    shared: SharedState,
+}
+
+impl GuidedTourWidget {
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            shared: shared.clone(),
+        }
+    }
+}
+
+impl SettingsWidget for GuidedTourWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let resp = ui.vertical(|ui| {
+            ui.label(
+                "A step by step introduction, shown in the 1D viewer. \
+                 Run with `--tour` and no GFA to use a bundled example \
+                 graph.",
+            );
+
+            let active = self.shared.tour.blocking_read().active;
+            let label = if active { "Restart tour" } else { "Start tour" };
+            if ui.button(label).clicked() {
+                start_tour(&self.shared);
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
@@ -59,3 +59,53 @@ pub(crate) fn spinner(
 
     egui::Shape::Vec(shapes)
 }
+
+/// Stroke drawn around the widget with the keyboard focus
+pub(crate) const FOCUS_STROKE: egui::Stroke = egui::Stroke {
+    width: 2.0,
+    color: egui::Color32::from_rgb(255, 200, 0),
+};
+
+/// Makes the keyboard focus easier to see: focused buttons, checkboxes
+/// and text fields get a thick, bright outline.
+pub(crate) fn apply_focus_visuals(ctx: &egui::Context) {
+    let mut style = (*ctx.style()).clone();
+    style.visuals.widgets.active.bg_stroke = FOCUS_STROKE;
+    style.visuals.selection.stroke.width = FOCUS_STROKE.width;
+    ctx.set_style(style);
+}
+
+/// Moves the keyboard focus to the previous or next item of a list
+/// with the up and down arrow keys, when one of the items has focus,
+/// and outlines the focused item. `items` are the responses of the
+/// list's interactive items, in order.
+pub(crate) fn arrow_key_focus(ui: &egui::Ui, items: &[egui::Response]) {
+    let Some(focused) = items.iter().position(|r| r.has_focus()) else {
+        return;
+    };
+
+    let (up, down) = ui.input(|i| {
+        (
+            i.key_pressed(egui::Key::ArrowUp),
+            i.key_pressed(egui::Key::ArrowDown),
+        )
+    });
+
+    let target = if up {
+        focused.saturating_sub(1)
+    } else if down {
+        (focused + 1).min(items.len() - 1)
+    } else {
+        focused
+    };
+
+    let item = &items[target];
+
+    if target != focused {
+        item.request_focus();
+        item.scroll_to_me(None);
+    }
+
+    ui.painter()
+        .rect_stroke(item.rect.expand(2.0), 2.0, FOCUS_STROKE);
+}
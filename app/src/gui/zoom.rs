@@ -0,0 +1,228 @@
+//! Semantic zoom: the scales, in bp per pixel, at which each display
+//! layer of the viewers is drawn. The viewers check the shared
+//! thresholds every frame, and the ranges can be changed in the
+//! settings window.
+
+use std::sync::Arc;
+
+use crossbeam::atomic::AtomicCell;
+
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ZoomLayer {
+    /// The node geometry in the 2D viewer
+    Nodes,
+    /// Paths drawn on top of the 2D viewer
+    PathLines,
+    /// Annotation labels, in both viewers
+    Labels,
+    /// Bases drawn on the paths in the 1D viewer
+    Sequence,
+}
+
+impl ZoomLayer {
+    pub const ALL: [ZoomLayer; 4] = [
+        ZoomLayer::Nodes,
+        ZoomLayer::PathLines,
+        ZoomLayer::Labels,
+        ZoomLayer::Sequence,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ZoomLayer::Nodes => "Nodes (2D)",
+            ZoomLayer::PathLines => "Path lines (2D)",
+            ZoomLayer::Labels => "Annotation labels",
+            ZoomLayer::Sequence => "Sequence (1D)",
+        }
+    }
+
+    pub fn default_range(&self) -> ZoomRange {
+        match self {
+            ZoomLayer::Sequence => ZoomRange {
+                min_bp_per_px: 0.0,
+                max_bp_per_px: 0.25,
+            },
+            _ => ZoomRange::ALWAYS,
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoomRange {
+    pub min_bp_per_px: f64,
+    /// Infinite if the layer is shown however far the view is zoomed out
+    pub max_bp_per_px: f64,
+}
+
+impl ZoomRange {
+    pub const ALWAYS: Self = Self {
+        min_bp_per_px: 0.0,
+        max_bp_per_px: f64::INFINITY,
+    };
+
+    pub fn contains(&self, bp_per_px: f64) -> bool {
+        bp_per_px >= self.min_bp_per_px && bp_per_px <= self.max_bp_per_px
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoomThresholds {
+    ranges: [ZoomRange; ZoomLayer::ALL.len()],
+}
+
+impl std::default::Default for ZoomThresholds {
+    fn default() -> Self {
+        Self {
+            ranges: ZoomLayer::ALL.map(|layer| layer.default_range()),
+        }
+    }
+}
+
+impl ZoomThresholds {
+    pub fn range(&self, layer: ZoomLayer) -> ZoomRange {
+        self.ranges[layer.index()]
+    }
+
+    pub fn set_range(&mut self, layer: ZoomLayer, range: ZoomRange) {
+        self.ranges[layer.index()] = range;
+    }
+
+    pub fn visible(&self, layer: ZoomLayer, bp_per_px: f64) -> bool {
+        self.range(layer).contains(bp_per_px)
+    }
+}
+
+/// Settings window panel for the zoom range of each layer.
+pub struct ZoomThresholdsWidget {
+    thresholds: Arc<AtomicCell<ZoomThresholds>>,
+}
+
+impl ZoomThresholdsWidget {
+    pub fn new(thresholds: Arc<AtomicCell<ZoomThresholds>>) -> Self {
+        Self { thresholds }
+    }
+}
+
+impl SettingsWidget for ZoomThresholdsWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let mut thresholds = self.thresholds.load();
+
+        let resp = ui.vertical(|ui| {
+            ui.label(
+                "Each layer is drawn when the view shows between \
+                 \"From\" and \"To\" bp per pixel",
+            );
+
+            egui::Grid::new("ZoomThresholdsWidget-layers")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Layer");
+                    ui.label("From");
+                    ui.label("To");
+                    ui.end_row();
+
+                    for layer in ZoomLayer::ALL {
+                        let mut range = thresholds.range(layer);
+
+                        ui.label(layer.name());
+
+                        let mut min_limited = range.min_bp_per_px > 0.0;
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut min_limited, "");
+                            if min_limited {
+                                let min = range.min_bp_per_px.max(0.01);
+                                range.min_bp_per_px = min;
+                                ui.add(bp_per_px_drag(
+                                    &mut range.min_bp_per_px,
+                                ));
+                            } else {
+                                range.min_bp_per_px = 0.0;
+                                ui.label("0");
+                            }
+                        });
+
+                        let mut max_limited = range.max_bp_per_px.is_finite();
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut max_limited, "");
+                            if max_limited {
+                                if !range.max_bp_per_px.is_finite() {
+                                    range.max_bp_per_px = 1000.0;
+                                }
+                                ui.add(bp_per_px_drag(
+                                    &mut range.max_bp_per_px,
+                                ));
+                            } else {
+                                range.max_bp_per_px = f64::INFINITY;
+                                ui.label("No limit");
+                            }
+                        });
+
+                        if ui
+                            .add_enabled(
+                                range != layer.default_range(),
+                                egui::Button::new("Reset").small(),
+                            )
+                            .clicked()
+                        {
+                            range = layer.default_range();
+                        }
+
+                        thresholds.set_range(layer, range);
+
+                        ui.end_row();
+                    }
+                });
+        });
+
+        self.thresholds.store(thresholds);
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+fn bp_per_px_drag(value: &mut f64) -> egui::DragValue<'_> {
+    let speed = (*value * 0.01).max(0.001);
+    egui::DragValue::new(value)
+        .speed(speed)
+        .clamp_range(0.001..=1e9)
+        .max_decimals(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layer_visibility() {
+        let mut thresholds = ZoomThresholds::default();
+
+        assert!(thresholds.visible(ZoomLayer::Sequence, 0.1));
+        assert!(!thresholds.visible(ZoomLayer::Sequence, 1.0));
+        assert!(thresholds.visible(ZoomLayer::Nodes, 1e6));
+
+        thresholds.set_range(
+            ZoomLayer::Labels,
+            ZoomRange {
+                min_bp_per_px: 10.0,
+                max_bp_per_px: 100.0,
+            },
+        );
+        assert!(!thresholds.visible(ZoomLayer::Labels, 1.0));
+        assert!(thresholds.visible(ZoomLayer::Labels, 50.0));
+        assert!(!thresholds.visible(ZoomLayer::Labels, 1000.0));
+    }
+}
@@ -1,4 +1,4 @@
-use waragraph::app::App;
+use waragraph::app::{App, ArgsError};
 
 use anyhow::Result;
 
@@ -10,23 +10,138 @@ pub fn main() -> Result<()> {
 
     let args = waragraph::app::parse_args();
 
+    if let Err(ArgsError::Load(e)) = &args {
+        log::error!("{e:?}");
+        std::process::exit(1);
+    }
+
     if args.is_err() {
         let name = std::env::args().next().unwrap();
-        println!("Usage: {name} <gfa> [tsv]");
-        println!("4-column BED file can be provided using the --bed flag");
+        println!("Usage: {name} [view1d] <gfa|gbz|og> [tsv|lay]");
+        println!(
+            "The view1d subcommand opens only the 1D viewer, without \
+             computing a layout"
+        );
+        println!(
+            "4-column BED and GFF files can be provided using the --bed \
+             and --gff flags, and bedGraph data tracks using --bedgraph; \
+             each may be given more than once"
+        );
+        println!(
+            "Use --region <[path:]start-end> to open the 1D view at a \
+             region, e.g. chr1:1m-2m"
+        );
+        println!(
+            "Without a layout, one is computed in the background; use \
+             --odgi-layout to run `odgi layout` instead, or --no-layout \
+             to skip it"
+        );
+        println!(
+            "odgi graphs (.og) are converted using odgi, which must be \
+             in PATH; odgi layouts (.lay) are read directly"
+        );
+        println!("Use --gpu-memory-budget <MiB> to limit GPU memory use");
+        println!(
+            "GPU selection: --backend <vulkan|metal|dx12|dx11|gl>, \
+             --adapter <name>, --power-pref <low|high>, --list-adapters"
+        );
+        println!(
+            "Without a GPU: --software-render [--data <key>] [--width <px>] \
+             [--output <file.ppm|file.png>]"
+        );
+        println!(
+            "Batch image export: --export-image <file.png> [--region <range>] \
+             [--width <px>] [--height <px>]"
+        );
+        println!(
+            "Locus grid figure: --software-render --locus-grid <bed> \
+             [--grid-columns <n>]"
+        );
+        println!(
+            "Use --find-regions [--reference <path>] to list regions with \
+             many bubbles or insertions"
+        );
+        println!(
+            "Use --nonref-report <out.tsv> --reference <path>[,<path>...] \
+             [--min-length <bp>] to export sequence not on the reference"
+        );
+        println!(
+            "Use --gaf <file> to add the sequences aligned in a GAF as \
+             paths; may be given more than once"
+        );
+        println!("Use --view <link> to open a view link");
+        println!("Use --session <file.json> to open a saved session");
+        println!("Use --script <file.rhai> to drive the viewers with a script");
+        println!(
+            "Use --config <file.toml> to load viewer settings, reloaded \
+             when the file changes"
+        );
+        println!("Use --tour, optionally without a GFA, for a guided tour");
+        println!("Use --one-based to show and enter 1-based positions");
+        println!(
+            "Use --safe-mode to start without annotations, 2D path lines, \
+             GPU sampling and the 2D view, for flaky GPU drivers"
+        );
+        println!(
+            "Graph QC report: {name} qc <gfa> [--reference <path>[,...]] \
+             [--output <report.json|report.html>]"
+        );
         std::process::exit(0);
     }
 
-    let args = args?;
+    let mut args = args?;
+
+    if args.list_adapters {
+        waragraph::app::adapter::print_adapters();
+        std::process::exit(0);
+    }
 
-    let (event_loop, state) =
+    // the conversion is removed when `args` (or the app) is dropped
+    waragraph::app::odgi::resolve_og_args(&mut args)?;
+
+    if args.qc {
+        return waragraph::app::qc::run(&args);
+    }
+
+    if let Some(tsv) = args.nonref_report.as_ref() {
+        return waragraph::viewer_1d::nonref_report::run(&args, tsv);
+    }
+
+    if args.find_regions {
+        return waragraph::viewer_1d::region_finder::run(&args);
+    }
+
+    if args.software_render {
+        if let Some(bed) = args.locus_grid.as_ref() {
+            return waragraph::viewer_1d::locus_grid::run(&args, bed);
+        }
+        return waragraph::viewer_1d::software::run(&args);
+    }
+
+    args.adapter.apply()?;
+
+    let (mut event_loop, state) =
         pollster::block_on(raving_wgpu::initialize_no_window())?;
 
-    let mut app = App::init(&state, args)?;
+    let Some(path_index) = waragraph::app::loading::load_graph_with_preview(
+        &mut event_loop,
+        &state,
+        &args.gfa,
+    )?
+    else {
+        return Ok(());
+    };
+
+    let view1d = args.view1d;
+
+    let mut app = App::init(&state, args, path_index)?;
 
     app.init_viewer_1d(&event_loop, &state)?;
 
-    if app.shared.workspace.blocking_read().tsv_path().is_some() {
+    if app.shared.workspace.blocking_read().tsv_path().is_some()
+        && !app.shared.safe_mode
+        && !view1d
+    {
         app.init_viewer_2d(&event_loop, &state)?;
     }
 
@@ -51,6 +51,8 @@ impl<T, const N: usize> Uniform<T, N> {
             },
         );
 
+        gpu_memory::record_buffer(&label_str, N as u64);
+
         Ok(Self {
             name,
             data,
@@ -90,6 +92,8 @@ impl<T, const N: usize> Uniform<T, N> {
     //
 }
 
+pub mod gpu_memory;
+
 pub mod geometry {
     pub fn centroid<P: Into<ultraviolet::Vec2>>(
         points: impl IntoIterator<Item = P>,
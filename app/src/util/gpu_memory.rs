@@ -0,0 +1,209 @@
+//! Bookkeeping for GPU buffer and texture allocations.
+//!
+//! wgpu doesn't report how much memory is in use, so allocations are
+//! recorded by label when they're created, with the latest allocation
+//! for a label replacing the previous one (as is the case when a
+//! buffer is reallocated).
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crossbeam::atomic::AtomicCell;
+
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AllocKind {
+    Buffer,
+    Texture,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allocation {
+    pub kind: AllocKind,
+    pub size: u64,
+}
+
+static ALLOCATIONS: Mutex<BTreeMap<String, Allocation>> =
+    Mutex::new(BTreeMap::new());
+
+// 0 means no budget
+static BUDGET: AtomicCell<u64> = AtomicCell::new(0);
+
+/// Records the buffer with the given label as having `size` bytes,
+/// replacing any previous buffer with the same label.
+pub fn record_buffer(label: &str, size: u64) {
+    record(label, AllocKind::Buffer, size);
+}
+
+/// Records a texture allocation; `bytes_per_texel` should match the
+/// texture format.
+pub fn record_texture(label: &str, dims: [u32; 2], bytes_per_texel: u32) {
+    let size = dims[0] as u64 * dims[1] as u64 * bytes_per_texel as u64;
+    record(label, AllocKind::Texture, size);
+}
+
+fn record(label: &str, kind: AllocKind, size: u64) {
+    let mut allocs = ALLOCATIONS.lock().unwrap();
+    allocs.insert(label.to_string(), Allocation { kind, size });
+}
+
+pub fn release(label: &str) {
+    let mut allocs = ALLOCATIONS.lock().unwrap();
+    allocs.remove(label);
+}
+
+/// All recorded allocations, sorted by label.
+pub fn allocations() -> Vec<(String, Allocation)> {
+    let allocs = ALLOCATIONS.lock().unwrap();
+    allocs.iter().map(|(l, a)| (l.clone(), *a)).collect()
+}
+
+pub fn total_usage() -> u64 {
+    let allocs = ALLOCATIONS.lock().unwrap();
+    allocs.values().map(|a| a.size).sum()
+}
+
+/// Sets the soft limit on total GPU memory use, in bytes; `None`
+/// means only the device limits are used.
+pub fn set_budget(budget: Option<u64>) {
+    BUDGET.store(budget.unwrap_or(0));
+}
+
+pub fn budget() -> Option<u64> {
+    let budget = BUDGET.load();
+    (budget > 0).then_some(budget)
+}
+
+/// Returns `true` if a storage buffer of `size` bytes can be created
+/// on the device, and, if a budget is set, if it would fit in the
+/// budget after replacing the existing allocation with the same label.
+pub fn storage_buffer_fits(
+    device: &wgpu::Device,
+    label: &str,
+    size: u64,
+) -> bool {
+    let limits = device.limits();
+
+    let max_size = limits
+        .max_buffer_size
+        .min(limits.max_storage_buffer_binding_size as u64);
+
+    if size > max_size {
+        return false;
+    }
+
+    if let Some(budget) = budget() {
+        let allocs = ALLOCATIONS.lock().unwrap();
+
+        let others: u64 = allocs
+            .iter()
+            .filter(|(l, _)| l.as_str() != label)
+            .map(|(_, a)| a.size)
+            .sum();
+
+        others + size <= budget
+    } else {
+        true
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// Settings window panel listing the recorded allocations.
+pub struct GpuMemoryWidget {
+    limits: wgpu::Limits,
+}
+
+impl GpuMemoryWidget {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            limits: device.limits(),
+        }
+    }
+}
+
+impl SettingsWidget for GpuMemoryWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let total = total_usage();
+
+        let resp = ui.vertical(|ui| {
+            if let Some(budget) = budget() {
+                let frac = total as f32 / budget as f32;
+                ui.add(egui::ProgressBar::new(frac.min(1.0)).text(format!(
+                    "{} / {}",
+                    format_bytes(total),
+                    format_bytes(budget)
+                )));
+            } else {
+                ui.label(format!("Total: {}", format_bytes(total)));
+            }
+
+            ui.label(format!(
+                "Max buffer size: {}",
+                format_bytes(self.limits.max_buffer_size)
+            ));
+            ui.label(format!(
+                "Max storage binding: {}",
+                format_bytes(
+                    self.limits.max_storage_buffer_binding_size as u64
+                )
+            ));
+
+            ui.separator();
+
+            egui::Grid::new("GpuMemoryWidget-allocations")
+                .striped(true)
+                .show(ui, |ui| {
+                    for (label, alloc) in allocations() {
+                        let kind = match alloc.kind {
+                            AllocKind::Buffer => "buffer",
+                            AllocKind::Texture => "texture",
+                        };
+                        ui.label(label);
+                        ui.label(kind);
+                        ui.label(format_bytes(alloc.size));
+                        ui.end_row();
+                    }
+                });
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gpu_memory_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
@@ -1,18 +1,25 @@
 use crate::annotations::GlobalAnnotationId;
+use crate::app::provenance::EventKind;
 use crate::app::settings_menu::SettingsWindow;
-use crate::app::{AppWindow, SharedState};
+use crate::app::{AppType, AppWindow, SharedState};
 use crate::color::ColorMap;
 use crate::context::{ContextQuery, ContextState};
+use crate::gui::coords::PosFormat;
+use crate::gui::layers::{Layer, LayeredShapes};
+use crate::gui::measure::MeasurePoint;
+use crate::gui::pinned::{self, PinnedItem};
+use crate::gui::zoom::ZoomLayer;
 use crate::gui::{GridEntry, RowEntry, RowGridLayout};
 use crate::list::ListView;
 use crate::viewer_1d::annotations::AnnotSlot;
 use crate::viewer_1d::config::Config;
 use crossbeam::atomic::AtomicCell;
 use tokio::sync::RwLock;
+use waragraph_core::graph::sampling::BinAggregate;
 use waragraph_core::graph::{Bp, Node, PathId};
 use wgpu::BufferUsages;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -34,13 +41,36 @@ use self::render::VizModeConfig;
 use self::view::View1D;
 use self::widgets::VisualizationModesWidget;
 
+pub mod annotation_layout;
+pub mod annotation_stats;
 pub mod annotations;
+pub mod bookmarks;
+pub mod bubble_popup;
 pub mod cache;
+pub mod compare;
 pub mod config;
 pub mod control;
+pub mod depth_track;
+pub mod export;
+pub mod gpu_sampler;
 pub mod gui;
+pub mod histogram;
+pub mod locus_grid;
+pub mod nonref_report;
+pub mod overlay_track;
+pub mod path_walk;
+pub mod presence_matrix;
+pub mod region_compare;
+pub mod region_finder;
+pub mod region_select;
 pub mod render;
+pub mod ruler;
 pub mod sampler;
+pub mod sequence_panel;
+pub mod software;
+pub mod subgraph_export;
+pub mod threshold_events;
+pub mod transform;
 pub mod util;
 pub mod view;
 pub mod widgets;
@@ -59,6 +89,11 @@ pub struct Viewer1D {
     force_resample: bool,
 
     slot_cache: SlotCache,
+    // the bin count the slot cache was created with, from the
+    // performance preset; the cache may use fewer if it didn't fit
+    slot_bin_count: usize,
+    // `None` if the pangenome is too long to sample on the GPU
+    gpu_sampler: Option<gpu_sampler::GpuPathSampler>,
 
     // vertices: BufferDesc,
     vert_uniform: wgpu::Buffer,
@@ -75,18 +110,72 @@ pub struct Viewer1D {
     color_mapping: crate::util::Uniform<Arc<AtomicCell<ColorMap>>, 16>,
 
     annotations: annotations::Annots1D,
+    annot_layout: Arc<RwLock<annotation_layout::AnnotSlotLayout>>,
+
+    bubble_popup: bubble_popup::BubblePopup,
+
+    ruler: ruler::Ruler,
+    region_selection: region_select::RegionSelection,
 
     pub msg_tx: crossbeam::channel::Sender<control::Msg>,
     msg_rx: crossbeam::channel::Receiver<control::Msg>,
 
     cfg: Config,
+    // the config generation last synced with `cfg`
+    config_generation: u64,
 
     // NB: very temporary, hopefully; bits are spread all over...
     viz_mode_config: HashMap<String, VizModeConfig>,
     viz_samplers: HashMap<String, Arc<dyn sampler::Sampler + 'static>>,
+    // the bin aggregate each data source was last sampled with
+    sampled_aggregates: HashMap<String, BinAggregate>,
 
     // NB: also temporary, hopefully
     view_control_widget: ViewControlWidget,
+
+    // presence of each path across the pangenome, indexed by path
+    path_sparklines: Arc<Vec<Vec<f32>>>,
+
+    // set by `Msg::ExportImage`, handled in the first `render` after
+    // the view's data has been sampled
+    pending_export: Option<PathBuf>,
+    // the screenshot path and scale, set by the screenshot key
+    pending_screenshot: Option<(PathBuf, u32)>,
+    // notified with the path when the pending export is written
+    export_done: Option<crossbeam::channel::Sender<PathBuf>>,
+    // (path, name rect, data rect) of each row in the last frame, in
+    // points; used to place the labels when exporting
+    last_rows: Vec<(PathId, egui::Rect, egui::Rect)>,
+    last_pixels_per_point: f32,
+
+    sequence_panel: sequence_panel::SequencePanel,
+
+    bookmarks: Arc<RwLock<bookmarks::BookmarkStore>>,
+
+    depth_track: depth_track::DepthTrack,
+    overlay_track: overlay_track::OverlayTrack,
+
+    histogram: histogram::ViewHistogram,
+
+    // the pangenome range of the view, for the annotation statistics
+    // panel
+    view_range: Arc<AtomicCell<(u64, u64)>>,
+
+    // the path colors used by the `path_name` color scheme, and the
+    // paths hidden from the path list
+    path_colors_generation: u64,
+    hidden_paths: HashSet<PathId>,
+    path_list_widget: crate::gui::path_list::PathListWidget,
+
+    path_walk: Arc<RwLock<path_walk::PathWalk>>,
+    // the path of the last walk step, to scroll the path list to it
+    // when it changes
+    walk_path: Option<PathId>,
+
+    compare: compare::CompareView,
+
+    // the node under the cursor in the path slots, for the status bar
+    hovered_node: Option<util::HoveredNode>,
 }
 
 impl Viewer1D {
@@ -162,6 +251,8 @@ impl Viewer1D {
 
             (vert_uniform, frag_uniform)
         };
+        // the only node in the graph, drawn below every egui layer in
+        // `gui::layers`
         let draw_node = graph.add_node(draw_schema);
         graph.add_link_from_transient("vertices", draw_node, 0);
         graph.add_link_from_transient("swapchain", draw_node, 1);
@@ -193,7 +284,12 @@ impl Viewer1D {
         let path_list_view =
             ListView::new(paths.clone().map(PathId::from), Some(256));
 
-        let active_viz_data_key = "path_name".to_string();
+        // data tracks from the command line are shown first
+        let tracks = shared.graph_data_cache.tracks();
+        let active_viz_data_key = tracks
+            .first()
+            .cloned()
+            .unwrap_or_else(|| "path_name".to_string());
 
         graph.set_node_preprocess_fn(draw_node, move |_ctx, op_state| {
             op_state.vertices = Some(0..6);
@@ -273,8 +369,27 @@ impl Viewer1D {
         let cfg = {
             let cfg = Config {
                 filter_path_list_by_visibility: Arc::new(true.into()),
+                show_path_sparklines: Arc::new(true.into()),
+                max_annotation_rows: Arc::new(5.into()),
+                show_sequence_panel: Arc::new(false.into()),
+                depth_track: Arc::new(Default::default()),
+                hide_out_of_bounds_annotations: Arc::new(false.into()),
+                gpu_sampling: Arc::new((!shared.safe_mode).into()),
+                bin_aggregate: ["depth", "multiplicity"]
+                    .into_iter()
+                    .chain(tracks.iter().map(String::as_str))
+                    .map(|key| (key.to_string(), Arc::new(Default::default())))
+                    .collect(),
+                overlay_tracks: ["depth", "multiplicity"]
+                    .into_iter()
+                    .chain(tracks.iter().map(String::as_str))
+                    .map(|key| (key.to_string(), Arc::new(false.into())))
+                    .collect(),
+                overlay_scale: Arc::new(Default::default()),
             };
 
+            shared.config.extend_schema(cfg.schema());
+
             let widget = config::ConfigWidget { cfg: cfg.clone() };
 
             settings_window.register_widget(
@@ -288,15 +403,20 @@ impl Viewer1D {
 
         let mut viz_samplers = HashMap::default();
 
-        {
+        let data_keys = ["depth", "multiplicity"]
+            .into_iter()
+            .chain(tracks.iter().map(String::as_str));
+
+        for data_key in data_keys {
             let sampler = sampler::PathDataSampler::new(
                 shared.graph.clone(),
                 shared.graph_data_cache.clone(),
-                "depth",
-            );
+                data_key,
+            )
+            .with_aggregate(cfg.bin_aggregate[data_key].clone());
 
             viz_samplers.insert(
-                "depth".to_string(),
+                data_key.to_string(),
                 Arc::new(sampler) as Arc<dyn sampler::Sampler + 'static>,
             );
         }
@@ -326,25 +446,66 @@ impl Viewer1D {
                 },
             };
 
-            for c in [depth, strand] {
+            // nodes traversed once per path map to the low end, so
+            // collapsed repeats stand out
+            let multiplicity = VizModeConfig {
+                name: "multiplicity".to_string(),
+                data_key: "multiplicity".to_string(),
+                color_scheme: colors.get_color_scheme_id("spectral").unwrap(),
+                default_color_map: ColorMap {
+                    value_range: [1.0, 8.0],
+                    color_range: [0.0, 1.0],
+                },
+            };
+
+            for c in [depth, strand, multiplicity] {
                 cfg.insert(c.name.clone(), c);
             }
 
+            // mapped over the range of the track's node values
+            for key in tracks {
+                let value_range = shared
+                    .graph_data_cache
+                    .fetch_graph_data_blocking(key)
+                    .map(|data| [data.stats.min.min(0.0), data.stats.max])
+                    .filter(|[min, max]| min < max)
+                    .unwrap_or([0.0, 1.0]);
+
+                let track = VizModeConfig {
+                    name: key.clone(),
+                    data_key: key.clone(),
+                    color_scheme: colors
+                        .get_color_scheme_id("spectral")
+                        .unwrap(),
+                    default_color_map: ColorMap {
+                        value_range,
+                        color_range: [0.0, 1.0],
+                    },
+                };
+                cfg.insert(key.clone(), track);
+            }
+
             cfg
         };
 
         log::error!("Initialized in {} seconds", t0.elapsed().as_secs_f32());
 
-        let row_count = 512;
-        let bin_count = 1024;
+        let bin_count = shared.performance.load().bin_count;
         let slot_cache = SlotCache::new(
             state,
             path_index.clone(),
             shared.graph_data_cache.clone(),
-            row_count,
+            Self::SLOT_CACHE_ROWS,
             bin_count,
         )?;
 
+        let gpu_sampler = gpu_sampler::GpuPathSampler::new(
+            state,
+            path_index.clone(),
+            shared.graph_data_cache.clone(),
+            ["depth", "multiplicity"].map(String::from),
+        );
+
         let annotations = annotations::Annots1D::default();
 
         util::init_path_name_hash_viz_mode(
@@ -356,9 +517,152 @@ impl Viewer1D {
 
         let (msg_tx, msg_rx) = crossbeam::channel::unbounded();
 
+        let path_sparklines = (0..path_index.path_names.len())
+            .map(|ix| {
+                util::path_presence_bins(
+                    &path_index,
+                    PathId::from(ix),
+                    Self::SPARKLINE_BINS,
+                )
+            })
+            .collect::<Vec<_>>();
+
         let view_control_widget =
             ViewControlWidget::new(shared, msg_tx.clone());
 
+        settings_window.register_widget(
+            "1D Viewer",
+            "Locus Grid",
+            Arc::new(RwLock::new(locus_grid::LocusGridWidget::new(
+                shared,
+                msg_tx.clone(),
+            ))),
+        );
+
+        settings_window.register_widget(
+            "1D Viewer",
+            "Interesting Regions",
+            Arc::new(RwLock::new(region_finder::RegionFinderWidget::new(
+                shared,
+                msg_tx.clone(),
+            ))),
+        );
+
+        settings_window.register_widget(
+            "1D Viewer",
+            "Non-reference Sequence",
+            Arc::new(RwLock::new(nonref_report::NonRefReportWidget::new(
+                shared,
+                msg_tx.clone(),
+            ))),
+        );
+
+        settings_window.register_widget(
+            "1D Viewer",
+            "Threshold Events",
+            Arc::new(RwLock::new(
+                threshold_events::ThresholdEventsWidget::new(
+                    shared,
+                    msg_tx.clone(),
+                ),
+            )),
+        );
+
+        let bookmarks = shared.bookmarks.clone();
+
+        settings_window.register_widget(
+            "1D Viewer",
+            "Bookmarks",
+            Arc::new(RwLock::new(bookmarks::BookmarksWidget::new(
+                shared,
+                bookmarks.clone(),
+                msg_tx.clone(),
+            ))),
+        );
+
+        settings_window.register_widget(
+            "1D Viewer",
+            "Compare Regions",
+            Arc::new(RwLock::new(region_compare::RegionCompareWidget::new(
+                shared,
+                bookmarks.clone(),
+                msg_tx.clone(),
+            ))),
+        );
+
+        settings_window.register_widget(
+            "1D Viewer",
+            "Export Image",
+            Arc::new(RwLock::new(export::ExportImageWidget::new(
+                shared,
+                msg_tx.clone(),
+            ))),
+        );
+
+        let annot_layout = Arc::new(RwLock::new(
+            annotation_layout::AnnotSlotLayout::default(),
+        ));
+
+        settings_window.register_widget(
+            "1D Viewer",
+            "Annotation Slots",
+            Arc::new(RwLock::new(
+                annotation_layout::AnnotSlotLayoutWidget::new(
+                    shared.annotations.clone(),
+                    annot_layout.clone(),
+                ),
+            )),
+        );
+
+        let path_walk = Arc::new(RwLock::new(path_walk::PathWalk::default()));
+
+        settings_window.register_widget(
+            "1D Viewer",
+            "Walk Along Path",
+            Arc::new(RwLock::new(path_walk::PathWalkWidget::new(
+                shared,
+                path_walk.clone(),
+            ))),
+        );
+
+        let view_range = {
+            let range = view.range();
+            Arc::new(AtomicCell::new((range.start, range.end)))
+        };
+
+        settings_window.register_widget(
+            "1D Viewer",
+            "Annotation Stats",
+            Arc::new(RwLock::new(
+                annotation_stats::AnnotationStatsWidget::new(
+                    shared,
+                    view_range.clone(),
+                ),
+            )),
+        );
+
+        settings_window.register_widget(
+            "1D Viewer",
+            "Subgraph Export",
+            Arc::new(RwLock::new(subgraph_export::SubgraphExportWidget::new(
+                shared,
+                view_range.clone(),
+            ))),
+        );
+
+        settings_window.register_widget(
+            "1D Viewer",
+            "Node Export",
+            Arc::new(RwLock::new(
+                crate::app::node_export::NodeExportWidget::new(
+                    shared,
+                    crate::app::node_export::NodeRegion::View1D(
+                        view_range.clone(),
+                    ),
+                ),
+            )),
+        );
+
         Ok(Viewer1D {
             render_graph: graph,
             draw_path_slot: draw_node,
@@ -367,6 +671,8 @@ impl Viewer1D {
             force_resample: false,
 
             slot_cache,
+            slot_bin_count: bin_count,
+            gpu_sampler,
 
             // vertices,
             vert_uniform,
@@ -378,6 +684,11 @@ impl Viewer1D {
             shared: shared.clone(),
 
             annotations,
+            annot_layout,
+
+            bubble_popup: Default::default(),
+            ruler: Default::default(),
+            region_selection: Default::default(),
 
             msg_tx,
             msg_rx,
@@ -386,6 +697,7 @@ impl Viewer1D {
 
             viz_mode_config,
             viz_samplers,
+            sampled_aggregates: HashMap::default(),
 
             active_viz_data_key,
             use_linear_sampler,
@@ -393,39 +705,193 @@ impl Viewer1D {
             color_mapping,
 
             cfg,
+            config_generation: 0,
             // color_map_widget,
+            path_sparklines: Arc::new(path_sparklines),
+
+            pending_export: None,
+            pending_screenshot: None,
+            export_done: None,
+            last_rows: Vec::new(),
+            last_pixels_per_point: 1.0,
+
+            sequence_panel: Default::default(),
+
+            depth_track: Default::default(),
+            overlay_track: Default::default(),
+
+            histogram: Default::default(),
+
+            bookmarks,
+
+            view_range,
+
+            path_colors_generation: shared
+                .path_colors
+                .blocking_read()
+                .generation(),
+            hidden_paths: shared
+                .path_colors
+                .blocking_read()
+                .hidden_1d_ids(&shared.graph),
+            path_list_widget: crate::gui::path_list::PathListWidget::new(
+                shared,
+            ),
+
+            path_walk,
+            walk_path: None,
+
+            compare: compare::CompareView::new(view.max()),
+
+            hovered_node: None,
         })
     }
+}
+
+impl Viewer1D {
+    /// How many of the paths through a node are listed in the tooltip
+    const TOOLTIP_PATH_NAMES: usize = 5;
+
+    /// The length of the hovered node, and the paths through it.
+    fn node_info_labels(&self, ui: &mut egui::Ui, hovered: util::HoveredNode) {
+        let graph = &self.shared.graph;
+
+        ui.label(format!("Length {} bp", hovered.len.0));
+        ui.label(format!("{} paths through node", hovered.path_count));
+
+        let names = graph
+            .paths_on_node(hovered.node)
+            .into_iter()
+            .flatten()
+            .filter_map(|path| graph.path_names.get_by_left(&path))
+            .take(Self::TOOLTIP_PATH_NAMES);
+        for name in names {
+            ui.weak(format!("  {name}"));
+        }
+        if hovered.path_count > Self::TOOLTIP_PATH_NAMES {
+            let more = hovered.path_count - Self::TOOLTIP_PATH_NAMES;
+            ui.weak(format!("  and {more} more"));
+        }
+    }
+
+    /// The hovered position and node, with its length and the number
+    /// of paths through it, or the view range when the path slots
+    /// aren't hovered.
+    fn show_status_bar(&self, ui: &mut egui::Ui) {
+        let fmt = PosFormat::load(&self.shared);
+        let graph = &self.shared.graph;
+
+        ui.horizontal(|ui| match self.hovered_node {
+            Some(hovered) => {
+                ui.monospace(fmt.pos(graph, hovered.pos));
+                ui.separator();
+                ui.label(format!(
+                    "Node {} (segment {})",
+                    hovered.node.ix(),
+                    graph.segment_id(hovered.node)
+                ));
+                ui.separator();
+                ui.label(format!("{} bp", hovered.len.0));
+                ui.separator();
+                ui.label(format!("{} paths", hovered.path_count));
+            }
+            None => {
+                let range = self.view.range();
+                let range = Bp(range.start)..Bp(range.end);
+                ui.monospace(fmt.range(graph, &range));
+            }
+        });
+    }
+
+    /// Replaces the slot cache with one that samples each path into
+    /// `bin_count` bins, when the performance preset changes.
+    fn resize_slot_cache(
+        &mut self,
+        state: &raving_wgpu::State,
+        bin_count: usize,
+    ) {
+        self.slot_bin_count = bin_count;
+
+        match SlotCache::new(
+            state,
+            self.shared.graph.clone(),
+            self.shared.graph_data_cache.clone(),
+            Self::SLOT_CACHE_ROWS,
+            bin_count,
+        ) {
+            Ok(slot_cache) => self.slot_cache = slot_cache,
+            Err(e) => log::error!("Error resizing the slot cache: {e:?}"),
+        }
+    }
+
+    /// Updates the `path_name` color scheme and the hidden paths if
+    /// the path colors have changed.
+    fn update_path_colors(&mut self, state: &raving_wgpu::State) {
+        let path_colors = self.shared.path_colors.blocking_read();
+
+        if path_colors.generation() == self.path_colors_generation {
+            return;
+        }
+        self.path_colors_generation = path_colors.generation();
+        self.hidden_paths = path_colors.hidden_1d_ids(&self.shared.graph);
+
+        let Some(scheme) = self
+            .viz_mode_config
+            .get("path_name")
+            .map(|c| c.color_scheme)
+        else {
+            return;
+        };
+
+        let colors = path_colors.colors(&self.shared.graph);
+        let result = self
+            .shared
+            .colors
+            .blocking_write()
+            .set_color_scheme_colors(state, scheme, colors);
+
+        if let Err(e) = result {
+            log::error!("Error updating path colors: {e:?}");
+        }
+    }
+
+    /// Centers the view on the walk's position, keeping the zoom
+    /// level, and brings the walked path into the path list.
+    fn follow_path_walk(&mut self, step: path_walk::WalkStep) {
+        let len = self.view.len();
+        let start = step.pan_pos.0.saturating_sub(len / 2);
+        self.view.set(start, start + len);
+
+        if self.walk_path != Some(step.path) {
+            self.walk_path = Some(step.path);
+
+            let list_ix = self
+                .path_list_view
+                .as_slice()
+                .iter()
+                .position(|(_, path)| *path == step.path);
 
-    /// Returns a line equation that defines the transformation
-    /// parameters used by the slot fragment shader
-    ///
-    /// `view0` corresponds to the view that has been sampled and is available
-    /// in the data buffer, while `view1` is the current view.
-    ///
-    /// Usage: If the returned value is [a, b], the transformation
-    /// is applied by a*t + b
-    fn sample_index_transform(
-        view0: &std::ops::Range<u64>,
-        view1: &std::ops::Range<u64>,
-    ) -> [f32; 2] {
-        let l0 = view0.start as f32;
-        let r0 = view0.end as f32;
-        let l1 = view1.start as f32;
-        let r1 = view1.end as f32;
-
-        let v0 = r0 - l0;
-        let v1 = r1 - l1;
-
-        let a = v1 / v0;
-        let b = (l1 - l0) / v0;
-
-        [a, b]
+            if let Some(ix) = list_ix {
+                self.path_list_view.scroll_absolute(ix);
+            }
+        }
+
+        if let Some(node) = step.node_2d {
+            let msg = crate::app::AppMsg::CenterNode2D(node);
+            if let Err(e) = self.shared.app_msg_send.try_send(msg) {
+                log::error!("{e:?}");
+            }
+        }
     }
 }
 
 impl Viewer1D {
     const COLUMN_SEPARATOR_ID: &'static str = "Viewer1D-Column-Separator";
+
+    const SLOT_CACHE_ROWS: usize = 512;
+
+    // resolution of the path presence sparklines in the path list
+    const SPARKLINE_BINS: usize = 64;
 }
 
 impl AppWindow for Viewer1D {
@@ -438,14 +904,66 @@ impl AppWindow for Viewer1D {
         context_state: &mut ContextState,
         dt: f32,
     ) {
+        self.config_generation = self
+            .shared
+            .config
+            .sync_cells(&self.cfg.config_cells(), self.config_generation);
+
         while let Ok(msg) = self.msg_rx.try_recv() {
             match msg {
                 control::Msg::View(cmd) => {
                     cmd.apply(&self.shared, &mut self.view)
                 }
+                control::Msg::ExportImage(path) => {
+                    self.pending_export = Some(path);
+                }
+                control::Msg::AddBookmark(name) => {
+                    let range = self.view.range();
+                    self.bookmarks.blocking_write().add(bookmarks::Bookmark {
+                        name,
+                        path: None,
+                        range: Bp(range.start)..Bp(range.end),
+                    });
+                }
+                control::Msg::ExportImageAuto => {
+                    let range = self.view.range();
+                    let coords = self.shared.coords.load();
+                    let region =
+                        coords.format_range(&(Bp(range.start)..Bp(range.end)));
+                    self.pending_export = self
+                        .shared
+                        .export_paths
+                        .blocking_read()
+                        .image_path("view", Some(&region), "png");
+
+                    if self.pending_export.is_none() {
+                        // no export directory set
+                        self.export_done = None;
+                    }
+                }
             }
         }
 
+        {
+            let range = self.view.range();
+            self.view_range.store((range.start, range.end));
+            // followed by the presence matrix window
+            context_state.set("Viewer1D", ["view_range"], range.clone());
+        }
+
+        self.compare.link_zoom(&self.view);
+
+        let bin_count = self.shared.performance.load().bin_count;
+        if bin_count != self.slot_bin_count {
+            self.resize_slot_cache(state, bin_count);
+        }
+
+        self.update_path_colors(state);
+
+        if let Some(step) = path_walk::step(&self.path_walk, &self.shared, dt) {
+            self.follow_path_walk(step);
+        }
+
         egui_ctx.begin_frame(&window.window);
 
         let time = egui_ctx.ctx().input(|i| i.time);
@@ -470,19 +988,54 @@ impl AppWindow for Viewer1D {
             egui::pos2(dims.x, dims.y),
         );
 
-        let mut shapes = Vec::new();
+        let mut shapes = LayeredShapes::default();
 
         let (main_panel_rect, side_panel_rect) = {
             // for now do the side panel stuff here, and use it to
             // derive the main panel size
 
-            let y_range = screen_rect.y_range();
+            let status_bar =
+                egui::TopBottomPanel::bottom("Viewer1D-status-bar")
+                    .show(egui_ctx.ctx(), |ui| self.show_status_bar(ui));
+
+            let y_range = screen_rect.top()..=status_bar.response.rect.top();
             let (xl, _xr) = screen_rect.x_range().into_inner();
 
             let side_panel = egui::SidePanel::right("Viewer1D-side-panel")
                 .max_width(screen_rect.width() * 0.5)
                 .show(egui_ctx.ctx(), |ui| {
                     self.view_control_widget.show(ui);
+                    self.compare.show_controls(
+                        ui,
+                        &self.shared,
+                        &mut self.view,
+                    );
+
+                    ui.separator();
+
+                    egui::CollapsingHeader::new("Paths").show(ui, |ui| {
+                        self.path_list_widget.show(ui);
+
+                        if ui
+                            .button("Presence matrix")
+                            .on_hover_text(
+                                "Opens a window showing which paths cover \
+                                 each part of the view",
+                            )
+                            .clicked()
+                        {
+                            let msg = crate::app::AppMsg::OpenPresenceMatrix;
+                            if let Err(e) =
+                                self.shared.app_msg_send.try_send(msg)
+                            {
+                                log::error!("{e:?}");
+                            }
+                        }
+                    });
+
+                    egui::CollapsingHeader::new("Histogram").show(ui, |ui| {
+                        self.histogram.show(ui, &self.shared);
+                    });
                 });
 
             let side_panel_rect = side_panel.response.rect;
@@ -496,7 +1049,10 @@ impl AppWindow for Viewer1D {
         };
 
         // let main_view_rect = screen_rect.shrink(2.0);
-        let main_view_rect = main_panel_rect.shrink(2.0);
+        let (main_view_rect, compare_rect) =
+            self.compare.split(main_panel_rect.shrink(2.0));
+
+        let annot_layout = self.annot_layout.blocking_read().clone();
 
         let row_grid_layout = {
             use taffy::prelude::*;
@@ -527,32 +1083,62 @@ impl AppWindow for Viewer1D {
                     ..RowEntry::default()
                 }
             };
-            let view_offset = self.path_list_view.offset();
-
-            let visible_node_range = {
-                let view = self.view.range();
-                let left_node =
-                    self.shared.graph.node_at_pangenome_pos(Bp(view.start));
-                let right_node =
-                    self.shared.graph.node_at_pangenome_pos(Bp(view.end));
+            let ruler_row = RowEntry {
+                grid_template_columns: vec![points(info_col_width), fr(1.0)],
+                grid_template_rows: vec![points(24.0)],
+                column_data: vec![
+                    GridEntry::new([1, 1], gui::SlotElem::ValueAxis),
+                    GridEntry::new([1, 2], gui::SlotElem::Ruler),
+                ],
+                ..RowEntry::default()
+            };
+            let depth_row = (self.cfg.depth_track.load()
+                != depth_track::DepthTrackMode::Hidden)
+                .then(|| RowEntry {
+                    grid_template_columns: vec![
+                        points(info_col_width),
+                        fr(1.0),
+                    ],
+                    grid_template_rows: vec![points(24.0)],
+                    column_data: vec![
+                        GridEntry::new([1, 1], gui::SlotElem::DepthTrackName),
+                        GridEntry::new([1, 2], gui::SlotElem::DepthTrack),
+                    ],
+                    ..RowEntry::default()
+                });
+            let overlay_row =
+                (!self.cfg.overlay_keys().is_empty()).then(|| RowEntry {
+                    grid_template_columns: vec![
+                        points(info_col_width),
+                        fr(1.0),
+                    ],
+                    grid_template_rows: vec![points(40.0)],
+                    column_data: vec![
+                        GridEntry::new([1, 1], gui::SlotElem::OverlayTrackName),
+                        GridEntry::new([1, 2], gui::SlotElem::OverlayTrack),
+                    ],
+                    ..RowEntry::default()
+                });
 
-                let left =
-                    left_node.and_then(|n| n.ix().checked_sub(1)).unwrap_or(0);
-                let right = right_node
-                    .map(|n| (n.ix() + 1).min(self.shared.graph.node_count - 1))
-                    .unwrap_or(self.shared.graph.node_count - 1);
+            let view_offset = self.path_list_view.offset();
 
-                (left as u32)..(right as u32)
-            };
+            let visible_node_range = self.visible_node_range();
 
             let should_filter = self.cfg.filter_path_list_by_visibility.load();
 
             let layout_result = row_grid_layout.fill_from_slice_index(
                 main_view_rect.height(),
-                [header_row],
+                [header_row, ruler_row]
+                    .into_iter()
+                    .chain(depth_row)
+                    .chain(overlay_row),
                 &self.path_list_view.as_slice(),
                 view_offset,
                 |&(_list_ix, path_id)| {
+                    if self.hidden_paths.contains(&path_id) {
+                        return None;
+                    }
+
                     let path_nodes =
                         &self.shared.graph.path_node_sets[path_id.ix()];
 
@@ -576,22 +1162,30 @@ impl AppWindow for Viewer1D {
 
                     let mut data_row = 1;
 
-                    if let Some(a_slot_id) =
-                        self.annotations.get_path_slot_id(path_id)
-                    {
-                        // println!("adding annot slot");
-                        // if annotation slot is present, change the grid_template_row field
-                        // and append the extra column data
-                        row_entry.grid_template_rows.insert(0, points(50.0));
-
-                        row_entry.column_data.push(GridEntry::new(
-                            [1, 2],
-                            gui::SlotElem::Annotations {
-                                annotation_slot_id: a_slot_id,
-                            },
-                        ));
-
-                        data_row = 2;
+                    // a row above the path data for each annotation
+                    // slot, in the order of the slot layout
+                    for entry in annot_layout.visible() {
+                        let slot_id =
+                            self.annotations.get_slot_id(path_id, entry.set_id);
+
+                        let elem = match slot_id {
+                            Some(annotation_slot_id) => {
+                                gui::SlotElem::Annotations {
+                                    annotation_slot_id,
+                                }
+                            }
+                            None if entry.pinned => gui::SlotElem::Empty,
+                            None => continue,
+                        };
+
+                        row_entry
+                            .grid_template_rows
+                            .insert(data_row - 1, points(entry.row_height()));
+                        row_entry
+                            .column_data
+                            .push(GridEntry::new([data_row, 2], elem));
+
+                        data_row += 1;
                     }
 
                     // add path name and path data
@@ -636,7 +1230,7 @@ impl AppWindow for Viewer1D {
                     egui::Rounding::default(),
                     stroke,
                 );
-                shapes.push(dbg_rect);
+                shapes.push(Layer::Ui, dbg_rect);
             });
             */
 
@@ -664,6 +1258,9 @@ impl AppWindow for Viewer1D {
         let mut annot_slots = Vec::new();
 
         let mut view_range_rect = None;
+        let mut ruler_rect = None;
+        let mut depth_track_rect = None;
+        let mut overlay_track_rect = None;
 
         let mut path_name_slots: HashMap<PathId, egui::Rect> =
             HashMap::default();
@@ -671,6 +1268,35 @@ impl AppWindow for Viewer1D {
         let mut path_name_region = egui::Rect::NOTHING;
         let mut path_slot_region = egui::Rect::NOTHING;
 
+        // the color scheme and mapping of the active data, if it's
+        // quantitative
+        let value_axis = {
+            let data_key = self.active_viz_data_key.blocking_read().clone();
+
+            self.cfg.bin_aggregate.contains_key(&data_key).then(|| {
+                let colors = self.shared.colors.blocking_read();
+                let id = self
+                    .shared
+                    .data_color_schemes
+                    .blocking_read()
+                    .get(&data_key)
+                    .copied();
+                let scheme = id
+                    .map(|id| colors.get_color_scheme(id).colors.clone())
+                    .unwrap_or_default();
+                let color_map = self.color_mapping.data_ref().load();
+                (data_key, scheme, color_map)
+            })
+        };
+        let depth_axis = (self.cfg.depth_track.load()
+            == depth_track::DepthTrackMode::Bars)
+            .then(|| self.depth_track.max_depth())
+            .flatten();
+        let overlay_keys = self.cfg.overlay_keys();
+        let overlay_scale = self.cfg.overlay_scale.load();
+        let overlay_maxes =
+            self.overlay_track.scale_max(&overlay_keys, overlay_scale);
+
         egui_ctx.ctx().fonts(|fonts| {
             let _ = row_grid_layout.visit_layout(|layout, elem| {
                 let rect = crate::gui::layout_egui_rect(&layout);
@@ -680,6 +1306,91 @@ impl AppWindow for Viewer1D {
                     gui::SlotElem::ViewRange => {
                         view_range_rect = Some(rect);
                     }
+                    gui::SlotElem::Ruler => {
+                        ruler_rect = Some(rect);
+                    }
+                    gui::SlotElem::ValueAxis => {
+                        if let Some((key, scheme, color_map)) = &value_axis {
+                            shapes.extend(
+                                Layer::DataTracks,
+                                gui::value_axis_shapes(
+                                    fonts, rect, key, scheme, *color_map,
+                                ),
+                            );
+                        }
+                    }
+                    gui::SlotElem::DepthTrackName => {
+                        shapes.push(
+                            Layer::DataTracks,
+                            egui::Shape::text(
+                                &fonts,
+                                rect.left_center(),
+                                egui::Align2::LEFT_CENTER,
+                                "Depth",
+                                egui::FontId::monospace(16.0),
+                                egui::Color32::WHITE,
+                            ),
+                        );
+
+                        // the range of the bars
+                        if let Some(max) = depth_axis {
+                            let x = rect.right() - 4.0;
+                            let color = egui::Color32::from_gray(200);
+                            let font_id = egui::FontId::monospace(11.0);
+
+                            shapes.push(
+                                Layer::DataTracks,
+                                egui::Shape::line_segment(
+                                    [
+                                        egui::pos2(x, rect.top()),
+                                        egui::pos2(x, rect.bottom()),
+                                    ],
+                                    egui::Stroke::new(1.0, color),
+                                ),
+                            );
+                            for (pos, anchor, text) in [
+                                (
+                                    egui::pos2(x - 3.0, rect.top()),
+                                    egui::Align2::RIGHT_TOP,
+                                    gui::format_value(max),
+                                ),
+                                (
+                                    egui::pos2(x - 3.0, rect.bottom()),
+                                    egui::Align2::RIGHT_BOTTOM,
+                                    "0".to_string(),
+                                ),
+                            ] {
+                                shapes.push(
+                                    Layer::DataTracks,
+                                    egui::Shape::text(
+                                        &fonts,
+                                        pos,
+                                        anchor,
+                                        text,
+                                        font_id.clone(),
+                                        color,
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                    gui::SlotElem::DepthTrack => {
+                        depth_track_rect = Some(rect);
+                    }
+                    gui::SlotElem::OverlayTrackName => {
+                        shapes.extend(
+                            Layer::DataTracks,
+                            gui::overlay_legend_shapes(
+                                fonts,
+                                rect,
+                                &overlay_keys,
+                                &overlay_maxes,
+                            ),
+                        );
+                    }
+                    gui::SlotElem::OverlayTrack => {
+                        overlay_track_rect = Some(rect);
+                    }
                     gui::SlotElem::PathData { path_id, data_id } => {
                         let rect = crate::gui::layout_egui_rect(&layout);
                         path_slot_region = path_slot_region.union(rect);
@@ -695,14 +1406,10 @@ impl AppWindow for Viewer1D {
                         if let Some((path, g_annot_id)) = hovered_annot {
                             if path == path_id {
                                 // draw regions here
-                                let annot_slot_id = self
-                                    .annotations
-                                    .get_path_slot_id(*path_id)
-                                    .unwrap();
-
                                 let regions = self
                                     .annotations
-                                    .get(&annot_slot_id)
+                                    .get_slot_id(*path_id, g_annot_id.set_id)
+                                    .and_then(|id| self.annotations.get(&id))
                                     .and_then(|slot| {
                                         slot.annotation_ranges
                                             .get(&g_annot_id.annot_id)
@@ -714,11 +1421,11 @@ impl AppWindow for Viewer1D {
                                     .shared
                                     .annotations
                                     .blocking_read()
-                                    .get(*g_annot_id)
-                                    .color
+                                    .get_color(*g_annot_id)
                                     .unwrap_or(egui::Color32::RED);
 
                                 shapes.extend(
+                                    Layer::Annotations,
                                     regions
                                         .into_iter()
                                         .flatten()
@@ -751,12 +1458,45 @@ impl AppWindow for Viewer1D {
                             .get_by_left(path_id)
                             .unwrap();
 
+                        let sparkline = self
+                            .path_sparklines
+                            .get(path_id.ix())
+                            .filter(|_| self.cfg.show_path_sparklines.load());
+
+                        let mut name_width = rect.size().x;
+
+                        if let Some(values) = sparkline {
+                            let width = (rect.width() * 0.35).min(64.0);
+                            name_width -= width + 4.0;
+
+                            let spark_rect = egui::Rect::from_min_max(
+                                egui::pos2(rect.right() - width, rect.top()),
+                                rect.right_bottom(),
+                            )
+                            .shrink2(egui::vec2(0.0, 2.0));
+
+                            let max = self.view.max() as f32;
+                            let view = self.view.range();
+                            let highlight = (view.start as f32 / max)
+                                ..(view.end as f32 / max);
+
+                            shapes.extend(
+                                Layer::Ui,
+                                gui::sparkline_shapes(
+                                    spark_rect,
+                                    values,
+                                    egui::Color32::from_gray(180),
+                                    Some(highlight),
+                                ),
+                            );
+                        }
+
                         let galley = crate::gui::util::fit_text_ellipsis(
                             &fonts,
                             path_name,
                             egui::FontId::monospace(16.0),
                             egui::Color32::WHITE,
-                            rect.size().x,
+                            name_width,
                         );
 
                         let text_pos = rect.left_top();
@@ -764,7 +1504,7 @@ impl AppWindow for Viewer1D {
                             egui::epaint::TextShape::new(text_pos, galley),
                         );
 
-                        shapes.push(text_shape);
+                        shapes.push(Layer::Ui, text_shape);
                     }
                     gui::SlotElem::Annotations { annotation_slot_id } => {
                         annot_slots.push((*annotation_slot_id, rect));
@@ -773,39 +1513,244 @@ impl AppWindow for Viewer1D {
             });
         });
 
+        if let Some(rect) = depth_track_rect {
+            let view_range = self.view.range().clone();
+
+            self.depth_track.update(
+                tokio_rt,
+                &self.shared,
+                view_range.clone(),
+                rect.width().round() as usize,
+            );
+
+            shapes.extend(
+                Layer::DataTracks,
+                self.depth_track.shapes(
+                    rect,
+                    self.cfg.depth_track.load(),
+                    view_range.clone(),
+                ),
+            );
+
+            let hover_depth = egui_ctx
+                .ctx()
+                .pointer_hover_pos()
+                .filter(|pos| rect.contains(*pos))
+                .and_then(|pos| {
+                    let bp =
+                        self.view.screen_map(&rect.x_range()).x_to_bp(pos.x);
+                    self.depth_track.depth_at(bp)
+                });
+
+            if let Some(depth) = hover_depth {
+                egui::containers::popup::show_tooltip(
+                    egui_ctx.ctx(),
+                    egui::Id::new("Viewer1D-Depth-Tooltip"),
+                    |ui| {
+                        ui.label(format!("Mean depth {depth:.2}"));
+                    },
+                );
+            }
+        }
+
+        if path_slot_region.is_positive() {
+            let view_range = self.view.range().clone();
+
+            self.histogram.update(
+                tokio_rt,
+                &self.shared,
+                view_range.clone(),
+                path_slot_region.width().round() as usize,
+            );
+
+            let color = self.shared.theme.load().hover_color();
+            shapes.extend(
+                Layer::Highlights,
+                self.histogram.highlight_shapes(
+                    path_slot_region,
+                    view_range,
+                    color,
+                ),
+            );
+        }
+
+        if let Some(rect) = overlay_track_rect {
+            let view_range = self.view.range().clone();
+
+            self.overlay_track.update(
+                tokio_rt,
+                &self.shared,
+                &overlay_keys,
+                view_range.clone(),
+                rect.width().round() as usize,
+            );
+
+            shapes.extend(
+                Layer::DataTracks,
+                self.overlay_track.shapes(
+                    rect,
+                    &overlay_keys,
+                    overlay_scale,
+                    view_range,
+                ),
+            );
+
+            let hover_values = egui_ctx
+                .ctx()
+                .pointer_hover_pos()
+                .filter(|pos| rect.contains(*pos))
+                .map(|pos| {
+                    let bp =
+                        self.view.screen_map(&rect.x_range()).x_to_bp(pos.x);
+                    self.overlay_track.values_at(&overlay_keys, bp)
+                })
+                .filter(|values| values.iter().any(Option::is_some));
+
+            if let Some(values) = hover_values {
+                egui::containers::popup::show_tooltip(
+                    egui_ctx.ctx(),
+                    egui::Id::new("Viewer1D-Overlay-Tooltip"),
+                    |ui| {
+                        let colors = overlay_track::OVERLAY_COLORS;
+                        for ((key, value), color) in
+                            overlay_keys.iter().zip(values).zip(colors)
+                        {
+                            let Some(value) = value else {
+                                continue;
+                            };
+                            let value = gui::format_value(value);
+                            ui.colored_label(
+                                color,
+                                format!("Mean {key} {value}"),
+                            );
+                        }
+                    },
+                );
+            }
+        }
+
         let pixels_per_bp = {
             let slot_width = path_slot_region.size().x as f64;
             let view_width = self.view.len() as f64;
             slot_width / view_width
         };
 
-        println!("pixels_per_bp: {pixels_per_bp}");
+        let zoom = self.shared.zoom_thresholds.load();
+        let bp_per_px = 1.0 / pixels_per_bp;
+        let show_sequence = zoom.visible(ZoomLayer::Sequence, bp_per_px);
+
+        let compare_layout = compare_rect
+            .filter(|_| path_slot_region.is_positive())
+            .map(|rect| compare::CompareLayout {
+                rect,
+                name_x: path_name_region.x_range(),
+                data_x: path_slot_region.x_range(),
+                main_bottom: path_slot_region.bottom(),
+            });
+
+        if let Some(layout) = compare_layout.as_ref() {
+            let data_key = self.active_viz_data_key.blocking_read().clone();
+
+            let mut rows =
+                data_slots.get(&data_key).cloned().unwrap_or_default();
+            rows.sort_by(|(_, a), (_, b)| a.top().total_cmp(&b.top()));
+
+            let scheme = {
+                let colors = self.shared.colors.blocking_read();
+                let id = self
+                    .shared
+                    .data_color_schemes
+                    .blocking_read()
+                    .get(&data_key)
+                    .copied();
+                id.map(|id| colors.get_color_scheme(id).colors.clone())
+                    .unwrap_or_default()
+            };
+
+            let key = compare::SampleKey {
+                range: self.compare.view().range().clone(),
+                paths: rows
+                    .iter()
+                    .map(|(path, _)| *path)
+                    .take(layout.row_count())
+                    .collect(),
+                bins: (path_slot_region.width() * pixels_per_point).round()
+                    as usize,
+                color_map: self.color_mapping.data_ref().load(),
+                scheme,
+                data_key,
+            };
+
+            if let Some(sampler) = self.viz_samplers.get(&key.data_key) {
+                self.compare.update_samples(
+                    egui_ctx.ctx(),
+                    tokio_rt,
+                    sampler.clone(),
+                    key,
+                );
+            }
+        }
 
         egui_ctx.ctx().fonts(|fonts| {
             for (data_key, path_rects) in data_slots {
                 let sampler = self.viz_samplers.get(&data_key).unwrap().clone();
+
+                let aggregate = self
+                    .cfg
+                    .bin_aggregate
+                    .get(&data_key)
+                    .map(|cell| cell.load())
+                    .unwrap_or_default();
+
+                let prev_aggregate =
+                    self.sampled_aggregates.insert(data_key.clone(), aggregate);
+                if prev_aggregate.is_some_and(|prev| prev != aggregate) {
+                    self.slot_cache.invalidate_data(&data_key);
+                }
+
+                let paths = path_rects.iter().map(|(path, _)| *path);
+                let gpu_sampler = self.gpu_sampler.as_mut().filter(|gpu| {
+                    self.cfg.gpu_sampling.load()
+                        && gpu.supports(&data_key)
+                        && aggregate == BinAggregate::Mean
+                });
+
+                let cpu_paths = if let Some(gpu) = gpu_sampler {
+                    gpu.sample(
+                        state,
+                        tokio_rt,
+                        &mut self.slot_cache,
+                        &self.view,
+                        data_key.as_str(),
+                        paths,
+                    )
+                } else {
+                    paths.collect()
+                };
+
                 let result = self.slot_cache.sample_with(
                     state,
                     tokio_rt,
                     &self.view,
                     data_key.as_str(),
-                    path_rects.iter().map(|(path, _)| *path),
+                    cpu_paths,
                     sampler,
                 );
 
                 for (path, rect) in path_rects {
                     let view_range = self.view.range().clone();
 
-                    // draw sequence if zoomed in
-                    if pixels_per_bp > 4.0 {
+                    if show_sequence {
+                        let mut seq_shapes = Vec::new();
                         render::sequence_shapes_in_slot(
                             fonts,
                             &self.shared.graph,
                             path,
                             view_range,
                             rect,
-                            &mut shapes,
+                            &mut seq_shapes,
                         );
+                        shapes.extend(Layer::Nodes, seq_shapes);
                     }
                 }
             }
@@ -840,29 +1785,67 @@ impl AppWindow for Viewer1D {
         {
             let annotations = self.shared.annotations.blocking_read();
 
+            self.annotations.set_hide_out_of_bounds(
+                self.cfg.hide_out_of_bounds_annotations.load(),
+            );
+            self.annotations
+                .set_style_generation(annotations.style_generation());
+            let hide_oob = self.annotations.hide_out_of_bounds();
+
+            self.annot_layout
+                .blocking_write()
+                .sync(annotations.annotation_sets.keys().copied());
+
+            for &set_id in annotations.annotation_sets.keys() {
+                let filter =
+                    annot_layout.get(set_id).and_then(|e| e.filter.clone());
+                self.annotations.set_filter(set_id, filter);
+            }
+
             for slot_key in viz_slot_rect_map.keys() {
                 let (path, _data_key) = slot_key;
                 let path = *path;
-                // initialize annotation slots if necessary; this part is kinda janky
-
-                let has_annot_slot =
-                    self.annotations.get_path_slot_id(path).is_some();
-
-                // TODO: annotations should be able to be sourced from
-                // any annot. set for a slot, and not only be
-                // initialized once and then forgotten about
-                if let Some((set_id, set)) =
-                    annotations.get_sets_for_path(path).next()
-                {
-                    if !has_annot_slot {
+                // initialize annotation slots if necessary, one for
+                // each set with annotations on the path
+                for (set_id, set) in annotations.get_sets_for_path(path) {
+                    let has_annot_slot =
+                        self.annotations.get_slot_id(path, set_id).is_some();
+                    let visible = annot_layout
+                        .get(set_id)
+                        .map(|entry| entry.visible)
+                        .unwrap_or(true);
+
+                    if !has_annot_slot && visible {
                         if let Some(annots) = set.path_annotations.get(&path) {
-                            let annot_items = annots
-                                .iter()
+                            let path_name = self
+                                .shared
+                                .graph
+                                .path_names
+                                .get_by_left(&path)
+                                .map(String::as_str)
+                                .unwrap_or_default();
+                            let filter = self.annotations.filter(set_id);
+
+                            let annot_items = annots
+                                .iter()
                                 .filter_map(|&i| set.annotations.get(i))
+                                .filter(|annot| {
+                                    !(hide_oob && annot.out_of_bounds)
+                                })
+                                .filter(|annot| {
+                                    filter.map_or(true, |f| {
+                                        f.matches(annot, path_name)
+                                    })
+                                })
                                 .map(|annot| {
-                                    let shape_fn =
-                                        annotations::text_shape(&annot.label);
-                                    (path, annot.range.clone(), shape_fn)
+                                    let shape_fn = annotations::text_shape(
+                                        &annot.label,
+                                        annot.label_color,
+                                        self.shared.theme.clone(),
+                                    );
+                                    let color = annotations
+                                        .annotation_color(set_id, annot);
+                                    (annot, color, shape_fn)
                                 });
 
                             let annot_slot = AnnotSlot::new_from_path_space(
@@ -887,40 +1870,137 @@ impl AppWindow for Viewer1D {
 
                         let t = time as f32;
 
-                        shapes.push(crate::gui::util::spinner(
-                            stroke,
-                            spin_offset,
-                            t,
-                        ));
+                        shapes.push(
+                            Layer::Ui,
+                            crate::gui::util::spinner(stroke, spin_offset, t),
+                        );
                     }
                 }
             }
         }
 
+        {
+            let mut rows = viz_slot_rect_map
+                .iter()
+                .filter_map(|((path, _), data_rect)| {
+                    let name_rect = path_name_slots.get(path)?;
+                    Some((*path, *name_rect, *data_rect))
+                })
+                .collect::<Vec<_>>();
+            rows.sort_by(|a, b| a.2.top().total_cmp(&b.2.top()));
+
+            self.last_rows = rows;
+            self.last_pixels_per_point = pixels_per_point;
+        }
+
         // add ruler
         {
             let query = ContextQuery::from_tags::<Bp>(["hover"]);
 
             let pan_pos = context_state.get_cast::<_, Bp>(&query);
             if let Some(pos) = pan_pos {
-                let p = pos.0 as f32;
-                let vrange = self.view.range();
-                let l = vrange.start as f32;
-                let r = vrange.end as f32;
-                let t = (p - l) / (r - l);
-
-                let (sl, sr) = path_slot_region.x_range().into_inner();
-                let x = sl + t * (sr - sl);
+                let x = self
+                    .view
+                    .screen_map(&path_slot_region.x_range())
+                    .bp_to_x(*pos);
 
                 let (y0, y1) = path_slot_region.y_range().into_inner();
 
-                shapes.push(egui::Shape::line_segment(
-                    [egui::pos2(x, y0), egui::pos2(x, y1)],
-                    egui::Stroke::new(1.5, egui::Color32::RED),
-                ));
+                let theme = self.shared.theme.load();
+                for stroke in theme.outlined_strokes(1.5, theme.hover_color()) {
+                    shapes.push(
+                        Layer::Highlights,
+                        egui::Shape::line_segment(
+                            [egui::pos2(x, y0), egui::pos2(x, y1)],
+                            stroke,
+                        ),
+                    );
+                }
             }
         }
 
+        // highlight the selected nodes
+        {
+            let selection = self.shared.selection.blocking_read();
+
+            let vrange = self.view.range();
+            let screen_interval = path_slot_region.x_range();
+            let (y0, y1) = path_slot_region.y_range().into_inner();
+
+            let theme = self.shared.theme.load();
+            let fill = theme.selection_fill();
+            let strokes = theme.outlined_strokes(1.0, theme.selection_color());
+
+            for node in selection.nodes() {
+                let range = self.shared.graph.node_pangenome_range(node);
+                if range.end.0 <= vrange.start || range.start.0 >= vrange.end {
+                    continue;
+                }
+
+                let Some(xs) = self
+                    .view
+                    .map_bp_interval_to_screen_x(&range, &screen_interval)
+                else {
+                    continue;
+                };
+
+                // keep short nodes visible when zoomed out
+                let (x0, x1) = xs.into_inner();
+                let mid = (x0 + x1) / 2.0;
+                let half = ((x1 - x0) / 2.0).max(1.0);
+
+                let rect = egui::Rect::from_x_y_ranges(
+                    (mid - half)..=(mid + half),
+                    y0..=y1,
+                );
+                shapes.push(
+                    Layer::Highlights,
+                    egui::Shape::rect_filled(rect, 0.0, fill),
+                );
+                for &stroke in &strokes {
+                    shapes.push(
+                        Layer::Highlights,
+                        egui::Shape::rect_stroke(rect, 0.0, stroke),
+                    );
+                }
+            }
+
+            for range in selection.ranges() {
+                shapes.extend(
+                    Layer::Background,
+                    region_select::region_band_shapes(
+                        &self.view,
+                        path_slot_region,
+                        range,
+                        theme.selection_color(),
+                    ),
+                );
+            }
+
+            // outline the rows of the selected paths
+            for (path, name_rect, data_rect) in self.last_rows.iter() {
+                if !selection.contains_path(*path) {
+                    continue;
+                }
+                let rect = name_rect.union(*data_rect);
+                for &stroke in &strokes {
+                    shapes.push(
+                        Layer::Highlights,
+                        egui::Shape::rect_stroke(rect, 0.0, stroke),
+                    );
+                }
+            }
+
+            shapes.extend(
+                Layer::Background,
+                self.region_selection.shapes(
+                    &self.view,
+                    path_slot_region,
+                    theme.hover_color(),
+                ),
+            );
+        }
+
         // NB: disabling the color map widget for the time being
         /*
         {
@@ -981,7 +2061,7 @@ impl AppWindow for Viewer1D {
             self.slot_cache.update_displayed_messages(show_state);
         });
 
-        shapes.extend(self.slot_cache.msg_shapes.drain(..));
+        shapes.extend(Layer::Ui, self.slot_cache.msg_shapes.drain(..));
 
         {
             let ctx = egui_ctx.ctx();
@@ -995,6 +2075,16 @@ impl AppWindow for Viewer1D {
                 .constrain(true);
 
             main_area.show(ctx, |ui| {
+                if let Some(layout) = compare_layout.as_ref() {
+                    self.compare.show(
+                        ui,
+                        &self.shared,
+                        &mut self.view,
+                        layout,
+                        &mut shapes,
+                    );
+                }
+
                 let path_names =
                     ui.allocate_rect(path_name_region, egui::Sense::hover());
 
@@ -1009,10 +2099,7 @@ impl AppWindow for Viewer1D {
                     // hardcoded row height for now; should be stored/fetched
                     let rows = (scroll.y / 20.0).round() as isize;
 
-                    let visible_node_range = {
-                        let range = self.visible_node_range();
-                        (range.start.ix() as u32)..(range.end.ix() as u32)
-                    };
+                    let visible_node_range = self.visible_node_range();
 
                     let filter_path_list = |path: &PathId| {
                         let path_nodes =
@@ -1033,12 +2120,35 @@ impl AppWindow for Viewer1D {
                     }
                 }
 
-                if path_slots.dragged_by(egui::PointerButton::Primary) {
+                // shift-dragging selects a region instead of panning
+                let drag_path =
+                    path_slots.interact_pointer_pos().and_then(|pos| {
+                        viz_slot_rect_map.iter().find_map(
+                            |((path, _), rect)| {
+                                rect.contains(pos).then_some(*path)
+                            },
+                        )
+                    });
+                let selecting_region = self.region_selection.update(
+                    &self.shared,
+                    &path_slots,
+                    &self.view,
+                    &path_slot_region.x_range(),
+                    drag_path,
+                );
+
+                if path_slots.dragged_by(egui::PointerButton::Primary)
+                    && !selecting_region
+                {
                     let dx =
                         path_slots.drag_delta().x / path_slot_region.width();
                     self.view.translate_norm_f32(-dx);
                 }
 
+                if path_slots.hover_pos().is_none() {
+                    self.hovered_node = None;
+                }
+
                 if let Some(pos) = path_slots.hover_pos() {
                     let left = path_slot_region.left();
                     let width = path_slot_region.width();
@@ -1051,10 +2161,14 @@ impl AppWindow for Viewer1D {
                         self.view.zoom_with_focus(rel_x, dz);
                     }
 
-                    let pan_pos = self.view.offset()
-                        + (rel_x * self.view.len() as f32) as u64;
-                    let hovered_node =
-                        self.shared.graph.node_at_pangenome_pos(Bp(pan_pos));
+                    let screen_map =
+                        self.view.screen_map(&path_slot_region.x_range());
+                    let pan_pos = screen_map.x_to_bp(pos.x).0;
+                    // the node containing the hovered base; the value
+                    // readout has to match it exactly
+                    self.hovered_node =
+                        util::pick_node(&self.shared.graph, &screen_map, pos.x);
+                    let hovered_node = self.hovered_node.map(|h| h.node);
 
                     if let Some(node) = hovered_node {
                         context_state.set("Viewer1D", ["hover"], node);
@@ -1067,19 +2181,72 @@ impl AppWindow for Viewer1D {
                             context_state.set("Viewer1D", ["goto"], node);
                         }
                     }
+
+                    let measuring = self.shared.measure.blocking_read().active;
+
+                    if path_slots.clicked() && measuring {
+                        self.shared.measure.blocking_write().add_point(
+                            MeasurePoint {
+                                pos: Bp(pan_pos),
+                                layout_pos: None,
+                            },
+                        );
+                    }
+
+                    // same as in the 2D viewer; the selection is shared
+                    if path_slots.clicked() && !measuring {
+                        let shift = ui.input(|i| i.modifiers.shift);
+                        let mut selection =
+                            self.shared.selection.blocking_write();
+
+                        match hovered_node {
+                            Some(node) if shift => selection.toggle(node),
+                            _ => selection.select_only(hovered_node),
+                        }
+                    }
                     context_state.set("Viewer1D", ["hover"], Bp(pan_pos));
 
                     // this is Some even when the mouse is over a gap in the path
-                    let hovered_path = viz_slot_rect_map.iter().find_map(
-                        |((path, _), rect)| rect.contains(pos).then_some(*path),
-                    );
+                    let hovered_slot =
+                        viz_slot_rect_map.iter().find_map(|(key, rect)| {
+                            rect.contains(pos).then_some(key)
+                        });
+                    let hovered_path = hovered_slot.map(|(path, _)| *path);
+
+                    // the exact value of the node in quantitative data,
+                    // rather than the binned value drawn in the slot
+                    let hovered_value = hovered_slot
+                        .filter(|(_, key)| {
+                            self.cfg.bin_aggregate.contains_key(key)
+                        })
+                        .zip(hovered_node)
+                        .and_then(|((path, data_key), node)| {
+                            let data = self
+                                .shared
+                                .graph_data_cache
+                                .fetch_path_data_blocking(data_key, *path)?;
+                            let value = self.shared.graph.path_node_value(
+                                *path,
+                                node,
+                                &data.path_data,
+                            )?;
+                            Some(gui::HoveredValue {
+                                data_key: data_key.clone(),
+                                value: *value,
+                            })
+                        });
+
+                    if let Some(value) = hovered_value {
+                        context_state.set("Viewer1D", ["hover"], value);
+                    }
 
                     if let Some((path, node)) = hovered_path.zip(hovered_node) {
-                        let (n_start, _n_end) =
+                        let (n_start, n_len) =
                             self.shared.graph.node_offset_length(node);
 
-                        let add_offset =
-                            n_start.0.checked_sub(pan_pos).unwrap_or_default();
+                        // the hovered position within the node
+                        let inner =
+                            pan_pos.checked_sub(n_start.0).unwrap_or_default();
 
                         let step_offset = self
                             .shared
@@ -1088,6 +2255,20 @@ impl AppWindow for Viewer1D {
                             .and_then(|mut iter| iter.next());
 
                         if let Some((step, offset)) = step_offset {
+                            // on reverse steps the path runs from the
+                            // node's end
+                            let reverse = self.shared.graph.path_steps
+                                [path.ix()]
+                            .get(step)
+                            .map(|s| s.is_reverse())
+                            .unwrap_or(false);
+
+                            let add_offset = if reverse {
+                                n_len.0.saturating_sub(inner + 1)
+                            } else {
+                                inner
+                            };
+
                             context_state.set(
                                 "Viewer1D",
                                 ["hover"],
@@ -1097,6 +2278,26 @@ impl AppWindow for Viewer1D {
                     }
                 }
 
+                // measure mode points, as lines across the path rows
+                {
+                    let measure = self.shared.measure.blocking_read();
+                    let range = path_slot_region.x_range();
+                    let stroke =
+                        egui::Stroke::new(1.5, ui.visuals().selection.bg_fill);
+
+                    let screen_map = self.view.screen_map(&range);
+                    for point in measure.points() {
+                        let x = screen_map.bp_to_x(point.pos);
+                        if range.contains(&x) {
+                            ui.painter().vline(
+                                x,
+                                path_slot_region.y_range(),
+                                stroke,
+                            );
+                        }
+                    }
+                }
+
                 //
                 if let Some(rect) = view_range_rect {
                     let range = self.view.range();
@@ -1108,16 +2309,39 @@ impl AppWindow for Viewer1D {
                         .copied();
 
                     ui.fonts(|fonts| {
-                        shapes.extend(gui::view_range_shapes(
-                            &fonts,
-                            rect,
-                            left,
-                            right,
-                            interact_pos,
-                        ));
+                        shapes.extend(
+                            Layer::Ui,
+                            gui::view_range_shapes(
+                                &fonts,
+                                rect,
+                                left,
+                                right,
+                                interact_pos,
+                                &self.shared.graph,
+                                PosFormat::load(&self.shared),
+                            ),
+                        );
                     });
                 }
 
+                // dragging along the ruler selects the nodes in the range
+                if let Some(rect) = ruler_rect {
+                    let fill = self.shared.theme.load().selection_fill();
+                    let dragged = self.ruler.show(ui, rect, &self.view, fill);
+
+                    if let Some(range) = dragged {
+                        let graph = &self.shared.graph;
+                        let last = range.end.0.min(graph.pangenome_len().0) - 1;
+                        let nodes = graph.pos_range_nodes(range.start.0..last);
+                        let nodes = nodes.start().ix()..=nodes.end().ix();
+
+                        self.shared
+                            .selection
+                            .blocking_write()
+                            .select_all(nodes.map(Node::from));
+                    }
+                }
+
                 for &(slot_id, rect) in annot_slots.iter() {
                     if let Some(annot_slot) = self.annotations.get_mut(&slot_id)
                     {
@@ -1125,8 +2349,24 @@ impl AppWindow for Viewer1D {
 
                         let cursor_pos =
                             ui.input(|input| input.pointer.hover_pos());
-                        let interacted =
-                            annot_slot.draw(&painter, &self.view, cursor_pos);
+                        // collapsed slots only show the intervals
+                        let collapsed = annot_layout
+                            .get(annot_slot.set_id)
+                            .map(|entry| entry.collapsed)
+                            .unwrap_or(false);
+                        let show_labels = !collapsed
+                            && zoom.visible(
+                                ZoomLayer::Labels,
+                                self.view.bp_per_pixel(rect.width()),
+                            );
+                        let interacted = annot_slot.draw(
+                            &painter,
+                            &self.view,
+                            cursor_pos,
+                            show_labels,
+                            annot_layout.marquee,
+                            annot_layout.density_threshold,
+                        );
 
                         if let Some(annot_id) = interacted {
                             let set_id = annot_slot.set_id;
@@ -1168,7 +2408,7 @@ impl AppWindow for Viewer1D {
                                 egui::Color32::from_white_alpha(180),
                             ),
                         );
-                        shapes.push(shape);
+                        shapes.push(Layer::Ui, shape);
                     }
                     let dx = column_separator.drag_delta().x;
 
@@ -1196,6 +2436,29 @@ impl AppWindow for Viewer1D {
                     .get_cast::<_, (Node, PathId, usize, Bp)>(&query);
 
                 if let Some((node, path, _step, pos)) = step_ctx {
+                    let seq = self.shared.graph.path_sequence(
+                        *path,
+                        Bp(pos.0.saturating_sub(10))..Bp(pos.0 + 11),
+                    );
+                    let center = pos.0.min(10) as usize;
+
+                    let fmt = PosFormat::load(&self.shared);
+                    let ref_pos = context_state
+                        .query_get_cast::<_, Bp>(Some("Viewer1D"), ["hover"])
+                        .filter(|_| {
+                            fmt.reference.is_some()
+                                && fmt.reference != Some(*path)
+                        })
+                        .map(|pan| fmt.pos(&self.shared.graph, *pan));
+
+                    let value = context_state
+                        .get_cast::<_, gui::HoveredValue>(
+                            &ContextQuery::from_source::<gui::HoveredValue>(
+                                "Viewer1D",
+                            ),
+                        )
+                        .cloned();
+
                     egui::containers::popup::show_tooltip(
                         egui_ctx.ctx(),
                         egui::Id::new("Viewer1D-PathPos-Tooltip"),
@@ -1208,16 +2471,142 @@ impl AppWindow for Viewer1D {
                                 .map(|n| n.as_str())
                                 .unwrap_or("ERROR");
                             ui.label(format!("Node {}", node.ix()));
+                            if let Some(hovered) = self.hovered_node {
+                                self.node_info_labels(ui, hovered);
+                            }
                             ui.label(format!("Path {path_name}"));
                             ui.label(format!("Pos {} bp", pos.0));
+                            if let Some(ref_pos) = ref_pos.as_ref() {
+                                ui.label(format!("Ref {ref_pos}"));
+                            }
+                            if let Some(value) = value.as_ref() {
+                                ui.label(format!(
+                                    "{} {}",
+                                    value.data_key,
+                                    gui::format_value(value.value)
+                                ));
+                            }
+
+                            if let Some(seq) = seq.as_ref() {
+                                let snippet = sequence_panel::sequence_snippet(
+                                    seq, center, 10,
+                                );
+                                ui.monospace(snippet);
+                            }
+                            ui.weak("P to pin, D for the bubble's alleles");
                         },
                     );
+                } else if let Some(hovered) = self.hovered_node {
+                    // over a path slot where the path skips the node
+                    egui::containers::popup::show_tooltip(
+                        egui_ctx.ctx(),
+                        egui::Id::new("Viewer1D-Node-Tooltip"),
+                        |ui| {
+                            ui.label(format!("Node {}", hovered.node.ix()));
+                            self.node_info_labels(ui, hovered);
+                        },
+                    );
+                }
+            }
+
+            // F12 saves a screenshot at twice the window's size, and
+            // shift+F12 at four times
+            let screenshot_scale = egui_ctx.ctx().input(|i| {
+                i.key_pressed(egui::Key::F12)
+                    .then_some(if i.modifiers.shift { 4 } else { 2 })
+            });
+
+            if let Some(scale) = screenshot_scale {
+                let range = self.view.range();
+                let coords = self.shared.coords.load();
+                let region =
+                    coords.format_range(&(Bp(range.start)..Bp(range.end)));
+                let path = self
+                    .shared
+                    .export_paths
+                    .blocking_read()
+                    .screenshot_path(&region);
+                self.pending_screenshot = Some((path, scale));
+            }
+
+            // D compares the alleles of the bubble around the hovered node
+            let bubble_key = !egui_ctx.ctx().wants_keyboard_input()
+                && egui_ctx.ctx().input(|i| i.key_pressed(egui::Key::D));
+
+            if bubble_key {
+                let node = context_state
+                    .query_get_cast::<_, Node>(Some("Viewer1D"), ["hover"])
+                    .copied();
+
+                if let Some(node) = node {
+                    self.bubble_popup.request(
+                        tokio_rt,
+                        &self.shared.graph,
+                        node,
+                    );
                 }
             }
 
+            // P pins the hovered annotation, or else the path position
+            if pinned::pin_key_pressed(egui_ctx.ctx()) {
+                let annot = context_state
+                    .get_cast::<_, (PathId, GlobalAnnotationId)>(
+                        &ContextQuery::from_source::<(
+                            PathId,
+                            GlobalAnnotationId,
+                        )>("Viewer1D"),
+                    )
+                    .map(|&(_, id)| PinnedItem::Annotation(id));
+
+                let path_pos = context_state
+                    .get_cast::<_, (Node, PathId, usize, Bp)>(
+                        &ContextQuery::from_source::<(Node, PathId, usize, Bp)>(
+                            "Viewer1D",
+                        ),
+                    )
+                    .map(|&(_, path, _, pos)| PinnedItem::PathPos {
+                        path,
+                        pos,
+                    });
+
+                if let Some(item) = annot.or(path_pos) {
+                    let pos =
+                        egui_ctx.ctx().pointer_hover_pos().unwrap_or_default();
+                    self.shared.pinned.blocking_write().pin(
+                        AppType::Viewer1D,
+                        item,
+                        pos,
+                    );
+                }
+            }
+
+            // sequence around the hovered position, in this or the 2D
+            // viewer
+            let mut show_seq = self.cfg.show_sequence_panel.load();
+            if show_seq {
+                let query =
+                    ContextQuery::from_source::<(Node, PathId, usize, Bp)>(
+                        "Viewer1D",
+                    );
+                let path_pos = context_state
+                    .get_cast::<_, (Node, PathId, usize, Bp)>(&query)
+                    .map(|&(_, path, _, pos)| (path, pos));
+                let pan_pos = context_state
+                    .query_get_cast::<_, Bp>(None, ["hover"])
+                    .copied();
+
+                self.sequence_panel.set_hover(pan_pos, path_pos);
+                self.sequence_panel.show(
+                    egui_ctx.ctx(),
+                    &mut show_seq,
+                    &self.shared.graph,
+                );
+                self.cfg.show_sequence_panel.store(show_seq);
+            }
+
             let painter =
                 egui_ctx.ctx().layer_painter(egui::LayerId::background());
-            painter.extend(shapes);
+            painter.extend(shapes.into_sorted());
 
             let painter = egui_ctx.ctx().layer_painter(egui::LayerId::new(
                 egui::Order::Foreground,
@@ -1227,12 +2616,49 @@ impl AppWindow for Viewer1D {
             painter.extend(self.slot_cache.msg_shapes.drain(..));
         }
 
+        let max_rows = self.cfg.max_annotation_rows.load().min(
+            self.shared
+                .performance
+                .load()
+                .max_label_rows
+                .unwrap_or(usize::MAX),
+        );
+
         for (slot_id, rect) in annot_slots {
             if let Some(annot_slot) = self.annotations.get_mut(&slot_id) {
-                annot_slot.update(tokio_rt, rect, &self.view, dt);
+                let collapsed = annot_layout
+                    .get(annot_slot.set_id)
+                    .map(|entry| entry.collapsed)
+                    .unwrap_or(false);
+
+                // collapsed slots don't show labels, so there's nothing
+                // to place
+                if !collapsed {
+                    annot_slot.update(tokio_rt, rect, &self.view, dt, max_rows);
+                }
             }
         }
 
+        self.shared.pinned.blocking_write().show(
+            egui_ctx.ctx(),
+            &self.shared,
+            &AppType::Viewer1D,
+        );
+
+        self.bubble_popup.show(egui_ctx.ctx(), &self.shared.graph);
+        self.region_selection.show_window(
+            egui_ctx.ctx(),
+            &self.shared,
+            &self.msg_tx,
+        );
+
+        self.shared.tour.blocking_write().show(
+            egui_ctx.ctx(),
+            &self.shared,
+            tokio_rt,
+            self.view.range().clone(),
+        );
+
         egui_ctx.end_frame(&window.window);
     }
 
@@ -1249,10 +2675,7 @@ impl AppWindow for Viewer1D {
                 use winit::event::VirtualKeyCode as Key;
                 let pressed = matches!(input.state, ElementState::Pressed);
 
-                let visible_node_range = {
-                    let range = self.visible_node_range();
-                    (range.start.ix() as u32)..(range.end.ix() as u32)
-                };
+                let visible_node_range = self.visible_node_range();
 
                 let filter_path_list = |path: &PathId| {
                     let path_nodes =
@@ -1284,7 +2707,25 @@ impl AppWindow for Viewer1D {
                                 .scroll_relative_filtered(1, filter_path_list);
                             self.force_resample = true;
                         }
-                        Key::Space => {
+                        Key::PageUp => {
+                            self.path_list_view.scroll_relative_filtered(
+                                -10,
+                                filter_path_list,
+                            );
+                            self.force_resample = true;
+                        }
+                        Key::PageDown => {
+                            self.path_list_view
+                                .scroll_relative_filtered(10, filter_path_list);
+                            self.force_resample = true;
+                        }
+                        Key::Equals | Key::Plus | Key::NumpadAdd => {
+                            self.view.zoom_with_focus(0.5, 0.8);
+                        }
+                        Key::Minus | Key::NumpadSubtract => {
+                            self.view.zoom_with_focus(0.5, 1.25);
+                        }
+                        Key::Space | Key::Home => {
                             self.view.reset();
                         }
                         _ => (),
@@ -1305,12 +2746,153 @@ impl AppWindow for Viewer1D {
         Ok(())
     }
 
+    fn view_state(&self) -> Option<crate::app::ViewState> {
+        let annotation_slots = {
+            let annotations = self.shared.annotations.blocking_read();
+            self.annot_layout
+                .blocking_read()
+                .visible()
+                .filter_map(|entry| {
+                    let set = annotations.annotation_sets.get(&entry.set_id)?;
+                    Some(set.name.clone())
+                })
+                .collect()
+        };
+
+        Some(crate::app::ViewState::View1D {
+            range: self.view.range().clone(),
+            data_key: self.active_viz_data_key.blocking_read().clone(),
+            path_offset: self.path_list_view.offset(),
+            annotation_slots: Some(annotation_slots),
+        })
+    }
+
+    fn goto_region(
+        &mut self,
+        path: Option<PathId>,
+        range: std::ops::Range<Bp>,
+    ) {
+        use control::{Msg, ViewCmd};
+        let _ = self
+            .msg_tx
+            .send(Msg::View(ViewCmd::GotoRange { path, range }));
+    }
+
+    fn goto_node(&mut self, node: Node) {
+        use control::{Msg, ViewCmd};
+        let _ = self.msg_tx.send(Msg::View(ViewCmd::GotoNode { node }));
+    }
+
+    fn set_view_range(&mut self, range: std::ops::Range<Bp>) {
+        use control::{Msg, ViewCmd};
+        let _ = self.msg_tx.send(Msg::View(ViewCmd::SetRange { range }));
+    }
+
+    fn request_image_export(
+        &mut self,
+        path: Option<PathBuf>,
+        done: crossbeam::channel::Sender<PathBuf>,
+    ) -> bool {
+        let msg = match path {
+            Some(path) => control::Msg::ExportImage(path),
+            None => control::Msg::ExportImageAuto,
+        };
+        self.export_done = Some(done);
+        self.msg_tx.send(msg).is_ok()
+    }
+
+    fn restore_view_state(&mut self, view_state: &crate::app::ViewState) {
+        if let crate::app::ViewState::View1D {
+            range,
+            data_key,
+            path_offset,
+            annotation_slots,
+        } = view_state
+        {
+            self.view.set(range.start, range.end);
+
+            if self.viz_mode_config.contains_key(data_key) {
+                *self.active_viz_data_key.blocking_write() = data_key.clone();
+            } else {
+                log::warn!("Unknown data source `{data_key}` in view state");
+            }
+
+            self.path_list_view.scroll_absolute(*path_offset);
+            self.force_resample = true;
+
+            if let Some(names) = annotation_slots {
+                let annotations = self.shared.annotations.blocking_read();
+                let sets = annotations.annotation_sets.iter();
+
+                // sets with the same name are shown together
+                let ids = names
+                    .iter()
+                    .flat_map(|name| {
+                        sets.clone()
+                            .filter(move |(_, set)| &set.name == name)
+                            .map(|(&id, _)| id)
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut layout = self.annot_layout.blocking_write();
+                layout.sync(annotations.annotation_sets.keys().copied());
+                layout.show_only(&ids);
+            }
+        }
+    }
+
     fn render(
         &mut self,
         state: &raving_wgpu::State,
         window: &raving_wgpu::WindowState,
         swapchain_view: &wgpu::TextureView,
         encoder: &mut wgpu::CommandEncoder,
+    ) -> anyhow::Result<()> {
+        let export = if self.slot_cache.tasks_running() {
+            None
+        } else {
+            self.pending_export.take()
+        };
+
+        if let Some(path) = export {
+            let done = self.export_done.take();
+            match self.export_image(state, window, &path) {
+                Ok(()) => {
+                    if let Some(done) = done {
+                        let _ = done.send(path);
+                    }
+                }
+                Err(e) => log::error!("Error exporting 1D view image: {e:?}"),
+            }
+        }
+
+        let screenshot = if self.slot_cache.tasks_running() {
+            None
+        } else {
+            self.pending_screenshot.take()
+        };
+
+        if let Some((path, scale)) = screenshot {
+            if let Err(e) = self.screenshot(state, window, &path, scale) {
+                log::error!("Error saving screenshot: {e:?}");
+            }
+        }
+
+        let size: [u32; 2] = window.window.inner_size().into();
+        self.render_sized(state, window, size, swapchain_view, encoder)
+    }
+}
+
+impl Viewer1D {
+    /// Renders the path data to `target`, of `size` pixels; anything
+    /// other than the window's size scales the view to fit.
+    fn render_sized(
+        &mut self,
+        state: &raving_wgpu::State,
+        window: &raving_wgpu::WindowState,
+        size: [u32; 2],
+        target: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
     ) -> anyhow::Result<()> {
         let data_id = self.active_viz_data_key.blocking_read().clone();
         let viz_mode_color = self
@@ -1329,8 +2911,6 @@ impl AppWindow for Viewer1D {
             return Ok(());
         }
 
-        let size: [u32; 2] = window.window.inner_size().into();
-
         let mut transient_res: HashMap<String, InputResource<'_>> =
             HashMap::default();
 
@@ -1342,7 +2922,7 @@ impl AppWindow for Viewer1D {
                 size,
                 format,
                 texture: None,
-                view: Some(&swapchain_view),
+                view: Some(target),
                 sampler: None,
             },
         );
@@ -1470,19 +3050,238 @@ impl AppWindow for Viewer1D {
 
         Ok(())
     }
-}
 
-impl Viewer1D {
-    fn visible_node_range(&self) -> std::ops::Range<Node> {
+    /// Renders the path data offscreen at the window's size, and
+    /// writes it to `png_path`, with the path names, view range and
+    /// annotations in an SVG next to it.
+    fn export_image(
+        &mut self,
+        state: &raving_wgpu::State,
+        window: &raving_wgpu::WindowState,
+        png_path: &std::path::Path,
+    ) -> Result<()> {
+        let (texture, [width, height]) =
+            self.render_offscreen(state, window, 1)?;
+
+        let pixels = export::read_texture_rgba(
+            state,
+            &texture,
+            [width, height],
+            window.surface_format,
+        )?;
+        export::write_png(
+            png_path,
+            width as usize,
+            height as usize,
+            4,
+            &pixels,
+        )?;
+
+        let svg_path = export::svg_path_for(png_path);
+        let ppp = self.last_pixels_per_point;
+        self.export_svg_overlay(png_path, [width, height], ppp)
+            .save(&svg_path)?;
+
+        log::warn!("Exported 1D view to {png_path:?} and {svg_path:?}");
+
+        let range = self.view.range();
+        let desc = format!("1D view {}-{}", range.start, range.end);
+
+        let mut log = self.shared.provenance.blocking_write();
+        log.record_path(EventKind::Export, &desc, png_path);
+        log.record_path(EventKind::Export, &desc, &svg_path);
+
+        Ok(())
+    }
+
+    /// Renders the view at `scale` times the window's size, with the
+    /// path names, view range and annotations drawn on top at the
+    /// same resolution, and writes it to `png_path`.
+    fn screenshot(
+        &mut self,
+        state: &raving_wgpu::State,
+        window: &raving_wgpu::WindowState,
+        png_path: &std::path::Path,
+        scale: u32,
+    ) -> Result<()> {
+        let (texture, [width, height]) =
+            self.render_offscreen(state, window, scale)?;
+        let format = window.surface_format;
+
+        let ppp = self.last_pixels_per_point * scale as f32;
+        let overlay = self.export_svg_overlay(png_path, [width, height], ppp);
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        export::draw_overlay(state, &view, format, [width, height], &overlay);
+
+        let pixels = export::read_texture_rgba(
+            state,
+            &texture,
+            [width, height],
+            format,
+        )?;
+        export::write_png(
+            png_path,
+            width as usize,
+            height as usize,
+            4,
+            &pixels,
+        )?;
+
+        log::warn!("Saved {width}x{height} screenshot to {png_path:?}");
+
+        let range = self.view.range();
+        let desc = format!("1D view {}-{} at {scale}x", range.start, range.end);
+        self.shared.provenance.blocking_write().record_path(
+            EventKind::Export,
+            &desc,
+            png_path,
+        );
+
+        Ok(())
+    }
+
+    /// Renders the path data to a new texture of `scale` times the
+    /// window's size, returning the texture and its size.
+    fn render_offscreen(
+        &mut self,
+        state: &raving_wgpu::State,
+        window: &raving_wgpu::WindowState,
+        scale: u32,
+    ) -> Result<(wgpu::Texture, [u32; 2])> {
+        let [width, height]: [u32; 2] = window.window.inner_size().into();
+        let [width, height] = [width * scale, height * scale];
+        let format = window.surface_format;
+
+        let max = state.device.limits().max_texture_dimension_2d;
+        anyhow::ensure!(
+            width <= max && height <= max,
+            "{width}x{height} is larger than the largest texture, {max}x{max}"
+        );
+
+        let texture = state.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Viewer1D Export"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = state.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Viewer1D Export"),
+            },
+        );
+
+        self.render_sized(state, window, [width, height], &view, &mut encoder)?;
+        state.queue.submit([encoder.finish()]);
+
+        Ok((texture, [width, height]))
+    }
+
+    /// The labels drawn over the view, for an image where a point is
+    /// `ppp` pixels.
+    fn export_svg_overlay(
+        &self,
+        png_path: &std::path::Path,
+        [width, height]: [u32; 2],
+        ppp: f32,
+    ) -> export::SvgOverlay {
+        let scale = |rect: egui::Rect| {
+            egui::Rect::from_min_max(
+                (rect.min.to_vec2() * ppp).to_pos2(),
+                (rect.max.to_vec2() * ppp).to_pos2(),
+            )
+        };
+
+        let mut svg = export::SvgOverlay::new(width as f32, height as f32);
+        svg.image = png_path.file_name().map(PathBuf::from);
+
+        let white = [255, 255, 255];
+
         let view = self.view.range();
-        let left_node = self.shared.graph.node_at_pangenome_pos(Bp(view.start));
-        let right_node = self.shared.graph.node_at_pangenome_pos(Bp(view.end));
+        let coords = self.shared.coords.load();
+        svg.labels.push(export::SvgLabel {
+            text: format!(
+                "{} bp ({})",
+                coords.format_range(&(Bp(view.start)..Bp(view.end))),
+                coords.short_name()
+            ),
+            pos: [4.0 * ppp, 4.0 * ppp],
+            font_size: 14.0 * ppp,
+            color: white,
+        });
 
-        let left = left_node.map(|n| n.ix()).unwrap_or(0);
-        let right = right_node
-            .map(|n| n.ix())
-            .unwrap_or(self.shared.graph.node_count - 1);
+        let annotations = self.shared.annotations.blocking_read();
+
+        let annot_layout = self.annot_layout.blocking_read();
+
+        for &(path, name_rect, data_rect) in &self.last_rows {
+            let name_rect = scale(name_rect);
+            let data_rect = scale(data_rect);
+
+            if let Some(name) = self.shared.graph.path_names.get_by_left(&path)
+            {
+                svg.labels.push(export::SvgLabel {
+                    text: name.clone(),
+                    pos: [name_rect.left(), name_rect.top()],
+                    font_size: 16.0 * ppp,
+                    color: white,
+                });
+            }
+
+            let slots = annot_layout.visible().filter_map(|entry| {
+                self.annotations
+                    .get_slot_id(path, entry.set_id)
+                    .and_then(|id| self.annotations.get(&id))
+            });
+
+            let slot_annots = slots.flat_map(|slot| {
+                slot.annotation_ranges.iter().map(move |r| (slot, r))
+            });
+
+            for (slot, (&annot_id, ranges)) in slot_annots {
+                let g_id = GlobalAnnotationId {
+                    set_id: slot.set_id,
+                    annot_id,
+                };
+                let Some(annot) = annotations.get(g_id) else {
+                    continue;
+                };
+                let color =
+                    annotations.get_color(g_id).unwrap_or(egui::Color32::RED);
+
+                for range in ranges {
+                    let Some(x) = self.view.map_bp_interval_to_screen_x(
+                        range,
+                        &data_rect.x_range(),
+                    ) else {
+                        continue;
+                    };
+
+                    svg.spans.push(export::SvgSpan {
+                        x: *x.start()..*x.end(),
+                        y: (data_rect.bottom() - 4.0 * ppp)..data_rect.bottom(),
+                        color: [color.r(), color.g(), color.b()],
+                        label: Some(annot.label.to_string()),
+                    });
+                }
+            }
+        }
+
+        svg
+    }
 
-        Node::from(left)..Node::from(right)
+    fn visible_node_range(&self) -> std::ops::RangeInclusive<u32> {
+        util::visible_nodes(&self.shared.graph, self.view.range().clone())
     }
 }
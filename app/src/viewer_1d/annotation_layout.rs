@@ -0,0 +1,359 @@
+//! The order and size of the annotation slots in each path row: one
+//! slot per annotation set (e.g. genes, repeats and variants as
+//! separate rows), which can be reordered, collapsed to just their
+//! intervals, hidden, or pinned so that every path gets the row.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+
+use crate::annotations::filter::AnnotationFilter;
+use crate::annotations::{AnnotationSetId, AnnotationStore};
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+
+/// Height of a collapsed slot, which only shows the intervals
+pub const COLLAPSED_HEIGHT: f32 = 8.0;
+
+const DEFAULT_HEIGHT: f32 = 50.0;
+
+const DEFAULT_DENSITY_THRESHOLD: usize = 1000;
+
+const FILTER_HELP: &str = "Only show the annotations the expression is \
+    true for, e.g. `len > 5000 && name ~ 'LINE'`. Records have `name`, \
+    `start`, `end`, `len`, `path`, and their attributes, e.g. `type`, \
+    or `attr[\"key\"]`; `~` matches a regex. Applied on Enter.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotLayoutEntry {
+    pub set_id: AnnotationSetId,
+    pub visible: bool,
+    pub collapsed: bool,
+    /// Shown on every path, even those without annotations in the
+    /// set, so that the rows line up
+    pub pinned: bool,
+    /// Height when expanded, in points
+    pub height: f32,
+    /// Only the annotations that pass the filter are shown
+    pub filter: Option<Arc<AnnotationFilter>>,
+}
+
+impl SlotLayoutEntry {
+    fn new(set_id: AnnotationSetId) -> Self {
+        Self {
+            set_id,
+            visible: true,
+            collapsed: false,
+            pinned: false,
+            height: DEFAULT_HEIGHT,
+            filter: None,
+        }
+    }
+
+    pub fn row_height(&self) -> f32 {
+        if self.collapsed {
+            COLLAPSED_HEIGHT
+        } else {
+            self.height
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotSlotLayout {
+    entries: Vec<SlotLayoutEntry>,
+    /// Hovered labels that are truncated scroll through their text,
+    /// instead of being shown in full on top of their neighbors
+    pub marquee: bool,
+    /// Slots with more annotations than this in view show their
+    /// density instead of labels
+    pub density_threshold: usize,
+}
+
+impl std::default::Default for AnnotSlotLayout {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            marquee: false,
+            density_threshold: DEFAULT_DENSITY_THRESHOLD,
+        }
+    }
+}
+
+impl AnnotSlotLayout {
+    /// Adds entries for new sets at the bottom, and removes those of
+    /// sets that are gone.
+    pub fn sync(&mut self, sets: impl IntoIterator<Item = AnnotationSetId>) {
+        let sets = sets.into_iter().collect::<Vec<_>>();
+
+        self.entries.retain(|e| sets.contains(&e.set_id));
+
+        for set_id in sets {
+            if self.get(set_id).is_none() {
+                self.entries.push(SlotLayoutEntry::new(set_id));
+            }
+        }
+    }
+
+    pub fn get(&self, set_id: AnnotationSetId) -> Option<&SlotLayoutEntry> {
+        self.entries.iter().find(|e| e.set_id == set_id)
+    }
+
+    /// The visible slots, top to bottom.
+    pub fn visible(&self) -> impl Iterator<Item = &SlotLayoutEntry> {
+        self.entries.iter().filter(|e| e.visible)
+    }
+
+    /// Shows only the given sets, in that order, above the hidden
+    /// ones; sets without an entry are ignored.
+    pub fn show_only(&mut self, sets: &[AnnotationSetId]) {
+        let rank = |e: &SlotLayoutEntry| {
+            sets.iter()
+                .position(|&id| id == e.set_id)
+                .unwrap_or(sets.len())
+        };
+
+        // stable, so the hidden ones keep their order
+        self.entries.sort_by_key(rank);
+
+        for entry in self.entries.iter_mut() {
+            entry.visible = sets.contains(&entry.set_id);
+        }
+    }
+
+    /// Swaps the entry at `ix` with the one above it.
+    pub fn move_up(&mut self, ix: usize) {
+        if ix > 0 && ix < self.entries.len() {
+            self.entries.swap(ix - 1, ix);
+        }
+    }
+
+    /// Swaps the entry at `ix` with the one below it.
+    pub fn move_down(&mut self, ix: usize) {
+        if ix + 1 < self.entries.len() {
+            self.entries.swap(ix, ix + 1);
+        }
+    }
+}
+
+/// Settings window panel for the annotation slot layout.
+pub struct AnnotSlotLayoutWidget {
+    annotations: Arc<RwLock<AnnotationStore>>,
+    layout: Arc<RwLock<AnnotSlotLayout>>,
+
+    filter_text: HashMap<AnnotationSetId, String>,
+    filter_errors: HashMap<AnnotationSetId, String>,
+}
+
+impl AnnotSlotLayoutWidget {
+    pub fn new(
+        annotations: Arc<RwLock<AnnotationStore>>,
+        layout: Arc<RwLock<AnnotSlotLayout>>,
+    ) -> Self {
+        Self {
+            annotations,
+            layout,
+            filter_text: HashMap::default(),
+            filter_errors: HashMap::default(),
+        }
+    }
+}
+
+impl SettingsWidget for AnnotSlotLayoutWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let annotations = self.annotations.blocking_read();
+        let mut layout = self.layout.blocking_write();
+
+        layout.sync(annotations.annotation_sets.keys().copied());
+
+        let resp = ui.vertical(|ui| {
+            if layout.entries.is_empty() {
+                ui.label("No annotations loaded");
+                return;
+            }
+
+            let mut move_up = None;
+            let mut move_down = None;
+            let count = layout.entries.len();
+
+            egui::Grid::new("AnnotSlotLayoutWidget-slots")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("");
+                    ui.label("Annotations");
+                    ui.label("Show");
+                    ui.label("Collapse");
+                    ui.label("Pin");
+                    ui.label("Height");
+                    ui.label("Filter").on_hover_text(FILTER_HELP);
+                    ui.end_row();
+
+                    for (ix, entry) in layout.entries.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            let up = ui.add_enabled(
+                                ix > 0,
+                                egui::Button::new("⏶").small(),
+                            );
+                            if up.clicked() {
+                                move_up = Some(ix);
+                            }
+
+                            let down = ui.add_enabled(
+                                ix + 1 < count,
+                                egui::Button::new("⏷").small(),
+                            );
+                            if down.clicked() {
+                                move_down = Some(ix);
+                            }
+                        });
+
+                        let name = annotations
+                            .annotation_sets
+                            .get(&entry.set_id)
+                            .map(|set| set.name.as_str())
+                            .unwrap_or_default();
+                        ui.label(name);
+
+                        ui.checkbox(&mut entry.visible, "");
+                        ui.checkbox(&mut entry.collapsed, "")
+                            .on_hover_text("Only show the intervals");
+                        ui.checkbox(&mut entry.pinned, "").on_hover_text(
+                            "Show on every path, even without annotations",
+                        );
+
+                        ui.add_enabled(
+                            !entry.collapsed,
+                            egui::DragValue::new(&mut entry.height)
+                                .clamp_range(20.0..=200.0)
+                                .suffix(" px"),
+                        );
+
+                        let set_id = entry.set_id;
+                        let text = self
+                            .filter_text
+                            .entry(set_id)
+                            .or_insert_with(|| {
+                                entry
+                                    .filter
+                                    .as_ref()
+                                    .map(|f| f.source().to_string())
+                                    .unwrap_or_default()
+                            });
+                        let error = self.filter_errors.get(&set_id);
+
+                        let mut edit = egui::TextEdit::singleline(text)
+                            .hint_text("all")
+                            .desired_width(160.0);
+                        if error.is_some() {
+                            edit = edit.text_color(ui.visuals().error_fg_color);
+                        }
+                        let resp = ui.add(edit);
+                        let resp = match error {
+                            Some(error) => resp.on_hover_text(error),
+                            None => resp.on_hover_text(FILTER_HELP),
+                        };
+
+                        if resp.lost_focus() {
+                            let text = text.trim();
+                            let current = entry.filter.as_ref();
+
+                            if text.is_empty() {
+                                entry.filter = None;
+                                self.filter_errors.remove(&set_id);
+                            } else if current.map(|f| f.source()) != Some(text)
+                            {
+                                match AnnotationFilter::parse(text) {
+                                    Ok(filter) => {
+                                        entry.filter = Some(Arc::new(filter));
+                                        self.filter_errors.remove(&set_id);
+                                    }
+                                    Err(e) => {
+                                        self.filter_errors
+                                            .insert(set_id, e.to_string());
+                                    }
+                                }
+                            }
+                        }
+
+                        ui.end_row();
+                    }
+                });
+
+            if let Some(ix) = move_up {
+                layout.move_up(ix);
+            }
+            if let Some(ix) = move_down {
+                layout.move_down(ix);
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Show density above");
+                ui.add(
+                    egui::DragValue::new(&mut layout.density_threshold)
+                        .clamp_range(1..=1_000_000)
+                        .speed(10.0),
+                );
+                ui.label("annotations in view");
+            })
+            .response
+            .on_hover_text(
+                "Slots with more annotations in view show a bar chart \
+                 of the number of annotations per pixel instead of the \
+                 labels, until zoomed in",
+            );
+
+            ui.checkbox(&mut layout.marquee, "Scroll truncated labels")
+                .on_hover_text(
+                    "Labels are cut to the width of their annotation; \
+                     when hovered, scroll through the text instead of \
+                     showing it in full",
+                );
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_sync_and_order() {
+        let mut layout = AnnotSlotLayout::default();
+        layout.sync([AnnotationSetId(0), AnnotationSetId(1)]);
+        layout.move_down(0);
+        layout.sync([
+            AnnotationSetId(0),
+            AnnotationSetId(1),
+            AnnotationSetId(2),
+        ]);
+
+        let order = |layout: &AnnotSlotLayout| {
+            layout.visible().map(|e| e.set_id.0).collect::<Vec<_>>()
+        };
+        assert_eq!(order(&layout), vec![1, 0, 2]);
+
+        layout.entries[1].visible = false;
+        layout.move_up(2);
+        assert_eq!(order(&layout), vec![1, 2]);
+
+        // removed sets are dropped, keeping the order of the rest
+        layout.sync([AnnotationSetId(2), AnnotationSetId(1)]);
+        assert_eq!(order(&layout), vec![1, 2]);
+
+        layout.sync([AnnotationSetId(0), AnnotationSetId(1)]);
+        layout.show_only(&[AnnotationSetId(0), AnnotationSetId(5)]);
+        assert_eq!(order(&layout), vec![0]);
+        layout.show_only(&[AnnotationSetId(1), AnnotationSetId(0)]);
+        assert_eq!(order(&layout), vec![1, 0]);
+    }
+}
@@ -0,0 +1,610 @@
+//! Aggregate statistics over a selection of annotations, e.g. all
+//! exons in the 1D view: the bp they cover, the mean pangenome depth
+//! over them, and how much they overlap another annotation track.
+
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use crossbeam::atomic::AtomicCell;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::coords::CoordSystem;
+use waragraph_core::graph::{Bp, PathId, PathIndex};
+
+use crate::annotations::{Annotation, AnnotationSet, AnnotationSetId};
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::SharedState;
+
+use super::annotations::merge_ranges;
+use super::control::path_range_to_pangenome;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationStatsRow {
+    pub label: Arc<String>,
+    pub path: PathId,
+    pub range: std::ops::Range<Bp>,
+    pub mean_depth: Option<f64>,
+    /// Bp of the annotation covered by the other track
+    pub overlap: Option<Bp>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationStats {
+    pub rows: Vec<AnnotationStatsRow>,
+    /// Bp covered by the annotations, counting overlaps once
+    pub covered: Bp,
+    /// Mean depth over the covered bp
+    pub mean_depth: Option<f64>,
+    /// Covered bp that are also covered by the other track
+    pub overlap: Option<Bp>,
+}
+
+/// Computes the statistics of `annotations`, with the mean depth if
+/// the depth of each node is given, and the overlap with `other` if
+/// given. Overlaps are computed in path coordinates, between
+/// annotations on the same path.
+pub fn compute_stats(
+    graph: &PathIndex,
+    annotations: &[&Annotation],
+    node_depth: Option<&[f32]>,
+    other: Option<&AnnotationSet>,
+) -> AnnotationStats {
+    let other_ranges = other.map(|set| path_ranges(set.annotations.iter()));
+
+    let row_overlap = |path: PathId, range: &std::ops::Range<Bp>| {
+        let other = other_ranges.as_ref()?;
+        let ranges = other.get(&path).map(|r| r.as_slice()).unwrap_or(&[]);
+        Some(Bp(intersection_len(std::slice::from_ref(range), ranges)))
+    };
+
+    let rows = annotations
+        .iter()
+        .map(|annot| {
+            let mean_depth = node_depth.and_then(|depth| {
+                let len = annot.range.end.0 - annot.range.start.0;
+                let sum = depth_sum(graph, annot.path, &annot.range, depth);
+                (len > 0).then(|| sum / len as f64)
+            });
+
+            AnnotationStatsRow {
+                label: annot.label.clone(),
+                path: annot.path,
+                range: annot.range.clone(),
+                mean_depth,
+                overlap: row_overlap(annot.path, &annot.range),
+            }
+        })
+        .collect();
+
+    let ranges = path_ranges(annotations.iter().copied());
+
+    let covered = ranges
+        .values()
+        .flatten()
+        .map(|r| r.end.0 - r.start.0)
+        .sum::<u64>();
+
+    let mean_depth = node_depth.and_then(|depth| {
+        let sum = ranges
+            .iter()
+            .flat_map(|(path, ranges)| {
+                ranges.iter().map(|r| depth_sum(graph, *path, r, depth))
+            })
+            .sum::<f64>();
+        (covered > 0).then(|| sum / covered as f64)
+    });
+
+    let overlap = other_ranges.as_ref().map(|other| {
+        let total = ranges
+            .iter()
+            .map(|(path, ranges)| {
+                let others =
+                    other.get(path).map(|r| r.as_slice()).unwrap_or(&[]);
+                intersection_len(ranges, others)
+            })
+            .sum::<u64>();
+        Bp(total)
+    });
+
+    AnnotationStats {
+        rows,
+        covered: Bp(covered),
+        mean_depth,
+        overlap,
+    }
+}
+
+impl AnnotationStats {
+    /// Writes a row per annotation, after the totals as `#` comments,
+    /// with the positions in the `coords` convention.
+    pub fn write_tsv(
+        &self,
+        graph: &PathIndex,
+        coords: CoordSystem,
+        mut out: impl Write,
+    ) -> Result<()> {
+        let opt = |v: Option<String>| v.unwrap_or_else(|| ".".to_string());
+
+        writeln!(out, "{}", coords.header_comment())?;
+        writeln!(out, "# annotations\t{}", self.rows.len())?;
+        writeln!(out, "# covered_bp\t{}", self.covered.0)?;
+        writeln!(
+            out,
+            "# mean_depth\t{}",
+            opt(self.mean_depth.map(|d| format!("{d:.3}")))
+        )?;
+        writeln!(
+            out,
+            "# overlap_bp\t{}",
+            opt(self.overlap.map(|o| o.0.to_string()))
+        )?;
+
+        writeln!(
+            out,
+            "label\tpath\tstart\tend\tlength\tmean_depth\toverlap_bp"
+        )?;
+
+        for row in &self.rows {
+            let path_name = graph
+                .path_names
+                .get_by_left(&row.path)
+                .map(|name| name.as_str())
+                .unwrap_or_default();
+
+            let (start, end) = coords.display_range(&row.range);
+
+            writeln!(
+                out,
+                "{}\t{path_name}\t{start}\t{end}\t{}\t{}\t{}",
+                row.label,
+                row.range.end.0 - row.range.start.0,
+                opt(row.mean_depth.map(|d| format!("{d:.3}"))),
+                opt(row.overlap.map(|o| o.0.to_string())),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The merged ranges of the annotations on each path.
+fn path_ranges<'a>(
+    annotations: impl Iterator<Item = &'a Annotation>,
+) -> HashMap<PathId, Vec<std::ops::Range<Bp>>> {
+    let mut ranges: HashMap<PathId, Vec<_>> = HashMap::default();
+
+    for annot in annotations {
+        ranges
+            .entry(annot.path)
+            .or_default()
+            .push(annot.range.clone());
+    }
+
+    ranges
+        .into_iter()
+        .map(|(path, ranges)| (path, merge_ranges(ranges)))
+        .collect()
+}
+
+/// The sum of the depth of each bp in the range of the path.
+fn depth_sum(
+    graph: &PathIndex,
+    path: PathId,
+    range: &std::ops::Range<Bp>,
+    node_depth: &[f32],
+) -> f64 {
+    let Some(steps) = graph.path_step_range_iter(path, range.clone()) else {
+        return 0.0;
+    };
+
+    let offsets = &graph.path_step_offsets[path.ix()];
+
+    steps
+        .filter_map(|(step_ix, step)| {
+            let start = offsets.select(step_ix as u64)?;
+            let end = start + graph.node_length(step.node()).0;
+
+            let overlap = end
+                .min(range.end.0)
+                .saturating_sub(start.max(range.start.0));
+            let depth = node_depth.get(step.node().ix()).copied()?;
+
+            Some(depth as f64 * overlap as f64)
+        })
+        .sum()
+}
+
+/// The total length of the intersection of two sorted lists of
+/// disjoint ranges.
+fn intersection_len(
+    a: &[std::ops::Range<Bp>],
+    b: &[std::ops::Range<Bp>],
+) -> u64 {
+    let (mut i, mut j) = (0, 0);
+    let mut total = 0;
+
+    while i < a.len() && j < b.len() {
+        let start = a[i].start.max(b[j].start);
+        let end = a[i].end.min(b[j].end);
+
+        if start < end {
+            total += end.0 - start.0;
+        }
+
+        if a[i].end < b[j].end {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    total
+}
+
+/// Settings window panel for computing the statistics of the
+/// annotations in a set, optionally filtered by label or attribute
+/// and to those in the 1D view.
+pub struct AnnotationStatsWidget {
+    shared: SharedState,
+    // the pangenome range of the 1D view
+    view_range: Arc<AtomicCell<(u64, u64)>>,
+
+    set_id: Option<AnnotationSetId>,
+    other_set_id: Option<AnnotationSetId>,
+    filter: String,
+    only_in_view: bool,
+
+    stats_recv: Option<oneshot::Receiver<AnnotationStats>>,
+    stats: Option<AnnotationStats>,
+
+    export_path_recv: Option<oneshot::Receiver<PathBuf>>,
+}
+
+impl AnnotationStatsWidget {
+    pub fn new(
+        shared: &SharedState,
+        view_range: Arc<AtomicCell<(u64, u64)>>,
+    ) -> Self {
+        Self {
+            shared: shared.clone(),
+            view_range,
+            set_id: None,
+            other_set_id: None,
+            filter: String::new(),
+            only_in_view: true,
+            stats_recv: None,
+            stats: None,
+            export_path_recv: None,
+        }
+    }
+
+    /// Whether the annotation passes the filter: `key=value` matches
+    /// an attribute, anything else is a substring of the label.
+    fn matches_filter(filter: &str, annot: &Annotation) -> bool {
+        let filter = filter.trim();
+
+        if let Some((key, value)) = filter.split_once('=') {
+            annot.attribute(key.trim()) == Some(value.trim())
+        } else {
+            annot.label.contains(filter)
+        }
+    }
+
+    fn spawn_compute(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        let Some(set_id) = self.set_id else {
+            return;
+        };
+
+        let annotations = self.shared.annotations.blocking_read();
+        let Some(set) = annotations.annotation_sets.get(&set_id).cloned()
+        else {
+            return;
+        };
+        let other = self
+            .other_set_id
+            .and_then(|id| annotations.annotation_sets.get(&id).cloned());
+        drop(annotations);
+
+        let graph = self.shared.graph.clone();
+        let data_cache = self.shared.graph_data_cache.clone();
+
+        let filter = self.filter.clone();
+        let view = self.only_in_view.then(|| {
+            let (start, end) = self.view_range.load();
+            Bp(start)..Bp(end)
+        });
+
+        let (send, recv) = oneshot::channel();
+
+        tokio_handle.spawn_blocking(move || {
+            let selected = set
+                .annotations
+                .iter()
+                .filter(|annot| Self::matches_filter(&filter, annot))
+                .filter(|annot| {
+                    let Some(view) = view.as_ref() else {
+                        return true;
+                    };
+                    path_range_to_pangenome(
+                        &graph,
+                        annot.path,
+                        annot.range.clone(),
+                    )
+                    .map_or(false, |r| r.start < view.end && view.start < r.end)
+                })
+                .collect::<Vec<_>>();
+
+            let depth = data_cache.fetch_graph_data_blocking("depth");
+            let depth = depth.as_ref().map(|d| d.node_data.as_slice());
+
+            let stats =
+                compute_stats(&graph, &selected, depth, other.as_deref());
+            let _ = send.send(stats);
+        });
+
+        self.stats_recv = Some(recv);
+    }
+
+    fn poll_channels(&mut self) {
+        if let Some(mut recv) = self.stats_recv.take() {
+            match recv.try_recv() {
+                Ok(stats) => self.stats = Some(stats),
+                Err(TryRecvError::Empty) => self.stats_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.export_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    if let Some(stats) = self.stats.as_ref() {
+                        let result = std::fs::File::create(&path)
+                            .map_err(anyhow::Error::from)
+                            .and_then(|file| {
+                                let mut out = std::io::BufWriter::new(file);
+                                stats.write_tsv(
+                                    &self.shared.graph,
+                                    self.shared.coords.load(),
+                                    &mut out,
+                                )?;
+                                out.flush()?;
+                                Ok(())
+                            });
+
+                        match result {
+                            Ok(()) => log::warn!(
+                                "Wrote annotation statistics to {path:?}"
+                            ),
+                            Err(e) => log::error!(
+                                "Error writing annotation statistics: {e:?}"
+                            ),
+                        }
+                    }
+                }
+                Err(TryRecvError::Empty) => self.export_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+impl SettingsWidget for AnnotationStatsWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels();
+
+        let sets = self
+            .shared
+            .annotations
+            .blocking_read()
+            .annotation_sets
+            .iter()
+            .map(|(id, set)| (*id, set.name.clone()))
+            .collect::<Vec<_>>();
+
+        let set_name = |id: Option<AnnotationSetId>, none: &str| {
+            id.and_then(|id| sets.iter().find(|(s, _)| *s == id))
+                .map(|(_, name)| name.clone())
+                .unwrap_or_else(|| none.to_string())
+        };
+
+        let resp = ui.vertical(|ui| {
+            egui::ComboBox::from_label("Annotations")
+                .selected_text(set_name(self.set_id, "Select annotations"))
+                .show_ui(ui, |ui| {
+                    for (id, name) in sets.iter() {
+                        ui.selectable_value(&mut self.set_id, Some(*id), name);
+                    }
+                });
+
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter)
+                    .hint_text("Label, or attribute=value"),
+            )
+            .on_hover_text("e.g. type=exon; empty to use all annotations");
+
+            ui.checkbox(&mut self.only_in_view, "Only in the 1D view");
+
+            egui::ComboBox::from_label("Overlap with")
+                .selected_text(set_name(self.other_set_id, "None"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.other_set_id, None, "None");
+                    for (id, name) in sets.iter() {
+                        ui.selectable_value(
+                            &mut self.other_set_id,
+                            Some(*id),
+                            name,
+                        );
+                    }
+                });
+
+            ui.horizontal(|ui| {
+                let computing = self.stats_recv.is_some();
+
+                if ui
+                    .add_enabled(
+                        !computing && self.set_id.is_some(),
+                        egui::Button::new("Compute"),
+                    )
+                    .clicked()
+                {
+                    self.spawn_compute(&settings_ctx.tokio_handle);
+                }
+
+                if computing {
+                    ui.spinner();
+                }
+
+                if ui
+                    .add_enabled(
+                        self.stats.is_some(),
+                        egui::Button::new("Export TSV"),
+                    )
+                    .clicked()
+                {
+                    let path = self
+                        .shared
+                        .export_paths
+                        .blocking_read()
+                        .export_path("annotation_stats", None, "tsv");
+
+                    let id = egui::Id::new("AnnotationStatsWidget_export");
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.export_path_recv = Some(recv);
+                }
+            });
+
+            let Some(stats) = self.stats.as_ref() else {
+                return;
+            };
+
+            egui::Grid::new("AnnotationStatsWidget-totals").show(ui, |ui| {
+                ui.label("Annotations");
+                ui.label(stats.rows.len().to_string());
+                ui.end_row();
+
+                ui.label("Covered bp");
+                ui.label(stats.covered.0.to_string());
+                ui.end_row();
+
+                if let Some(depth) = stats.mean_depth {
+                    ui.label("Mean depth");
+                    ui.label(format!("{depth:.2}"));
+                    ui.end_row();
+                }
+
+                if let Some(overlap) = stats.overlap {
+                    let percent = 100.0 * overlap.0 as f64
+                        / stats.covered.0.max(1) as f64;
+                    ui.label("Overlap bp");
+                    ui.label(format!("{} ({percent:.1}%)", overlap.0));
+                    ui.end_row();
+                }
+            });
+
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("AnnotationStatsWidget-rows")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label("Label");
+                            ui.label("Length");
+                            ui.label("Mean depth");
+                            ui.label("Overlap");
+                            ui.end_row();
+
+                            for row in stats.rows.iter() {
+                                let len = row.range.end.0 - row.range.start.0;
+                                let depth = row
+                                    .mean_depth
+                                    .map(|d| format!("{d:.2}"))
+                                    .unwrap_or_default();
+                                let overlap = row
+                                    .overlap
+                                    .map(|o| o.0.to_string())
+                                    .unwrap_or_default();
+
+                                ui.label(row.label.as_str());
+                                ui.label(len.to_string());
+                                ui.label(depth);
+                                ui.label(overlap);
+                                ui.end_row();
+                            }
+                        });
+                });
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annot(path: u32, range: std::ops::Range<u64>) -> Annotation {
+        Annotation {
+            path: PathId::from(path),
+            range: Bp(range.start)..Bp(range.end),
+            label: Arc::new("a".to_string()),
+            color: None,
+            blocks: Vec::new(),
+            out_of_bounds: false,
+            attributes: Vec::new(),
+            label_color: None,
+        }
+    }
+
+    #[test]
+    fn annotation_stats_merge_overlaps() {
+        let gfa_path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../test/data/A-3105.fa.353ea42.34ee7b1.1576367.smooth.fix.gfa"
+        );
+        let graph = PathIndex::from_gfa(gfa_path).unwrap();
+
+        let a = annot(0, 100..200);
+        let b = annot(0, 150..300);
+        let c = annot(1, 0..50);
+
+        // every node has depth 2, so the mean is 2 everywhere
+        let depth = vec![2f32; graph.node_count];
+
+        let other = AnnotationSet {
+            name: "other".to_string(),
+            annotations: vec![annot(0, 250..400), annot(1, 40..45)],
+            path_annotations: Default::default(),
+            out_of_bounds: 0,
+            source: None,
+        };
+
+        let stats =
+            compute_stats(&graph, &[&a, &b, &c], Some(&depth), Some(&other));
+
+        assert_eq!(stats.covered, Bp(250));
+        assert!((stats.mean_depth.unwrap() - 2.0).abs() < 1e-6);
+        assert_eq!(stats.overlap, Some(Bp(55)));
+
+        assert_eq!(stats.rows[0].overlap, Some(Bp(0)));
+        assert_eq!(stats.rows[1].overlap, Some(Bp(50)));
+        assert_eq!(stats.rows[2].overlap, Some(Bp(5)));
+        assert!((stats.rows[0].mean_depth.unwrap() - 2.0).abs() < 1e-6);
+
+        let stats = compute_stats(&graph, &[&a], None, None);
+        assert_eq!(stats.covered, Bp(100));
+        assert_eq!(stats.mean_depth, None);
+        assert_eq!(stats.overlap, None);
+    }
+}
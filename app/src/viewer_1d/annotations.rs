@@ -1,7 +1,8 @@
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
 
 use bimap::BiHashMap;
+use crossbeam::atomic::AtomicCell;
 use rstar::{
     primitives::{GeomWithData, Line},
     RTree,
@@ -10,7 +11,9 @@ use tokio::{sync::Mutex, task::JoinHandle};
 use ultraviolet::Vec2;
 use waragraph_core::graph::{Bp, PathId, PathIndex};
 
-use crate::annotations::{AnnotationId, AnnotationSetId};
+use crate::annotations::filter::AnnotationFilter;
+use crate::annotations::{Annotation, AnnotationId, AnnotationSetId};
+use crate::gui::theme::Theme;
 
 use super::view::View1D;
 
@@ -24,12 +27,80 @@ pub struct Annots1D {
     slots: HashMap<AnnotSlotId, AnnotSlot>,
     next_slot_id: AnnotSlotId,
 
-    path_annot_slot: BiHashMap<PathId, AnnotSlotId>,
+    // one slot for each annotation set on each path
+    path_annot_slot: BiHashMap<(PathId, AnnotationSetId), AnnotSlotId>,
+
+    hide_out_of_bounds: bool,
+    style_generation: u64,
+    filters: HashMap<AnnotationSetId, Arc<AnnotationFilter>>,
 }
 
 impl Annots1D {
-    pub fn get_path_slot_id(&self, path: PathId) -> Option<AnnotSlotId> {
-        let slot = self.path_annot_slot.get_by_left(&path)?;
+    pub fn hide_out_of_bounds(&self) -> bool {
+        self.hide_out_of_bounds
+    }
+
+    /// Sets whether annotations that were clamped to the end of their
+    /// path are hidden, or pinned to the path end; the slots are
+    /// removed when this changes, so that they get rebuilt.
+    pub fn set_hide_out_of_bounds(&mut self, hide: bool) {
+        if hide != self.hide_out_of_bounds {
+            self.hide_out_of_bounds = hide;
+            self.slots.clear();
+            self.path_annot_slot.clear();
+        }
+    }
+
+    /// Removes the slots when the annotation styles have changed
+    /// since they were built, so that they get rebuilt.
+    pub fn set_style_generation(&mut self, generation: u64) {
+        if generation != self.style_generation {
+            self.style_generation = generation;
+            self.slots.clear();
+            self.path_annot_slot.clear();
+        }
+    }
+
+    pub fn filter(&self, set_id: AnnotationSetId) -> Option<&AnnotationFilter> {
+        self.filters.get(&set_id).map(|f| f.as_ref())
+    }
+
+    /// Sets the filter the annotations in the set's slots must pass;
+    /// the set's slots are removed when it changes, so that they get
+    /// rebuilt.
+    pub fn set_filter(
+        &mut self,
+        set_id: AnnotationSetId,
+        filter: Option<Arc<AnnotationFilter>>,
+    ) {
+        if self.filters.get(&set_id) == filter.as_ref() {
+            return;
+        }
+
+        match filter {
+            Some(filter) => self.filters.insert(set_id, filter),
+            None => self.filters.remove(&set_id),
+        };
+
+        let slot_ids = self
+            .path_annot_slot
+            .iter()
+            .filter(|((_, set), _)| *set == set_id)
+            .map(|(_, slot_id)| *slot_id)
+            .collect::<Vec<_>>();
+
+        for slot_id in slot_ids {
+            self.path_annot_slot.remove_by_right(&slot_id);
+            self.slots.remove(&slot_id);
+        }
+    }
+
+    pub fn get_slot_id(
+        &self,
+        path: PathId,
+        set_id: AnnotationSetId,
+    ) -> Option<AnnotSlotId> {
+        let slot = self.path_annot_slot.get_by_left(&(path, set_id))?;
         Some(*slot)
     }
 
@@ -37,7 +108,7 @@ impl Annots1D {
         &self,
         annot_slot: AnnotSlotId,
     ) -> Option<PathId> {
-        let path = self.path_annot_slot.get_by_right(&annot_slot)?;
+        let (path, _) = self.path_annot_slot.get_by_right(&annot_slot)?;
         Some(*path)
     }
 
@@ -47,8 +118,8 @@ impl Annots1D {
         slot: AnnotSlot,
     ) -> AnnotSlotId {
         let slot_id = self.next_slot_id;
+        self.path_annot_slot.insert((path, slot.set_id), slot_id);
         self.slots.insert(slot_id, slot);
-        self.path_annot_slot.insert(path, slot_id);
         self.next_slot_id = AnnotSlotId(slot_id.0 + 1);
         slot_id
     }
@@ -64,26 +135,99 @@ impl Annots1D {
 
 type AnnotsTreeObj = GeomWithData<Line<(i64, i64)>, AnnotationId>;
 
-type ShapeFn = Box<dyn Fn(&egui::Painter, egui::Pos2) -> egui::Shape>;
+type ShapeFn = Box<dyn Fn(&egui::Painter, egui::Pos2, LabelFit) -> egui::Shape>;
 
-pub fn text_shape<L: ToString>(label: L) -> ShapeFn {
+/// How a label is fit to the width of its annotation
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LabelFit {
+    /// Labels wider than this are truncated with an ellipsis
+    pub max_width: Option<f32>,
+    /// If the label is truncated, scroll it by this many characters
+    /// instead of ending it with an ellipsis
+    pub scroll: Option<usize>,
+}
+
+/// Draws the label in the given color, or the theme's label color.
+pub fn text_shape<L: ToString>(
+    label: L,
+    color: Option<egui::Color32>,
+    theme: Arc<AtomicCell<Theme>>,
+) -> ShapeFn {
     let label = label.to_string();
-    Box::new(move |painter, pos| {
+    Box::new(move |painter, pos, fit| {
+        let theme = theme.load();
         painter.fonts(|fonts| {
             let font = egui::FontId::proportional(16.0);
-            let color = egui::Color32::WHITE;
-            egui::Shape::text(
+            let text = fit_label(fonts, &label, &font, fit);
+            theme.text_shape(
                 &fonts,
                 pos,
                 egui::Align2::CENTER_CENTER,
-                label.clone(),
+                text,
                 font,
-                color,
+                color.unwrap_or_else(|| theme.label_color()),
             )
         })
     })
 }
 
+/// The part of `label` that fits in `fit.max_width`: the longest
+/// prefix followed by `…`, or, when scrolling, the longest run of
+/// characters from the scroll offset, wrapping around to the start.
+fn fit_label(
+    fonts: &egui::text::Fonts,
+    label: &str,
+    font: &egui::FontId,
+    fit: LabelFit,
+) -> String {
+    let width = |text: String| {
+        let color = egui::Color32::WHITE;
+        fonts.layout_no_wrap(text, font.clone(), color).size().x
+    };
+
+    let Some(max_width) = fit.max_width else {
+        return label.to_string();
+    };
+
+    if width(label.to_string()) <= max_width {
+        return label.to_string();
+    }
+
+    let (chars, suffix): (Vec<char>, &str) = match fit.scroll {
+        Some(offset) => {
+            // the label repeats with a gap in between
+            let cycle = label.chars().chain("   ".chars()).collect::<Vec<_>>();
+            let start = offset % cycle.len();
+            let chars = cycle[start..].iter().chain(&cycle[..start]);
+            (chars.copied().collect(), "")
+        }
+        None => (label.chars().collect(), "…"),
+    };
+
+    let text = |n: usize| {
+        let prefix = chars[..n].iter().collect::<String>();
+        let prefix = if suffix.is_empty() {
+            prefix.as_str()
+        } else {
+            prefix.trim_end()
+        };
+        format!("{prefix}{suffix}")
+    };
+
+    // the longest prefix that fits
+    let (mut lo, mut hi) = (0, chars.len());
+    while lo < hi {
+        let mid = (lo + hi + 1) / 2;
+        if width(text(mid)) <= max_width {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+
+    text(lo)
+}
+
 // Container for annotations displayed in a single 1D slot,
 // with the annotations "flattened" to the pangenome coordinate
 // space, down from the path-range space
@@ -96,12 +240,17 @@ pub struct AnnotSlot {
 
     shape_fns: Vec<ShapeFn>,
 
+    // pangenome ranges of the thick parts of each annotation's
+    // interval, and the interval colors, indexed by annotation ID
+    interval_blocks: HashMap<AnnotationId, Vec<std::ops::Range<Bp>>>,
+    interval_colors: Vec<egui::Color32>,
+
     dynamics: Arc<Mutex<AnnotSlotDynamics>>,
 
-    task: Option<JoinHandle<Vec<(AnnotationId, Vec2)>>>,
+    task: Option<JoinHandle<Vec<PlacedLabel>>>,
 
-    // pair of (annot_id, pos) as produced by task; first value is used as key to shape_fn
-    positions: Vec<(AnnotationId, Vec2)>,
+    // as produced by task; the annotation ID is used as key to shape_fn
+    positions: Vec<PlacedLabel>,
 
     // pair of (annot_id, shape size) as produced by rendering
     shape_sizes: Vec<(AnnotationId, Vec2)>,
@@ -120,6 +269,11 @@ struct AnnotSlotDynamics {
 
     visible_set: BTreeSet<AnnotationId>,
     // visible_set: HashSet<AnnotationId>,
+
+    // pangenome ranges of the anchor tree entries that intersect the
+    // current view, for each annotation in the visible set
+    visible_ranges: HashMap<AnnotationId, Vec<std::ops::Range<Bp>>>,
+    prev_screen_rect: Option<egui::Rect>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -168,107 +322,216 @@ impl AnnotSlotDynamics {
         screen_rect: egui::Rect,
         view: &View1D,
     ) {
-        use rstar::AABB;
-        let rleft = screen_rect.left();
-        let rright = screen_rect.right();
+        self.prev_view = self.cur_view.clone();
+        self.cur_view = Some(view.clone());
+
+        let prev_rect = self.prev_screen_rect.replace(screen_rect);
 
-        let screen_interval = rleft..=rright;
+        let prev_view = self
+            .prev_view
+            .clone()
+            .filter(|v0| is_small_pan(v0, view))
+            .filter(|_| prev_rect == Some(screen_rect));
+
+        if let Some(prev_view) = prev_view {
+            self.prepare_pan(annots, screen_rect, &prev_view, view);
+        } else {
+            self.prepare_full(annots, screen_rect, view);
+        }
+    }
 
+    /// Rebuilds the visible set from scratch.
+    fn prepare_full(
+        &mut self,
+        annots: &RTree<AnnotsTreeObj>,
+        screen_rect: egui::Rect,
+        view: &View1D,
+    ) {
         let range = view.range();
+        let range = Bp(range.start)..Bp(range.end);
 
-        let aabb =
-            AABB::from_corners((range.start as i64, 0), (range.end as i64, 0));
+        self.visible_ranges.clear();
+        self.visible_set.clear();
 
-        let in_view = annots.locate_in_envelope_intersecting(&aabb);
+        // collect the visible annotations
+        for (a_id, anchor_range) in locate_in_bp_range(annots, range) {
+            self.visible_ranges
+                .entry(a_id)
+                .or_default()
+                .push(anchor_range);
+        }
 
-        self.prev_view = self.cur_view.clone();
-        self.cur_view = Some(view.clone());
+        let a_ids = self.visible_ranges.keys().copied().collect::<Vec<_>>();
 
-        let mut annot_ranges: HashMap<AnnotationId, Vec<_>> =
-            HashMap::default();
+        let mut rng = rand::thread_rng();
 
-        // let mut annot_reset_pos: HashSet<_> = HashSet::default();
+        for a_id in a_ids {
+            self.place_anchor_target(a_id, screen_rect, view, &mut rng);
+        }
+    }
 
-        // collect the visible annotations
-        for line in in_view {
-            let a_id = line.data;
-            let left = Bp(line.geom().from.0 as u64);
-            let right = Bp(line.geom().to.0 as u64);
-
-            if let Some(anchor_range) = view
-                .map_bp_interval_to_screen_x(&(left..right), &screen_interval)
-            {
-                annot_ranges
-                    .entry(a_id)
-                    .or_default()
-                    .push(anchor_range.clone());
+    /// Updates the visible set after the view has been translated by
+    /// less than its length, looking only at the annotations in the
+    /// strips of the pangenome that left or entered the view. The
+    /// anchor targets of the annotations that stay in view are kept
+    /// as they are, unless part of their anchor range left the view.
+    fn prepare_pan(
+        &mut self,
+        annots: &RTree<AnnotsTreeObj>,
+        screen_rect: egui::Rect,
+        prev_view: &View1D,
+        view: &View1D,
+    ) {
+        let r0 = prev_view.range();
+        let r1 = view.range();
+
+        if r0 == r1 {
+            return;
+        }
+
+        let (leaving, entering) = if r1.start > r0.start {
+            (r0.start..r1.start, r0.end..r1.end)
+        } else {
+            (r1.end..r0.end, r1.start..r0.start)
+        };
+
+        let mut rng = rand::thread_rng();
+
+        let screen_interval = screen_rect.left()..=screen_rect.right();
+
+        // annotations that touch the leaving strip have either left
+        // the view, or need their anchor target constrained to what's
+        // still visible
+        let mut touched = BTreeSet::new();
+
+        let leaving = Bp(leaving.start)..Bp(leaving.end);
+        for (a_id, _) in locate_in_bp_range(annots, leaving) {
+            touched.insert(a_id);
+        }
+
+        for &a_id in touched.iter() {
+            let Some(ranges) = self.visible_ranges.get_mut(&a_id) else {
+                continue;
+            };
+
+            ranges.retain(|r| {
+                view.map_bp_interval_to_screen_x(r, &screen_interval)
+                    .is_some()
+            });
+
+            if ranges.is_empty() {
+                self.visible_ranges.remove(&a_id);
+                self.visible_set.remove(&a_id);
             }
         }
 
-        self.visible_set.clear();
+        let entering = Bp(entering.start)..Bp(entering.end);
+        for (a_id, anchor_range) in locate_in_bp_range(annots, entering) {
+            let ranges = self.visible_ranges.entry(a_id).or_default();
 
+            if !ranges.contains(&anchor_range) {
+                ranges.push(anchor_range);
+            }
+
+            touched.insert(a_id);
+        }
+
+        for a_id in touched {
+            if self.visible_ranges.contains_key(&a_id) {
+                self.place_anchor_target(a_id, screen_rect, view, &mut rng);
+            }
+        }
+    }
+
+    /// Sets the anchor target of an annotation in the visible set,
+    /// adding it to the set.
+    ///
+    /// If the annotation already has a target, it's constrained to
+    /// the visible part of the anchor ranges; otherwise a random point
+    /// on the visible anchor ranges is chosen.
+    fn place_anchor_target(
+        &mut self,
+        a_id: AnnotationId,
+        screen_rect: egui::Rect,
+        view: &View1D,
+        rng: &mut impl rand::Rng,
+    ) {
         use rand::distributions::WeightedIndex;
-        use rand::prelude::*;
-        let mut rng = rand::thread_rng();
 
-        for (&a_id, ranges) in annot_ranges.iter() {
-            // if the annotation has no object, create it
-            let obj = self.get_or_insert_annot_obj_mut(a_id);
+        let screen_interval = screen_rect.left()..=screen_rect.right();
 
-            // if there's already an anchor target on this object,
-            // constrain it to the visible anchor set
+        let ranges = self
+            .visible_ranges
+            .get(&a_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|r| {
+                view.map_bp_interval_to_screen_x(r, &screen_interval)
+            })
+            .collect::<Vec<_>>();
+
+        if ranges.is_empty() {
+            return;
+        }
 
-            if let Some(tgt) = obj.anchor_target_pos.as_mut() {
-                let mut dist = std::f32::INFINITY;
-                let mut closest_tgt = None;
+        // if the annotation has no object, create it
+        let obj = self.get_or_insert_annot_obj_mut(a_id);
 
-                for range in ranges {
-                    // if the current target is already on one of the ranges,
-                    // we're done
-                    if *tgt >= *range.start() && *tgt <= *range.end() {
-                        // dist = 0.0;
-                        closest_tgt = Some(*tgt);
-                        break;
-                    }
+        // if there's already an anchor target on this object,
+        // constrain it to the visible anchor set
 
-                    let closest = if *tgt < *range.start() {
-                        *range.start()
-                    } else if *tgt > *range.end() {
-                        *range.end()
-                    } else {
-                        unreachable!();
-                    };
+        if let Some(tgt) = obj.anchor_target_pos.as_mut() {
+            let mut dist = std::f32::INFINITY;
+            let mut closest_tgt = None;
 
-                    let new_dist = (closest - *tgt).abs();
-                    if new_dist < dist {
-                        closest_tgt = Some(closest);
-                        dist = new_dist;
-                    }
+            for range in ranges.iter() {
+                // if the current target is already on one of the ranges,
+                // we're done
+                if *tgt >= *range.start() && *tgt <= *range.end() {
+                    // dist = 0.0;
+                    closest_tgt = Some(*tgt);
+                    break;
                 }
 
-                if let Some(new_tgt) = closest_tgt {
-                    *tgt = new_tgt;
+                let closest = if *tgt < *range.start() {
+                    *range.start()
+                } else if *tgt > *range.end() {
+                    *range.end()
+                } else {
+                    unreachable!();
+                };
+
+                let new_dist = (closest - *tgt).abs();
+                if new_dist < dist {
+                    closest_tgt = Some(closest);
+                    dist = new_dist;
                 }
-            } else {
-                let (ranges, lens): (Vec<_>, Vec<_>) =
-                    ranges.iter().map(|r| (r, r.end() - r.start())).unzip();
+            }
 
-                // if the ann. object has no anchor target,
-                // choose a random position from the intersection of view
-                // with the anchor ranges (across all visible sections) &
-                // set the anchor target position to that point
+            if let Some(new_tgt) = closest_tgt {
+                *tgt = new_tgt;
+            }
+        } else {
+            let lens = ranges
+                .iter()
+                .map(|r| r.end() - r.start())
+                .collect::<Vec<_>>();
 
-                let dist = WeightedIndex::new(&lens).unwrap();
+            // if the ann. object has no anchor target,
+            // choose a random position from the intersection of view
+            // with the anchor ranges (across all visible sections) &
+            // set the anchor target position to that point
 
-                let ix = rng.sample(dist);
-                let anchor_target = rng.gen_range(ranges[ix].clone());
+            let ix = WeightedIndex::new(&lens)
+                .map(|dist| rng.sample(dist))
+                .unwrap_or(0);
+            let anchor_target = rng.gen_range(ranges[ix].clone());
 
-                obj.anchor_target_pos = Some(anchor_target);
-            }
+            obj.anchor_target_pos = Some(anchor_target);
+        }
 
-            if obj.anchor_target_pos.is_some() {
-                self.visible_set.insert(a_id);
-            }
+        if obj.anchor_target_pos.is_some() {
+            self.visible_set.insert(a_id);
         }
     }
 
@@ -276,13 +539,8 @@ impl AnnotSlotDynamics {
         &mut self,
         screen_rect: egui::Rect,
         dt: f32,
-    ) -> Vec<(AnnotationId, Vec2)> {
-        use iset::IntervalMap;
-
-        const MAX_OVERLAPS: usize = 5;
-
-        let objs_n = self.annot_shape_objs.len();
-
+        max_rows: usize,
+    ) -> Vec<PlacedLabel> {
         // NB: this might get weird... maybe i want to store the last
         // updated view for each object, and use that to compute the
         // transform -- but that's only if this ends up not working
@@ -292,17 +550,12 @@ impl AnnotSlotDynamics {
             .and_then(|v0| Some((v0, self.cur_view.as_ref()?)))
             .filter(|(v0, v1)| v0 != v1) // no need to transform if there's no change in view
             .map(|(v0, v1)| {
-                super::Viewer1D::sample_index_transform(v0.range(), v1.range())
+                super::transform::sample_index_transform(v0.range(), v1.range())
             });
 
-        let mut placed_labels: IntervalMap<f32, (usize, usize)> =
-            IntervalMap::default();
-
-        let mut object_range: HashMap<usize, std::ops::Range<f32>> =
-            HashMap::default();
-
-        // AnnotationId -> [Object Id]
-        let mut overlapping: BTreeMap<usize, Vec<usize>> = BTreeMap::default();
+        // (object ix, label interval in screen space)
+        let mut label_objs: Vec<usize> = Vec::new();
+        let mut label_ivals: Vec<std::ops::Range<f32>> = Vec::new();
 
         for &annot_id in &self.visible_set {
             let obj_i = self.annot_obj_map[&annot_id];
@@ -368,69 +621,31 @@ impl AnnotSlotDynamics {
                 apply_tf(obj.anchor_pos.as_mut());
             }
 
-            // then lay out the labels horizontally in the interval
-            // map; skip once they start overlapping
-
-            // just use the anchor (not target) as the position for
-            // now
-
-            let ival = if let Some(pos) = obj.anchor_pos {
-                let l = pos - width / 2.0;
-                let r = pos + width / 2.0;
-                l..r
-            } else {
-                continue;
-            };
-
-            let overlaps = placed_labels
-                .values(ival.clone())
-                .map(|(obj_i, _)| *obj_i)
-                .collect::<Vec<_>>();
-            let overlap_count = overlaps.len();
-
-            if overlap_count < MAX_OVERLAPS {
-                object_range.insert(obj_i, ival.clone());
-                placed_labels.insert(ival, (obj_i, overlap_count));
-                overlapping.insert(obj_i, overlaps);
-            }
-        }
-
-        // Object ID -> Option<Row Index>
-        let mut rows: HashMap<usize, usize> = HashMap::default();
-
-        for (range, (obj_i, overlap_count)) in placed_labels.iter(..) {
-            let overlaps =
-                placed_labels.values(range.clone()).map(|(obj_i, _)| *obj_i);
-
-            let overlapping_rows = overlaps
-                .filter_map(|j| rows.get(&j))
-                .copied()
-                .collect::<HashSet<_>>();
+            // just use the anchor (not target) as the position of the
+            // label; the leader line goes to the target
 
-            let row_ix = (0..MAX_OVERLAPS - 1)
-                .find(|row| !overlapping_rows.contains(&row));
-
-            if let Some(i) = row_ix {
-                rows.insert(*obj_i, i);
+            if let Some(pos) = obj.anchor_pos {
+                label_objs.push(obj_i);
+                label_ivals.push((pos - width / 2.0)..(pos + width / 2.0));
             }
         }
 
-        let mut positions = Vec::with_capacity(placed_labels.len());
+        // stack overlapping labels into rows; anything that doesn't
+        // fit in `max_rows` rows is left out
+        let rows = stack_label_rows(&label_ivals, max_rows, LABEL_GAP);
 
-        for (_range, (obj_i, overlap_count)) in placed_labels.into_iter(..) {
-            let obj = &mut self.annot_shape_objs[obj_i];
+        let mut placed = Vec::with_capacity(label_objs.len());
 
-            let row_ix = if let Some(i) = rows.get(&obj_i) {
-                *i
-            } else {
+        for (&obj_i, row) in label_objs.iter().zip(rows) {
+            let Some(row) = row else {
                 continue;
             };
 
-            let annot_id = obj.annot_id;
+            let obj = &self.annot_shape_objs[obj_i];
 
-            let x = if let Some(anchor) = obj.anchor_pos {
-                anchor
-            } else {
+            let (Some(x), Some(anchor_x)) =
+                (obj.anchor_pos, obj.anchor_target_pos)
+            else {
                 continue;
             };
 
@@ -440,16 +655,118 @@ impl AnnotSlotDynamics {
                 6.0
             };
 
-            let y0 = screen_rect.bottom() - 8.0;
-            let y = y0 - yd * row_ix as f32;
+            let y0 = screen_rect.bottom() - INTERVAL_STRIP_HEIGHT - 8.0;
+            let y = y0 - yd * row as f32;
 
-            positions.push((annot_id, Vec2::new(x, y)));
+            placed.push(PlacedLabel {
+                annot_id: obj.annot_id,
+                pos: Vec2::new(x, y),
+                anchor_x,
+                row,
+            });
         }
 
-        positions
+        placed
     }
 }
 
+/// True if `v1` is `v0` translated by less than half its length, in
+/// which case the visible set can be updated incrementally.
+fn is_small_pan(v0: &View1D, v1: &View1D) -> bool {
+    let shift = v0.offset().abs_diff(v1.offset());
+    v0.len() == v1.len() && shift < v0.len() / 2
+}
+
+/// The anchor tree entries intersecting the given pangenome range
+fn locate_in_bp_range(
+    annots: &RTree<AnnotsTreeObj>,
+    range: std::ops::Range<Bp>,
+) -> impl Iterator<Item = (AnnotationId, std::ops::Range<Bp>)> + '_ {
+    use rstar::AABB;
+
+    let aabb =
+        AABB::from_corners((range.start.0 as i64, 0), (range.end.0 as i64, 0));
+
+    annots.locate_in_envelope_intersecting(&aabb).map(|line| {
+        let left = Bp(line.geom().from.0 as u64);
+        let right = Bp(line.geom().to.0 as u64);
+        (line.data, left..right)
+    })
+}
+
+/// Minimum horizontal space between two labels in the same row, in pixels
+const LABEL_GAP: f32 = 4.0;
+
+/// Labels are truncated to the screen width of their annotation, but
+/// not to less than this, in pixels
+const MIN_LABEL_WIDTH: f32 = 40.0;
+
+/// Characters per second that a hovered, truncated label scrolls by
+/// when marquee labels are enabled
+const MARQUEE_SPEED: f64 = 4.0;
+
+/// Height of the strip at the bottom of a slot where the annotation
+/// intervals are drawn, below the labels
+const INTERVAL_STRIP_HEIGHT: f32 = 8.0;
+const BLOCK_HEIGHT: f32 = 6.0;
+
+const DENSITY_COLOR: egui::Color32 = egui::Color32::from_gray(150);
+
+const DEFAULT_INTERVAL_COLOR: egui::Color32 = egui::Color32::LIGHT_GRAY;
+
+/// Greedy interval partitioning: the intervals are visited from left
+/// to right, and each is put in the first row where it doesn't
+/// overlap the previous label (with at least `gap` pixels between
+/// them). Returns the row index for each interval, or `None` if
+/// it didn't fit in any of the `max_rows` rows.
+fn stack_label_rows(
+    intervals: &[std::ops::Range<f32>],
+    max_rows: usize,
+    gap: f32,
+) -> Vec<Option<usize>> {
+    let mut order = (0..intervals.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (&intervals[a], &intervals[b]);
+        a.start.total_cmp(&b.start).then(a.end.total_cmp(&b.end))
+    });
+
+    // the right hand end of the last label placed in each row
+    let mut row_ends: Vec<f32> = Vec::new();
+    let mut rows = vec![None; intervals.len()];
+
+    for i in order {
+        let ival = &intervals[i];
+
+        let row = row_ends
+            .iter()
+            .position(|&end| end + gap <= ival.start)
+            .or_else(|| {
+                (row_ends.len() < max_rows).then(|| {
+                    row_ends.push(ival.start);
+                    row_ends.len() - 1
+                })
+            });
+
+        if let Some(row) = row {
+            row_ends[row] = ival.end;
+            rows[i] = Some(row);
+        }
+    }
+
+    rows
+}
+
+/// A label position as produced by the layout task
+#[derive(Debug, Clone, Copy)]
+struct PlacedLabel {
+    annot_id: AnnotationId,
+    /// Center of the label
+    pos: Vec2,
+    /// Point on the annotation's anchor interval the label refers to
+    anchor_x: f32,
+    row: usize,
+}
+
 impl AnnotObj {
     fn empty(annot_id: AnnotationId) -> Self {
         Self {
@@ -487,6 +804,7 @@ impl AnnotSlot {
     ) -> Self {
         let mut annot_objs = Vec::new();
         let mut shape_fns = Vec::new();
+        let mut interval_colors = Vec::new();
 
         let mut annotation_ranges: HashMap<
             AnnotationId,
@@ -499,6 +817,7 @@ impl AnnotSlot {
                 Line::new((range.start.0 as i64, 0), (range.end.0 as i64, 0));
             annot_objs.push(GeomWithData::new(geom, a_id));
             shape_fns.push(shape);
+            interval_colors.push(DEFAULT_INTERVAL_COLOR);
 
             annotation_ranges.entry(a_id).or_default().push(range);
         }
@@ -507,6 +826,8 @@ impl AnnotSlot {
             ranges.sort_by_key(|r| (r.start, r.end));
         }
 
+        let interval_blocks = annotation_ranges.clone();
+
         let annots = RTree::<AnnotsTreeObj>::bulk_load(annot_objs);
 
         Self {
@@ -514,6 +835,8 @@ impl AnnotSlot {
             annots: Arc::new(annots),
             annotation_ranges,
             shape_fns,
+            interval_blocks,
+            interval_colors,
             dynamics: Default::default(),
             task: None,
             positions: Vec::new(),
@@ -521,30 +844,55 @@ impl AnnotSlot {
         }
     }
 
-    /// Initializes an annotation slot given items in path space.
-    /// The path ranges to pangenome space, splitting them if
-    /// necessary.
-    pub fn new_from_path_space(
+    /// Initializes an annotation slot given annotations in path
+    /// space. The path ranges are mapped to pangenome space,
+    /// splitting them if necessary.
+    pub fn new_from_path_space<'a>(
         graph: &PathIndex,
         set_id: AnnotationSetId,
         annotations: impl IntoIterator<
-            Item = (PathId, std::ops::Range<Bp>, ShapeFn),
+            Item = (&'a Annotation, Option<egui::Color32>, ShapeFn),
         >,
     ) -> Self {
         let mut annot_objs = Vec::new();
         let mut shape_fns = Vec::new();
+        let mut interval_colors = Vec::new();
 
         let mut annotation_ranges: HashMap<
             AnnotationId,
             Vec<std::ops::Range<Bp>>,
         > = HashMap::default();
 
-        for (a_id, (path, path_range, shape)) in
-            annotations.into_iter().enumerate()
+        let mut interval_blocks: HashMap<_, Vec<_>> = HashMap::default();
+
+        for (a_id, (annot, color, shape)) in annotations.into_iter().enumerate()
         {
             let a_id = AnnotationId(a_id);
             shape_fns.push(shape);
-            let range_end = path_range.end;
+            interval_colors.push(color.unwrap_or(DEFAULT_INTERVAL_COLOR));
+
+            let path = annot.path;
+            let path_range = annot.range.clone();
+
+            let blocks = if annot.blocks.is_empty() {
+                std::slice::from_ref(&annot.range)
+            } else {
+                annot.blocks.as_slice()
+            };
+
+            let block_ranges = blocks
+                .iter()
+                .filter_map(|block| {
+                    graph.path_step_range_iter(path, block.clone())
+                })
+                .flatten()
+                .map(|(_, step)| {
+                    let (offset, len) = graph.node_offset_length(step.node());
+                    offset..Bp(offset.0 + len.0)
+                })
+                .collect::<Vec<_>>();
+
+            interval_blocks.insert(a_id, merge_ranges(block_ranges));
 
             if let Some(steps) = graph.path_step_range_iter(path, path_range) {
                 for (_path_start, step) in steps {
@@ -576,6 +924,8 @@ impl AnnotSlot {
             annots: Arc::new(annots),
             annotation_ranges,
             shape_fns,
+            interval_blocks,
+            interval_colors,
             dynamics: Default::default(),
             task: None,
             positions: Vec::new(),
@@ -589,6 +939,7 @@ impl AnnotSlot {
         screen_rect: egui::Rect,
         view: &View1D,
         dt: f32,
+        max_rows: usize,
     ) {
         if let Some(handle) = self.task.take() {
             // if done, update the stored positions
@@ -600,7 +951,7 @@ impl AnnotSlot {
                 self.task = Some(handle);
             }
         } else {
-            self.update_spawn_task(rt, screen_rect, view, dt);
+            self.update_spawn_task(rt, screen_rect, view, dt, max_rows);
         }
     }
 
@@ -610,6 +961,7 @@ impl AnnotSlot {
         screen_rect: egui::Rect,
         view: &View1D,
         dt: f32,
+        max_rows: usize,
     ) {
         if self.task.is_some() {
             return;
@@ -633,19 +985,26 @@ impl AnnotSlot {
         let handle = rt.spawn(async move {
             let mut dynamics = dynamics.lock().await;
             dynamics.prepare(&annots_tree, screen_rect, &view);
-            dynamics.update_simple(screen_rect, dt)
+            dynamics.update_simple(screen_rect, dt, max_rows)
             // dynamics.update(screen_rect, dt)
         });
 
         self.task = Some(handle);
     }
 
-    /// returns the hovered annotation, if any
+    /// Returns the hovered annotation, if any. Labels are truncated
+    /// to the width of their annotation on screen; a hovered label is
+    /// shown in full on top of its neighbors, or, with `marquee`,
+    /// scrolls through its text. With more than `density_threshold`
+    /// annotations in view, their density is shown instead.
     pub(super) fn draw(
         &mut self,
         painter: &egui::Painter,
         view: &View1D,
         cursor_pos: Option<egui::Pos2>,
+        show_labels: bool,
+        marquee: bool,
+        density_threshold: usize,
     ) -> Option<AnnotationId> {
         self.shape_sizes.clear();
 
@@ -654,28 +1013,297 @@ impl AnnotSlot {
             .map(|pos| painter.clip_rect().contains(pos))
             .unwrap_or(false);
 
-        for &(a_id, pos) in self.positions.iter() {
-            let pos = mint::Point2::<f32>::from(pos);
-            let shape = self.shape_fns[a_id.0](painter, pos.into());
-            let size =
-                mint::Vector2::<f32>::from(shape.visual_bounding_rect().size());
+        let clip_rect = painter.clip_rect();
+
+        let leader_stroke =
+            egui::Stroke::new(1.0, egui::Color32::from_white_alpha(128));
+
+        let spans = self.screen_spans(view, clip_rect);
+
+        painter.extend(self.interval_shapes(view, &spans, clip_rect));
+
+        if !show_labels {
+            return None;
+        }
+
+        if spans.len() > density_threshold {
+            self.draw_density(painter, &spans, cursor_pos);
+            return None;
+        }
+
+        let mut label_shapes = Vec::with_capacity(self.positions.len());
+        let mut full_label = None;
+
+        for label in self.positions.iter() {
+            let a_id = label.annot_id;
+            let pos = mint::Point2::<f32>::from(label.pos);
+
+            let fit = LabelFit {
+                max_width: spans
+                    .get(&a_id)
+                    .map(|(l, r)| (r - l).max(MIN_LABEL_WIDTH)),
+                scroll: None,
+            };
+
+            let shape_fn = &self.shape_fns[a_id.0];
+            let mut shape = shape_fn(painter, pos.into(), fit);
+            let rect = shape.visual_bounding_rect();
+            let size = mint::Vector2::<f32>::from(rect.size());
             self.shape_sizes.push((a_id, size.into()));
 
-            if cursor_in_slot && interacted.is_none() {
-                if let Some(pos) = cursor_pos {
-                    if shape.visual_bounding_rect().contains(pos) {
-                        interacted = Some(a_id);
-                    }
+            let hovered = cursor_in_slot
+                && interacted.is_none()
+                && cursor_pos.is_some_and(|pos| rect.contains(pos));
+
+            if hovered {
+                interacted = Some(a_id);
+
+                let full = shape_fn(painter, pos.into(), LabelFit::default());
+                let truncated =
+                    full.visual_bounding_rect().width() > rect.width() + 0.5;
+
+                if truncated && marquee {
+                    let time = painter.ctx().input(|i| i.time);
+                    let fit = LabelFit {
+                        scroll: Some((time * MARQUEE_SPEED) as usize),
+                        ..fit
+                    };
+                    shape = shape_fn(painter, pos.into(), fit);
+                    painter.ctx().request_repaint();
+                } else if truncated {
+                    full_label = Some(full);
                 }
             }
 
-            if painter.clip_rect().intersects(shape.visual_bounding_rect()) {
-                painter.add(shape);
+            if !clip_rect.intersects(rect) {
+                continue;
+            }
+
+            // labels that have been moved up a row, or away from
+            // their anchor, get a line down to the anchor interval
+            if label.row > 0 || (label.pos.x - label.anchor_x).abs() > 1.0 {
+                let from = egui::pos2(label.pos.x, rect.bottom());
+                let to = egui::pos2(
+                    label.anchor_x,
+                    clip_rect.bottom() - INTERVAL_STRIP_HEIGHT,
+                );
+                painter.line_segment([from, to], leader_stroke);
             }
+
+            label_shapes.push(shape);
+        }
+
+        // labels are drawn on top of the leader lines, and the full
+        // text of a truncated label on top of everything
+        painter.extend(label_shapes);
+
+        if let Some(full) = full_label {
+            let bg = full.visual_bounding_rect().expand(2.0);
+            let fill = painter.ctx().style().visuals.window_fill();
+            painter.rect_filled(bg, 2.0, fill);
+            painter.add(full);
         }
 
         interacted
     }
+
+    /// Draws the number of annotations over each pixel column as a
+    /// bar chart, for when there are too many in view to label.
+    fn draw_density(
+        &self,
+        painter: &egui::Painter,
+        spans: &BTreeMap<AnnotationId, (f32, f32)>,
+        cursor_pos: Option<egui::Pos2>,
+    ) {
+        let rect = painter.clip_rect();
+        let bins = density_bins(
+            spans.values().copied(),
+            rect.left(),
+            rect.width() as usize,
+        );
+        let max = bins.iter().copied().max().unwrap_or_default().max(1);
+
+        let text_color = painter.ctx().style().visuals.text_color();
+        let font = egui::FontId::proportional(12.0);
+
+        // leave room for the count at the top
+        let bottom = rect.bottom() - INTERVAL_STRIP_HEIGHT - 2.0;
+        let max_height = (bottom - rect.top() - 16.0).max(1.0);
+
+        // runs of columns with the same count are drawn as one bar
+        let mut start = 0;
+        for ix in 1..=bins.len() {
+            if ix < bins.len() && bins[ix] == bins[start] {
+                continue;
+            }
+
+            let count = bins[start];
+            if count > 0 {
+                let height = max_height * count as f32 / max as f32;
+                let bar = egui::Rect::from_min_max(
+                    egui::pos2(rect.left() + start as f32, bottom - height),
+                    egui::pos2(rect.left() + ix as f32, bottom),
+                );
+                painter.rect_filled(bar, 0.0, DENSITY_COLOR);
+            }
+
+            start = ix;
+        }
+
+        painter.text(
+            rect.left_top() + egui::vec2(4.0, 2.0),
+            egui::Align2::LEFT_TOP,
+            format!(
+                "{} annotations, up to {max} per pixel; zoom in for labels",
+                spans.len()
+            ),
+            font.clone(),
+            text_color,
+        );
+
+        let hovered_bin = cursor_pos
+            .filter(|pos| rect.contains(*pos))
+            .and_then(|pos| bins.get((pos.x - rect.left()) as usize));
+
+        if let (Some(pos), Some(count)) = (cursor_pos, hovered_bin) {
+            painter.text(
+                pos + egui::vec2(8.0, -4.0),
+                egui::Align2::LEFT_BOTTOM,
+                count.to_string(),
+                font,
+                text_color,
+            );
+        }
+    }
+
+    /// The screen extent of the visible part of each annotation in
+    /// view.
+    fn screen_spans(
+        &self,
+        view: &View1D,
+        rect: egui::Rect,
+    ) -> BTreeMap<AnnotationId, (f32, f32)> {
+        let range = view.range();
+        let range = Bp(range.start)..Bp(range.end);
+        let screen_interval = rect.left()..=rect.right();
+
+        let mut spans: BTreeMap<AnnotationId, (f32, f32)> = BTreeMap::new();
+
+        for (a_id, anchor) in locate_in_bp_range(&self.annots, range) {
+            let Some(x) =
+                view.map_bp_interval_to_screen_x(&anchor, &screen_interval)
+            else {
+                continue;
+            };
+            let (l, r) = x.into_inner();
+            let span = spans.entry(a_id).or_insert((l, r));
+            *span = (span.0.min(l), span.1.max(r));
+        }
+
+        spans
+    }
+
+    /// The intervals of the annotations in view, along the bottom of
+    /// the slot: a thin line across each annotation, with its blocks
+    /// (e.g. exons) drawn thick on top.
+    fn interval_shapes(
+        &self,
+        view: &View1D,
+        spans: &BTreeMap<AnnotationId, (f32, f32)>,
+        rect: egui::Rect,
+    ) -> Vec<egui::Shape> {
+        let screen_interval = rect.left()..=rect.right();
+
+        let y = rect.bottom() - INTERVAL_STRIP_HEIGHT * 0.5;
+
+        let mut shapes = Vec::new();
+
+        for (&a_id, &(left, right)) in spans {
+            let color = self.interval_colors[a_id.0];
+
+            shapes.push(egui::Shape::line_segment(
+                [egui::pos2(left, y), egui::pos2(right, y)],
+                egui::Stroke::new(1.0, color),
+            ));
+
+            for block in self.interval_blocks.get(&a_id).into_iter().flatten() {
+                let Some(x) =
+                    view.map_bp_interval_to_screen_x(block, &screen_interval)
+                else {
+                    continue;
+                };
+                let (l, r) = x.into_inner();
+
+                let block_rect = egui::Rect::from_min_max(
+                    egui::pos2(l, y - BLOCK_HEIGHT * 0.5),
+                    egui::pos2(r.max(l + 1.0), y + BLOCK_HEIGHT * 0.5),
+                );
+                shapes.push(egui::Shape::rect_filled(block_rect, 0.0, color));
+            }
+        }
+
+        shapes
+    }
+}
+
+/// The number of screen intervals covering each of `width` pixel
+/// columns starting at `left`; every interval that overlaps the
+/// columns covers at least the column it starts in.
+fn density_bins(
+    intervals: impl Iterator<Item = (f32, f32)>,
+    left: f32,
+    width: usize,
+) -> Vec<u32> {
+    if width == 0 {
+        return Vec::new();
+    }
+
+    // +1 at the first column of each interval, -1 past the last
+    let mut deltas = vec![0i64; width + 1];
+
+    for (l, r) in intervals {
+        if r < left || l >= left + width as f32 {
+            continue;
+        }
+
+        let first = (l - left).floor().max(0.0) as usize;
+        let end = (r - left).ceil().max(0.0) as usize;
+
+        let first = first.min(width - 1);
+        let end = end.clamp(first + 1, width);
+
+        deltas[first] += 1;
+        deltas[end] -= 1;
+    }
+
+    let mut count = 0;
+    deltas[..width]
+        .iter()
+        .map(|delta| {
+            count += delta;
+            count as u32
+        })
+        .collect()
+}
+
+/// Sorts the ranges and merges the ones that overlap or touch.
+pub(super) fn merge_ranges(
+    mut ranges: Vec<std::ops::Range<Bp>>,
+) -> Vec<std::ops::Range<Bp>> {
+    ranges.sort_by_key(|r| (r.start, r.end));
+
+    let mut merged: Vec<std::ops::Range<Bp>> = Vec::with_capacity(ranges.len());
+
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => {
+                last.end = last.end.max(range.end);
+            }
+            _ => merged.push(range),
+        }
+    }
+
+    merged
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -704,3 +1332,136 @@ impl AnnotObjPos {
         self.accel += acc;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn density_bins_count_overlaps() {
+        let intervals = [(10.0, 13.5), (12.2, 12.4), (11.0, 30.0), (-5.0, 1.0)];
+        let bins = density_bins(intervals.into_iter(), 10.0, 5);
+        // the last interval is left of the columns, the third runs
+        // past them, and the second covers the column it's in
+        assert_eq!(bins, vec![1, 2, 3, 2, 1]);
+
+        assert!(density_bins(intervals.into_iter(), 0.0, 0).is_empty());
+    }
+
+    #[test]
+    fn labels_fit_max_width() {
+        let fonts = egui::text::Fonts::new(1.0, 2048, Default::default());
+        let font = egui::FontId::proportional(16.0);
+        let width = |text: &str| {
+            let color = egui::Color32::WHITE;
+            fonts
+                .layout_no_wrap(text.into(), font.clone(), color)
+                .size()
+                .x
+        };
+
+        let label = "ENSG00000139618 BRCA2";
+        let fit = |max_width, scroll| {
+            let max_width = Some(max_width);
+            fit_label(&fonts, label, &font, LabelFit { max_width, scroll })
+        };
+
+        assert_eq!(fit(1000.0, None), label);
+
+        let truncated = fit(60.0, None);
+        assert!(truncated.ends_with('…'));
+        assert!(width(&truncated) <= 60.0);
+        assert!(label.starts_with(truncated.trim_end_matches('…')));
+
+        // scrolling shows the text from the offset, without ellipsis
+        let scrolled = fit(60.0, Some(4));
+        assert!(width(&scrolled) <= 60.0);
+        assert!(label[4..].starts_with(&scrolled));
+
+        // and wraps around after a gap
+        let wrapped = fit(60.0, Some(label.len() + 3));
+        assert!(label.starts_with(&wrapped));
+    }
+
+    #[test]
+    fn label_rows_stack_overlaps() {
+        let ivals = [0.0..10.0, 5.0..15.0, 12.0..20.0, 30.0..40.0, 8.0..9.0];
+
+        let rows = stack_label_rows(&ivals, 2, 1.0);
+        assert_eq!(rows, vec![Some(0), Some(1), Some(0), Some(0), None]);
+
+        // labels in the same row never overlap
+        let rows = stack_label_rows(&ivals, 8, 1.0);
+        for (i, a) in ivals.iter().enumerate() {
+            for (j, b) in ivals.iter().enumerate().skip(i + 1) {
+                if rows[i] == rows[j] {
+                    assert!(a.end + 1.0 <= b.start || b.end + 1.0 <= a.start);
+                }
+            }
+        }
+        assert!(rows.iter().all(|r| r.is_some()));
+    }
+
+    #[test]
+    fn merged_ranges_are_disjoint() {
+        let ranges =
+            vec![Bp(10)..Bp(20), Bp(0)..Bp(5), Bp(20)..Bp(25), Bp(12)..Bp(14)];
+        assert_eq!(merge_ranges(ranges), vec![Bp(0)..Bp(5), Bp(10)..Bp(25)]);
+    }
+
+    #[test]
+    fn panning_matches_full_rebuild() {
+        use rand::prelude::*;
+
+        let mut rng = StdRng::seed_from_u64(506);
+
+        let max = 100_000u64;
+
+        let objs = (0..2_000)
+            .map(|i| {
+                let start = rng.gen_range(0..max - 500) as i64;
+                let end = start + rng.gen_range(1..500);
+                // some annotations have more than one anchor range
+                let a_id = AnnotationId(i % 1_500);
+                GeomWithData::new(Line::new((start, 0), (end, 0)), a_id)
+            })
+            .collect::<Vec<_>>();
+        let annots = RTree::bulk_load(objs);
+
+        let rect = egui::Rect::from_min_size(
+            egui::pos2(0.0, 0.0),
+            egui::vec2(800.0, 40.0),
+        );
+
+        let mut view = View1D::new(max);
+        view.set(40_000, 50_000);
+
+        let mut incremental = AnnotSlotDynamics::default();
+        incremental.prepare(&annots, rect, &view);
+
+        for _ in 0..50 {
+            view.translate_bp(rng.gen_range(-3_000..3_000));
+            incremental.prepare(&annots, rect, &view);
+
+            let mut full = AnnotSlotDynamics::default();
+            full.prepare(&annots, rect, &view);
+
+            assert_eq!(incremental.visible_set, full.visible_set);
+
+            for (a_id, ranges) in full.visible_ranges.iter() {
+                let mut a = ranges.clone();
+                let mut b = incremental.visible_ranges[a_id].clone();
+                a.sort_by_key(|r| (r.start, r.end));
+                b.sort_by_key(|r| (r.start, r.end));
+                assert_eq!(a, b);
+            }
+
+            // every visible annotation has a target in view
+            for a_id in incremental.visible_set.iter() {
+                let obj = incremental.get_annot_obj(*a_id).unwrap();
+                let x = obj.anchor_target_pos.unwrap();
+                assert!(x >= rect.left() && x <= rect.right());
+            }
+        }
+    }
+}
@@ -0,0 +1,688 @@
+//! Named 1D view ranges, saved next to the GFA so that they're kept
+//! between sessions, and exported and imported as TSV or JSON.
+
+use std::collections::{HashMap, HashSet};
+use std::io::prelude::*;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, RwLock};
+use tokio::task::JoinHandle;
+use waragraph_core::graph::coords::CoordSystem;
+use waragraph_core::graph::{Bp, PathIndex};
+
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::SharedState;
+use crate::color::paths::PathColors;
+
+use super::control::{path_range_to_pangenome, Msg, ViewCmd};
+use super::util::{lerp_color, path_presence_bins_in_range};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "BookmarkRecord", into = "BookmarkRecord")]
+pub struct Bookmark {
+    pub name: String,
+    /// Name of the path `range` is on, or `None` for a pangenome range
+    pub path: Option<String>,
+    pub range: std::ops::Range<Bp>,
+}
+
+/// A bookmark as stored in JSON
+#[derive(Serialize, Deserialize)]
+struct BookmarkRecord {
+    name: String,
+    path: Option<String>,
+    start: u64,
+    end: u64,
+}
+
+impl From<BookmarkRecord> for Bookmark {
+    fn from(b: BookmarkRecord) -> Self {
+        Self {
+            name: b.name,
+            path: b.path,
+            range: Bp(b.start)..Bp(b.end),
+        }
+    }
+}
+
+impl From<Bookmark> for BookmarkRecord {
+    fn from(b: Bookmark) -> Self {
+        Self {
+            name: b.name,
+            path: b.path,
+            start: b.range.start.0,
+            end: b.range.end.0,
+        }
+    }
+}
+
+impl Bookmark {
+    /// The bookmark's range in pangenome coordinates; for a path range,
+    /// from the first to the last node the path steps on in it.
+    pub fn pangenome_range(
+        &self,
+        graph: &PathIndex,
+    ) -> std::result::Result<Range<Bp>, String> {
+        let Some(name) = self.path.as_ref() else {
+            return Ok(self.range.clone());
+        };
+
+        let Some(path) = graph.path_names.get_by_right(name) else {
+            return Err(format!("Unknown path `{name}`"));
+        };
+
+        path_range_to_pangenome(graph, *path, self.range.clone())
+            .ok_or_else(|| format!("Range is outside of path `{name}`"))
+    }
+}
+
+#[derive(Default)]
+pub struct BookmarkStore {
+    bookmarks: Vec<Bookmark>,
+    // saved to after each change, if set
+    file: Option<PathBuf>,
+}
+
+impl BookmarkStore {
+    /// The bookmarks of a graph are kept in `<gfa>.bookmarks.tsv`.
+    pub fn file_for_graph(gfa_path: &Path) -> PathBuf {
+        let mut file = gfa_path.as_os_str().to_owned();
+        file.push(".bookmarks.tsv");
+        PathBuf::from(file)
+    }
+
+    /// Loads the bookmarks in `file`, if it exists; the store is
+    /// saved to the file on every change.
+    pub fn open(file: PathBuf) -> Result<Self> {
+        let bookmarks = if file.exists() {
+            read_tsv(&std::fs::read_to_string(&file)?)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            bookmarks,
+            file: Some(file),
+        })
+    }
+
+    pub fn bookmarks(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    pub fn add(&mut self, bookmark: Bookmark) {
+        self.bookmarks.push(bookmark);
+        self.save();
+    }
+
+    pub fn remove(&mut self, ix: usize) {
+        if ix < self.bookmarks.len() {
+            self.bookmarks.remove(ix);
+            self.save();
+        }
+    }
+
+    /// Appends the bookmarks in the file, which is read as JSON if
+    /// the name ends with `.json`, and as TSV otherwise. Returns the
+    /// number of bookmarks added.
+    pub fn import(&mut self, path: &Path) -> Result<usize> {
+        let text = std::fs::read_to_string(path)?;
+
+        let bookmarks = if is_json_path(path) {
+            parse_json(&text)?
+        } else {
+            read_tsv(&text)?
+        };
+
+        let count = bookmarks.len();
+        self.bookmarks.extend(bookmarks);
+        self.save();
+
+        Ok(count)
+    }
+
+    /// Writes the bookmarks as JSON if the file name ends with
+    /// `.json`, and as TSV otherwise, with the TSV positions in the
+    /// `coords` convention. JSON is always 0-based.
+    pub fn export(&self, path: &Path, coords: CoordSystem) -> Result<()> {
+        let file = std::fs::File::create(path)?;
+        let mut out = std::io::BufWriter::new(file);
+
+        if is_json_path(path) {
+            write_json(&self.bookmarks, &mut out)?;
+        } else {
+            write_tsv(&self.bookmarks, coords, &mut out)?;
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+
+    fn save(&self) {
+        let Some(file) = self.file.as_ref() else {
+            return;
+        };
+
+        if let Err(e) = self.export(file, CoordSystem::ZeroBased) {
+            log::error!("Error saving bookmarks to {file:?}: {e:?}");
+        }
+    }
+}
+
+fn is_json_path(path: &Path) -> bool {
+    path.extension().map(|ext| ext == "json").unwrap_or(false)
+}
+
+// written in the path column for pangenome ranges
+const PANGENOME: &str = "*";
+
+/// Parses bookmarks from TSV with the columns name, path (`*` for
+/// the pangenome), start and end; lines starting with `#` are
+/// skipped. The positions are 0-based, unless a comment written by
+/// `write_tsv` says otherwise.
+pub fn read_tsv(text: &str) -> Result<Vec<Bookmark>> {
+    let mut bookmarks = Vec::new();
+    let mut coords = CoordSystem::ZeroBased;
+
+    for (line_ix, line) in text.lines().enumerate() {
+        if let Some(c) = CoordSystem::from_header_comment(line) {
+            coords = c;
+            continue;
+        }
+
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let err = |msg: &str| anyhow!("Line {}: {msg}", line_ix + 1);
+
+        let fields = line.split('\t').collect::<Vec<_>>();
+
+        let [name, path, start, end] = fields[..] else {
+            return Err(err("expected 4 columns"));
+        };
+
+        let parse = |s: &str| {
+            s.trim().parse::<u64>().map_err(|_| err("invalid position"))
+        };
+
+        bookmarks.push(Bookmark {
+            name: name.to_string(),
+            path: (path != PANGENOME).then(|| path.to_string()),
+            range: coords
+                .from_display_range(parse(start)?, parse(end)?)
+                .ok_or_else(|| err("invalid range"))?,
+        });
+    }
+
+    Ok(bookmarks)
+}
+
+pub fn write_tsv(
+    bookmarks: &[Bookmark],
+    coords: CoordSystem,
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    // tabs and newlines in names would break the columns
+    let clean = |s: &str| s.replace(['\t', '\n', '\r'], " ");
+
+    writeln!(out, "{}", coords.header_comment())?;
+    writeln!(out, "#name\tpath\tstart\tend")?;
+
+    for bookmark in bookmarks {
+        let path = bookmark.path.as_deref().unwrap_or(PANGENOME);
+        let (start, end) = coords.display_range(&bookmark.range);
+        writeln!(
+            out,
+            "{}\t{}\t{start}\t{end}",
+            clean(&bookmark.name),
+            clean(path),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Parses bookmarks from a JSON array of objects with the fields
+/// `name`, `path` (`null` for the pangenome), `start` and `end`.
+pub fn parse_json(text: &str) -> Result<Vec<Bookmark>> {
+    Ok(serde_json::from_str(text)?)
+}
+
+pub fn write_json(
+    bookmarks: &[Bookmark],
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    serde_json::to_writer_pretty(&mut out, bookmarks)?;
+    writeln!(out)
+}
+
+/// Size of the thumbnail images, in bins and path rows
+const THUMBNAIL_BINS: usize = 96;
+const THUMBNAIL_MAX_ROWS: usize = 64;
+const THUMBNAIL_SIZE: egui::Vec2 = egui::vec2(96.0, 32.0);
+/// Thumbnails computed at the same time
+const MAX_THUMBNAIL_TASKS: usize = 4;
+
+/// The input a thumbnail was rendered for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ThumbnailKey {
+    range: Range<u64>,
+    path_colors_generation: u64,
+}
+
+/// Renders the presence of the paths shown in the 1D view over the
+/// pangenome range, a row per path in its color, as in the presence
+/// matrix. Only the first `THUMBNAIL_MAX_ROWS` paths are drawn.
+fn render_thumbnail(
+    graph: &PathIndex,
+    path_colors: &PathColors,
+    range: Range<u64>,
+) -> egui::ColorImage {
+    let hidden = path_colors.hidden_1d_ids(graph);
+
+    let mut rows = graph
+        .path_names
+        .left_values()
+        .copied()
+        .filter(|path| !hidden.contains(path))
+        .collect::<Vec<_>>();
+    rows.sort();
+    rows.truncate(THUMBNAIL_MAX_ROWS);
+
+    let absent = egui::Color32::from_gray(24);
+    let mut pixels = Vec::with_capacity(rows.len() * THUMBNAIL_BINS);
+
+    for &path in rows.iter() {
+        let name = graph.path_names.get_by_left(&path).unwrap();
+        let color = path_colors.color(path, name);
+
+        let bins = path_presence_bins_in_range(
+            graph,
+            path,
+            range.clone(),
+            THUMBNAIL_BINS,
+        );
+        pixels.extend(bins.into_iter().map(|v| lerp_color(absent, color, v)));
+    }
+
+    if rows.is_empty() {
+        return egui::ColorImage::new([THUMBNAIL_BINS, 1], absent);
+    }
+
+    egui::ColorImage {
+        size: [THUMBNAIL_BINS, rows.len()],
+        pixels,
+    }
+}
+
+/// Settings window panel for saving the current 1D view as a
+/// bookmark, and going back to saved ones.
+pub struct BookmarksWidget {
+    shared: SharedState,
+    store: Arc<RwLock<BookmarkStore>>,
+    msg_tx: crossbeam::channel::Sender<Msg>,
+
+    name_text: String,
+
+    show_thumbnails: bool,
+    thumbnails: HashMap<ThumbnailKey, egui::TextureHandle>,
+    thumbnail_tasks: HashMap<ThumbnailKey, JoinHandle<egui::ColorImage>>,
+
+    import_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    export_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    error: Option<String>,
+}
+
+impl BookmarksWidget {
+    pub fn new(
+        shared: &SharedState,
+        store: Arc<RwLock<BookmarkStore>>,
+        msg_tx: crossbeam::channel::Sender<Msg>,
+    ) -> Self {
+        Self {
+            shared: shared.clone(),
+            store,
+            msg_tx,
+            name_text: String::new(),
+            show_thumbnails: true,
+            thumbnails: HashMap::new(),
+            thumbnail_tasks: HashMap::new(),
+            import_path_recv: None,
+            export_path_recv: None,
+            error: None,
+        }
+    }
+
+    fn goto(&mut self, bookmark: &Bookmark) {
+        match bookmark.pangenome_range(&self.shared.graph) {
+            Ok(range) => {
+                self.error = None;
+                let _ =
+                    self.msg_tx.send(Msg::View(ViewCmd::SetRange { range }));
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Picks up finished thumbnails, starts rendering the missing
+    /// ones, and drops those no bookmark uses anymore. Returns the
+    /// thumbnail key of each bookmark, `None` if its range is invalid.
+    fn update_thumbnails(
+        &mut self,
+        tokio_handle: &tokio::runtime::Handle,
+        ctx: &egui::Context,
+        bookmarks: &[Bookmark],
+    ) -> Vec<Option<ThumbnailKey>> {
+        let finished = self
+            .thumbnail_tasks
+            .iter()
+            .filter(|(_, task)| task.is_finished())
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+
+        for key in finished {
+            let task = self.thumbnail_tasks.remove(&key).unwrap();
+            if let Ok(image) = tokio_handle.block_on(task) {
+                let texture = ctx.load_texture(
+                    "bookmark_thumbnail",
+                    image,
+                    egui::TextureOptions::LINEAR,
+                );
+                self.thumbnails.insert(key, texture);
+            }
+        }
+
+        let path_colors_generation =
+            self.shared.path_colors.blocking_read().generation();
+
+        let keys = bookmarks
+            .iter()
+            .map(|bookmark| {
+                let range =
+                    bookmark.pangenome_range(&self.shared.graph).ok()?;
+                Some(ThumbnailKey {
+                    range: range.start.0..range.end.0,
+                    path_colors_generation,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for key in keys.iter().flatten() {
+            if self.thumbnail_tasks.len() >= MAX_THUMBNAIL_TASKS {
+                break;
+            }
+
+            if self.thumbnails.contains_key(key)
+                || self.thumbnail_tasks.contains_key(key)
+            {
+                continue;
+            }
+
+            let graph = self.shared.graph.clone();
+            let path_colors = self.shared.path_colors.clone();
+            let range = key.range.clone();
+
+            let task = tokio_handle.spawn_blocking(move || {
+                let path_colors = path_colors.blocking_read();
+                render_thumbnail(&graph, &path_colors, range)
+            });
+            self.thumbnail_tasks.insert(key.clone(), task);
+        }
+
+        let used = keys.iter().flatten().collect::<HashSet<_>>();
+        self.thumbnails.retain(|key, _| used.contains(key));
+
+        if !self.thumbnail_tasks.is_empty() {
+            ctx.request_repaint();
+        }
+
+        keys
+    }
+
+    fn poll_channels(&mut self) {
+        use oneshot::error::TryRecvError;
+
+        if let Some(mut recv) = self.import_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => match self.store.blocking_write().import(&path) {
+                    Ok(count) => {
+                        log::warn!("Imported {count} bookmarks");
+                        self.error = None;
+                    }
+                    Err(e) => {
+                        log::error!("Error importing bookmarks: {e:?}");
+                        self.error = Some(e.to_string());
+                    }
+                },
+                Err(TryRecvError::Empty) => self.import_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.export_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    let coords = self.shared.coords.load();
+                    let store = self.store.blocking_read();
+                    if let Err(e) = store.export(&path, coords) {
+                        log::error!("Error exporting bookmarks: {e:?}");
+                        self.error = Some(e.to_string());
+                    }
+                }
+                Err(TryRecvError::Empty) => self.export_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+impl SettingsWidget for BookmarksWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels();
+
+        let coords = self.shared.coords.load();
+
+        let resp = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.name_text)
+                        .hint_text("Bookmark name"),
+                );
+
+                if ui.button("Add current view").clicked() {
+                    let name = if self.name_text.trim().is_empty() {
+                        let count =
+                            self.store.blocking_read().bookmarks().len();
+                        format!("Bookmark {}", count + 1)
+                    } else {
+                        self.name_text.trim().to_string()
+                    };
+
+                    let _ = self.msg_tx.send(Msg::AddBookmark(name));
+                    self.name_text.clear();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Import")
+                    .on_hover_text("Adds the bookmarks in a TSV or JSON file")
+                    .clicked()
+                {
+                    let mut files = egui_file::FileDialog::open_file(None);
+                    files.open();
+
+                    let id = egui::Id::new("BookmarksWidget_import");
+                    let recv = settings_ctx.with_file_dialog_oneshot(id, files);
+                    self.import_path_recv = Some(recv);
+                }
+
+                if ui
+                    .button("Export")
+                    .on_hover_text(
+                        "Saved as JSON if the file name ends with .json, \
+                         and as TSV otherwise",
+                    )
+                    .clicked()
+                {
+                    let path = self
+                        .shared
+                        .export_paths
+                        .blocking_read()
+                        .export_path("bookmarks", None, "tsv");
+
+                    let id = egui::Id::new("BookmarksWidget_export");
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.export_path_recv = Some(recv);
+                }
+            });
+
+            if let Some(err) = self.error.as_ref() {
+                ui.label(format!("Error: {err}"));
+            }
+
+            ui.checkbox(&mut self.show_thumbnails, "Show thumbnails")
+                .on_hover_text(
+                    "Previews of the bookmarked regions, with a row per \
+                     path shown in the 1D view",
+                );
+
+            let bookmarks = self.store.blocking_read().bookmarks().to_vec();
+
+            let thumbnail_keys = if self.show_thumbnails {
+                self.update_thumbnails(
+                    &settings_ctx.tokio_handle,
+                    ui.ctx(),
+                    &bookmarks,
+                )
+            } else {
+                Vec::new()
+            };
+
+            let mut goto = None;
+            let mut remove = None;
+
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("BookmarksWidget-bookmarks")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            let mut links = Vec::new();
+
+                            for (i, bookmark) in bookmarks.iter().enumerate() {
+                                if let Some(key) = thumbnail_keys.get(i) {
+                                    let thumbnail = key
+                                        .as_ref()
+                                        .and_then(|k| self.thumbnails.get(k));
+
+                                    if let Some(texture) = thumbnail {
+                                        let image = ui
+                                            .image(texture.id(), THUMBNAIL_SIZE)
+                                            .interact(egui::Sense::click());
+                                        if image.clicked() {
+                                            goto = Some(i);
+                                        }
+                                    } else if key.is_some() {
+                                        ui.add_sized(
+                                            THUMBNAIL_SIZE,
+                                            egui::Spinner::new(),
+                                        );
+                                    } else {
+                                        ui.add_sized(
+                                            THUMBNAIL_SIZE,
+                                            egui::Label::new("–"),
+                                        );
+                                    }
+                                }
+
+                                let link = ui.link(bookmark.name.as_str());
+                                if link.clicked() {
+                                    goto = Some(i);
+                                }
+                                links.push(link);
+
+                                let range_text =
+                                    coords.format_range(&bookmark.range);
+
+                                if let Some(path) = bookmark.path.as_ref() {
+                                    ui.label(format!("{path}:{range_text}"));
+                                } else {
+                                    ui.label(range_text);
+                                }
+
+                                if ui.small_button("Delete").clicked() {
+                                    remove = Some(i);
+                                }
+
+                                ui.end_row();
+                            }
+
+                            crate::gui::util::arrow_key_focus(ui, &links);
+                        });
+                });
+
+            if let Some(i) = goto {
+                self.goto(&bookmarks[i]);
+            }
+
+            if let Some(i) = remove {
+                self.store.blocking_write().remove(i);
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bookmarks_roundtrip() {
+        let bookmarks = vec![
+            Bookmark {
+                name: "gene \"A\"".to_string(),
+                path: None,
+                range: Bp(10)..Bp(200),
+            },
+            Bookmark {
+                name: "exon".to_string(),
+                path: Some("chr1#0".to_string()),
+                range: Bp(5)..Bp(15),
+            },
+        ];
+
+        for coords in CoordSystem::ALL {
+            let mut tsv = Vec::new();
+            write_tsv(&bookmarks, coords, &mut tsv).unwrap();
+            let tsv = String::from_utf8(tsv).unwrap();
+            assert_eq!(read_tsv(&tsv).unwrap(), bookmarks);
+        }
+
+        // without the comment, positions are 0-based
+        let tsv = "a\t*\t10\t20\n";
+        assert_eq!(read_tsv(tsv).unwrap()[0].range, Bp(10)..Bp(20));
+
+        let mut json = Vec::new();
+        write_json(&bookmarks, &mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(parse_json(&json).unwrap(), bookmarks);
+
+        assert!(read_tsv("a\t*\t1").is_err());
+    }
+}
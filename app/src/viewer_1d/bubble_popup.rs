@@ -0,0 +1,211 @@
+//! Window comparing the alleles of the bubble around the hovered
+//! node with dot matrices, to tell SNPs, indels and repeats apart at a
+//! glance. Opened by pressing D over a node.
+
+use std::sync::Arc;
+
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::bubble::{
+    dot_matrix, find_bubble, Bubble, DotMatrix,
+};
+use waragraph_core::graph::{Node, PathIndex};
+
+/// How far to look for the flanking nodes
+const MAX_SPAN: usize = 200;
+/// Only the pairs of the most common alleles are compared
+const MAX_ALLELES: usize = 4;
+const MAX_BINS: usize = 64;
+
+struct BubbleView {
+    bubble: Bubble,
+    /// The dot matrix of each pair of alleles, by allele index
+    matrices: Vec<((usize, usize), DotMatrix)>,
+}
+
+impl BubbleView {
+    fn compute(graph: &PathIndex, node: Node) -> Option<Self> {
+        let bubble = find_bubble(graph, node, MAX_SPAN)?;
+
+        let alleles = &bubble.alleles[..bubble.alleles.len().min(MAX_ALLELES)];
+        let mut matrices = Vec::new();
+
+        for i in 0..alleles.len() {
+            for j in (i + 1)..alleles.len() {
+                let (a, b) = (&alleles[i].seq, &alleles[j].seq);
+                // single bases are informative for short alleles
+                let k = if a.len().max(b.len()) <= 50 { 1 } else { 4 };
+                matrices.push(((i, j), dot_matrix(a, b, k, MAX_BINS)));
+            }
+        }
+
+        Some(Self { bubble, matrices })
+    }
+}
+
+#[derive(Default)]
+pub struct BubblePopup {
+    open: bool,
+    node: Option<Node>,
+    recv: Option<oneshot::Receiver<Option<BubbleView>>>,
+    view: Option<BubbleView>,
+}
+
+impl BubblePopup {
+    /// Opens the window, and finds the bubble around `node` in the
+    /// background.
+    pub fn request(
+        &mut self,
+        tokio_handle: &tokio::runtime::Handle,
+        graph: &Arc<PathIndex>,
+        node: Node,
+    ) {
+        let graph = graph.clone();
+        let (send, recv) = oneshot::channel();
+
+        tokio_handle.spawn_blocking(move || {
+            let _ = send.send(BubbleView::compute(&graph, node));
+        });
+
+        self.open = true;
+        self.node = Some(node);
+        self.recv = Some(recv);
+        self.view = None;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, graph: &PathIndex) {
+        if let Some(mut recv) = self.recv.take() {
+            match recv.try_recv() {
+                Ok(view) => self.view = view,
+                Err(TryRecvError::Empty) => self.recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if !self.open {
+            return;
+        }
+
+        let Some(node) = self.node else {
+            return;
+        };
+
+        egui::Window::new(format!("Bubble at node {}", node.ix()))
+            .id(egui::Id::new("Viewer1D-BubblePopup"))
+            .open(&mut self.open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if self.recv.is_some() {
+                    ui.spinner();
+                    return;
+                }
+
+                let Some(view) = self.view.as_ref() else {
+                    ui.label("The node isn't in a simple bubble");
+                    return;
+                };
+
+                show_bubble(ui, graph, view);
+            });
+    }
+}
+
+fn show_bubble(ui: &mut egui::Ui, graph: &PathIndex, view: &BubbleView) {
+    let bubble = &view.bubble;
+
+    ui.label(format!(
+        "Between nodes {} and {}, {} alleles",
+        bubble.source.ix(),
+        bubble.sink.ix(),
+        bubble.alleles.len()
+    ));
+
+    egui::Grid::new("Viewer1D-BubblePopup-alleles")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("");
+            ui.label("Length");
+            ui.label("Paths");
+            ui.end_row();
+
+            for (ix, allele) in
+                bubble.alleles.iter().take(MAX_ALLELES).enumerate()
+            {
+                let names = allele
+                    .paths
+                    .iter()
+                    .filter_map(|p| graph.path_names.get_by_left(p))
+                    .map(|n| n.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                ui.label(allele_name(ix));
+                ui.label(format!("{} bp", allele.seq.len()));
+                ui.label(format!("{}", allele.paths.len()))
+                    .on_hover_text(names);
+                ui.end_row();
+            }
+        });
+
+    if bubble.alleles.len() > MAX_ALLELES {
+        ui.weak(format!(
+            "Only the {MAX_ALLELES} most common alleles are compared"
+        ));
+    }
+
+    ui.separator();
+
+    ui.horizontal_wrapped(|ui| {
+        for ((i, j), matrix) in view.matrices.iter() {
+            ui.vertical(|ui| {
+                ui.label(format!("{} vs {}", allele_name(*i), allele_name(*j)));
+                draw_matrix(ui, matrix);
+            });
+        }
+    });
+}
+
+fn allele_name(ix: usize) -> String {
+    format!("Allele {}", ix + 1)
+}
+
+/// Draws the matrix with the first allele down and the second across.
+fn draw_matrix(ui: &mut egui::Ui, matrix: &DotMatrix) {
+    let size = 128.0;
+    let (rect, _) =
+        ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::hover());
+
+    let painter = ui.painter_at(rect);
+    painter.rect_stroke(
+        rect,
+        0.0,
+        egui::Stroke::new(1.0, ui.visuals().weak_text_color()),
+    );
+
+    if matrix.rows == 0 || matrix.cols == 0 {
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "empty",
+            egui::FontId::proportional(12.0),
+            ui.visuals().weak_text_color(),
+        );
+        return;
+    }
+
+    let cell = egui::vec2(size / matrix.cols as f32, size / matrix.rows as f32);
+    let color = ui.visuals().strong_text_color();
+
+    for row in 0..matrix.rows {
+        for col in 0..matrix.cols {
+            if matrix.get(row, col) {
+                let min = rect.min
+                    + egui::vec2(col as f32 * cell.x, row as f32 * cell.y);
+                painter.rect_filled(
+                    egui::Rect::from_min_size(min, cell),
+                    0.0,
+                    color,
+                );
+            }
+        }
+    }
+}
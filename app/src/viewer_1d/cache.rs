@@ -10,13 +10,22 @@ use waragraph_core::graph::{Bp, PathId, PathIndex};
 
 use anyhow::Result;
 
-use crate::{app::resource::GraphDataCache, util::BufferDesc};
+use crate::{
+    app::resource::GraphDataCache,
+    util::{gpu_memory, BufferDesc},
+};
 
 use super::view::View1D;
 
 // TODO: still using string-typed keys for data sources and viz. modes! bad!
 pub type SlotKey = (PathId, String);
 
+const DATA_BUFFER_LABEL: &str = "Viewer 1D Data Buffer";
+
+// the slot cache won't reduce the resolution below this, even if
+// the data buffer doesn't fit in the GPU memory budget
+const MIN_BIN_COUNT: usize = 64;
+
 #[derive(
     Clone, Copy, PartialEq, PartialOrd, bytemuck::Zeroable, bytemuck::Pod,
 )]
@@ -52,6 +61,9 @@ pub struct SlotState {
     pub data_generation: Option<u64>,
     pub updated_at: Option<Instant>,
     pub last_updated_view: Option<[Bp; 2]>,
+    /// Set when the row's data no longer matches how the slot is
+    /// sampled, so it's sampled again even if the view hasn't changed
+    pub stale: bool,
     task_handle: Option<SlotTaskHandle>,
     pub last_msg: Option<SlotMsg>,
     pub last_rect: Option<egui::Rect>,
@@ -113,6 +125,24 @@ impl SlotCache {
         row_count: usize,
         bin_count: usize,
     ) -> Result<Self> {
+        // reduce the resolution if the data buffer would be too big
+        // for the device (or the memory budget)
+        let mut bin_count = bin_count;
+
+        while bin_count > MIN_BIN_COUNT
+            && !gpu_memory::storage_buffer_fits(
+                &state.device,
+                DATA_BUFFER_LABEL,
+                Self::data_buffer_size(row_count, bin_count) as u64,
+            )
+        {
+            bin_count /= 2;
+            log::warn!(
+                "Slot cache data buffer too large, reducing bin count to {}",
+                bin_count
+            );
+        }
+
         let data_buffer =
             Self::allocate_data_buffer(state, row_count, bin_count)?;
 
@@ -200,7 +230,7 @@ impl SlotCache {
             if &view0 == view1 {
                 [1.0, 0.0]
             } else {
-                super::Viewer1D::sample_index_transform(&view0, view1)
+                super::transform::sample_index_transform(&view0, view1)
             }
         } else {
             [1.0, 0.0]
@@ -245,7 +275,8 @@ impl SlotCache {
             };
 
             if state.task_handle.is_some()
-                || state.last_updated_view == Some(current_view)
+                || (state.last_updated_view == Some(current_view)
+                    && !state.stale)
             {
                 continue;
             }
@@ -291,6 +322,78 @@ impl SlotCache {
         Ok(())
     }
 
+    /// Assigns rows to the slots like `sample_with`, but instead of
+    /// spawning sampling tasks, returns the slots that don't hold the
+    /// data for the current view yet, with their row, so that they
+    /// can be filled on the GPU. Filled rows must be reported with
+    /// `mark_sampled`.
+    pub fn slots_to_sample(
+        &mut self,
+        view: &View1D,
+        data_key: &str,
+        paths: impl IntoIterator<Item = PathId>,
+    ) -> Vec<(SlotKey, usize)> {
+        let vl = view.range().start;
+        let vr = view.range().end;
+        let current_view = [Bp(vl), Bp(vr)];
+
+        let slots = paths
+            .into_iter()
+            .map(|path| (path, data_key.to_string()))
+            .collect::<Vec<_>>();
+
+        let result = self.assign_rows_for_slots(slots.iter(), current_view);
+
+        if let Err(SlotCacheError::OutOfRows) = result {
+            log::error!("Slot cache full! TODO reallocate");
+        }
+
+        self.last_dispatched_view = Some(current_view);
+
+        slots
+            .into_iter()
+            .filter_map(|slot_key| {
+                let state = self.slot_state.get(&slot_key)?;
+                let row = *self.slot_id_map.get(&slot_key)?;
+
+                let up_to_date = state.task_handle.is_some()
+                    || (state.last_updated_view == Some(current_view)
+                        && !state.stale);
+
+                (!up_to_date).then_some((slot_key, row))
+            })
+            .collect()
+    }
+
+    /// Records that the slot's row holds the data for `view`.
+    pub fn mark_sampled(&mut self, slot_key: &SlotKey, view: [Bp; 2]) {
+        if let Some(state) = self.slot_state.get_mut(slot_key) {
+            state.last_updated_view = Some(view);
+            state.stale = false;
+            state.data_generation = Some(self.generation);
+            state.updated_at = Some(Instant::now());
+        }
+    }
+
+    /// Marks the slots of the data source to be sampled again, e.g.
+    /// after changing how its bins are aggregated.
+    pub fn invalidate_data(&mut self, data_key: &str) {
+        for (key, state) in self.slot_state.iter_mut() {
+            if key.1 != data_key {
+                continue;
+            }
+
+            if let Some(handle) = state.task_handle.take() {
+                handle.abort();
+            }
+            state.stale = true;
+        }
+    }
+
+    pub fn bin_count(&self) -> usize {
+        self.bin_count
+    }
+
     pub fn sample_for_data(
         &mut self,
         state: &raving_wgpu::State,
@@ -328,7 +431,8 @@ impl SlotCache {
             };
 
             if state.task_handle.is_some()
-                || state.last_updated_view == Some(current_view)
+                || (state.last_updated_view == Some(current_view)
+                    && !state.stale)
             {
                 continue;
             }
@@ -416,6 +520,7 @@ impl SlotCache {
                 );
 
                 slot_state.last_updated_view = Some(task_view);
+                slot_state.stale = false;
                 slot_state.data_generation = Some(data_ts);
                 slot_state.updated_at = Some(Instant::now());
 
@@ -567,6 +672,12 @@ impl SlotCache {
         prefix_size + self.rows * self.bin_count
     }
 
+    pub fn tasks_running(&self) -> bool {
+        self.slot_state
+            .values()
+            .any(|state| state.task_handle.is_some())
+    }
+
     pub fn slot_task_running(&self, key: &SlotKey) -> bool {
         self.slot_state
             .get(key)
@@ -597,13 +708,17 @@ impl SlotCache {
 
             let buf_size = t_stride * self.rows.next_power_of_two();
 
+            let label = "Slot Cache Fragment Transform Buffer";
+
             let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some("Slot Cache Fragment Transform Buffer"),
+                label: Some(label),
                 usage,
                 size: buf_size as u64,
                 mapped_at_creation: false,
             });
 
+            gpu_memory::record_buffer(label, buf_size as u64);
+
             self.transform_buffer = Some(BufferDesc {
                 buffer,
                 size: buf_size,
@@ -680,13 +795,17 @@ impl SlotCache {
 
             let buf_size = vx_stride * vertices.len().next_power_of_two();
 
+            let label = "Slot Cache Vertex Buffer";
+
             let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some("Slot Cache Vertex Buffer"),
+                label: Some(label),
                 usage,
                 size: buf_size as u64,
                 mapped_at_creation: false,
             });
 
+            gpu_memory::record_buffer(label, buf_size as u64);
+
             self.vertex_buffer = Some(BufferDesc {
                 buffer,
                 size: buf_size,
@@ -707,6 +826,12 @@ impl SlotCache {
         Ok(())
     }
 
+    fn data_buffer_size(row_count: usize, bin_count: usize) -> usize {
+        let prefix_size = std::mem::size_of::<[u32; 2]>();
+        let elem_size = std::mem::size_of::<f32>();
+        prefix_size + row_count * bin_count * elem_size
+    }
+
     fn allocate_data_buffer(
         state: &raving_wgpu::State,
         row_count: usize,
@@ -717,21 +842,32 @@ impl SlotCache {
         // let prefix = [rows * cols, cols, !0u32, !0u32];
         let prefix = [rows * cols, cols];
 
-        let prefix_size = std::mem::size_of::<[u32; 2]>();
-        let elem_size = std::mem::size_of::<f32>();
-        let size = prefix_size + row_count * bin_count * elem_size;
+        let size = Self::data_buffer_size(row_count, bin_count);
+
+        if !gpu_memory::storage_buffer_fits(
+            &state.device,
+            DATA_BUFFER_LABEL,
+            size as u64,
+        ) {
+            anyhow::bail!(
+                "Slot cache data buffer of {} exceeds the GPU limits",
+                gpu_memory::format_bytes(size as u64)
+            );
+        }
 
         let usage = wgpu::BufferUsages::COPY_DST
             | wgpu::BufferUsages::COPY_SRC
             | wgpu::BufferUsages::STORAGE;
 
         let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Viewer 1D Data Buffer"),
+            label: Some(DATA_BUFFER_LABEL),
             size: size as u64,
             usage,
             mapped_at_creation: false,
         });
 
+        gpu_memory::record_buffer(DATA_BUFFER_LABEL, size as u64);
+
         state
             .queue
             .write_buffer(&buffer, 0, bytemuck::cast_slice(&prefix));
@@ -0,0 +1,431 @@
+//! The comparison view of the 1D viewer: a second view of the same
+//! paths, below the main one, at the same zoom level but with its own
+//! offset, for looking at two regions side by side, e.g. a duplication
+//! and its source. A band between the views connects the part of the
+//! pangenome that both show, if any.
+
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::{Bp, PathId};
+
+use crate::app::SharedState;
+use crate::color::ColorMap;
+use crate::gui::coords::PosFormat;
+use crate::gui::layers::{Layer, LayeredShapes};
+
+use super::control::{GotoBox, GotoTarget, ViewCmd};
+use super::ruler::format_bp;
+use super::sampler::Sampler;
+use super::software::value_color;
+use super::view::View1D;
+
+/// The part of the data area the comparison view takes up, when shown
+const HEIGHT_FRACTION: f32 = 0.4;
+const CONNECTOR_HEIGHT: f32 = 28.0;
+const HEADER_HEIGHT: f32 = 20.0;
+const ROW_HEIGHT: f32 = 20.0;
+
+/// What the comparison rows were sampled for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleKey {
+    pub range: std::ops::Range<u64>,
+    pub data_key: String,
+    pub paths: Vec<PathId>,
+    pub bins: usize,
+    pub color_map: ColorMap,
+    pub scheme: Vec<[f32; 4]>,
+}
+
+/// Where the comparison view is drawn: `rect` is the area below the
+/// main view, and the name and data columns line up with the main
+/// view's; `main_bottom` is the bottom of the main view's path rows.
+pub struct CompareLayout {
+    pub rect: egui::Rect,
+    pub name_x: RangeInclusive<f32>,
+    pub data_x: RangeInclusive<f32>,
+    pub main_bottom: f32,
+}
+
+impl CompareLayout {
+    fn rows_top(&self) -> f32 {
+        self.rect.top() + CONNECTOR_HEIGHT + HEADER_HEIGHT
+    }
+
+    /// How many path rows fit.
+    pub fn row_count(&self) -> usize {
+        ((self.rect.bottom() - self.rows_top()) / ROW_HEIGHT).max(0.0) as usize
+    }
+
+    fn row_rects(&self, row: usize) -> (egui::Rect, egui::Rect) {
+        let y0 = self.rows_top() + row as f32 * ROW_HEIGHT;
+        let y_range = y0..=(y0 + ROW_HEIGHT - 2.0);
+        (
+            egui::Rect::from_x_y_ranges(self.name_x.clone(), y_range.clone()),
+            egui::Rect::from_x_y_ranges(self.data_x.clone(), y_range),
+        )
+    }
+}
+
+pub struct CompareView {
+    pub enabled: bool,
+    view: View1D,
+    goto_box: GotoBox,
+
+    sampled: Option<(SampleKey, egui::TextureHandle)>,
+    pending: Option<(SampleKey, oneshot::Receiver<Result<egui::ColorImage>>)>,
+}
+
+impl CompareView {
+    pub fn new(max: u64) -> Self {
+        Self {
+            enabled: false,
+            view: View1D::new(max),
+            goto_box: GotoBox::default(),
+            sampled: None,
+            pending: None,
+        }
+    }
+
+    pub fn view(&self) -> &View1D {
+        &self.view
+    }
+
+    /// Keeps the comparison view as long as the main view, around its
+    /// own center.
+    pub fn link_zoom(&mut self, main: &View1D) {
+        if self.view.len() == main.len() {
+            return;
+        }
+        let center = self.view.offset() + self.view.len() / 2;
+        let start = center.saturating_sub(main.len() / 2);
+        self.view.set(start, start + main.len());
+    }
+
+    /// Splits the data area into the main view, and the comparison
+    /// view if it's shown.
+    pub fn split(&self, rect: egui::Rect) -> (egui::Rect, Option<egui::Rect>) {
+        if !self.enabled {
+            return (rect, None);
+        }
+
+        let y = rect.bottom() - rect.height() * HEIGHT_FRACTION;
+        let main = egui::Rect::from_x_y_ranges(rect.x_range(), rect.top()..=y);
+        let compare =
+            egui::Rect::from_x_y_ranges(rect.x_range(), y..=rect.bottom());
+        (main, Some(compare))
+    }
+
+    /// The toggle, a goto box for the comparison view, and buttons to
+    /// swap the views and to move the comparison view to the main one.
+    pub fn show_controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        shared: &SharedState,
+        main: &mut View1D,
+    ) {
+        ui.checkbox(&mut self.enabled, "Show comparison view")
+            .on_hover_text(
+                "A second view below the main one, at the same zoom \
+                 level; drag it to move it on its own",
+            );
+
+        if !self.enabled {
+            return;
+        }
+
+        if let Some(target) = self.goto_box.show(ui, shared) {
+            let cmd = match target {
+                GotoTarget::Node(node) => ViewCmd::GotoNode { node },
+                GotoTarget::Range { path, range } => {
+                    ViewCmd::GotoRange { path, range }
+                }
+            };
+            // the zoom is linked to the main view again next frame
+            cmd.apply(shared, &mut self.view);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Swap views").clicked() {
+                std::mem::swap(main, &mut self.view);
+            }
+            if ui.button("Match main view").clicked() {
+                self.view = main.clone();
+            }
+        });
+    }
+
+    /// Samples the rows of the comparison view in the background, if
+    /// they haven't been sampled for `key` yet.
+    pub fn update_samples(
+        &mut self,
+        ctx: &egui::Context,
+        tokio_rt: &tokio::runtime::Handle,
+        sampler: Arc<dyn Sampler>,
+        key: SampleKey,
+    ) {
+        if let Some((pending_key, mut recv)) = self.pending.take() {
+            match recv.try_recv() {
+                Ok(Ok(image)) => {
+                    let texture = ctx.load_texture(
+                        "Viewer1D_compare",
+                        image,
+                        egui::TextureOptions::NEAREST,
+                    );
+                    self.sampled = Some((pending_key, texture));
+                }
+                Ok(Err(e)) => {
+                    log::error!("Error sampling the comparison view: {e:?}");
+                }
+                Err(TryRecvError::Empty) => {
+                    self.pending = Some((pending_key, recv));
+                }
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        let up_to_date = |k: Option<&SampleKey>| k == Some(&key);
+        if up_to_date(self.sampled.as_ref().map(|(k, _)| k))
+            || up_to_date(self.pending.as_ref().map(|(k, _)| k))
+            || key.paths.is_empty()
+            || key.bins == 0
+        {
+            return;
+        }
+
+        // only the latest view is sampled; a stale result is dropped
+        let (send, recv) = oneshot::channel();
+        let task_key = key.clone();
+
+        tokio_rt.spawn(async move {
+            let key = task_key;
+            let result = async {
+                let range = Bp(key.range.start)..Bp(key.range.end);
+                let mut pixels = Vec::with_capacity(key.bins * 3);
+
+                for (row, &path) in key.paths.iter().enumerate() {
+                    let data = sampler
+                        .sample_range(key.bins, path, range.clone())
+                        .await?;
+                    let values: &[f32] = bytemuck::cast_slice(&data);
+
+                    for &v in values.iter().take(key.bins) {
+                        let color = value_color(&key.scheme, key.color_map, v);
+                        pixels.extend(color);
+                    }
+                    pixels.resize((row + 1) * key.bins * 3, 255);
+                }
+
+                Ok(egui::ColorImage::from_rgb(
+                    [key.bins, key.paths.len()],
+                    &pixels,
+                ))
+            }
+            .await;
+
+            let _ = send.send(result);
+        });
+
+        self.pending = Some((key, recv));
+    }
+
+    /// Draws the connector band, the range of the comparison view, and
+    /// its rows, and handles dragging (which only moves the comparison
+    /// view) and scrolling (which zooms both views).
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        shared: &SharedState,
+        main: &mut View1D,
+        layout: &CompareLayout,
+        shapes: &mut LayeredShapes,
+    ) {
+        let visuals = ui.visuals().clone();
+
+        // the connector band
+        let top = layout.main_bottom + 2.0;
+        let bottom = layout.rect.top() + CONNECTOR_HEIGHT - 2.0;
+
+        match shared_span(main, &self.view, &layout.data_x) {
+            Some((upper, lower)) => {
+                let fill = visuals.selection.bg_fill.gamma_multiply(0.4);
+                shapes.push(
+                    Layer::Background,
+                    egui::Shape::convex_polygon(
+                        vec![
+                            egui::pos2(*upper.start(), top),
+                            egui::pos2(*upper.end(), top),
+                            egui::pos2(*lower.end(), bottom),
+                            egui::pos2(*lower.start(), bottom),
+                        ],
+                        fill,
+                        egui::Stroke::new(1.0, visuals.selection.bg_fill),
+                    ),
+                );
+            }
+            None => {
+                let offset = self.view.offset() as i64 - main.offset() as i64;
+                let sign = if offset < 0 { "-" } else { "+" };
+                let step = (main.len() / 100).max(1);
+                let text =
+                    format!("{sign}{}", format_bp(offset.unsigned_abs(), step));
+
+                let x = (layout.data_x.start() + layout.data_x.end()) / 2.0;
+                let y = (top + bottom) / 2.0;
+                ui.fonts(|fonts| {
+                    shapes.push(
+                        Layer::Ui,
+                        egui::Shape::text(
+                            fonts,
+                            egui::pos2(x, y),
+                            egui::Align2::CENTER_CENTER,
+                            text,
+                            egui::FontId::monospace(12.0),
+                            visuals.text_color(),
+                        ),
+                    );
+                });
+            }
+        }
+
+        // the range of the comparison view, above its rows
+        let header = egui::Rect::from_x_y_ranges(
+            layout.data_x.clone(),
+            (bottom + 2.0)..=(bottom + HEADER_HEIGHT),
+        );
+        let range = self.view.range();
+        let format = PosFormat::load(shared);
+        let text =
+            format.range(&shared.graph, &(Bp(range.start)..Bp(range.end)));
+        ui.fonts(|fonts| {
+            shapes.push(
+                Layer::Ui,
+                egui::Shape::text(
+                    fonts,
+                    header.center(),
+                    egui::Align2::CENTER_CENTER,
+                    text,
+                    egui::FontId::monospace(12.0),
+                    visuals.text_color(),
+                ),
+            );
+        });
+
+        // the rows
+        let row_count = self
+            .sampled
+            .as_ref()
+            .map_or(0, |(key, _)| key.paths.len().min(layout.row_count()));
+        let mut data_region = egui::Rect::NOTHING;
+
+        if let Some((key, texture)) = self.sampled.as_ref() {
+            let rows = key.paths.len() as f32;
+
+            for (row, path) in key.paths.iter().take(row_count).enumerate() {
+                let (name_rect, data_rect) = layout.row_rects(row);
+                data_region = data_region.union(data_rect);
+
+                let name = shared
+                    .graph
+                    .path_names
+                    .get_by_left(path)
+                    .map_or("", |n| n.as_str());
+                ui.fonts(|fonts| {
+                    shapes.push(
+                        Layer::Ui,
+                        egui::Shape::text(
+                            fonts,
+                            name_rect.left_center(),
+                            egui::Align2::LEFT_CENTER,
+                            name,
+                            egui::FontId::monospace(14.0),
+                            visuals.text_color(),
+                        ),
+                    );
+                });
+
+                let uv = egui::Rect::from_min_max(
+                    egui::pos2(0.0, row as f32 / rows),
+                    egui::pos2(1.0, (row + 1) as f32 / rows),
+                );
+                shapes.push(
+                    Layer::DataTracks,
+                    egui::Shape::image(
+                        texture.id(),
+                        data_rect,
+                        uv,
+                        egui::Color32::WHITE,
+                    ),
+                );
+            }
+        }
+
+        if !data_region.is_positive() {
+            return;
+        }
+
+        let resp = ui.allocate_rect(data_region, egui::Sense::drag());
+
+        if resp.dragged_by(egui::PointerButton::Primary) {
+            let dx = resp.drag_delta().x / data_region.width();
+            self.view.translate_norm_f32(-dx);
+        }
+
+        if let Some(pos) = resp.hover_pos() {
+            let scroll = ui.input(|i| i.scroll_delta);
+            let rel_x = (pos.x - data_region.left()) / data_region.width();
+
+            if scroll.y.abs() > 1.0 {
+                let dz = 1.0 - scroll.y * 0.01;
+                main.zoom_with_focus(rel_x, dz);
+                self.view.zoom_with_focus(rel_x, dz);
+            }
+        }
+    }
+}
+
+/// The part of the pangenome both views show, as the x ranges it's
+/// drawn at in the main view and in the other view.
+fn shared_span(
+    main: &View1D,
+    other: &View1D,
+    data_x: &RangeInclusive<f32>,
+) -> Option<(RangeInclusive<f32>, RangeInclusive<f32>)> {
+    let start = main.offset().max(other.offset());
+    let end = main.range().end.min(other.range().end);
+    if start >= end {
+        return None;
+    }
+
+    let span = Bp(start)..Bp(end);
+    let upper = main.map_bp_interval_to_screen_x(&span, data_x)?;
+    let lower = other.map_bp_interval_to_screen_x(&span, data_x)?;
+    Some((upper, lower))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linked_zoom_and_shared_span() {
+        let mut main = View1D::new(10_000);
+        main.set(1000, 2000);
+
+        let mut compare = CompareView::new(10_000);
+        compare.view.set(1500, 1700);
+        compare.link_zoom(&main);
+        assert_eq!(compare.view().range(), &(1100..2100));
+
+        let data_x = 0.0..=100.0;
+        let (upper, lower) =
+            shared_span(&main, compare.view(), &data_x).unwrap();
+        assert_eq!(upper, 10.0..=100.0);
+        assert_eq!(lower, 0.0..=90.0);
+
+        compare.view.set(5000, 6000);
+        assert!(shared_span(&main, compare.view(), &data_x).is_none());
+    }
+}
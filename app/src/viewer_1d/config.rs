@@ -1,12 +1,102 @@
 use crossbeam::atomic::AtomicCell;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
+use waragraph_core::graph::sampling::BinAggregate;
+
+use crate::app::config::{ConfigCell, ConfigSchema};
 use crate::app::settings_menu;
 
+use super::depth_track::DepthTrackMode;
+use super::overlay_track::{OverlayScale, MAX_OVERLAY_TRACKS, OVERLAY_COLORS};
+
 #[derive(Debug, Clone)]
 pub struct Config {
     // use_linear_sampler: Arc<AtomicCell<bool>>,
     pub(super) filter_path_list_by_visibility: Arc<AtomicCell<bool>>,
+    pub(super) show_path_sparklines: Arc<AtomicCell<bool>>,
+    /// Overlapping annotation labels are stacked in at most this
+    /// many rows per slot
+    pub(super) max_annotation_rows: Arc<AtomicCell<usize>>,
+    pub(super) show_sequence_panel: Arc<AtomicCell<bool>>,
+    pub(super) depth_track: Arc<AtomicCell<DepthTrackMode>>,
+    /// Hide annotations that extend past the end of their path,
+    /// rather than pinning them to the path end
+    pub(super) hide_out_of_bounds_annotations: Arc<AtomicCell<bool>>,
+    /// Sample path data with a compute shader, rather than on the CPU
+    pub(super) gpu_sampling: Arc<AtomicCell<bool>>,
+    /// How the values in each bin are combined, by quantitative data
+    /// source
+    pub(super) bin_aggregate: BTreeMap<String, Arc<AtomicCell<BinAggregate>>>,
+    /// The quantitative data sources drawn in the overlay track
+    pub(super) overlay_tracks: BTreeMap<String, Arc<AtomicCell<bool>>>,
+    pub(super) overlay_scale: Arc<AtomicCell<OverlayScale>>,
+}
+
+impl Config {
+    /// The `viewer_1d.*` config keys, with the current values as
+    /// defaults
+    pub(super) fn schema(&self) -> ConfigSchema {
+        let max_rows = self.max_annotation_rows.load() as i64;
+        ConfigSchema::builder()
+            .bool(
+                "viewer_1d.filter_path_list_by_visibility",
+                self.filter_path_list_by_visibility.load(),
+            )
+            .bool(
+                "viewer_1d.show_path_sparklines",
+                self.show_path_sparklines.load(),
+            )
+            .int("viewer_1d.max_annotation_rows", max_rows, 1..=16)
+            .bool(
+                "viewer_1d.show_sequence_panel",
+                self.show_sequence_panel.load(),
+            )
+            .bool(
+                "viewer_1d.hide_out_of_bounds_annotations",
+                self.hide_out_of_bounds_annotations.load(),
+            )
+            .bool("viewer_1d.gpu_sampling", self.gpu_sampling.load())
+            .build()
+    }
+
+    pub(super) fn config_cells(&self) -> [(&'static str, ConfigCell); 6] {
+        use ConfigCell as C;
+        [
+            (
+                "viewer_1d.filter_path_list_by_visibility",
+                C::Bool(self.filter_path_list_by_visibility.clone()),
+            ),
+            (
+                "viewer_1d.show_path_sparklines",
+                C::Bool(self.show_path_sparklines.clone()),
+            ),
+            (
+                "viewer_1d.max_annotation_rows",
+                C::Int(self.max_annotation_rows.clone()),
+            ),
+            (
+                "viewer_1d.show_sequence_panel",
+                C::Bool(self.show_sequence_panel.clone()),
+            ),
+            (
+                "viewer_1d.hide_out_of_bounds_annotations",
+                C::Bool(self.hide_out_of_bounds_annotations.clone()),
+            ),
+            ("viewer_1d.gpu_sampling", C::Bool(self.gpu_sampling.clone())),
+        ]
+    }
+
+    /// The data sources shown in the overlay track, in the order
+    /// their colors are picked in.
+    pub(super) fn overlay_keys(&self) -> Vec<String> {
+        self.overlay_tracks
+            .iter()
+            .filter(|(_, shown)| shown.load())
+            .map(|(key, _)| key.clone())
+            .take(MAX_OVERLAY_TRACKS)
+            .collect()
+    }
 }
 
 pub struct ConfigWidget {
@@ -19,11 +109,117 @@ impl settings_menu::SettingsWidget for ConfigWidget {
         ui: &mut egui::Ui,
         _settings_ctx: &settings_menu::SettingsUiContext,
     ) -> settings_menu::SettingsUiResponse {
-        let mut filter_paths = self.cfg.filter_path_list_by_visibility.load();
-        let response =
+        let resp = ui.vertical(|ui| {
+            let mut filter_paths =
+                self.cfg.filter_path_list_by_visibility.load();
             ui.checkbox(&mut filter_paths, "Filter paths by visibility");
-        self.cfg.filter_path_list_by_visibility.store(filter_paths);
+            self.cfg.filter_path_list_by_visibility.store(filter_paths);
+
+            let mut sparklines = self.cfg.show_path_sparklines.load();
+            ui.checkbox(&mut sparklines, "Show path coverage sparklines");
+            self.cfg.show_path_sparklines.store(sparklines);
+
+            let mut seq_panel = self.cfg.show_sequence_panel.load();
+            ui.checkbox(&mut seq_panel, "Show sequence panel");
+            self.cfg.show_sequence_panel.store(seq_panel);
+
+            ui.horizontal(|ui| {
+                let mut mode = self.cfg.depth_track.load();
+                ui.label("Depth track");
+                ui.radio_value(&mut mode, DepthTrackMode::Hidden, "Hidden");
+                ui.radio_value(&mut mode, DepthTrackMode::Bars, "Bars");
+                ui.radio_value(&mut mode, DepthTrackMode::Heatmap, "Heatmap");
+                self.cfg.depth_track.store(mode);
+            });
+
+            ui.horizontal(|ui| {
+                let mut max_rows = self.cfg.max_annotation_rows.load();
+                ui.label("Max annotation label rows");
+                ui.add(egui::DragValue::new(&mut max_rows).clamp_range(1..=16));
+                self.cfg.max_annotation_rows.store(max_rows);
+            });
+
+            let mut hide_oob = self.cfg.hide_out_of_bounds_annotations.load();
+            ui.checkbox(&mut hide_oob, "Hide annotations beyond path ends")
+                .on_hover_text(
+                    "Records that extend past the end of their path are \
+                     otherwise clamped, and pinned to the path end",
+                );
+            self.cfg.hide_out_of_bounds_annotations.store(hide_oob);
+
+            let mut gpu_sampling = self.cfg.gpu_sampling.load();
+            ui.checkbox(&mut gpu_sampling, "Sample path data on the GPU")
+                .on_hover_text(
+                    "Keeps panning and zooming smooth on large graphs; \
+                     turn off to use the CPU sampler",
+                );
+            self.cfg.gpu_sampling.store(gpu_sampling);
+
+            ui.separator();
+            ui.label("Bin aggregation").on_hover_text(
+                "How the values of the nodes in each bin are \
+                 combined; use max to keep narrow peaks visible when \
+                 zoomed out. Only the mean is sampled on the GPU.",
+            );
+
+            egui::Grid::new("Viewer1D-Config-BinAggregate").show(ui, |ui| {
+                for (data_key, cell) in self.cfg.bin_aggregate.iter() {
+                    let mut aggregate = cell.load();
+                    ui.label(data_key);
+                    egui::ComboBox::from_id_source(("bin_aggregate", data_key))
+                        .selected_text(aggregate.name())
+                        .show_ui(ui, |ui| {
+                            for a in BinAggregate::ALL {
+                                ui.selectable_value(
+                                    &mut aggregate,
+                                    a,
+                                    a.name(),
+                                );
+                            }
+                        });
+                    cell.store(aggregate);
+                    ui.end_row();
+                }
+            });
+
+            ui.separator();
+            ui.label("Overlay track").on_hover_text(format!(
+                "Draws up to {MAX_OVERLAY_TRACKS} data tracks as lines \
+                 in one row"
+            ));
+
+            let shown = self.cfg.overlay_keys();
+
+            ui.horizontal(|ui| {
+                for (data_key, cell) in self.cfg.overlay_tracks.iter() {
+                    let mut show = cell.load();
+                    let color = shown
+                        .iter()
+                        .position(|k| k == data_key)
+                        .map(|ix| OVERLAY_COLORS[ix])
+                        .unwrap_or(ui.visuals().text_color());
+
+                    let text = egui::RichText::new(data_key).color(color);
+                    let enabled = show || shown.len() < MAX_OVERLAY_TRACKS;
+                    ui.add_enabled(
+                        enabled,
+                        egui::Checkbox::new(&mut show, text),
+                    );
+                    cell.store(show);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let mut scale = self.cfg.overlay_scale.load();
+                ui.label("Scale");
+                ui.radio_value(&mut scale, OverlayScale::Shared, "Shared");
+                ui.radio_value(&mut scale, OverlayScale::PerTrack, "Per track");
+                self.cfg.overlay_scale.store(scale);
+            });
+        });
 
-        settings_menu::SettingsUiResponse { response }
+        settings_menu::SettingsUiResponse {
+            response: resp.response,
+        }
     }
 }
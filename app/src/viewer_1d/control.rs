@@ -1,4 +1,6 @@
-use waragraph_core::graph::{Bp, Node, PathId};
+use anyhow::{anyhow, bail, Result};
+use waragraph_core::graph::coords::CoordSystem;
+use waragraph_core::graph::{Bp, Node, PathId, PathIndex};
 
 use crate::app::SharedState;
 
@@ -6,6 +8,14 @@ use super::view::View1D;
 
 pub enum Msg {
     View(ViewCmd),
+    /// Save the path data of the current view as PNG, with an SVG
+    /// of the labels next to it
+    ExportImage(std::path::PathBuf),
+    /// As `ExportImage`, to a path generated from the export settings
+    /// and the view range
+    ExportImageAuto,
+    /// Bookmark the current view with the given name
+    AddBookmark(String),
 }
 
 struct ViewMsgParams {
@@ -20,6 +30,10 @@ pub enum ViewCmd {
         path: Option<PathId>,
         range: std::ops::Range<Bp>,
     },
+    /// Show exactly the given pangenome range
+    SetRange {
+        range: std::ops::Range<Bp>,
+    },
 }
 
 impl ViewCmd {
@@ -31,36 +45,10 @@ impl ViewCmd {
             }
             ViewCmd::GotoRange { path, range } => {
                 let range = if let Some(path) = path {
-                    // TODO: this just reduces to the pangenome
-                    // interval containing the nodes in the path
-                    // range; it doesn't try to find the correct
-                    // position on the bp-level
-
-                    let steps = shared.graph.path_step_range_iter(path, range);
-
-                    let node_bounds = steps
-                        .map(|steps| {
-                            steps.fold(
-                                (u32::MAX, u32::MIN),
-                                |(min, max), (_, step)| {
-                                    let min = min.min(step.node().ix() as u32);
-                                    let max = max.max(step.node().ix() as u32);
-                                    (min, max)
-                                },
-                            )
-                        })
-                        .filter(|&(min, max)| {
-                            min != u32::MAX && max != u32::MIN
-                        })
-                        .map(|(min, max)| (Node::from(min), Node::from(max)));
-
-                    if let Some((min_n, max_n)) = node_bounds {
-                        let (left, _) = shared.graph.node_offset_length(min_n);
-                        let (r_off, r_len) =
-                            shared.graph.node_offset_length(max_n);
-                        let right = Bp(r_off.0 + r_len.0);
-
-                        left..right
+                    if let Some(range) =
+                        path_range_to_pangenome(&shared.graph, path, range)
+                    {
+                        range
                     } else {
                         return;
                     }
@@ -71,16 +59,48 @@ impl ViewCmd {
 
                 view.try_center(range);
             }
+            ViewCmd::SetRange { range } => {
+                view.set(range.start.0, range.end.0);
+            }
         }
     }
 }
 
+/// Finds the pangenome interval covering the nodes in the given
+/// range of the path.
+// TODO: this just reduces to the pangenome interval containing the
+// nodes in the path range; it doesn't try to find the correct
+// position on the bp-level
+pub fn path_range_to_pangenome(
+    graph: &PathIndex,
+    path: PathId,
+    range: std::ops::Range<Bp>,
+) -> Option<std::ops::Range<Bp>> {
+    let steps = graph.path_step_range_iter(path, range)?;
+
+    let (min, max) =
+        steps.fold((u32::MAX, u32::MIN), |(min, max), (_, step)| {
+            let min = min.min(step.node().ix() as u32);
+            let max = max.max(step.node().ix() as u32);
+            (min, max)
+        });
+
+    if min == u32::MAX || max == u32::MIN {
+        return None;
+    }
+
+    let (left, _) = graph.node_offset_length(Node::from(min));
+    let (r_off, r_len) = graph.node_offset_length(Node::from(max));
+    let right = Bp(r_off.0 + r_len.0);
+
+    Some(left..right)
+}
+
 pub struct ViewControlWidget {
     shared: SharedState,
     msg_tx: crossbeam::channel::Sender<Msg>,
 
-    node_id_text: String,
-    pos_text: String,
+    goto_box: GotoBox,
 }
 
 impl ViewControlWidget {
@@ -92,86 +112,266 @@ impl ViewControlWidget {
             shared: shared.clone(),
             msg_tx,
 
-            node_id_text: String::new(),
-            pos_text: String::new(),
+            goto_box: GotoBox::default(),
         }
     }
 
     pub fn show(&mut self, ui: &mut egui::Ui) {
-        ui.label("Node ID");
-        let node_id_entry = ui.add_sized(
-            [ui.available_size().x, 0f32],
-            egui::TextEdit::singleline(&mut self.node_id_text),
-        );
-        let goto_node_b = ui.button("Go to node");
+        let Some(target) = self.goto_box.show(ui, &self.shared) else {
+            return;
+        };
 
-        let goto_node = goto_node_b.clicked()
-            || (node_id_entry.lost_focus()
-                && ui.input(|i| i.key_pressed(egui::Key::Enter)));
+        let cmd = match target {
+            GotoTarget::Node(node) => ViewCmd::GotoRange {
+                path: None,
+                range: self.shared.graph.node_pangenome_range(node),
+            },
+            GotoTarget::Range { path, range } => {
+                ViewCmd::GotoRange { path, range }
+            }
+        };
 
-        ui.label("Position");
-        let pos_entry = ui.add_sized(
-            [ui.available_size().x, 0f32],
-            egui::TextEdit::singleline(&mut self.pos_text),
-        );
+        let _ = self.msg_tx.send(Msg::View(cmd));
+    }
+}
 
-        let goto_pos_b = ui.button("Go to position");
+/// A location entered in the goto box
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GotoTarget {
+    Node(Node),
+    /// A range of the path, or of the pangenome if `path` is `None`
+    Range {
+        path: Option<PathId>,
+        range: std::ops::Range<Bp>,
+    },
+}
 
-        let goto_pos = goto_pos_b.clicked()
-            || (pos_entry.lost_focus()
-                && ui.input(|i| i.key_pressed(egui::Key::Enter)));
+/// Parses the text of the goto box: `node:<index>`,
+/// `segment:<GFA segment ID>`, `<path>:<start>-<end>` or
+/// `<path>:<pos>` in path coordinates, or a range or position on the
+/// `reference` path, or on the pangenome if there's no reference, in
+/// the `coords` convention. Numbers may contain `,`
+/// separators, and `k`, `m` or `g` suffixes, as in `chr1:1m-2m`.
+/// Fails if the node or path isn't in the graph, or the range starts
+/// past its end.
+pub fn parse_goto(
+    graph: &PathIndex,
+    coords: CoordSystem,
+    reference: Option<PathId>,
+    text: &str,
+) -> Result<GotoTarget> {
+    let text = text.trim();
 
-        if goto_node {
-            let node = parse_node(&self.node_id_text);
+    if text.is_empty() {
+        bail!("Enter a node, path range, or pangenome position");
+    }
 
-            if let Some(range) =
-                node.map(|n| self.shared.graph.node_pangenome_range(n))
-            {
-                let _ = self
-                    .msg_tx
-                    .send(Msg::View(ViewCmd::GotoRange { path: None, range }));
-            }
-        }
+    let parse_id = |id: &str| {
+        let id = id.trim().replace(',', "");
+        id.parse::<u64>()
+            .map_err(|_| anyhow!("Invalid node ID `{id}`"))
+    };
 
-        if goto_pos {
-            if let Some((path_name, range)) = parse_pos_range(&self.pos_text) {
-                let path = path_name
-                    .and_then(|name| {
-                        self.shared.graph.path_names.get_by_right(name)
-                    })
-                    .copied();
-
-                let _ = self
-                    .msg_tx
-                    .send(Msg::View(ViewCmd::GotoRange { path, range }));
-            }
+    if let Some(id) = text.strip_prefix("node:") {
+        let id = parse_id(id)?;
+        let count = graph.node_count;
+        if id >= count as u64 {
+            bail!("No node {id} in the graph ({count} nodes)");
         }
+        return Ok(GotoTarget::Node(Node::from(id as u32)));
+    }
+
+    if let Some(id) = text.strip_prefix("segment:") {
+        let id = parse_id(id)?;
+        let node = graph.segment_node(id).ok_or_else(|| {
+            let (first, last) = graph.segment_id_range;
+            anyhow!("No segment {id} in the graph (IDs {first}-{last})")
+        })?;
+        return Ok(GotoTarget::Node(node));
+    }
+
+    let (path_name, range_text) = match text.rsplit_once(':') {
+        Some((path_name, range_text)) => (Some(path_name), range_text),
+        None => (None, text),
+    };
+
+    let range = expand_bp_suffixes(&range_text.replace(',', ""))
+        .and_then(|text| coords.parse_range(&text))
+        .ok_or_else(|| anyhow!("Invalid position or range `{range_text}`"))?;
+
+    let path = match path_name {
+        Some(name) => Some(
+            *graph
+                .path_names
+                .get_by_right(name)
+                .ok_or_else(|| anyhow!("Unknown path `{name}`"))?,
+        ),
+        None => reference,
+    };
+
+    let len = match path {
+        Some(path) => graph.path_len(path).unwrap_or_default(),
+        None => graph.pangenome_len(),
+    };
+
+    if range.start >= len {
+        let of = path
+            .and_then(|path| graph.path_names.get_by_left(&path))
+            .map_or("the pangenome", |name| name.as_str());
+        bail!("Position is past the end of {of} ({} bp)", len.0);
     }
+
+    let range = range.start..range.end.min(len);
+
+    Ok(GotoTarget::Range { path, range })
 }
 
-pub fn parse_node(text: &str) -> Option<Node> {
-    text.parse::<u32>().map(Node::from).ok()
+/// Replaces the `k`, `m` and `g` suffixes of the numbers in a
+/// position or range with the factor they stand for, so that
+/// `1.5m-2m` becomes `1500000-2000000`.
+fn expand_bp_suffixes(text: &str) -> Option<String> {
+    let numbers = text
+        .split('-')
+        .map(|num| {
+            let num = num.trim();
+            let factor = match num.chars().last()?.to_ascii_lowercase() {
+                'k' => 1e3,
+                'm' => 1e6,
+                'g' => 1e9,
+                _ => return Some(num.to_string()),
+            };
+            let value = num[..num.len() - 1].parse::<f64>().ok()?;
+            (value >= 0.0)
+                .then(|| ((value * factor).round() as u64).to_string())
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(numbers.join("-"))
 }
 
-pub fn parse_pos_range(
-    text: &str,
-) -> Option<(Option<&str>, std::ops::Range<Bp>)> {
-    fn parse_range(text: &str) -> Option<std::ops::Range<Bp>> {
-        if let Some((from, to)) = text.split_once("-") {
-            let from = from.parse::<u64>().ok()?;
-            let to = to.parse::<u64>().ok()?;
-            Some(Bp(from)..Bp(to))
-        } else {
-            let pos = text.parse::<u64>().ok()?;
-            Some(Bp(pos)..Bp(pos + 1))
+/// The text field for `parse_goto`, with the error of the last
+/// attempt shown below it.
+#[derive(Default)]
+pub struct GotoBox {
+    text: String,
+    error: Option<String>,
+}
+
+impl GotoBox {
+    /// Shows the field, and returns the target when one is entered
+    /// with Enter or the button.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        shared: &SharedState,
+    ) -> Option<GotoTarget> {
+        let coords = shared.coords.load();
+
+        let format = crate::gui::coords::PosFormat::load(shared);
+        ui.label(format!("Go to ({})", format.label(&shared.graph)));
+
+        let entry = ui.add_sized(
+            [ui.available_size().x, 0f32],
+            egui::TextEdit::singleline(&mut self.text)
+                .hint_text("chr1:10000-20000, node:123, or 5000"),
+        );
+
+        let submit = ui.button("Go").clicked()
+            || (entry.lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter)));
+
+        if entry.changed() {
+            self.error = None;
         }
+
+        let target = submit
+            .then(|| {
+                let graph = &shared.graph;
+                parse_goto(graph, coords, format.reference, &self.text)
+            })
+            .and_then(|result| {
+                result.map_err(|e| self.error = Some(e.to_string())).ok()
+            });
+
+        if let Some(err) = self.error.as_ref() {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+
+        target
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn goto_parsing() {
+        let gfa = "\
+S\t10\tAAAA
+S\t11\tAA
+S\t12\tAAAAAA
+P\tchr1\t10+,12+\t*
+";
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let coords = CoordSystem::ZeroBased;
+        let parse = |text: &str| parse_goto(&graph, coords, None, text);
+        let chr1 = *graph.path_names.get_by_right("chr1").unwrap();
+
+        assert_eq!(
+            parse("node:1").unwrap(),
+            GotoTarget::Node(Node::from(1u32))
+        );
+        assert_eq!(
+            parse("segment:11").unwrap(),
+            GotoTarget::Node(Node::from(1u32))
+        );
+        assert_eq!(
+            parse("chr1:2-8").unwrap(),
+            GotoTarget::Range {
+                path: Some(chr1),
+                range: Bp(2)..Bp(8)
+            }
+        );
+        // clamped to the end of the path
+        assert_eq!(
+            parse("chr1:8-1,000").unwrap(),
+            GotoTarget::Range {
+                path: Some(chr1),
+                range: Bp(8)..Bp(10)
+            }
+        );
+        assert_eq!(
+            parse("5").unwrap(),
+            GotoTarget::Range {
+                path: None,
+                range: Bp(5)..Bp(6)
+            }
+        );
+
+        assert_eq!(
+            parse("chr1:0.002k-8").unwrap(),
+            GotoTarget::Range {
+                path: Some(chr1),
+                range: Bp(2)..Bp(8)
+            }
+        );
+        assert_eq!(
+            expand_bp_suffixes("1m-2.5M").as_deref(),
+            Some("1000000-2500000")
+        );
+        assert_eq!(expand_bp_suffixes("12k").as_deref(), Some("12000"));
 
-    if let Some((path_name, range_text)) = text.rsplit_once(":") {
-        let range = parse_range(range_text)?;
-        Some((Some(path_name), range))
-    } else {
-        let range = parse_range(text)?;
-        Some((None, range))
+        assert!(parse("").is_err());
+        assert!(parse("chr1:xk-8").is_err());
+        assert!(parse("node:3").is_err());
+        assert!(parse("segment:9").is_err());
+        assert!(parse("node:x").is_err());
+        assert!(parse("chr2:1-5").is_err());
+        assert!(parse("chr1:10-20").is_err());
+        assert!(parse("12").is_err());
+        assert!(parse("5-2").is_err());
     }
 }
@@ -0,0 +1,284 @@
+//! Pangenome depth track: the number of path traversals of each node,
+//! binned over the 1D view range and shown above the path slots.
+
+use std::ops::Range;
+
+use tokio::task::JoinHandle;
+use waragraph_core::graph::{Bp, Node, PathIndex};
+
+use crate::app::SharedState;
+
+use super::software::SoftwareRender1D;
+use super::transform::BpScreenMap;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DepthTrackMode {
+    Hidden,
+    #[default]
+    Bars,
+    Heatmap,
+}
+
+/// Mean node depth in each bin, weighted by the number of base pairs
+/// of each node in the bin.
+#[derive(Debug, Clone)]
+pub struct DepthBins {
+    pub range: Range<u64>,
+    pub values: Vec<f32>,
+    /// Max node depth in the graph, used to scale the bars
+    pub max_depth: f32,
+}
+
+#[derive(Default)]
+pub struct DepthTrack {
+    bins: Option<DepthBins>,
+    task: Option<JoinHandle<Option<DepthBins>>>,
+}
+
+impl DepthTrack {
+    /// Picks up finished results, and starts recomputing the bins in
+    /// the background if the view range or bin count has changed.
+    pub fn update(
+        &mut self,
+        tokio_rt: &tokio::runtime::Handle,
+        shared: &SharedState,
+        view_range: Range<u64>,
+        bin_count: usize,
+    ) {
+        if self.task.as_ref().map(|t| t.is_finished()).unwrap_or(false) {
+            let task = self.task.take().unwrap();
+            if let Ok(Some(bins)) = tokio_rt.block_on(task) {
+                self.bins = Some(bins);
+            }
+        }
+
+        let up_to_date = self
+            .bins
+            .as_ref()
+            .map(|bins| {
+                bins.range == view_range && bins.values.len() == bin_count
+            })
+            .unwrap_or(false);
+
+        if up_to_date || self.task.is_some() || bin_count == 0 {
+            return;
+        }
+
+        let graph = shared.graph.clone();
+        let data_cache = shared.graph_data_cache.clone();
+
+        self.task = Some(tokio_rt.spawn_blocking(move || {
+            let depth = data_cache.fetch_graph_data_blocking("depth")?;
+            let values = bin_depth(
+                &graph,
+                &depth.node_data,
+                view_range.clone(),
+                bin_count,
+            );
+
+            Some(DepthBins {
+                range: view_range,
+                values,
+                max_depth: depth.stats.max,
+            })
+        }));
+    }
+
+    /// Draws the latest bins into `rect`, shifted and scaled to match
+    /// `view_range` if they were computed for a different range.
+    pub fn shapes(
+        &self,
+        rect: egui::Rect,
+        mode: DepthTrackMode,
+        view_range: Range<u64>,
+    ) -> Vec<egui::Shape> {
+        let mut shapes = vec![egui::Shape::rect_filled(
+            rect,
+            0.0,
+            egui::Color32::from_gray(32),
+        )];
+
+        let Some(bins) = self.bins.as_ref() else {
+            return shapes;
+        };
+
+        let map = BpScreenMap::new(view_range, rect.x_range());
+        let bin_len = (bins.range.end - bins.range.start) as f64
+            / bins.values.len() as f64;
+        let bin_start =
+            |ix: usize| Bp(bins.range.start + (ix as f64 * bin_len) as u64);
+
+        let (scheme_name, color_map) =
+            SoftwareRender1D::default_color_scheme("depth");
+        let scheme = crate::color::builtin_color_schemes()
+            .into_iter()
+            .find_map(|(name, colors)| (name == scheme_name).then_some(colors))
+            .unwrap_or_default();
+
+        for (ix, &v) in bins.values.iter().enumerate() {
+            if v <= 0.0 {
+                continue;
+            }
+
+            let x0 = map.bp_to_x(bin_start(ix)).max(rect.left());
+            let x1 = map.bp_to_x(bin_start(ix + 1)).min(rect.right());
+
+            if x1 <= x0 {
+                continue;
+            }
+
+            let shape = match mode {
+                DepthTrackMode::Hidden => continue,
+                DepthTrackMode::Bars => {
+                    let h =
+                        (v / bins.max_depth.max(1.0)).min(1.0) * rect.height();
+                    let bar = egui::Rect::from_x_y_ranges(
+                        x0..=x1,
+                        (rect.bottom() - h)..=rect.bottom(),
+                    );
+                    egui::Shape::rect_filled(
+                        bar,
+                        0.0,
+                        egui::Color32::from_gray(180),
+                    )
+                }
+                DepthTrackMode::Heatmap => {
+                    let [v0, v1] = color_map.value_range;
+                    let [c0, c1] = color_map.color_range;
+                    let t = c0 + (c1 - c0) * (v - v0) / (v1 - v0);
+                    let [r, g, b, _] = crate::color::color_at(&scheme, t);
+                    let color = egui::Color32::from_rgb(
+                        (r * 255.0) as u8,
+                        (g * 255.0) as u8,
+                        (b * 255.0) as u8,
+                    );
+
+                    let cell =
+                        egui::Rect::from_x_y_ranges(x0..=x1, rect.y_range());
+                    egui::Shape::rect_filled(cell, 0.0, color)
+                }
+            };
+
+            shapes.push(shape);
+        }
+
+        shapes
+    }
+
+    /// The highest node depth in the graph, which the bars are
+    /// scaled to, once the bins have been computed.
+    pub fn max_depth(&self) -> Option<f32> {
+        self.bins.as_ref().map(|bins| bins.max_depth.max(1.0))
+    }
+
+    /// The mean depth at the bp position, from the latest bins.
+    pub fn depth_at(&self, pos: Bp) -> Option<f32> {
+        let bins = self.bins.as_ref()?;
+        if !bins.range.contains(&pos.0) {
+            return None;
+        }
+
+        let len = bins.range.end - bins.range.start;
+        let ix = (pos.0 - bins.range.start) * bins.values.len() as u64 / len;
+        bins.values.get(ix as usize).copied()
+    }
+}
+
+/// Splits `range` into `bin_count` equal bins, returning the mean of
+/// `node_depth` in each, weighted by the overlap of each node with
+/// the bin.
+pub fn bin_depth(
+    graph: &PathIndex,
+    node_depth: &[f32],
+    range: Range<u64>,
+    bin_count: usize,
+) -> Vec<f32> {
+    let mut sums = vec![0f64; bin_count];
+
+    let len = range.end.saturating_sub(range.start);
+    if len == 0 || bin_count == 0 {
+        return vec![0.0; bin_count];
+    }
+
+    let bin_len = len as f64 / bin_count as f64;
+
+    let first = graph
+        .node_at_pangenome_pos(Bp(range.start))
+        .map(|n| n.ix())
+        .unwrap_or(0);
+
+    for ix in first..graph.node_count {
+        let (offset, node_len) =
+            graph.node_offset_length(Node::from(ix as u32));
+
+        if offset.0 >= range.end {
+            break;
+        }
+
+        let start = offset.0.max(range.start);
+        let end = (offset.0 + node_len.0).min(range.end);
+
+        if end <= start {
+            continue;
+        }
+
+        let depth = node_depth.get(ix).copied().unwrap_or_default() as f64;
+
+        let x0 = (start - range.start) as f64;
+        let x1 = (end - range.start) as f64;
+
+        let b0 = (x0 / bin_len) as usize;
+        let b1 = ((x1 / bin_len) as usize).min(bin_count - 1);
+
+        for (b, sum) in sums.iter_mut().enumerate().take(b1 + 1).skip(b0) {
+            let overlap =
+                x1.min((b + 1) as f64 * bin_len) - x0.max(b as f64 * bin_len);
+            if overlap > 0.0 {
+                *sum += depth * overlap;
+            }
+        }
+    }
+
+    sums.into_iter().map(|s| (s / bin_len) as f32).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_bins_are_length_weighted_means() {
+        let gfa_path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../test/data/A-3105.fa.353ea42.34ee7b1.1576367.smooth.fix.gfa"
+        );
+        let graph = PathIndex::from_gfa(gfa_path).unwrap();
+
+        let mut depth = vec![0f32; graph.node_count];
+        for steps in graph.path_steps.iter() {
+            for step in steps {
+                depth[step.node().ix()] += 1.0;
+            }
+        }
+
+        let total_len = graph.pangenome_len().0;
+
+        let expected = (0..graph.node_count)
+            .map(|ix| {
+                let len = graph.node_length(Node::from(ix as u32)).0;
+                depth[ix] as f64 * len as f64
+            })
+            .sum::<f64>()
+            / total_len as f64;
+
+        let whole = bin_depth(&graph, &depth, 0..total_len, 1);
+        assert!((whole[0] as f64 - expected).abs() < 1e-3 * expected);
+
+        // the bins of a split range average to the whole
+        let halves = bin_depth(&graph, &depth, 0..total_len, 2);
+        let mean = (halves[0] + halves[1]) / 2.0;
+        assert!((mean - whole[0]).abs() < 1e-3 * whole[0]);
+
+        assert_eq!(bin_depth(&graph, &depth, 10..10, 4), vec![0.0; 4]);
+    }
+}
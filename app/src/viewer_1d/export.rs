@@ -0,0 +1,467 @@
+//! Writing the 1D view to image files. The path data is written as a
+//! PNG, and the labels and annotations that are drawn on top of it by
+//! egui can be written as an SVG that references the PNG, so that
+//! they stay editable in figures.
+
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::RwLock;
+use waragraph_core::export::html_escape;
+
+use crate::app::export_paths::ExportPaths;
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::SharedState;
+
+use super::control::Msg;
+
+/// Writes 8-bit RGB or RGBA pixels, depending on `channels`, as PNG.
+pub fn write_png(
+    path: impl AsRef<Path>,
+    width: usize,
+    height: usize,
+    channels: usize,
+    pixels: &[u8],
+) -> Result<()> {
+    anyhow::ensure!(
+        pixels.len() == width * height * channels,
+        "Pixel buffer doesn't match image size"
+    );
+
+    let color_type = match channels {
+        3 => png::ColorType::Rgb,
+        4 => png::ColorType::Rgba,
+        _ => anyhow::bail!("Unsupported channel count {channels}"),
+    };
+
+    let file = std::fs::File::create(path)?;
+    let out = std::io::BufWriter::new(file);
+
+    let mut encoder = png::Encoder::new(out, width as u32, height as u32);
+    encoder.set_color(color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+
+    Ok(())
+}
+
+/// Writes RGB8 pixels as PNG if the file name ends with `.png`, and
+/// as PPM otherwise.
+pub fn write_rgb_image(
+    path: impl AsRef<Path>,
+    width: usize,
+    height: usize,
+    pixels: &[u8],
+) -> Result<()> {
+    let path = path.as_ref();
+    if is_png_path(path) {
+        write_png(path, width, height, 3, pixels)
+    } else {
+        super::software::write_ppm(path, width, height, pixels)
+    }
+}
+
+pub fn is_png_path(path: &Path) -> bool {
+    path.extension().map(|ext| ext == "png").unwrap_or(false)
+}
+
+/// The SVG overlay is written next to the image.
+pub fn svg_path_for(image: &Path) -> PathBuf {
+    image.with_extension("svg")
+}
+
+pub struct SvgLabel {
+    pub text: String,
+    /// Position of the top left corner of the text
+    pub pos: [f32; 2],
+    pub font_size: f32,
+    pub color: [u8; 3],
+}
+
+pub struct SvgSpan {
+    pub x: std::ops::Range<f32>,
+    pub y: std::ops::Range<f32>,
+    pub color: [u8; 3],
+    pub label: Option<String>,
+}
+
+/// Labels and annotation spans to draw on top of an image, in the
+/// image's pixel coordinates.
+pub struct SvgOverlay {
+    pub width: f32,
+    pub height: f32,
+    /// Referenced by relative path, if set
+    pub image: Option<PathBuf>,
+    /// Where the image is placed in the SVG
+    pub image_offset: [f32; 2],
+    pub image_size: [f32; 2],
+
+    pub labels: Vec<SvgLabel>,
+    pub spans: Vec<SvgSpan>,
+}
+
+impl SvgOverlay {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            image: None,
+            image_offset: [0.0, 0.0],
+            image_size: [width, height],
+            labels: Vec::new(),
+            spans: Vec::new(),
+        }
+    }
+
+    pub fn write(&self, mut out: impl Write) -> std::io::Result<()> {
+        let (w, h) = (self.width, self.height);
+
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+             xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+             width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">"
+        )?;
+
+        if let Some(image) = self.image.as_ref() {
+            let [x, y] = self.image_offset;
+            let [iw, ih] = self.image_size;
+            writeln!(
+                out,
+                "<image x=\"{x}\" y=\"{y}\" width=\"{iw}\" height=\"{ih}\" \
+                 preserveAspectRatio=\"none\" \
+                 style=\"image-rendering: pixelated\" xlink:href=\"{}\"/>",
+                html_escape(&image.to_string_lossy())
+            )?;
+        }
+
+        for span in &self.spans {
+            let [r, g, b] = span.color;
+            let (x, y) = (span.x.start, span.y.start);
+            let sw = span.x.end - span.x.start;
+            let sh = span.y.end - span.y.start;
+
+            writeln!(
+                out,
+                "<rect x=\"{x}\" y=\"{y}\" width=\"{sw}\" height=\"{sh}\" \
+                 fill=\"rgb({r},{g},{b})\" fill-opacity=\"0.5\"/>"
+            )?;
+
+            if let Some(label) = span.label.as_ref() {
+                writeln!(
+                    out,
+                    "<text x=\"{x}\" y=\"{}\" font-family=\"monospace\" \
+                     font-size=\"{}\" fill=\"rgb({r},{g},{b})\">{}</text>",
+                    span.y.end,
+                    sh.max(8.0),
+                    html_escape(label)
+                )?;
+            }
+        }
+
+        for label in &self.labels {
+            let [x, y] = label.pos;
+            let [r, g, b] = label.color;
+            writeln!(
+                out,
+                "<text x=\"{x}\" y=\"{y}\" dominant-baseline=\"hanging\" \
+                 font-family=\"monospace\" font-size=\"{}\" \
+                 fill=\"rgb({r},{g},{b})\">{}</text>",
+                label.font_size,
+                html_escape(&label.text)
+            )?;
+        }
+
+        writeln!(out, "</svg>")?;
+
+        Ok(())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = std::fs::File::create(path)?;
+        let mut out = std::io::BufWriter::new(file);
+        self.write(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Copies a texture to host memory as tightly packed RGBA8 pixels.
+/// Only 8-bit RGBA and BGRA formats are supported.
+pub fn read_texture_rgba(
+    state: &raving_wgpu::State,
+    texture: &wgpu::Texture,
+    dims: [u32; 2],
+    format: wgpu::TextureFormat,
+) -> Result<Vec<u8>> {
+    use wgpu::TextureFormat as Format;
+
+    let bgra = match format {
+        Format::Rgba8Unorm | Format::Rgba8UnormSrgb => false,
+        Format::Bgra8Unorm | Format::Bgra8UnormSrgb => true,
+        _ => anyhow::bail!("Can't export texture with format {format:?}"),
+    };
+
+    let [width, height] = dims;
+
+    // rows in the buffer must be aligned to 256 bytes
+    let row_size = width * 4;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let aligned_row_size = ((row_size + align - 1) / align) * align;
+
+    let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Image Export Buffer"),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        size: (aligned_row_size * height) as u64,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder =
+        state
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Image Export"),
+            });
+
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                bytes_per_row: Some(aligned_row_size),
+                ..wgpu::ImageDataLayout::default()
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    state.queue.submit([encoder.finish()]);
+
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, Result::unwrap);
+    state.device.poll(wgpu::Maintain::Wait);
+
+    let mut pixels = Vec::with_capacity((row_size * height) as usize);
+
+    {
+        let data = buffer.slice(..).get_mapped_range();
+
+        for row in data.chunks_exact(aligned_row_size as usize) {
+            pixels.extend_from_slice(&row[..row_size as usize]);
+        }
+    }
+
+    buffer.unmap();
+
+    if bgra {
+        for px in pixels.chunks_exact_mut(4) {
+            px.swap(0, 2);
+        }
+    }
+
+    Ok(pixels)
+}
+
+/// Draws the labels and annotation spans of the overlay onto the
+/// texture behind `target`, with egui. The text is rasterized at the
+/// texture's resolution rather than scaled, so that it stays sharp in
+/// screenshots larger than the window.
+pub fn draw_overlay(
+    state: &raving_wgpu::State,
+    target: &wgpu::TextureView,
+    format: wgpu::TextureFormat,
+    dims: [u32; 2],
+    overlay: &SvgOverlay,
+) {
+    use egui::epaint::{tessellator, ClippedShape, TessellationOptions};
+
+    let max_texture_side =
+        state.device.limits().max_texture_dimension_2d as usize;
+    // one point per pixel, as the overlay is in pixels
+    let fonts = egui::text::Fonts::new(
+        1.0,
+        max_texture_side,
+        egui::FontDefinitions::default(),
+    );
+
+    let color = |[r, g, b]: [u8; 3]| egui::Color32::from_rgb(r, g, b);
+    let clip = egui::Rect::from_min_size(
+        egui::Pos2::ZERO,
+        egui::vec2(dims[0] as f32, dims[1] as f32),
+    );
+
+    let mut shapes = Vec::new();
+
+    for span in &overlay.spans {
+        let rect = egui::Rect::from_x_y_ranges(
+            span.x.start..=span.x.end,
+            span.y.start..=span.y.end,
+        );
+        let fill = color(span.color).linear_multiply(0.5);
+        shapes.push(egui::Shape::rect_filled(rect, 0.0, fill));
+
+        if let Some(label) = span.label.as_ref() {
+            let font = egui::FontId::monospace(rect.height().max(8.0));
+            let galley =
+                fonts.layout_no_wrap(label.clone(), font, color(span.color));
+            let pos = egui::pos2(rect.left(), rect.bottom() - galley.size().y);
+            shapes.push(egui::Shape::galley(pos, galley));
+        }
+    }
+
+    for label in &overlay.labels {
+        let font = egui::FontId::monospace(label.font_size);
+        let galley =
+            fonts.layout_no_wrap(label.text.clone(), font, color(label.color));
+        shapes.push(egui::Shape::galley(label.pos.into(), galley));
+    }
+
+    let shapes = shapes
+        .into_iter()
+        .map(|shape| ClippedShape(clip, shape))
+        .collect();
+
+    let prepared_discs = fonts.texture_atlas().lock().prepared_discs();
+    let primitives = tessellator::tessellate_shapes(
+        1.0,
+        TessellationOptions::default(),
+        fonts.font_image_size(),
+        prepared_discs,
+        shapes,
+    );
+
+    let mut renderer = egui_wgpu::Renderer::new(&state.device, format, None, 1);
+
+    if let Some(delta) = fonts.font_image_delta() {
+        renderer.update_texture(
+            &state.device,
+            &state.queue,
+            egui::TextureId::default(),
+            &delta,
+        );
+    }
+
+    let screen = egui_wgpu::renderer::ScreenDescriptor {
+        size_in_pixels: dims,
+        pixels_per_point: 1.0,
+    };
+
+    let mut encoder =
+        state
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Image Export Overlay"),
+            });
+
+    let buffer_cmds = renderer.update_buffers(
+        &state.device,
+        &state.queue,
+        &mut encoder,
+        &primitives,
+        &screen,
+    );
+
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Image Export Overlay"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        renderer.render(&mut pass, &primitives, &screen);
+    }
+
+    state
+        .queue
+        .submit(buffer_cmds.into_iter().chain([encoder.finish()]));
+}
+
+/// Settings window panel for saving the current 1D view as PNG, with
+/// the labels in an SVG next to it.
+pub struct ExportImageWidget {
+    export_paths: Arc<RwLock<ExportPaths>>,
+    msg_tx: crossbeam::channel::Sender<Msg>,
+    path_recv: Option<tokio::sync::oneshot::Receiver<PathBuf>>,
+}
+
+impl ExportImageWidget {
+    pub fn new(
+        shared: &SharedState,
+        msg_tx: crossbeam::channel::Sender<Msg>,
+    ) -> Self {
+        Self {
+            export_paths: shared.export_paths.clone(),
+            msg_tx,
+            path_recv: None,
+        }
+    }
+}
+
+impl SettingsWidget for ExportImageWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        use tokio::sync::oneshot::error::TryRecvError;
+
+        if let Some(mut recv) = self.path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    let path = if is_png_path(&path) {
+                        path
+                    } else {
+                        path.with_extension("png")
+                    };
+                    let _ = self.msg_tx.send(Msg::ExportImage(path));
+                }
+                Err(TryRecvError::Empty) => self.path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        let resp = ui.vertical(|ui| {
+            ui.label(
+                "Saves the path data as PNG, and the path names and \
+                 annotations as SVG next to it",
+            );
+
+            if ui.button("Export view").clicked() {
+                let has_dir = {
+                    let paths = self.export_paths.blocking_read();
+                    paths.image_directory.is_some() || paths.directory.is_some()
+                };
+
+                if has_dir {
+                    let _ = self.msg_tx.send(Msg::ExportImageAuto);
+                } else {
+                    let id = egui::Id::new("ExportImageWidget");
+                    let recv = settings_ctx.save_file_oneshot(id, None);
+                    self.path_recv = Some(recv);
+                }
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
@@ -0,0 +1,430 @@
+//! Samples path data into the 1D viewer's slot rows with a compute
+//! shader. The nodes of each path, with their pangenome ranges and
+//! data values, are uploaded once per path and data source; after
+//! that, panning and zooming only dispatches the shader, which writes
+//! directly into the slot cache's data buffer.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::Result;
+use bytemuck::Zeroable;
+use tokio::task::JoinHandle;
+use waragraph_core::graph::{Bp, Node, PathId, PathIndex};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use crate::app::resource::GraphDataCache;
+use crate::util::gpu_memory;
+
+use super::cache::{SlotCache, SlotKey};
+use super::view::View1D;
+
+const NODE_BUFFERS_LABEL: &str = "Viewer 1D GPU Sampler Path Nodes";
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// A node of a path, in the layout of `PathNode` in the shader.
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+pub struct PathNodeRecord {
+    pub start: u32,
+    pub end: u32,
+    pub value: f32,
+}
+
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct SampleParams {
+    view_start: u32,
+    bin_size: u32,
+    used_bins: u32,
+    bin_count: u32,
+    row_offset: u32,
+    node_count: u32,
+    _pad: [u32; 2],
+}
+
+struct PathNodeBuffer {
+    buffer: wgpu::Buffer,
+    node_count: u32,
+}
+
+type RecordsTask = JoinHandle<Result<Vec<PathNodeRecord>>>;
+
+pub struct GpuPathSampler {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+
+    path_index: Arc<PathIndex>,
+    data_cache: Arc<GraphDataCache>,
+    /// The data sources that can be sampled on the GPU
+    data_keys: HashSet<String>,
+
+    node_buffers: HashMap<SlotKey, PathNodeBuffer>,
+    uploaded_bytes: u64,
+    pending: HashMap<SlotKey, RecordsTask>,
+    // paths that don't fit in GPU memory, which are sampled on the CPU
+    cpu_fallback: HashSet<SlotKey>,
+}
+
+impl GpuPathSampler {
+    /// Returns `None` if the pangenome is too long for the 32-bit
+    /// positions used by the shader.
+    pub fn new(
+        state: &raving_wgpu::State,
+        path_index: Arc<PathIndex>,
+        data_cache: Arc<GraphDataCache>,
+        data_keys: impl IntoIterator<Item = String>,
+    ) -> Option<Self> {
+        if path_index.pangenome_len().0 > u32::MAX as u64 {
+            log::warn!("Pangenome too long for GPU sampling");
+            return None;
+        }
+
+        let device = &state.device;
+
+        let module =
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Viewer 1D Sample Path Data"),
+                source: wgpu::ShaderSource::Wgsl(
+                    include_str!(concat!(
+                        env!("CARGO_MANIFEST_DIR"),
+                        "/shaders/sample_path_data.wgsl"
+                    ))
+                    .into(),
+                ),
+            });
+
+        let storage =
+            |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            };
+
+        let bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Viewer 1D Sample Path Data"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    storage(1, true),
+                    storage(2, false),
+                ],
+            });
+
+        let layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Viewer 1D Sample Path Data"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Viewer 1D Sample Path Data"),
+                layout: Some(&layout),
+                module: &module,
+                entry_point: "main",
+            });
+
+        Some(Self {
+            pipeline,
+            bind_group_layout,
+
+            path_index,
+            data_cache,
+            data_keys: data_keys.into_iter().collect(),
+
+            node_buffers: HashMap::default(),
+            uploaded_bytes: 0,
+            pending: HashMap::default(),
+            cpu_fallback: HashSet::default(),
+        })
+    }
+
+    pub fn supports(&self, data_key: &str) -> bool {
+        self.data_keys.contains(data_key)
+    }
+
+    /// Fills the rows of the slots that aren't up to date for the
+    /// current view. Paths whose nodes haven't been uploaded yet are
+    /// skipped until they are. Returns the paths that have to be
+    /// sampled on the CPU instead.
+    pub fn sample(
+        &mut self,
+        state: &raving_wgpu::State,
+        rt: &tokio::runtime::Handle,
+        slot_cache: &mut SlotCache,
+        view: &View1D,
+        data_key: &str,
+        paths: impl IntoIterator<Item = PathId>,
+    ) -> Vec<PathId> {
+        self.upload_finished(state, rt);
+
+        let (gpu_paths, cpu_paths): (Vec<_>, Vec<_>) =
+            paths.into_iter().partition(|&path| {
+                !self.cpu_fallback.contains(&(path, data_key.to_string()))
+            });
+
+        let slots = slot_cache.slots_to_sample(view, data_key, gpu_paths);
+        if slots.is_empty() {
+            return cpu_paths;
+        }
+
+        let view_range = view.range().clone();
+        let current_view = [Bp(view_range.start), Bp(view_range.end)];
+
+        let bin_count = slot_cache.bin_count();
+        let view_len = view_range.end - view_range.start;
+        // as in the CPU sampler, the bins are whole bp, and only as
+        // many as there are bp in the view are used
+        let used_bins = (view_len as usize).min(bin_count).max(1);
+        let bin_size = view_len / used_bins as u64;
+
+        let mut encoder = state.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Viewer 1D Sample Path Data"),
+            },
+        );
+
+        let mut sampled = Vec::new();
+
+        for (slot_key, row) in slots {
+            let Some(nodes) = self.node_buffers.get(&slot_key) else {
+                self.spawn_records_task(rt, &slot_key);
+                continue;
+            };
+
+            let params = SampleParams {
+                view_start: view_range.start as u32,
+                bin_size: bin_size as u32,
+                used_bins: used_bins as u32,
+                bin_count: bin_count as u32,
+                row_offset: (row * bin_count) as u32,
+                node_count: nodes.node_count,
+                _pad: [0; 2],
+            };
+
+            let params_buffer =
+                state.device.create_buffer_init(&BufferInitDescriptor {
+                    label: Some("Viewer 1D Sample Path Data Params"),
+                    contents: bytemuck::cast_slice(&[params]),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                });
+
+            let bind_group =
+                state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Viewer 1D Sample Path Data"),
+                    layout: &self.bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: params_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: nodes.buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: slot_cache
+                                .data_buffer
+                                .buffer
+                                .as_entire_binding(),
+                        },
+                    ],
+                });
+
+            {
+                let mut pass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                        label: Some("Viewer 1D Sample Path Data"),
+                    });
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                let groups =
+                    (bin_count as u32 + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+                pass.dispatch_workgroups(groups, 1, 1);
+            }
+
+            sampled.push(slot_key);
+        }
+
+        state.queue.submit(Some(encoder.finish()));
+
+        for slot_key in sampled {
+            slot_cache.mark_sampled(&slot_key, current_view);
+        }
+
+        cpu_paths
+    }
+
+    fn spawn_records_task(
+        &mut self,
+        rt: &tokio::runtime::Handle,
+        slot_key: &SlotKey,
+    ) {
+        if self.pending.contains_key(slot_key) {
+            return;
+        }
+
+        let (path, data_key) = slot_key.clone();
+        let path_index = self.path_index.clone();
+        let data_cache = self.data_cache.clone();
+
+        let task = rt.spawn(async move {
+            let data = data_cache.fetch_path_data(&data_key, path).await?;
+            let records = tokio::task::spawn_blocking(move || {
+                path_node_records(&path_index, path, &data.path_data)
+            })
+            .await?;
+            Ok(records)
+        });
+
+        self.pending.insert(slot_key.clone(), task);
+    }
+
+    fn upload_finished(
+        &mut self,
+        state: &raving_wgpu::State,
+        rt: &tokio::runtime::Handle,
+    ) {
+        let finished = self
+            .pending
+            .iter()
+            .filter(|(_, task)| task.is_finished())
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+
+        for slot_key in finished {
+            let Some(task) = self.pending.remove(&slot_key) else {
+                continue;
+            };
+
+            let records = match rt.block_on(task) {
+                Ok(Ok(records)) => records,
+                Ok(Err(e)) => {
+                    log::error!("Error fetching path data for GPU: {e:?}");
+                    self.cpu_fallback.insert(slot_key);
+                    continue;
+                }
+                Err(e) => {
+                    log::error!("Error fetching path data for GPU: {e:?}");
+                    continue;
+                }
+            };
+
+            let node_count = records.len() as u32;
+
+            // storage buffers can't be empty
+            let contents = if records.is_empty() {
+                vec![PathNodeRecord::zeroed()]
+            } else {
+                records
+            };
+            let bytes: &[u8] = bytemuck::cast_slice(&contents);
+            let size = bytes.len() as u64;
+
+            if !gpu_memory::storage_buffer_fits(
+                &state.device,
+                NODE_BUFFERS_LABEL,
+                self.uploaded_bytes + size,
+            ) {
+                log::warn!(
+                    "Path {} nodes don't fit in GPU memory, sampling on the CPU",
+                    slot_key.0.ix()
+                );
+                self.cpu_fallback.insert(slot_key);
+                continue;
+            }
+
+            let buffer =
+                state.device.create_buffer_init(&BufferInitDescriptor {
+                    label: Some(NODE_BUFFERS_LABEL),
+                    contents: bytes,
+                    usage: wgpu::BufferUsages::STORAGE,
+                });
+
+            self.uploaded_bytes += size;
+            gpu_memory::record_buffer(NODE_BUFFERS_LABEL, self.uploaded_bytes);
+
+            self.node_buffers
+                .insert(slot_key, PathNodeBuffer { buffer, node_count });
+        }
+    }
+}
+
+/// The nodes of the path in pangenome order, with the path's data
+/// value for each; `data` has one value per node in the path's node
+/// set, in the same order.
+pub fn path_node_records(
+    graph: &PathIndex,
+    path: PathId,
+    data: &[f32],
+) -> Vec<PathNodeRecord> {
+    let path_nodes = &graph.path_node_sets[path.ix()];
+
+    path_nodes
+        .iter()
+        .zip(data)
+        .map(|(node_ix, &value)| {
+            let node = Node::from(node_ix as usize);
+            let (offset, len) = graph.node_offset_length(node);
+            PathNodeRecord {
+                start: offset.0 as u32,
+                end: (offset.0 + len.0) as u32,
+                value,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GFA_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../test/data/A-3105.fa.353ea42.34ee7b1.1576367.smooth.fix.gfa"
+    );
+
+    #[test]
+    fn path_node_records_in_order() {
+        let graph = PathIndex::from_gfa(GFA_PATH).unwrap();
+        let path = PathId::from(0u32);
+
+        let node_count = graph.path_node_sets[path.ix()].len() as usize;
+        let data = (0..node_count).map(|i| i as f32).collect::<Vec<_>>();
+
+        let records = path_node_records(&graph, path, &data);
+        assert_eq!(records.len(), node_count);
+
+        for (ix, pair) in records.windows(2).enumerate() {
+            assert!(pair[0].start < pair[0].end);
+            assert!(pair[0].end <= pair[1].start);
+            assert_eq!(pair[0].value, ix as f32);
+        }
+
+        let total_len: u64 =
+            records.iter().map(|r| (r.end - r.start) as u64).sum();
+        let path_nodes_len: u64 = graph.path_node_sets[path.ix()]
+            .iter()
+            .map(|n| graph.node_length(Node::from(n as usize)).0)
+            .sum();
+        assert_eq!(total_len, path_nodes_len);
+    }
+}
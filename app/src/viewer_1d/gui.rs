@@ -1,20 +1,197 @@
 use taffy::error::TaffyError;
-use waragraph_core::graph::{Bp, PathId};
+use waragraph_core::graph::{Bp, PathId, PathIndex};
 
+use crate::color::ColorMap;
+use crate::gui::coords::PosFormat;
 use crate::gui::FlexLayout;
 
 use super::annotations::AnnotSlotId;
+use super::transform::BpScreenMap;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) enum SlotElem {
     Empty,
     ViewRange,
+    Ruler,
     PathData { path_id: PathId, data_id: String },
     PathName { path_id: PathId },
     Annotations { annotation_slot_id: AnnotSlotId },
+    DepthTrackName,
+    DepthTrack,
+    OverlayTrackName,
+    OverlayTrack,
+    ValueAxis,
     // Annotations { path: PathId, annotation_id: String },
 }
 
+/// The exact value of the hovered node in the hovered path slot's
+/// data, rather than the binned value drawn there.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct HoveredValue {
+    pub data_key: String,
+    pub value: f32,
+}
+
+/// Formats a data value with as few decimals as it needs, up to three.
+pub(super) fn format_value(value: f32) -> String {
+    if value.fract() == 0.0 {
+        format!("{value:.0}")
+    } else {
+        let text = format!("{value:.3}");
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
+
+/// Lists the overlaid tracks in their line colors, with the value
+/// each is scaled to.
+pub(super) fn overlay_legend_shapes(
+    fonts: &egui::text::Fonts,
+    rect: egui::Rect,
+    keys: &[String],
+    maxes: &[Option<f32>],
+) -> Vec<egui::Shape> {
+    let font_id = egui::FontId::monospace(11.0);
+    let row_height = rect.height() / keys.len().max(1) as f32;
+
+    keys.iter()
+        .zip(maxes)
+        .zip(super::overlay_track::OVERLAY_COLORS)
+        .enumerate()
+        .map(|(ix, ((key, max), color))| {
+            let text = match max {
+                Some(max) => format!("{key} (0-{})", format_value(*max)),
+                None => key.clone(),
+            };
+            let y = rect.top() + (ix as f32 + 0.5) * row_height;
+            egui::Shape::text(
+                fonts,
+                egui::pos2(rect.left(), y),
+                egui::Align2::LEFT_CENTER,
+                text,
+                font_id.clone(),
+                color,
+            )
+        })
+        .collect()
+}
+
+/// Draws a value axis for the quantitative data in the path slots:
+/// the color scheme as a gradient, labeled with the values mapped to
+/// its ends.
+pub(super) fn value_axis_shapes(
+    fonts: &egui::text::Fonts,
+    rect: egui::Rect,
+    data_key: &str,
+    colors: &[[f32; 4]],
+    color_map: ColorMap,
+) -> Vec<egui::Shape> {
+    let mut shapes = Vec::new();
+
+    let rect = rect.shrink2(egui::vec2(4.0, 1.0));
+    let bar =
+        egui::Rect::from_min_size(rect.min, egui::vec2(rect.width(), 8.0));
+
+    let [c0, c1] = color_map.color_range;
+    let segments = 32;
+    let seg_width = bar.width() / segments as f32;
+
+    for i in 0..segments {
+        let t = c0 + (c1 - c0) * (i as f32 + 0.5) / segments as f32;
+        let [r, g, b, _] = crate::color::color_at(colors, t);
+        let color = egui::Color32::from_rgb(
+            (r * 255.0) as u8,
+            (g * 255.0) as u8,
+            (b * 255.0) as u8,
+        );
+
+        let x0 = bar.left() + i as f32 * seg_width;
+        let seg =
+            egui::Rect::from_x_y_ranges(x0..=(x0 + seg_width), bar.y_range());
+        shapes.push(egui::Shape::rect_filled(seg, 0.0, color));
+    }
+
+    let font_id = egui::FontId::monospace(11.0);
+    let color = egui::Color32::from_gray(200);
+    let [v0, v1] = color_map.value_range;
+
+    for (pos, anchor, text) in [
+        (
+            rect.left_bottom(),
+            egui::Align2::LEFT_BOTTOM,
+            format_value(v0),
+        ),
+        (
+            rect.center_bottom(),
+            egui::Align2::CENTER_BOTTOM,
+            data_key.to_string(),
+        ),
+        (
+            rect.right_bottom(),
+            egui::Align2::RIGHT_BOTTOM,
+            format_value(v1),
+        ),
+    ] {
+        shapes.push(egui::Shape::text(
+            fonts,
+            pos,
+            anchor,
+            text,
+            font_id.clone(),
+            color,
+        ));
+    }
+
+    shapes
+}
+
+/// Draws `values`, in `0.0..=1.0`, as a bar chart filling `rect`,
+/// with `highlight` (normalized to the width of the rect) outlined.
+pub(super) fn sparkline_shapes(
+    rect: egui::Rect,
+    values: &[f32],
+    color: egui::Color32,
+    highlight: Option<std::ops::Range<f32>>,
+) -> Vec<egui::Shape> {
+    let mut shapes = Vec::with_capacity(values.len() + 2);
+
+    shapes.push(egui::Shape::rect_filled(
+        rect,
+        0.0,
+        egui::Color32::from_gray(32),
+    ));
+
+    let bar_width = rect.width() / values.len().max(1) as f32;
+
+    for (ix, &v) in values.iter().enumerate() {
+        if v <= 0.0 {
+            continue;
+        }
+
+        let x0 = rect.left() + ix as f32 * bar_width;
+        let y0 = rect.bottom() - v * rect.height();
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(x0, y0),
+            egui::pos2(x0 + bar_width, rect.bottom()),
+        );
+        shapes.push(egui::Shape::rect_filled(bar, 0.0, color));
+    }
+
+    if let Some(range) = highlight {
+        let x0 = rect.left() + range.start.clamp(0.0, 1.0) * rect.width();
+        let x1 = rect.left() + range.end.clamp(0.0, 1.0) * rect.width();
+        // keep the outline visible when zoomed in
+        let x1 = x1.max(x0 + 1.0);
+        let view_rect = egui::Rect::from_x_y_ranges(x0..=x1, rect.y_range());
+        shapes.push(egui::Shape::rect_stroke(
+            view_rect,
+            0.0,
+            egui::Stroke::new(1.0, egui::Color32::YELLOW),
+        ));
+    }
+
+    shapes
+}
+
 pub(super) fn fill_h_range_of_rect(
     color: impl Into<egui::Color32>,
     rect: egui::Rect,
@@ -54,12 +231,16 @@ pub(super) fn fill_h_range_of_rect(
     */
 }
 
+/// The view range, and the position under the cursor, as text in the
+/// given coordinate convention, which is named after the range start.
 pub(super) fn view_range_shapes(
     fonts: &egui::text::Fonts,
     rect: egui::Rect,
     left: Bp,
     right: Bp,
     ruler: Option<Bp>,
+    graph: &PathIndex,
+    fmt: PosFormat,
 ) -> impl Iterator<Item = egui::Shape> {
     let center = rect.center();
 
@@ -79,7 +260,7 @@ pub(super) fn view_range_shapes(
         &fonts,
         left_pos,
         egui::Align2::LEFT_CENTER,
-        left.0,
+        format!("{} ({})", fmt.pos(graph, left), fmt.label(graph)),
         font_id.clone(),
         color,
     );
@@ -88,27 +269,21 @@ pub(super) fn view_range_shapes(
         &fonts,
         right_pos,
         egui::Align2::RIGHT_CENTER,
-        right.0,
+        fmt.end(graph, right),
         font_id.clone(),
         color,
     );
 
     let ruler_shapes = ruler.map(|r| {
-        let rf = r.0 as f64;
-        let rl = left.0 as f64;
-        let rr = right.0 as f64;
-
-        let t = (rf - rl) / (rr - rl);
-        let w = (r_right as f64) - (r_left as f64);
-        let x = (t * w) as f32;
+        let x = BpScreenMap::new(left.0..right.0, r_left..=r_right).bp_to_x(r);
 
-        let rt_pos = egui::pos2(r_left + x + 4.0, r_mid_y);
+        let rt_pos = egui::pos2(x + 4.0, r_mid_y);
 
         let ruler_text = egui::Shape::text(
             &fonts,
             rt_pos,
             egui::Align2::LEFT_CENTER,
-            r.0,
+            fmt.pos(graph, r),
             font_id,
             color,
         );
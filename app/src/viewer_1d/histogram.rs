@@ -0,0 +1,336 @@
+//! In-view histogram: the distribution of a graph data track's values
+//! (path depth by default) over the 1D view range, shown in the side
+//! panel and updated as the view moves. Brushing a range of values on
+//! the histogram highlights the parts of the view with values in it.
+
+use std::ops::{Range, RangeInclusive};
+
+use tokio::task::JoinHandle;
+use waragraph_core::graph::Bp;
+
+use crate::app::SharedState;
+
+use super::depth_track::bin_depth;
+use super::transform::BpScreenMap;
+
+const DEFAULT_KEY: &str = "depth";
+
+const BUCKET_COUNT: usize = 32;
+
+const HISTOGRAM_HEIGHT: f32 = 80.0;
+
+/// The mean value of a track in each of the equal-length bins of a
+/// view range.
+#[derive(Debug, Clone)]
+pub struct ViewValues {
+    pub key: String,
+    pub range: Range<u64>,
+    pub bins: Vec<f32>,
+    /// The values of the track over the whole graph, which the
+    /// histogram spans, so that it stays put while navigating
+    pub min: f32,
+    pub max: f32,
+}
+
+impl ViewValues {
+    fn bucket(&self, value: f32, bucket_count: usize) -> usize {
+        let span = self.max - self.min;
+        if span <= 0.0 || !value.is_finite() {
+            return 0;
+        }
+        let t = (value - self.min) / span;
+        ((t * bucket_count as f32) as usize).min(bucket_count - 1)
+    }
+
+    /// The number of bins with values in each of `bucket_count` equal
+    /// buckets between `min` and `max`.
+    pub fn histogram(&self, bucket_count: usize) -> Vec<usize> {
+        let mut counts = vec![0; bucket_count];
+        if bucket_count == 0 {
+            return counts;
+        }
+        for &v in self.bins.iter() {
+            counts[self.bucket(v, bucket_count)] += 1;
+        }
+        counts
+    }
+
+    /// The pangenome ranges of the runs of bins with values in
+    /// `values`.
+    pub fn matching_ranges(
+        &self,
+        values: &RangeInclusive<f32>,
+    ) -> Vec<Range<Bp>> {
+        let bin_len = (self.range.end - self.range.start) as f64
+            / self.bins.len().max(1) as f64;
+        let bin_start =
+            |ix: usize| Bp(self.range.start + (ix as f64 * bin_len) as u64);
+
+        let mut ranges: Vec<Range<Bp>> = Vec::new();
+        for (ix, v) in self.bins.iter().enumerate() {
+            if !values.contains(v) {
+                continue;
+            }
+            let range = bin_start(ix)..bin_start(ix + 1);
+            match ranges.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => ranges.push(range),
+            }
+        }
+        ranges
+    }
+}
+
+pub struct ViewHistogram {
+    key: String,
+    values: Option<ViewValues>,
+    task: Option<JoinHandle<Option<ViewValues>>>,
+
+    brush: Option<RangeInclusive<f32>>,
+    brush_start: Option<f32>,
+}
+
+impl std::default::Default for ViewHistogram {
+    fn default() -> Self {
+        Self {
+            key: DEFAULT_KEY.to_string(),
+            values: None,
+            task: None,
+            brush: None,
+            brush_start: None,
+        }
+    }
+}
+
+impl ViewHistogram {
+    /// Picks up finished results, and starts recomputing the values
+    /// in the background if the view range, bin count, or track have
+    /// changed.
+    pub fn update(
+        &mut self,
+        tokio_rt: &tokio::runtime::Handle,
+        shared: &SharedState,
+        view_range: Range<u64>,
+        bin_count: usize,
+    ) {
+        if self.task.as_ref().map(|t| t.is_finished()).unwrap_or(false) {
+            let task = self.task.take().unwrap();
+            if let Ok(Some(values)) = tokio_rt.block_on(task) {
+                self.values = Some(values);
+            }
+        }
+
+        let up_to_date = self.values.as_ref().is_some_and(|values| {
+            values.key == self.key
+                && values.range == view_range
+                && values.bins.len() == bin_count
+        });
+
+        if up_to_date || self.task.is_some() || bin_count == 0 {
+            return;
+        }
+
+        let graph = shared.graph.clone();
+        let data_cache = shared.graph_data_cache.clone();
+        let key = self.key.clone();
+
+        self.task = Some(tokio_rt.spawn_blocking(move || {
+            let data = data_cache.fetch_graph_data_blocking(&key)?;
+            let bins = bin_depth(
+                &graph,
+                &data.node_data,
+                view_range.clone(),
+                bin_count,
+            );
+
+            Some(ViewValues {
+                key,
+                range: view_range,
+                bins,
+                min: data.stats.min.min(0.0),
+                max: data.stats.max,
+            })
+        }));
+    }
+
+    /// The track picker, the histogram, and the brushed range, with
+    /// how much of the view is in it.
+    pub fn show(&mut self, ui: &mut egui::Ui, shared: &SharedState) {
+        let mut keys = shared
+            .graph_data_cache
+            .graph_data_source_names()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        keys.sort();
+
+        egui::ComboBox::from_id_source("Viewer1D-histogram-key")
+            .selected_text(&self.key)
+            .show_ui(ui, |ui| {
+                for key in keys {
+                    if ui.selectable_label(self.key == key, &key).clicked() {
+                        self.key = key;
+                        self.brush = None;
+                    }
+                }
+            });
+
+        let Some(values) = self.values.as_ref().filter(|v| v.key == self.key)
+        else {
+            ui.spinner();
+            return;
+        };
+
+        let width = ui.available_width();
+        let (rect, resp) = ui.allocate_exact_size(
+            egui::vec2(width, HISTOGRAM_HEIGHT),
+            egui::Sense::click_and_drag(),
+        );
+
+        let span = (values.max - values.min).max(f32::EPSILON);
+        let value_at = |x: f32| {
+            let t = ((x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+            values.min + t * span
+        };
+        let x_at =
+            |v: f32| rect.left() + (v - values.min) / span * rect.width();
+
+        if resp.drag_started() {
+            self.brush_start =
+                resp.interact_pointer_pos().map(|p| value_at(p.x));
+        }
+        if let Some((start, pos)) =
+            self.brush_start.zip(resp.interact_pointer_pos())
+        {
+            if resp.dragged() {
+                let end = value_at(pos.x);
+                self.brush = Some(start.min(end)..=start.max(end));
+            }
+        }
+        if resp.drag_released() {
+            self.brush_start = None;
+        }
+        if resp.clicked() {
+            self.brush = None;
+        }
+
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+
+        let counts = values.histogram(BUCKET_COUNT);
+        let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+        let bar_width = rect.width() / BUCKET_COUNT as f32;
+
+        for (ix, &count) in counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let h = count as f32 / max_count as f32 * rect.height();
+            let x0 = rect.left() + ix as f32 * bar_width;
+            let bar = egui::Rect::from_x_y_ranges(
+                x0..=(x0 + bar_width - 1.0).max(x0),
+                (rect.bottom() - h)..=rect.bottom(),
+            );
+            painter.rect_filled(bar, 0.0, visuals.text_color());
+        }
+
+        if let Some(brush) = self.brush.as_ref() {
+            let color = visuals.selection.bg_fill;
+            let band = egui::Rect::from_x_y_ranges(
+                x_at(*brush.start())..=x_at(*brush.end()),
+                rect.y_range(),
+            );
+            painter.rect_filled(band, 0.0, color.linear_multiply(0.3));
+            painter.rect_stroke(band, 0.0, egui::Stroke::new(1.0, color));
+        }
+
+        ui.horizontal(|ui| {
+            ui.weak(super::gui::format_value(values.min));
+            ui.with_layout(
+                egui::Layout::right_to_left(egui::Align::Center),
+                |ui| {
+                    ui.weak(super::gui::format_value(values.max));
+                },
+            );
+        });
+
+        match self.brush.as_ref() {
+            Some(brush) => {
+                let in_brush =
+                    values.bins.iter().filter(|&&v| brush.contains(&v)).count();
+                let percent =
+                    100.0 * in_brush as f32 / values.bins.len().max(1) as f32;
+                ui.label(format!(
+                    "{} to {}: {percent:.1}% of the view",
+                    super::gui::format_value(*brush.start()),
+                    super::gui::format_value(*brush.end()),
+                ));
+            }
+            None => {
+                ui.weak("Drag across the histogram to highlight values");
+            }
+        }
+    }
+
+    /// Bands over `rect` for the parts of the view with values in the
+    /// brushed range, shifted and scaled to match `view_range` if the
+    /// values were computed for a different range.
+    pub fn highlight_shapes(
+        &self,
+        rect: egui::Rect,
+        view_range: Range<u64>,
+        color: egui::Color32,
+    ) -> Vec<egui::Shape> {
+        let Some((values, brush)) =
+            self.values.as_ref().zip(self.brush.as_ref())
+        else {
+            return Vec::new();
+        };
+
+        let map = BpScreenMap::new(view_range, rect.x_range());
+
+        values
+            .matching_ranges(brush)
+            .into_iter()
+            .filter_map(|range| {
+                let x0 = map.bp_to_x(range.start).max(rect.left());
+                let x1 = map.bp_to_x(range.end).min(rect.right());
+                (x1 > x0).then(|| {
+                    let band =
+                        egui::Rect::from_x_y_ranges(x0..=x1, rect.y_range());
+                    egui::Shape::rect_filled(
+                        band,
+                        0.0,
+                        color.linear_multiply(0.2),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_and_brushed_ranges() {
+        let values = ViewValues {
+            key: "depth".to_string(),
+            range: 100..200,
+            bins: vec![0.0, 1.0, 4.0, 4.5, 10.0, 2.0, 5.0, 5.0, 5.0, 0.5],
+            min: 0.0,
+            max: 10.0,
+        };
+
+        let counts = values.histogram(5);
+        assert_eq!(counts, vec![3, 1, 5, 0, 1]);
+        assert_eq!(counts.iter().sum::<usize>(), values.bins.len());
+
+        // adjacent matching bins are merged
+        let ranges = values.matching_ranges(&(4.0..=5.0));
+        assert_eq!(ranges, vec![Bp(120)..Bp(140), Bp(160)..Bp(190)]);
+
+        assert!(values.matching_ranges(&(20.0..=30.0)).is_empty());
+    }
+}
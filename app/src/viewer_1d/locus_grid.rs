@@ -0,0 +1,609 @@
+//! "Locus grid" figures: a grid of small 1D panels, one per locus in
+//! a BED file, all showing the same paths and data with the same
+//! color mapping. The panels are rendered on the CPU, using the
+//! software renderer, so the same code produces both the image shown
+//! in the GUI and the exported figure.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::{Bp, PathId, PathIndex};
+
+use crate::annotations::AnnotationSet;
+use crate::app::provenance::EventKind;
+use crate::app::resource::GraphDataCache;
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::{Args, SharedState};
+
+use super::control::{path_range_to_pangenome, Msg, ViewCmd};
+use super::export::write_rgb_image;
+use super::software::SoftwareRender1D;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locus {
+    pub label: String,
+    pub path: PathId,
+    /// Range on the path, as given in the BED file
+    pub range: std::ops::Range<Bp>,
+    pub pangenome_range: std::ops::Range<Bp>,
+}
+
+/// Loads the loci from a 4-column BED file; records on paths that
+/// aren't in the graph, or that don't cover any nodes, are skipped.
+pub fn loci_from_bed(
+    graph: &PathIndex,
+    bed_path: impl AsRef<Path>,
+) -> Result<Vec<Locus>> {
+    let set =
+        AnnotationSet::from_bed(graph, None, |n| n.to_string(), bed_path)?;
+
+    let loci = set
+        .annotations
+        .into_iter()
+        .filter_map(|annot| {
+            let pangenome_range = path_range_to_pangenome(
+                graph,
+                annot.path,
+                annot.range.clone(),
+            )?;
+
+            Some(Locus {
+                label: annot.label.to_string(),
+                path: annot.path,
+                range: annot.range,
+                pangenome_range,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    anyhow::ensure!(!loci.is_empty(), "No loci found in BED file");
+
+    Ok(loci)
+}
+
+#[derive(Debug, Clone)]
+pub struct LocusGrid {
+    pub columns: usize,
+    pub panel_width: usize,
+    pub padding: usize,
+    pub row_height: usize,
+    pub row_gap: usize,
+}
+
+impl Default for LocusGrid {
+    fn default() -> Self {
+        Self {
+            columns: 4,
+            panel_width: 256,
+            padding: 8,
+            row_height: 4,
+            row_gap: 1,
+        }
+    }
+}
+
+pub struct LocusGridImage {
+    pub width: usize,
+    pub height: usize,
+    /// RGB8 pixels
+    pub pixels: Vec<u8>,
+    pub loci: Vec<Locus>,
+    /// The layout the image was rendered with
+    pub grid: LocusGrid,
+}
+
+impl LocusGridImage {
+    pub fn panel_at(
+        &self,
+        pos: [usize; 2],
+        path_count: usize,
+    ) -> Option<usize> {
+        self.grid.panel_at(pos, self.loci.len(), path_count)
+    }
+}
+
+impl LocusGrid {
+    /// The default column count for `n` loci, giving a roughly
+    /// square grid.
+    pub fn default_columns(n: usize) -> usize {
+        ((n as f64).sqrt().ceil() as usize).max(1)
+    }
+
+    fn panel_height(&self, path_count: usize) -> usize {
+        path_count * (self.row_height + self.row_gap)
+    }
+
+    fn panel_origin(&self, ix: usize, path_count: usize) -> [usize; 2] {
+        let col = ix % self.columns;
+        let row = ix / self.columns;
+        let x = self.padding + col * (self.panel_width + self.padding);
+        let y =
+            self.padding + row * (self.panel_height(path_count) + self.padding);
+        [x, y]
+    }
+
+    pub fn image_size(
+        &self,
+        locus_count: usize,
+        path_count: usize,
+    ) -> [usize; 2] {
+        let rows = (locus_count + self.columns - 1) / self.columns;
+        let cols = self.columns.min(locus_count);
+        let width = self.padding + cols * (self.panel_width + self.padding);
+        let height = self.padding
+            + rows * (self.panel_height(path_count) + self.padding);
+        [width, height]
+    }
+
+    /// Returns the index of the panel containing the given pixel of
+    /// the image, if any.
+    pub fn panel_at(
+        &self,
+        [x, y]: [usize; 2],
+        locus_count: usize,
+        path_count: usize,
+    ) -> Option<usize> {
+        let cell_w = self.panel_width + self.padding;
+        let cell_h = self.panel_height(path_count) + self.padding;
+
+        let x = x.checked_sub(self.padding)?;
+        let y = y.checked_sub(self.padding)?;
+
+        if x % cell_w >= self.panel_width
+            || y % cell_h >= self.panel_height(path_count)
+        {
+            return None;
+        }
+
+        let col = x / cell_w;
+        if col >= self.columns {
+            return None;
+        }
+
+        let ix = (y / cell_h) * self.columns + col;
+        (ix < locus_count).then_some(ix)
+    }
+
+    /// Renders one panel per locus, in row-major order; every panel
+    /// has the same width and color mapping regardless of the length
+    /// of the locus.
+    pub async fn render(
+        &self,
+        graph: &Arc<PathIndex>,
+        data_cache: &Arc<GraphDataCache>,
+        data_key: &str,
+        loci: Vec<Locus>,
+        paths: &[PathId],
+    ) -> Result<LocusGridImage> {
+        let (scheme_name, color_map) =
+            SoftwareRender1D::default_color_scheme(data_key);
+
+        let scheme = crate::color::builtin_color_schemes()
+            .into_iter()
+            .find_map(|(name, colors)| (name == scheme_name).then_some(colors))
+            .unwrap();
+
+        let [width, height] = self.image_size(loci.len(), paths.len());
+        let mut pixels = vec![224u8; width * height * 3];
+
+        let panel_stride = self.panel_width * 3;
+
+        for (ix, locus) in loci.iter().enumerate() {
+            let renderer = SoftwareRender1D {
+                width: self.panel_width,
+                row_height: self.row_height,
+                row_gap: self.row_gap,
+                view: locus.pangenome_range.clone(),
+                data_key: data_key.to_string(),
+                color_map,
+            };
+
+            let panel =
+                renderer.render(graph, data_cache, &scheme, paths).await?;
+
+            let [x0, y0] = self.panel_origin(ix, paths.len());
+
+            for (row, src) in panel.chunks_exact(panel_stride).enumerate() {
+                let start = ((y0 + row) * width + x0) * 3;
+                pixels[start..start + panel_stride].copy_from_slice(src);
+            }
+        }
+
+        Ok(LocusGridImage {
+            width,
+            height,
+            pixels,
+            loci,
+            grid: self.clone(),
+        })
+    }
+}
+
+/// Entry point for `--software-render --locus-grid <bed>`: writes the
+/// locus grid figure for all paths to an image.
+pub fn run(args: &Args, bed_path: &Path) -> Result<()> {
+    let graph = Arc::new(PathIndex::from_file(&args.gfa)?);
+    let data_cache = Arc::new(GraphDataCache::init(&graph));
+
+    let data_key = args.render_data.as_deref().unwrap_or("depth");
+
+    let loci = loci_from_bed(&graph, bed_path)?;
+
+    let grid = LocusGrid {
+        columns: args
+            .grid_columns
+            .unwrap_or_else(|| LocusGrid::default_columns(loci.len()))
+            .max(1),
+        panel_width: args.render_width.unwrap_or(256),
+        ..LocusGrid::default()
+    };
+
+    let paths = (0..graph.path_names.len())
+        .map(PathId::from)
+        .collect::<Vec<_>>();
+
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+
+    let image =
+        rt.block_on(grid.render(&graph, &data_cache, data_key, loci, &paths))?;
+
+    let out_path = args
+        .output
+        .clone()
+        .unwrap_or_else(|| "waragraph-loci.ppm".into());
+
+    write_rgb_image(&out_path, image.width, image.height, &image.pixels)?;
+
+    // the figure has no labels, so list the panel order
+    println!("{}", args.coords.header_comment());
+    for (ix, locus) in image.loci.iter().enumerate() {
+        let path = graph.path_names.get_by_left(&locus.path).unwrap();
+        let range = args.coords.format_range(&locus.range);
+        println!("{ix}\t{}\t{path}:{range}", locus.label);
+    }
+
+    log::warn!(
+        "Wrote {}x{} image to {out_path:?}",
+        image.width,
+        image.height
+    );
+
+    Ok(())
+}
+
+/// Settings window panel for viewing locus grids interactively;
+/// clicking a panel moves the 1D view to the locus.
+pub struct LocusGridWidget {
+    shared: SharedState,
+    msg_tx: crossbeam::channel::Sender<Msg>,
+
+    grid: LocusGrid,
+    data_key: String,
+
+    bed_path: Option<PathBuf>,
+    bed_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    export_path_recv: Option<oneshot::Receiver<PathBuf>>,
+
+    render_recv: Option<oneshot::Receiver<Result<LocusGridImage>>>,
+    image: Option<(LocusGridImage, egui::TextureHandle)>,
+    error: Option<String>,
+}
+
+impl LocusGridWidget {
+    pub fn new(
+        shared: &SharedState,
+        msg_tx: crossbeam::channel::Sender<Msg>,
+    ) -> Self {
+        Self {
+            shared: shared.clone(),
+            msg_tx,
+
+            grid: LocusGrid::default(),
+            data_key: "depth".to_string(),
+
+            bed_path: None,
+            bed_path_recv: None,
+            export_path_recv: None,
+
+            render_recv: None,
+            image: None,
+            error: None,
+        }
+    }
+
+    fn path_count(&self) -> usize {
+        self.shared.graph.path_names.len()
+    }
+
+    fn spawn_render(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        let Some(bed_path) = self.bed_path.clone() else {
+            return;
+        };
+
+        let (send, recv) = oneshot::channel();
+
+        let graph = self.shared.graph.clone();
+        let data_cache = self.shared.graph_data_cache.clone();
+        let data_key = self.data_key.clone();
+        let grid = self.grid.clone();
+
+        tokio_handle.spawn(async move {
+            let result = async {
+                let loci = loci_from_bed(&graph, &bed_path)?;
+
+                let paths = (0..graph.path_names.len())
+                    .map(PathId::from)
+                    .collect::<Vec<_>>();
+
+                grid.render(&graph, &data_cache, &data_key, loci, &paths)
+                    .await
+            }
+            .await;
+
+            let _ = send.send(result);
+        });
+
+        self.render_recv = Some(recv);
+        self.error = None;
+    }
+
+    fn poll_channels(&mut self, ctx: &egui::Context) {
+        if let Some(mut recv) = self.bed_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    self.bed_path = Some(path);
+                }
+                Err(TryRecvError::Empty) => self.bed_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.render_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok(image)) => {
+                    let color_image = egui::ColorImage::from_rgb(
+                        [image.width, image.height],
+                        &image.pixels,
+                    );
+                    let texture = ctx.load_texture(
+                        "locus_grid",
+                        color_image,
+                        egui::TextureOptions::NEAREST,
+                    );
+                    self.image = Some((image, texture));
+                }
+                Ok(Err(e)) => {
+                    log::error!("Error rendering locus grid: {e:?}");
+                    self.error = Some(e.to_string());
+                }
+                Err(TryRecvError::Empty) => self.render_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.export_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    if let Some((image, _)) = self.image.as_ref() {
+                        if let Err(e) = write_rgb_image(
+                            &path,
+                            image.width,
+                            image.height,
+                            &image.pixels,
+                        ) {
+                            log::error!("Error exporting locus grid: {e:?}");
+                            self.error = Some(e.to_string());
+                        } else {
+                            self.shared
+                                .provenance
+                                .blocking_write()
+                                .record_path(
+                                    EventKind::Export,
+                                    "Locus grid",
+                                    &path,
+                                );
+                        }
+                    }
+                }
+                Err(TryRecvError::Empty) => self.export_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+impl SettingsWidget for LocusGridWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels(ui.ctx());
+
+        let resp = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                let mut bed_buf = self
+                    .bed_path
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                ui.label("Loci:");
+                ui.add_enabled(false, egui::TextEdit::singleline(&mut bed_buf));
+
+                if ui.button("Choose").clicked() {
+                    let mut files =
+                        egui_file::FileDialog::open_file(self.bed_path.clone())
+                            .filter(Box::new(|p: &Path| {
+                                p.extension()
+                                    .map(|e| e.eq_ignore_ascii_case("bed"))
+                                    .unwrap_or(false)
+                            }));
+                    files.open();
+
+                    let id = egui::Id::new("LocusGridWidget_bed");
+                    let recv = settings_ctx.with_file_dialog_oneshot(id, files);
+                    self.bed_path_recv = Some(recv);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Data:");
+                egui::ComboBox::from_id_source("LocusGridWidget_data")
+                    .selected_text(&self.data_key)
+                    .show_ui(ui, |ui| {
+                        let names = self
+                            .shared
+                            .graph_data_cache
+                            .path_data_source_names()
+                            .map(|n| n.to_string())
+                            .collect::<Vec<_>>();
+
+                        for name in names {
+                            ui.selectable_value(
+                                &mut self.data_key,
+                                name.clone(),
+                                name,
+                            );
+                        }
+                    });
+
+                ui.label("Columns:");
+                ui.add(
+                    egui::DragValue::new(&mut self.grid.columns)
+                        .clamp_range(1..=32),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                let rendering = self.render_recv.is_some();
+
+                let render = ui.add_enabled(
+                    self.bed_path.is_some() && !rendering,
+                    egui::Button::new("Render"),
+                );
+
+                if render.clicked() {
+                    self.spawn_render(&settings_ctx.tokio_handle);
+                }
+
+                if rendering {
+                    ui.spinner();
+                }
+
+                let export = ui.add_enabled(
+                    self.image.is_some(),
+                    egui::Button::new("Export"),
+                );
+
+                if export.clicked() {
+                    let path = self
+                        .shared
+                        .export_paths
+                        .blocking_read()
+                        .image_path("locus-grid", None, "png");
+
+                    let id = egui::Id::new("LocusGridWidget_export");
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.export_path_recv = Some(recv);
+                }
+            });
+
+            if let Some(err) = self.error.as_ref() {
+                ui.label(format!("Error: {err}"));
+            }
+
+            let path_count = self.path_count();
+
+            if let Some((image, texture)) = self.image.as_ref() {
+                let scale =
+                    (ui.available_width() / image.width as f32).min(1.0);
+                let size = egui::vec2(
+                    image.width as f32 * scale,
+                    image.height as f32 * scale,
+                );
+
+                let img_resp = egui::ScrollArea::vertical()
+                    .max_height(600.0)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::Image::new(texture.id(), size)
+                                .sense(egui::Sense::click()),
+                        )
+                    })
+                    .inner;
+
+                let hovered_panel = img_resp.hover_pos().and_then(|pos| {
+                    let local = (pos - img_resp.rect.min) / scale;
+                    let local = [local.x as usize, local.y as usize];
+                    image.panel_at(local, path_count)
+                });
+
+                if let Some(ix) = hovered_panel {
+                    let locus = &image.loci[ix];
+                    let coords = self.shared.coords.load();
+                    let path_name = self
+                        .shared
+                        .graph
+                        .path_names
+                        .get_by_left(&locus.path)
+                        .map(|s| s.as_str())
+                        .unwrap_or_default();
+
+                    let text = format!(
+                        "{}\n{path_name}:{} ({})",
+                        locus.label,
+                        coords.format_range(&locus.range),
+                        coords.short_name()
+                    );
+
+                    if img_resp.clicked() {
+                        let _ =
+                            self.msg_tx.send(Msg::View(ViewCmd::GotoRange {
+                                path: Some(locus.path),
+                                range: locus.range.clone(),
+                            }));
+                    }
+
+                    img_resp.on_hover_text(text);
+                }
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locus_grid_panel_lookup() {
+        let grid = LocusGrid {
+            columns: 3,
+            panel_width: 10,
+            padding: 2,
+            row_height: 2,
+            row_gap: 1,
+        };
+
+        // 5 loci in 2 rows, 4 paths per panel (12px high)
+        assert_eq!(grid.image_size(5, 4), [2 + 3 * 12, 2 + 2 * 14]);
+
+        assert_eq!(grid.panel_at([0, 0], 5, 4), None);
+        assert_eq!(grid.panel_at([2, 2], 5, 4), Some(0));
+        assert_eq!(grid.panel_at([12, 2], 5, 4), None);
+        assert_eq!(grid.panel_at([14, 16], 5, 4), Some(4));
+        // the last row only has two panels
+        assert_eq!(grid.panel_at([26, 16], 5, 4), None);
+    }
+}
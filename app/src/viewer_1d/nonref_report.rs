@@ -0,0 +1,304 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::coords::CoordSystem;
+use waragraph_core::graph::nonref::{
+    find_insertions, write_event_table, write_fasta, InsertionEvent,
+};
+use waragraph_core::graph::{Bp, PathId, PathIndex};
+
+use crate::app::provenance::EventKind;
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::{Args, SharedState};
+
+use super::control::{Msg, ViewCmd};
+
+/// Parses a comma-separated list of path names.
+pub fn parse_path_list(graph: &PathIndex, names: &str) -> Result<Vec<PathId>> {
+    names
+        .split(',')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            graph
+                .path_names
+                .get_by_right(name)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("Unknown path `{name}`"))
+        })
+        .collect()
+}
+
+/// Writes the event table to `tsv_path`, and the inserted sequences
+/// to the same path with a `.fa` extension.
+pub fn export_report(
+    graph: &PathIndex,
+    events: &[InsertionEvent],
+    coords: CoordSystem,
+    tsv_path: &Path,
+) -> Result<()> {
+    let fasta_path = tsv_path.with_extension("fa");
+
+    let tsv = std::io::BufWriter::new(std::fs::File::create(tsv_path)?);
+    write_event_table(graph, events, coords, tsv)?;
+
+    let fasta = std::io::BufWriter::new(std::fs::File::create(&fasta_path)?);
+    write_fasta(graph, events, fasta)?;
+
+    log::warn!(
+        "Wrote {} insertion events to {tsv_path:?} and {fasta_path:?}",
+        events.len()
+    );
+
+    Ok(())
+}
+
+/// Entry point for `--nonref-report <tsv>`.
+pub fn run(args: &Args, tsv_path: &Path) -> Result<()> {
+    let graph = PathIndex::from_file(&args.gfa)?;
+
+    let references = args
+        .reference
+        .as_deref()
+        .map(|names| parse_path_list(&graph, names))
+        .transpose()?
+        .unwrap_or_default();
+
+    anyhow::ensure!(
+        !references.is_empty(),
+        "--nonref-report requires --reference <path>[,<path>...]"
+    );
+
+    let events = find_insertions(&graph, &references, Bp(args.min_length));
+
+    export_report(&graph, &events, args.coords, tsv_path)
+}
+
+/// Settings window panel listing the insertion events relative to
+/// the chosen reference paths.
+pub struct NonRefReportWidget {
+    shared: SharedState,
+    msg_tx: crossbeam::channel::Sender<Msg>,
+
+    references: String,
+    min_length: u64,
+
+    events_recv: Option<oneshot::Receiver<Result<Vec<InsertionEvent>>>>,
+    events: Arc<Vec<InsertionEvent>>,
+    export_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    error: Option<String>,
+}
+
+impl NonRefReportWidget {
+    pub fn new(
+        shared: &SharedState,
+        msg_tx: crossbeam::channel::Sender<Msg>,
+    ) -> Self {
+        let references = shared.detected_reference_name();
+
+        Self {
+            shared: shared.clone(),
+            msg_tx,
+
+            references,
+            min_length: 50,
+
+            events_recv: None,
+            events: Arc::new(Vec::new()),
+            export_path_recv: None,
+            error: None,
+        }
+    }
+
+    fn spawn_search(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        let graph = self.shared.graph.clone();
+        let references = self.references.clone();
+        let min_length = Bp(self.min_length);
+
+        self.shared.provenance.blocking_write().record(
+            EventKind::Filter,
+            format!(
+                "Non-reference insertions of at least {} bp, \
+                 relative to `{references}`",
+                min_length.0
+            ),
+        );
+
+        let (send, recv) = oneshot::channel();
+
+        tokio_handle.spawn_blocking(move || {
+            let result = parse_path_list(&graph, &references)
+                .map(|refs| find_insertions(&graph, &refs, min_length));
+            let _ = send.send(result);
+        });
+
+        self.events_recv = Some(recv);
+        self.error = None;
+    }
+
+    fn poll_channels(&mut self) {
+        if let Some(mut recv) = self.events_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok(events)) => self.events = Arc::new(events),
+                Ok(Err(e)) => self.error = Some(e.to_string()),
+                Err(TryRecvError::Empty) => self.events_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.export_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    let coords = self.shared.coords.load();
+                    if let Err(e) = export_report(
+                        &self.shared.graph,
+                        &self.events,
+                        coords,
+                        &path,
+                    ) {
+                        log::error!("Error exporting report: {e:?}");
+                        self.error = Some(e.to_string());
+                    } else {
+                        let mut log = self.shared.provenance.blocking_write();
+                        let desc = "Non-reference insertions";
+                        log.record_path(EventKind::Export, desc, &path);
+                        let fasta_path = path.with_extension("fa");
+                        log.record_path(EventKind::Export, desc, &fasta_path);
+                    }
+                }
+                Err(TryRecvError::Empty) => self.export_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+impl SettingsWidget for NonRefReportWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels();
+
+        let coords = self.shared.coords.load();
+
+        let resp = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Reference paths:");
+                ui.text_edit_singleline(&mut self.references)
+                    .on_hover_text("Comma-separated path names");
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Min. length (bp):");
+                ui.add(
+                    egui::DragValue::new(&mut self.min_length)
+                        .clamp_range(1..=1_000_000),
+                );
+
+                let searching = self.events_recv.is_some();
+
+                if ui
+                    .add_enabled(!searching, egui::Button::new("Find"))
+                    .clicked()
+                {
+                    self.spawn_search(&settings_ctx.tokio_handle);
+                }
+
+                if searching {
+                    ui.spinner();
+                }
+
+                let export = ui.add_enabled(
+                    !self.events.is_empty(),
+                    egui::Button::new("Export"),
+                );
+
+                if export.clicked() {
+                    let path = self
+                        .shared
+                        .export_paths
+                        .blocking_read()
+                        .export_path("nonref", None, "tsv");
+
+                    let id = egui::Id::new("NonRefReportWidget_export");
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.export_path_recv = Some(recv);
+                }
+            });
+
+            if let Some(err) = self.error.as_ref() {
+                ui.label(format!("Error: {err}"));
+            }
+
+            let total: u64 = self.events.iter().map(|e| e.length.0).sum();
+            ui.label(format!(
+                "{} insertion events, {total} bp",
+                self.events.len()
+            ));
+
+            egui::ScrollArea::vertical()
+                .max_height(400.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("NonRefReportWidget-events")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label(format!(
+                                "Position ({})",
+                                coords.short_name()
+                            ));
+                            ui.label("Length");
+                            ui.label("Paths");
+                            ui.end_row();
+
+                            let mut links = Vec::new();
+
+                            for event in self.events.iter() {
+                                let range = &event.pangenome_range;
+                                let label = coords.format_range(range);
+
+                                let link = ui.link(label);
+                                if link.clicked() {
+                                    let _ = self.msg_tx.send(Msg::View(
+                                        ViewCmd::GotoRange {
+                                            path: None,
+                                            range: range.clone(),
+                                        },
+                                    ));
+                                }
+                                links.push(link);
+
+                                ui.label(format!("{} bp", event.length.0));
+
+                                let paths = event
+                                    .paths
+                                    .iter()
+                                    .filter_map(|p| {
+                                        self.shared
+                                            .graph
+                                            .path_names
+                                            .get_by_left(p)
+                                    })
+                                    .map(|s| s.as_str())
+                                    .collect::<Vec<_>>();
+
+                                ui.label(paths.len().to_string())
+                                    .on_hover_text(paths.join("\n"));
+                                ui.end_row();
+                            }
+
+                            crate::gui::util::arrow_key_focus(ui, &links);
+                        });
+                });
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
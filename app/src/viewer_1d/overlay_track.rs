@@ -0,0 +1,193 @@
+//! Overlay track: several quantitative graph data tracks, binned over
+//! the 1D view range and drawn as lines in one row, to compare
+//! related signals without a row each.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use tokio::task::JoinHandle;
+use waragraph_core::graph::Bp;
+
+use crate::app::SharedState;
+
+use super::depth_track::bin_depth;
+use super::transform::BpScreenMap;
+
+/// The most tracks that can be overlaid in the row
+pub const MAX_OVERLAY_TRACKS: usize = 4;
+
+/// The line color of each overlaid track, by position in the row
+pub const OVERLAY_COLORS: [egui::Color32; MAX_OVERLAY_TRACKS] = [
+    egui::Color32::from_rgb(86, 180, 233),
+    egui::Color32::from_rgb(230, 159, 0),
+    egui::Color32::from_rgb(0, 158, 115),
+    egui::Color32::from_rgb(204, 121, 167),
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayScale {
+    /// All tracks are scaled to the highest max of the tracks
+    #[default]
+    Shared,
+    /// Each track is scaled to its own max
+    PerTrack,
+}
+
+#[derive(Debug, Clone)]
+struct TrackBins {
+    range: Range<u64>,
+    values: Vec<f32>,
+    /// Max node value in the graph, used to scale the line
+    max: f32,
+}
+
+#[derive(Default)]
+pub struct OverlayTrack {
+    bins: BTreeMap<String, TrackBins>,
+    task: Option<JoinHandle<Vec<(String, TrackBins)>>>,
+}
+
+impl OverlayTrack {
+    /// Picks up finished results, and starts recomputing the bins of
+    /// `keys` in the background if the view range, bin count, or the
+    /// tracks have changed.
+    pub fn update(
+        &mut self,
+        tokio_rt: &tokio::runtime::Handle,
+        shared: &SharedState,
+        keys: &[String],
+        view_range: Range<u64>,
+        bin_count: usize,
+    ) {
+        if self.task.as_ref().map(|t| t.is_finished()).unwrap_or(false) {
+            let task = self.task.take().unwrap();
+            if let Ok(bins) = tokio_rt.block_on(task) {
+                self.bins = bins.into_iter().collect();
+            }
+        }
+
+        let up_to_date = self.bins.len() == keys.len()
+            && keys.iter().all(|key| {
+                self.bins.get(key).is_some_and(|bins| {
+                    bins.range == view_range && bins.values.len() == bin_count
+                })
+            });
+
+        if up_to_date || self.task.is_some() || bin_count == 0 {
+            return;
+        }
+
+        let graph = shared.graph.clone();
+        let data_cache = shared.graph_data_cache.clone();
+        let keys = keys.to_vec();
+
+        self.task = Some(tokio_rt.spawn_blocking(move || {
+            keys.into_iter()
+                .filter_map(|key| {
+                    let data = data_cache.fetch_graph_data_blocking(&key)?;
+                    let values = bin_depth(
+                        &graph,
+                        &data.node_data,
+                        view_range.clone(),
+                        bin_count,
+                    );
+                    let bins = TrackBins {
+                        range: view_range.clone(),
+                        values,
+                        max: data.stats.max,
+                    };
+                    Some((key, bins))
+                })
+                .collect()
+        }));
+    }
+
+    /// The value each of `keys` is scaled to, which is the top of the
+    /// row.
+    pub fn scale_max(
+        &self,
+        keys: &[String],
+        scale: OverlayScale,
+    ) -> Vec<Option<f32>> {
+        let maxes = keys
+            .iter()
+            .map(|key| self.bins.get(key).map(|bins| bins.max.max(1.0)))
+            .collect::<Vec<_>>();
+
+        match scale {
+            OverlayScale::PerTrack => maxes,
+            OverlayScale::Shared => {
+                let max = maxes.iter().flatten().copied().reduce(f32::max);
+                vec![max; keys.len()]
+            }
+        }
+    }
+
+    /// Draws the latest bins of `keys` as lines into `rect`, shifted
+    /// and scaled to match `view_range` if they were computed for a
+    /// different range.
+    pub fn shapes(
+        &self,
+        rect: egui::Rect,
+        keys: &[String],
+        scale: OverlayScale,
+        view_range: Range<u64>,
+    ) -> Vec<egui::Shape> {
+        let mut shapes = vec![egui::Shape::rect_filled(
+            rect,
+            0.0,
+            egui::Color32::from_gray(32),
+        )];
+
+        let map = BpScreenMap::new(view_range, rect.x_range());
+        let maxes = self.scale_max(keys, scale);
+
+        for ((key, max), color) in keys.iter().zip(maxes).zip(OVERLAY_COLORS) {
+            let Some((bins, max)) = self.bins.get(key).zip(max) else {
+                continue;
+            };
+
+            let bin_len = (bins.range.end - bins.range.start) as f64
+                / bins.values.len() as f64;
+
+            let points = bins
+                .values
+                .iter()
+                .enumerate()
+                .filter_map(|(ix, &v)| {
+                    let mid =
+                        bins.range.start as f64 + (ix as f64 + 0.5) * bin_len;
+                    let x = map.bp_to_x(Bp(mid as u64));
+                    if x < rect.left() || x > rect.right() {
+                        return None;
+                    }
+                    let h = (v / max).clamp(0.0, 1.0) * (rect.height() - 1.0);
+                    Some(egui::pos2(x, rect.bottom() - h))
+                })
+                .collect::<Vec<_>>();
+
+            shapes
+                .push(egui::Shape::line(points, egui::Stroke::new(1.5, color)));
+        }
+
+        shapes
+    }
+
+    /// The mean value of each of `keys` at the bp position, from the
+    /// latest bins.
+    pub fn values_at(&self, keys: &[String], pos: Bp) -> Vec<Option<f32>> {
+        keys.iter()
+            .map(|key| {
+                let bins = self.bins.get(key)?;
+                if !bins.range.contains(&pos.0) {
+                    return None;
+                }
+
+                let len = bins.range.end - bins.range.start;
+                let ix =
+                    (pos.0 - bins.range.start) * bins.values.len() as u64 / len;
+                bins.values.get(ix as usize).copied()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,367 @@
+//! Playback that moves the 1D view along a path at a steady speed,
+//! optionally pausing at the start of each annotation on the path,
+//! and keeping the 2D view centered on the same node.
+
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+use waragraph_core::graph::{Bp, Node, PathId, PathIndex};
+
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::SharedState;
+
+/// How often the 2D view is moved while following the walk
+const FOLLOW_2D_INTERVAL: f32 = 0.25;
+
+pub struct PathWalk {
+    pub path: Option<PathId>,
+    /// Position along the path, in bp
+    pub pos: f64,
+    /// In bp per second
+    pub speed: f64,
+    pub playing: bool,
+
+    pub pause_at_annotations: bool,
+    /// Seconds to stay at each annotation
+    pub pause_secs: f32,
+    /// Keep the 2D view centered on the node at the walk position
+    pub follow_2d: bool,
+
+    // seconds left of the current pause at an annotation
+    paused_for: f32,
+    // set when the position is changed directly, so that the view
+    // moves even if the walk isn't playing
+    seeked: bool,
+    since_2d_update: f32,
+
+    // sorted start positions of the annotations on `path`, and the
+    // (path, annotation count) they were collected for
+    stops: Vec<u64>,
+    stops_for: Option<(PathId, usize)>,
+}
+
+impl std::default::Default for PathWalk {
+    fn default() -> Self {
+        Self {
+            path: None,
+            pos: 0.0,
+            speed: 10_000.0,
+            playing: false,
+            pause_at_annotations: true,
+            pause_secs: 2.0,
+            follow_2d: false,
+            paused_for: 0.0,
+            seeked: false,
+            since_2d_update: 0.0,
+            stops: Vec::new(),
+            stops_for: None,
+        }
+    }
+}
+
+impl PathWalk {
+    /// Moves the walk to `pos` on its path.
+    pub fn seek(&mut self, pos: f64) {
+        self.pos = pos.max(0.0);
+        self.paused_for = 0.0;
+        self.seeked = true;
+    }
+
+    pub fn paused_at_annotation(&self) -> bool {
+        self.paused_for > 0.0
+    }
+
+    /// Advances the walk by `dt` seconds along a path of length
+    /// `path_len`, stopping at the next annotation if enabled.
+    /// Returns `true` if the position changed.
+    pub fn advance(&mut self, dt: f32, path_len: u64) -> bool {
+        let seeked = std::mem::take(&mut self.seeked);
+
+        if !self.playing || self.path.is_none() {
+            return seeked;
+        }
+
+        if self.paused_for > 0.0 {
+            self.paused_for -= dt;
+            return seeked;
+        }
+
+        let next = self.pos + self.speed * dt as f64;
+
+        let stop = self
+            .pause_at_annotations
+            .then(|| {
+                // the first annotation starting after the current
+                // position, up to and including the next one
+                let ix = self.stops.partition_point(|&s| s as f64 <= self.pos);
+                self.stops.get(ix).filter(|&&s| s as f64 <= next)
+            })
+            .flatten();
+
+        if let Some(&stop) = stop {
+            self.pos = stop as f64;
+            self.paused_for = self.pause_secs;
+        } else {
+            self.pos = next;
+        }
+
+        if self.pos >= path_len as f64 {
+            self.pos = path_len as f64;
+            self.playing = false;
+        }
+
+        true
+    }
+
+    /// Collects the annotation starts on the walk's path, if the path
+    /// or the loaded annotations have changed.
+    fn update_stops(&mut self, shared: &SharedState) {
+        let Some(path) = self.path else {
+            return;
+        };
+
+        let annotations = shared.annotations.blocking_read();
+        let key = (path, annotations.total_annotation_count());
+
+        if self.stops_for == Some(key) {
+            return;
+        }
+
+        self.stops.clear();
+        for set in annotations.annotation_sets.values() {
+            for &ix in set.path_annotations.get(&path).into_iter().flatten() {
+                self.stops.push(set.annotations[ix].range.start.0);
+            }
+        }
+        self.stops.sort_unstable();
+        self.stops.dedup();
+
+        self.stops_for = Some(key);
+    }
+
+    /// Returns `true` when it's time to move the 2D view again.
+    fn take_2d_update(&mut self, dt: f32) -> bool {
+        self.since_2d_update += dt;
+        if self.since_2d_update >= FOLLOW_2D_INTERVAL {
+            self.since_2d_update = 0.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// What the viewers should do after a frame of the walk.
+pub struct WalkStep {
+    pub path: PathId,
+    /// The pangenome position to center the 1D view on
+    pub pan_pos: Bp,
+    /// The node to center the 2D view on, if following
+    pub node_2d: Option<Node>,
+}
+
+/// Advances the walk, returning where the views should move.
+pub fn step(
+    walk: &RwLock<PathWalk>,
+    shared: &SharedState,
+    dt: f32,
+) -> Option<WalkStep> {
+    let mut walk = walk.blocking_write();
+
+    let path = walk.path?;
+    walk.update_stops(shared);
+
+    let path_len = shared.graph.path_len(path)?.0;
+    if !walk.advance(dt, path_len) {
+        return None;
+    }
+
+    let pos = (walk.pos as u64).min(path_len.saturating_sub(1));
+    let pan_pos = path_pos_to_pangenome(&shared.graph, path, pos)?;
+
+    let node_2d = if walk.follow_2d && walk.take_2d_update(dt) {
+        shared.graph.node_at_pangenome_pos(pan_pos)
+    } else {
+        None
+    };
+
+    Some(WalkStep {
+        path,
+        pan_pos,
+        node_2d,
+    })
+}
+
+/// The pangenome position of the base at `pos` on the path, taking
+/// the orientation of the step into account.
+pub fn path_pos_to_pangenome(
+    graph: &PathIndex,
+    path: PathId,
+    pos: u64,
+) -> Option<Bp> {
+    let offsets = graph.path_step_offsets.get(path.ix())?;
+    let steps = graph.path_steps.get(path.ix())?;
+
+    // the offsets are the start positions of each step
+    let step_ix = offsets.rank(pos).checked_sub(1)?;
+    let step = steps.get(step_ix as usize)?;
+    let step_start = offsets.select(step_ix)?;
+
+    let (node_start, node_len) = graph.node_offset_length(step.node());
+    let inner = (pos - step_start).min(node_len.0.saturating_sub(1));
+
+    let offset = if step.is_reverse() {
+        node_len.0.saturating_sub(inner + 1)
+    } else {
+        inner
+    };
+
+    Some(Bp(node_start.0 + offset))
+}
+
+/// Settings window panel with the playback controls.
+pub struct PathWalkWidget {
+    graph: Arc<PathIndex>,
+    walk: Arc<RwLock<PathWalk>>,
+}
+
+impl PathWalkWidget {
+    pub fn new(shared: &SharedState, walk: Arc<RwLock<PathWalk>>) -> Self {
+        Self {
+            graph: shared.graph.clone(),
+            walk,
+        }
+    }
+}
+
+impl SettingsWidget for PathWalkWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        let mut walk = self.walk.blocking_write();
+
+        let resp = ui.vertical(|ui| {
+            let path_name = |path: PathId| {
+                self.graph
+                    .path_names
+                    .get_by_left(&path)
+                    .map(|name| name.as_str())
+                    .unwrap_or_default()
+            };
+
+            let mut path = walk.path;
+            egui::ComboBox::from_label("Path")
+                .selected_text(path.map(path_name).unwrap_or("Select a path"))
+                .show_ui(ui, |ui| {
+                    for (&id, name) in self.graph.path_names.iter() {
+                        ui.selectable_value(&mut path, Some(id), name);
+                    }
+                });
+
+            if path != walk.path {
+                walk.path = path;
+                walk.playing = false;
+                walk.seek(0.0);
+            }
+
+            let Some(path) = walk.path else {
+                return;
+            };
+            let path_len = self.graph.path_len(path).map(|l| l.0).unwrap_or(0);
+
+            ui.horizontal(|ui| {
+                let label = if walk.playing { "Pause" } else { "Play" };
+                if ui.button(label).clicked() {
+                    if !walk.playing && walk.pos >= path_len as f64 {
+                        walk.seek(0.0);
+                    }
+                    walk.playing = !walk.playing;
+                }
+
+                if ui.button("Restart").clicked() {
+                    walk.seek(0.0);
+                }
+
+                if walk.paused_at_annotation() {
+                    ui.label("At annotation");
+                }
+            });
+
+            let mut pos = walk.pos;
+            let pos_resp = ui.add(
+                egui::Slider::new(&mut pos, 0.0..=path_len as f64)
+                    .text("Position")
+                    .suffix(" bp")
+                    .max_decimals(0),
+            );
+            if pos_resp.changed() {
+                walk.seek(pos);
+            }
+
+            ui.add(
+                egui::Slider::new(&mut walk.speed, 10.0..=10_000_000.0)
+                    .logarithmic(true)
+                    .text("Speed")
+                    .suffix(" bp/s")
+                    .max_decimals(0),
+            );
+
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut walk.pause_at_annotations,
+                    "Pause at annotations",
+                );
+                ui.add_enabled(
+                    walk.pause_at_annotations,
+                    egui::DragValue::new(&mut walk.pause_secs)
+                        .clamp_range(0.0..=60.0)
+                        .speed(0.1)
+                        .suffix(" s"),
+                );
+            });
+
+            ui.checkbox(&mut walk.follow_2d, "Move the 2D view too");
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_pauses_at_annotations() {
+        let mut walk = PathWalk {
+            path: Some(PathId::from(0u32)),
+            speed: 100.0,
+            playing: true,
+            pause_secs: 1.0,
+            stops: vec![50, 120],
+            ..Default::default()
+        };
+
+        assert!(walk.advance(0.25, 1000));
+        assert_eq!(walk.pos, 25.0);
+
+        // stops at the first annotation, then waits there
+        assert!(walk.advance(0.5, 1000));
+        assert_eq!(walk.pos, 50.0);
+        assert!(!walk.advance(0.5, 1000));
+        assert!(!walk.advance(0.5, 1000));
+        assert!(walk.advance(0.5, 1000));
+        assert_eq!(walk.pos, 100.0);
+
+        walk.pause_at_annotations = false;
+        assert!(walk.advance(10.0, 1000));
+        assert_eq!(walk.pos, 1000.0);
+        assert!(!walk.playing);
+    }
+}
@@ -0,0 +1,341 @@
+//! Path presence matrix: a window with a row per path and a column
+//! per bin of the 1D view range, shaded by how much of the bin the
+//! path covers, to spot presence/absence variation across many
+//! haplotypes at a glance.
+
+use std::ops::Range;
+
+use raving_wgpu::gui::EguiCtx;
+use tokio::task::JoinHandle;
+use waragraph_core::graph::{Bp, PathId};
+
+use crate::app::{AppWindow, SharedState};
+use crate::context::ContextState;
+
+use super::util::{lerp_color, path_presence_bins_in_range};
+
+/// The most columns computed, however wide the window
+const MAX_BINS: usize = 2048;
+/// Rows at least this tall get a label
+const LABEL_ROW_HEIGHT: f32 = 10.0;
+const LABEL_WIDTH: f32 = 160.0;
+
+/// The input the matrix was computed for
+#[derive(Debug, Clone, PartialEq)]
+struct MatrixKey {
+    range: Range<u64>,
+    bin_count: usize,
+    path_colors_generation: u64,
+}
+
+struct Matrix {
+    key: MatrixKey,
+    rows: Vec<PathId>,
+    // row-major, one value per path and bin
+    coverage: Vec<f32>,
+    texture: egui::TextureHandle,
+}
+
+pub struct PresenceMatrix {
+    shared: SharedState,
+
+    follow_1d: bool,
+    // the 1D view range, as of the last frame the 1D view was updated
+    view_range: Range<u64>,
+
+    matrix: Option<Matrix>,
+    task: Option<
+        JoinHandle<(MatrixKey, Vec<PathId>, Vec<f32>, egui::ColorImage)>,
+    >,
+}
+
+impl PresenceMatrix {
+    pub const TITLE: &'static str = "Path Presence Matrix";
+
+    pub fn new(shared: &SharedState) -> Self {
+        Self {
+            shared: shared.clone(),
+            follow_1d: true,
+            view_range: 0..shared.graph.pangenome_len().0,
+            matrix: None,
+            task: None,
+        }
+    }
+
+    fn range(&self) -> Range<u64> {
+        if self.follow_1d {
+            self.view_range.clone()
+        } else {
+            0..self.shared.graph.pangenome_len().0
+        }
+    }
+
+    /// Picks up the finished matrix, and starts computing a new one
+    /// if the range, width, or path visibility or colors changed.
+    fn update_matrix(
+        &mut self,
+        tokio_rt: &tokio::runtime::Handle,
+        ctx: &egui::Context,
+        bin_count: usize,
+    ) {
+        if self.task.as_ref().is_some_and(|t| t.is_finished()) {
+            let task = self.task.take().unwrap();
+            if let Ok((key, rows, coverage, image)) = tokio_rt.block_on(task) {
+                let texture = ctx.load_texture(
+                    "presence_matrix",
+                    image,
+                    egui::TextureOptions::NEAREST,
+                );
+                self.matrix = Some(Matrix {
+                    key,
+                    rows,
+                    coverage,
+                    texture,
+                });
+            }
+        }
+
+        let key = MatrixKey {
+            range: self.range(),
+            bin_count,
+            path_colors_generation: self
+                .shared
+                .path_colors
+                .blocking_read()
+                .generation(),
+        };
+
+        let up_to_date = self.matrix.as_ref().is_some_and(|m| m.key == key);
+
+        if up_to_date || self.task.is_some() || bin_count == 0 {
+            return;
+        }
+
+        let graph = self.shared.graph.clone();
+        let path_colors = self.shared.path_colors.clone();
+
+        self.task = Some(tokio_rt.spawn_blocking(move || {
+            let path_colors = path_colors.blocking_read();
+            let hidden = path_colors.hidden_1d_ids(&graph);
+
+            let mut rows = graph
+                .path_names
+                .left_values()
+                .copied()
+                .filter(|path| !hidden.contains(path))
+                .collect::<Vec<_>>();
+            rows.sort();
+
+            let mut coverage = Vec::with_capacity(rows.len() * bin_count);
+            let mut pixels = Vec::with_capacity(rows.len() * bin_count);
+
+            let absent = egui::Color32::from_gray(24);
+
+            for &path in rows.iter() {
+                let name = graph.path_names.get_by_left(&path).unwrap();
+                let color = path_colors.color(path, name);
+
+                let bins = path_presence_bins_in_range(
+                    &graph,
+                    path,
+                    key.range.clone(),
+                    bin_count,
+                );
+
+                pixels
+                    .extend(bins.iter().map(|&v| lerp_color(absent, color, v)));
+                coverage.extend(bins);
+            }
+
+            let image = egui::ColorImage {
+                size: [bin_count, rows.len()],
+                pixels,
+            };
+
+            (key, rows, coverage, image)
+        }));
+    }
+
+    fn show_matrix(&self, ui: &mut egui::Ui) {
+        let Some(matrix) = self.matrix.as_ref() else {
+            ui.centered_and_justified(|ui| ui.spinner());
+            return;
+        };
+
+        if matrix.rows.is_empty() {
+            ui.weak("No paths are shown in the 1D view");
+            return;
+        }
+
+        let rect = ui.available_rect_before_wrap();
+        let row_height = rect.height() / matrix.rows.len() as f32;
+        let labels = row_height >= LABEL_ROW_HEIGHT;
+
+        let matrix_rect = if labels {
+            rect.with_min_x(rect.left() + LABEL_WIDTH)
+        } else {
+            rect
+        };
+
+        let resp = ui.allocate_rect(rect, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+
+        painter.image(
+            matrix.texture.id(),
+            matrix_rect,
+            egui::Rect::from_min_max(
+                egui::pos2(0.0, 0.0),
+                egui::pos2(1.0, 1.0),
+            ),
+            egui::Color32::WHITE,
+        );
+
+        if labels {
+            let graph = &self.shared.graph;
+            let font = egui::FontId::monospace(row_height.min(14.0) - 2.0);
+
+            for (ix, path) in matrix.rows.iter().enumerate() {
+                let Some(name) = graph.path_names.get_by_left(path) else {
+                    continue;
+                };
+                let y = rect.top() + (ix as f32 + 0.5) * row_height;
+                painter.text(
+                    egui::pos2(matrix_rect.left() - 4.0, y),
+                    egui::Align2::RIGHT_CENTER,
+                    name,
+                    font.clone(),
+                    ui.visuals().text_color(),
+                );
+            }
+        }
+
+        let hovered = resp
+            .hover_pos()
+            .filter(|pos| matrix_rect.contains(*pos))
+            .map(|pos| {
+                let rel = (pos - matrix_rect.min) / matrix_rect.size();
+                let bins = matrix.key.bin_count;
+                let row = ((rel.y * matrix.rows.len() as f32) as usize)
+                    .min(matrix.rows.len() - 1);
+                let bin = ((rel.x * bins as f32) as usize).min(bins - 1);
+                (row, bin)
+            });
+
+        if let Some((row, bin)) = hovered {
+            let path = matrix.rows[row];
+            let name = self
+                .shared
+                .graph
+                .path_names
+                .get_by_left(&path)
+                .cloned()
+                .unwrap_or_default();
+            let value = matrix.coverage[row * matrix.key.bin_count + bin];
+
+            let range = &matrix.key.range;
+            let bin_len =
+                (range.end - range.start) as f64 / matrix.key.bin_count as f64;
+            let start = range.start + (bin as f64 * bin_len) as u64;
+            let end = range.start + ((bin + 1) as f64 * bin_len) as u64;
+            let region = self
+                .shared
+                .coords
+                .load()
+                .format_range(&(Bp(start)..Bp(end)));
+
+            resp.on_hover_ui_at_pointer(|ui| {
+                ui.label(name);
+                ui.label(region);
+                ui.label(format!("{:.1}% covered", value * 100.0));
+            });
+        }
+    }
+}
+
+impl AppWindow for PresenceMatrix {
+    fn update(
+        &mut self,
+        tokio_rt: &tokio::runtime::Handle,
+        _state: &raving_wgpu::State,
+        window: &raving_wgpu::WindowState,
+        egui_ctx: &mut EguiCtx,
+        context_state: &mut ContextState,
+        _dt: f32,
+    ) {
+        if let Some(range) = context_state
+            .query_get_cast::<_, Range<u64>>(Some("Viewer1D"), ["view_range"])
+        {
+            self.view_range = range.clone();
+        }
+
+        egui_ctx.begin_frame(&window.window);
+
+        let ctx = egui_ctx.ctx().clone();
+
+        egui::TopBottomPanel::top("presence_matrix_top").show(&ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.follow_1d, "Follow the 1D view")
+                    .on_hover_text(
+                        "Show the 1D view range, or the whole pangenome \
+                         when unchecked",
+                    );
+
+                let range = self.range();
+                let coords = self.shared.coords.load();
+                ui.label(
+                    coords.format_range(&(Bp(range.start)..Bp(range.end))),
+                );
+
+                if let Some(matrix) = self.matrix.as_ref() {
+                    ui.weak(format!(
+                        "{} paths × {} bins",
+                        matrix.rows.len(),
+                        matrix.key.bin_count
+                    ));
+                }
+            });
+        });
+
+        egui::CentralPanel::default().show(&ctx, |ui| {
+            let width = ui.available_width() - LABEL_WIDTH;
+            let bin_count = (width.max(1.0) as usize).min(MAX_BINS);
+
+            self.update_matrix(tokio_rt, &ctx, bin_count);
+            self.show_matrix(ui);
+        });
+
+        if self.task.is_some() {
+            ctx.request_repaint();
+        }
+
+        egui_ctx.end_frame(&window.window);
+    }
+
+    fn on_event(
+        &mut self,
+        _window_dims: [u32; 2],
+        _event: &winit::event::WindowEvent,
+    ) -> bool {
+        false
+    }
+
+    fn on_resize(
+        &mut self,
+        _state: &raving_wgpu::State,
+        _old_window_dims: [u32; 2],
+        _new_window_dims: [u32; 2],
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        _state: &raving_wgpu::State,
+        _window: &raving_wgpu::WindowState,
+        _swapchain_view: &wgpu::TextureView,
+        _encoder: &mut wgpu::CommandEncoder,
+    ) -> anyhow::Result<()> {
+        // everything is drawn with egui
+        Ok(())
+    }
+}
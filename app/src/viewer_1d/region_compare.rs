@@ -0,0 +1,815 @@
+//! Side-by-side comparison of two bookmarked regions: the nodes in
+//! each, how many they share, how much of each region every path
+//! covers, and the annotations within each, to show in a panel or
+//! export as TSV or HTML when writing up structural comparisons.
+
+use std::collections::BTreeSet;
+use std::io::prelude::*;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use tokio::sync::RwLock;
+use waragraph_core::export::html_escape;
+use waragraph_core::graph::coords::CoordSystem;
+use waragraph_core::graph::{Bp, Node, PathId, PathIndex};
+
+use crate::annotations::AnnotationSet;
+use crate::app::provenance::EventKind;
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::SharedState;
+
+use super::bookmarks::{Bookmark, BookmarkStore};
+use super::control::{path_range_to_pangenome, Msg, ViewCmd};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionAnnotation {
+    pub label: Arc<String>,
+    pub path: PathId,
+    /// In path coordinates
+    pub range: Range<Bp>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionSummary {
+    pub bookmark: Bookmark,
+    pub pangenome_range: Range<Bp>,
+    pub nodes: usize,
+    /// Total length of the nodes
+    pub length: Bp,
+    /// Nodes that aren't in the other region
+    pub unique_nodes: usize,
+    pub unique_length: Bp,
+    pub annotations: Vec<RegionAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathPresence {
+    pub path: PathId,
+    /// Length of the nodes of each region that the path is on
+    pub covered: [Bp; 2],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionComparison {
+    pub regions: [RegionSummary; 2],
+    pub shared_nodes: usize,
+    pub shared_length: Bp,
+    /// The paths on either region, by name
+    pub paths: Vec<PathPresence>,
+}
+
+/// The nodes of a bookmarked region: those the path steps on in the
+/// range for a path bookmark, and all nodes overlapping it otherwise.
+fn region_nodes(
+    graph: &PathIndex,
+    path: Option<PathId>,
+    range: &Range<Bp>,
+) -> BTreeSet<Node> {
+    if range.start >= range.end {
+        return BTreeSet::new();
+    }
+
+    match path {
+        Some(path) => graph
+            .path_step_range_iter(path, range.clone())
+            .into_iter()
+            .flatten()
+            .map(|(_, step)| step.node())
+            .collect(),
+        None => {
+            let end = range.end.min(graph.pangenome_len());
+            if range.start >= end {
+                return BTreeSet::new();
+            }
+            let nodes = graph.pos_range_nodes(range.start.0..end.0 - 1);
+            (nodes.start().ix()..=nodes.end().ix())
+                .map(Node::from)
+                .collect()
+        }
+    }
+}
+
+/// The annotations overlapping a bookmarked region; for a path
+/// bookmark, only those on the path.
+fn region_annotations(
+    graph: &PathIndex,
+    path: Option<PathId>,
+    range: &Range<Bp>,
+    pangenome_range: &Range<Bp>,
+    annotation_sets: &[Arc<AnnotationSet>],
+) -> Vec<RegionAnnotation> {
+    let overlaps =
+        |a: &Range<Bp>, b: &Range<Bp>| a.start < b.end && b.start < a.end;
+
+    let mut annotations = Vec::new();
+
+    for set in annotation_sets {
+        let in_region = set.annotations.iter().filter(|annot| match path {
+            Some(path) => annot.path == path && overlaps(&annot.range, range),
+            None => {
+                path_range_to_pangenome(graph, annot.path, annot.range.clone())
+                    .map_or(false, |r| overlaps(&r, pangenome_range))
+            }
+        });
+
+        annotations.extend(in_region.map(|annot| RegionAnnotation {
+            label: annot.label.clone(),
+            path: annot.path,
+            range: annot.range.clone(),
+        }));
+    }
+
+    annotations
+}
+
+fn nodes_length<'a>(
+    graph: &PathIndex,
+    nodes: impl Iterator<Item = &'a Node>,
+) -> Bp {
+    Bp(nodes.map(|&n| graph.node_length(n).0).sum())
+}
+
+/// Compares the regions of two bookmarks, with the annotations of
+/// `annotation_sets` within each.
+pub fn compare_regions(
+    graph: &PathIndex,
+    bookmarks: [&Bookmark; 2],
+    annotation_sets: &[Arc<AnnotationSet>],
+) -> Result<RegionComparison> {
+    let mut nodes = Vec::with_capacity(2);
+    let mut summaries = Vec::with_capacity(2);
+
+    for bookmark in bookmarks {
+        let path = bookmark
+            .path
+            .as_ref()
+            .map(|name| {
+                graph
+                    .path_names
+                    .get_by_right(name)
+                    .copied()
+                    .ok_or_else(|| anyhow!("Unknown path `{name}`"))
+            })
+            .transpose()?;
+
+        let pangenome_range = bookmark
+            .pangenome_range(graph)
+            .map_err(|e| anyhow!("Bookmark `{}`: {e}", bookmark.name))?;
+
+        let region_nodes = region_nodes(graph, path, &bookmark.range);
+
+        let annotations = region_annotations(
+            graph,
+            path,
+            &bookmark.range,
+            &pangenome_range,
+            annotation_sets,
+        );
+
+        summaries.push(RegionSummary {
+            bookmark: bookmark.clone(),
+            pangenome_range,
+            nodes: region_nodes.len(),
+            length: nodes_length(graph, region_nodes.iter()),
+            unique_nodes: 0,
+            unique_length: Bp(0),
+            annotations,
+        });
+        nodes.push(region_nodes);
+    }
+
+    let shared = nodes[0].intersection(&nodes[1]).count();
+    let shared_length = nodes_length(graph, nodes[0].intersection(&nodes[1]));
+
+    for (ix, summary) in summaries.iter_mut().enumerate() {
+        let (this, other) = (&nodes[ix], &nodes[1 - ix]);
+        summary.unique_nodes = this.difference(other).count();
+        summary.unique_length = nodes_length(graph, this.difference(other));
+    }
+
+    let mut paths = graph
+        .path_names
+        .iter()
+        .map(|(&path, name)| {
+            let covered = [0, 1].map(|ix| {
+                let on_path =
+                    nodes[ix].iter().filter(|&&n| graph.node_on_path(n, path));
+                nodes_length(graph, on_path)
+            });
+            (name, PathPresence { path, covered })
+        })
+        .filter(|(_, p)| p.covered.iter().any(|c| c.0 > 0))
+        .collect::<Vec<_>>();
+    paths.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(RegionComparison {
+        regions: summaries.try_into().expect("two regions"),
+        shared_nodes: shared,
+        shared_length,
+        paths: paths.into_iter().map(|(_, p)| p).collect(),
+    })
+}
+
+impl RegionSummary {
+    /// The bookmarked range, with the path name for a path range.
+    pub fn format_region(&self, coords: CoordSystem) -> String {
+        let range = coords.format_range(&self.bookmark.range);
+        match self.bookmark.path.as_ref() {
+            Some(path) => format!("{path}:{range}"),
+            None => range,
+        }
+    }
+}
+
+impl RegionComparison {
+    /// Label and value in each region of each scalar in the summary.
+    pub fn summary_rows(&self) -> Vec<(&'static str, [String; 2])> {
+        let both = |f: fn(&RegionSummary) -> String| {
+            [f(&self.regions[0]), f(&self.regions[1])]
+        };
+        let shared_nodes = self.shared_nodes.to_string();
+        let shared_length = self.shared_length.0.to_string();
+        let path_counts = [0, 1].map(|ix| {
+            let on_region = self.paths.iter().filter(|p| p.covered[ix].0 > 0);
+            on_region.count().to_string()
+        });
+
+        vec![
+            ("Nodes", both(|r| r.nodes.to_string())),
+            ("Length (bp)", both(|r| r.length.0.to_string())),
+            ("Shared nodes", [shared_nodes.clone(), shared_nodes]),
+            ("Shared bp", [shared_length.clone(), shared_length]),
+            ("Unique nodes", both(|r| r.unique_nodes.to_string())),
+            ("Unique bp", both(|r| r.unique_length.0.to_string())),
+            ("Paths", path_counts),
+            ("Annotations", both(|r| r.annotations.len().to_string())),
+        ]
+    }
+
+    /// The fraction of the region's node length the path covers.
+    pub fn presence_fraction(&self, presence: &PathPresence, ix: usize) -> f64 {
+        let len = self.regions[ix].length.0;
+        if len == 0 {
+            0.0
+        } else {
+            presence.covered[ix].0 as f64 / len as f64
+        }
+    }
+
+    /// Writes the summary, the path presence, and the annotations as
+    /// three tables separated by blank lines, with the positions in
+    /// the `coords` convention.
+    pub fn write_tsv(
+        &self,
+        graph: &PathIndex,
+        coords: CoordSystem,
+        mut out: impl Write,
+    ) -> Result<()> {
+        let [a, b] = &self.regions;
+
+        writeln!(out, "{}", coords.header_comment())?;
+        for (tag, region) in [("A", a), ("B", b)] {
+            writeln!(
+                out,
+                "# {tag}\t{}\t{}",
+                region.bookmark.name,
+                region.format_region(coords)
+            )?;
+        }
+
+        writeln!(out, "field\tA\tB")?;
+        for (label, [a, b]) in self.summary_rows() {
+            writeln!(out, "{label}\t{a}\t{b}")?;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "path\tA_bp\tA_fraction\tB_bp\tB_fraction")?;
+        for presence in &self.paths {
+            writeln!(
+                out,
+                "{}\t{}\t{:.4}\t{}\t{:.4}",
+                path_name(graph, presence.path),
+                presence.covered[0].0,
+                self.presence_fraction(presence, 0),
+                presence.covered[1].0,
+                self.presence_fraction(presence, 1),
+            )?;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "region\tlabel\tpath\tstart\tend")?;
+        for (tag, region) in [("A", a), ("B", b)] {
+            for annot in &region.annotations {
+                let (start, end) = coords.display_range(&annot.range);
+                writeln!(
+                    out,
+                    "{tag}\t{}\t{}\t{start}\t{end}",
+                    annot.label,
+                    path_name(graph, annot.path),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn write_html(
+        &self,
+        graph: &PathIndex,
+        coords: CoordSystem,
+        mut out: impl Write,
+    ) -> Result<()> {
+        let [a, b] = &self.regions;
+        let names = [a, b].map(|r| html_escape(&r.bookmark.name));
+
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html><head><meta charset=\"utf-8\">")?;
+        writeln!(
+            out,
+            "<title>Region comparison: {} vs. {}</title>",
+            names[0], names[1]
+        )?;
+        writeln!(
+            out,
+            "<style>body {{ font-family: sans-serif; }} \
+             table {{ border-collapse: collapse; margin-bottom: 1em; }} \
+             td, th {{ border: 1px solid #ccc; padding: 2px 8px; \
+             text-align: right; }} \
+             .regions {{ display: flex; gap: 2em; }}</style>"
+        )?;
+        writeln!(out, "</head><body>")?;
+        writeln!(
+            out,
+            "<h1>Region comparison: {} vs. {}</h1>",
+            names[0], names[1]
+        )?;
+        writeln!(out, "<p>Positions are {}.</p>", coords.short_name())?;
+
+        writeln!(out, "<h2>Summary</h2>\n<table>")?;
+        writeln!(
+            out,
+            "<tr><th></th><th>{}<br>{}</th><th>{}<br>{}</th></tr>",
+            names[0],
+            html_escape(&a.format_region(coords)),
+            names[1],
+            html_escape(&b.format_region(coords)),
+        )?;
+        for (label, [a, b]) in self.summary_rows() {
+            writeln!(out, "<tr><th>{label}</th><td>{a}</td><td>{b}</td></tr>")?;
+        }
+        writeln!(out, "</table>")?;
+
+        writeln!(out, "<h2>Path presence</h2>\n<table>")?;
+        writeln!(
+            out,
+            "<tr><th>Path</th><th>{0} bp</th><th>{0} %</th>\
+             <th>{1} bp</th><th>{1} %</th></tr>",
+            names[0], names[1]
+        )?;
+        for presence in &self.paths {
+            writeln!(
+                out,
+                "<tr><th>{}</th><td>{}</td><td>{:.1}</td>\
+                 <td>{}</td><td>{:.1}</td></tr>",
+                html_escape(path_name(graph, presence.path)),
+                presence.covered[0].0,
+                self.presence_fraction(presence, 0) * 100.0,
+                presence.covered[1].0,
+                self.presence_fraction(presence, 1) * 100.0,
+            )?;
+        }
+        writeln!(out, "</table>")?;
+
+        writeln!(out, "<h2>Annotations</h2>\n<div class=\"regions\">")?;
+        for (name, region) in names.iter().zip([a, b]) {
+            writeln!(out, "<div><h3>{name}</h3>\n<table>")?;
+            writeln!(
+                out,
+                "<tr><th>Label</th><th>Path</th><th>Start</th><th>End</th></tr>"
+            )?;
+            for annot in &region.annotations {
+                let (start, end) = coords.display_range(&annot.range);
+                writeln!(
+                    out,
+                    "<tr><th>{}</th><td>{}</td>\
+                     <td>{start}</td><td>{end}</td></tr>",
+                    html_escape(&annot.label),
+                    html_escape(path_name(graph, annot.path)),
+                )?;
+            }
+            writeln!(out, "</table></div>")?;
+        }
+        writeln!(out, "</div>")?;
+
+        writeln!(out, "</body></html>")?;
+
+        Ok(())
+    }
+}
+
+fn path_name(graph: &PathIndex, path: PathId) -> &str {
+    graph
+        .path_names
+        .get_by_left(&path)
+        .map(|name| name.as_str())
+        .unwrap_or_default()
+}
+
+/// Writes the report as HTML if the file extension is `html` or
+/// `htm`, and as TSV otherwise.
+pub fn export_report(
+    graph: &PathIndex,
+    comparison: &RegionComparison,
+    coords: CoordSystem,
+    path: &Path,
+) -> Result<()> {
+    let is_html = path
+        .extension()
+        .map(|ext| ext == "html" || ext == "htm")
+        .unwrap_or(false);
+
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    if is_html {
+        comparison.write_html(graph, coords, &mut out)?;
+    } else {
+        comparison.write_tsv(graph, coords, &mut out)?;
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Settings window panel comparing two bookmarks.
+pub struct RegionCompareWidget {
+    shared: SharedState,
+    store: Arc<RwLock<BookmarkStore>>,
+    msg_tx: crossbeam::channel::Sender<Msg>,
+
+    selected: [Option<usize>; 2],
+
+    comparison_recv: Option<oneshot::Receiver<Result<RegionComparison>>>,
+    comparison: Option<Arc<RegionComparison>>,
+    export_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    error: Option<String>,
+}
+
+impl RegionCompareWidget {
+    pub fn new(
+        shared: &SharedState,
+        store: Arc<RwLock<BookmarkStore>>,
+        msg_tx: crossbeam::channel::Sender<Msg>,
+    ) -> Self {
+        Self {
+            shared: shared.clone(),
+            store,
+            msg_tx,
+            selected: [None, None],
+            comparison_recv: None,
+            comparison: None,
+            export_path_recv: None,
+            error: None,
+        }
+    }
+
+    fn spawn_compare(
+        &mut self,
+        tokio_handle: &tokio::runtime::Handle,
+        bookmarks: [Bookmark; 2],
+    ) {
+        let graph = self.shared.graph.clone();
+        let annotation_sets = self
+            .shared
+            .annotations
+            .blocking_read()
+            .annotation_sets
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let (send, recv) = oneshot::channel();
+
+        tokio_handle.spawn_blocking(move || {
+            let [a, b] = &bookmarks;
+            let result = compare_regions(&graph, [a, b], &annotation_sets);
+            let _ = send.send(result);
+        });
+
+        self.comparison_recv = Some(recv);
+        self.error = None;
+    }
+
+    fn poll_channels(&mut self) {
+        if let Some(mut recv) = self.comparison_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok(comparison)) => {
+                    self.comparison = Some(Arc::new(comparison))
+                }
+                Ok(Err(e)) => self.error = Some(e.to_string()),
+                Err(TryRecvError::Empty) => self.comparison_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.export_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    let Some(comparison) = self.comparison.as_ref() else {
+                        return;
+                    };
+                    let coords = self.shared.coords.load();
+                    match export_report(
+                        &self.shared.graph,
+                        comparison,
+                        coords,
+                        &path,
+                    ) {
+                        Ok(()) => {
+                            self.shared
+                                .provenance
+                                .blocking_write()
+                                .record_path(
+                                    EventKind::Export,
+                                    "Region comparison",
+                                    &path,
+                                );
+                        }
+                        Err(e) => {
+                            log::error!("Error exporting comparison: {e:?}");
+                            self.error = Some(e.to_string());
+                        }
+                    }
+                }
+                Err(TryRecvError::Empty) => self.export_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+
+    fn goto(&self, region: &RegionSummary) {
+        let range = region.pangenome_range.clone();
+        let _ = self.msg_tx.send(Msg::View(ViewCmd::SetRange { range }));
+    }
+}
+
+impl SettingsWidget for RegionCompareWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels();
+
+        let coords = self.shared.coords.load();
+        let bookmarks = self.store.blocking_read().bookmarks().to_vec();
+
+        // the bookmarks may have been removed since they were picked
+        for selected in self.selected.iter_mut() {
+            if selected.is_some_and(|ix| ix >= bookmarks.len()) {
+                *selected = None;
+            }
+        }
+
+        let resp = ui.vertical(|ui| {
+            if bookmarks.len() < 2 {
+                ui.label("Add at least two bookmarks to compare them");
+            }
+
+            egui::Grid::new("RegionCompareWidget-pick").show(ui, |ui| {
+                for (tag, selected) in ["A", "B"].iter().zip(&mut self.selected)
+                {
+                    ui.label(format!("Region {tag}:"));
+                    let text = selected
+                        .map(|ix| bookmarks[ix].name.as_str())
+                        .unwrap_or("Select a bookmark");
+                    egui::ComboBox::from_id_source((
+                        "RegionCompareWidget",
+                        tag,
+                    ))
+                    .selected_text(text)
+                    .show_ui(ui, |ui| {
+                        for (ix, bookmark) in bookmarks.iter().enumerate() {
+                            ui.selectable_value(
+                                selected,
+                                Some(ix),
+                                &bookmark.name,
+                            );
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let comparing = self.comparison_recv.is_some();
+                let picked = self.selected[0].zip(self.selected[1]);
+
+                if ui
+                    .add_enabled(
+                        picked.is_some() && !comparing,
+                        egui::Button::new("Compare"),
+                    )
+                    .clicked()
+                {
+                    if let Some((a, b)) = picked {
+                        let picked =
+                            [bookmarks[a].clone(), bookmarks[b].clone()];
+                        self.spawn_compare(&settings_ctx.tokio_handle, picked);
+                    }
+                }
+
+                if comparing {
+                    ui.spinner();
+                }
+
+                if ui
+                    .add_enabled(
+                        self.comparison.is_some(),
+                        egui::Button::new("Export"),
+                    )
+                    .on_hover_text(
+                        "Saved as HTML if the file name ends with .html, \
+                         and as TSV otherwise",
+                    )
+                    .clicked()
+                {
+                    let path = self
+                        .shared
+                        .export_paths
+                        .blocking_read()
+                        .export_path("region_comparison", None, "html");
+
+                    let id = egui::Id::new("RegionCompareWidget_export");
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.export_path_recv = Some(recv);
+                }
+            });
+
+            if let Some(err) = self.error.as_ref() {
+                ui.label(format!("Error: {err}"));
+            }
+
+            let Some(comparison) = self.comparison.clone() else {
+                return;
+            };
+            let graph = &self.shared.graph;
+
+            ui.separator();
+
+            egui::Grid::new("RegionCompareWidget-summary")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("");
+                    for region in comparison.regions.iter() {
+                        ui.vertical(|ui| {
+                            if ui.link(&region.bookmark.name).clicked() {
+                                self.goto(region);
+                            }
+                            ui.weak(region.format_region(coords));
+                        });
+                    }
+                    ui.end_row();
+
+                    for (label, values) in comparison.summary_rows() {
+                        ui.label(label);
+                        for value in values {
+                            ui.label(value);
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            egui::CollapsingHeader::new("Path presence").show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_source("RegionCompareWidget-paths-scroll")
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("RegionCompareWidget-paths")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.label("Path");
+                                for region in comparison.regions.iter() {
+                                    ui.label(&region.bookmark.name);
+                                }
+                                ui.end_row();
+
+                                for presence in comparison.paths.iter() {
+                                    ui.label(path_name(graph, presence.path));
+                                    for ix in 0..2 {
+                                        let fraction = comparison
+                                            .presence_fraction(presence, ix);
+                                        ui.label(format!(
+                                            "{} bp ({:.1}%)",
+                                            presence.covered[ix].0,
+                                            fraction * 100.0
+                                        ));
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+
+            for (ix, region) in comparison.regions.iter().enumerate() {
+                let header = format!(
+                    "Annotations in {} ({})",
+                    region.bookmark.name,
+                    region.annotations.len()
+                );
+                egui::CollapsingHeader::new(header)
+                    .id_source(("RegionCompareWidget-annots", ix))
+                    .show(ui, |ui| {
+                        egui::ScrollArea::vertical()
+                            .id_source((
+                                "RegionCompareWidget-annots-scroll",
+                                ix,
+                            ))
+                            .max_height(200.0)
+                            .show(ui, |ui| {
+                                for annot in region.annotations.iter() {
+                                    let range =
+                                        coords.format_range(&annot.range);
+                                    ui.label(format!(
+                                        "{}  {}:{range}",
+                                        annot.label,
+                                        path_name(graph, annot.path),
+                                    ));
+                                }
+                            });
+                    });
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_bubble_alleles() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tC
+S\t3\tGG
+S\t4\tTT
+L\t1\t+\t2\t+\t0M
+L\t1\t+\t3\t+\t0M
+L\t2\t+\t4\t+\t0M
+L\t3\t+\t4\t+\t0M
+P\ta\t1+,2+,4+\t*
+P\tb\t1+,3+,4+\t*
+";
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let bookmark = |name: &str, path: &str, range: Range<u64>| Bookmark {
+            name: name.to_string(),
+            path: Some(path.to_string()),
+            range: Bp(range.start)..Bp(range.end),
+        };
+
+        // each path's allele with the flanking bases
+        let a = bookmark("a", "a", 3..6);
+        let b = bookmark("b", "b", 3..7);
+
+        let comparison = compare_regions(&graph, [&a, &b], &[]).unwrap();
+        let [ra, rb] = &comparison.regions;
+
+        assert_eq!((ra.nodes, ra.length), (3, Bp(7)));
+        assert_eq!((rb.nodes, rb.length), (3, Bp(8)));
+        assert_eq!(comparison.shared_nodes, 2);
+        assert_eq!(comparison.shared_length, Bp(6));
+        assert_eq!((ra.unique_nodes, ra.unique_length), (1, Bp(1)));
+        assert_eq!((rb.unique_nodes, rb.unique_length), (1, Bp(2)));
+
+        let presence = comparison
+            .paths
+            .iter()
+            .map(|p| (path_name(&graph, p.path), p.covered))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            presence,
+            vec![("a", [Bp(7), Bp(6)]), ("b", [Bp(6), Bp(8)])]
+        );
+
+        let mut tsv = Vec::new();
+        comparison
+            .write_tsv(&graph, CoordSystem::ZeroBased, &mut tsv)
+            .unwrap();
+        let tsv = String::from_utf8(tsv).unwrap();
+        assert!(tsv.contains("Shared nodes\t2\t2\n"));
+        assert!(tsv.contains("a\t7\t1.0000\t6\t0.7500\n"));
+
+        let unknown = bookmark("c", "c", 0..1);
+        assert!(compare_regions(&graph, [&a, &unknown], &[]).is_err());
+    }
+}
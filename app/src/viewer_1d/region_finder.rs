@@ -0,0 +1,213 @@
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::complexity::{
+    find_complex_regions, CandidateRegion, ComplexityParams,
+};
+use waragraph_core::graph::{Bp, PathIndex};
+
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::{Args, SharedState};
+
+use super::control::{Msg, ViewCmd};
+
+/// Entry point for `--find-regions`: prints the candidate regions as
+/// TSV, ranked by score.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = PathIndex::from_file(&args.gfa)?;
+
+    let reference = args
+        .reference
+        .as_ref()
+        .map(|name| {
+            graph
+                .path_names
+                .get_by_right(name)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("Unknown path `{name}`"))
+        })
+        .transpose()?;
+
+    let params = ComplexityParams {
+        reference,
+        ..ComplexityParams::default()
+    };
+
+    let regions = find_complex_regions(&graph, &params);
+
+    println!("{}", args.coords.header_comment());
+    println!("rank\tstart\tend\tscore\texcess_edges\tlongest_insertion");
+
+    for (rank, region) in regions.iter().enumerate() {
+        let (start, end) = args.coords.display_range(&region.range);
+        println!(
+            "{}\t{start}\t{end}\t{:.3}\t{}\t{}",
+            rank + 1,
+            region.score,
+            region.excess_edges,
+            region.longest_insertion.0
+        );
+    }
+
+    Ok(())
+}
+
+/// Settings window panel listing the candidate regions; clicking a
+/// region moves the 1D view to it.
+pub struct RegionFinderWidget {
+    shared: SharedState,
+    msg_tx: crossbeam::channel::Sender<Msg>,
+
+    reference_name: String,
+    window: u64,
+
+    regions_recv: Option<oneshot::Receiver<Vec<CandidateRegion>>>,
+    regions: Vec<CandidateRegion>,
+}
+
+impl RegionFinderWidget {
+    pub fn new(
+        shared: &SharedState,
+        msg_tx: crossbeam::channel::Sender<Msg>,
+    ) -> Self {
+        let reference_name = shared.detected_reference_name();
+
+        Self {
+            shared: shared.clone(),
+            msg_tx,
+
+            reference_name,
+            window: ComplexityParams::default().window.0,
+
+            regions_recv: None,
+            regions: Vec::new(),
+        }
+    }
+
+    fn spawn_search(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        let graph = self.shared.graph.clone();
+
+        let reference = graph
+            .path_names
+            .get_by_right(self.reference_name.trim())
+            .copied();
+
+        if reference.is_none() && !self.reference_name.trim().is_empty() {
+            log::warn!(
+                "Unknown reference path `{}`, scoring bubbles only",
+                self.reference_name
+            );
+        }
+
+        let params = ComplexityParams {
+            window: Bp(self.window),
+            reference,
+            ..ComplexityParams::default()
+        };
+
+        let (send, recv) = oneshot::channel();
+
+        tokio_handle.spawn_blocking(move || {
+            let regions = find_complex_regions(&graph, &params);
+            let _ = send.send(regions);
+        });
+
+        self.regions_recv = Some(recv);
+    }
+}
+
+impl SettingsWidget for RegionFinderWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        if let Some(mut recv) = self.regions_recv.take() {
+            match recv.try_recv() {
+                Ok(regions) => self.regions = regions,
+                Err(TryRecvError::Empty) => self.regions_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        let coords = self.shared.coords.load();
+
+        let resp = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Reference path:");
+                ui.text_edit_singleline(&mut self.reference_name);
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Window (bp):");
+                ui.add(
+                    egui::DragValue::new(&mut self.window)
+                        .clamp_range(100..=1_000_000)
+                        .speed(100),
+                );
+
+                let searching = self.regions_recv.is_some();
+
+                if ui
+                    .add_enabled(!searching, egui::Button::new("Find regions"))
+                    .clicked()
+                {
+                    self.spawn_search(&settings_ctx.tokio_handle);
+                }
+
+                if searching {
+                    ui.spinner();
+                }
+            });
+
+            egui::ScrollArea::vertical()
+                .max_height(400.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("RegionFinderWidget-regions")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label(format!(
+                                "Region ({})",
+                                coords.short_name()
+                            ));
+                            ui.label("Score");
+                            ui.label("Excess edges");
+                            ui.label("Longest insertion");
+                            ui.end_row();
+
+                            let mut links = Vec::new();
+
+                            for region in &self.regions {
+                                let range = &region.range;
+                                let label = coords.format_range(range);
+
+                                let link = ui.link(label);
+                                if link.clicked() {
+                                    let _ = self.msg_tx.send(Msg::View(
+                                        ViewCmd::GotoRange {
+                                            path: None,
+                                            range: range.clone(),
+                                        },
+                                    ));
+                                }
+                                links.push(link);
+
+                                ui.label(format!("{:.2}", region.score));
+                                ui.label(region.excess_edges.to_string());
+                                ui.label(format!(
+                                    "{} bp",
+                                    region.longest_insertion.0
+                                ));
+                                ui.end_row();
+                            }
+
+                            crate::gui::util::arrow_key_focus(ui, &links);
+                        });
+                });
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
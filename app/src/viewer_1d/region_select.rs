@@ -0,0 +1,261 @@
+//! Rubber-band selection of a pangenome range in the 1D view, made by
+//! shift-dragging over the paths, with a window to zoom to the region,
+//! copy it as a region string, or annotate it. The selected region is
+//! kept in the shared selection, as its last range.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use waragraph_core::graph::{Bp, PathId, PathIndex};
+
+use crate::annotations::{Annotation, USER_SET_NAME};
+use crate::app::SharedState;
+use crate::gui::coords::PosFormat;
+
+use super::control::{Msg, ViewCmd};
+use super::view::View1D;
+
+pub struct RegionSelection {
+    // where the drag in progress started
+    drag_start: Option<Bp>,
+    /// The path the drag started on, which regions are annotated on
+    path: Option<PathId>,
+    // the region being dragged over
+    drag_region: Option<Range<Bp>>,
+
+    open: bool,
+    label: String,
+}
+
+impl std::default::Default for RegionSelection {
+    fn default() -> Self {
+        Self {
+            drag_start: None,
+            path: None,
+            drag_region: None,
+            open: false,
+            label: "region".to_string(),
+        }
+    }
+}
+
+impl RegionSelection {
+    /// Starts a selection on shift-drag, and updates it until the drag
+    /// is released. Returns true while selecting, when the drag
+    /// shouldn't pan the view.
+    pub fn update(
+        &mut self,
+        shared: &SharedState,
+        resp: &egui::Response,
+        view: &View1D,
+        x_range: &std::ops::RangeInclusive<f32>,
+        hovered_path: Option<PathId>,
+    ) -> bool {
+        let shift = resp.ctx.input(|i| i.modifiers.shift);
+        let pointer = resp
+            .interact_pointer_pos()
+            .map(|pos| view.screen_map(x_range).x_to_bp(pos.x));
+
+        if resp.drag_started() && shift {
+            self.drag_start = pointer;
+            self.path = hovered_path;
+        }
+
+        let Some(start) = self.drag_start else {
+            return false;
+        };
+
+        if let Some(pos) = pointer {
+            self.drag_region = Some(start.min(pos)..Bp(start.max(pos).0 + 1));
+        }
+
+        if resp.drag_released() {
+            self.drag_start = None;
+            self.open = true;
+
+            let region = self.drag_region.take();
+            shared.selection.blocking_write().select_range_only(region);
+        }
+
+        true
+    }
+
+    /// Shades the region being dragged over the path slots in `rect`.
+    pub fn shapes(
+        &self,
+        view: &View1D,
+        rect: egui::Rect,
+        color: egui::Color32,
+    ) -> Vec<egui::Shape> {
+        let Some(region) = self.drag_region.as_ref() else {
+            return Vec::new();
+        };
+
+        region_band_shapes(view, rect, region, color)
+    }
+
+    pub fn show_window(
+        &mut self,
+        ctx: &egui::Context,
+        shared: &SharedState,
+        msg_tx: &crossbeam::channel::Sender<Msg>,
+    ) {
+        if self.drag_start.is_some() {
+            return;
+        }
+
+        let region = shared.selection.blocking_read().ranges().last().cloned();
+        let Some(region) = region else {
+            self.open = false;
+            return;
+        };
+
+        let graph = &shared.graph;
+        let fmt = PosFormat::load(shared);
+        let path_range = self
+            .path
+            .and_then(|path| Some((path, path_range(graph, path, &region)?)));
+
+        let mut open = self.open;
+        let mut clear = false;
+
+        egui::Window::new("Selected region")
+            .id(egui::Id::new("Viewer1D-RegionSelection"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let len = region.end.0 - region.start.0;
+                ui.label(format!("{} ({len} bp)", fmt.range(graph, &region)));
+
+                if let Some((path, range)) = path_range.as_ref() {
+                    ui.label(fmt.path_range(graph, *path, range));
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Zoom to selection").clicked() {
+                        let cmd = ViewCmd::SetRange {
+                            range: region.clone(),
+                        };
+                        if let Err(e) = msg_tx.send(Msg::View(cmd)) {
+                            log::error!("{e:?}");
+                        }
+                    }
+
+                    // the region on the path if the drag started on one
+                    if ui.button("Copy region").clicked() {
+                        let text = match path_range.as_ref() {
+                            Some((path, range)) => {
+                                region_string(graph, fmt, *path, range)
+                            }
+                            None => fmt.range(graph, &region),
+                        };
+                        ui.output_mut(|o| o.copied_text = text);
+                    }
+
+                    if ui.button("Clear").clicked() {
+                        clear = true;
+                    }
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("Label");
+                    ui.text_edit_singleline(&mut self.label);
+                });
+
+                let annotate = ui
+                    .add_enabled(
+                        path_range.is_some(),
+                        egui::Button::new("Create annotation here"),
+                    )
+                    .on_disabled_hover_text(
+                        "Shift-drag over a path that traverses the region \
+                         to annotate it",
+                    );
+
+                if annotate.clicked() {
+                    if let Some((path, range)) = path_range.clone() {
+                        let annot = Annotation {
+                            path,
+                            range,
+                            label: Arc::new(self.label.clone()),
+                            color: None,
+                            blocks: Vec::new(),
+                            out_of_bounds: false,
+                            attributes: Vec::new(),
+                            label_color: None,
+                        };
+
+                        shared
+                            .annotations
+                            .blocking_write()
+                            .add_annotation(USER_SET_NAME, annot);
+                    }
+                }
+
+                ui.weak(format!(
+                    "Annotations are added to the \"{USER_SET_NAME}\" set"
+                ));
+            });
+
+        self.open = open;
+        if clear {
+            shared.selection.blocking_write().remove_range(&region);
+        }
+    }
+}
+
+/// A band over `rect` shading the region, or nothing if the region
+/// is out of view.
+pub fn region_band_shapes(
+    view: &View1D,
+    rect: egui::Rect,
+    region: &Range<Bp>,
+    color: egui::Color32,
+) -> Vec<egui::Shape> {
+    let screen_map = view.screen_map(&rect.x_range());
+    let x0 = screen_map.bp_to_x(region.start).max(rect.left());
+    let x1 = screen_map.bp_to_x(region.end).min(rect.right());
+
+    if x1 < x0 {
+        return Vec::new();
+    }
+
+    let band = egui::Rect::from_x_y_ranges(x0..=x1, rect.y_range());
+    vec![
+        egui::Shape::rect_filled(band, 0.0, color.linear_multiply(0.2)),
+        egui::Shape::rect_stroke(band, 0.0, egui::Stroke::new(1.0, color)),
+    ]
+}
+
+/// The part of `path` spanning the region, from its first to its
+/// last base in the region.
+fn path_range(
+    graph: &PathIndex,
+    path: PathId,
+    region: &Range<Bp>,
+) -> Option<Range<Bp>> {
+    graph
+        .pangenome_range_to_path_ranges(region.clone())
+        .into_iter()
+        .filter(|r| r.path == path)
+        .map(|r| r.range)
+        .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+}
+
+/// The region as `path:start-end`, in the displayed coordinate
+/// convention.
+fn region_string(
+    graph: &PathIndex,
+    fmt: PosFormat,
+    path: PathId,
+    range: &Range<Bp>,
+) -> String {
+    let name = graph
+        .path_names
+        .get_by_left(&path)
+        .map(|n| n.as_str())
+        .unwrap_or("path");
+    format!("{name}:{}", fmt.coords.format_range(range))
+}
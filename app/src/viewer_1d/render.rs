@@ -6,7 +6,9 @@ use raving_wgpu::graph::dfrog::Graph;
 use raving_wgpu::{NodeId, State, WindowState};
 
 use anyhow::Result;
-use waragraph_core::graph::{PathId, PathIndex};
+use waragraph_core::graph::{Bp, PathId, PathIndex};
+
+use super::transform::BpScreenMap;
 
 // contains all the config/info needed to render a data buffer
 // sampled from the data source corresponding to `data_key`
@@ -26,23 +28,14 @@ pub fn sequence_shapes_in_slot(
     rect: egui::Rect,
     shapes: &mut Vec<egui::Shape>,
 ) {
-    let view_len = (view_range.end - view_range.start) as f64;
-
     let path_set = &graph.path_node_sets[path.ix()];
-    let bp_width = (rect.width() as f64 / view_len) as f32;
+    let map = BpScreenMap::new(view_range.clone(), rect.x_range());
+    let bp_width = map.px_per_bp();
 
     let view_start = view_range.start;
 
-    for (node, span) in graph.nodes_span_iter(view_range.clone()) {
+    for (node, _span) in graph.nodes_span_iter(view_range.clone()) {
         if path_set.contains(node.into()) {
-            let span_l = (span.start.0 - view_start) as f32;
-            let span_r = (span.end.0 - view_start) as f32;
-
-            let xl = rect.left() + span_l * bp_width;
-            let xr = rect.left() + span_r * bp_width;
-
-            let y_range = rect.y_range();
-
             let seq = graph.node_sequence(node);
 
             let node_start = graph.node_offset(node).0;
@@ -50,8 +43,9 @@ pub fn sequence_shapes_in_slot(
             let to_skip =
                 (view_start.checked_sub(node_start)).unwrap_or(0) as usize;
 
-            for (ix, &base) in seq.iter().skip(to_skip).enumerate() {
-                let x = xl + bp_width / 2.0 + bp_width * ix as f32;
+            for (ix, &base) in seq.iter().enumerate().skip(to_skip) {
+                let pos = Bp(node_start + ix as u64);
+                let x = map.bp_to_x(pos) + bp_width / 2.0;
 
                 let c = base as char;
 
@@ -0,0 +1,183 @@
+//! Coordinate ruler drawn above the 1D slots, with the ticks spaced to
+//! suit the zoom level. Dragging along the ruler selects the nodes in
+//! the dragged range.
+
+use std::ops::Range;
+
+use waragraph_core::graph::Bp;
+
+use super::view::View1D;
+
+/// Minimum distance between labeled ticks, in points
+const MIN_TICK_GAP: f32 = 90.0;
+
+/// The smallest of 1, 2, and 5 times a power of ten, in bp, that
+/// keeps ticks at least `min_gap` points apart.
+pub fn tick_spacing(view_len: u64, width: f32, min_gap: f32) -> u64 {
+    if view_len == 0 || width <= 0.0 {
+        return 1;
+    }
+
+    let min_bp = view_len as f64 * min_gap as f64 / width as f64;
+
+    let mut magnitude = 1u64;
+    loop {
+        for m in [1, 2, 5] {
+            let step = magnitude * m;
+            if step as f64 >= min_bp {
+                return step;
+            }
+        }
+        magnitude *= 10;
+    }
+}
+
+/// Formats a tick position with the largest unit that fits it, and
+/// enough decimals to tell apart ticks `step` bp apart.
+pub fn format_bp(pos: u64, step: u64) -> String {
+    let (unit, suffix) = match pos {
+        p if p >= 1_000_000_000 => (1_000_000_000, "Gbp"),
+        p if p >= 1_000_000 => (1_000_000, "Mbp"),
+        p if p >= 1_000 => (1_000, "kbp"),
+        _ => return format!("{pos} bp"),
+    };
+
+    let step = step.max(1);
+    let decimals = (0..9u32)
+        .find(|&d| (step * 10u64.pow(d)) % unit == 0)
+        .unwrap_or(9) as usize;
+
+    format!("{:.*} {suffix}", decimals, pos as f64 / unit as f64)
+}
+
+#[derive(Default)]
+pub struct Ruler {
+    // where the drag in progress started
+    drag_start: Option<Bp>,
+}
+
+impl Ruler {
+    /// Draws the ruler for the view in `rect`, and returns the range
+    /// dragged along it, once the drag is released.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        view: &View1D,
+        drag_fill: egui::Color32,
+    ) -> Option<Range<Bp>> {
+        let resp = ui.allocate_rect(rect, egui::Sense::drag());
+        let screen_map = view.screen_map(&rect.x_range());
+
+        let pointer_bp = resp
+            .interact_pointer_pos()
+            .map(|pos| screen_map.x_to_bp(pos.x));
+
+        if resp.drag_started() {
+            self.drag_start = pointer_bp;
+        }
+
+        let dragged = self.drag_start.zip(pointer_bp).map(|(a, b)| {
+            let (start, end) = (a.min(b), a.max(b));
+            start..Bp(end.0 + 1)
+        });
+
+        let painter = ui.painter_at(rect);
+
+        if let Some(range) = dragged.as_ref() {
+            let x0 = screen_map.bp_to_x(range.start);
+            let x1 = screen_map.bp_to_x(range.end);
+            let fill_rect =
+                egui::Rect::from_x_y_ranges(x0..=x1, rect.y_range());
+            painter.rect_filled(fill_rect, 0.0, drag_fill);
+        }
+
+        self.draw_ticks(&painter, rect, view);
+
+        if resp.drag_released() {
+            self.drag_start = None;
+            return dragged;
+        }
+
+        None
+    }
+
+    fn draw_ticks(
+        &self,
+        painter: &egui::Painter,
+        rect: egui::Rect,
+        view: &View1D,
+    ) {
+        let range = view.range();
+        let screen_map = view.screen_map(&rect.x_range());
+
+        let step = tick_spacing(view.len(), rect.width(), MIN_TICK_GAP);
+        // minor ticks split each step in five, or in two for steps
+        // starting with 2
+        let mantissa = step / 10u64.pow(step.ilog10());
+        let minor = if mantissa == 2 { step / 2 } else { step / 5 };
+
+        let color = egui::Color32::from_gray(200);
+        let stroke = egui::Stroke::new(1.0, color);
+        let font_id = egui::FontId::monospace(12.0);
+
+        let bottom = rect.bottom() - 1.0;
+        painter.line_segment(
+            [
+                egui::pos2(rect.left(), bottom),
+                egui::pos2(rect.right(), bottom),
+            ],
+            stroke,
+        );
+
+        if minor > 0 {
+            let first = (range.start + minor - 1) / minor * minor;
+            for pos in (first..range.end).step_by(minor as usize) {
+                if pos % step == 0 {
+                    continue;
+                }
+                let x = screen_map.bp_to_x(Bp(pos));
+                painter.line_segment(
+                    [egui::pos2(x, bottom - 4.0), egui::pos2(x, bottom)],
+                    stroke,
+                );
+            }
+        }
+
+        let first = (range.start + step - 1) / step * step;
+        for pos in (first..=range.end).step_by(step as usize) {
+            let x = screen_map.bp_to_x(Bp(pos));
+            painter.line_segment(
+                [egui::pos2(x, bottom - 8.0), egui::pos2(x, bottom)],
+                stroke,
+            );
+            painter.text(
+                egui::pos2(x + 3.0, rect.top()),
+                egui::Align2::LEFT_TOP,
+                format_bp(pos, step),
+                font_id.clone(),
+                color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_and_units() {
+        // 1000 bp over 1000 points, at least 90 points apart
+        assert_eq!(tick_spacing(1000, 1000.0, 90.0), 100);
+        assert_eq!(tick_spacing(1000, 100.0, 90.0), 1000);
+        assert_eq!(tick_spacing(3_000_000, 1000.0, 90.0), 500_000);
+        assert_eq!(tick_spacing(10, 1000.0, 90.0), 1);
+
+        assert_eq!(format_bp(500, 100), "500 bp");
+        assert_eq!(format_bp(1500, 500), "1.5 kbp");
+        assert_eq!(format_bp(2000, 1000), "2 kbp");
+        assert_eq!(format_bp(3_500_000, 500_000), "3.5 Mbp");
+        assert_eq!(format_bp(1_020_000, 20_000), "1.02 Mbp");
+    }
+}
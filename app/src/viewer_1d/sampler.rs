@@ -1,9 +1,11 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use crossbeam::atomic::AtomicCell;
 
 use anyhow::Result;
 
+use waragraph_core::graph::sampling::BinAggregate;
 use waragraph_core::graph::{Bp, PathId, PathIndex};
 
 use crate::app::resource::GraphDataCache;
@@ -35,6 +37,7 @@ pub struct PathDataSampler {
     path_index: Arc<PathIndex>,
     data_cache: Arc<GraphDataCache>,
     data_key: Arc<String>,
+    aggregate: Arc<AtomicCell<BinAggregate>>,
 }
 
 impl PathDataSampler {
@@ -47,8 +50,19 @@ impl PathDataSampler {
             path_index,
             data_cache,
             data_key: Arc::new(data_key.to_string()),
+            aggregate: Arc::new(AtomicCell::new(BinAggregate::Mean)),
         }
     }
+
+    /// Combines the values in each bin with the aggregate in the
+    /// cell at the time of sampling, rather than the mean.
+    pub fn with_aggregate(
+        mut self,
+        aggregate: Arc<AtomicCell<BinAggregate>>,
+    ) -> Self {
+        self.aggregate = aggregate;
+        self
+    }
 }
 
 #[async_trait]
@@ -65,6 +79,7 @@ impl Sampler for PathDataSampler {
             .await?;
 
         let path_index = self.path_index.clone();
+        let aggregate = self.aggregate.load();
 
         let sample_vec = tokio::task::spawn_blocking(move || {
             let mut buf = vec![0u8; 4 * bin_count];
@@ -75,12 +90,13 @@ impl Sampler for PathDataSampler {
             let used_bins = view_len.min(bin_count);
             let used_slice = &mut buf[..used_bins * 4];
 
-            waragraph_core::graph::sampling::sample_data_into_buffer(
+            waragraph_core::graph::sampling::sample_data_into_buffer_with(
                 &path_index,
                 path,
                 &data.path_data,
                 l..r,
                 bytemuck::cast_slice_mut(used_slice),
+                aggregate,
             );
 
             buf
@@ -0,0 +1,218 @@
+//! Showing the sequence around the hovered position, in a window and
+//! in the node tooltips.
+
+use waragraph_core::graph::{Bp, PathId, PathIndex};
+
+/// A short snippet of `seq` around `center`, with the center base in
+/// brackets, for tooltips.
+pub fn sequence_snippet(seq: &[u8], center: usize, radius: usize) -> String {
+    if seq.is_empty() {
+        return String::new();
+    }
+
+    let center = center.min(seq.len() - 1);
+    let start = center.saturating_sub(radius);
+    let end = (center + radius + 1).min(seq.len());
+
+    let text = |s: &[u8]| String::from_utf8_lossy(s).into_owned();
+
+    format!(
+        "{}{}[{}]{}{}",
+        if start > 0 { "…" } else { "" },
+        text(&seq[start..center]),
+        text(&seq[center..center + 1]),
+        text(&seq[center + 1..end]),
+        if end < seq.len() { "…" } else { "" },
+    )
+}
+
+/// The position the sequence panel is showing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SeqPos {
+    Pangenome(Bp),
+    Path(PathId, Bp),
+}
+
+/// Window with the sequence around the hovered position, on the
+/// hovered path if there is one, and in pangenome space otherwise.
+pub struct SequencePanel {
+    radius: u64,
+    line_width: usize,
+
+    // kept when the cursor leaves the viewers, so that the sequence
+    // can be copied
+    pos: Option<SeqPos>,
+}
+
+impl std::default::Default for SequencePanel {
+    fn default() -> Self {
+        Self {
+            radius: 50,
+            line_width: 60,
+            pos: None,
+        }
+    }
+}
+
+impl SequencePanel {
+    /// Updates the shown position; `path_pos` is preferred over
+    /// `pan_pos` if both are set.
+    pub fn set_hover(
+        &mut self,
+        pan_pos: Option<Bp>,
+        path_pos: Option<(PathId, Bp)>,
+    ) {
+        let pos = path_pos
+            .map(|(path, pos)| SeqPos::Path(path, pos))
+            .or(pan_pos.map(SeqPos::Pangenome));
+
+        if pos.is_some() {
+            self.pos = pos;
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        graph: &PathIndex,
+    ) {
+        egui::Window::new("Sequence")
+            .id(egui::Id::new("Viewer1D-Sequence-Panel"))
+            .open(open)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Context");
+                    ui.add(
+                        egui::DragValue::new(&mut self.radius)
+                            .clamp_range(1..=5_000)
+                            .suffix(" bp"),
+                    );
+                    ui.label("Line width");
+                    ui.add(
+                        egui::DragValue::new(&mut self.line_width)
+                            .clamp_range(10..=200),
+                    );
+                });
+
+                let Some(pos) = self.pos else {
+                    ui.label("Hover over the graph to show its sequence");
+                    return;
+                };
+
+                let (start, center, seq) = match pos {
+                    SeqPos::Pangenome(pos) => {
+                        let start = pos.0.saturating_sub(self.radius);
+                        let end = pos.0 + self.radius + 1;
+                        let seq = graph.pangenome_sequence(Bp(start)..Bp(end));
+
+                        let node = graph
+                            .node_at_pangenome_pos(pos)
+                            .map(|n| format!(", node {}", n.ix()))
+                            .unwrap_or_default();
+                        ui.label(format!("Pangenome position {}{node}", pos.0));
+
+                        (start, pos.0, seq.to_vec())
+                    }
+                    SeqPos::Path(path, pos) => {
+                        let start = pos.0.saturating_sub(self.radius);
+                        let end = pos.0 + self.radius + 1;
+                        let seq = graph
+                            .path_sequence(path, Bp(start)..Bp(end))
+                            .unwrap_or_default();
+
+                        let name = graph
+                            .path_names
+                            .get_by_left(&path)
+                            .map(|n| n.as_str())
+                            .unwrap_or("ERROR");
+                        ui.label(format!("Path {name}, position {}", pos.0));
+
+                        (start, pos.0, seq)
+                    }
+                };
+
+                if ui.button("Copy").clicked() {
+                    let text = String::from_utf8_lossy(&seq).into_owned();
+                    ui.output_mut(|o| o.copied_text = text);
+                }
+
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        let job = self.layout_lines(ui, start, center, &seq);
+                        ui.label(job);
+                    });
+            });
+    }
+
+    /// One line per `line_width` bases, prefixed by the position of
+    /// the first base, with the base at `center` highlighted.
+    fn layout_lines(
+        &self,
+        ui: &egui::Ui,
+        start: u64,
+        center: u64,
+        seq: &[u8],
+    ) -> egui::text::LayoutJob {
+        use egui::text::{LayoutJob, TextFormat};
+
+        let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+        let color = ui.visuals().text_color();
+
+        let normal = TextFormat::simple(font_id.clone(), color);
+        let dim =
+            TextFormat::simple(font_id.clone(), color.gamma_multiply(0.5));
+        let highlight = TextFormat {
+            background: egui::Color32::YELLOW,
+            color: egui::Color32::BLACK,
+            ..TextFormat::simple(font_id, color)
+        };
+
+        let width = self.line_width.max(1);
+        let pos_width = (start + seq.len() as u64).to_string().len();
+
+        let mut job = LayoutJob::default();
+
+        for (i, line) in seq.chunks(width).enumerate() {
+            let line_start = start + (i * width) as u64;
+
+            job.append(&format!("{line_start:>pos_width$} "), 0.0, dim.clone());
+
+            let text = |s: &[u8]| String::from_utf8_lossy(s).into_owned();
+
+            let center_ix = center
+                .checked_sub(line_start)
+                .map(|c| c as usize)
+                .filter(|&c| c < line.len());
+
+            if let Some(c) = center_ix {
+                job.append(&text(&line[..c]), 0.0, normal.clone());
+                job.append(&text(&line[c..c + 1]), 0.0, highlight.clone());
+                job.append(&text(&line[c + 1..]), 0.0, normal.clone());
+            } else {
+                job.append(&text(line), 0.0, normal.clone());
+            }
+
+            job.append("\n", 0.0, normal.clone());
+        }
+
+        job
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippet_brackets_center() {
+        assert_eq!(sequence_snippet(b"ACGTACGT", 3, 2), "…CG[T]AC…");
+        assert_eq!(sequence_snippet(b"ACGT", 0, 2), "[A]CG…");
+        assert_eq!(sequence_snippet(b"ACGT", 10, 8), "ACG[T]");
+        assert_eq!(sequence_snippet(b"", 0, 2), "");
+    }
+}
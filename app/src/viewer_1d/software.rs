@@ -0,0 +1,268 @@
+//! Minimal CPU rendering of the 1D view, for environments without a
+//! usable GPU (remote X sessions, CI). The path data is sampled using
+//! the same samplers as the GPU path, and colored the same way as the
+//! `path_slot_1d_color_map` shader, but there's no GUI; the result is
+//! written to an image file.
+
+use std::io::prelude::*;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use waragraph_core::graph::{Bp, PathId, PathIndex};
+
+use crate::app::{resource::GraphDataCache, Args};
+use crate::color::ColorMap;
+
+use super::sampler::{PathDataSampler, Sampler};
+
+pub struct SoftwareRender1D {
+    pub width: usize,
+    pub row_height: usize,
+    pub row_gap: usize,
+    pub view: std::ops::Range<Bp>,
+    pub data_key: String,
+    pub color_map: ColorMap,
+}
+
+impl SoftwareRender1D {
+    /// The color scheme and default color mapping for the data
+    /// sources that the 1D viewer provides by default.
+    pub fn default_color_scheme(data_key: &str) -> (&'static str, ColorMap) {
+        match data_key {
+            "strand" => (
+                "black_red",
+                ColorMap {
+                    value_range: [0.0, 1.0],
+                    color_range: [0.0, 1.0],
+                },
+            ),
+            "multiplicity" => (
+                "spectral",
+                ColorMap {
+                    value_range: [1.0, 8.0],
+                    color_range: [0.0, 1.0],
+                },
+            ),
+            _ => (
+                "spectral",
+                ColorMap {
+                    value_range: [0.0, 13.0],
+                    color_range: [0.0, 1.0],
+                },
+            ),
+        }
+    }
+
+    pub fn image_height(&self, rows: usize) -> usize {
+        rows * (self.row_height + self.row_gap)
+    }
+
+    /// Renders one row per path, returning the image as tightly
+    /// packed RGB8 pixels.
+    pub async fn render(
+        &self,
+        graph: &Arc<PathIndex>,
+        data_cache: &Arc<GraphDataCache>,
+        color_scheme: &[[f32; 4]],
+        paths: &[PathId],
+    ) -> Result<Vec<u8>> {
+        let sampler = PathDataSampler::new(
+            graph.clone(),
+            data_cache.clone(),
+            &self.data_key,
+        );
+
+        let height = self.image_height(paths.len());
+        let mut pixels = vec![255u8; self.width * height * 3];
+
+        let view_len = (self.view.end.0 - self.view.start.0) as usize;
+        let bin_count = self.width.min(view_len).max(1);
+
+        let row_stride = self.width * 3;
+
+        for (row_ix, &path) in paths.iter().enumerate() {
+            let data = sampler
+                .sample_range(bin_count, path, self.view.clone())
+                .await?;
+            let values: &[f32] = bytemuck::cast_slice(&data);
+
+            let row_colors = (0..self.width)
+                .map(|x| {
+                    // same bin lookup as the fragment shader
+                    let t = x as f32 / self.width as f32;
+                    let ix = ((t * bin_count as f32).round() as usize)
+                        .min(bin_count - 1);
+                    self.value_color(color_scheme, values[ix])
+                })
+                .collect::<Vec<_>>();
+
+            let y0 = row_ix * (self.row_height + self.row_gap);
+
+            for y in y0..(y0 + self.row_height) {
+                let row = &mut pixels[y * row_stride..(y + 1) * row_stride];
+                for (px, color) in row.chunks_exact_mut(3).zip(&row_colors) {
+                    px.copy_from_slice(color);
+                }
+            }
+        }
+
+        Ok(pixels)
+    }
+
+    fn value_color(&self, scheme: &[[f32; 4]], v: f32) -> [u8; 3] {
+        value_color(scheme, self.color_map, v)
+    }
+}
+
+/// The color of a sampled value, as the `path_slot_1d_color_map`
+/// shader computes it; infinite values (gaps in the path) are white.
+pub fn value_color(
+    scheme: &[[f32; 4]],
+    color_map: ColorMap,
+    v: f32,
+) -> [u8; 3] {
+    if v.is_infinite() {
+        return [255; 3];
+    }
+
+    let [v0, v1] = color_map.value_range;
+    let [c0, c1] = color_map.color_range;
+
+    let v_n = (v - v0) / (v1 - v0);
+    let c_n = c0 + (c1 - c0) * v_n;
+
+    let [r, g, b, _] = crate::color::color_at(scheme, c_n);
+    [(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8]
+}
+
+/// Writes RGB8 pixels as a binary PPM image.
+pub fn write_ppm(
+    path: impl AsRef<Path>,
+    width: usize,
+    height: usize,
+    pixels: &[u8],
+) -> Result<()> {
+    anyhow::ensure!(
+        pixels.len() == width * height * 3,
+        "Pixel buffer doesn't match image size"
+    );
+
+    let file = std::fs::File::create(path)?;
+    let mut out = std::io::BufWriter::new(file);
+
+    write!(out, "P6\n{width} {height}\n255\n")?;
+    out.write_all(pixels)?;
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Entry point for `--software-render`: renders the entire pangenome
+/// for all paths, without touching the GPU. The image is written as
+/// PNG if the output file name ends with `.png`, and as PPM otherwise.
+pub fn run(args: &Args) -> Result<()> {
+    let graph = Arc::new(PathIndex::from_file(&args.gfa)?);
+    let data_cache = Arc::new(GraphDataCache::init(&graph));
+
+    let data_key = args.render_data.as_deref().unwrap_or("depth");
+
+    if !data_cache.path_data_source_names().any(|n| n == data_key) {
+        let available = data_cache.path_data_source_names().collect::<Vec<_>>();
+        anyhow::bail!(
+            "Unknown data source `{data_key}`, available: {available:?}"
+        );
+    }
+
+    let (scheme_name, color_map) =
+        SoftwareRender1D::default_color_scheme(data_key);
+
+    let scheme = crate::color::builtin_color_schemes()
+        .into_iter()
+        .find_map(|(name, colors)| (name == scheme_name).then_some(colors))
+        .unwrap();
+
+    let renderer = SoftwareRender1D {
+        width: args.render_width.unwrap_or(1024),
+        row_height: 8,
+        row_gap: 2,
+        view: Bp(0)..graph.pangenome_len(),
+        data_key: data_key.to_string(),
+        color_map,
+    };
+
+    let paths = (0..graph.path_names.len())
+        .map(PathId::from)
+        .collect::<Vec<_>>();
+
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+
+    let pixels =
+        rt.block_on(renderer.render(&graph, &data_cache, &scheme, &paths))?;
+
+    let out_path = args
+        .output
+        .clone()
+        .unwrap_or_else(|| "waragraph-1d.ppm".into());
+
+    let height = renderer.image_height(paths.len());
+    super::export::write_rgb_image(&out_path, renderer.width, height, &pixels)?;
+
+    // the image has no labels, so list the row order
+    for (row, path) in paths.iter().enumerate() {
+        let name = graph.path_names.get_by_left(path).unwrap();
+        println!("{row}\t{name}");
+    }
+
+    log::warn!("Wrote {}x{height} image to {out_path:?}", renderer.width);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GFA_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../test/data/A-3105.fa.353ea42.34ee7b1.1576367.smooth.fix.gfa"
+    );
+
+    #[tokio::test]
+    async fn software_render_depth() -> Result<()> {
+        let graph = Arc::new(PathIndex::from_gfa(GFA_PATH)?);
+        let data_cache = Arc::new(GraphDataCache::init(&graph));
+
+        let (scheme_name, color_map) =
+            SoftwareRender1D::default_color_scheme("depth");
+        let scheme = crate::color::builtin_color_schemes()
+            .into_iter()
+            .find_map(|(name, colors)| (name == scheme_name).then_some(colors))
+            .unwrap();
+
+        let renderer = SoftwareRender1D {
+            width: 64,
+            row_height: 4,
+            row_gap: 1,
+            view: Bp(0)..graph.pangenome_len(),
+            data_key: "depth".to_string(),
+            color_map,
+        };
+
+        let paths = [PathId::from(0usize), PathId::from(1usize)];
+
+        let pixels = renderer
+            .render(&graph, &data_cache, &scheme, &paths)
+            .await?;
+
+        assert_eq!(pixels.len(), 64 * renderer.image_height(2) * 3);
+        // the gap between the rows is left blank
+        let gap_row = 4 * 64 * 3;
+        assert!(pixels[gap_row..gap_row + 64 * 3].iter().all(|&c| c == 255));
+        assert!(pixels[..64 * 3].iter().any(|&c| c != 255));
+
+        Ok(())
+    }
+}
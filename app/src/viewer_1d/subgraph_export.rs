@@ -0,0 +1,229 @@
+//! Exporting the part of the graph in the 1D view, or the selected
+//! nodes, as a standalone GFA.
+
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Result;
+use crossbeam::atomic::AtomicCell;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::subgraph::Subgraph;
+use waragraph_core::graph::Bp;
+
+use crate::app::notify::{JobAction, JobDone};
+use crate::app::provenance::EventKind;
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::{AppMsg, SharedState};
+
+/// Writes the subgraph to `path` as GFA, and records the export.
+pub fn export_subgraph(
+    shared: &SharedState,
+    subgraph: &Subgraph,
+    path: &Path,
+) -> Result<()> {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    subgraph.write_gfa(&shared.graph, &mut out)?;
+    out.flush()?;
+
+    log::warn!(
+        "Wrote subgraph with {} nodes and {} path intervals to {path:?}",
+        subgraph.node_count(),
+        subgraph.paths.len()
+    );
+
+    shared.provenance.blocking_write().record_path(
+        EventKind::Export,
+        "Subgraph",
+        path,
+    );
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SubgraphSource {
+    ViewRange,
+    Selection,
+}
+
+/// Settings window panel for exporting the subgraph of the 1D view
+/// range or of the selection.
+pub struct SubgraphExportWidget {
+    shared: SharedState,
+    // the pangenome range of the 1D view
+    view_range: Arc<AtomicCell<(u64, u64)>>,
+
+    source: SubgraphSource,
+
+    export_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    result_recv: Option<oneshot::Receiver<Result<PathBuf>>>,
+    status: Option<String>,
+}
+
+impl SubgraphExportWidget {
+    pub fn new(
+        shared: &SharedState,
+        view_range: Arc<AtomicCell<(u64, u64)>>,
+    ) -> Self {
+        Self {
+            shared: shared.clone(),
+            view_range,
+            source: SubgraphSource::ViewRange,
+            export_path_recv: None,
+            result_recv: None,
+            status: None,
+        }
+    }
+
+    fn spawn_export(
+        &mut self,
+        tokio_handle: &tokio::runtime::Handle,
+        path: PathBuf,
+    ) {
+        let shared = self.shared.clone();
+        let source = self.source;
+        let (start, end) = self.view_range.load();
+
+        let (send, recv) = oneshot::channel();
+        let started = Instant::now();
+
+        tokio_handle.spawn_blocking(move || {
+            let subgraph = match source {
+                SubgraphSource::ViewRange => Subgraph::from_pangenome_range(
+                    &shared.graph,
+                    Bp(start)..Bp(end),
+                ),
+                SubgraphSource::Selection => {
+                    let selection = shared.selection.blocking_read();
+                    Subgraph::from_nodes(&shared.graph, selection.nodes())
+                }
+            };
+
+            let result =
+                export_subgraph(&shared, &subgraph, &path).map(|_| path);
+
+            let done = match &result {
+                Ok(path) => JobDone::finished("Subgraph export", started)
+                    .with_detail(path.display().to_string())
+                    .with_action(JobAction::CopyPath(path.clone())),
+                Err(e) => JobDone::failed("Subgraph export", started, e),
+            };
+            if let Err(e) =
+                shared.app_msg_send.blocking_send(AppMsg::JobDone(done))
+            {
+                log::error!("{e:?}");
+            }
+
+            let _ = send.send(result);
+        });
+
+        self.result_recv = Some(recv);
+        self.status = None;
+    }
+
+    fn poll_channels(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        if let Some(mut recv) = self.export_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => self.spawn_export(tokio_handle, path),
+                Err(TryRecvError::Empty) => self.export_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.result_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok(path)) => {
+                    self.status = Some(format!("Wrote {}", path.display()));
+                }
+                Ok(Err(e)) => {
+                    log::error!("Error exporting subgraph: {e:?}");
+                    self.status = Some(format!("Error: {e}"));
+                }
+                Err(TryRecvError::Empty) => self.result_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+impl SettingsWidget for SubgraphExportWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels(&settings_ctx.tokio_handle);
+
+        let coords = self.shared.coords.load();
+        let (start, end) = self.view_range.load();
+        let view_range = Bp(start)..Bp(end);
+        let selected = self.shared.selection.blocking_read().nodes().count();
+
+        let resp = ui.vertical(|ui| {
+            ui.radio_value(
+                &mut self.source,
+                SubgraphSource::ViewRange,
+                format!("1D view range ({})", coords.format_range(&view_range)),
+            );
+            ui.radio_value(
+                &mut self.source,
+                SubgraphSource::Selection,
+                format!("Selected nodes ({selected})"),
+            );
+
+            ui.weak(
+                "Nodes are kept whole, and each path is cut to the \
+                 stretches that stay in the subgraph, named \
+                 `path:start-end` (0-based).",
+            );
+
+            let busy =
+                self.export_path_recv.is_some() || self.result_recv.is_some();
+            let empty =
+                self.source == SubgraphSource::Selection && selected == 0;
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        !busy && !empty,
+                        egui::Button::new("Export GFA"),
+                    )
+                    .clicked()
+                {
+                    let region = match self.source {
+                        SubgraphSource::ViewRange => {
+                            coords.format_range(&view_range)
+                        }
+                        SubgraphSource::Selection => "selection".to_string(),
+                    };
+
+                    let path = self
+                        .shared
+                        .export_paths
+                        .blocking_read()
+                        .export_path("subgraph", Some(&region), "gfa");
+
+                    let id = egui::Id::new("SubgraphExportWidget_export");
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.export_path_recv = Some(recv);
+                }
+
+                if self.result_recv.is_some() {
+                    ui.spinner();
+                }
+            });
+
+            if let Some(status) = self.status.as_ref() {
+                ui.label(status);
+            }
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use waragraph_core::graph::threshold::{threshold_intervals, write_bed};
+use waragraph_core::graph::{Bp, PathId, PathIndex};
+
+use crate::annotations::Annotation;
+use crate::app::provenance::EventKind;
+use crate::app::resource::GraphDataCache;
+use crate::app::settings_menu::{
+    SettingsUiContext, SettingsUiResponse, SettingsWidget,
+};
+use crate::app::SharedState;
+use crate::gui::coords::PosFormat;
+
+use super::control::{Msg, ViewCmd};
+use super::nonref_report::parse_path_list;
+
+type Events = Vec<(PathId, Range<Bp>)>;
+
+/// Finds the intervals of the paths where the values of `data_key`
+/// are above `threshold` for at least `min_length` bp.
+pub fn find_events(
+    graph: &PathIndex,
+    data_cache: &GraphDataCache,
+    data_key: &str,
+    paths: &[PathId],
+    threshold: f32,
+    min_length: Bp,
+) -> Result<Events> {
+    let mut events = Vec::new();
+
+    for &path in paths {
+        let data = data_cache
+            .fetch_path_data_blocking(data_key, path)
+            .ok_or_else(|| anyhow::anyhow!("No path data for `{data_key}`"))?;
+
+        let intervals = threshold_intervals(
+            graph,
+            path,
+            &data.path_data,
+            threshold,
+            min_length,
+        );
+        events.extend(intervals.into_iter().map(|range| (path, range)));
+    }
+
+    Ok(events)
+}
+
+/// Settings window panel that turns a data track into the intervals
+/// where it crosses a threshold, which can be added as an annotation
+/// track or exported as BED.
+pub struct ThresholdEventsWidget {
+    shared: SharedState,
+    msg_tx: crossbeam::channel::Sender<Msg>,
+
+    data_key: String,
+    // empty for all paths
+    paths: String,
+    threshold: f32,
+    min_length: u64,
+
+    events_recv: Option<oneshot::Receiver<Result<Events>>>,
+    events: Arc<Events>,
+    // the name of the events, from the settings they were found with
+    events_name: String,
+    export_path_recv: Option<oneshot::Receiver<PathBuf>>,
+    error: Option<String>,
+}
+
+impl ThresholdEventsWidget {
+    pub fn new(
+        shared: &SharedState,
+        msg_tx: crossbeam::channel::Sender<Msg>,
+    ) -> Self {
+        let data_key = shared
+            .graph_data_cache
+            .path_data_source_names()
+            .next()
+            .unwrap_or("depth")
+            .to_string();
+
+        Self {
+            shared: shared.clone(),
+            msg_tx,
+
+            data_key,
+            paths: String::new(),
+            threshold: 1.0,
+            min_length: 100,
+
+            events_recv: None,
+            events: Arc::new(Vec::new()),
+            events_name: String::new(),
+            export_path_recv: None,
+            error: None,
+        }
+    }
+
+    fn spawn_search(&mut self, tokio_handle: &tokio::runtime::Handle) {
+        let graph = self.shared.graph.clone();
+        let data_cache = self.shared.graph_data_cache.clone();
+        let data_key = self.data_key.clone();
+        let paths = self.paths.clone();
+        let threshold = self.threshold;
+        let min_length = Bp(self.min_length);
+
+        self.events_name = format!("{data_key} > {threshold}");
+
+        self.shared.provenance.blocking_write().record(
+            EventKind::Filter,
+            format!(
+                "Intervals with {data_key} above {threshold} for at \
+                 least {} bp",
+                min_length.0
+            ),
+        );
+
+        let (send, recv) = oneshot::channel();
+
+        tokio_handle.spawn_blocking(move || {
+            let result = if paths.trim().is_empty() {
+                Ok(graph.path_names.left_values().copied().collect())
+            } else {
+                parse_path_list(&graph, &paths)
+            }
+            .and_then(|paths| {
+                find_events(
+                    &graph,
+                    &data_cache,
+                    &data_key,
+                    &paths,
+                    threshold,
+                    min_length,
+                )
+            });
+            let _ = send.send(result);
+        });
+
+        self.events_recv = Some(recv);
+        self.error = None;
+    }
+
+    fn export(&self, path: &Path) -> Result<()> {
+        let out = std::io::BufWriter::new(std::fs::File::create(path)?);
+        write_bed(&self.shared.graph, &self.events, &self.events_name, out)?;
+
+        log::warn!("Wrote {} intervals to {path:?}", self.events.len());
+
+        Ok(())
+    }
+
+    fn add_annotations(&self) {
+        let mut store = self.shared.annotations.blocking_write();
+        let label = Arc::new(self.events_name.clone());
+
+        for (path, range) in self.events.iter() {
+            let annot = Annotation {
+                path: *path,
+                range: range.clone(),
+                label: label.clone(),
+                color: None,
+                blocks: Vec::new(),
+                out_of_bounds: false,
+                attributes: Vec::new(),
+                label_color: None,
+            };
+            store.add_annotation(&self.events_name, annot);
+        }
+    }
+
+    fn poll_channels(&mut self) {
+        if let Some(mut recv) = self.events_recv.take() {
+            match recv.try_recv() {
+                Ok(Ok(events)) => self.events = Arc::new(events),
+                Ok(Err(e)) => self.error = Some(e.to_string()),
+                Err(TryRecvError::Empty) => self.events_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+
+        if let Some(mut recv) = self.export_path_recv.take() {
+            match recv.try_recv() {
+                Ok(path) => {
+                    if let Err(e) = self.export(&path) {
+                        log::error!("Error exporting intervals: {e:?}");
+                        self.error = Some(e.to_string());
+                    } else {
+                        self.shared.provenance.blocking_write().record_path(
+                            EventKind::Export,
+                            &self.events_name,
+                            &path,
+                        );
+                    }
+                }
+                Err(TryRecvError::Empty) => self.export_path_recv = Some(recv),
+                Err(TryRecvError::Closed) => {}
+            }
+        }
+    }
+}
+
+impl SettingsWidget for ThresholdEventsWidget {
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_ctx: &SettingsUiContext,
+    ) -> SettingsUiResponse {
+        self.poll_channels();
+
+        let graph = self.shared.graph.clone();
+        let fmt = PosFormat::load(&self.shared);
+
+        let resp = ui.vertical(|ui| {
+            egui::ComboBox::from_label("Data")
+                .selected_text(&self.data_key)
+                .show_ui(ui, |ui| {
+                    for key in
+                        self.shared.graph_data_cache.path_data_source_names()
+                    {
+                        ui.selectable_value(
+                            &mut self.data_key,
+                            key.to_string(),
+                            key,
+                        );
+                    }
+                });
+
+            ui.horizontal(|ui| {
+                ui.label("Paths:");
+                ui.text_edit_singleline(&mut self.paths).on_hover_text(
+                    "Comma-separated path names; leave empty for all paths",
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Above:");
+                ui.add(egui::DragValue::new(&mut self.threshold).speed(0.1));
+
+                ui.label("Min. length (bp):");
+                ui.add(
+                    egui::DragValue::new(&mut self.min_length)
+                        .clamp_range(1..=1_000_000),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                let searching = self.events_recv.is_some();
+
+                if ui
+                    .add_enabled(!searching, egui::Button::new("Find"))
+                    .clicked()
+                {
+                    self.spawn_search(&settings_ctx.tokio_handle);
+                }
+
+                if searching {
+                    ui.spinner();
+                }
+
+                let has_events = !self.events.is_empty();
+
+                let annotate = ui
+                    .add_enabled(has_events, egui::Button::new("Add as track"))
+                    .on_hover_text(
+                        "Adds the intervals as an annotation set named \
+                         after the data and threshold",
+                    );
+
+                if annotate.clicked() {
+                    self.add_annotations();
+                }
+
+                let export =
+                    ui.add_enabled(has_events, egui::Button::new("Export BED"));
+
+                if export.clicked() {
+                    let path = self
+                        .shared
+                        .export_paths
+                        .blocking_read()
+                        .export_path("threshold", None, "bed");
+
+                    let id = egui::Id::new("ThresholdEventsWidget_export");
+                    let recv = settings_ctx.save_file_oneshot(id, path);
+                    self.export_path_recv = Some(recv);
+                }
+            });
+
+            if let Some(err) = self.error.as_ref() {
+                ui.label(format!("Error: {err}"));
+            }
+
+            let total: u64 =
+                self.events.iter().map(|(_, r)| r.end.0 - r.start.0).sum();
+            ui.label(format!("{} intervals, {total} bp", self.events.len()));
+
+            let row_height = ui.text_style_height(&egui::TextStyle::Body);
+
+            egui::ScrollArea::vertical().max_height(400.0).show_rows(
+                ui,
+                row_height,
+                self.events.len(),
+                |ui, range| {
+                    for (path, range) in &self.events[range] {
+                        let label = fmt.path_range(&graph, *path, range);
+                        if ui.link(label).clicked() {
+                            let _ = self.msg_tx.send(Msg::View(
+                                ViewCmd::GotoRange {
+                                    path: Some(*path),
+                                    range: range.clone(),
+                                },
+                            ));
+                        }
+                    }
+                },
+            );
+        });
+
+        SettingsUiResponse {
+            response: resp.response,
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Transforms between pangenome (bp) coordinates and screen space,
+//! shared by the slot rendering, annotations, ruler, and picking so
+//! that they agree on where each base pair is drawn.
+//!
+//! Base pair `p` covers the half-open screen interval
+//! `bp_to_x(p)..bp_to_x(p + 1)`, and picking returns the base pair
+//! whose interval contains the cursor. The math is done in `f64`, so
+//! there's no drift between subsystems even for long pangenomes.
+
+use std::ops::{Range, RangeInclusive};
+
+use waragraph_core::graph::Bp;
+
+/// Maps the bp range `view` onto the screen interval `screen`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BpScreenMap {
+    view: Range<u64>,
+    screen: RangeInclusive<f32>,
+}
+
+impl BpScreenMap {
+    pub fn new(view: Range<u64>, screen: RangeInclusive<f32>) -> Self {
+        Self { view, screen }
+    }
+
+    pub fn view(&self) -> &Range<u64> {
+        &self.view
+    }
+
+    fn view_len(&self) -> f64 {
+        self.view.end.saturating_sub(self.view.start).max(1) as f64
+    }
+
+    fn screen_width(&self) -> f64 {
+        (*self.screen.end() - *self.screen.start()) as f64
+    }
+
+    /// Width of a single base pair on screen.
+    pub fn px_per_bp(&self) -> f32 {
+        (self.screen_width() / self.view_len()) as f32
+    }
+
+    /// The x-coordinate of the left edge of `pos`; positions outside
+    /// the view map outside the screen interval.
+    pub fn bp_to_x(&self, pos: Bp) -> f32 {
+        let t = (pos.0 as f64 - self.view.start as f64) / self.view_len();
+        (*self.screen.start() as f64 + t * self.screen_width()) as f32
+    }
+
+    /// The base pair drawn at `x`, clamped to the view.
+    pub fn x_to_bp(&self, x: f32) -> Bp {
+        let width = self.screen_width().max(f32::EPSILON as f64);
+        let t = (x as f64 - *self.screen.start() as f64) / width;
+        let pos = self.view.start as f64 + t * self.view_len();
+
+        let last = self.view.end.saturating_sub(1).max(self.view.start);
+        let pos = (pos.floor().max(0.0) as u64).clamp(self.view.start, last);
+        Bp(pos)
+    }
+
+    /// The screen interval covered by `range`, clipped to the view;
+    /// `None` if `range` doesn't touch the view.
+    pub fn interval_to_screen(
+        &self,
+        range: &Range<Bp>,
+    ) -> Option<RangeInclusive<f32>> {
+        if range.start.0 > self.view.end || range.end.0 < self.view.start {
+            return None;
+        }
+
+        let left = range.start.0.max(self.view.start);
+        let right = range.end.0.min(self.view.end);
+
+        Some(self.bp_to_x(Bp(left))..=self.bp_to_x(Bp(right)))
+    }
+}
+
+/// Returns a line equation that defines the transformation parameters
+/// used by the slot fragment shader, and by annotation labels, to
+/// reuse data sampled for one view while another is shown.
+///
+/// `view0` corresponds to the view that has been sampled and is
+/// available in the data buffer, while `view1` is the current view.
+///
+/// Usage: If the returned value is [a, b], a position `t` normalized
+/// to `view1` is at `a*t + b` normalized to `view0`.
+pub fn sample_index_transform(
+    view0: &Range<u64>,
+    view1: &Range<u64>,
+) -> [f32; 2] {
+    let l0 = view0.start as f64;
+    let l1 = view1.start as f64;
+
+    let v0 = view0.end.saturating_sub(view0.start).max(1) as f64;
+    let v1 = view1.end.saturating_sub(view1.start) as f64;
+
+    let a = v1 / v0;
+    let b = (l1 - l0) / v0;
+
+    [a as f32, b as f32]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn random_map(rng: &mut StdRng) -> BpScreenMap {
+        let max = rng.gen_range(2..3_000_000_000u64);
+        let start = rng.gen_range(0..max - 1);
+        let end = rng.gen_range(start + 1..=max);
+        let left = rng.gen_range(0.0..500.0);
+        let width = rng.gen_range(1.0..4000.0);
+        BpScreenMap::new(start..end, left..=(left + width))
+    }
+
+    #[test]
+    fn picking_inverts_bp_to_x() {
+        let mut rng = StdRng::seed_from_u64(0xb9);
+
+        for _ in 0..10_000 {
+            let map = random_map(&mut rng);
+            let view = map.view().clone();
+
+            let pos = Bp(rng.gen_range(view.clone()));
+            let x = map.bp_to_x(pos);
+            assert!(map.screen.contains(&x), "{map:?} {pos:?}");
+
+            let back = map.x_to_bp(x);
+            assert!(view.contains(&back.0));
+
+            if map.px_per_bp() >= 1.0 {
+                // every base pair is at least a pixel wide, so picking
+                // at its center gives it back exactly
+                let mid = x + map.px_per_bp() / 2.0;
+                assert_eq!(map.x_to_bp(mid), pos, "{map:?}");
+                assert!(back.0.abs_diff(pos.0) <= 1, "{map:?}");
+            } else {
+                // otherwise at most one pixel's worth of bp off, up to
+                // f32 precision of the screen coordinate
+                let bp_per_px = 1.0 / map.px_per_bp() as f64;
+                let err = (back.0 as f64 - pos.0 as f64).abs();
+                let slack = bp_per_px * (1.0 + x.abs() as f64 * 1e-6);
+                assert!(err <= slack.max(1.0), "{map:?} {pos:?} {back:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn picking_is_clamped_to_view() {
+        let map = BpScreenMap::new(100..200, 0.0..=100.0);
+
+        assert_eq!(map.x_to_bp(-50.0), Bp(100));
+        assert_eq!(map.x_to_bp(0.0), Bp(100));
+        assert_eq!(map.x_to_bp(0.99), Bp(100));
+        assert_eq!(map.x_to_bp(1.0), Bp(101));
+        // the right edge belongs to the last base pair in view
+        assert_eq!(map.x_to_bp(100.0), Bp(199));
+        assert_eq!(map.x_to_bp(500.0), Bp(199));
+    }
+
+    #[test]
+    fn intervals_are_clipped_to_view() {
+        let map = BpScreenMap::new(100..200, 0.0..=100.0);
+
+        assert_eq!(
+            map.interval_to_screen(&(Bp(150)..Bp(160))),
+            Some(50.0..=60.0)
+        );
+        assert_eq!(
+            map.interval_to_screen(&(Bp(50)..Bp(120))),
+            Some(0.0..=20.0)
+        );
+        assert_eq!(
+            map.interval_to_screen(&(Bp(190)..Bp(300))),
+            Some(90.0..=100.0)
+        );
+        assert_eq!(map.interval_to_screen(&(Bp(0)..Bp(99))), None);
+        assert_eq!(map.interval_to_screen(&(Bp(201)..Bp(300))), None);
+
+        // adjacent intervals share their boundary exactly
+        let a = map.interval_to_screen(&(Bp(110)..Bp(133))).unwrap();
+        let b = map.interval_to_screen(&(Bp(133)..Bp(170))).unwrap();
+        assert_eq!(a.end(), b.start());
+    }
+
+    #[test]
+    fn sample_transform_maps_between_views() {
+        let mut rng = StdRng::seed_from_u64(0x5a);
+
+        for _ in 0..1000 {
+            let a = random_map(&mut rng).view().clone();
+            let b = random_map(&mut rng).view().clone();
+
+            assert_eq!(sample_index_transform(&a, &a), [1.0, 0.0]);
+
+            let [m, c] = sample_index_transform(&a, &b);
+
+            // the same bp, normalized to each of the views
+            let pos = rng.gen_range(b.clone()) as f64;
+            let t1 = (pos - b.start as f64) / (b.end - b.start) as f64;
+            let t0 = (pos - a.start as f64) / (a.end - a.start) as f64;
+
+            // up to the f32 precision of the coefficients
+            let mapped = m as f64 * t1 + c as f64;
+            let tolerance = 1e-6 * (1.0 + m.abs() as f64 + c.abs() as f64);
+            assert!((mapped - t0).abs() <= tolerance, "{a:?} {b:?} {pos}");
+        }
+    }
+}
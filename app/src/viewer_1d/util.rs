@@ -1,25 +1,10 @@
 use std::{collections::HashMap, sync::Arc};
-use waragraph_core::graph::{PathId, PathIndex};
+use waragraph_core::graph::{Bp, Node, PathId, PathIndex};
 
 use crate::{app::SharedState, color::ColorMap};
 
 use super::render::VizModeConfig;
-
-pub(super) fn create_path_name_hash_colors<'a>(
-    paths: impl Iterator<Item = (PathId, &'a str)>,
-) -> Vec<[f32; 4]> {
-    let mut paths = paths.collect::<Vec<_>>();
-    paths.sort_by_key(|(p, _)| *p);
-
-    let mut colors = Vec::with_capacity(paths.len());
-
-    for (_, path_name) in paths {
-        let [r, g, b] = crate::color::util::path_name_hash_color(path_name);
-        colors.push([r, g, b, 1.]);
-    }
-
-    colors
-}
+use super::transform::BpScreenMap;
 
 pub(super) fn init_path_name_hash_viz_mode(
     state: &raving_wgpu::State,
@@ -45,12 +30,7 @@ pub(super) fn init_path_name_hash_viz_mode(
     viz_samplers.insert("path_name".to_string(), Arc::new(sampler) as Arc<_>);
 
     // create color buffer
-    let path_names = shared
-        .graph
-        .path_names
-        .iter()
-        .map(|(p, n)| (*p, n.as_str()));
-    let color_vec = create_path_name_hash_colors(path_names);
+    let color_vec = shared.path_colors.blocking_read().colors(&shared.graph);
 
     // create color scheme & upload texture
     let color_scheme = {
@@ -77,3 +57,233 @@ pub(super) fn init_path_name_hash_viz_mode(
         .blocking_write()
         .insert("path_name".into(), color_scheme);
 }
+
+pub(super) fn lerp_color(
+    a: egui::Color32,
+    b: egui::Color32,
+    t: f32,
+) -> egui::Color32 {
+    let t = t.clamp(0.0, 1.0);
+    let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+    egui::Color32::from_rgb(
+        lerp(a.r(), b.r()),
+        lerp(a.g(), b.g()),
+        lerp(a.b(), b.b()),
+    )
+}
+
+/// The node under the cursor in the path slots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct HoveredNode {
+    /// The pangenome position drawn under the cursor
+    pub pos: Bp,
+    pub node: Node,
+    pub len: Bp,
+    /// The number of paths through the node
+    pub path_count: usize,
+}
+
+/// Maps a screen x-coordinate in the path slots back to the pangenome
+/// position drawn there, using the same transform the slots are
+/// sampled with, and to the node containing it. `None` if the view is
+/// past the end of the pangenome.
+pub(super) fn pick_node(
+    graph: &PathIndex,
+    screen_map: &BpScreenMap,
+    x: f32,
+) -> Option<HoveredNode> {
+    let pos = screen_map.x_to_bp(x);
+    let node = graph.node_at_pangenome_pos(pos)?;
+    let path_count = graph.paths_on_node(node).map_or(0, |paths| paths.count());
+
+    Some(HoveredNode {
+        pos,
+        node,
+        len: graph.node_length(node),
+        path_count,
+    })
+}
+
+/// The nodes overlapping the pangenome range `view`, including those
+/// only partly inside it, as the node indices the path node sets use.
+pub(super) fn visible_nodes(
+    graph: &PathIndex,
+    view: std::ops::Range<u64>,
+) -> std::ops::RangeInclusive<u32> {
+    let last = graph.node_count.saturating_sub(1);
+
+    let left = graph
+        .node_at_pangenome_pos(Bp(view.start))
+        .map_or(0, |n| n.ix());
+    let right = graph
+        .node_at_pangenome_pos(Bp(view.end.saturating_sub(1)))
+        .map_or(last, |n| n.ix());
+
+    (left as u32)..=(right.max(left) as u32)
+}
+
+/// Bins the pangenome into `bin_count` equal-length bins, returning
+/// the fraction of each bin covered by nodes on the path.
+pub(super) fn path_presence_bins(
+    graph: &PathIndex,
+    path: PathId,
+    bin_count: usize,
+) -> Vec<f32> {
+    let range = 0..graph.pangenome_len().0;
+    path_presence_bins_in_range(graph, path, range, bin_count)
+}
+
+/// As `path_presence_bins`, over the pangenome range rather than the
+/// whole pangenome.
+pub(super) fn path_presence_bins_in_range(
+    graph: &PathIndex,
+    path: PathId,
+    range: std::ops::Range<u64>,
+    bin_count: usize,
+) -> Vec<f32> {
+    let mut bins = vec![0f64; bin_count];
+
+    let total_len = range.end.saturating_sub(range.start) as f64;
+    let Some(node_set) = graph.path_node_sets.get(path.ix()) else {
+        return vec![0.0; bin_count];
+    };
+
+    if total_len == 0.0 || bin_count == 0 {
+        return vec![0.0; bin_count];
+    }
+
+    let bin_len = total_len / bin_count as f64;
+
+    // only the nodes of the path within the range are visited
+    let nodes = graph.pos_range_nodes(range.clone());
+    let (first, last) = (nodes.start().ix() as u32, nodes.end().ix() as u32);
+    let skip = first.checked_sub(1).map_or(0, |n| node_set.rank(n));
+
+    let path_nodes = (skip..)
+        .map_while(|ix| node_set.select(ix as u32))
+        .take_while(|&node| node <= last);
+
+    for node in path_nodes {
+        let (offset, len) = graph.node_offset_length(Node::from(node));
+        let start = offset.0 as f64 - range.start as f64;
+        let end = start + len.0 as f64;
+
+        // nodes can span several bins
+        let first = ((start.max(0.0) / bin_len) as usize).min(bin_count - 1);
+        let last = ((end / bin_len).ceil().max(0.0) as usize).min(bin_count);
+
+        for (ix, bin) in bins.iter_mut().enumerate().take(last).skip(first) {
+            let bin_start = ix as f64 * bin_len;
+            let bin_end = bin_start + bin_len;
+            let overlap = end.min(bin_end) - start.max(bin_start);
+            *bin += overlap.max(0.0);
+        }
+    }
+
+    bins.into_iter()
+        .map(|covered| (covered / bin_len).min(1.0) as f32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GFA_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../test/data/A-3105.fa.353ea42.34ee7b1.1576367.smooth.fix.gfa"
+    );
+
+    #[test]
+    fn path_presence_bins_cover_path() {
+        let graph = PathIndex::from_gfa(GFA_PATH).unwrap();
+
+        let bins = path_presence_bins(&graph, PathId::from(0u32), 32);
+        assert_eq!(bins.len(), 32);
+        assert!(bins.iter().all(|&v| (0.0..=1.0).contains(&v)));
+
+        // the total covered length should match the path's nodes
+        let bin_len = graph.pangenome_len().0 as f64 / 32.0;
+        let covered: f64 = bins.iter().map(|&v| v as f64 * bin_len).sum();
+
+        let node_len: u64 = graph.path_node_sets[0]
+            .iter()
+            .map(|n| graph.node_length(Node::from(n)).0)
+            .sum();
+
+        assert!((covered - node_len as f64).abs() < 1.0);
+    }
+
+    #[test]
+    fn pick_node_inverts_screen_map() {
+        let graph = PathIndex::from_gfa(GFA_PATH).unwrap();
+        let len = graph.pangenome_len().0;
+
+        // two pixels per base pair
+        let map = BpScreenMap::new(0..len, 10.0..=(10.0 + 2.0 * len as f32));
+
+        for ix in [0, 1, graph.node_count / 2, graph.node_count - 1] {
+            let node = Node::from(ix);
+            let (offset, node_len) = graph.node_offset_length(node);
+            let last = Bp(offset.0 + node_len.0 - 1);
+
+            for pos in [offset, last] {
+                let x = map.bp_to_x(pos) + 1.0;
+                let hovered = pick_node(&graph, &map, x).unwrap();
+                assert_eq!(hovered.pos, pos);
+                assert_eq!(hovered.node, node);
+                assert_eq!(hovered.len, node_len);
+                assert!(hovered.path_count > 0);
+            }
+        }
+
+        let map = BpScreenMap::new(len..len + 100, 0.0..=100.0);
+        assert_eq!(pick_node(&graph, &map, 50.0), None);
+    }
+
+    #[test]
+    fn visible_nodes_include_partial_nodes() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tCC
+S\t3\tGGG
+P\tp\t1+,2+,3+\t*
+";
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        // nodes at 0..4, 4..6, and 6..9
+        assert_eq!(visible_nodes(&graph, 2..8), 0..=2);
+        // ending in the middle of node 2
+        assert_eq!(visible_nodes(&graph, 0..5), 0..=1);
+        // ending exactly at the end of node 2
+        assert_eq!(visible_nodes(&graph, 0..6), 0..=1);
+        assert_eq!(visible_nodes(&graph, 4..5), 1..=1);
+        assert_eq!(visible_nodes(&graph, 8..20), 2..=2);
+    }
+
+    #[test]
+    fn path_presence_bins_in_subrange() {
+        let graph = PathIndex::from_gfa(GFA_PATH).unwrap();
+        let path = PathId::from(0u32);
+
+        // a range of whole bins of the full pangenome gives the same
+        // values as those bins
+        let len = graph.pangenome_len().0;
+        let bin_count = 32;
+        let bin_len = len / bin_count as u64;
+        let len = bin_len * bin_count as u64;
+
+        let all = path_presence_bins_in_range(&graph, path, 0..len, 32);
+        let part = path_presence_bins_in_range(
+            &graph,
+            path,
+            bin_len * 8..bin_len * 16,
+            8,
+        );
+
+        for (a, b) in all[8..16].iter().zip(part.iter()) {
+            assert!((a - b).abs() < 1e-3, "{a} != {b}");
+        }
+    }
+}
@@ -1,5 +1,14 @@
+//! The visible range of the 1D viewer, in pangenome coordinates.
+//!
+//! All operations keep the view valid: within `0..max`, at least 1bp
+//! long (if `max` > 0), and no longer than the pangenome.
+
 use waragraph_core::graph::Bp;
 
+use super::transform::BpScreenMap;
+
+/// A range `start..end` in `0..max`, where `max` is the length of the
+/// pangenome.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct View1D {
     range: std::ops::Range<u64>,
@@ -32,37 +41,85 @@ impl View1D {
         self.range = 0..self.max;
     }
 
-    fn make_valid(&mut self) {
-        let len = self.len();
-
-        if self.range.end > self.max() {
-            self.range.end = self.max();
-        }
-
-        let max_offset = self.max.checked_sub(len).unwrap_or_default();
-        if self.offset() > max_offset {
-            self.range.start = max_offset;
-        }
+    /// Makes the view valid, preserving its length if possible: a
+    /// view that extends past the end of the pangenome is shifted to
+    /// the left, one that's longer than the pangenome is shrunk, and
+    /// an empty one is grown to 1bp.
+    pub fn clamp_to_genome(&mut self) {
+        let len = self
+            .range
+            .end
+            .saturating_sub(self.range.start)
+            .max(1)
+            .min(self.max);
+
+        let start = self.range.start.min(self.max - len);
+        self.range = start..(start + len);
     }
 
     pub fn set(&mut self, left: u64, right: u64) {
         self.range = left..right;
-        self.make_valid();
+        self.clamp_to_genome();
     }
 
     pub fn translate(&mut self, delta: i64) {
-        let d = delta.abs() as u64;
+        self.translate_bp(delta);
+    }
+
+    /// Moves the view `delta` bp to the right (or left, if negative),
+    /// without changing its length; stops at the ends of the
+    /// pangenome.
+    pub fn translate_bp(&mut self, delta: i64) {
         let len = self.len();
-        if delta > 0 {
-            self.range.end += d;
-            self.range.start += d;
-        } else if delta < 0 {
-            self.range.start =
-                self.range.start.checked_sub(d).unwrap_or_default();
-            self.range.end = self.range.start + len;
+        let start = if delta >= 0 {
+            self.range.start.saturating_add(delta as u64)
+        } else {
+            self.range.start.saturating_sub(delta.unsigned_abs())
+        };
+
+        self.range = start..start.saturating_add(len);
+        self.clamp_to_genome();
+    }
+
+    /// Scales the view length by `factor`, keeping `pos` at the same
+    /// relative position in the view; `factor` > 1.0 zooms out. If
+    /// `pos` is outside the view, the closest end of the view is kept
+    /// fixed instead.
+    pub fn zoom_around(&mut self, pos: Bp, factor: f64) {
+        if !factor.is_finite() || factor <= 0.0 || self.max == 0 {
+            return;
         }
 
-        self.make_valid();
+        let start = self.range.start as f64;
+        let len = self.len().max(1) as f64;
+
+        let pos = pos.0.clamp(self.range.start, self.range.end) as f64;
+        let t = (pos - start) / len;
+
+        let new_len = (len * factor).round().clamp(1.0, self.max as f64);
+        let new_start = (pos - t * new_len).round().max(0.0);
+
+        let new_start = new_start as u64;
+        self.range = new_start..(new_start + new_len as u64);
+        self.clamp_to_genome();
+    }
+
+    /// Number of bp covered by each pixel when the view is drawn
+    /// `width` pixels wide.
+    pub fn bp_per_pixel(&self, width: f32) -> f64 {
+        self.len() as f64 / width.max(1.0) as f64
+    }
+
+    /// Adjusts the view after the screen region it's drawn to
+    /// changes width, so that the scale (bp per pixel) stays the
+    /// same; the center of the view stays in place.
+    pub fn resize_keep_scale(&mut self, old_width: f32, new_width: f32) {
+        if old_width <= 0.0 || new_width <= 0.0 {
+            return;
+        }
+
+        let center = Bp(self.offset() + self.len() / 2);
+        self.zoom_around(center, new_width as f64 / old_width as f64);
     }
 
     /// `delta` is in "view width" units, so +1 means panning the view
@@ -96,7 +153,7 @@ impl View1D {
         self.range = l..r;
         println!("new range: {l}..{r}");
 
-        self.make_valid();
+        self.clamp_to_genome();
     }
 
     /// Expands/contracts the view by a factor of `s`, keeping the point
@@ -149,6 +206,14 @@ impl View1D {
     //   => Bp -> Bp
     //   => Screen space range -> Bp -> Bp
 
+    /// The map from the view (`self`) to `screen_interval`.
+    pub fn screen_map(
+        &self,
+        screen_interval: &std::ops::RangeInclusive<f32>,
+    ) -> BpScreenMap {
+        BpScreenMap::new(self.range.clone(), screen_interval.clone())
+    }
+
     /// Maps the view (`self`) to `screen_interval`, and then returns
     /// the intersection of the image of `pan_range` under this map
     /// with the given `screen_interval`. Returns `None` if the
@@ -158,34 +223,8 @@ impl View1D {
         pan_range: &std::ops::Range<Bp>,
         screen_interval: &std::ops::RangeInclusive<f32>,
     ) -> Option<std::ops::RangeInclusive<f32>> {
-        let vrange = self.range();
-        let pleft = pan_range.start.0;
-        let pright = pan_range.end.0;
-
-        if pleft > vrange.end || pright < vrange.start {
-            return None;
-        }
-
-        let vl = vrange.start as f32;
-        let vr = vrange.end as f32;
-        let vlen = vr - vl;
-
-        let left = pleft.max(vrange.start);
-        let right = pright.min(vrange.end);
-
-        let l = left as f32;
-        let r = right as f32;
-
-        let lt = (l - vl) / vlen;
-        let rt = (r - vl) / vlen;
-
-        let (sleft, sright) = screen_interval.clone().into_inner();
-        let slen = sright - sleft;
-
-        let a_left = sleft + lt * slen;
-        let a_right = sleft + rt * slen;
-
-        Some(a_left..=a_right)
+        self.screen_map(screen_interval)
+            .interval_to_screen(pan_range)
     }
 }
 
@@ -210,3 +249,145 @@ impl View1D {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn assert_valid(view: &View1D) {
+        let range = view.range();
+        assert!(range.end <= view.max(), "{view:?}");
+        assert!(range.start < range.end, "{view:?}");
+    }
+
+    fn random_view(rng: &mut StdRng) -> View1D {
+        let max = rng.gen_range(1..1_000_000u64);
+        let mut view = View1D::new(max);
+        let start = rng.gen_range(0..max);
+        let end = rng.gen_range(start + 1..=max);
+        view.set(start, end);
+        view
+    }
+
+    #[test]
+    fn view_ops_stay_valid() {
+        let mut rng = StdRng::seed_from_u64(0x1d);
+
+        for _ in 0..1000 {
+            let mut view = random_view(&mut rng);
+            let max = view.max();
+
+            match rng.gen_range(0..5) {
+                0 => {
+                    let a = rng.gen_range(0..2 * max);
+                    let b = rng.gen_range(0..2 * max);
+                    view.set(a, b);
+                }
+                1 => {
+                    let d = rng.gen_range(-(2 * max as i64)..2 * max as i64);
+                    view.translate_bp(d);
+                }
+                2 => {
+                    let pos = Bp(rng.gen_range(0..=max));
+                    view.zoom_around(pos, rng.gen_range(0.01..100.0));
+                }
+                3 => {
+                    view.zoom_around_norm_f32(
+                        rng.gen_range(0.0..1.0),
+                        rng.gen_range(0.1..10.0),
+                    );
+                }
+                _ => {
+                    view.resize_keep_scale(
+                        rng.gen_range(1.0..4000.0),
+                        rng.gen_range(1.0..4000.0),
+                    );
+                }
+            }
+
+            assert_valid(&view);
+        }
+    }
+
+    #[test]
+    fn translate_preserves_length() {
+        let mut rng = StdRng::seed_from_u64(0x7a);
+
+        for _ in 0..1000 {
+            let mut view = random_view(&mut rng);
+            let len = view.len();
+            let max = view.max() as i64;
+            view.translate_bp(rng.gen_range(-2 * max..2 * max));
+            assert_eq!(view.len(), len);
+        }
+    }
+
+    #[test]
+    fn zoom_around_keeps_position() {
+        let mut rng = StdRng::seed_from_u64(0x200);
+
+        for _ in 0..1000 {
+            let mut view = random_view(&mut rng);
+            let before = view.clone();
+
+            let pos = Bp(rng.gen_range(before.offset()..=before.range().end));
+            let factor = rng.gen_range(0.1..10.0);
+
+            view.zoom_around(pos, factor);
+            assert_valid(&view);
+
+            let expected_len = (before.len() as f64 * factor)
+                .round()
+                .clamp(1.0, view.max() as f64);
+            assert_eq!(view.len(), expected_len as u64);
+
+            // unless the view was clamped, `pos` stays in place,
+            // up to rounding
+            let t0 = (pos.0 - before.offset()) as f64 / before.len() as f64;
+            let new_start = pos.0 as f64 - t0 * expected_len;
+            let clamped =
+                new_start < 0.0 || new_start + expected_len > view.max() as f64;
+
+            if !clamped {
+                let t1 =
+                    (pos.0 as f64 - view.offset() as f64) / view.len() as f64;
+                let err = (t1 - t0).abs() * view.len() as f64;
+                assert!(err <= 1.0, "{before:?} -> {view:?}, {pos:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn screen_mapping_roundtrip() {
+        let mut rng = StdRng::seed_from_u64(0x5c);
+
+        for _ in 0..1000 {
+            let view = random_view(&mut rng);
+            let screen = 100.0..=1100.0;
+
+            let pos = Bp(rng.gen_range(view.offset()..=view.range().end));
+            let screen_map = view.screen_map(&screen);
+            let x = screen_map.bp_to_x(pos);
+            assert!(screen.contains(&x));
+
+            let back = screen_map.x_to_bp(x);
+            let bp_per_px = view.bp_per_pixel(1000.0);
+            let err = (back.0 as f64 - pos.0 as f64).abs();
+            assert!(err <= bp_per_px.max(1.0), "{view:?} {pos:?} {back:?}");
+        }
+    }
+
+    #[test]
+    fn resize_keeps_scale() {
+        let mut view = View1D::new(1_000_000);
+        view.set(100_000, 200_000);
+
+        let scale = view.bp_per_pixel(1000.0);
+        view.resize_keep_scale(1000.0, 500.0);
+
+        assert_eq!(view.len(), 50_000);
+        assert_eq!(view.bp_per_pixel(500.0), scale);
+        assert_eq!(view.offset() + view.len() / 2, 150_000);
+    }
+}
@@ -37,7 +37,10 @@ impl SettingsWidget for VisualizationModesWidget {
     ) -> SettingsUiResponse {
         let mut current_key = self.active_viz_data_key.blocking_write();
 
-        let viz_modes = ["depth", "strand", "path_name"];
+        let tracks = self.shared.graph_data_cache.tracks();
+        let viz_modes = ["depth", "strand", "multiplicity", "path_name"]
+            .into_iter()
+            .chain(tracks.iter().map(String::as_str));
         // let mut path_data_sources = self
         //     .shared
         //     .graph_data_cache
@@ -1,9 +1,14 @@
 use crate::annotations::{AnnotationId, GlobalAnnotationId};
 use crate::app::settings_menu::SettingsWindow;
-use crate::app::{AppWindow, SharedState};
+use crate::app::{AppType, AppWindow, SharedState};
 use crate::color::ColorMap;
 use crate::context::{ContextQuery, ContextState};
 use crate::gui::annotations::AnnotationListWidget;
+use crate::gui::coords::PosFormat;
+use crate::gui::layers::{Layer, LayeredShapes};
+use crate::gui::measure::MeasurePoint;
+use crate::gui::pinned::{self, PinnedItem};
+use crate::gui::zoom::ZoomLayer;
 use crate::util::BufferDesc;
 use crate::viewer_2d::config::Config;
 
@@ -29,13 +34,17 @@ use anyhow::Result;
 
 use ultraviolet::*;
 
-use waragraph_core::graph::{Bp, Node, PathIndex};
+use waragraph_core::graph::{Bp, Node, PathId, PathIndex};
 
 pub mod annotations;
 pub mod config;
 pub mod control;
 pub mod gui;
 pub mod layout;
+pub mod minimap;
+pub mod odgi_lay;
+pub mod path_overlay;
+pub mod tiles;
 pub mod util;
 pub mod view;
 
@@ -43,10 +52,11 @@ pub mod lyon_path_renderer;
 
 use control::ViewControlWidget;
 
-use layout::NodePositions;
+use layout::{LayoutScale, NodePositions};
 
 use self::annotations::AnnotationLayer;
-use self::view::View2D;
+use self::path_overlay::PathOverlay;
+use self::view::{View2D, ViewAnimation};
 
 #[derive(Debug)]
 pub struct Args {
@@ -57,10 +67,33 @@ pub struct Args {
 
 pub struct Viewer2D {
     node_positions: Arc<NodePositions>,
+    // the positions as loaded, before any cleanup
+    raw_node_positions: Arc<NodePositions>,
+    // the angle of the raw layout's principal axis
+    raw_principal_angle: f32,
+    // (remove outliers, relax, rotation) as applied to `node_positions`
+    applied_layout_cleanup: (bool, bool, f32),
     vertex_buffer: wgpu::Buffer,
     instance_count: usize,
 
+    node_tree: tiles::NodeQuadTree,
+    // the instances of the nodes in view, when culling
+    visible_buffer: Option<wgpu::Buffer>,
+    visible_capacity: usize,
+    visible_count: usize,
+    visible_nodes: Vec<u32>,
+    // the view center and size, and screen width, the visible nodes
+    // were found for
+    culled_view: Option<(Vec2, Vec2, f32)>,
+    draw_culled: bool,
+    // for the zoom thresholds, which are in bp per pixel
+    bp_per_unit: f64,
+    layout_scale: LayoutScale,
+
     view: View2D,
+    // the transition in progress, if any; input that moves the view
+    // cancels it
+    view_animation: Option<ViewAnimation>,
 
     transform_uniform: wgpu::Buffer,
     vert_config: wgpu::Buffer,
@@ -73,6 +106,8 @@ pub struct Viewer2D {
     shared: SharedState,
 
     annotation_layer: AnnotationLayer,
+    path_overlay: PathOverlay,
+    minimap: minimap::Minimap,
 
     active_viz_data_key: String,
     color_mapping: crate::util::Uniform<ColorMap, 16>,
@@ -84,8 +119,15 @@ pub struct Viewer2D {
     msg_rx: crossbeam::channel::Receiver<control::Msg>,
 
     cfg: Config,
+    // the config generation last synced with `cfg`
+    config_generation: u64,
 
     annotation_list_widget: AnnotationListWidget,
+    path_list_widget: crate::gui::path_list::PathListWidget,
+
+    // the view bounds and node positions, for the node export panel
+    export_viewport: Arc<AtomicCell<(Vec2, Vec2)>>,
+    export_positions: Arc<RwLock<Arc<NodePositions>>>,
 }
 
 impl Viewer2D {
@@ -98,52 +140,26 @@ impl Viewer2D {
         settings_window: &mut SettingsWindow,
     ) -> Result<Self> {
         let (node_positions, vertex_buffer, instance_count) = {
-            let pos = NodePositions::from_layout_tsv(layout_tsv)?;
-
-            // TODO: ideally the node IDs and positions would be
-            // stored in different buffers
-            let vertex_data = pos
-                .iter_nodes()
-                .enumerate()
-                .map(|(ix, p)| {
-                    let ix = [ix as u32];
-                    let pos: &[u8] = bytemuck::cast_slice(&p);
-                    let id: &[u8] = bytemuck::cast_slice(&ix);
-                    let mut out = [0u8; 4 * 5];
-                    out[0..(4 * 4)].clone_from_slice(pos);
-                    out[(4 * 4)..].clone_from_slice(id);
-                    out
-                })
-                .collect::<Vec<_>>();
-
-            let instance_count = vertex_data.len();
-
-            let buffer = state.device.create_buffer_init(
-                &wgpu::util::BufferInitDescriptor {
-                    label: Some("Viewer2D Vertex Buffer"),
-                    contents: bytemuck::cast_slice(&vertex_data),
-                    usage: wgpu::BufferUsages::VERTEX,
-                },
-            );
-
+            let og_path = shared.workspace.blocking_read().og_path().cloned();
+            let pos = NodePositions::from_layout_file(
+                layout_tsv,
+                og_path.as_deref(),
+            )?;
+            let (buffer, instance_count) = create_vertex_buffer(state, &pos);
             (pos, buffer, instance_count)
         };
+        let bp_per_unit = node_positions.bp_per_unit(&path_index);
+        let layout_scale = LayoutScale::from_positions(&node_positions);
+        let node_tree = tiles::NodeQuadTree::from_positions(&node_positions);
 
         let win_dims = {
             let [w, h]: [u32; 2] = window.window.inner_size().into();
             Vec2::new(w as f32, h as f32)
         };
 
-        let (tl, br) = node_positions.bounds;
-        let center = tl + 0.5 * (br - tl);
-        let total_size = br - tl;
-
         let aspect = win_dims.x / win_dims.y;
 
-        let cam_width = total_size.y * aspect;
-        let size = Vec2::new(cam_width, total_size.y);
-
-        let view = View2D::new(center, size);
+        let view = View2D::fit_bounds(layout_scale.view_bounds, aspect);
 
         let mut graph = Graph::new();
 
@@ -224,6 +240,8 @@ impl Viewer2D {
             (transform, vert_config)
         };
 
+        // the only node in the graph, drawn below every egui layer in
+        // `gui::layers`
         let draw_node = graph.add_node(draw_node_schema);
 
         graph.add_link_from_transient("vertices", draw_node, 0);
@@ -300,29 +318,15 @@ impl Viewer2D {
 
         let node_positions = Arc::new(node_positions);
 
-        {
-            let annotations = shared
-                .annotations
-                .blocking_read()
-                .annotation_sets
-                .iter()
-                .flat_map(|(set_id, set)| {
-                    (0..set.annotations.len()).map(|i| GlobalAnnotationId {
-                        set_id: *set_id,
-                        annot_id: AnnotationId(i),
-                    })
-                })
-                .collect::<Vec<_>>();
-
-            annotation_layer.load_annotations(
-                shared,
-                node_positions.clone(),
-                annotations,
-            );
-        }
+        annotation_layer.load_annotations(
+            shared,
+            node_positions.clone(),
+            all_annotation_ids(shared),
+        );
 
         let cfg = {
             let cfg = Config::default();
+            shared.config.extend_schema(cfg.schema());
 
             let widget = config::ConfigWidget { cfg: cfg.clone() };
 
@@ -338,13 +342,45 @@ impl Viewer2D {
         let annotation_list_widget =
             AnnotationListWidget::new(shared.annotations.clone());
 
+        let export_viewport = Arc::new(AtomicCell::new(view_bounds(&view)));
+        let export_positions = Arc::new(RwLock::new(node_positions.clone()));
+
+        settings_window.register_widget(
+            "2D Viewer",
+            "Node Export",
+            Arc::new(RwLock::new(
+                crate::app::node_export::NodeExportWidget::new(
+                    shared,
+                    crate::app::node_export::NodeRegion::View2D {
+                        viewport: export_viewport.clone(),
+                        positions: export_positions.clone(),
+                    },
+                ),
+            )),
+        );
+
         Ok(Self {
+            raw_principal_angle: node_positions.principal_angle(),
+            raw_node_positions: node_positions.clone(),
             node_positions,
+            applied_layout_cleanup: (false, false, 0.0),
 
             vertex_buffer,
             instance_count,
 
+            node_tree,
+            visible_buffer: None,
+            visible_capacity: 0,
+            visible_count: 0,
+            visible_nodes: Vec::new(),
+            culled_view: None,
+            draw_culled: false,
+
+            bp_per_unit,
+            layout_scale,
+
             view,
+            view_animation: None,
 
             transform_uniform,
             vert_config,
@@ -364,15 +400,260 @@ impl Viewer2D {
             msg_rx,
 
             cfg,
+            config_generation: 0,
 
             view_control_widget,
 
             annotation_layer,
+            path_overlay: PathOverlay::default(),
+            minimap: Default::default(),
 
             annotation_list_widget,
+            path_list_widget: crate::gui::path_list::PathListWidget::new(
+                shared,
+            ),
+
+            export_viewport,
+            export_positions,
         })
     }
 
+    /// Moves the view smoothly to `target`, from the current view.
+    pub fn animate_to(&mut self, target: View2D) {
+        self.view_animation = Some(ViewAnimation::new(
+            self.view.clone(),
+            target,
+            ViewAnimation::DEFAULT_DURATION,
+        ));
+    }
+
+    /// Moves the view to center the node, keeping the zoom level.
+    pub fn center_on_node(&mut self, node: Node) {
+        self.apply_view_cmd(control::ViewCmd::GotoNode { node });
+    }
+
+    /// Moves the view to show the nodes in the range of the path, or
+    /// of the pangenome if `path` is `None`.
+    pub fn center_on_region(
+        &mut self,
+        path: Option<PathId>,
+        range: std::ops::Range<Bp>,
+    ) {
+        self.apply_view_cmd(control::ViewCmd::GotoRange { path, range });
+    }
+
+    fn apply_view_cmd(&mut self, cmd: control::ViewCmd) {
+        // chained commands start from where the last one is going
+        let current = self
+            .view_animation
+            .as_ref()
+            .map(|anim| anim.target())
+            .unwrap_or(&self.view);
+
+        if let Some(target) = cmd.target_view(
+            &self.shared,
+            &self.node_positions,
+            &self.layout_scale,
+            current,
+        ) {
+            self.animate_to(target);
+        }
+    }
+
+    /// Applies or reverts the layout cleanup options if they've been
+    /// changed in the config, rebuilding the vertex buffer and
+    /// annotation layer.
+    fn update_layout_cleanup(&mut self, state: &raving_wgpu::State) {
+        let rotation = if self.cfg.layout_rotate_to_fit.load() {
+            // the principal axis along the longer side of the window
+            let target = if self.view.aspect() >= 1.0 {
+                0.0
+            } else {
+                std::f32::consts::FRAC_PI_2
+            };
+            target - self.raw_principal_angle
+        } else {
+            0.0
+        };
+
+        let cleanup = (
+            self.cfg.layout_remove_outliers.load(),
+            self.cfg.layout_relax.load(),
+            rotation,
+        );
+
+        if cleanup == self.applied_layout_cleanup {
+            return;
+        }
+
+        let (remove_outliers, relax, rotation) = cleanup;
+        let rotated = rotation != self.applied_layout_cleanup.2;
+
+        let node_positions = if remove_outliers || relax || rotation != 0.0 {
+            let defaults = layout::LayoutCleanup::default();
+
+            let opts = layout::LayoutCleanup {
+                outlier_threshold: if remove_outliers {
+                    defaults.outlier_threshold
+                } else {
+                    f32::INFINITY
+                },
+                relax_iterations: if relax { 10 } else { 0 },
+                rotation,
+            };
+
+            let (cleaned, stats) = self.raw_node_positions.cleaned(&opts);
+            log::warn!(
+                "Layout cleanup: moved {} outlier nodes, {} relaxation moves",
+                stats.outliers,
+                stats.relaxed
+            );
+            Arc::new(cleaned)
+        } else {
+            self.raw_node_positions.clone()
+        };
+
+        let (vertex_buffer, instance_count) =
+            create_vertex_buffer(state, &node_positions);
+
+        self.vertex_buffer = vertex_buffer;
+        self.instance_count = instance_count;
+        self.node_tree = tiles::NodeQuadTree::from_positions(&node_positions);
+        self.culled_view = None;
+        self.bp_per_unit = node_positions.bp_per_unit(&self.shared.graph);
+        self.layout_scale = LayoutScale::from_positions(&node_positions);
+
+        self.annotation_layer = AnnotationLayer::default();
+        self.annotation_layer.load_annotations(
+            &self.shared,
+            node_positions.clone(),
+            all_annotation_ids(&self.shared),
+        );
+
+        *self.export_positions.blocking_write() = node_positions.clone();
+        self.node_positions = node_positions;
+        self.applied_layout_cleanup = cleanup;
+
+        if rotated {
+            self.animate_to(View2D::fit_bounds(
+                self.layout_scale.view_bounds,
+                self.view.aspect(),
+            ));
+        }
+    }
+
+    /// The most recently selected node, in this or another viewer
+    pub fn selected_node(&self) -> Option<Node> {
+        self.shared.selection.blocking_read().primary()
+    }
+
+    /// Replaces the shared selection with `node`, or clears it.
+    pub fn select_node(&mut self, node: Option<Node>) {
+        let node = node.filter(|n| n.ix() < self.shared.graph.node_count);
+        self.shared.selection.blocking_write().select_only(node);
+    }
+
+    /// Outlines the node in screen space; short nodes are circled.
+    fn node_highlight_shape(
+        &self,
+        node: Node,
+        dims: Vec2,
+        color: egui::Color32,
+    ) -> egui::Shape {
+        let (n0, n1) = self.node_positions.node_pos(node);
+        let mid = n0 + (n1 - n0) * 0.5;
+
+        let mat = self.view.to_viewport_matrix(dims);
+
+        let p0 = mat * n0.into_homogeneous_point();
+        let p1 = mat * n1.into_homogeneous_point();
+        let pmid = mat * mid.into_homogeneous_point();
+
+        let dist = (p1.xy() - p0.xy()).mag();
+
+        let p0 = egui::pos2(p0.x, p0.y);
+        let p1 = egui::pos2(p1.x, p1.y);
+        let pmid = egui::pos2(pmid.x, pmid.y);
+
+        let theme = self.shared.theme.load();
+
+        let shapes = if dist > 2.0 {
+            theme
+                .outlined_strokes(5.0, color)
+                .into_iter()
+                .map(|stroke| egui::Shape::line(vec![p0, p1], stroke))
+                .collect()
+        } else {
+            theme
+                .outlined_strokes(2.0, color)
+                .into_iter()
+                .map(|stroke| egui::Shape::circle_stroke(pmid, 5.0, stroke))
+                .collect()
+        };
+
+        egui::Shape::Vec(shapes)
+    }
+
+    fn show_selected_node_window(&mut self, ctx: &egui::Context) {
+        let Some(node) = self.selected_node() else {
+            return;
+        };
+
+        let graph = &self.shared.graph;
+
+        let mut open = true;
+
+        egui::Window::new("Selected Node")
+            .id(egui::Id::new("Viewer2D-Selected-Node"))
+            .open(&mut open)
+            .default_pos([100.0, 100.0])
+            .show(ctx, |ui| {
+                let (offset, len) = graph.node_offset_length(node);
+                let segment_id = node.ix() as u32 + graph.segment_id_range.0;
+
+                ui.label(format!("Node {} (segment {segment_id})", node.ix()));
+                ui.label(format!("Length {} bp", len.0));
+                ui.label(format!(
+                    "Pangenome position {}-{}",
+                    offset.0,
+                    offset.0 + len.0
+                ));
+
+                let mut paths = graph
+                    .paths_on_node(node)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|path| {
+                        let name = graph.path_names.get_by_left(&path)?;
+                        let steps = graph.node_path_steps(node, path)?.count();
+                        Some((name.as_str(), steps))
+                    })
+                    .collect::<Vec<_>>();
+                paths.sort();
+
+                ui.separator();
+                ui.label(format!("On {} paths", paths.len()));
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("Viewer2D-Selected-Node-Paths")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (name, steps) in paths {
+                                    ui.label(name);
+                                    ui.label(format!("{steps}x"));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+
+        if !open {
+            self.select_node(None);
+        }
+    }
+
     fn update_transform_uniform(&self, queue: &wgpu::Queue) {
         let data = self.view.to_matrix();
         queue.write_buffer(
@@ -387,18 +668,173 @@ impl Viewer2D {
         queue: &wgpu::Queue,
         window_dims: [f32; 2],
     ) {
-        // not in pixels (not even sure what it is)
-        let node_width = 120.0;
-
         let [w, h] = window_dims;
+        let dim = w.max(h).max(1.0);
+
+        // the shader offsets the sides of each node by the square of
+        // the configured width, in clip space, so a width of `nw`
+        // gives nodes `nw^2 * dim / 2` pixels wide
+        let default_px = 120.0f32.powi(2) / (2.0 * dim);
+
+        let px_per_unit = h / self.view.size.y;
+        let node_px = if px_per_unit.is_finite() {
+            self.layout_scale.node_width_px(default_px, px_per_unit)
+        } else {
+            default_px
+        };
 
-        let nw = node_width / w.max(h);
+        let nw = (2.0 * node_px / dim).sqrt();
 
         let data: [f32; 4] = [nw, 0.0, 0.0, 0.0];
         queue.write_buffer(&self.vert_config, 0, bytemuck::cast_slice(&[data]));
     }
 }
 
+/// The corners of the view in layout space, (min, max).
+fn view_bounds(view: &View2D) -> (Vec2, Vec2) {
+    let (x0, x1) = view.x_range();
+    let (y0, y1) = view.y_range();
+    (
+        Vec2::new(x0.min(x1), y0.min(y1)),
+        Vec2::new(x0.max(x1), y0.max(y1)),
+    )
+}
+
+fn all_annotation_ids(shared: &SharedState) -> Vec<GlobalAnnotationId> {
+    shared
+        .annotations
+        .blocking_read()
+        .annotation_sets
+        .iter()
+        .flat_map(|(set_id, set)| {
+            (0..set.annotations.len()).map(|i| GlobalAnnotationId {
+                set_id: *set_id,
+                annot_id: AnnotationId(i),
+            })
+        })
+        .collect()
+}
+
+/// Tiles smaller than this many pixels are drawn as one node
+const MERGE_TILE_PX: f32 = 2.0;
+
+/// At most this many nodes of the selected ranges are highlighted
+const MAX_RANGE_HIGHLIGHT_NODES: usize = 10_000;
+
+impl Viewer2D {
+    /// Finds the nodes in view, merging those in tiles too small to
+    /// see, and uploads their instances, if the view has changed.
+    fn update_visible_instances(
+        &mut self,
+        state: &raving_wgpu::State,
+        screen_width: f32,
+    ) {
+        let key = (self.view.center(), self.view.size(), screen_width);
+        if self.culled_view == Some(key) {
+            return;
+        }
+        self.culled_view = Some(key);
+
+        let (min, max) = view_bounds(&self.view);
+        // nodes are drawn wider than their layout, so keep a margin
+        let margin = Vec2::broadcast(self.layout_scale.node_len);
+        let (min, max) = (min - margin, max + margin);
+
+        let units_per_px = self.view.size().x / screen_width.max(1.0);
+
+        self.node_tree.visible_nodes(
+            min,
+            max,
+            MERGE_TILE_PX * units_per_px,
+            &mut self.visible_nodes,
+        );
+
+        let vertex_data = self
+            .visible_nodes
+            .iter()
+            .map(|&ix| {
+                let (p0, p1) = self.node_positions.node_pos(Node::from(ix));
+                let mut out = [0u8; 4 * 5];
+                out[0..(4 * 4)]
+                    .clone_from_slice(bytemuck::cast_slice(&[p0, p1]));
+                out[(4 * 4)..].clone_from_slice(&ix.to_ne_bytes());
+                out
+            })
+            .collect::<Vec<_>>();
+
+        self.visible_count = vertex_data.len();
+
+        if self.visible_count > self.visible_capacity
+            || self.visible_buffer.is_none()
+        {
+            let capacity = self.visible_count.next_power_of_two().max(1024);
+            let size = (capacity * std::mem::size_of::<[u8; 4 * 5]>()) as u64;
+
+            crate::util::gpu_memory::record_buffer(
+                "Viewer2D Visible Vertex Buffer",
+                size,
+            );
+
+            self.visible_buffer =
+                Some(state.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Viewer2D Visible Vertex Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::VERTEX
+                        | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }));
+            self.visible_capacity = capacity;
+        }
+
+        if let Some(buffer) = self.visible_buffer.as_ref() {
+            state.queue.write_buffer(
+                buffer,
+                0,
+                bytemuck::cast_slice(&vertex_data),
+            );
+        }
+    }
+}
+
+fn create_vertex_buffer(
+    state: &State,
+    node_positions: &NodePositions,
+) -> (wgpu::Buffer, usize) {
+    // TODO: ideally the node IDs and positions would be
+    // stored in different buffers
+    let vertex_data = node_positions
+        .iter_nodes()
+        .enumerate()
+        .map(|(ix, p)| {
+            let ix = [ix as u32];
+            let pos: &[u8] = bytemuck::cast_slice(&p);
+            let id: &[u8] = bytemuck::cast_slice(&ix);
+            let mut out = [0u8; 4 * 5];
+            out[0..(4 * 4)].clone_from_slice(pos);
+            out[(4 * 4)..].clone_from_slice(id);
+            out
+        })
+        .collect::<Vec<_>>();
+
+    let instance_count = vertex_data.len();
+
+    crate::util::gpu_memory::record_buffer(
+        "Viewer2D Vertex Buffer",
+        (vertex_data.len() * std::mem::size_of::<[u8; 4 * 5]>()) as u64,
+    );
+
+    let buffer =
+        state
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Viewer2D Vertex Buffer"),
+                contents: bytemuck::cast_slice(&vertex_data),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+    (buffer, instance_count)
+}
+
 impl AppWindow for Viewer2D {
     fn update(
         &mut self,
@@ -409,26 +845,40 @@ impl AppWindow for Viewer2D {
         context_state: &mut ContextState,
         dt: f32,
     ) {
+        self.config_generation = self
+            .shared
+            .config
+            .sync_cells(&self.cfg.config_cells(), self.config_generation);
+
         while let Ok(msg) = self.msg_rx.try_recv() {
             match msg {
-                control::Msg::View(cmd) => cmd.apply(
-                    &self.shared,
-                    &self.node_positions,
-                    &mut self.view,
-                ),
+                control::Msg::View(cmd) => self.apply_view_cmd(cmd),
+            }
+        }
+
+        if let Some(anim) = self.view_animation.as_mut() {
+            let (view, done) = anim.step(dt);
+            self.view = view;
+            if done {
+                self.view_animation = None;
             }
         }
 
+        self.update_layout_cleanup(state);
+
+        self.export_viewport.store(view_bounds(&self.view));
+
         egui_ctx.begin_frame(&window.window);
 
         let [width, height]: [u32; 2] = window.window.inner_size().into();
         let dims = ultraviolet::Vec2::new(width as f32, height as f32);
 
-        let scale_dims = dims * egui_ctx.ctx().pixels_per_point();
+        // egui works in points, the geometry buffers in pixels
+        let pixels_per_point = egui_ctx.ctx().pixels_per_point();
 
         let screen_rect = egui::Rect::from_min_max(
             egui::pos2(0.0, 0.0),
-            egui::pos2(dims.x, dims.y),
+            egui::pos2(dims.x, dims.y) / pixels_per_point,
         );
 
         let (main_panel_rect, side_panel_rect) = {
@@ -442,6 +892,11 @@ impl AppWindow for Viewer2D {
 
                     ui.separator();
 
+                    egui::CollapsingHeader::new("Paths")
+                        .show(ui, |ui| self.path_list_widget.show(ui));
+
+                    ui.separator();
+
                     util::node_context_side_panel_info(
                         &self.shared.graph,
                         context_state,
@@ -493,26 +948,12 @@ impl AppWindow for Viewer2D {
                             }
 
                             if label.clicked_by(egui::PointerButton::Primary) {
-                                // pan to annotation
-                                let nodes =
-                                    self.shared.graph.path_step_range_iter(
-                                        annotation.path,
-                                        annotation.range.clone(),
-                                    );
-                                let node_centers =
-                                    nodes.unwrap().map(|(_, step)| {
-                                        let (n0, n1) = self
-                                            .node_positions
-                                            .node_pos(step.node());
-                                        let mid = n0 + (n1 - n0) * 0.5;
-                                        mid
-                                    });
-
-                                let pos = crate::util::geometry::centroid(
-                                    node_centers,
-                                );
-
-                                self.view.center = pos;
+                                let _ = self.msg_tx.send(control::Msg::View(
+                                    control::ViewCmd::GotoRange {
+                                        path: Some(annotation.path),
+                                        range: annotation.range.clone(),
+                                    },
+                                ));
                             }
 
                             if label.clicked_by(egui::PointerButton::Secondary)
@@ -534,9 +975,9 @@ impl AppWindow for Viewer2D {
             (main_panel, side_panel_rect)
         };
 
-        let dims = dims / egui_ctx.ctx().pixels_per_point();
+        let dims = dims / pixels_per_point;
 
-        let mut annot_shapes = Vec::new();
+        let mut annot_shapes = LayeredShapes::default();
 
         let hovered_node_1d = context_state
             // .query_get_cast::<_, Node>(Some("Viewer1D"), ["hover"])
@@ -547,37 +988,94 @@ impl AppWindow for Viewer2D {
             .query_get_cast::<_, Node>(Some("Viewer1D"), ["goto"])
             .copied();
 
-        if let Some(node) = hovered_node_1d {
-            let (n0, n1) = self.node_positions.node_pos(node);
-            let mid = n0 + (n1 - n0) * 0.5;
+        let theme = self.shared.theme.load();
 
-            // a bit hacky but its fine
-            if goto_node_1d.is_some() {
-                self.view.center = mid;
-            }
+        let zoom = self.shared.zoom_thresholds.load();
+        let bp_per_px = self.view.size().x as f64 / dims.x.max(1.0) as f64
+            * self.bp_per_unit;
 
-            let mat = self.view.to_viewport_matrix(dims);
+        self.draw_culled = self.cfg.cull_nodes.load();
+        if self.draw_culled {
+            self.update_visible_instances(state, dims.x);
+        }
+
+        let instance_count = if self.draw_culled {
+            self.visible_count
+        } else {
+            self.instance_count
+        };
+
+        let instances = if zoom.visible(ZoomLayer::Nodes, bp_per_px) {
+            0..instance_count as u32
+        } else {
+            0..0
+        };
+        self.render_graph.set_node_preprocess_fn(
+            self.draw_node,
+            move |_ctx, op_state| {
+                op_state.vertices = Some(0..6);
+                op_state.instances = Some(instances.clone());
+            },
+        );
 
-            let p0 = mat * n0.into_homogeneous_point();
-            let p1 = mat * n1.into_homogeneous_point();
-            let pmid = mat * mid.into_homogeneous_point();
+        if zoom.visible(ZoomLayer::PathLines, bp_per_px) {
+            self.path_overlay.update(&self.shared, &self.node_positions);
+            annot_shapes.extend(
+                Layer::Nodes,
+                self.path_overlay.shapes(&self.view, dims, &theme),
+            );
+        }
 
-            let dist = (p1.xy() - p0.xy()).mag();
+        {
+            let selection = self.shared.selection.blocking_read();
+            let graph = &self.shared.graph;
 
-            let p0 = egui::pos2(p0.x, p0.y);
-            let p1 = egui::pos2(p1.x, p1.y);
-            let pmid = egui::pos2(pmid.x, pmid.y);
+            let range_nodes = selection
+                .ranges()
+                .iter()
+                .filter(|range| {
+                    range.start < range.end
+                        && range.start < graph.pangenome_len()
+                })
+                .flat_map(|range| {
+                    let end = range.end.min(graph.pangenome_len());
+                    let nodes = graph.pos_range_nodes(range.start.0..end.0 - 1);
+                    let (first, last) = nodes.into_inner();
+                    (first.ix()..=last.ix()).map(Node::from)
+                });
 
-            if dist > 2.0 {
-                let stroke = egui::Stroke::new(5.0, egui::Color32::RED);
-                annot_shapes.push(egui::Shape::line(vec![p0, p1], stroke));
-            } else {
-                let stroke = egui::Stroke::new(2.0, egui::Color32::RED);
-                annot_shapes
-                    .push(egui::Shape::circle_stroke(pmid, 5.0, stroke));
+            let nodes = selection
+                .nodes()
+                .chain(range_nodes.take(MAX_RANGE_HIGHLIGHT_NODES));
+
+            for node in nodes {
+                annot_shapes.push(
+                    Layer::Highlights,
+                    self.node_highlight_shape(
+                        node,
+                        dims,
+                        theme.selection_color(),
+                    ),
+                );
             }
+        }
 
-            let node_len = self.shared.graph.node_length(node);
+        self.show_selected_node_window(egui_ctx.ctx());
+
+        if let Some(node) = hovered_node_1d {
+            // a bit hacky but its fine
+            if goto_node_1d.is_some() {
+                self.center_on_node(node);
+            }
+
+            annot_shapes.push(
+                Layer::Highlights,
+                self.node_highlight_shape(node, dims, theme.hover_color()),
+            );
+
+            let (node_offset, node_len) =
+                self.shared.graph.node_offset_length(node);
+            let fmt = PosFormat::load(&self.shared);
 
             egui::containers::popup::show_tooltip(
                 egui_ctx.ctx(),
@@ -585,8 +1083,33 @@ impl AppWindow for Viewer2D {
                 |ui| {
                     ui.label(format!("Node {}", node.ix()));
                     ui.label(format!("Length {} bp", node_len.0));
+                    if fmt.reference.is_some() {
+                        let range = node_offset..Bp(node_offset.0 + node_len.0);
+                        let range = fmt.range(&self.shared.graph, &range);
+                        ui.label(format!("Ref {range}"));
+                    }
+
+                    let seq = self.shared.graph.node_sequence(node);
+                    let mut text =
+                        String::from_utf8_lossy(&seq[..seq.len().min(32)])
+                            .into_owned();
+                    if seq.len() > 32 {
+                        text.push('…');
+                    }
+                    ui.monospace(text);
+                    ui.weak("P to pin");
                 },
             );
+
+            if pinned::pin_key_pressed(egui_ctx.ctx()) {
+                let pos =
+                    egui_ctx.ctx().pointer_hover_pos().unwrap_or_default();
+                self.shared.pinned.blocking_write().pin(
+                    AppType::Viewer2D,
+                    PinnedItem::Node(node),
+                    pos,
+                );
+            }
         }
 
         let mut highlight_annots: HashSet<GlobalAnnotationId> =
@@ -618,11 +1141,15 @@ impl AppWindow for Viewer2D {
             let annotations = self.shared.annotations.blocking_read();
 
             for annot_id in highlight_annots {
-                let annot = annotations.get(annot_id);
+                let Some(annot) = annotations.get(annot_id) else {
+                    continue;
+                };
 
                 let stroke = egui::Stroke::new(
                     5.0,
-                    annot.color.unwrap_or(egui::Color32::RED),
+                    annotations
+                        .get_color(annot_id)
+                        .unwrap_or(egui::Color32::RED),
                 );
 
                 let mut shapes_vec = Vec::new();
@@ -645,12 +1172,14 @@ impl AppWindow for Viewer2D {
                         ));
                     }
 
-                    annot_shapes.push(egui::Shape::Vec(shapes_vec));
+                    annot_shapes
+                        .push(Layer::Annotations, egui::Shape::Vec(shapes_vec));
                 }
             }
         }
 
         let mut hover_pos: Option<[f32; 2]> = None;
+        let mut clicked = false;
 
         {
             let ctx = egui_ctx.ctx();
@@ -671,6 +1200,7 @@ impl AppWindow for Viewer2D {
 
                 self.view.translate_size_rel(t_);
                 self.view.size *= z;
+                self.view_animation = None;
             }
 
             main_area.show(ctx, |ui| {
@@ -690,8 +1220,11 @@ impl AppWindow for Viewer2D {
                     let mut norm_delta = -1.0 * (delta / dims);
                     norm_delta.y *= -1.0;
                     self.view.translate_size_rel(norm_delta);
+                    self.view_animation = None;
                 }
 
+                clicked = area_rect.clicked();
+
                 if let Some(pos) = area_rect.hover_pos() {
                     hover_pos = Some([pos.x, pos.y]);
 
@@ -706,14 +1239,18 @@ impl AppWindow for Viewer2D {
                         let mut norm = uvp / dims;
                         norm.y = 1.0 - norm.y;
                         self.view.zoom_with_focus(norm, dz);
+                        self.view_animation = None;
                     }
                 }
 
                 let painter = ui.painter();
 
-                painter.extend(annot_shapes);
+                painter.extend(annot_shapes.into_sorted());
 
-                if self.cfg.show_annotation_labels.load() {
+                // labels go on top of everything in the annotations layer
+                if self.cfg.show_annotation_labels.load()
+                    && zoom.visible(ZoomLayer::Labels, bp_per_px)
+                {
                     self.annotation_layer.draw(
                         tokio_handle,
                         &self.shared,
@@ -724,13 +1261,88 @@ impl AppWindow for Viewer2D {
                     );
                 }
             });
+
+            if self.cfg.show_minimap.load() {
+                let bounds = self.layout_scale.view_bounds;
+                self.minimap.update(
+                    tokio_handle,
+                    ctx,
+                    &self.node_positions,
+                    bounds,
+                );
+
+                let color = ctx.style().visuals.text_color();
+                if let Some(center) = self.minimap.show(
+                    ctx,
+                    main_panel_rect,
+                    bounds,
+                    &self.view,
+                    color,
+                ) {
+                    self.view.center = center;
+                    self.view_animation = None;
+                }
+            }
         }
 
+        self.shared.pinned.blocking_write().show(
+            egui_ctx.ctx(),
+            &self.shared,
+            &AppType::Viewer2D,
+        );
+
         egui_ctx.end_frame(&window.window);
 
         if let Some(hover_pos) = hover_pos {
             // look up in geometry buffer
-            let node = self.geometry_bufs.lookup(&state.device, hover_pos);
+            let [x, y] = hover_pos;
+            let pixel_pos = [x * pixels_per_point, y * pixels_per_point];
+            let node = self.geometry_bufs.lookup(&state.device, pixel_pos);
+
+            let measuring = self.shared.measure.blocking_read().active;
+
+            if clicked && measuring {
+                if let Some((node, u)) =
+                    node.filter(|(n, _)| n.ix() < self.shared.graph.node_count)
+                {
+                    let (node_offset, node_len) =
+                        self.shared.graph.node_offset_length(node);
+                    let local_pos =
+                        (u as f64 * node_len.0 as f64).round() as u64;
+                    let local_pos = local_pos.min(node_len.0.saturating_sub(1));
+
+                    let (n0, n1) = self.node_positions.node_pos(node);
+                    let p = n0 + (n1 - n0) * u;
+
+                    self.shared.measure.blocking_write().add_point(
+                        MeasurePoint {
+                            pos: Bp(node_offset.0 + local_pos),
+                            layout_pos: Some([p.x, p.y]),
+                        },
+                    );
+                }
+            }
+
+            // clicking outside the graph clears the selection, and
+            // shift-clicking adds to or removes from it
+            if clicked && !measuring {
+                let node = node
+                    .map(|(node, _)| node)
+                    .filter(|n| n.ix() < self.shared.graph.node_count);
+                let shift = egui_ctx.ctx().input(|i| i.modifiers.shift);
+
+                match node {
+                    Some(node) if shift => {
+                        self.shared.selection.blocking_write().toggle(node)
+                    }
+                    _ => self.select_node(node),
+                }
+
+                if let Some(node) = self.selected_node() {
+                    context_state.set("Viewer2D", ["selected"], node);
+                }
+            }
+
             if let Some((node, u)) = node {
                 if node.ix() < self.shared.graph.node_count {
                     let (node_offset, node_len) =
@@ -756,6 +1368,11 @@ impl AppWindow for Viewer2D {
         let width = window.window.inner_size().width as f32;
         let height = window.window.inner_size().height as f32;
 
+        self.view.constrain_to_bounds_with_min(
+            self.layout_scale.view_bounds,
+            self.layout_scale.min_view_height(),
+        );
+
         self.update_transform_uniform(&state.queue);
         self.update_vert_config_uniform(&state.queue, [width, height]);
     }
@@ -775,34 +1392,46 @@ impl AppWindow for Viewer2D {
 
                 let mut translation = Vec2::zero();
 
+                // relative to the view, so the same at any zoom level
+                let pan = self.cfg.key_pan_step.load();
+                let zoom = self.cfg.key_zoom_step.load();
+
                 if pressed {
                     match key {
                         Key::Right => {
-                            translation.x += 0.1;
+                            translation.x += pan;
                         }
                         Key::Left => {
-                            translation.x -= 0.1;
+                            translation.x -= pan;
                         }
                         Key::Up => {
-                            translation.y += 0.1;
+                            translation.y += pan;
                         }
                         Key::Down => {
-                            translation.y -= 0.1;
+                            translation.y -= pan;
                         }
-                        Key::Space => {
-                            println!("resetting view");
-                            // self.view.reset();
-                            let (tl, br) = self.node_positions.bounds;
-                            let center = tl + 0.5 * (br - tl);
-                            let total_size = br - tl;
-
+                        Key::Equals | Key::Plus | Key::NumpadAdd => {
+                            let center = Vec2::new(0.5, 0.5);
+                            self.view.zoom_with_focus(center, 1.0 / zoom);
+                            self.view_animation = None;
+                        }
+                        Key::Minus | Key::NumpadSubtract => {
+                            let center = Vec2::new(0.5, 0.5);
+                            self.view.zoom_with_focus(center, zoom);
+                            self.view_animation = None;
+                        }
+                        Key::R => {
+                            let rotate = &self.cfg.layout_rotate_to_fit;
+                            rotate.store(!rotate.load());
+                        }
+                        Key::Space | Key::Home => {
                             let [w, h] = window_dims;
                             let aspect = w as f32 / h as f32;
 
-                            let cam_width = total_size.y * aspect;
-                            let size = Vec2::new(cam_width, total_size.y);
-
-                            self.view = View2D::new(center, size);
+                            self.animate_to(View2D::fit_bounds(
+                                self.layout_scale.view_bounds,
+                                aspect,
+                            ));
                         }
                         _ => (),
                     }
@@ -810,6 +1439,7 @@ impl AppWindow for Viewer2D {
 
                 if translation.mag() > 0.0 {
                     self.view.translate_size_rel(translation);
+                    self.view_animation = None;
                 }
             }
         }
@@ -817,6 +1447,31 @@ impl AppWindow for Viewer2D {
         consume
     }
 
+    fn view_state(&self) -> Option<crate::app::ViewState> {
+        Some(crate::app::ViewState::View2D {
+            center: *self.view.center.as_array(),
+            size: *self.view.size.as_array(),
+        })
+    }
+
+    fn goto_region(
+        &mut self,
+        path: Option<PathId>,
+        range: std::ops::Range<Bp>,
+    ) {
+        self.center_on_region(path, range);
+    }
+
+    fn goto_node(&mut self, node: Node) {
+        self.center_on_node(node);
+    }
+
+    fn restore_view_state(&mut self, view_state: &crate::app::ViewState) {
+        if let crate::app::ViewState::View2D { center, size } = view_state {
+            self.view = View2D::new(Vec2::from(*center), Vec2::from(*size));
+        }
+    }
+
     fn on_resize(
         &mut self,
         state: &raving_wgpu::State,
@@ -824,7 +1479,16 @@ impl AppWindow for Viewer2D {
         new_window_dims: [u32; 2],
     ) -> anyhow::Result<()> {
         let aspect = new_window_dims[0] as f32 / new_window_dims[1] as f32;
-        self.view.set_aspect(aspect);
+
+        // refit the region that was visible before the resize, so
+        // that it's still visible afterward
+        if aspect.is_finite() && aspect > 0.0 {
+            let (x0, x1) = self.view.x_range();
+            let (y0, y1) = self.view.y_range();
+            let visible = (Vec2::new(x0, y0), Vec2::new(x1, y1));
+            self.view = View2D::fit_bounds(visible, aspect);
+            self.view_animation = None;
+        }
 
         log::info!("reallocating geometry buffers");
         self.geometry_bufs = GeometryBuffers::allocate(state, new_window_dims)?;
@@ -860,12 +1524,17 @@ impl AppWindow for Viewer2D {
         self.geometry_bufs.use_as_resource(&mut transient_res);
 
         let v_stride = std::mem::size_of::<[f32; 5]>();
+        let (vertices, instance_count) =
+            match (self.draw_culled, self.visible_buffer.as_ref()) {
+                (true, Some(buffer)) => (buffer, self.visible_count),
+                _ => (&self.vertex_buffer, self.instance_count),
+            };
         transient_res.insert(
             "vertices".into(),
             InputResource::Buffer {
-                size: self.instance_count * v_stride,
+                size: instance_count * v_stride,
                 stride: Some(v_stride),
-                buffer: &self.vertex_buffer,
+                buffer: vertices,
             },
         );
 
@@ -1313,6 +1982,26 @@ impl GeometryBuffers {
             }
         };
 
+        {
+            use crate::util::gpu_memory::{record_buffer, record_texture};
+
+            let [w, h] = dims;
+            let aligned = [aligned_width as u32, h];
+
+            record_texture("Viewer2D Node ID Attch.", [w, h], 4);
+            record_texture("Viewer2D Node Position Attch.", [w, h], 8);
+            record_texture("Viewer2D Node ID Copy Dst", aligned, 4);
+            record_texture("Viewer2D Node Position Copy Dst", aligned, 8);
+            record_buffer(
+                "Viewer2D Node ID Output Buffer",
+                node_id_buf.size as u64,
+            );
+            record_buffer(
+                "Viewer2D Node UV Output Buffer",
+                node_uv_buf.size as u64,
+            );
+        }
+
         Ok(Self {
             dims,
             node_id_tex,
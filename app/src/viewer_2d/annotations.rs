@@ -33,18 +33,51 @@ struct AnchorSet {
 
 type AnchorTreeObj = GeomWithData<Line<[f32; 2]>, (Node, AnnotObjId)>;
 
+/// Seconds over which labels move from the previous layout to the
+/// next, when a layout task finishes
+const LAYOUT_BLEND_TIME: f32 = 0.15;
+
+/// Label positions in world space, so that they can be drawn in any
+/// view until the next layout is computed.
+type LabelLayout = Vec<(GlobalAnnotationId, Vec2)>;
+
 #[derive(Default)]
 pub struct AnnotationLayer {
     state: Arc<RwLock<AnnotationLayerState>>,
 
     last_view: Option<View2D>,
 
-    to_draw_task:
-        Option<JoinHandle<(View2D, Vec<(GlobalAnnotationId, [f32; 2])>)>>,
-    to_draw_cache: Vec<(GlobalAnnotationId, [f32; 2])>,
+    to_draw_task: Option<JoinHandle<(View2D, LabelLayout)>>,
+
+    // the last two finished layouts; labels are drawn interpolated
+    // from the previous to the current one while `blend` < 1
+    prev_layout: HashMap<GlobalAnnotationId, Vec2>,
+    layout: LabelLayout,
+    blend: f32,
 }
 
 impl AnnotationLayer {
+    /// Makes `layout` the current layout, keeping the positions of
+    /// the current one to move the labels from.
+    fn swap_layout(&mut self, layout: LabelLayout) {
+        let prev = std::mem::replace(&mut self.layout, layout);
+
+        // if the labels were still moving, they continue from where
+        // they were drawn last
+        let t = self.blend;
+        let mut prev_layout = HashMap::with_capacity(prev.len());
+        for (annot_id, pos) in prev {
+            let pos = match self.prev_layout.get(&annot_id) {
+                Some(before) => *before + (pos - *before) * t,
+                None => pos,
+            };
+            prev_layout.insert(annot_id, pos);
+        }
+
+        self.prev_layout = prev_layout;
+        self.blend = 0.0;
+    }
+
     pub fn load_annotations(
         &self,
         shared: &SharedState,
@@ -98,11 +131,14 @@ impl AnnotationLayer {
 
                 let state = state.read().await;
 
+                let to_world = view.to_viewport_matrix(dims).inversed();
+
                 let to_draw_annots = to_draw_objs
                     .into_iter()
                     .map(|(obj_id, pos)| {
                         let obj = &state.annot_objs[obj_id];
-                        (obj.annot_id, pos)
+                        let pos = Vec2::from(pos).into_homogeneous_point();
+                        (obj.annot_id, (to_world * pos).xy())
                     })
                     .collect();
 
@@ -121,26 +157,69 @@ impl AnnotationLayer {
 
             if let Some((task_view, to_draw)) = tokio_rt.block_on(handle).ok() {
                 self.last_view = Some(task_view);
-                self.to_draw_cache = to_draw;
+                self.swap_layout(to_draw);
             }
         }
 
+        let dt = painter.ctx().input(|i| i.stable_dt);
+        if self.blend < 1.0 {
+            self.blend = (self.blend + dt / LAYOUT_BLEND_TIME).min(1.0);
+            painter.ctx().request_repaint();
+        }
+
         let annots = shared.annotations.blocking_read();
+        let theme = shared.theme.load();
+
+        // the latest layouts are drawn in the current view, so labels
+        // follow the view while the next layout is computed
+        let mat = view.to_viewport_matrix(dims);
+        let t = self.blend;
 
-        // use latest task results to draw labels
-        for (annot_id, pos) in &self.to_draw_cache {
-            let text = &annots.get(*annot_id).label;
+        let current = self.layout.iter().map(|(annot_id, pos)| {
+            let pos = match self.prev_layout.get(annot_id) {
+                Some(prev) => *prev + (*pos - *prev) * t,
+                None => *pos,
+            };
+            let alpha = if self.prev_layout.contains_key(annot_id) {
+                1.0
+            } else {
+                t
+            };
+            (*annot_id, pos, alpha)
+        });
+
+        // labels that are left out of the current layout fade out
+        let current_ids = if t < 1.0 {
+            self.layout
+                .iter()
+                .map(|(id, _)| *id)
+                .collect::<HashSet<_>>()
+        } else {
+            HashSet::new()
+        };
+        let removed = self
+            .prev_layout
+            .iter()
+            .filter(|_| t < 1.0)
+            .filter(|(id, _)| !current_ids.contains(*id))
+            .map(|(annot_id, pos)| (*annot_id, *pos, 1.0 - t));
+
+        for (annot_id, pos, alpha) in current.chain(removed) {
+            let annot = annots.get(annot_id);
+            let pos = (mat * pos.into_homogeneous_point()).xy();
+
+            let color =
+                annot.label_color.unwrap_or_else(|| theme.label_color());
 
             let shape = painter.fonts(|fonts| {
                 let font = egui::FontId::proportional(16.0);
-                let color = egui::Color32::WHITE;
-                egui::Shape::text(
+                theme.text_shape(
                     &fonts,
-                    pos.into(),
+                    egui::pos2(pos.x, pos.y),
                     egui::Align2::CENTER_CENTER,
-                    &text,
+                    &annot.label,
                     font,
-                    color,
+                    color.linear_multiply(alpha),
                 )
             });
 
@@ -1,21 +1,113 @@
 use crossbeam::atomic::AtomicCell;
 use std::sync::Arc;
 
+use crate::app::config::{ConfigCell, ConfigSchema};
 use crate::app::settings_menu;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub(super) show_annotation_labels: Arc<AtomicCell<bool>>,
+
+    pub(super) layout_remove_outliers: Arc<AtomicCell<bool>>,
+    pub(super) layout_relax: Arc<AtomicCell<bool>>,
+    /// Rotate the layout so that it's most spread out along the
+    /// longer side of the window
+    pub(super) layout_rotate_to_fit: Arc<AtomicCell<bool>>,
+
+    /// Fraction of the view the arrow keys pan by
+    pub(super) key_pan_step: Arc<AtomicCell<f32>>,
+    /// Factor the `+` and `-` keys zoom by
+    pub(super) key_zoom_step: Arc<AtomicCell<f32>>,
+
+    /// Draw only the nodes in view, with those in tiles smaller than a
+    /// few pixels merged into one
+    pub(super) cull_nodes: Arc<AtomicCell<bool>>,
+
+    pub(super) show_minimap: Arc<AtomicCell<bool>>,
 }
 
 impl std::default::Default for Config {
     fn default() -> Self {
         Self {
             show_annotation_labels: Arc::new(true.into()),
+
+            layout_remove_outliers: Arc::new(false.into()),
+            layout_relax: Arc::new(false.into()),
+            layout_rotate_to_fit: Arc::new(false.into()),
+
+            key_pan_step: Arc::new(0.1.into()),
+            key_zoom_step: Arc::new(1.25.into()),
+
+            cull_nodes: Arc::new(true.into()),
+
+            show_minimap: Arc::new(true.into()),
         }
     }
 }
 
+impl Config {
+    /// The `viewer_2d.*` config keys, with the current values as
+    /// defaults
+    pub(super) fn schema(&self) -> ConfigSchema {
+        ConfigSchema::builder()
+            .bool(
+                "viewer_2d.show_annotation_labels",
+                self.show_annotation_labels.load(),
+            )
+            .bool(
+                "viewer_2d.layout_remove_outliers",
+                self.layout_remove_outliers.load(),
+            )
+            .bool("viewer_2d.layout_relax", self.layout_relax.load())
+            .bool(
+                "viewer_2d.layout_rotate_to_fit",
+                self.layout_rotate_to_fit.load(),
+            )
+            .float(
+                "viewer_2d.key_pan_step",
+                self.key_pan_step.load(),
+                0.02..=0.5,
+            )
+            .float(
+                "viewer_2d.key_zoom_step",
+                self.key_zoom_step.load(),
+                1.05..=3.0,
+            )
+            .bool("viewer_2d.cull_nodes", self.cull_nodes.load())
+            .bool("viewer_2d.show_minimap", self.show_minimap.load())
+            .build()
+    }
+
+    pub(super) fn config_cells(&self) -> [(&'static str, ConfigCell); 8] {
+        use ConfigCell as C;
+        [
+            (
+                "viewer_2d.show_annotation_labels",
+                C::Bool(self.show_annotation_labels.clone()),
+            ),
+            (
+                "viewer_2d.layout_remove_outliers",
+                C::Bool(self.layout_remove_outliers.clone()),
+            ),
+            ("viewer_2d.layout_relax", C::Bool(self.layout_relax.clone())),
+            (
+                "viewer_2d.layout_rotate_to_fit",
+                C::Bool(self.layout_rotate_to_fit.clone()),
+            ),
+            (
+                "viewer_2d.key_pan_step",
+                C::Float(self.key_pan_step.clone()),
+            ),
+            (
+                "viewer_2d.key_zoom_step",
+                C::Float(self.key_zoom_step.clone()),
+            ),
+            ("viewer_2d.cull_nodes", C::Bool(self.cull_nodes.clone())),
+            ("viewer_2d.show_minimap", C::Bool(self.show_minimap.clone())),
+        ]
+    }
+}
+
 pub struct ConfigWidget {
     pub(super) cfg: Config,
 }
@@ -27,10 +119,64 @@ impl settings_menu::SettingsWidget for ConfigWidget {
         _settings_ctx: &settings_menu::SettingsUiContext,
     ) -> settings_menu::SettingsUiResponse {
         let mut filter_paths = self.cfg.show_annotation_labels.load();
-        let response =
+        let mut response =
             ui.checkbox(&mut filter_paths, "Display annotation labels");
         self.cfg.show_annotation_labels.store(filter_paths);
 
+        ui.separator();
+        ui.label("Layout cleanup");
+
+        let mut remove_outliers = self.cfg.layout_remove_outliers.load();
+        response |= ui.checkbox(&mut remove_outliers, "Move in outlier nodes");
+        self.cfg.layout_remove_outliers.store(remove_outliers);
+
+        let mut relax = self.cfg.layout_relax.load();
+        response |= ui.checkbox(&mut relax, "Relax overlapping nodes");
+        self.cfg.layout_relax.store(relax);
+
+        let mut rotate = self.cfg.layout_rotate_to_fit.load();
+        response |= ui
+            .checkbox(&mut rotate, "Rotate to fit the window (R)")
+            .on_hover_text(
+                "Turns the layout so that its longest direction is along \
+                 the longer side of the window",
+            );
+        self.cfg.layout_rotate_to_fit.store(rotate);
+
+        ui.separator();
+
+        let mut cull = self.cfg.cull_nodes.load();
+        response |= ui
+            .checkbox(&mut cull, "Draw only nodes in view")
+            .on_hover_text(
+                "Nodes in regions smaller than a few pixels are merged \
+                 into one, which keeps large layouts responsive",
+            );
+        self.cfg.cull_nodes.store(cull);
+
+        let mut minimap = self.cfg.show_minimap.load();
+        response |= ui.checkbox(&mut minimap, "Show minimap").on_hover_text(
+            "An overview of the whole layout in the bottom left \
+                 corner; click or drag in it to move the view",
+        );
+        self.cfg.show_minimap.store(minimap);
+
+        ui.separator();
+        ui.label("Keyboard navigation");
+
+        let mut pan = self.cfg.key_pan_step.load();
+        response |= ui.add(
+            egui::Slider::new(&mut pan, 0.02..=0.5)
+                .text("Arrow key pan (fraction of view)"),
+        );
+        self.cfg.key_pan_step.store(pan);
+
+        let mut zoom = self.cfg.key_zoom_step.load();
+        response |= ui.add(
+            egui::Slider::new(&mut zoom, 1.05..=3.0).text("+/- zoom factor"),
+        );
+        self.cfg.key_zoom_step.store(zoom);
+
         settings_menu::SettingsUiResponse { response }
     }
 }
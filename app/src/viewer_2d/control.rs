@@ -1,8 +1,10 @@
 use waragraph_core::graph::{Bp, Node, PathId};
 
 use crate::app::SharedState;
+use crate::viewer_1d::control::{GotoBox, GotoTarget};
 
-use super::{layout::NodePositions, view::View2D};
+use super::layout::{LayoutScale, NodePositions};
+use super::view::View2D;
 
 pub enum Msg {
     View(ViewCmd),
@@ -13,6 +15,7 @@ struct ViewMsgParams {
 }
 
 pub enum ViewCmd {
+    Home,
     GotoNode {
         node: Node,
     },
@@ -23,32 +26,34 @@ pub enum ViewCmd {
 }
 
 impl ViewCmd {
-    pub fn apply(
+    /// The view that the command moves `view` to, which the viewer
+    /// animates to.
+    pub fn target_view(
         self,
         shared: &SharedState,
         node_layout: &NodePositions,
-        view: &mut View2D,
-    ) {
+        scale: &LayoutScale,
+        view: &View2D,
+    ) -> Option<View2D> {
         match self {
+            ViewCmd::Home => {
+                Some(View2D::fit_bounds(scale.view_bounds, view.aspect()))
+            }
             ViewCmd::GotoNode { node } => {
                 // TODO improve; make sure the scale is correct (i.e.
                 // the node fits on the screen properly)
                 let (p0, p1) = node_layout.node_pos(node);
                 let mid = p0 + (p1 - p0) * 0.5;
-                view.center = mid;
+                Some(View2D::new(mid, view.size))
             }
             ViewCmd::GotoRange { path, range } => {
                 use ultraviolet::Vec2;
 
                 let bounds = if let Some(path) = path {
-                    let steps = shared.graph.path_step_range_iter(path, range);
-
-                    if steps.is_none() {
-                        return;
-                    }
+                    let steps =
+                        shared.graph.path_step_range_iter(path, range)?;
 
                     let steps = steps
-                        .unwrap()
                         .map(|(_, step)| node_layout.node_pos(step.node()));
 
                     let bounds = steps.fold(
@@ -68,7 +73,8 @@ impl ViewCmd {
                     bounds
                 } else {
                     let node0 = shared.graph.node_at_pangenome_pos(range.start);
-                    let node1 = shared.graph.node_at_pangenome_pos(range.end);
+                    let last = Bp(range.end.0.saturating_sub(1));
+                    let node1 = shared.graph.node_at_pangenome_pos(last);
 
                     let bounds = node0.zip(node1).and_then(|(n0, n1)| {
                         let (a0, a1) = node_layout.node_pos(n0);
@@ -97,19 +103,23 @@ impl ViewCmd {
                         }
                     });
 
-                    if let Some(bounds) = bounds {
-                        bounds
-                    } else {
-                        return;
-                    }
+                    bounds?
                 };
 
-                // TODO set scale as well
+                if bounds.0.component_min().is_infinite()
+                    || bounds.1.component_max().is_infinite()
+                {
+                    return None;
+                }
 
-                let (p0, p1) = bounds;
+                // a single short node would be framed too closely
+                let (min, max) = bounds;
+                let center = (min + max) * 0.5;
+                let half = ((max - min) * 0.5)
+                    .max_by_component(Vec2::broadcast(scale.node_len * 0.5));
+                let bounds = (center - half, center + half);
 
-                let mid = p0 + (p1 - p0) * 0.5;
-                view.center = mid;
+                Some(View2D::frame_region(bounds, view.aspect()))
             }
         }
     }
@@ -119,8 +129,7 @@ pub struct ViewControlWidget {
     shared: SharedState,
     msg_tx: crossbeam::channel::Sender<Msg>,
 
-    node_id_text: String,
-    pos_text: String,
+    goto_box: GotoBox,
 }
 
 impl ViewControlWidget {
@@ -132,58 +141,30 @@ impl ViewControlWidget {
             shared: shared.clone(),
             msg_tx,
 
-            node_id_text: String::new(),
-            pos_text: String::new(),
+            goto_box: GotoBox::default(),
         }
     }
 
     pub fn show(&mut self, ui: &mut egui::Ui) {
-        ui.label("Node ID");
-        let node_id_entry = ui.add_sized(
-            [ui.available_size().x, 0f32],
-            egui::TextEdit::singleline(&mut self.node_id_text),
-        );
-        let goto_node_b = ui.button("Go to node");
-
-        let goto_node = goto_node_b.clicked()
-            || (node_id_entry.lost_focus()
-                && ui.input(|i| i.key_pressed(egui::Key::Enter)));
-
-        ui.label("Position");
-        let pos_entry = ui.add_sized(
-            [ui.available_size().x, 0f32],
-            egui::TextEdit::singleline(&mut self.pos_text),
-        );
-
-        let goto_pos_b = ui.button("Go to position");
-
-        let goto_pos = goto_pos_b.clicked()
-            || (pos_entry.lost_focus()
-                && ui.input(|i| i.key_pressed(egui::Key::Enter)));
-
-        if goto_node {
-            let node =
-                crate::viewer_1d::control::parse_node(&self.node_id_text);
-
-            if let Some(node) = node {
-                let _ = self.msg_tx.send(Msg::View(ViewCmd::GotoNode { node }));
-            }
+        if ui
+            .button("Home")
+            .on_hover_text("Fit the whole layout in the view (Space)")
+            .clicked()
+        {
+            let _ = self.msg_tx.send(Msg::View(ViewCmd::Home));
         }
 
-        if goto_pos {
-            if let Some((path_name, range)) =
-                crate::viewer_1d::control::parse_pos_range(&self.pos_text)
-            {
-                let path = path_name
-                    .and_then(|name| {
-                        self.shared.graph.path_names.get_by_right(name)
-                    })
-                    .copied();
-
-                let _ = self
-                    .msg_tx
-                    .send(Msg::View(ViewCmd::GotoRange { path, range }));
+        let Some(target) = self.goto_box.show(ui, &self.shared) else {
+            return;
+        };
+
+        let cmd = match target {
+            GotoTarget::Node(node) => ViewCmd::GotoNode { node },
+            GotoTarget::Range { path, range } => {
+                ViewCmd::GotoRange { path, range }
             }
-        }
+        };
+
+        let _ = self.msg_tx.send(Msg::View(cmd));
     }
 }
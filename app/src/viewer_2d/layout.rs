@@ -35,42 +35,1170 @@ impl NodePositions {
         (self.positions[ix0], self.positions[ix1])
     }
 
+    /// The nodes whose bounding boxes intersect the rectangle, in
+    /// pangenome order.
+    pub fn nodes_in_rect(&self, min: Vec2, max: Vec2) -> Vec<Node> {
+        self.iter_nodes()
+            .enumerate()
+            .filter(|(_, [p0, p1])| {
+                let lo = p0.min_by_component(*p1);
+                let hi = p0.max_by_component(*p1);
+                lo.x <= max.x && hi.x >= min.x && lo.y <= max.y && hi.y >= min.y
+            })
+            .map(|(ix, _)| Node::from(ix))
+            .collect()
+    }
+
+    /// Average bp per layout unit, from the node lengths in the
+    /// layout; used to compare the 2D view's scale with the 1D view's.
+    pub fn bp_per_unit(&self, graph: &PathIndex) -> f64 {
+        let layout_len = self
+            .iter_nodes()
+            .map(|[p0, p1]| (p1 - p0).mag() as f64)
+            .sum::<f64>();
+
+        if layout_len > 0.0 {
+            graph.pangenome_len().0 as f64 / layout_len
+        } else {
+            1.0
+        }
+    }
+
+    /// Loads node positions from a layout file: the binary layouts
+    /// written by `odgi layout -o` (`.lay`) are read directly, anything
+    /// else is parsed as a table. If a `.lay` file can't be read, and
+    /// the graph is available in odgi format (`.og`), it's converted
+    /// with `odgi draw` instead.
+    pub fn from_layout_file(
+        path: impl AsRef<std::path::Path>,
+        og_path: Option<&std::path::Path>,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+
+        let is_lay = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("lay"))
+            .unwrap_or(false);
+
+        if !is_lay {
+            return Self::from_layout_tsv(path);
+        }
+
+        let err = match Self::from_odgi_lay(path) {
+            Ok(positions) => return Ok(positions),
+            Err(err) => err,
+        };
+
+        // the fallback needs odgi, and the graph in odgi format
+        let og_path =
+            og_path.filter(|_| crate::app::odgi::find_odgi().is_some());
+
+        let Some(og_path) = og_path else {
+            return Err(err);
+        };
+
+        log::warn!(
+            "Couldn't read odgi layout {} ({err}), converting it with odgi",
+            path.display()
+        );
+
+        Self::from_odgi_layout(og_path, path)
+    }
+
+    /// Reads a binary layout written by `odgi layout -o`.
+    pub fn from_odgi_lay(
+        lay_path: impl AsRef<std::path::Path>,
+    ) -> Result<Self> {
+        use std::fs::File;
+
+        let lay_path = lay_path.as_ref();
+        let reader = File::open(lay_path).map(BufReader::new)?;
+
+        Self::from_odgi_lay_reader(reader).map_err(|err| {
+            anyhow::anyhow!(
+                "Error reading odgi layout {}: {err}",
+                lay_path.display()
+            )
+        })
+    }
+
+    pub fn from_odgi_lay_reader(reader: impl Read) -> Result<Self> {
+        let positions = super::odgi_lay::read_odgi_lay(reader)?;
+
+        let mut min = Vec2::broadcast(f32::MAX);
+        let mut max = Vec2::broadcast(f32::MIN);
+
+        for &v in positions.iter() {
+            min = min.min_by_component(v);
+            max = max.max_by_component(v);
+        }
+
+        Ok(Self {
+            positions,
+            bounds: (min, max),
+        })
+    }
+
+    /// Loads a binary layout written by `odgi layout -o`, for the odgi
+    /// graph at `og_path`, by converting it to TSV with `odgi draw` in
+    /// a temporary directory.
+    pub fn from_odgi_layout(
+        og_path: impl AsRef<std::path::Path>,
+        lay_path: impl AsRef<std::path::Path>,
+    ) -> Result<Self> {
+        use crate::app::odgi;
+
+        let Some(odgi_path) = odgi::find_odgi() else {
+            anyhow::bail!("Loading odgi layouts requires odgi in PATH");
+        };
+
+        // the TSV is only needed until it's loaded
+        let dir = odgi::ConversionDir::new()?;
+        let tsv_path = odgi::lay_to_tsv(
+            &odgi_path,
+            og_path.as_ref(),
+            lay_path.as_ref(),
+            &dir,
+        )?;
+
+        Self::from_layout_tsv(tsv_path)
+    }
+
+    /// Writes the positions as a layout table, in the format written
+    /// by `odgi layout -T`.
+    pub fn write_layout_tsv(&self, mut writer: impl Write) -> Result<()> {
+        writeln!(writer, "idx\tX\tY")?;
+
+        for (ix, p) in self.positions.iter().enumerate() {
+            writeln!(writer, "{ix}\t{}\t{}", p.x, p.y)?;
+        }
+
+        Ok(())
+    }
+
     pub fn from_layout_tsv(
         // path_index: &PathIndex,
         tsv_path: impl AsRef<std::path::Path>,
     ) -> Result<Self> {
         use std::fs::File;
-        // use std::io::{prelude::*, BufReader};
-        let mut lines = File::open(tsv_path).map(BufReader::new)?.lines();
 
-        let _header = lines.next();
-        let mut positions = Vec::new();
+        let tsv_path = tsv_path.as_ref();
+        let reader = File::open(tsv_path).map(BufReader::new)?;
 
-        fn parse_row(line: &str) -> Option<(usize, Vec2)> {
-            let mut fields = line.split('\t');
-            let idx = fields.next()?.parse::<usize>().ok()?;
-            let x = fields.next()?.parse::<f32>().ok()?;
-            let y = fields.next()?.parse::<f32>().ok()?;
-            Some((idx, Vec2::new(x, y)))
+        let (positions, malformed) = Self::from_layout_reader(reader)?;
+
+        const MAX_REPORTED: usize = 10;
+
+        for line in malformed.iter().take(MAX_REPORTED) {
+            log::warn!("{}: {line}", tsv_path.display());
         }
 
+        if malformed.len() > MAX_REPORTED {
+            log::warn!(
+                "{}: skipped {} more malformed lines",
+                tsv_path.display(),
+                malformed.len() - MAX_REPORTED
+            );
+        }
+
+        Ok(positions)
+    }
+
+    /// Parses a layout table as produced by `odgi layout`, returning
+    /// the node positions along with any lines that could not be
+    /// parsed.
+    ///
+    /// The delimiter (tab, comma, or whitespace) is detected from the
+    /// first non-empty line, as is the presence of a header. If there
+    /// is a header, it's used to find the index and coordinate
+    /// columns; otherwise the columns are assumed to be `idx, X, Y`,
+    /// or just `X, Y` if there are only two columns.
+    pub fn from_layout_reader(
+        reader: impl BufRead,
+    ) -> Result<(Self, Vec<MalformedLine>)> {
+        let mut lines = reader.lines().enumerate();
+
+        let mut format: Option<(LayoutDelimiter, LayoutColumns)> = None;
+
+        let mut positions = Vec::new();
+        let mut malformed = Vec::new();
+
         let mut min = Vec2::broadcast(f32::MAX);
         let mut max = Vec2::broadcast(f32::MIN);
 
-        for line in lines {
+        while let Some((line_ix, line)) = lines.next() {
             let line = line?;
-            if let Some((i, v)) = parse_row(&line) {
-                min = min.min_by_component(v);
-                max = max.max_by_component(v);
-                positions.push((i, v));
+            let line_number = line_ix + 1;
+
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (delim, columns) = if let Some(format) = format {
+                format
+            } else {
+                let delim = LayoutDelimiter::detect(&line);
+                let fields = delim.split(&line).collect::<Vec<_>>();
+
+                if let Some(columns) = LayoutColumns::from_header(&fields) {
+                    format = Some((delim, columns));
+                    continue;
+                }
+
+                let columns = LayoutColumns::from_column_count(fields.len())
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Layout line {line_number}: expected at least \
+                             two columns, found {}",
+                            fields.len()
+                        )
+                    })?;
+
+                format = Some((delim, columns));
+                (delim, columns)
+            };
+
+            let fields = delim.split(&line).collect::<Vec<_>>();
+
+            match columns.parse_row(&fields) {
+                Ok((idx, v)) => {
+                    let idx = idx.unwrap_or(positions.len());
+                    min = min.min_by_component(v);
+                    max = max.max_by_component(v);
+                    positions.push((idx, v));
+                }
+                Err(reason) => {
+                    malformed.push(MalformedLine {
+                        line_number,
+                        reason,
+                    });
+                }
+            }
+        }
+
+        if positions.is_empty() {
+            if let Some(first) = malformed.first() {
+                anyhow::bail!(
+                    "Layout contains no valid rows ({} malformed lines, \
+                     first: {first})",
+                    malformed.len()
+                );
+            } else {
+                anyhow::bail!("Layout contains no rows");
             }
         }
+
         let bounds = (min, max);
 
         positions.sort_by_key(|(i, _)| *i);
-        let positions =
-            positions.into_iter().map(|(_, p)| p).collect::<Vec<_>>();
 
-        Ok(Self { positions, bounds })
+        // when the same index shows up more than once, the last
+        // occurrence is used
+        let mut deduped: Vec<(usize, Vec2)> =
+            Vec::with_capacity(positions.len());
+
+        for (i, v) in positions {
+            match deduped.last_mut() {
+                Some(last) if last.0 == i => {
+                    log::warn!("Duplicate layout index {i}");
+                    last.1 = v;
+                }
+                _ => deduped.push((i, v)),
+            }
+        }
+
+        if let Some((expected, (found, _))) = deduped
+            .iter()
+            .enumerate()
+            .find(|(expected, (found, _))| expected != found)
+        {
+            anyhow::bail!(
+                "Layout is missing index {expected} (found {found} instead)"
+            );
+        }
+
+        if deduped.len() % 2 != 0 {
+            anyhow::bail!(
+                "Layout must contain two positions per node, \
+                 but has {} rows",
+                deduped.len()
+            );
+        }
+
+        let positions = deduped.into_iter().map(|(_, p)| p).collect::<Vec<_>>();
+
+        Ok((Self { positions, bounds }, malformed))
+    }
+}
+
+/// Sizes derived from the scale of a layout, so that node widths and
+/// zoom limits work for graphs with a handful of nodes as well as for
+/// large ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutScale {
+    /// Median node length, in layout units
+    pub node_len: f32,
+    /// The layout bounds with a margin, and with each side at least a
+    /// few node lengths long, so that the view that fits a tiny or
+    /// collinear layout isn't degenerate
+    pub view_bounds: (Vec2, Vec2),
+}
+
+impl LayoutScale {
+    /// Minimum width and height of the view bounds, in node lengths
+    const MIN_EXTENT_NODES: f32 = 4.0;
+    /// Node width as a fraction of the median node length, when
+    /// zoomed in far enough for that to be wider than the default
+    const NODE_WIDTH_FRACTION: f32 = 0.25;
+    const MAX_NODE_WIDTH_PX: f32 = 32.0;
+
+    pub fn from_positions(positions: &NodePositions) -> Self {
+        let mut lengths = positions
+            .iter_nodes()
+            .map(|[p0, p1]| (p1 - p0).mag())
+            .filter(|len| len.is_finite() && *len > 0.0)
+            .collect::<Vec<_>>();
+
+        let node_len = if lengths.is_empty() {
+            1.0
+        } else {
+            let mid = lengths.len() / 2;
+            *lengths.select_nth_unstable_by(mid, |a, b| a.total_cmp(b)).1
+        };
+
+        let (min, max) = positions.bounds;
+        let (min, max) = if min.x <= max.x && min.y <= max.y {
+            (min, max)
+        } else {
+            // no nodes
+            (Vec2::zero(), Vec2::zero())
+        };
+
+        let center = (min + max) * 0.5;
+        let min_half = Vec2::broadcast(node_len * Self::MIN_EXTENT_NODES / 2.0);
+        let half = ((max - min) * 0.5 + Vec2::broadcast(node_len))
+            .max_by_component(min_half);
+
+        Self {
+            node_len,
+            view_bounds: (center - half, center + half),
+        }
+    }
+
+    /// The smallest view height, at which a median node is about
+    /// twice as long as the view is tall.
+    pub fn min_view_height(&self) -> f32 {
+        self.node_len * 0.5
+    }
+
+    /// The node width in pixels, given the width used when zoomed
+    /// out; nodes get wider, up to a limit, when zoomed in so far
+    /// that they'd otherwise look like thin lines.
+    pub fn node_width_px(&self, default_px: f32, px_per_unit: f32) -> f32 {
+        let scaled = self.node_len * Self::NODE_WIDTH_FRACTION * px_per_unit;
+        scaled.min(Self::MAX_NODE_WIDTH_PX).max(default_px)
+    }
+}
+
+/// Options for [`NodePositions::cleaned`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutCleanup {
+    /// Nodes with an endpoint further than this many (scaled) median
+    /// absolute deviations from the median, along either axis, are
+    /// treated as outliers and moved to the edge of the allowed range.
+    pub outlier_threshold: f32,
+
+    /// Number of relaxation steps used to push apart nodes that
+    /// overlap; zero disables relaxation.
+    pub relax_iterations: usize,
+
+    /// Angle in radians, counterclockwise, to rotate the layout by
+    /// around the center of its bounds, after the other steps.
+    pub rotation: f32,
+}
+
+impl std::default::Default for LayoutCleanup {
+    fn default() -> Self {
+        Self {
+            outlier_threshold: 10.0,
+            relax_iterations: 0,
+            rotation: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutCleanupStats {
+    pub outliers: usize,
+    pub relaxed: usize,
+}
+
+impl NodePositions {
+    /// Returns a copy of the positions with extreme outliers moved in,
+    /// and optionally overlapping nodes pushed apart and the layout
+    /// rotated, with the bounds recomputed.
+    pub fn cleaned(&self, opts: &LayoutCleanup) -> (Self, LayoutCleanupStats) {
+        let mut positions = self.positions.clone();
+        let mut stats = LayoutCleanupStats::default();
+
+        if let Some((min, max)) =
+            robust_range(&positions, opts.outlier_threshold)
+        {
+            for node in positions.chunks_exact_mut(2) {
+                let is_outlier = node.iter().any(|p| {
+                    p.x < min.x || p.x > max.x || p.y < min.y || p.y > max.y
+                });
+
+                if is_outlier {
+                    stats.outliers += 1;
+                    for p in node.iter_mut() {
+                        *p = p.clamped(min, max);
+                    }
+                }
+            }
+        }
+
+        for _ in 0..opts.relax_iterations {
+            stats.relaxed += relax_step(&mut positions);
+        }
+
+        if opts.rotation != 0.0 {
+            let (min, max) = self.bounds;
+            let center = (min + max) * 0.5;
+            let (sin, cos) = opts.rotation.sin_cos();
+
+            for p in positions.iter_mut() {
+                let d = *p - center;
+                *p = center
+                    + Vec2::new(cos * d.x - sin * d.y, sin * d.x + cos * d.y);
+            }
+        }
+
+        let mut min = Vec2::broadcast(f32::MAX);
+        let mut max = Vec2::broadcast(f32::MIN);
+
+        for &p in positions.iter() {
+            min = min.min_by_component(p);
+            max = max.max_by_component(p);
+        }
+
+        let cleaned = Self {
+            positions,
+            bounds: (min, max),
+        };
+
+        (cleaned, stats)
+    }
+}
+
+impl NodePositions {
+    /// The angle in radians, in `-PI/2..=PI/2`, of the direction the
+    /// layout is most spread out along (its first principal axis),
+    /// from the x axis.
+    pub fn principal_angle(&self) -> f32 {
+        let points = self
+            .positions
+            .iter()
+            .filter(|p| p.x.is_finite() && p.y.is_finite());
+
+        let (mut n, mut sx, mut sy) = (0.0f64, 0.0f64, 0.0f64);
+        for p in points.clone() {
+            n += 1.0;
+            sx += p.x as f64;
+            sy += p.y as f64;
+        }
+
+        if n == 0.0 {
+            return 0.0;
+        }
+
+        let (mx, my) = (sx / n, sy / n);
+        let (mut sxx, mut syy, mut sxy) = (0.0f64, 0.0f64, 0.0f64);
+        for p in points {
+            let (dx, dy) = (p.x as f64 - mx, p.y as f64 - my);
+            sxx += dx * dx;
+            syy += dy * dy;
+            sxy += dx * dy;
+        }
+
+        (0.5 * (2.0 * sxy).atan2(sxx - syy)) as f32
+    }
+}
+
+/// Options for [`NodePositions::from_path_sgd`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathSgdLayout {
+    pub iterations: usize,
+    /// Node pairs updated per iteration, as a multiple of the total
+    /// number of path steps
+    pub terms_per_step: f32,
+    /// Final learning rate
+    pub eta_min: f32,
+    pub seed: u64,
+}
+
+impl std::default::Default for PathSgdLayout {
+    fn default() -> Self {
+        Self {
+            iterations: 30,
+            terms_per_step: 10.0,
+            eta_min: 0.01,
+            seed: 9399220,
+        }
+    }
+}
+
+impl NodePositions {
+    /// Computes a layout from the paths using path-guided stochastic
+    /// gradient descent (as in `odgi layout`): pairs of node endpoints
+    /// visited by the same path are repeatedly moved towards their
+    /// distance along the path, in base pairs.
+    ///
+    /// `progress` is called after each iteration with the fraction
+    /// done.
+    pub fn from_path_sgd(
+        graph: &PathIndex,
+        opts: &PathSgdLayout,
+        mut progress: impl FnMut(f32),
+    ) -> Self {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(opts.seed);
+
+        // nodes start out laid along the X axis in pangenome order,
+        // with some vertical jitter so they can move apart
+        let mut positions = Vec::with_capacity(graph.node_count * 2);
+
+        for ix in 0..graph.node_count {
+            let (offset, len) = graph.node_offset_length(Node::from(ix as u32));
+            let y = rng.gen_range(0.0..1.0) * (len.0 as f32).sqrt().max(1.0);
+            positions.push(Vec2::new(offset.0 as f32, y));
+            positions.push(Vec2::new((offset.0 + len.0) as f32, y));
+        }
+
+        // each path as a list of (endpoint index, path position)
+        let path_visits = graph
+            .path_steps
+            .iter()
+            .map(|steps| {
+                let mut offset = 0u64;
+                let mut visits = Vec::with_capacity(steps.len() * 2);
+
+                for step in steps {
+                    let ix = step.node().ix();
+                    let len = graph.node_length(step.node()).0;
+                    let (first, last) = if step.is_reverse() {
+                        (ix * 2 + 1, ix * 2)
+                    } else {
+                        (ix * 2, ix * 2 + 1)
+                    };
+                    visits.push((first, offset as f32));
+                    visits.push((last, (offset + len) as f32));
+                    offset += len;
+                }
+
+                visits
+            })
+            .filter(|visits| visits.len() > 1)
+            .collect::<Vec<_>>();
+
+        let step_count: usize = path_visits.iter().map(|v| v.len() / 2).sum();
+
+        if path_visits.is_empty() || opts.iterations == 0 {
+            progress(1.0);
+            return Self::from_positions(positions);
+        }
+
+        let path_weights = rand::distributions::WeightedIndex::new(
+            path_visits.iter().map(|v| v.len()),
+        )
+        .unwrap();
+
+        let max_len = path_visits
+            .iter()
+            .filter_map(|v| v.last().map(|(_, pos)| *pos))
+            .fold(1.0f32, f32::max);
+
+        // learning rate schedule from Zheng et al., "Graph drawing by
+        // stochastic gradient descent"
+        let eta_max = max_len * max_len;
+        let lambda = if opts.iterations > 1 {
+            (eta_max / opts.eta_min).ln() / (opts.iterations - 1) as f32
+        } else {
+            0.0
+        };
+
+        let terms = ((step_count as f32 * opts.terms_per_step) as usize).max(1);
+
+        for iter in 0..opts.iterations {
+            let eta = eta_max * (-lambda * iter as f32).exp();
+
+            for _ in 0..terms {
+                let visits = &path_visits[rng.sample(&path_weights)];
+
+                let i = rng.gen_range(0..visits.len());
+
+                // half of the updates are between nearby endpoints,
+                // which keeps the local structure intact
+                let j = if rng.gen_bool(0.5) {
+                    let max_hop = 1 + (visits.len() as f32).sqrt() as usize;
+                    let hop = rng.gen_range(1..=max_hop);
+                    if rng.gen_bool(0.5) {
+                        i.saturating_sub(hop)
+                    } else {
+                        (i + hop).min(visits.len() - 1)
+                    }
+                } else {
+                    rng.gen_range(0..visits.len())
+                };
+
+                let (a, pos_a) = visits[i];
+                let (b, pos_b) = visits[j];
+
+                if a == b {
+                    continue;
+                }
+
+                let dist = (pos_a - pos_b).abs().max(1.0);
+
+                let w = 1.0 / (dist * dist);
+                let mu = (eta * w).min(1.0);
+
+                let mut delta = positions[a] - positions[b];
+                let mut mag = delta.mag();
+
+                if mag < 1e-6 {
+                    delta = Vec2::new(rng.gen_range(-1.0..1.0), 1e-3);
+                    mag = delta.mag();
+                }
+
+                let r = mu * (mag - dist) / (2.0 * mag);
+                let r = delta * r;
+
+                positions[a] -= r;
+                positions[b] += r;
+            }
+
+            progress((iter + 1) as f32 / opts.iterations as f32);
+        }
+
+        Self::from_positions(positions)
+    }
+
+    fn from_positions(positions: Vec<Vec2>) -> Self {
+        let mut min = Vec2::broadcast(f32::MAX);
+        let mut max = Vec2::broadcast(f32::MIN);
+
+        for &p in positions.iter() {
+            min = min.min_by_component(p);
+            max = max.max_by_component(p);
+        }
+
+        Self {
+            positions,
+            bounds: (min, max),
+        }
+    }
+}
+
+fn median(vals: &mut [f32]) -> f32 {
+    let mid = vals.len() / 2;
+    let (_, m, _) = vals.select_nth_unstable_by(mid, |a, b| a.total_cmp(b));
+    *m
+}
+
+/// Per-axis `median +- threshold * MAD` range of the points, or `None`
+/// if there's nothing to compare against.
+fn robust_range(points: &[Vec2], threshold: f32) -> Option<(Vec2, Vec2)> {
+    if points.len() < 4 || !threshold.is_finite() {
+        return None;
+    }
+
+    // scales the MAD to match the standard deviation for normally
+    // distributed data
+    const MAD_SCALE: f32 = 1.4826;
+
+    let axis_range = |get: fn(&Vec2) -> f32| {
+        let mut vals = points.iter().map(get).collect::<Vec<_>>();
+        let med = median(&mut vals);
+
+        vals.iter_mut().for_each(|v| *v = (*v - med).abs());
+        let mad = median(&mut vals) * MAD_SCALE;
+
+        if mad > 0.0 {
+            (med - threshold * mad, med + threshold * mad)
+        } else {
+            (f32::MIN, f32::MAX)
+        }
+    };
+
+    let (x0, x1) = axis_range(|p| p.x);
+    let (y0, y1) = axis_range(|p| p.y);
+
+    Some((Vec2::new(x0, y0), Vec2::new(x1, y1)))
+}
+
+/// Pushes apart nodes whose midpoints are closer than the median
+/// node length, returning the number of nodes that were moved.
+fn relax_step(positions: &mut [Vec2]) -> usize {
+    let node_count = positions.len() / 2;
+
+    if node_count < 2 {
+        return 0;
+    }
+
+    let mids = positions
+        .chunks_exact(2)
+        .map(|n| n[0] + (n[1] - n[0]) * 0.5)
+        .collect::<Vec<_>>();
+
+    let min_dist = {
+        let mut lens = positions
+            .chunks_exact(2)
+            .map(|n| (n[1] - n[0]).mag())
+            .collect::<Vec<_>>();
+        median(&mut lens)
+    };
+
+    if min_dist <= 0.0 || !min_dist.is_finite() {
+        return 0;
+    }
+
+    let cell_of = |p: Vec2| {
+        (
+            (p.x / min_dist).floor() as i64,
+            (p.y / min_dist).floor() as i64,
+        )
+    };
+
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+
+    for (ix, &mid) in mids.iter().enumerate() {
+        grid.entry(cell_of(mid)).or_default().push(ix);
+    }
+
+    let mut deltas = vec![Vec2::zero(); node_count];
+
+    for (ix, &mid) in mids.iter().enumerate() {
+        let (cx, cy) = cell_of(mid);
+
+        for nx in (cx - 1)..=(cx + 1) {
+            for ny in (cy - 1)..=(cy + 1) {
+                let Some(cell) = grid.get(&(nx, ny)) else {
+                    continue;
+                };
+
+                for &other in cell.iter().filter(|&&o| o > ix) {
+                    let d = mid - mids[other];
+                    let dist = d.mag();
+
+                    if dist >= min_dist {
+                        continue;
+                    }
+
+                    let dir = if dist > 0.0 {
+                        d / dist
+                    } else {
+                        // coincident nodes are pushed apart along an
+                        // arbitrary but deterministic direction
+                        let angle = (ix ^ other) as f32;
+                        Vec2::new(angle.cos(), angle.sin())
+                    };
+
+                    let push = dir * (0.5 * (min_dist - dist));
+                    deltas[ix] += push * 0.5;
+                    deltas[other] -= push * 0.5;
+                }
+            }
+        }
+    }
+
+    let mut moved = 0;
+
+    for (node, delta) in positions.chunks_exact_mut(2).zip(deltas) {
+        if delta != Vec2::zero() {
+            moved += 1;
+            node[0] += delta;
+            node[1] += delta;
+        }
+    }
+
+    moved
+}
+
+/// A line in a layout file that was skipped during parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MalformedLine {
+    pub line_number: usize,
+    pub reason: String,
+}
+
+impl std::fmt::Display for MalformedLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line_number, self.reason)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutDelimiter {
+    Tab,
+    Comma,
+    Whitespace,
+}
+
+impl LayoutDelimiter {
+    fn detect(line: &str) -> Self {
+        if line.contains('\t') {
+            Self::Tab
+        } else if line.contains(',') {
+            Self::Comma
+        } else {
+            Self::Whitespace
+        }
+    }
+
+    fn split<'a>(&self, line: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let fields: Box<dyn Iterator<Item = &'a str>> = match self {
+            Self::Tab => Box::new(line.split('\t')),
+            Self::Comma => Box::new(line.split(',')),
+            Self::Whitespace => Box::new(line.split_whitespace()),
+        };
+        fields.map(|s| s.trim())
+    }
+}
+
+/// Column indices of the node index and coordinates in a layout
+/// table. If `idx` is `None`, rows are indexed by their order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutColumns {
+    pub idx: Option<usize>,
+    pub x: usize,
+    pub y: usize,
+}
+
+impl LayoutColumns {
+    fn from_header(fields: &[&str]) -> Option<Self> {
+        // a row of numbers can't be a header
+        if fields.iter().all(|f| f.parse::<f64>().is_ok()) {
+            return None;
+        }
+
+        let find = |names: &[&str]| {
+            fields.iter().position(|field| {
+                let field = field.trim_matches('"');
+                names.iter().any(|name| field.eq_ignore_ascii_case(name))
+            })
+        };
+
+        let idx = find(&["idx", "id", "index", "node"]);
+        let x = find(&["x"]);
+        let y = find(&["y"]);
+
+        match (x, y) {
+            (Some(x), Some(y)) => Some(Self { idx, x, y }),
+            // a non-numeric first line without recognizable column
+            // names is treated as a header in the default order
+            _ => Self::from_column_count(fields.len()),
+        }
+    }
+
+    fn from_column_count(count: usize) -> Option<Self> {
+        match count {
+            0 | 1 => None,
+            2 => Some(Self {
+                idx: None,
+                x: 0,
+                y: 1,
+            }),
+            _ => Some(Self {
+                idx: Some(0),
+                x: 1,
+                y: 2,
+            }),
+        }
+    }
+
+    fn parse_row(
+        &self,
+        fields: &[&str],
+    ) -> std::result::Result<(Option<usize>, Vec2), String> {
+        let get = |col: usize, name: &str| {
+            fields.get(col).copied().ok_or_else(|| {
+                format!(
+                    "missing {name} column (expected at least {} fields, \
+                     found {})",
+                    col + 1,
+                    fields.len()
+                )
+            })
+        };
+
+        let parse_coord = |col: usize, name: &str| {
+            let field = get(col, name)?;
+            let v = field
+                .parse::<f32>()
+                .map_err(|_| format!("could not parse {name} `{field}`"))?;
+
+            if v.is_finite() {
+                Ok(v)
+            } else {
+                Err(format!("{name} is not finite: `{field}`"))
+            }
+        };
+
+        let idx = if let Some(col) = self.idx {
+            let field = get(col, "index")?;
+            let idx = field
+                .parse::<usize>()
+                .map_err(|_| format!("could not parse index `{field}`"))?;
+            Some(idx)
+        } else {
+            None
+        };
+
+        let x = parse_coord(self.x, "X")?;
+        let y = parse_coord(self.y, "Y")?;
+
+        Ok((idx, Vec2::new(x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<(NodePositions, Vec<MalformedLine>)> {
+        NodePositions::from_layout_reader(text.as_bytes())
+    }
+
+    #[test]
+    fn layout_scale_of_tiny_layouts() -> Result<()> {
+        // three collinear nodes of length 2
+        let text = "idx\tX\tY\n\
+                    0\t0.0\t0.0\n\
+                    1\t2.0\t0.0\n\
+                    2\t3.0\t0.0\n\
+                    3\t5.0\t0.0\n\
+                    4\t6.0\t0.0\n\
+                    5\t8.0\t0.0\n";
+
+        let (pos, _) = parse(text)?;
+        let scale = LayoutScale::from_positions(&pos);
+
+        assert_eq!(scale.node_len, 2.0);
+        // a margin of one node length on either side, and a height of
+        // at least four node lengths
+        assert_eq!(
+            scale.view_bounds,
+            (Vec2::new(-2.0, -4.0), Vec2::new(10.0, 4.0))
+        );
+
+        assert_eq!(scale.node_width_px(6.0, 1.0), 6.0);
+        assert_eq!(scale.node_width_px(6.0, 20.0), 10.0);
+        assert_eq!(scale.node_width_px(6.0, 1000.0), 32.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn layout_tsv_with_header() -> Result<()> {
+        let text = "idx\tX\tY\tcomponent\n\
+                    0\t1.0\t2.0\t0\n\
+                    1\t3.0\t4.0\t0\n";
+
+        let (pos, malformed) = parse(text)?;
+
+        assert!(malformed.is_empty());
+        assert_eq!(
+            pos.iter_nodes().collect::<Vec<_>>(),
+            vec![[Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)]]
+        );
+        assert_eq!(pos.bounds, (Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn layout_delimiters_and_column_order() -> Result<()> {
+        let expected = vec![[Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)]];
+
+        // headerless, comma-separated
+        let (pos, _) = parse("0,1.0,2.0\n1,3.0,4.0\n")?;
+        assert_eq!(pos.iter_nodes().collect::<Vec<_>>(), expected);
+
+        // headerless, whitespace-separated, only coordinates
+        let (pos, _) = parse("1.0  2.0\n3.0 4.0\n")?;
+        assert_eq!(pos.iter_nodes().collect::<Vec<_>>(), expected);
+
+        // reordered columns, rows out of order
+        let (pos, _) = parse("X Y idx\n3.0 4.0 1\n1.0 2.0 0\n")?;
+        assert_eq!(pos.iter_nodes().collect::<Vec<_>>(), expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn layout_malformed_lines_are_reported() -> Result<()> {
+        let text = "idx\tX\tY\n\
+                    0\t1.0\t2.0\n\
+                    oops\t1.0\t2.0\n\
+                    1\t3.0\n\
+                    1\t3.0\t4.0\n";
+
+        let (pos, malformed) = parse(text)?;
+
+        assert_eq!(pos.iter_nodes().count(), 1);
+        assert_eq!(
+            malformed.iter().map(|m| m.line_number).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+
+        assert!(parse("idx\tX\tY\n0\t1.0\t2.0\n2\t3.0\t4.0\n").is_err());
+        assert!(parse("idx\tX\tY\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn layout_tsv_roundtrip() -> Result<()> {
+        let (pos, _) = parse("0\t1.5\t-2.0\n1\t3.0\t4.25\n")?;
+
+        let mut tsv = Vec::new();
+        pos.write_layout_tsv(&mut tsv)?;
+
+        let (from_tsv, malformed) =
+            NodePositions::from_layout_reader(tsv.as_slice())?;
+        assert!(malformed.is_empty());
+        assert_eq!(from_tsv.positions, pos.positions);
+        assert_eq!(from_tsv.bounds, pos.bounds);
+
+        Ok(())
+    }
+
+    #[test]
+    fn layout_cleanup_outliers() -> Result<()> {
+        let mut text = String::new();
+        for i in 0..10 {
+            let x = i as f32;
+            text.push_str(&format!("{}\t{x}\t0.0\n", 2 * i));
+            text.push_str(&format!("{}\t{x}\t1.0\n", 2 * i + 1));
+        }
+        text.push_str("20\t1e9\t0.0\n21\t1e9\t1.0\n");
+
+        let (pos, _) = parse(&text)?;
+        assert_eq!(pos.bounds.1.x, 1e9);
+
+        let opts = LayoutCleanup::default();
+        let (cleaned, stats) = pos.cleaned(&opts);
+
+        assert_eq!(stats.outliers, 1);
+        assert!(cleaned.bounds.1.x < 100.0);
+        assert_eq!(cleaned.node_pos(Node::from(0u32)), pos.node_pos(0.into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn layout_relaxation_separates_nodes() -> Result<()> {
+        let text = "0\t0.0\t0.0\n1\t0.0\t1.0\n\
+                    2\t0.1\t0.0\n3\t0.1\t1.0\n";
+        let (pos, _) = parse(text)?;
+
+        let opts = LayoutCleanup {
+            outlier_threshold: f32::INFINITY,
+            relax_iterations: 5,
+            rotation: 0.0,
+        };
+        let (relaxed, stats) = pos.cleaned(&opts);
+
+        let mid = |p: &NodePositions, n: u32| {
+            let (a, b) = p.node_pos(Node::from(n));
+            a + (b - a) * 0.5
+        };
+
+        let before = (mid(&pos, 0) - mid(&pos, 1)).mag();
+        let after = (mid(&relaxed, 0) - mid(&relaxed, 1)).mag();
+
+        assert!(stats.relaxed > 0);
+        assert!(after > before);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotate_diagonal_layout_to_axis() -> Result<()> {
+        let text = "idx\tX\tY\n\
+                    0\t0.0\t0.0\n\
+                    1\t1.0\t1.0\n\
+                    2\t2.0\t2.0\n\
+                    3\t3.0\t3.0\n";
+
+        let (pos, _) = parse(text)?;
+        let angle = pos.principal_angle();
+        assert!((angle - std::f32::consts::FRAC_PI_4).abs() < 1e-5);
+
+        let opts = LayoutCleanup {
+            outlier_threshold: f32::INFINITY,
+            relax_iterations: 0,
+            rotation: -angle,
+        };
+        let (rotated, _) = pos.cleaned(&opts);
+        let (min, max) = rotated.bounds;
+
+        assert!((max.y - min.y).abs() < 1e-4);
+        assert!((max.x - min.x - 18f32.sqrt()).abs() < 1e-4);
+        assert!(rotated.principal_angle().abs() < 1e-4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn path_sgd_layout_follows_paths() {
+        let gfa_path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../test/data/A-3105.fa.353ea42.34ee7b1.1576367.smooth.fix.gfa"
+        );
+        let graph = PathIndex::from_gfa(gfa_path).unwrap();
+
+        let opts = PathSgdLayout {
+            iterations: 10,
+            ..PathSgdLayout::default()
+        };
+
+        let mut last_progress = 0.0;
+        let pos = NodePositions::from_path_sgd(&graph, &opts, |p| {
+            assert!(p >= last_progress);
+            last_progress = p;
+        });
+
+        assert_eq!(last_progress, 1.0);
+        assert_eq!(pos.iter_nodes().count(), graph.node_count);
+        assert!(pos.iter_nodes().all(|[a, b]| a.x.is_finite()
+            && a.y.is_finite()
+            && b.x.is_finite()
+            && b.y.is_finite()));
+
+        // consecutive nodes on a path end up roughly their length apart
+        let steps = &graph.path_steps[0];
+        let node = steps[steps.len() / 2].node();
+        let (a, b) = pos.node_pos(node);
+        let len = graph.node_length(node).0 as f32;
+        assert!((a - b).mag() < len * 4.0 + 10.0);
+    }
+
+    #[test]
+    fn layout_from_odgi_tsv() -> Result<()> {
+        let tsv_path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../test/data/A-3105.layout.tsv"
+        );
+
+        let pos = NodePositions::from_layout_tsv(tsv_path)?;
+
+        let (min, max) = pos.bounds;
+        assert!(min.x <= max.x && min.y <= max.y);
+        assert!(pos.iter_nodes().count() > 0);
+
+        Ok(())
     }
 }
@@ -0,0 +1,307 @@
+//! Minimap: an inset in the corner of the 2D view showing the whole
+//! layout, with the current view drawn as a rectangle. Clicking or
+//! dragging in it moves the view there. The layout is rasterized once
+//! into a small texture, in the background, and again only when the
+//! node positions change (e.g. with layout cleanup).
+
+use std::sync::Arc;
+
+use tokio::task::JoinHandle;
+use ultraviolet::Vec2;
+
+use super::layout::NodePositions;
+use super::view::View2D;
+
+/// Longest side of the rasterized layout, in pixels
+const TEXTURE_SIZE: usize = 256;
+
+/// Longest side of the inset, in points
+const INSET_SIZE: f32 = 160.0;
+
+const INSET_MARGIN: f32 = 8.0;
+
+/// Maps between layout space and the minimap's screen rectangle; the
+/// layout's y axis points up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimapTransform {
+    pub bounds: (Vec2, Vec2),
+    pub rect: egui::Rect,
+}
+
+impl MinimapTransform {
+    pub fn to_screen(&self, p: Vec2) -> egui::Pos2 {
+        let (min, max) = self.bounds;
+        let size = (max - min).max_by_component(Vec2::broadcast(f32::EPSILON));
+        let t = (p - min) / size;
+        egui::pos2(
+            self.rect.left() + t.x * self.rect.width(),
+            self.rect.bottom() - t.y * self.rect.height(),
+        )
+    }
+
+    pub fn to_layout(&self, p: egui::Pos2) -> Vec2 {
+        let (min, max) = self.bounds;
+        let tx = (p.x - self.rect.left()) / self.rect.width();
+        let ty = (self.rect.bottom() - p.y) / self.rect.height();
+        min + (max - min) * Vec2::new(tx, ty)
+    }
+}
+
+/// The pixel dimensions of an image of `bounds` with the longest side
+/// `max_side` pixels long.
+fn image_size(bounds: (Vec2, Vec2), max_side: usize) -> [usize; 2] {
+    let (min, max) = bounds;
+    let size = max - min;
+    let longest = size.x.max(size.y);
+    if !(longest > 0.0) {
+        return [max_side, max_side];
+    }
+    let side = |len: f32| {
+        ((len / longest * max_side as f32).round() as usize).clamp(1, max_side)
+    };
+    [side(size.x), side(size.y)]
+}
+
+/// Draws the node segments into a white image of the given size whose
+/// alpha is the log-scaled number of segments crossing each pixel, so
+/// that it can be tinted when painted.
+pub fn rasterize_layout(
+    segments: impl Iterator<Item = [Vec2; 2]>,
+    bounds: (Vec2, Vec2),
+    size: [usize; 2],
+) -> egui::ColorImage {
+    let [w, h] = size;
+    let mut counts = vec![0u32; w * h];
+
+    let transform = MinimapTransform {
+        bounds,
+        rect: egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(w as f32, h as f32),
+        ),
+    };
+
+    for [p0, p1] in segments {
+        let (a, b) = (transform.to_screen(p0), transform.to_screen(p1));
+        if !(a.is_finite() && b.is_finite()) {
+            continue;
+        }
+
+        let steps = (b - a).abs().max_elem().ceil().max(1.0) as usize;
+        let mut last = None;
+        for i in 0..=steps {
+            let p = a.lerp(b, i as f32 / steps as f32);
+            let (x, y) = (p.x.floor(), p.y.floor());
+            if x < 0.0 || y < 0.0 || x >= w as f32 || y >= h as f32 {
+                continue;
+            }
+            let ix = y as usize * w + x as usize;
+            // count each segment once per pixel
+            if last != Some(ix) {
+                counts[ix] += 1;
+                last = Some(ix);
+            }
+        }
+    }
+
+    let max = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let max = max.ln_1p();
+
+    let pixels = counts
+        .into_iter()
+        .map(|count| {
+            if count == 0 {
+                return egui::Color32::TRANSPARENT;
+            }
+            // the faintest pixels are still visible
+            let alpha = 0.35 + 0.65 * (count as f32).ln_1p() / max;
+            egui::Color32::from_white_alpha((alpha * 255.0) as u8)
+        })
+        .collect();
+
+    egui::ColorImage { size, pixels }
+}
+
+#[derive(Default)]
+pub struct Minimap {
+    // the positions the texture, or the task, is for
+    positions: Option<Arc<NodePositions>>,
+    texture: Option<egui::TextureHandle>,
+    task: Option<JoinHandle<egui::ColorImage>>,
+}
+
+impl Minimap {
+    /// Picks up the finished texture, and starts rasterizing the layout
+    /// if the node positions have changed.
+    pub fn update(
+        &mut self,
+        tokio_handle: &tokio::runtime::Handle,
+        ctx: &egui::Context,
+        positions: &Arc<NodePositions>,
+        bounds: (Vec2, Vec2),
+    ) {
+        if self.task.as_ref().map(|t| t.is_finished()).unwrap_or(false) {
+            let task = self.task.take().unwrap();
+            if let Ok(image) = tokio_handle.block_on(task) {
+                self.texture = Some(ctx.load_texture(
+                    "Viewer2D-minimap",
+                    image,
+                    egui::TextureOptions::LINEAR,
+                ));
+            }
+        }
+
+        let current = self
+            .positions
+            .as_ref()
+            .is_some_and(|p| Arc::ptr_eq(p, positions));
+
+        if !current {
+            // a stale task is dropped, and its result ignored
+            self.task = None;
+            self.texture = None;
+            self.positions = Some(positions.clone());
+
+            let positions = positions.clone();
+            self.task = Some(tokio_handle.spawn_blocking(move || {
+                let size = image_size(bounds, TEXTURE_SIZE);
+                rasterize_layout(positions.iter_nodes(), bounds, size)
+            }));
+        }
+
+        if self.task.is_some() {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Shows the minimap in the bottom left corner of `panel_rect`,
+    /// with the layout in `bounds`, tinted `color`. Returns the layout
+    /// point clicked or dragged to, if any.
+    pub fn show(
+        &self,
+        ctx: &egui::Context,
+        panel_rect: egui::Rect,
+        bounds: (Vec2, Vec2),
+        view: &View2D,
+        color: egui::Color32,
+    ) -> Option<Vec2> {
+        let (min, max) = bounds;
+        let extent = max - min;
+        let longest = extent.x.max(extent.y).max(f32::EPSILON);
+        let size = egui::vec2(extent.x, extent.y) / longest * INSET_SIZE;
+        let size = size.max(egui::vec2(16.0, 16.0));
+
+        let pos = egui::pos2(
+            panel_rect.left() + INSET_MARGIN,
+            panel_rect.bottom() - INSET_MARGIN - size.y,
+        );
+
+        let mut recenter = None;
+
+        egui::Area::new("Viewer2D-minimap")
+            .fixed_pos(pos)
+            .show(ctx, |ui| {
+                let (rect, resp) =
+                    ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+
+                let visuals = ui.visuals();
+                let painter = ui.painter_at(rect.expand(1.0));
+
+                painter.rect(
+                    rect,
+                    2.0,
+                    visuals.extreme_bg_color.linear_multiply(0.85),
+                    visuals.widgets.noninteractive.bg_stroke,
+                );
+
+                match self.texture.as_ref() {
+                    Some(texture) => {
+                        let uv = egui::Rect::from_min_max(
+                            egui::Pos2::ZERO,
+                            egui::pos2(1.0, 1.0),
+                        );
+                        painter.image(texture.id(), rect, uv, color);
+                    }
+                    None => {
+                        painter.text(
+                            rect.center(),
+                            egui::Align2::CENTER_CENTER,
+                            "…",
+                            egui::FontId::default(),
+                            visuals.weak_text_color(),
+                        );
+                    }
+                }
+
+                let transform = MinimapTransform { bounds, rect };
+
+                let (x0, x1) = view.x_range();
+                let (y0, y1) = view.y_range();
+                let frustum = egui::Rect::from_two_pos(
+                    transform.to_screen(Vec2::new(x0, y0)),
+                    transform.to_screen(Vec2::new(x1, y1)),
+                )
+                .intersect(rect);
+
+                let stroke = visuals.selection.stroke;
+                painter.rect_filled(
+                    frustum,
+                    0.0,
+                    stroke.color.linear_multiply(0.15),
+                );
+                painter.rect_stroke(frustum, 0.0, stroke);
+
+                if resp.clicked() || resp.dragged() {
+                    if let Some(p) = resp.interact_pointer_pos() {
+                        recenter = Some(transform.to_layout(p));
+                    }
+                }
+
+                resp.on_hover_text("Click or drag to move the view");
+            });
+
+        recenter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimap_transform_and_raster() {
+        let bounds = (Vec2::new(0.0, 0.0), Vec2::new(100.0, 50.0));
+        let transform = MinimapTransform {
+            bounds,
+            rect: egui::Rect::from_min_size(
+                egui::pos2(10.0, 20.0),
+                egui::vec2(200.0, 100.0),
+            ),
+        };
+
+        // the layout's y axis points up
+        assert_eq!(transform.to_screen(bounds.0), egui::pos2(10.0, 120.0));
+        assert_eq!(transform.to_screen(bounds.1), egui::pos2(210.0, 20.0));
+
+        let p = Vec2::new(25.0, 40.0);
+        let back = transform.to_layout(transform.to_screen(p));
+        assert!((back - p).mag() < 1e-3);
+
+        assert_eq!(image_size(bounds, 256), [256, 128]);
+
+        // a horizontal segment across the whole layout, and a short
+        // one crossing it
+        let segments = vec![
+            [Vec2::new(0.0, 15.0), Vec2::new(100.0, 15.0)],
+            [Vec2::new(50.0, 5.0), Vec2::new(50.0, 15.0)],
+        ];
+        let image = rasterize_layout(segments.into_iter(), bounds, [10, 5]);
+
+        let alpha = |x: usize, y: usize| image.pixels[y * 10 + x].a();
+        // y = 15 is in the second row from the bottom
+        assert!(alpha(0, 3) > 0);
+        assert!(alpha(9, 3) > 0);
+        assert!(alpha(5, 3) > alpha(0, 3));
+        assert_eq!(alpha(0, 0), 0);
+    }
+}
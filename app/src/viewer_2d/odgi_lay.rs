@@ -0,0 +1,474 @@
+//! Reader for the binary layouts written by `odgi layout -o` (`.lay`).
+//!
+//! odgi stores the layout as the minimum X and Y coordinates, as
+//! little-endian `f64`s, followed by an sdsl `dac_vector_dp` over an
+//! `rrr_vector<63>`. The vector holds the X and Y of the start and the
+//! end of each node, in node order, with the minimum subtracted and
+//! truncated to integers.
+
+use anyhow::Result;
+use std::io::Read;
+use ultraviolet::Vec2;
+
+/// Bits per `rrr_vector` block.
+const RRR_BLOCK: usize = 63;
+
+/// Reads an odgi layout, returning the start and end position of each
+/// node, in the order expected by `NodePositions`.
+pub fn read_odgi_lay(mut reader: impl Read) -> Result<Vec<Vec2>> {
+    let min_x = read_f64(&mut reader)?;
+    let min_y = read_f64(&mut reader)?;
+
+    if !min_x.is_finite() || !min_y.is_finite() {
+        anyhow::bail!("non-finite layout origin ({min_x}, {min_y})");
+    }
+
+    let values = read_dac_vector(&mut reader)?;
+
+    if values.is_empty() || values.len() % 4 != 0 {
+        anyhow::bail!(
+            "odgi layout must hold four coordinates per node, but has {}",
+            values.len()
+        );
+    }
+
+    let positions = values
+        .chunks_exact(2)
+        .map(|xy| {
+            let x = xy[0] as f64 + min_x;
+            let y = xy[1] as f64 + min_y;
+            Vec2::new(x as f32, y as f32)
+        })
+        .collect();
+
+    Ok(positions)
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64(reader: &mut impl Read) -> Result<f64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+/// An sdsl `int_vector`, packed little-endian into 64-bit words.
+struct IntVector {
+    len: usize,
+    width: u8,
+    words: Vec<u64>,
+}
+
+impl IntVector {
+    /// Reads an `int_vector<>`, which stores its width in the header.
+    fn read(reader: &mut impl Read) -> Result<Self> {
+        let bits = read_u64(reader)?;
+        let mut width = [0u8];
+        reader.read_exact(&mut width)?;
+        Self::read_data(reader, bits, width[0])
+    }
+
+    /// Reads an `int_vector` with a fixed width, such as a
+    /// `bit_vector`, whose header is only the size.
+    fn read_fixed(reader: &mut impl Read, width: u8) -> Result<Self> {
+        let bits = read_u64(reader)?;
+        Self::read_data(reader, bits, width)
+    }
+
+    fn read_data(reader: &mut impl Read, bits: u64, width: u8) -> Result<Self> {
+        if !(1..=64).contains(&width) {
+            anyhow::bail!("invalid int_vector width {width}");
+        }
+
+        if !bits.is_multiple_of(width as u64) {
+            anyhow::bail!(
+                "int_vector size of {bits} bits is not a multiple of \
+                 its width {width}"
+            );
+        }
+
+        let word_count = bits.div_ceil(64);
+        let Some(byte_count) = word_count.checked_mul(8) else {
+            anyhow::bail!("int_vector of {bits} bits too large");
+        };
+
+        // the size isn't trusted for the allocation; the buffer only
+        // grows as far as the data actually goes
+        let mut bytes = Vec::new();
+        reader.take(byte_count).read_to_end(&mut bytes)?;
+
+        if bytes.len() as u64 != byte_count {
+            anyhow::bail!(
+                "int_vector truncated, expected {byte_count} bytes but \
+                 found {}",
+                bytes.len()
+            );
+        }
+
+        let words = bytes
+            .chunks_exact(8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+
+        Ok(Self {
+            len: (bits / width as u64) as usize,
+            width,
+            words,
+        })
+    }
+
+    /// Reads `width` bits starting at bit `offset`.
+    fn bits(&self, offset: usize, width: u8) -> Option<u64> {
+        if width == 0 {
+            return Some(0);
+        }
+
+        let end = offset.checked_add(width as usize)?;
+        if end > self.words.len() * 64 {
+            return None;
+        }
+
+        let word = offset / 64;
+        let shift = offset % 64;
+
+        let mut value = self.words[word] >> shift;
+        if shift + width as usize > 64 {
+            value |= self.words[word + 1] << (64 - shift);
+        }
+
+        if width < 64 {
+            value &= (1 << width) - 1;
+        }
+
+        Some(value)
+    }
+
+    fn get(&self, ix: usize) -> Option<u64> {
+        if ix >= self.len {
+            return None;
+        }
+        self.bits(ix * self.width as usize, self.width)
+    }
+}
+
+/// Binomial coefficients `C(n, k)` for `n, k <= 63`; all fit in a u64.
+fn binomial_table() -> Vec<[u64; RRR_BLOCK + 1]> {
+    let mut table = vec![[0u64; RRR_BLOCK + 1]; RRR_BLOCK + 1];
+
+    for n in 0..=RRR_BLOCK {
+        table[n][0] = 1;
+        for k in 1..=n {
+            table[n][k] = table[n - 1][k - 1] + table[n - 1][k];
+        }
+    }
+
+    table
+}
+
+/// The number of bits sdsl uses to store the block number of a block
+/// with `k` set bits.
+fn rrr_nr_width(table: &[[u64; RRR_BLOCK + 1]], k: usize) -> u8 {
+    let count = table[RRR_BLOCK][k];
+    if count == 1 {
+        0
+    } else {
+        (64 - (count - 1).leading_zeros()) as u8
+    }
+}
+
+/// Decodes an sdsl `rrr_vector<63>` into one bool per bit.
+fn read_rrr_vector(reader: &mut impl Read) -> Result<Vec<bool>> {
+    let size = read_u64(reader)?;
+    let classes = IntVector::read(reader)?;
+    let numbers = IntVector::read_fixed(reader, 1)?;
+    // block number pointers and ranks per superblock; only needed for
+    // random access
+    let _ = IntVector::read(reader)?;
+    let _ = IntVector::read(reader)?;
+    let invert = IntVector::read_fixed(reader, 1)?;
+
+    if invert.words.iter().any(|&w| w != 0) {
+        anyhow::bail!("inverted rrr_vector superblocks are not supported");
+    }
+
+    let size = size as usize;
+    let block_count = size.div_ceil(RRR_BLOCK);
+
+    if classes.len < block_count {
+        anyhow::bail!(
+            "rrr_vector has {} block classes for {size} bits",
+            classes.len
+        );
+    }
+
+    let table = binomial_table();
+
+    // the bits can't outnumber what the classes describe, which are
+    // backed by data that was actually read
+    let mut bits = Vec::with_capacity(block_count * RRR_BLOCK);
+    let mut nr_offset = 0;
+
+    for block in 0..block_count {
+        let mut k = classes.get(block).unwrap() as usize;
+
+        if k > RRR_BLOCK {
+            anyhow::bail!("rrr_vector block class {k} out of range");
+        }
+
+        let width = rrr_nr_width(&table, k);
+        let Some(mut nr) = numbers.bits(nr_offset, width) else {
+            anyhow::bail!("rrr_vector block numbers truncated");
+        };
+        nr_offset += width as usize;
+
+        for i in 0..RRR_BLOCK {
+            let nn = RRR_BLOCK - i;
+            let c = table[nn - 1].get(k).copied().unwrap_or(0);
+
+            if k > 0 && nr >= c {
+                bits.push(true);
+                nr -= c;
+                k -= 1;
+            } else {
+                bits.push(false);
+            }
+        }
+    }
+
+    bits.truncate(size);
+    Ok(bits)
+}
+
+/// Decodes an sdsl `dac_vector_dp<rrr_vector<63>>`.
+///
+/// Each value is split across levels of packed chunks; a set overflow
+/// bit means the value continues with the next unused chunk of the
+/// following level, shifted past the bits of the levels before it.
+fn read_dac_vector(reader: &mut impl Read) -> Result<Vec<u64>> {
+    let overflow = read_rrr_vector(reader)?;
+    // the rank support for the rrr_vector serializes nothing
+    let _offsets = IntVector::read_fixed(reader, 64)?;
+    let level_starts = IntVector::read_fixed(reader, 64)?;
+    let level_count = read_u64(reader)?;
+
+    if level_count > 64 {
+        anyhow::bail!("dac_vector has {level_count} levels");
+    }
+
+    let levels = (0..level_count)
+        .map(|_| IntVector::read(reader))
+        .collect::<Result<Vec<_>>>()?;
+
+    let Some(first) = levels.first() else {
+        return Ok(Vec::new());
+    };
+
+    let mut values = (0..first.len)
+        .map(|i| first.get(i).unwrap())
+        .collect::<Vec<_>>();
+
+    // indices into `values` of the entries of the current level
+    let mut indices = (0..values.len()).collect::<Vec<_>>();
+    let mut shift = 0u32;
+
+    for (level, data) in levels.iter().enumerate() {
+        let Some(next) = levels.get(level + 1) else {
+            break;
+        };
+
+        shift += data.width as u32;
+
+        let Some(start) = level_starts.get(level) else {
+            anyhow::bail!("dac_vector level starts truncated");
+        };
+
+        let mut next_indices = Vec::new();
+
+        for (i, &value_ix) in indices.iter().enumerate() {
+            let bit = (start as usize)
+                .checked_add(i)
+                .and_then(|ix| overflow.get(ix).copied());
+
+            let Some(bit) = bit else {
+                anyhow::bail!("dac_vector overflow bits truncated");
+            };
+
+            if !bit {
+                continue;
+            }
+
+            let Some(chunk) = next.get(next_indices.len()) else {
+                anyhow::bail!("dac_vector level {} truncated", level + 1);
+            };
+
+            if shift >= 64 {
+                anyhow::bail!("dac_vector values wider than 64 bits");
+            }
+
+            values[value_ix] |= chunk << shift;
+            next_indices.push(value_ix);
+        }
+
+        indices = next_indices;
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Packs values into an `int_vector`, with the header sdsl writes
+    /// for the given width, or with a width byte if `fixed` is false.
+    fn write_int_vector(
+        out: &mut Vec<u8>,
+        values: &[u64],
+        width: u8,
+        fixed: bool,
+    ) {
+        let bits = values.len() * width as usize;
+        let mut words = vec![0u64; bits.div_ceil(64)];
+
+        for (i, &v) in values.iter().enumerate() {
+            let offset = i * width as usize;
+            let (word, shift) = (offset / 64, offset % 64);
+            words[word] |= v << shift;
+            if shift + width as usize > 64 {
+                words[word + 1] |= v >> (64 - shift);
+            }
+        }
+
+        out.extend_from_slice(&(bits as u64).to_le_bytes());
+        if !fixed {
+            out.push(width);
+        }
+        for w in words {
+            out.extend_from_slice(&w.to_le_bytes());
+        }
+    }
+
+    fn write_bits(out: &mut Vec<u8>, bits: &[bool]) {
+        let values = bits.iter().map(|&b| b as u64).collect::<Vec<_>>();
+        write_int_vector(out, &values, 1, true);
+    }
+
+    fn write_rrr_vector(out: &mut Vec<u8>, bits: &[bool]) {
+        let table = binomial_table();
+
+        let mut classes = Vec::new();
+        let mut numbers = Vec::new();
+
+        for block in bits.chunks(RRR_BLOCK) {
+            let mut k = block.iter().filter(|&&b| b).count();
+            classes.push(k as u64);
+
+            let width = rrr_nr_width(&table, k);
+            let mut nr = 0u64;
+            for (i, &b) in block.iter().enumerate() {
+                if b {
+                    nr += table[RRR_BLOCK - i - 1][k];
+                    k -= 1;
+                }
+            }
+
+            numbers.extend((0..width).map(|i| (nr >> i) & 1 == 1));
+        }
+
+        out.extend_from_slice(&(bits.len() as u64).to_le_bytes());
+        write_int_vector(out, &classes, 6, false);
+        write_bits(out, &numbers);
+        write_int_vector(out, &[0], 1, false);
+        write_int_vector(out, &[0], 1, false);
+        write_bits(out, &[false]);
+    }
+
+    /// Encodes the values with the given chunk width at every level.
+    fn write_dac_vector(out: &mut Vec<u8>, values: &[u64], width: u8) {
+        let mut levels: Vec<Vec<u64>> = Vec::new();
+        let mut overflow = Vec::new();
+        let mut level_starts = Vec::new();
+
+        let mut current = values.to_vec();
+
+        while !current.is_empty() {
+            level_starts.push(overflow.len() as u64);
+
+            let mask = (1u64 << width) - 1;
+            levels.push(current.iter().map(|v| v & mask).collect());
+
+            let mut next = Vec::new();
+            for &v in current.iter() {
+                let rest = v >> width;
+                overflow.push(rest != 0);
+                if rest != 0 {
+                    next.push(rest);
+                }
+            }
+            current = next;
+        }
+
+        write_rrr_vector(out, &overflow);
+        write_int_vector(out, &vec![0; levels.len()], 64, true);
+        write_int_vector(out, &level_starts, 64, true);
+        out.extend_from_slice(&(levels.len() as u64).to_le_bytes());
+        for level in levels {
+            write_int_vector(out, &level, width, false);
+        }
+    }
+
+    fn encode_layout(min: [f64; 2], values: &[u64]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&min[0].to_le_bytes());
+        out.extend_from_slice(&min[1].to_le_bytes());
+        write_dac_vector(&mut out, values, 3);
+        out
+    }
+
+    #[test]
+    fn odgi_lay_roundtrip() -> Result<()> {
+        // enough values to span several rrr blocks, some needing three
+        // levels of 3-bit chunks
+        let values = (0..200u64).map(|i| (i * 37) % 300).collect::<Vec<_>>();
+
+        let lay = encode_layout([-10.0, 5.0], &values);
+        let positions = read_odgi_lay(lay.as_slice())?;
+
+        assert_eq!(positions.len(), 100);
+
+        for (pos, xy) in positions.iter().zip(values.chunks(2)) {
+            assert_eq!(pos.x, xy[0] as f32 - 10.0);
+            assert_eq!(pos.y, xy[1] as f32 + 5.0);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn odgi_lay_errors() {
+        let values = (0..16u64).collect::<Vec<_>>();
+        let lay = encode_layout([0.0, 0.0], &values);
+
+        assert!(read_odgi_lay(lay.as_slice()).is_ok());
+
+        for len in [0, 8, 16, 24, lay.len() / 2, lay.len() - 1] {
+            assert!(read_odgi_lay(&lay[..len]).is_err());
+        }
+
+        // a TSV layout isn't a binary layout
+        assert!(read_odgi_lay(b"idx\tX\tY\n0\t1.0\t2.0\n".as_slice()).is_err());
+
+        // not four coordinates per node
+        let lay = encode_layout([0.0, 0.0], &values[..6]);
+        assert!(read_odgi_lay(lay.as_slice()).is_err());
+
+        // a vector header claiming far more data than there is
+        let mut lay = encode_layout([0.0, 0.0], &values);
+        lay[16..24].copy_from_slice(&(u64::MAX - 1).to_le_bytes());
+        assert!(read_odgi_lay(lay.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! Paths drawn as lines through their nodes, on top of the 2D view,
+//! in the colors from the shared path colors, and the selected paths
+//! in the selection color.
+
+use std::sync::Arc;
+
+use egui::Color32;
+use ultraviolet::Vec2;
+use waragraph_core::graph::{PathId, PathIndex};
+
+use crate::app::SharedState;
+use crate::gui::theme::Theme;
+
+use super::layout::NodePositions;
+use super::view::View2D;
+
+#[derive(Default)]
+pub struct PathOverlay {
+    // the path colors and selection generations, and layout, the
+    // lines were built from
+    built_from: Option<(u64, u64, Arc<NodePositions>)>,
+    lines: Vec<(Color32, Vec<Vec2>)>,
+    selected_lines: Vec<Vec<Vec2>>,
+}
+
+impl PathOverlay {
+    /// Rebuilds the lines if the shown or selected paths, their
+    /// colors, or the layout have changed.
+    pub fn update(
+        &mut self,
+        shared: &SharedState,
+        node_positions: &Arc<NodePositions>,
+    ) {
+        let path_colors = shared.path_colors.blocking_read();
+        let selection = shared.selection.blocking_read();
+
+        let up_to_date = self.built_from.as_ref().map_or(
+            false,
+            |(colors_gen, selection_gen, pos)| {
+                *colors_gen == path_colors.generation()
+                    && *selection_gen == selection.generation()
+                    && Arc::ptr_eq(pos, node_positions)
+            },
+        );
+
+        if up_to_date {
+            return;
+        }
+
+        let graph = &shared.graph;
+
+        self.lines = path_colors
+            .shown_2d
+            .iter()
+            .filter_map(|name| {
+                let path: PathId = *graph.path_names.get_by_right(name)?;
+                let color = path_colors.color(path, name);
+                Some((color, path_line(graph, path, node_positions)))
+            })
+            .collect();
+
+        self.selected_lines = selection
+            .paths()
+            .map(|path| path_line(graph, path, node_positions))
+            .collect();
+
+        self.built_from = Some((
+            path_colors.generation(),
+            selection.generation(),
+            node_positions.clone(),
+        ));
+    }
+
+    pub fn shapes(
+        &self,
+        view: &View2D,
+        dims: Vec2,
+        theme: &Theme,
+    ) -> Vec<egui::Shape> {
+        let mat = view.to_viewport_matrix(dims);
+
+        let mut shapes = Vec::new();
+
+        let selected = self
+            .selected_lines
+            .iter()
+            .map(|line| (3.0, theme.selection_color(), line));
+        let lines = self
+            .lines
+            .iter()
+            .map(|(color, line)| (2.0, *color, line))
+            .chain(selected);
+
+        for (width, color, line) in lines {
+            let mut points: Vec<egui::Pos2> = Vec::with_capacity(line.len());
+
+            for p in line.iter() {
+                let p = mat * p.into_homogeneous_point();
+                let p = egui::pos2(p.x, p.y);
+
+                // skip points that would land on the previous pixel
+                if points.last().map_or(true, |last| last.distance(p) >= 1.0) {
+                    points.push(p);
+                }
+            }
+
+            if points.len() < 2 {
+                continue;
+            }
+
+            for stroke in theme.outlined_strokes(width, color) {
+                shapes.push(egui::Shape::line(points.clone(), stroke));
+            }
+        }
+
+        shapes
+    }
+}
+
+/// The points of the path in world space, following the orientation
+/// of each step through its node.
+fn path_line(
+    graph: &PathIndex,
+    path: PathId,
+    node_positions: &NodePositions,
+) -> Vec<Vec2> {
+    let steps = &graph.path_steps[path.ix()];
+    let mut line = Vec::with_capacity(steps.len() * 2);
+
+    for step in steps.iter() {
+        let (p0, p1) = node_positions.node_pos(step.node());
+        let (from, to) = if step.is_reverse() {
+            (p1, p0)
+        } else {
+            (p0, p1)
+        };
+        line.push(from);
+        line.push(to);
+    }
+
+    line
+}
@@ -0,0 +1,270 @@
+//! Spatial index over the node positions, used to draw only the nodes
+//! in view, with the nodes of tiles smaller than a few pixels merged
+//! into one, so that the instances drawn per frame are bounded by the
+//! screen size rather than the graph size.
+
+use ultraviolet::Vec2;
+
+use super::layout::NodePositions;
+
+/// Tiles with at most this many nodes aren't split further
+const LEAF_SIZE: usize = 64;
+const MAX_DEPTH: usize = 24;
+
+#[derive(Debug, Clone)]
+struct Tile {
+    /// The bounding box of the nodes in the tile, both ends included
+    min: Vec2,
+    max: Vec2,
+    /// Index of the first of the four child tiles, or 0 for leaves
+    first_child: u32,
+    /// The nodes of a leaf, as a range of `NodeQuadTree::nodes`
+    nodes: (u32, u32),
+    /// The longest node in the tile, drawn for the whole tile when
+    /// it's too small to see
+    representative: u32,
+}
+
+impl Tile {
+    fn intersects(&self, min: Vec2, max: Vec2) -> bool {
+        self.min.x <= max.x
+            && self.max.x >= min.x
+            && self.min.y <= max.y
+            && self.max.y >= min.y
+    }
+
+    fn extent(&self) -> f32 {
+        let size = self.max - self.min;
+        size.x.max(size.y)
+    }
+}
+
+pub struct NodeQuadTree {
+    tiles: Vec<Tile>,
+    // node indices, grouped by leaf
+    nodes: Vec<u32>,
+}
+
+impl NodeQuadTree {
+    pub fn from_positions(positions: &NodePositions) -> Self {
+        let segments = positions.iter_nodes().collect::<Vec<_>>();
+        Self::from_segments(&segments)
+    }
+
+    /// Builds the tree over the nodes' start and end positions,
+    /// splitting tiles at the center of the bounding box of their
+    /// nodes' midpoints.
+    pub fn from_segments(segments: &[[Vec2; 2]]) -> Self {
+        let mut tree = Self {
+            tiles: Vec::new(),
+            nodes: (0..segments.len() as u32).collect(),
+        };
+
+        if !segments.is_empty() {
+            tree.tiles.push(tree.leaf(segments, 0..segments.len()));
+            tree.split(segments, 0, 0);
+        }
+
+        tree
+    }
+
+    fn leaf(
+        &self,
+        segments: &[[Vec2; 2]],
+        range: std::ops::Range<usize>,
+    ) -> Tile {
+        let mut min = Vec2::broadcast(f32::MAX);
+        let mut max = Vec2::broadcast(f32::MIN);
+        let mut representative = (0, f32::MIN);
+
+        for &ix in &self.nodes[range.clone()] {
+            let [p0, p1] = segments[ix as usize];
+            min = min.min_by_component(p0).min_by_component(p1);
+            max = max.max_by_component(p0).max_by_component(p1);
+
+            let len = (p1 - p0).mag_sq();
+            if len > representative.1 {
+                representative = (ix, len);
+            }
+        }
+
+        Tile {
+            min,
+            max,
+            first_child: 0,
+            nodes: (range.start as u32, range.end as u32),
+            representative: representative.0,
+        }
+    }
+
+    fn split(&mut self, segments: &[[Vec2; 2]], tile_ix: usize, depth: usize) {
+        let (start, end) = self.tiles[tile_ix].nodes;
+        let (start, end) = (start as usize, end as usize);
+
+        if end - start <= LEAF_SIZE || depth >= MAX_DEPTH {
+            return;
+        }
+
+        let mid = |ix: u32| {
+            let [p0, p1] = segments[ix as usize];
+            (p0 + p1) * 0.5
+        };
+
+        let nodes = &mut self.nodes[start..end];
+
+        let (min, max) = nodes.iter().fold(
+            (Vec2::broadcast(f32::MAX), Vec2::broadcast(f32::MIN)),
+            |(min, max), &ix| {
+                let p = mid(ix);
+                (min.min_by_component(p), max.max_by_component(p))
+            },
+        );
+
+        // all midpoints coincide
+        if min == max {
+            return;
+        }
+
+        // partition the nodes into quadrants, by midpoint, which puts
+        // nodes in at least two of them
+        let center = (min + max) * 0.5;
+        let y_split = partition(nodes, |ix| mid(ix).y < center.y);
+        let x_split_lo =
+            partition(&mut nodes[..y_split], |ix| mid(ix).x < center.x);
+        let x_split_hi =
+            partition(&mut nodes[y_split..], |ix| mid(ix).x < center.x);
+
+        let bounds = [
+            start,
+            start + x_split_lo,
+            start + y_split,
+            start + y_split + x_split_hi,
+            end,
+        ];
+
+        let first_child = self.tiles.len();
+        for w in bounds.windows(2) {
+            let child = self.leaf(segments, w[0]..w[1]);
+            self.tiles.push(child);
+        }
+        self.tiles[tile_ix].first_child = first_child as u32;
+
+        for child in first_child..first_child + 4 {
+            self.split(segments, child, depth + 1);
+        }
+    }
+
+    /// Collects the nodes that may intersect the view bounds into
+    /// `out`; tiles no larger than `min_tile_size` (in layout units)
+    /// are represented by their longest node.
+    pub fn visible_nodes(
+        &self,
+        view_min: Vec2,
+        view_max: Vec2,
+        min_tile_size: f32,
+        out: &mut Vec<u32>,
+    ) {
+        out.clear();
+
+        if self.tiles.is_empty() {
+            return;
+        }
+
+        let mut stack = vec![0usize];
+
+        while let Some(ix) = stack.pop() {
+            let tile = &self.tiles[ix];
+
+            if !tile.intersects(view_min, view_max) {
+                continue;
+            }
+
+            let (start, end) = tile.nodes;
+
+            if end - start > 1 && tile.extent() <= min_tile_size {
+                out.push(tile.representative);
+            } else if tile.first_child == 0 {
+                out.extend_from_slice(
+                    &self.nodes[start as usize..end as usize],
+                );
+            } else {
+                let first = tile.first_child as usize;
+                stack.extend(first..first + 4);
+            }
+        }
+    }
+}
+
+/// Moves the elements for which `pred` is true to the front, and
+/// returns how many there are.
+fn partition(slice: &mut [u32], pred: impl Fn(u32) -> bool) -> usize {
+    let mut split = 0;
+    for i in 0..slice.len() {
+        if pred(slice[i]) {
+            slice.swap(split, i);
+            split += 1;
+        }
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 100x100 grid of unit-length nodes, one per layout unit
+    fn grid() -> Vec<[Vec2; 2]> {
+        (0..100)
+            .flat_map(|y| {
+                (0..100).map(move |x| {
+                    let p = Vec2::new(x as f32, y as f32);
+                    [p, p + Vec2::new(0.5, 0.0)]
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn culls_to_view() {
+        let segments = grid();
+        let tree = NodeQuadTree::from_segments(&segments);
+
+        let mut out = Vec::new();
+        let all = (Vec2::broadcast(-1.0), Vec2::broadcast(101.0));
+        tree.visible_nodes(all.0, all.1, 0.0, &mut out);
+        out.sort();
+        assert_eq!(out, (0..10_000).collect::<Vec<_>>());
+
+        let (min, max) = (Vec2::new(10.0, 20.0), Vec2::new(19.9, 29.9));
+        tree.visible_nodes(min, max, 0.0, &mut out);
+
+        let in_view = segments
+            .iter()
+            .enumerate()
+            .filter(|(_, [p0, _])| {
+                p0.x >= 10.0 && p0.x < 20.0 && p0.y >= 20.0 && p0.y < 30.0
+            })
+            .map(|(ix, _)| ix as u32);
+
+        // all nodes in view are drawn, and tiles only overlapping the
+        // view add at most a margin around it
+        for ix in in_view {
+            assert!(out.contains(&ix));
+        }
+        assert!(out.len() < 40 * 40);
+    }
+
+    #[test]
+    fn merges_tiny_tiles() {
+        let segments = grid();
+        let tree = NodeQuadTree::from_segments(&segments);
+
+        let mut out = Vec::new();
+        let (min, max) = (Vec2::broadcast(-1.0), Vec2::broadcast(101.0));
+
+        // tiles of up to 10x10 units are drawn as one node
+        tree.visible_nodes(min, max, 10.0, &mut out);
+        assert!(!out.is_empty());
+        assert!(out.len() <= 400, "{} nodes drawn", out.len());
+    }
+}
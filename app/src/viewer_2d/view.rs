@@ -13,6 +13,99 @@ impl View2D {
         Self { center, size }
     }
 
+    /// Creates a view with the given aspect ratio (width over
+    /// height) that fits the bounding box `(min, max)`.
+    pub fn fit_bounds(bounds: (Vec2, Vec2), aspect: f32) -> Self {
+        let (min, max) = bounds;
+        let center = min + 0.5 * (max - min);
+        let total_size = max - min;
+
+        let size = if total_size.x > total_size.y * aspect {
+            Vec2::new(total_size.x, total_size.x / aspect)
+        } else {
+            Vec2::new(total_size.y * aspect, total_size.y)
+        };
+
+        Self { center, size }
+    }
+
+    /// Keeps the view from getting lost in empty space, by limiting
+    /// how far it can be zoomed out (relative to the view that fits
+    /// `bounds`) and keeping the center within `bounds`.
+    pub fn constrain_to_bounds(&mut self, bounds: (Vec2, Vec2)) {
+        self.constrain_to_bounds_with_min(bounds, 0.0);
+    }
+
+    /// As [`Self::constrain_to_bounds`], but also keeps the view at
+    /// least `min_height` tall, in world units.
+    pub fn constrain_to_bounds_with_min(
+        &mut self,
+        bounds: (Vec2, Vec2),
+        min_height: f32,
+    ) {
+        const MAX_ZOOM_OUT: f32 = 4.0;
+        // relative to the bounds; keeps the view from collapsing when
+        // zooming in too far for f32 coordinates
+        const MIN_ZOOM_IN: f32 = 1.0e-5;
+
+        let aspect = self.aspect();
+
+        if !aspect.is_finite() || aspect <= 0.0 {
+            return;
+        }
+
+        let home = Self::fit_bounds(bounds, aspect);
+
+        if home.size.y.is_nan() || home.size.y <= 0.0 {
+            return;
+        }
+
+        let max_height = home.size.y * MAX_ZOOM_OUT;
+        let min_height = (home.size.y * MIN_ZOOM_IN).max(min_height);
+
+        if self.size.y > max_height {
+            self.size = Vec2::new(max_height * aspect, max_height);
+        } else if self.size.y < min_height {
+            self.size = Vec2::new(min_height * aspect, min_height);
+        }
+
+        let (min, max) = bounds;
+        self.center = self.center.clamped(min, max);
+    }
+
+    /// Creates a view with the given aspect ratio that shows the
+    /// region `(min, max)` with some margin around it.
+    pub fn frame_region(region: (Vec2, Vec2), aspect: f32) -> Self {
+        const MARGIN: f32 = 0.25;
+
+        let (min, max) = region;
+        let pad = (max - min) * MARGIN;
+
+        Self::fit_bounds((min - pad, max + pad), aspect)
+    }
+
+    /// Interpolates between the views, with the center moving
+    /// linearly and the size changing geometrically, so that zooming
+    /// looks even.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let center = self.center + (other.center - self.center) * t;
+
+        let scale = |a: f32, b: f32| {
+            if a > 0.0 && b > 0.0 {
+                a * (b / a).powf(t)
+            } else {
+                a + (b - a) * t
+            }
+        };
+
+        let size = Vec2::new(
+            scale(self.size.x, other.size.x),
+            scale(self.size.y, other.size.y),
+        );
+
+        Self { center, size }
+    }
+
     pub fn center(&self) -> Vec2 {
         self.center
     }
@@ -120,6 +213,60 @@ impl View2D {
     }
 }
 
+/// A smooth transition from one view to another, eased in and out.
+#[derive(Debug, Clone)]
+pub struct ViewAnimation {
+    from: View2D,
+    to: View2D,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl ViewAnimation {
+    /// The duration of transitions, in seconds
+    pub const DEFAULT_DURATION: f32 = 0.4;
+
+    pub fn new(from: View2D, to: View2D, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: 0.0,
+            duration,
+        }
+    }
+
+    pub fn target(&self) -> &View2D {
+        &self.to
+    }
+
+    /// Advances the animation by `dt` seconds, returning the view at
+    /// that point, and whether the animation is done.
+    pub fn step(&mut self, dt: f32) -> (View2D, bool) {
+        self.elapsed += dt;
+
+        let t = if self.duration > 0.0 {
+            (self.elapsed / self.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        if t >= 1.0 {
+            return (self.to.clone(), true);
+        }
+
+        (self.from.lerp(&self.to, ease_in_out(t)), false)
+    }
+}
+
+// cubic ease-in-out
+fn ease_in_out(t: f32) -> f32 {
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
+
 fn expand_with_fixpoint(a: f32, b: f32, t: f32, s: f32) -> (f32, f32) {
     let l = b - a;
     let x = a + t * l;
@@ -144,3 +291,64 @@ fn expand_with_fixpoint(a: f32, b: f32, t: f32, s: f32) -> (f32, f32) {
 
     (a_, b_)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_2d_fit_and_constrain() {
+        let bounds = (Vec2::new(0.0, 0.0), Vec2::new(100.0, 10.0));
+
+        // wide bounds in a square window are fit by width
+        let view = View2D::fit_bounds(bounds, 1.0);
+        assert_eq!(view.center(), Vec2::new(50.0, 5.0));
+        assert_eq!(view.size(), Vec2::new(100.0, 100.0));
+
+        // tall window
+        let view = View2D::fit_bounds(bounds, 20.0);
+        assert_eq!(view.size(), Vec2::new(200.0, 10.0));
+
+        let mut view =
+            View2D::new(Vec2::new(1000.0, -50.0), Vec2::broadcast(1e6));
+        view.constrain_to_bounds(bounds);
+
+        assert_eq!(view.center(), Vec2::new(100.0, 0.0));
+        assert_eq!(view.size(), Vec2::broadcast(400.0));
+        assert_eq!(view.aspect(), 1.0);
+    }
+
+    #[test]
+    fn view_2d_min_height() {
+        let bounds = (Vec2::new(0.0, 0.0), Vec2::new(10.0, 10.0));
+
+        let mut view = View2D::new(Vec2::new(5.0, 5.0), Vec2::new(0.2, 0.1));
+        view.constrain_to_bounds_with_min(bounds, 2.0);
+
+        assert_eq!(view.size(), Vec2::new(4.0, 2.0));
+        assert_eq!(view.center(), Vec2::new(5.0, 5.0));
+    }
+
+    #[test]
+    fn view_animation_eases_to_target() {
+        let from = View2D::new(Vec2::new(0.0, 0.0), Vec2::broadcast(100.0));
+        let to = View2D::new(Vec2::new(10.0, 20.0), Vec2::broadcast(1.0));
+
+        let mut anim = ViewAnimation::new(from.clone(), to.clone(), 1.0);
+
+        let (view, done) = anim.step(0.5);
+        assert!(!done);
+        // halfway in time is halfway in position, and zoom
+        assert_eq!(view.center(), Vec2::new(5.0, 10.0));
+        assert!((view.size().x - 10.0).abs() < 1e-3);
+
+        // eased: the first quarter covers less than a quarter
+        let mut anim = ViewAnimation::new(from, to.clone(), 1.0);
+        let (view, _) = anim.step(0.25);
+        assert!(view.center().x < 2.5);
+
+        let (view, done) = anim.step(1.0);
+        assert!(done);
+        assert_eq!(view, to);
+    }
+}
@@ -8,9 +8,25 @@ use std::io::BufReader;
 use self::iter::PangenomeNodePosRangeIter;
 use self::iter::PangenomeNodeRangeIter;
 use self::iter::PangenomePathDataPosRangeIter;
-
+use self::progress::{LoadProgress, LoadStage, COVERAGE_BINS};
+
+pub mod bubble;
+pub mod complexity;
+pub mod consensus;
+pub mod coords;
+pub mod gaf;
+pub mod gbz;
+pub mod gfa;
 pub mod iter;
+pub mod liftover;
+pub mod nonref;
+pub mod progress;
+pub mod qc;
+pub mod reference;
 pub mod sampling;
+pub mod stats;
+pub mod subgraph;
+pub mod threshold;
 
 pub mod spoke;
 
@@ -235,6 +251,17 @@ pub struct PathStepRangeIter<'a> {
     // last_step_end_pos: u32,
 }
 
+/// A contiguous stretch of a path, corresponding to (part of) some
+/// pangenome range; see `PathIndex::pangenome_range_to_path_ranges`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathRange {
+    pub path: PathId,
+    /// Range in the path's own coordinates
+    pub range: std::ops::Range<Bp>,
+    /// The indices of the steps covering `range`
+    pub steps: std::ops::Range<usize>,
+}
+
 impl<'a> Iterator for PathStepRangeIter<'a> {
     type Item = (usize, &'a OrientedNode);
 
@@ -243,12 +270,73 @@ impl<'a> Iterator for PathStepRangeIter<'a> {
     }
 }
 
+// the number of records parsed between progress updates
+const PROGRESS_INTERVAL: usize = 10_000;
+
 impl PathIndex {
+    /// Loads the graph from a GBZ file if the path ends with `.gbz`,
+    /// and from a GFA otherwise.
+    pub fn from_file(
+        path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<Self> {
+        Self::from_file_with_progress(path, None)
+    }
+
+    /// Like `from_file`, reporting the progress of GFA parsing; GBZ
+    /// files are loaded in one step, so only the end is reported, and
+    /// can't be cancelled.
+    pub fn from_file_with_progress(
+        path: impl AsRef<std::path::Path>,
+        progress: Option<&LoadProgress>,
+    ) -> std::io::Result<Self> {
+        let result = if gbz::is_gbz_file(&path) {
+            Self::from_gbz(path)
+        } else {
+            Self::from_gfa_with_progress(path, progress)
+        };
+
+        if let Some(progress) = progress {
+            progress.set_stage(LoadStage::Done);
+        }
+
+        result
+    }
+
     pub fn from_gfa(
         gfa_path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<Self> {
+        Self::from_gfa_with_progress(gfa_path, None)
+    }
+
+    /// Parses the GFA in `gfa_reader`, which is read once for the
+    /// segments, once for the links, and once for the paths.
+    pub fn from_gfa_reader(
+        gfa_reader: impl BufRead + Seek,
+    ) -> std::io::Result<Self> {
+        Self::from_gfa_reader_with_progress(gfa_reader, None)
+    }
+
+    fn from_gfa_with_progress(
+        gfa_path: impl AsRef<std::path::Path>,
+        progress: Option<&LoadProgress>,
     ) -> std::io::Result<Self> {
         let gfa = std::fs::File::open(&gfa_path)?;
-        let mut gfa_reader = BufReader::new(gfa);
+
+        if let Some(progress) = progress {
+            let file_len = gfa.metadata()?.len();
+            progress.update(|s| s.file_len = file_len);
+        }
+
+        Self::from_gfa_reader_with_progress(BufReader::new(gfa), progress)
+    }
+
+    fn from_gfa_reader_with_progress(
+        mut gfa_reader: impl BufRead + Seek,
+        progress: Option<&LoadProgress>,
+    ) -> std::io::Result<Self> {
+        if let Some(progress) = progress {
+            progress.set_stage(LoadStage::Segments);
+        }
 
         let mut line_buf = Vec::new();
 
@@ -258,6 +346,7 @@ impl PathIndex {
         let mut sequence = Vec::new();
 
         let mut seg_id_range = (std::u32::MAX, 0u32);
+        let mut seg_ids = RoaringBitmap::new();
 
         loop {
             line_buf.clear();
@@ -267,6 +356,11 @@ impl PathIndex {
                 break;
             }
 
+            if let Some(progress) = progress {
+                progress.add_bytes(len);
+                progress.check_cancelled()?;
+            }
+
             let line = &line_buf[..len - 1];
 
             if !matches!(line.first(), Some(b'S')) {
@@ -287,6 +381,13 @@ impl PathIndex {
                 std::io::Error::new(std::io::ErrorKind::InvalidData, e)
             })?;
 
+            if !seg_ids.insert(seg_id) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Duplicate GFA segment name {seg_id}"),
+                ));
+            }
+
             seg_id_range.0 = seg_id_range.0.min(seg_id);
             seg_id_range.1 = seg_id_range.1.max(seg_id);
 
@@ -296,17 +397,58 @@ impl PathIndex {
             sequence_total_len += len;
             seg_lens.push(len);
             sequence.extend(seq);
+
+            if let Some(progress) = progress {
+                if seg_lens.len() % PROGRESS_INTERVAL == 0 {
+                    progress.update(|s| {
+                        s.segments = seg_lens.len();
+                        s.sequence_len = sequence_total_len as u64;
+                    });
+                }
+            }
         }
 
-        assert!(
-        seg_id_range.1 - seg_id_range.0 == (seg_lens.len() as u32) - 1,
-        "GFA segments must be tightly packed: min ID {}, max ID {}, node count {}, was {}",
-        seg_id_range.0, seg_id_range.1, seg_lens.len(),
-        seg_id_range.1 - seg_id_range.0,
-        );
+        // the coverage bin of each node, for the progress preview
+        let node_bins = progress.map(|progress| {
+            progress.update(|s| {
+                s.segments = seg_lens.len();
+                s.sequence_len = sequence_total_len as u64;
+                s.coverage = vec![0; COVERAGE_BINS];
+            });
+            progress.set_stage(LoadStage::Links);
 
-        let gfa = std::fs::File::open(&gfa_path)?;
-        let mut gfa_reader = BufReader::new(gfa);
+            let offsets = seg_lens
+                .iter()
+                .scan(0u64, |offset, &len| {
+                    let start = *offset;
+                    *offset += len as u64;
+                    Some(start)
+                })
+                .collect::<Vec<_>>();
+            progress::node_bins(&offsets, sequence_total_len as u64)
+        });
+
+        if seg_lens.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "GFA has no segments",
+            ));
+        }
+
+        if (seg_id_range.1 - seg_id_range.0) as usize != seg_lens.len() - 1 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "GFA segments must be tightly packed: \
+                     min ID {}, max ID {}, but {} segments",
+                    seg_id_range.0,
+                    seg_id_range.1,
+                    seg_lens.len(),
+                ),
+            ));
+        }
+
+        gfa_reader.rewind()?;
 
         let mut edges = Vec::new();
 
@@ -318,6 +460,11 @@ impl PathIndex {
                 break;
             }
 
+            if let Some(progress) = progress {
+                progress.add_bytes(len);
+                progress.check_cancelled()?;
+            }
+
             let line = &line_buf[..len - 1];
 
             if !matches!(line.first(), Some(b'L')) {
@@ -327,6 +474,17 @@ impl PathIndex {
             let fields = line.split(|&c| c == b'\t');
             let edge = Self::parse_gfa_link(seg_id_range.0, fields)?;
             edges.push(edge);
+
+            if let Some(progress) = progress {
+                if edges.len() % PROGRESS_INTERVAL == 0 {
+                    progress.update(|s| s.edges = edges.len());
+                }
+            }
+        }
+
+        if let Some(progress) = progress {
+            progress.update(|s| s.edges = edges.len());
+            progress.set_stage(LoadStage::Paths);
         }
         println!("parsed {} edges", edges.len());
 
@@ -334,8 +492,7 @@ impl PathIndex {
 
         let node_count = seg_lens.len();
 
-        let gfa = std::fs::File::open(&gfa_path)?;
-        let mut gfa_reader = BufReader::new(gfa);
+        gfa_reader.rewind()?;
 
         let mut path_names = BiBTreeMap::default();
 
@@ -355,6 +512,11 @@ impl PathIndex {
                 break;
             }
 
+            if let Some(progress) = progress {
+                progress.add_bytes(len);
+                progress.check_cancelled()?;
+            }
+
             let line = &line_buf[..len];
             if !matches!(line.first(), Some(b'P')) {
                 continue;
@@ -411,6 +573,20 @@ impl PathIndex {
                 pos += len;
             }
 
+            if let (Some(progress), Some(node_bins)) = (progress, &node_bins) {
+                let mut coverage = vec![0u64; COVERAGE_BINS];
+                for step in parsed_steps.iter() {
+                    coverage[node_bins[step.node().ix()] as usize] += 1;
+                }
+
+                progress.update(|s| {
+                    s.paths.push((name.to_string(), pos as u64));
+                    for (bin, count) in s.coverage.iter_mut().zip(coverage) {
+                        *bin += count;
+                    }
+                });
+            }
+
             path_steps.push(parsed_steps);
             path_step_offsets.push(offsets);
             path_node_sets.push(path_nodes);
@@ -495,6 +671,28 @@ impl PathIndex {
         (offset, length)
     }
 
+    /// The length of the path's sequence, i.e. the end of its last
+    /// step in path coordinates.
+    pub fn path_len(&self, path: PathId) -> Option<Bp> {
+        let offsets = self.path_step_offsets.get(path.ix())?;
+        let last = self.path_steps.get(path.ix())?.last()?;
+        let start = offsets.max().unwrap_or_default();
+        Some(Bp(start + self.node_length(last.node()).0))
+    }
+
+    /// The GFA segment ID of the node.
+    pub fn segment_id(&self, node: Node) -> u64 {
+        node.ix() as u64 + self.segment_id_range.0 as u64
+    }
+
+    /// The node with the given GFA segment ID, if it's in the graph.
+    pub fn segment_node(&self, id: u64) -> Option<Node> {
+        let (first, last) = self.segment_id_range;
+        (first as u64..=last as u64)
+            .contains(&id)
+            .then(|| Node::from((id - first as u64) as u32))
+    }
+
     #[inline]
     pub fn node_offset(&self, node: Node) -> Bp {
         self.node_offset_length(node).0
@@ -507,12 +705,66 @@ impl PathIndex {
         &self.sequence[start..end]
     }
 
+    /// The pangenome sequence in `range`, clamped to the pangenome.
+    pub fn pangenome_sequence(&self, range: std::ops::Range<Bp>) -> &[u8] {
+        let end = (range.end.0 as usize).min(self.sequence.len());
+        let start = (range.start.0 as usize).min(end);
+        &self.sequence[start..end]
+    }
+
+    /// The sequence of `path` in `range`, given in path coordinates
+    /// and clamped to the path. Nodes traversed in reverse are
+    /// reverse complemented.
+    pub fn path_sequence(
+        &self,
+        path: PathId,
+        range: std::ops::Range<Bp>,
+    ) -> Option<Vec<u8>> {
+        let offsets = self.path_step_offsets.get(path.ix())?;
+        let steps = self.path_steps.get(path.ix())?;
+
+        let (start, end) = (range.start.0, range.end.0);
+
+        let mut seq = Vec::new();
+
+        if start >= end || steps.is_empty() {
+            return Some(seq);
+        }
+
+        let first = (offsets.rank(start) as usize).saturating_sub(1);
+
+        for (ix, step) in steps.iter().enumerate().skip(first) {
+            let offset = offsets.select(ix as u64).unwrap();
+
+            if offset >= end {
+                break;
+            }
+
+            let node_seq = self.node_sequence(step.node());
+            let len = node_seq.len() as u64;
+
+            let l = (start.max(offset) - offset).min(len) as usize;
+            let r = (end.min(offset + len) - offset) as usize;
+
+            if step.is_reverse() {
+                let rev = reverse_complement(node_seq);
+                seq.extend_from_slice(&rev[l..r]);
+            } else {
+                seq.extend_from_slice(&node_seq[l..r]);
+            }
+        }
+
+        Some(seq)
+    }
+
+    /// The node containing the given pangenome position, or `None` if
+    /// the position is past the end of the pangenome.
     pub fn node_at_pangenome_pos(&self, pos: Bp) -> Option<Node> {
-        if pos > self.sequence_total_len {
+        if pos >= self.sequence_total_len {
             return None;
         }
         let rank = self.segment_offsets.rank(pos.0);
-        Some(Node::from(rank as u32))
+        Some(Node::from(rank as usize - 1))
     }
 
     #[inline]
@@ -555,6 +807,22 @@ impl PathIndex {
         }))
     }
 
+    /// The average number of times the node is traversed by each of
+    /// the paths that traverse it, or 0.0 if no path traverses it.
+    /// Values above 1.0 indicate collapsed repeats.
+    pub fn node_multiplicity(&self, node: Node) -> f32 {
+        let Some(occs) = self.node_path_steps.get(node.ix()) else {
+            return 0.0;
+        };
+
+        if occs.is_empty() {
+            return 0.0;
+        }
+
+        let steps: usize = occs.values().map(|steps| steps.len()).sum();
+        steps as f32 / occs.len() as f32
+    }
+
     pub fn node_on_path(&self, node: Node, path: PathId) -> bool {
         self.node_path_steps
             .get(node.ix())
@@ -603,6 +871,25 @@ impl PathIndex {
         )
     }
 
+    /// The value of `node` in per-path data with one value per node
+    /// on the path, in node order, such as the path data sources
+    /// produce; `None` if the path doesn't visit the node.
+    pub fn path_node_value<'data, T>(
+        &self,
+        path_id: PathId,
+        node: Node,
+        data: &'data [T],
+    ) -> Option<&'data T> {
+        let path_nodes = self.path_node_sets.get(path_id.ix())?;
+        let ix = node.ix() as u32;
+
+        if !path_nodes.contains(ix) {
+            return None;
+        }
+
+        data.get(path_nodes.rank(ix) as usize - 1)
+    }
+
     pub fn path_steps<'a>(
         &'a self,
         path_name: &str,
@@ -622,6 +909,91 @@ impl PathIndex {
         steps.get(pos_rank).copied()
     }
 
+    /// Maps a pangenome range to the ranges of the paths that
+    /// traverse it, i.e. the inverse of flattening a path range to
+    /// the pangenome. A path that leaves and reenters the range, or
+    /// that loops through it, has one `PathRange` per stretch.
+    ///
+    /// Nodes traversed in reverse are handled at the bp level, so
+    /// the path ranges cover exactly the part of each node that is
+    /// in `pan_range`. The result is sorted by path, then position.
+    pub fn pangenome_range_to_path_ranges(
+        &self,
+        pan_range: std::ops::Range<Bp>,
+    ) -> Vec<PathRange> {
+        let start = pan_range.start.0;
+        let end = pan_range.end.0.min(self.pangenome_len().0);
+
+        if start >= end {
+            return Vec::new();
+        }
+
+        // (path, step index, path-local range) for each step on a
+        // node that overlaps the range
+        let mut steps: Vec<(PathId, usize, std::ops::Range<u64>)> = Vec::new();
+
+        let first = self.segment_offsets.rank(start) as usize - 1;
+        let last = self.segment_offsets.rank(end - 1) as usize - 1;
+
+        for ix in first..=last {
+            let node = Node::from(ix);
+            let (offset, len) = self.node_offset_length(node);
+
+            // the overlap, relative to the start of the node
+            let l = start.max(offset.0) - offset.0;
+            let r = end.min(offset.0 + len.0) - offset.0;
+
+            for (&path, step_ixs) in self.node_path_steps[ix].iter() {
+                let path_steps = &self.path_steps[path.ix()];
+                let offsets = &self.path_step_offsets[path.ix()];
+
+                for &step_ix in step_ixs {
+                    let step_ix = step_ix as usize;
+                    let step_offset = offsets.select(step_ix as u64).unwrap();
+
+                    let local = if path_steps[step_ix].is_reverse() {
+                        (len.0 - r)..(len.0 - l)
+                    } else {
+                        l..r
+                    };
+
+                    steps.push((
+                        path,
+                        step_ix,
+                        (step_offset + local.start)..(step_offset + local.end),
+                    ));
+                }
+            }
+        }
+
+        steps.sort_by_key(|(path, step_ix, _)| (*path, *step_ix));
+
+        let mut result: Vec<PathRange> = Vec::new();
+
+        for (path, step_ix, range) in steps {
+            if let Some(prev) = result.last_mut() {
+                // consecutive steps can be far apart on the path, e.g.
+                // when a reversed step only covers the end of a node
+                if prev.path == path
+                    && prev.steps.end == step_ix
+                    && prev.range.end == Bp(range.start)
+                {
+                    prev.range.end = Bp(range.end);
+                    prev.steps.end = step_ix + 1;
+                    continue;
+                }
+            }
+
+            result.push(PathRange {
+                path,
+                range: Bp(range.start)..Bp(range.end),
+                steps: step_ix..step_ix + 1,
+            });
+        }
+
+        result
+    }
+
     pub fn path_step_range_iter<'a, P: Into<u64>>(
         &'a self,
         path_id: PathId,
@@ -662,6 +1034,25 @@ impl PathIndex {
     }
 }
 
+/// Reverse complement of a nucleotide sequence; the case is kept, and
+/// anything other than ACGT is left as it is.
+pub fn reverse_complement(seq: &[u8]) -> Vec<u8> {
+    seq.iter()
+        .rev()
+        .map(|&b| match b {
+            b'A' => b'T',
+            b'T' => b'A',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'a' => b't',
+            b't' => b'a',
+            b'c' => b'g',
+            b'g' => b'c',
+            other => other,
+        })
+        .collect()
+}
+
 impl PathIndex {
     fn parse_gfa_link<'a>(
         min_id: u32,
@@ -737,6 +1128,59 @@ mod tests {
         assert_eq!(node_lengths, expected);
     }
 
+    #[test]
+    fn path_lengths() {
+        let index = PathIndex::from_gfa(GFA_PATH).unwrap();
+
+        for (ix, steps) in index.path_steps.iter().enumerate() {
+            let expected: u64 =
+                steps.iter().map(|s| index.node_length(s.node()).0).sum();
+            let path = PathId::from(ix as u32);
+            assert_eq!(index.path_len(path), Some(Bp(expected)));
+        }
+
+        let missing = PathId::from(index.path_names.len() as u32);
+        assert_eq!(index.path_len(missing), None);
+    }
+
+    #[test]
+    fn load_progress_preview() {
+        let progress = LoadProgress::default();
+        let index =
+            PathIndex::from_file_with_progress(GFA_PATH, Some(&progress))
+                .unwrap();
+
+        let snapshot = progress.snapshot();
+        assert_eq!(snapshot.stage, LoadStage::Done);
+        assert_eq!(snapshot.segments, index.node_count);
+        assert_eq!(snapshot.edges, index.edges.len());
+        assert_eq!(snapshot.sequence_len, index.sequence_total_len.0);
+
+        assert_eq!(snapshot.paths.len(), index.path_names.len());
+        for (ix, (name, len)) in snapshot.paths.iter().enumerate() {
+            let path = PathId::from(ix as u32);
+            assert_eq!(index.path_names.get_by_left(&path), Some(name));
+            assert_eq!(index.path_len(path), Some(Bp(*len)));
+        }
+
+        let total_steps: usize =
+            index.path_steps.iter().map(|steps| steps.len()).sum();
+        assert_eq!(snapshot.coverage.len(), COVERAGE_BINS);
+        assert_eq!(snapshot.coverage.iter().sum::<u64>(), total_steps as u64);
+    }
+
+    #[test]
+    fn load_cancelled() {
+        let progress = LoadProgress::default();
+        progress.cancel();
+
+        let result =
+            PathIndex::from_file_with_progress(GFA_PATH, Some(&progress));
+        let err = result.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+        assert_eq!(progress.snapshot().segments, 0);
+    }
+
     #[test]
     fn pangenome_nodes_range() {
         let index = PathIndex::from_gfa(GFA_PATH).unwrap();
@@ -754,4 +1198,217 @@ mod tests {
         assert_eq!(range0, Node(1)..=Node(1));
         assert_eq!(range1, Node(4964)..=Node(4965));
     }
+
+    #[test]
+    fn node_at_pangenome_pos_bounds() {
+        let index = PathIndex::from_gfa(GFA_PATH).unwrap();
+        let total_len = index.pangenome_len();
+        let last = Node::from(index.node_count - 1);
+
+        let node1_start = index.node_offset(Node(1));
+
+        assert_eq!(index.node_at_pangenome_pos(Bp(0)), Some(Node(0)));
+        assert_eq!(index.node_at_pangenome_pos(Bp(44)), Some(Node(1)));
+        assert_eq!(index.node_at_pangenome_pos(node1_start), Some(Node(1)));
+        assert_eq!(
+            index.node_at_pangenome_pos(Bp(node1_start.0 - 1)),
+            Some(Node(0))
+        );
+        assert_eq!(
+            index.node_at_pangenome_pos(Bp(total_len.0 - 1)),
+            Some(last)
+        );
+        assert_eq!(index.node_at_pangenome_pos(total_len), None);
+    }
+
+    #[test]
+    fn pangenome_range_to_path_ranges_roundtrip() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tCC
+S\t3\tGGG
+S\t4\tTTTT
+L\t1\t+\t2\t+\t0M
+L\t1\t+\t3\t+\t0M
+L\t2\t+\t4\t+\t0M
+L\t3\t+\t4\t+\t0M
+P\tfwd\t1+,2+,4+\t*
+P\trev\t4-,3-,1-\t*
+";
+        let index =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let fwd = *index.path_names.get_by_right("fwd").unwrap();
+        let rev = *index.path_names.get_by_right("rev").unwrap();
+
+        // pangenome layout: 1 at 0..4, 2 at 4..6, 3 at 6..9, 4 at 9..13
+
+        // last two bp of node 1 and the first of node 2
+        let ranges = index.pangenome_range_to_path_ranges(Bp(2)..Bp(5));
+        assert_eq!(
+            ranges,
+            vec![
+                PathRange {
+                    path: fwd,
+                    range: Bp(2)..Bp(5),
+                    steps: 0..2,
+                },
+                // node 1 is the last step, at 7..11, in reverse
+                PathRange {
+                    path: rev,
+                    range: Bp(7)..Bp(9),
+                    steps: 2..3,
+                },
+            ]
+        );
+
+        // node 3 is only on the reverse path
+        let ranges = index.pangenome_range_to_path_ranges(Bp(6)..Bp(9));
+        assert_eq!(
+            ranges,
+            vec![PathRange {
+                path: rev,
+                range: Bp(4)..Bp(7),
+                steps: 1..2,
+            }]
+        );
+
+        // the entire pangenome covers entire paths
+        let ranges =
+            index.pangenome_range_to_path_ranges(Bp(0)..index.pangenome_len());
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0].range, Bp(0)..Bp(10));
+        assert_eq!(ranges[1].range, Bp(0)..Bp(11));
+
+        assert!(index
+            .pangenome_range_to_path_ranges(Bp(5)..Bp(5))
+            .is_empty());
+    }
+
+    #[test]
+    fn path_ranges_split_at_gaps() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tCC
+P\trev\t1-,2-\t*
+P\tloop\t1+,2+,1+\t*
+";
+        let index =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let rev = *index.path_names.get_by_right("rev").unwrap();
+        let looping = *index.path_names.get_by_right("loop").unwrap();
+
+        // the end of node 1 and the start of node 2
+        let ranges = index.pangenome_range_to_path_ranges(Bp(2)..Bp(5));
+        assert_eq!(
+            ranges,
+            vec![
+                PathRange {
+                    path: rev,
+                    range: Bp(0)..Bp(2),
+                    steps: 0..1,
+                },
+                PathRange {
+                    path: rev,
+                    range: Bp(5)..Bp(6),
+                    steps: 1..2,
+                },
+                PathRange {
+                    path: looping,
+                    range: Bp(2)..Bp(5),
+                    steps: 0..2,
+                },
+                // node 1 again, after the end of the previous range
+                PathRange {
+                    path: looping,
+                    range: Bp(8)..Bp(10),
+                    steps: 2..3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn path_sequence_reverse_complements() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tCC
+S\t3\tGGG
+S\t4\tTTTT
+P\tfwd\t1+,2+,4+\t*
+P\trev\t4-,3-,1-\t*
+";
+        let index =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let fwd = *index.path_names.get_by_right("fwd").unwrap();
+        let rev = *index.path_names.get_by_right("rev").unwrap();
+
+        let seq = |path, l, r| index.path_sequence(path, Bp(l)..Bp(r)).unwrap();
+
+        assert_eq!(seq(fwd, 0, 10), b"AAAACCTTTT");
+        assert_eq!(seq(fwd, 3, 7), b"ACCT");
+        assert_eq!(seq(rev, 0, 11), b"AAAACCCTTTT");
+        assert_eq!(seq(rev, 2, 6), b"AACC");
+        // clamped to the path
+        assert_eq!(seq(rev, 9, 100), b"TT");
+        assert!(seq(rev, 20, 30).is_empty());
+
+        assert_eq!(index.pangenome_sequence(Bp(3)..Bp(7)), b"ACCG");
+        assert_eq!(index.pangenome_sequence(Bp(10)..Bp(100)), b"TTT");
+    }
+
+    #[test]
+    fn node_multiplicity_matches_steps() {
+        let index = PathIndex::from_gfa(GFA_PATH).unwrap();
+
+        let mut steps = vec![0usize; index.node_count];
+        let mut paths = vec![0usize; index.node_count];
+
+        for (path_ix, path_steps) in index.path_steps.iter().enumerate() {
+            for step in path_steps {
+                steps[step.node().ix()] += 1;
+            }
+            for node in index.path_node_sets[path_ix].iter() {
+                paths[node as usize] += 1;
+            }
+        }
+
+        for ix in 0..index.node_count {
+            let mult = index.node_multiplicity(Node::from(ix));
+            if paths[ix] == 0 {
+                assert_eq!(mult, 0.0);
+            } else {
+                assert!(mult >= 1.0);
+                let expected = steps[ix] as f32 / paths[ix] as f32;
+                assert!((mult - expected).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn path_node_values_match_range_iter() {
+        use sampling::PathData;
+
+        let index = PathIndex::from_gfa(GFA_PATH).unwrap();
+        let depth = sampling::PathDepthData::new(&index);
+
+        let path = PathId::from(0u32);
+        let data = depth.get_path(path);
+
+        let range = 0..index.pangenome_len().0;
+        for ((node, _), val) in
+            index.path_data_pan_range_iter(range, path, data)
+        {
+            assert_eq!(index.path_node_value(path, node, data), Some(val));
+        }
+
+        let missing = (0..index.node_count)
+            .map(Node::from)
+            .find(|n| !index.path_node_sets[0].contains(n.ix() as u32));
+        if let Some(node) = missing {
+            assert_eq!(index.path_node_value(path, node, data), None);
+        }
+    }
 }
@@ -0,0 +1,238 @@
+//! Finding the simple bubble around a node, i.e. the alternative
+//! sequences the paths take between two flanking nodes they all
+//! share, and comparing its alleles with dot matrices.
+
+use std::collections::HashMap;
+
+use super::{reverse_complement, Bp, Node, PathId, PathIndex};
+
+/// Alleles longer than this are left out of bubbles
+pub const MAX_ALLELE_LEN: u64 = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allele {
+    /// Sequence between the flanking nodes, in the orientation of
+    /// the graph
+    pub seq: Vec<u8>,
+    pub paths: Vec<PathId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bubble {
+    pub source: Node,
+    pub sink: Node,
+    /// Distinct alleles, those taken by the most paths first
+    pub alleles: Vec<Allele>,
+}
+
+/// Finds the bubble containing `node`, flanked by the nearest nodes,
+/// within `max_span` nodes in either direction, that are on every
+/// path that visits `node`. `None` if there is no such pair, or if
+/// all paths between them take the same sequence.
+pub fn find_bubble(
+    graph: &PathIndex,
+    node: Node,
+    max_span: usize,
+) -> Option<Bubble> {
+    let paths = graph.paths_on_node(node)?.collect::<Vec<_>>();
+    if paths.is_empty() {
+        return None;
+    }
+
+    let is_anchor = |ix: usize| {
+        paths
+            .iter()
+            .all(|p| graph.path_node_sets[p.ix()].contains(ix as u32))
+    };
+
+    let source = (node.ix().saturating_sub(max_span)..node.ix())
+        .rev()
+        .find(|&ix| is_anchor(ix))?;
+    let sink = (node.ix() + 1
+        ..(node.ix() + 1 + max_span).min(graph.node_count))
+        .find(|&ix| is_anchor(ix))?;
+
+    let source = Node::from(source);
+    let sink = Node::from(sink);
+
+    let mut alleles: HashMap<Vec<u8>, Vec<PathId>> = HashMap::default();
+
+    for path in graph.paths_on_node(source)? {
+        if let Some(seq) = allele_seq(graph, path, source, sink) {
+            alleles.entry(seq).or_default().push(path);
+        }
+    }
+
+    if alleles.len() < 2 {
+        return None;
+    }
+
+    let mut alleles = alleles
+        .into_iter()
+        .map(|(seq, mut paths)| {
+            paths.sort();
+            Allele { seq, paths }
+        })
+        .collect::<Vec<_>>();
+    alleles.sort_by(|a, b| {
+        b.paths.len().cmp(&a.paths.len()).then(a.seq.cmp(&b.seq))
+    });
+
+    Some(Bubble {
+        source,
+        sink,
+        alleles,
+    })
+}
+
+/// The sequence `path` takes between its first visit to `source` and
+/// the nearest visit to `sink`, reverse complemented if the path goes
+/// from `sink` to `source`.
+fn allele_seq(
+    graph: &PathIndex,
+    path: PathId,
+    source: Node,
+    sink: Node,
+) -> Option<Vec<u8>> {
+    let (src_step, src_offset) =
+        graph.node_path_step_offsets(source, path)?.next()?;
+    let sink_steps = graph
+        .node_path_step_offsets(sink, path)?
+        .collect::<Vec<_>>();
+
+    let forward = sink_steps.iter().find(|&&(step, _)| step > src_step);
+
+    let (range, reverse) = if let Some((_, sink_offset)) = forward {
+        let start = src_offset.0 + graph.node_length(source).0;
+        (start..sink_offset.0, false)
+    } else {
+        let (_, sink_offset) = sink_steps
+            .iter()
+            .filter(|&&(step, _)| step < src_step)
+            .last()?;
+        let start = sink_offset.0 + graph.node_length(sink).0;
+        (start..src_offset.0, true)
+    };
+
+    if range.end.saturating_sub(range.start) > MAX_ALLELE_LEN {
+        return None;
+    }
+
+    let seq = graph.path_sequence(path, Bp(range.start)..Bp(range.end))?;
+
+    Some(if reverse {
+        reverse_complement(&seq)
+    } else {
+        seq
+    })
+}
+
+/// Positions where two sequences share a k-mer, binned to at most
+/// `max_bins` rows and columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotMatrix {
+    /// Bins along the first sequence
+    pub rows: usize,
+    /// Bins along the second sequence
+    pub cols: usize,
+    /// Row-major
+    pub cells: Vec<bool>,
+}
+
+impl DotMatrix {
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        self.cells[row * self.cols + col]
+    }
+}
+
+/// The dot matrix of the (case-insensitive) `k`-mers shared by `a`
+/// and `b`, on the same strand.
+pub fn dot_matrix(a: &[u8], b: &[u8], k: usize, max_bins: usize) -> DotMatrix {
+    let k = k.max(1);
+    let max_bins = max_bins.max(1);
+
+    let rows = a.len().min(max_bins);
+    let cols = b.len().min(max_bins);
+    let mut cells = vec![false; rows * cols];
+
+    if a.len() < k || b.len() < k {
+        return DotMatrix { rows, cols, cells };
+    }
+
+    let a = a.to_ascii_uppercase();
+    let b = b.to_ascii_uppercase();
+
+    let mut b_kmers: HashMap<&[u8], Vec<usize>> = HashMap::default();
+    for (j, kmer) in b.windows(k).enumerate() {
+        b_kmers.entry(kmer).or_default().push(j);
+    }
+
+    for (i, kmer) in a.windows(k).enumerate() {
+        let Some(js) = b_kmers.get(kmer) else {
+            continue;
+        };
+
+        let row = i * rows / a.len();
+        for &j in js {
+            let col = j * cols / b.len();
+            cells[row * cols + col] = true;
+        }
+    }
+
+    DotMatrix { rows, cols, cells }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bubble_alleles() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tC
+S\t3\tGT
+S\t4\tTTTT
+L\t1\t+\t2\t+\t0M
+L\t1\t+\t3\t+\t0M
+L\t1\t+\t4\t+\t0M
+L\t2\t+\t4\t+\t0M
+L\t3\t+\t4\t+\t0M
+P\ta\t1+,2+,4+\t*
+P\tb\t1+,3+,4+\t*
+P\tc\t1+,2+,4+\t*
+P\td\t4-,1-\t*
+";
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let bubble = find_bubble(&graph, Node::from(1u32), 10).unwrap();
+        assert_eq!(bubble.source, Node::from(0u32));
+        assert_eq!(bubble.sink, Node::from(3u32));
+
+        let seqs = bubble
+            .alleles
+            .iter()
+            .map(|a| (a.seq.as_slice(), a.paths.len()))
+            .collect::<Vec<_>>();
+        // the reverse path `d` skips the bubble
+        assert_eq!(seqs, vec![(&b"C"[..], 2), (&b""[..], 1), (&b"GT"[..], 1)]);
+
+        // the flanking nodes are on every path
+        assert_eq!(find_bubble(&graph, Node::from(0u32), 10), None);
+    }
+
+    #[test]
+    fn dot_matrix_diagonal() {
+        let m = dot_matrix(b"ACGTTGCA", b"acgttgca", 3, 100);
+        assert_eq!((m.rows, m.cols), (8, 8));
+        for i in 0..6 {
+            assert!(m.get(i, i));
+        }
+        assert!(!m.get(0, 5));
+
+        let binned = dot_matrix(&[b'A'; 40], &[b'A'; 20], 1, 10);
+        assert_eq!((binned.rows, binned.cols), (10, 10));
+        assert!(binned.cells.iter().all(|&c| c));
+    }
+}
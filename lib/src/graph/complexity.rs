@@ -0,0 +1,242 @@
+//! Finding regions of the graph that are likely to be interesting to
+//! look at: regions with many bubbles, and long insertions relative
+//! to a reference path.
+//!
+//! The pangenome is split into fixed-length windows, which are scored
+//! by the number of edges in excess of a linear chain of the window's
+//! nodes (each bubble adds at least one), and by the longest run of
+//! nodes not on the reference path. The highest scoring windows are
+//! merged with their neighbors into regions.
+
+use super::nonref::{non_reference_runs, reference_node_set};
+use super::{Bp, Node, PathId, PathIndex};
+
+#[derive(Debug, Clone)]
+pub struct ComplexityParams {
+    pub window: Bp,
+    /// Path to measure insertions against; if `None`, only bubbles
+    /// are used for scoring
+    pub reference: Option<PathId>,
+    /// Runs of non-reference nodes shorter than this are ignored
+    pub min_insertion: Bp,
+    pub max_regions: usize,
+}
+
+impl Default for ComplexityParams {
+    fn default() -> Self {
+        Self {
+            window: Bp(10_000),
+            reference: None,
+            min_insertion: Bp(50),
+            max_regions: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidateRegion {
+    pub range: std::ops::Range<Bp>,
+    /// In `0.0..=2.0`; bubbles and insertions contribute up to 1.0
+    /// each, relative to the highest scoring window
+    pub score: f64,
+    pub excess_edges: usize,
+    pub longest_insertion: Bp,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct WindowStats {
+    nodes: usize,
+    edges: usize,
+    longest_insertion: u64,
+}
+
+impl WindowStats {
+    fn excess_edges(&self) -> usize {
+        (self.edges + 1).saturating_sub(self.nodes)
+    }
+}
+
+/// Returns up to `params.max_regions` candidate regions, sorted by
+/// decreasing score.
+pub fn find_complex_regions(
+    graph: &PathIndex,
+    params: &ComplexityParams,
+) -> Vec<CandidateRegion> {
+    let total_len = graph.pangenome_len().0;
+    let window = params.window.0.max(1);
+
+    if total_len == 0 {
+        return Vec::new();
+    }
+
+    let window_count = ((total_len + window - 1) / window) as usize;
+    let mut windows = vec![WindowStats::default(); window_count];
+
+    let window_of = |node: Node| -> usize {
+        let offset = graph.node_offset(node).0;
+        ((offset / window) as usize).min(window_count - 1)
+    };
+
+    for ix in 0..graph.node_count {
+        windows[window_of(Node::from(ix as u32))].nodes += 1;
+    }
+
+    for edge in graph.edges_iter() {
+        windows[window_of(edge.from.node())].edges += 1;
+    }
+
+    if let Some(reference) = params.reference {
+        let ref_nodes = reference_node_set(graph, &[reference]);
+
+        // insertions are attributed to the window they start in
+        for (nodes, len) in non_reference_runs(graph, &ref_nodes) {
+            if len >= params.min_insertion {
+                let w = &mut windows[window_of(*nodes.start())];
+                w.longest_insertion = w.longest_insertion.max(len.0);
+            }
+        }
+    }
+
+    let max_excess =
+        windows.iter().map(|w| w.excess_edges()).max().unwrap_or(0);
+    let max_insertion = windows
+        .iter()
+        .map(|w| w.longest_insertion)
+        .max()
+        .unwrap_or(0);
+
+    let norm = |v: f64, max: f64| if max > 0.0 { v / max } else { 0.0 };
+
+    let scores = windows
+        .iter()
+        .map(|w| {
+            norm(w.excess_edges() as f64, max_excess as f64)
+                + norm(w.longest_insertion as f64, max_insertion as f64)
+        })
+        .collect::<Vec<_>>();
+
+    let mut ranked = (0..window_count)
+        .filter(|&ix| scores[ix] > 0.0)
+        .collect::<Vec<_>>();
+    ranked.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]).then(a.cmp(&b)));
+
+    // windows as (first, last) index ranges, merged with already
+    // selected neighboring windows
+    let mut regions: Vec<(usize, usize)> = Vec::new();
+
+    for ix in ranked {
+        if let Some(region) = regions
+            .iter_mut()
+            .find(|(first, last)| ix + 1 == *first || ix == *last + 1)
+        {
+            region.0 = region.0.min(ix);
+            region.1 = region.1.max(ix);
+        } else if regions.len() < params.max_regions {
+            regions.push((ix, ix));
+        }
+    }
+
+    let mut result = regions
+        .into_iter()
+        .map(|(first, last)| {
+            let stats = &windows[first..=last];
+
+            let start = first as u64 * window;
+            let end = ((last as u64 + 1) * window).min(total_len);
+
+            let score =
+                scores[first..=last].iter().copied().fold(0.0, f64::max);
+
+            CandidateRegion {
+                range: Bp(start)..Bp(end),
+                score,
+                excess_edges: stats.iter().map(|w| w.excess_edges()).sum(),
+                longest_insertion: Bp(stats
+                    .iter()
+                    .map(|w| w.longest_insertion)
+                    .max()
+                    .unwrap_or(0)),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    result.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.range.start.cmp(&b.range.start))
+    });
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complex_regions_ranked() {
+        let graph = PathIndex::from_gfa(super::super::tests::GFA_PATH).unwrap();
+
+        let params = ComplexityParams {
+            window: Bp(1_000),
+            reference: Some(PathId::from(0u32)),
+            max_regions: 10,
+            ..ComplexityParams::default()
+        };
+
+        let regions = find_complex_regions(&graph, &params);
+
+        assert!(!regions.is_empty());
+        assert!(regions.len() <= 10);
+
+        for pair in regions.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+        }
+
+        let mut ranges =
+            regions.iter().map(|r| r.range.clone()).collect::<Vec<_>>();
+        ranges.sort_by_key(|r| r.start);
+
+        for pair in ranges.windows(2) {
+            assert!(pair[0].end <= pair[1].start);
+        }
+
+        assert!(ranges.last().unwrap().end <= graph.pangenome_len());
+    }
+
+    #[test]
+    fn complex_regions_insertion() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tC
+S\t3\tG
+S\t4\t\
+TTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTT
+S\t5\tAAAA
+L\t1\t+\t2\t+\t0M
+L\t1\t+\t3\t+\t0M
+L\t2\t+\t5\t+\t0M
+L\t3\t+\t4\t+\t0M
+L\t4\t+\t5\t+\t0M
+P\tref\t1+,2+,5+\t*
+P\talt\t1+,3+,4+,5+\t*
+";
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let reference = graph.path_names.get_by_right("ref").copied();
+
+        let params = ComplexityParams {
+            window: Bp(1_000),
+            reference,
+            ..ComplexityParams::default()
+        };
+
+        let regions = find_complex_regions(&graph, &params);
+
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].range, Bp(0)..graph.pangenome_len());
+        assert_eq!(regions[0].longest_insertion, Bp(61));
+        assert_eq!(regions[0].excess_edges, 1);
+    }
+}
@@ -0,0 +1,166 @@
+//! Consensus walks: the traversal of a pangenome range that's best
+//! supported by the paths, e.g. to pick a representative allele.
+
+use std::io::prelude::*;
+
+use super::{reverse_complement, Bp, Node, OrientedNode, PathIndex};
+
+impl PathIndex {
+    /// The walk through the nodes overlapping the pangenome range with
+    /// the highest total weight, where a node weighs its length times
+    /// the number of paths on it. Only edges between nodes in the
+    /// range are followed, in pangenome order, so the walk never goes
+    /// back along the range. Empty if the range is.
+    pub fn consensus_walk(
+        &self,
+        range: std::ops::Range<Bp>,
+    ) -> Vec<OrientedNode> {
+        let end = range.end.min(self.pangenome_len());
+        if range.start >= end {
+            return Vec::new();
+        }
+
+        let nodes = self.pos_range_nodes(range.start.0..end.0 - 1);
+        let first = nodes.start().ix();
+        let last = nodes.end().ix();
+
+        // states are the nodes in the range, in either orientation
+        let state = |step: OrientedNode| {
+            (step.node().ix() - first) * 2 + step.is_reverse() as usize
+        };
+        let state_count = (last - first + 1) * 2;
+
+        let mut preds: Vec<Vec<usize>> = vec![Vec::new(); state_count];
+
+        for edge in self.edges_iter() {
+            // an edge can be traversed either way; follow it toward the
+            // end of the range
+            let (from, to) = if edge.from.node() < edge.to.node() {
+                (edge.from, edge.to)
+            } else if edge.to.node() < edge.from.node() {
+                (edge.to.flip(), edge.from.flip())
+            } else {
+                continue;
+            };
+
+            if from.node().ix() < first || to.node().ix() > last {
+                continue;
+            }
+            preds[state(to)].push(state(from));
+        }
+
+        let mut best = vec![0u64; state_count];
+        let mut back: Vec<Option<usize>> = vec![None; state_count];
+
+        for s in 0..state_count {
+            let node = Node::from(first + s / 2);
+            let support = self.paths_on_node(node).map_or(0, |p| p.count());
+            let weight = support as u64 * self.node_length(node).0;
+
+            // predecessors are earlier in the range, so already done
+            let pred = preds[s].iter().copied().max_by_key(|&p| best[p]);
+            best[s] = weight + pred.map_or(0, |p| best[p]);
+            back[s] = pred;
+        }
+
+        // ties go to the first state, i.e. the forward orientation
+        let Some(mut s) = (0..state_count).rev().max_by_key(|&s| best[s])
+        else {
+            return Vec::new();
+        };
+
+        let step =
+            |s: usize| OrientedNode::new((first + s / 2) as u32, s % 2 == 1);
+
+        let mut walk = vec![step(s)];
+        while let Some(pred) = back[s] {
+            s = pred;
+            walk.push(step(s));
+        }
+        walk.reverse();
+
+        walk
+    }
+
+    /// The sequence spelled by the walk.
+    pub fn walk_sequence(&self, steps: &[OrientedNode]) -> Vec<u8> {
+        let mut seq = Vec::new();
+
+        for step in steps {
+            let node_seq = self.node_sequence(step.node());
+            if step.is_reverse() {
+                seq.extend(reverse_complement(node_seq));
+            } else {
+                seq.extend_from_slice(node_seq);
+            }
+        }
+
+        seq
+    }
+
+    /// Writes the walk as a GAF line of a sequence named `name` aligned
+    /// exactly along it, which `gaf::read_gaf_paths` reads back as a
+    /// path.
+    pub fn write_gaf_walk(
+        &self,
+        name: &str,
+        steps: &[OrientedNode],
+        mut out: impl Write,
+    ) -> std::io::Result<()> {
+        let len: u64 = steps.iter().map(|s| self.node_length(s.node()).0).sum();
+        let walk = self.format_walk(steps);
+
+        // query and path coordinates are the same, and every base
+        // matches
+        writeln!(
+            out,
+            "{name}\t{len}\t0\t{len}\t+\t{walk}\t\
+             {len}\t0\t{len}\t{len}\t{len}\t60"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::gaf;
+
+    #[test]
+    fn consensus_follows_most_supported_allele() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tC
+S\t3\tG
+S\t4\tTT
+L\t1\t+\t2\t+\t0M
+L\t1\t+\t3\t+\t0M
+L\t2\t+\t4\t+\t0M
+L\t3\t+\t4\t+\t0M
+P\ta\t1+,2+,4+\t*
+P\tb\t4-,2-,1-\t*
+P\tc\t1+,3+,4+\t*
+";
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let node = |id: u64| graph.segment_node(id).unwrap().ix() as u32;
+        let walk = graph.consensus_walk(Bp(0)..graph.pangenome_len());
+        let expected = [1, 2, 4]
+            .map(|id| OrientedNode::new(node(id), false))
+            .to_vec();
+        assert_eq!(walk, expected);
+        assert_eq!(graph.walk_sequence(&walk), b"AAAACTT");
+
+        // starting inside the bubble
+        let start = graph.node_offset(Node::from(node(2))).0;
+        let walk = graph.consensus_walk(Bp(start)..graph.pangenome_len());
+        assert_eq!(walk.first(), Some(&OrientedNode::new(node(2), false)));
+
+        let mut line = Vec::new();
+        graph
+            .write_gaf_walk("consensus", &expected, &mut line)
+            .unwrap();
+        let paths = gaf::read_gaf_paths(&graph, line.as_slice()).unwrap();
+        assert_eq!(paths, vec![("consensus".to_string(), expected)]);
+    }
+}
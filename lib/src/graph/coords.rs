@@ -0,0 +1,218 @@
+//! Coordinate conventions for positions shown to and entered by the
+//! user. Internally, ranges are always 0-based and half-open; the
+//! convention only changes how they're formatted and parsed.
+
+use std::ops::Range;
+
+use super::{Bp, Node, PathId, PathIndex};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CoordSystem {
+    /// As in BED: the first base is 0, and the end is excluded
+    #[default]
+    ZeroBased,
+    /// As in GFF and SAM: the first base is 1, and the end is included
+    OneBased,
+}
+
+impl CoordSystem {
+    pub const ALL: [CoordSystem; 2] =
+        [CoordSystem::ZeroBased, CoordSystem::OneBased];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CoordSystem::ZeroBased => "0-based, half-open",
+            CoordSystem::OneBased => "1-based, closed",
+        }
+    }
+
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            CoordSystem::ZeroBased => "0-based",
+            CoordSystem::OneBased => "1-based",
+        }
+    }
+
+    fn offset(&self) -> u64 {
+        match self {
+            CoordSystem::ZeroBased => 0,
+            CoordSystem::OneBased => 1,
+        }
+    }
+
+    /// The displayed position of the base at `pos`.
+    pub fn display_pos(&self, pos: Bp) -> u64 {
+        pos.0 + self.offset()
+    }
+
+    /// The displayed start and end of `range`. The end is the same in
+    /// both conventions, as a closed range ends at the last base.
+    pub fn display_range(&self, range: &Range<Bp>) -> (u64, u64) {
+        (self.display_pos(range.start), range.end.0)
+    }
+
+    /// Formats `range` as `start-end`.
+    pub fn format_range(&self, range: &Range<Bp>) -> String {
+        let (start, end) = self.display_range(range);
+        format!("{start}-{end}")
+    }
+
+    /// Converts a displayed range to a 0-based half-open range; `None`
+    /// if a 1-based start is 0, or the range is empty.
+    pub fn from_display_range(
+        &self,
+        start: u64,
+        end: u64,
+    ) -> Option<Range<Bp>> {
+        let start = start.checked_sub(self.offset())?;
+        (start < end).then(|| Bp(start)..Bp(end))
+    }
+
+    /// Parses `start-end`, or a single position, as displayed in this
+    /// convention.
+    pub fn parse_range(&self, text: &str) -> Option<Range<Bp>> {
+        if let Some((from, to)) = text.split_once('-') {
+            let from = from.trim().parse::<u64>().ok()?;
+            let to = to.trim().parse::<u64>().ok()?;
+            self.from_display_range(from, to)
+        } else {
+            let pos = text.trim().parse::<u64>().ok()?;
+            let start = pos.checked_sub(self.offset())?;
+            Some(Bp(start)..Bp(start + 1))
+        }
+    }
+
+    /// A comment line for the top of exported tables, naming the
+    /// convention their coordinates use.
+    pub fn header_comment(&self) -> String {
+        format!("# coordinates: {}", self.name())
+    }
+
+    /// Reads the convention from a line written by `header_comment`.
+    pub fn from_header_comment(line: &str) -> Option<Self> {
+        let name = line.strip_prefix("# coordinates: ")?.trim();
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+}
+
+/// A pangenome position projected onto a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathProjection {
+    pub pos: Bp,
+    /// False if the path doesn't visit the base, in which case `pos`
+    /// is where the path leaves the closest node before it that it
+    /// does visit
+    pub exact: bool,
+}
+
+/// Projects the base at pangenome position `pos` onto `path`, using
+/// the path's first visit to each node; `None` if the path visits no
+/// node at or before the base.
+pub fn project_to_path(
+    graph: &PathIndex,
+    path: PathId,
+    pos: Bp,
+) -> Option<PathProjection> {
+    let node = graph.node_at_pangenome_pos(pos)?;
+
+    let path_nodes = graph.path_node_sets.get(path.ix())?;
+    let rank = path_nodes.rank(node.ix() as u32) as u32;
+    let prev = Node::from(path_nodes.select(rank.checked_sub(1)?)?);
+
+    let (n_start, n_len) = graph.node_offset_length(prev);
+    let (step, offset) = graph.node_path_step_offsets(prev, path)?.next()?;
+
+    // on reverse steps the path runs from the node's end
+    let reverse = graph.path_steps[path.ix()][step].is_reverse();
+
+    if prev == node {
+        let inner = pos.0 - n_start.0;
+        let inner = if reverse { n_len.0 - inner - 1 } else { inner };
+        Some(PathProjection {
+            pos: Bp(offset.0 + inner),
+            exact: true,
+        })
+    } else {
+        let end = if reverse {
+            offset.0
+        } else {
+            offset.0 + n_len.0
+        };
+        Some(PathProjection {
+            pos: Bp(end),
+            exact: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projection_onto_path() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tCC
+S\t3\tG
+S\t4\tTTTT
+P\tref\t1+,3+,4+\t*
+P\trev\t4-,1-\t*
+";
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let ref_path = *graph.path_names.get_by_right("ref").unwrap();
+        let rev_path = *graph.path_names.get_by_right("rev").unwrap();
+
+        let project = |path, pos| project_to_path(&graph, path, Bp(pos));
+
+        // node 3 follows node 1 on the reference
+        let exact = |pos| {
+            Some(PathProjection {
+                pos: Bp(pos),
+                exact: true,
+            })
+        };
+        assert_eq!(project(ref_path, 6), exact(4));
+        assert_eq!(project(ref_path, 8), exact(6));
+
+        // node 2 is skipped, so it maps to the end of node 1
+        let skipped = Some(PathProjection {
+            pos: Bp(4),
+            exact: false,
+        });
+        assert_eq!(project(ref_path, 5), skipped);
+
+        // the last base of node 4 is the first of the reverse path
+        assert_eq!(project(rev_path, 10), exact(0));
+        assert_eq!(project(rev_path, 0), exact(7));
+
+        assert_eq!(project(ref_path, 11), None);
+    }
+
+    #[test]
+    fn coord_round_trip() {
+        let range = Bp(99)..Bp(200);
+
+        let zero = CoordSystem::ZeroBased;
+        let one = CoordSystem::OneBased;
+
+        assert_eq!(zero.format_range(&range), "99-200");
+        assert_eq!(one.format_range(&range), "100-200");
+
+        for coords in CoordSystem::ALL {
+            let text = coords.format_range(&range);
+            assert_eq!(coords.parse_range(&text), Some(range.clone()));
+
+            let header = coords.header_comment();
+            assert_eq!(CoordSystem::from_header_comment(&header), Some(coords));
+        }
+
+        assert_eq!(one.parse_range("100"), Some(Bp(99)..Bp(100)));
+        assert_eq!(zero.parse_range("100"), Some(Bp(100)..Bp(101)));
+        assert_eq!(one.parse_range("0-10"), None);
+        assert_eq!(one.parse_range("10-9"), None);
+        assert_eq!(one.parse_range("10-10"), Some(Bp(9)..Bp(10)));
+    }
+}
@@ -0,0 +1,270 @@
+//! Adding paths to a loaded graph from sequences aligned to it, in
+//! GAF format (e.g. from GraphAligner or minigraph).
+//!
+//! Only the node walks of the alignments are used: each aligned
+//! sequence becomes a path over the whole nodes it was aligned to, in
+//! the order of the alignments along the sequence. Overlapping
+//! alignments of the same sequence are skipped, and walks must use
+//! node IDs (`>12<13`), not stable coordinates.
+
+use std::collections::BTreeMap;
+use std::io::prelude::*;
+
+use roaring::{RoaringBitmap, RoaringTreemap};
+
+use super::{OrientedNode, PathId, PathIndex};
+
+fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GafAlignment {
+    query: String,
+    query_start: u64,
+    query_end: u64,
+    steps: Vec<OrientedNode>,
+}
+
+/// Parses a walk like `>12<13>14` into steps on the graph's nodes.
+fn parse_walk(
+    graph: &PathIndex,
+    walk: &str,
+) -> std::io::Result<Vec<OrientedNode>> {
+    if !walk.starts_with(['>', '<']) {
+        return Err(invalid_data(format!(
+            "GAF path `{walk}` uses stable coordinates, which aren't supported"
+        )));
+    }
+
+    let mut steps = Vec::new();
+    let mut rest = walk;
+
+    while let Some(orient) = rest.chars().next() {
+        let tail = &rest[1..];
+        let end = tail.find(['>', '<']).unwrap_or(tail.len());
+        let id = &tail[..end];
+        rest = &tail[end..];
+
+        let node = id
+            .parse::<u64>()
+            .ok()
+            .and_then(|id| graph.segment_node(id))
+            .ok_or_else(|| {
+                invalid_data(format!("Unknown segment `{id}` in GAF path"))
+            })?;
+
+        steps.push(OrientedNode::new(node.ix() as u32, orient == '<'));
+    }
+
+    Ok(steps)
+}
+
+fn parse_gaf_line(
+    graph: &PathIndex,
+    line: &str,
+) -> std::io::Result<Option<GafAlignment>> {
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let fields = line.split('\t').collect::<Vec<_>>();
+    if fields.len() < 12 {
+        return Err(invalid_data(format!(
+            "GAF line has {} columns, expected at least 12",
+            fields.len()
+        )));
+    }
+
+    // unaligned sequences are reported with `*` as the path
+    if fields[5] == "*" {
+        return Ok(None);
+    }
+
+    let int = |ix: usize| {
+        fields[ix].parse::<u64>().map_err(|_| {
+            invalid_data(format!(
+                "Invalid GAF column {}: {}",
+                ix + 1,
+                fields[ix]
+            ))
+        })
+    };
+
+    let mut steps = parse_walk(graph, fields[5])?;
+
+    // the query aligned to the reverse of the walk
+    if fields[4] == "-" {
+        steps = steps.into_iter().rev().map(OrientedNode::flip).collect();
+    }
+
+    Ok(Some(GafAlignment {
+        query: fields[0].to_string(),
+        query_start: int(2)?,
+        query_end: int(3)?,
+        steps,
+    }))
+}
+
+/// Reads the alignments in the GAF, and chains the alignments of each
+/// sequence, in query order, into the steps of a path named after the
+/// sequence.
+pub fn read_gaf_paths(
+    graph: &PathIndex,
+    gaf: impl BufRead,
+) -> std::io::Result<Vec<(String, Vec<OrientedNode>)>> {
+    let mut alignments: BTreeMap<String, Vec<GafAlignment>> = BTreeMap::new();
+
+    for line in gaf.lines() {
+        if let Some(aln) = parse_gaf_line(graph, line?.trim_end())? {
+            alignments.entry(aln.query.clone()).or_default().push(aln);
+        }
+    }
+
+    let paths = alignments
+        .into_iter()
+        .map(|(name, mut alns)| {
+            alns.sort_by_key(|aln| (aln.query_start, aln.query_end));
+
+            let mut steps: Vec<OrientedNode> = Vec::new();
+            let mut query_end = 0;
+
+            for aln in alns {
+                if aln.query_start < query_end {
+                    continue;
+                }
+                query_end = aln.query_end;
+
+                // consecutive alignments often meet inside a node
+                let skip = (steps.last() == aln.steps.first()) as usize;
+                steps.extend(aln.steps.into_iter().skip(skip));
+            }
+
+            (name, steps)
+        })
+        .filter(|(_, steps)| !steps.is_empty())
+        .collect();
+
+    Ok(paths)
+}
+
+impl PathIndex {
+    /// Adds a path with the given steps to the graph, returning its
+    /// ID; the name must not already be used.
+    pub fn add_path(
+        &mut self,
+        name: &str,
+        steps: Vec<OrientedNode>,
+    ) -> std::io::Result<PathId> {
+        if self.path_names.contains_right(name) {
+            return Err(invalid_data(format!("Path `{name}` already exists")));
+        }
+        if steps.is_empty() {
+            return Err(invalid_data(format!("Path `{name}` has no steps")));
+        }
+
+        let path_id = PathId::from(self.path_steps.len());
+
+        let mut offsets = RoaringTreemap::new();
+        let mut path_nodes = RoaringBitmap::new();
+        let mut pos = 0;
+
+        for (step_ix, step) in steps.iter().enumerate() {
+            offsets.push(pos);
+            path_nodes.insert(step.node().ix() as u32);
+
+            self.node_path_steps[step.node().ix()]
+                .entry(path_id)
+                .or_default()
+                .push(step_ix as u32);
+
+            pos += self.node_length(step.node()).0;
+        }
+
+        self.path_names.insert(path_id, name.to_string());
+        self.path_steps.push(steps);
+        self.path_step_offsets.push(offsets);
+        self.path_node_sets.push(path_nodes);
+
+        Ok(path_id)
+    }
+
+    /// Adds a path for each sequence aligned in the GAF file, skipping
+    /// those whose names are already used by a path.
+    pub fn add_gaf_paths(
+        &mut self,
+        gaf_path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<Vec<PathId>> {
+        let gaf = std::fs::File::open(gaf_path).map(std::io::BufReader::new)?;
+
+        let mut added = Vec::new();
+
+        for (name, steps) in read_gaf_paths(self, gaf)? {
+            if self.path_names.contains_right(&name) {
+                log::warn!("Skipping aligned sequence `{name}`: path exists");
+                continue;
+            }
+            added.push(self.add_path(&name, steps)?);
+        }
+
+        Ok(added)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::tests::GFA_PATH;
+    use crate::graph::Bp;
+
+    fn gaf_line(query: &str, start: u64, end: u64, walk: &str) -> String {
+        format!(
+            "{query}\t100\t{start}\t{end}\t+\t{walk}\t50\t0\t50\t40\t50\t60"
+        )
+    }
+
+    #[test]
+    fn gaf_alignments_become_paths() {
+        let mut graph = PathIndex::from_gfa(GFA_PATH).unwrap();
+        let path_count = graph.path_names.len();
+
+        let gaf = [
+            gaf_line("sample", 20, 40, ">3>4"),
+            gaf_line("sample", 0, 20, ">1>2>3"),
+            // overlaps the first alignment
+            gaf_line("sample", 10, 30, ">7"),
+            gaf_line("other", 0, 10, "<2<1"),
+            "unaligned\t100\t0\t0\t*\t*\t0\t0\t0\t0\t0\t0".to_string(),
+        ]
+        .join("\n");
+
+        let paths = read_gaf_paths(&graph, gaf.as_bytes()).unwrap();
+        let names = paths.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["other", "sample"]);
+
+        let node = |id: u64| graph.segment_node(id).unwrap().ix() as u32;
+        let expected = [1, 2, 3, 4]
+            .map(|id| OrientedNode::new(node(id), false))
+            .to_vec();
+        assert_eq!(paths[1].1, expected);
+        assert!(paths[0].1.iter().all(|step| step.is_reverse()));
+
+        for (name, steps) in paths {
+            graph.add_path(&name, steps).unwrap();
+        }
+        assert_eq!(graph.path_names.len(), path_count + 2);
+
+        let sample = *graph.path_names.get_by_right("sample").unwrap();
+        let len = [1, 2, 3, 4]
+            .map(|id| graph.node_length(graph.segment_node(id).unwrap()).0)
+            .iter()
+            .sum::<u64>();
+        assert_eq!(graph.path_len(sample), Some(Bp(len)));
+
+        let first = graph.segment_node(1).unwrap();
+        assert!(graph.node_path_steps(first, sample).is_some());
+
+        assert!(graph.add_path("sample", expected).is_err());
+        assert!(parse_walk(&graph, "chr1:0-100").is_err());
+    }
+}
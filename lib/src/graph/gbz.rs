@@ -0,0 +1,250 @@
+//! Building a `PathIndex` from a GBZ file, the combined GBWT and
+//! graph format used by vg and the Minigraph-Cactus pipeline.
+
+use bimap::BiBTreeMap;
+use roaring::{RoaringBitmap, RoaringTreemap};
+use std::collections::HashMap;
+use std::path::Path;
+
+use gbwt::{Orientation, GBZ};
+use simple_sds::serialize;
+
+use super::{Bp, Edge, OrientedNode, PathId, PathIndex};
+
+/// The sample name GBWT uses for generic (non-haplotype) paths, such
+/// as reference paths converted from GFA P-lines.
+const REF_SAMPLE: &str = "_gbwt_ref";
+
+/// Whether the file should be loaded as GBZ rather than GFA, going
+/// by its extension.
+pub fn is_gbz_file(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("gbz"))
+}
+
+/// Formats a GBWT path name using the PanSN convention, e.g.
+/// `HG002#1#chr20`; generic paths are named by their contig only.
+/// Nonzero fragments (e.g. haplotype blocks split by gaps) are
+/// appended in brackets, so that the names stay unique.
+fn path_name(
+    sample: &str,
+    phase: usize,
+    contig: &str,
+    fragment: usize,
+) -> String {
+    let mut name = if sample == REF_SAMPLE {
+        contig.to_string()
+    } else {
+        format!("{sample}#{phase}#{contig}")
+    };
+
+    if fragment != 0 {
+        name.push_str(&format!("[{fragment}]"));
+    }
+
+    name
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+impl PathIndex {
+    pub fn from_gbz(gbz_path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let gbz: GBZ = serialize::load_from(gbz_path.as_ref())?;
+
+        let node_ids = gbz.node_iter().collect::<Vec<_>>();
+
+        let (Some(&min_id), Some(&max_id)) =
+            (node_ids.first(), node_ids.last())
+        else {
+            return Err(invalid_data("GBZ graph has no nodes".to_string()));
+        };
+
+        if max_id - min_id != node_ids.len() - 1 {
+            return Err(invalid_data(format!(
+                "GBZ node IDs must be tightly packed: min ID {min_id}, \
+                 max ID {max_id}, node count {}",
+                node_ids.len()
+            )));
+        }
+
+        let node_count = node_ids.len();
+        let seg_id_range = (min_id as u32, max_id as u32);
+
+        let oriented = |id: usize, orient: Orientation| {
+            OrientedNode::new(
+                (id - min_id) as u32,
+                orient == Orientation::Reverse,
+            )
+        };
+
+        let mut segment_offsets = RoaringTreemap::new();
+        let mut seg_lens = Vec::with_capacity(node_count);
+        let mut sequence = Vec::new();
+
+        let mut edges = Vec::new();
+
+        for &node_id in node_ids.iter() {
+            let seq = gbz.sequence(node_id).unwrap_or_default();
+
+            segment_offsets.push(sequence.len() as u64);
+            seg_lens.push(seq.len());
+            sequence.extend_from_slice(seq);
+
+            // each edge is stored in both orientations, only keep
+            // one of them
+            for orient in [Orientation::Forward, Orientation::Reverse] {
+                let from = oriented(node_id, orient);
+
+                for (to_id, to_orient) in
+                    gbz.successors(node_id, orient).into_iter().flatten()
+                {
+                    if !gbz.has_node(to_id) {
+                        continue;
+                    }
+
+                    let to = oriented(to_id, to_orient);
+
+                    let edge = Edge::new(from, to);
+                    let flipped = Edge::new(to.flip(), from.flip());
+                    edges.push(edge.min(flipped));
+                }
+            }
+        }
+
+        edges.sort();
+        edges.dedup();
+
+        let sequence_total_len = sequence.len();
+
+        let metadata = gbz.metadata().filter(|m| m.has_path_names());
+
+        let mut path_names = BiBTreeMap::default();
+
+        let mut path_steps: Vec<Vec<OrientedNode>> = Vec::new();
+        let mut path_step_offsets: Vec<RoaringTreemap> = Vec::new();
+        let mut path_node_sets: Vec<RoaringBitmap> = Vec::new();
+
+        let mut node_path_steps: Vec<HashMap<PathId, Vec<u32>>> =
+            vec![HashMap::default(); node_count];
+
+        for gbz_path_id in 0..gbz.paths() {
+            let Some(steps) = gbz.path(gbz_path_id, Orientation::Forward)
+            else {
+                continue;
+            };
+
+            let name = metadata
+                .and_then(|m| {
+                    let p = m.path(gbz_path_id)?;
+                    Some(path_name(
+                        &m.sample_name(p.sample()),
+                        p.phase(),
+                        &m.contig_name(p.contig()),
+                        p.fragment(),
+                    ))
+                })
+                .unwrap_or_else(|| format!("path{gbz_path_id}"));
+
+            let path_id = PathId::from(path_steps.len());
+            path_names.insert(path_id, name);
+
+            let mut pos = 0;
+
+            let mut parsed_steps = Vec::new();
+
+            let mut offsets = RoaringTreemap::new();
+            let mut path_nodes = RoaringBitmap::new();
+
+            for (node_id, orient) in steps {
+                let step = oriented(node_id, orient);
+                let seg_ix = step.node().ix();
+
+                let step_index = parsed_steps.len();
+
+                parsed_steps.push(step);
+                offsets.push(pos as u64);
+                path_nodes.insert(seg_ix as u32);
+
+                node_path_steps[seg_ix]
+                    .entry(path_id)
+                    .or_default()
+                    .push(step_index as u32);
+
+                pos += seg_lens[seg_ix];
+            }
+
+            path_steps.push(parsed_steps);
+            path_step_offsets.push(offsets);
+            path_node_sets.push(path_nodes);
+        }
+
+        Ok(Self {
+            sequence,
+
+            path_names,
+            path_steps,
+            path_step_offsets,
+            path_node_sets,
+
+            node_path_steps,
+
+            segment_offsets,
+            node_count,
+            segment_id_range: seg_id_range,
+            sequence_total_len: Bp(sequence_total_len as u64),
+
+            edges,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gbz_path_names() {
+        assert_eq!(path_name("HG002", 1, "chr20", 0), "HG002#1#chr20");
+        assert_eq!(path_name(REF_SAMPLE, 0, "chr20", 0), "chr20");
+        assert_eq!(path_name("HG002", 2, "chr20", 3), "HG002#2#chr20[3]");
+
+        assert!(is_gbz_file("data/graph.gbz"));
+        assert!(is_gbz_file("data/graph.GBZ"));
+        assert!(!is_gbz_file("data/graph.gfa"));
+    }
+
+    #[test]
+    fn gbz_load() {
+        // 5 nodes (GAT, T, A, CAG, TA) forming a bubble, a reference
+        // path and two haplotypes, the second of them on the reverse
+        // strand
+        let gbz_path =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../test/data/small.gbz");
+        let index = PathIndex::from_gbz(gbz_path).unwrap();
+
+        assert_eq!(index.node_count, 5);
+        assert_eq!(index.segment_id_range, (1, 5));
+        assert_eq!(index.pangenome_len(), Bp(10));
+        assert_eq!(index.sequence, b"GATTACAGTA");
+        assert_eq!(index.edges.len(), 5);
+
+        let names = index
+            .path_names
+            .iter()
+            .map(|(_, name)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["chr1", "HG1#1#chr1", "HG1#2#chr1"]);
+
+        let steps = &index.path_steps[2];
+        assert_eq!(steps.len(), 4);
+        assert_eq!(steps[0].node().ix(), 4);
+        assert!(steps.iter().all(|step| step.is_reverse()));
+
+        for &path in index.path_names.left_values() {
+            assert_eq!(index.path_len(path), Some(Bp(9)));
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Writing the whole graph as GFA, e.g. to save paths that were added
+//! after loading (see `gaf`).
+
+use std::io::prelude::*;
+
+use super::{Node, OrientedNode, PathId, PathIndex};
+
+/// Splits a PanSN path name, `sample#haplotype#contig`, into its
+/// parts, if the haplotype is a number.
+fn pansn_parts(name: &str) -> Option<(&str, u64, &str)> {
+    let mut parts = name.splitn(3, '#');
+    let sample = parts.next()?;
+    let hap = parts.next()?.parse().ok()?;
+    let contig = parts.next()?;
+
+    let valid = |s: &str| !s.is_empty() && !s.contains(char::is_whitespace);
+    (valid(sample) && valid(contig)).then_some((sample, hap, contig))
+}
+
+impl PathIndex {
+    /// Writes the graph as GFA 1.1, with the original segment IDs: a
+    /// header, the `comments` as `#` lines (e.g. where the graph came
+    /// from), the segments, links and paths. Overlaps aren't kept, so
+    /// links are written with `0M`.
+    ///
+    /// If `walks` is set, paths with PanSN names are written as W
+    /// lines, covering the whole contig; Waragraph itself only reads
+    /// P lines.
+    pub fn write_gfa(
+        &self,
+        comments: &[String],
+        walks: bool,
+        mut out: impl Write,
+    ) -> std::io::Result<()> {
+        writeln!(out, "H\tVN:Z:1.1")?;
+
+        for comment in comments {
+            for line in comment.lines() {
+                writeln!(out, "# {line}")?;
+            }
+        }
+
+        for ix in 0..self.node_count {
+            let node = Node::from(ix);
+            write!(out, "S\t{}\t", self.segment_id(node))?;
+            out.write_all(self.node_sequence(node))?;
+            writeln!(out)?;
+        }
+
+        let orient = |reverse: bool| if reverse { '-' } else { '+' };
+
+        for edge in self.edges_iter() {
+            let (from, to) = (edge.from, edge.to);
+            writeln!(
+                out,
+                "L\t{}\t{}\t{}\t{}\t0M",
+                self.segment_id(from.node()),
+                orient(from.is_reverse()),
+                self.segment_id(to.node()),
+                orient(to.is_reverse()),
+            )?;
+        }
+
+        for (ix, steps) in self.path_steps.iter().enumerate() {
+            let path = PathId::from(ix);
+            let name = self
+                .path_names
+                .get_by_left(&path)
+                .map(|n| n.as_str())
+                .unwrap_or("path");
+
+            match pansn_parts(name).filter(|_| walks) {
+                Some((sample, hap, contig)) => {
+                    let len = self.path_len(path).map_or(0, |len| len.0);
+                    let walk = self.format_walk(steps);
+                    writeln!(
+                        out,
+                        "W\t{sample}\t{hap}\t{contig}\t0\t{len}\t{walk}"
+                    )?;
+                }
+                None => {
+                    let steps = steps
+                        .iter()
+                        .map(|step| {
+                            format!(
+                                "{}{}",
+                                self.segment_id(step.node()),
+                                orient(step.is_reverse())
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    writeln!(out, "P\t{name}\t{steps}\t*")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Formats the steps as a GFA walk, e.g. `>12<13`.
+    pub fn format_walk(&self, steps: &[OrientedNode]) -> String {
+        steps
+            .iter()
+            .map(|step| {
+                let orient = if step.is_reverse() { '<' } else { '>' };
+                format!("{orient}{}", self.segment_id(step.node()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn augmented_graph_roundtrip() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tC
+S\t3\tG
+L\t1\t+\t2\t+\t0M
+L\t1\t+\t3\t-\t0M
+P\tref\t1+,2+\t*
+";
+        let mut graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let node = |id: u64| graph.segment_node(id).unwrap().ix() as u32;
+        let steps = vec![
+            OrientedNode::new(node(1), false),
+            OrientedNode::new(node(3), true),
+        ];
+        graph.add_path("HG002#1#chr1", steps.clone()).unwrap();
+
+        let comments = ["source: test.gfa".to_string()];
+
+        let mut out = Vec::new();
+        graph.write_gfa(&comments, true, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("# source: test.gfa\n"));
+        assert!(text.contains("L\t1\t+\t3\t-\t0M\n"));
+        assert!(text.contains("W\tHG002\t1\tchr1\t0\t5\t>1<3\n"));
+
+        let mut out = Vec::new();
+        graph.write_gfa(&comments, false, &mut out).unwrap();
+        let saved =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(out)).unwrap();
+
+        assert_eq!(saved.node_count, graph.node_count);
+        assert_eq!(saved.pangenome_len(), graph.pangenome_len());
+        let added = *saved.path_names.get_by_right("HG002#1#chr1").unwrap();
+        assert_eq!(saved.path_steps[added.ix()], steps);
+    }
+}
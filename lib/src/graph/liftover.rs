@@ -0,0 +1,226 @@
+//! Node ID translation between two builds of a graph, for carrying
+//! positions and node sets over to a rebuilt graph.
+//!
+//! The liftover file is tab-separated, one line per segment of the
+//! old graph whose ID changed:
+//!
+//! `old_id <TAB> new_id[,new_id...] [<TAB> offset]`
+//!
+//! A node that was split lists the new segments that make up its
+//! sequence, in order. Nodes that were merged each list the segment
+//! they were merged into, along with the offset of their sequence in
+//! it (0 if omitted). Nodes are assumed to keep their orientation.
+//! Segments that aren't in the file keep their ID.
+//!
+//! IDs are GFA segment names, so they don't depend on the order in
+//! which the graphs were loaded.
+
+use std::collections::HashMap;
+use std::io::prelude::*;
+
+use super::{Bp, Node, PathIndex};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LiftEntry {
+    new_ids: Vec<u64>,
+    // of the old node's start in the first new node
+    offset: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NodeLiftover {
+    entries: HashMap<u64, LiftEntry>,
+}
+
+impl NodeLiftover {
+    pub fn from_file(
+        path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Self::parse(std::io::BufReader::new(file))
+    }
+
+    pub fn parse(reader: impl BufRead) -> std::io::Result<Self> {
+        let mut entries = HashMap::default();
+
+        for (line_ix, line) in reader.lines().enumerate() {
+            let line = line?;
+            let invalid = |msg: &str| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Liftover line {}: {msg}", line_ix + 1),
+                )
+            };
+
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split('\t');
+
+            let old_id = fields
+                .next()
+                .and_then(|f| f.trim().parse::<u64>().ok())
+                .ok_or_else(|| invalid("expected an old segment ID"))?;
+
+            let new_ids = fields
+                .next()
+                .ok_or_else(|| invalid("expected the new segment IDs"))?
+                .split(',')
+                .map(|id| id.trim().parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid("invalid new segment ID"))?;
+
+            let offset = match fields.next() {
+                Some(offset) if !offset.trim().is_empty() => offset
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| invalid("invalid offset"))?,
+                _ => 0,
+            };
+
+            entries.insert(old_id, LiftEntry { new_ids, offset });
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Number of old segments with a new ID
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The new segments covering the old segment.
+    pub fn lift_id(&self, old_id: u64) -> Vec<u64> {
+        match self.entries.get(&old_id) {
+            Some(entry) => entry.new_ids.clone(),
+            None => vec![old_id],
+        }
+    }
+
+    /// Lifts the position `offset` bp into the old segment to a
+    /// position in a new segment, given the lengths of the new
+    /// segments.
+    pub fn lift_offset(
+        &self,
+        old_id: u64,
+        offset: u64,
+        new_len: impl Fn(u64) -> Option<u64>,
+    ) -> Option<(u64, u64)> {
+        let Some(entry) = self.entries.get(&old_id) else {
+            let len = new_len(old_id)?;
+            return Some((old_id, offset.min(len.saturating_sub(1))));
+        };
+
+        let mut remaining = entry.offset + offset;
+        let mut last = None;
+
+        for &new_id in entry.new_ids.iter() {
+            let len = new_len(new_id)?;
+            if remaining < len {
+                return Some((new_id, remaining));
+            }
+            remaining -= len;
+            last = Some((new_id, len.saturating_sub(1)));
+        }
+
+        // past the end of the new segments; the files disagree on the
+        // node length, so use the last base
+        last
+    }
+
+    /// The nodes of the new graph covering the node of the old graph
+    /// with the given segment ID.
+    pub fn lift_node(&self, old_id: u64, new: &PathIndex) -> Vec<Node> {
+        self.lift_id(old_id)
+            .into_iter()
+            .filter_map(|id| new.segment_node(id))
+            .collect()
+    }
+
+    /// Lifts a pangenome position in the old graph to the new graph.
+    pub fn lift_pangenome_pos(
+        &self,
+        old: &PathIndex,
+        new: &PathIndex,
+        pos: Bp,
+    ) -> Option<Bp> {
+        let node = old.node_at_pangenome_pos(pos)?;
+        let offset = pos.0 - old.node_offset(node).0;
+        let old_id = old.segment_id(node);
+
+        let (new_id, new_offset) = self.lift_offset(old_id, offset, |id| {
+            Some(new.node_length(new.segment_node(id)?).0)
+        })?;
+
+        let new_node = new.segment_node(new_id)?;
+        Some(Bp(new.node_offset(new_node).0 + new_offset))
+    }
+
+    /// Lifts a pangenome range in the old graph to the range between
+    /// its lifted ends in the new graph.
+    pub fn lift_pangenome_range(
+        &self,
+        old: &PathIndex,
+        new: &PathIndex,
+        range: std::ops::Range<Bp>,
+    ) -> Option<std::ops::Range<Bp>> {
+        let last = Bp(range.end.0.saturating_sub(1).max(range.start.0));
+
+        let start = self.lift_pangenome_pos(old, new, range.start)?;
+        let last = self.lift_pangenome_pos(old, new, last)?;
+
+        // nodes may be in a different order in the new graph
+        let (start, last) = (start.min(last), start.max(last));
+        Some(start..Bp(last.0 + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn liftover_splits_and_merges() {
+        let text = "\
+# old\tnew\toffset
+1\t10,11,12
+2\t20
+3\t20\t4
+";
+        let liftover = NodeLiftover::parse(text.as_bytes()).unwrap();
+        assert_eq!(liftover.len(), 3);
+
+        let new_len = |id: u64| match id {
+            10 => Some(2),
+            11 => Some(3),
+            12 => Some(5),
+            20 => Some(10),
+            5 => Some(7),
+            _ => None,
+        };
+
+        // split: old node 1 (10bp) over new nodes 10, 11 and 12
+        assert_eq!(liftover.lift_id(1), vec![10, 11, 12]);
+        assert_eq!(liftover.lift_offset(1, 0, new_len), Some((10, 0)));
+        assert_eq!(liftover.lift_offset(1, 3, new_len), Some((11, 1)));
+        assert_eq!(liftover.lift_offset(1, 9, new_len), Some((12, 4)));
+        assert_eq!(liftover.lift_offset(1, 12, new_len), Some((12, 4)));
+
+        // merge: old nodes 2 and 3 into new node 20
+        assert_eq!(liftover.lift_offset(2, 1, new_len), Some((20, 1)));
+        assert_eq!(liftover.lift_offset(3, 1, new_len), Some((20, 5)));
+
+        // unchanged
+        assert_eq!(liftover.lift_id(5), vec![5]);
+        assert_eq!(liftover.lift_offset(5, 2, new_len), Some((5, 2)));
+        assert_eq!(liftover.lift_offset(6, 0, new_len), None);
+
+        assert!(NodeLiftover::parse("1\tx\n".as_bytes()).is_err());
+        assert!(NodeLiftover::parse("1\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! Sequence not covered by the reference path(s), aggregated into
+//! insertion events: runs of consecutive nodes (in pangenome order)
+//! that aren't on any reference path.
+
+use std::collections::BTreeSet;
+use std::io::prelude::*;
+
+use roaring::RoaringBitmap;
+
+use super::coords::CoordSystem;
+use super::{Bp, Node, PathId, PathIndex};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsertionEvent {
+    pub nodes: std::ops::RangeInclusive<Node>,
+    pub pangenome_range: std::ops::Range<Bp>,
+    /// Total length of the nodes in the event
+    pub length: Bp,
+    /// Paths that traverse at least one of the nodes
+    pub paths: Vec<PathId>,
+}
+
+/// The union of the node sets of the reference paths.
+pub fn reference_node_set(
+    graph: &PathIndex,
+    references: &[PathId],
+) -> RoaringBitmap {
+    references
+        .iter()
+        .filter_map(|path| graph.path_node_sets.get(path.ix()))
+        .fold(RoaringBitmap::new(), |acc, set| acc | set)
+}
+
+/// Iterates over the runs of consecutive nodes not in `ref_nodes`,
+/// with their total length.
+pub fn non_reference_runs<'a>(
+    graph: &'a PathIndex,
+    ref_nodes: &'a RoaringBitmap,
+) -> impl Iterator<Item = (std::ops::RangeInclusive<Node>, Bp)> + 'a {
+    let node_count = graph.node_count as u32;
+    let mut ix = 0u32;
+
+    std::iter::from_fn(move || {
+        while ix < node_count && ref_nodes.contains(ix) {
+            ix += 1;
+        }
+
+        if ix >= node_count {
+            return None;
+        }
+
+        let first = ix;
+        let mut len = 0;
+
+        while ix < node_count && !ref_nodes.contains(ix) {
+            len += graph.node_length(Node::from(ix)).0;
+            ix += 1;
+        }
+
+        Some((Node::from(first)..=Node::from(ix - 1), Bp(len)))
+    })
+}
+
+/// Finds the insertion events at least `min_length` long, relative to
+/// the given reference paths, in pangenome order.
+pub fn find_insertions(
+    graph: &PathIndex,
+    references: &[PathId],
+    min_length: Bp,
+) -> Vec<InsertionEvent> {
+    let ref_nodes = reference_node_set(graph, references);
+
+    non_reference_runs(graph, &ref_nodes)
+        .filter(|(_, len)| *len >= min_length)
+        .map(|(nodes, length)| {
+            let paths = (nodes.start().ix()..=nodes.end().ix())
+                .filter_map(|ix| graph.paths_on_node(Node::from(ix as u32)))
+                .flatten()
+                .collect::<BTreeSet<_>>();
+
+            let start = graph.node_offset(*nodes.start());
+            let (end_offset, end_len) = graph.node_offset_length(*nodes.end());
+
+            InsertionEvent {
+                nodes,
+                pangenome_range: start..Bp(end_offset.0 + end_len.0),
+                length,
+                paths: paths.into_iter().collect(),
+            }
+        })
+        .collect()
+}
+
+fn event_name(ix: usize, event: &InsertionEvent) -> String {
+    let start = event.pangenome_range.start.0;
+    let end = event.pangenome_range.end.0;
+    format!("ins{ix}_{start}-{end}")
+}
+
+/// Writes the events as TSV, one row per event, with the nodes given
+/// by their GFA segment IDs and the positions in the `coords`
+/// convention, which is named in a comment on the first line.
+pub fn write_event_table(
+    graph: &PathIndex,
+    events: &[InsertionEvent],
+    coords: CoordSystem,
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    let id_offset = graph.segment_id_range.0;
+
+    writeln!(out, "{}", coords.header_comment())?;
+    writeln!(
+        out,
+        "name\tstart\tend\tlength\tfirst_node\tlast_node\tpath_count\tpaths"
+    )?;
+
+    for (ix, event) in events.iter().enumerate() {
+        let paths = event
+            .paths
+            .iter()
+            .filter_map(|p| graph.path_names.get_by_left(p))
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let (start, end) = coords.display_range(&event.pangenome_range);
+
+        writeln!(
+            out,
+            "{}\t{start}\t{end}\t{}\t{}\t{}\t{}\t{paths}",
+            event_name(ix, event),
+            event.length.0,
+            event.nodes.start().ix() as u32 + id_offset,
+            event.nodes.end().ix() as u32 + id_offset,
+            event.paths.len(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes the sequence of each event, i.e. the concatenated sequences
+/// of its nodes in forward orientation, as FASTA.
+pub fn write_fasta(
+    graph: &PathIndex,
+    events: &[InsertionEvent],
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    for (ix, event) in events.iter().enumerate() {
+        writeln!(out, ">{}", event_name(ix, event))?;
+
+        let seq = (event.nodes.start().ix()..=event.nodes.end().ix())
+            .flat_map(|ix| graph.node_sequence(Node::from(ix as u32)))
+            .copied()
+            .collect::<Vec<_>>();
+
+        for line in seq.chunks(80) {
+            out.write_all(line)?;
+            out.write_all(b"\n")?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_reference_insertions() {
+        let graph = PathIndex::from_gfa(super::super::tests::GFA_PATH).unwrap();
+
+        let reference = PathId::from(0u32);
+        let events = find_insertions(&graph, &[reference], Bp(1));
+
+        let ref_nodes = &graph.path_node_sets[0];
+
+        for event in &events {
+            assert!(!event.paths.contains(&reference));
+
+            for ix in event.nodes.start().ix()..=event.nodes.end().ix() {
+                assert!(!ref_nodes.contains(ix as u32));
+            }
+        }
+
+        // the events and the reference cover the entire pangenome
+        let ref_len: u64 = ref_nodes
+            .iter()
+            .map(|n| graph.node_length(Node::from(n)).0)
+            .sum();
+        let event_len: u64 = events.iter().map(|e| e.length.0).sum();
+        assert_eq!(ref_len + event_len, graph.pangenome_len().0);
+
+        let mut fasta = Vec::new();
+        write_fasta(&graph, &events, &mut fasta).unwrap();
+        let seq_len = fasta
+            .split(|&b| b == b'\n')
+            .filter(|l| !l.starts_with(b">"))
+            .map(|l| l.len() as u64)
+            .sum::<u64>();
+        assert_eq!(seq_len, event_len);
+    }
+}
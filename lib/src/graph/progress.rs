@@ -0,0 +1,114 @@
+//! Progress reporting while a graph is loaded, detailed enough to
+//! draw a coarse preview of the graph before it's done.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// The resolution of the coverage sketch, in bins across the
+/// pangenome.
+pub const COVERAGE_BINS: usize = 256;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LoadStage {
+    #[default]
+    Segments,
+    Links,
+    Paths,
+    Done,
+}
+
+impl LoadStage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LoadStage::Segments => "Reading segments",
+            LoadStage::Links => "Reading links",
+            LoadStage::Paths => "Reading paths",
+            LoadStage::Done => "Done",
+        }
+    }
+}
+
+/// What's been parsed so far.
+#[derive(Debug, Default, Clone)]
+pub struct LoadSnapshot {
+    pub stage: LoadStage,
+    pub file_len: u64,
+    /// Bytes read in the current stage; each stage reads the whole
+    /// file
+    pub bytes_read: u64,
+
+    pub segments: usize,
+    pub sequence_len: u64,
+    pub edges: usize,
+
+    /// Name and length in bp of each path parsed so far
+    pub paths: Vec<(String, u64)>,
+    /// The number of path steps on the nodes in each bin, across the
+    /// pangenome; empty until the segments have been read
+    pub coverage: Vec<u64>,
+}
+
+/// Shared between the thread loading a graph, via
+/// `PathIndex::from_file_with_progress`, and whatever shows the
+/// progress, which can also cancel the loading.
+#[derive(Default)]
+pub struct LoadProgress {
+    bytes_read: AtomicU64,
+    cancelled: AtomicBool,
+    snapshot: Mutex<LoadSnapshot>,
+}
+
+impl LoadProgress {
+    pub fn snapshot(&self) -> LoadSnapshot {
+        let mut snapshot = self.snapshot.lock().unwrap().clone();
+        snapshot.bytes_read = self.bytes_read.load(Ordering::Relaxed);
+        snapshot
+    }
+
+    /// Stops the loading at the next line; the loading function then
+    /// returns an error of kind `Interrupted`.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn check_cancelled(&self) -> std::io::Result<()> {
+        if self.is_cancelled() {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "Graph loading cancelled",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(super) fn update(&self, f: impl FnOnce(&mut LoadSnapshot)) {
+        f(&mut self.snapshot.lock().unwrap());
+    }
+
+    pub(super) fn set_stage(&self, stage: LoadStage) {
+        self.bytes_read.store(0, Ordering::Relaxed);
+        self.update(|s| s.stage = stage);
+    }
+
+    pub(super) fn add_bytes(&self, bytes: usize) {
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+/// The coverage bin of each node, given its offset in the pangenome.
+pub(super) fn node_bins(node_offsets: &[u64], total_len: u64) -> Vec<u16> {
+    let total_len = total_len.max(1);
+
+    node_offsets
+        .iter()
+        .map(|&offset| {
+            let bin = (offset * COVERAGE_BINS as u64) / total_len;
+            bin.min(COVERAGE_BINS as u64 - 1) as u16
+        })
+        .collect()
+}
@@ -0,0 +1,599 @@
+//! Graph quality control summary: basic node, edge and path
+//! statistics, connected components, dead ends, and how much of the
+//! pangenome the reference path(s) cover.
+//!
+//! The summary can be written as JSON, or as a standalone HTML page.
+
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::io::BufReader;
+
+use reunion::{UnionFind, UnionFindTrait};
+
+use crate::export::{html_escape, json_string};
+
+use super::nonref::reference_node_set;
+use super::{Bp, Node, PathId, PathIndex};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LengthStats {
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub n50: u64,
+}
+
+impl LengthStats {
+    pub fn from_lengths(lengths: impl IntoIterator<Item = u64>) -> Self {
+        let mut lengths = lengths.into_iter().collect::<Vec<_>>();
+
+        if lengths.is_empty() {
+            return Self::default();
+        }
+
+        lengths.sort_unstable_by(|a, b| b.cmp(a));
+
+        let total: u64 = lengths.iter().sum();
+
+        let mut acc = 0;
+        let n50 = lengths
+            .iter()
+            .copied()
+            .find(|&len| {
+                acc += len;
+                acc * 2 >= total
+            })
+            .unwrap_or(0);
+
+        Self {
+            min: *lengths.last().unwrap(),
+            max: lengths[0],
+            mean: total as f64 / lengths.len() as f64,
+            n50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentSize {
+    pub nodes: usize,
+    pub length: Bp,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceCoverage {
+    pub name: String,
+    /// Total length of the nodes on the path
+    pub covered: Bp,
+    /// `covered` relative to the pangenome length
+    pub fraction: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQc {
+    /// `false` if the graph couldn't be indexed, in which case only
+    /// `duplicate_segment_names` is filled in; see `unindexed`
+    pub indexed: bool,
+
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub path_count: usize,
+    pub total_length: Bp,
+
+    pub node_lengths: LengthStats,
+    /// Path lengths in bp
+    pub path_lengths: LengthStats,
+
+    /// Weakly connected components, largest first
+    pub components: Vec<ComponentSize>,
+
+    /// Node ends (of the two per node) without any edges
+    pub dead_ends: usize,
+    pub isolated_nodes: usize,
+    pub nodes_without_paths: usize,
+
+    /// One entry per reference path, followed by their union if
+    /// there's more than one
+    pub reference_coverage: Vec<ReferenceCoverage>,
+
+    pub duplicate_segment_names: Vec<String>,
+}
+
+impl GraphQc {
+    pub fn compute(graph: &PathIndex, references: &[PathId]) -> Self {
+        let node_count = graph.node_count;
+        let total_length = graph.pangenome_len();
+
+        let node_lengths = LengthStats::from_lengths(
+            (0..node_count).map(|ix| graph.node_length(Node::from(ix)).0),
+        );
+
+        let path_lengths =
+            LengthStats::from_lengths(graph.path_steps.iter().map(|steps| {
+                steps.iter().map(|s| graph.node_length(s.node()).0).sum()
+            }));
+
+        let mut has_edge = vec![[false; 2]; node_count];
+        let mut ufind = UnionFind::<Node>::new();
+
+        for edge in graph.edges_iter() {
+            let (a, b) = edge.endpoints();
+
+            for end in [a, b] {
+                has_edge[end.node().ix()][end.is_reverse() as usize] = true;
+            }
+
+            ufind.union(a.node(), b.node());
+        }
+
+        let dead_ends = has_edge
+            .iter()
+            .map(|ends| ends.iter().filter(|&&e| !e).count())
+            .sum();
+        let isolated_nodes =
+            has_edge.iter().filter(|ends| !ends[0] && !ends[1]).count();
+
+        let mut components: HashMap<Node, ComponentSize> = HashMap::new();
+
+        for ix in 0..node_count {
+            let node = Node::from(ix);
+            let comp =
+                components.entry(ufind.find(node)).or_insert(ComponentSize {
+                    nodes: 0,
+                    length: Bp(0),
+                });
+            comp.nodes += 1;
+            comp.length.0 += graph.node_length(node).0;
+        }
+
+        let mut components = components.into_values().collect::<Vec<_>>();
+        components.sort_by(|a, b| {
+            b.length.cmp(&a.length).then(b.nodes.cmp(&a.nodes))
+        });
+
+        let nodes_on_paths = graph
+            .path_node_sets
+            .iter()
+            .fold(roaring::RoaringBitmap::new(), |acc, set| acc | set);
+        let nodes_without_paths = node_count - nodes_on_paths.len() as usize;
+
+        let coverage = |name: String, nodes: &roaring::RoaringBitmap| {
+            let covered: u64 = nodes
+                .iter()
+                .map(|n| graph.node_length(Node::from(n)).0)
+                .sum();
+
+            let fraction = if total_length.0 > 0 {
+                covered as f64 / total_length.0 as f64
+            } else {
+                0.0
+            };
+
+            ReferenceCoverage {
+                name,
+                covered: Bp(covered),
+                fraction,
+            }
+        };
+
+        let mut reference_coverage = references
+            .iter()
+            .filter_map(|path| {
+                let name = graph.path_names.get_by_left(path)?;
+                let nodes = graph.path_node_sets.get(path.ix())?;
+                Some(coverage(name.clone(), nodes))
+            })
+            .collect::<Vec<_>>();
+
+        if references.len() > 1 {
+            let union = reference_node_set(graph, references);
+            reference_coverage
+                .push(coverage("(all references)".into(), &union));
+        }
+
+        Self {
+            indexed: true,
+
+            node_count,
+            edge_count: graph.edges_iter().count(),
+            path_count: graph.path_names.len(),
+            total_length,
+
+            node_lengths,
+            path_lengths,
+
+            components,
+
+            dead_ends,
+            isolated_nodes,
+            nodes_without_paths,
+
+            reference_coverage,
+
+            duplicate_segment_names: Vec::new(),
+        }
+    }
+
+    /// The report for a GFA that can't be indexed because its segment
+    /// names aren't unique, listing only the duplicates.
+    pub fn unindexed(duplicate_segment_names: Vec<String>) -> Self {
+        Self {
+            indexed: false,
+
+            node_count: 0,
+            edge_count: 0,
+            path_count: 0,
+            total_length: Bp(0),
+
+            node_lengths: LengthStats::default(),
+            path_lengths: LengthStats::default(),
+
+            components: Vec::new(),
+
+            dead_ends: 0,
+            isolated_nodes: 0,
+            nodes_without_paths: 0,
+
+            reference_coverage: Vec::new(),
+
+            duplicate_segment_names,
+        }
+    }
+
+    fn duplicates_json(&self) -> String {
+        let duplicates = self
+            .duplicate_segment_names
+            .iter()
+            .map(|name| json_string(name))
+            .collect::<Vec<_>>();
+        format!("[{}]", duplicates.join(", "))
+    }
+
+    pub fn write_json(&self, mut out: impl Write) -> std::io::Result<()> {
+        if !self.indexed {
+            writeln!(out, "{{")?;
+            writeln!(out, "  \"indexed\": false,")?;
+            writeln!(
+                out,
+                "  \"duplicate_segment_names\": {}",
+                self.duplicates_json()
+            )?;
+            writeln!(out, "}}")?;
+            return Ok(());
+        }
+
+        let stats_json = |s: &LengthStats| {
+            format!(
+                "{{\"min\": {}, \"max\": {}, \"mean\": {:.2}, \"n50\": {}}}",
+                s.min, s.max, s.mean, s.n50
+            )
+        };
+
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"indexed\": true,")?;
+        writeln!(out, "  \"node_count\": {},", self.node_count)?;
+        writeln!(out, "  \"edge_count\": {},", self.edge_count)?;
+        writeln!(out, "  \"path_count\": {},", self.path_count)?;
+        writeln!(out, "  \"total_length\": {},", self.total_length.0)?;
+        writeln!(
+            out,
+            "  \"node_lengths\": {},",
+            stats_json(&self.node_lengths)
+        )?;
+        writeln!(
+            out,
+            "  \"path_lengths\": {},",
+            stats_json(&self.path_lengths)
+        )?;
+
+        let components = self
+            .components
+            .iter()
+            .map(|c| {
+                format!(
+                    "{{\"nodes\": {}, \"length\": {}}}",
+                    c.nodes, c.length.0
+                )
+            })
+            .collect::<Vec<_>>();
+        writeln!(out, "  \"components\": [{}],", components.join(", "))?;
+
+        writeln!(out, "  \"dead_ends\": {},", self.dead_ends)?;
+        writeln!(out, "  \"isolated_nodes\": {},", self.isolated_nodes)?;
+        writeln!(
+            out,
+            "  \"nodes_without_paths\": {},",
+            self.nodes_without_paths
+        )?;
+
+        let coverage = self
+            .reference_coverage
+            .iter()
+            .map(|c| {
+                format!(
+                    "{{\"name\": {}, \"covered\": {}, \"fraction\": {:.4}}}",
+                    json_string(&c.name),
+                    c.covered.0,
+                    c.fraction
+                )
+            })
+            .collect::<Vec<_>>();
+        writeln!(out, "  \"reference_coverage\": [{}],", coverage.join(", "))?;
+
+        writeln!(
+            out,
+            "  \"duplicate_segment_names\": {}",
+            self.duplicates_json()
+        )?;
+        writeln!(out, "}}")?;
+
+        Ok(())
+    }
+
+    pub fn write_html(&self, mut out: impl Write) -> std::io::Result<()> {
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html><head><meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>Graph QC report</title>")?;
+        writeln!(
+            out,
+            "<style>body {{ font-family: sans-serif; }} \
+             table {{ border-collapse: collapse; margin-bottom: 1em; }} \
+             td, th {{ border: 1px solid #ccc; padding: 2px 8px; \
+             text-align: right; }}</style>"
+        )?;
+        writeln!(out, "</head><body>")?;
+        writeln!(out, "<h1>Graph QC report</h1>")?;
+
+        if !self.indexed {
+            writeln!(
+                out,
+                "<p>The graph could not be loaded, as its segment names \
+                 aren't unique, so only the duplicates are listed.</p>"
+            )?;
+            self.write_html_duplicates(&mut out)?;
+            writeln!(out, "</body></html>")?;
+            return Ok(());
+        }
+
+        writeln!(out, "<h2>Summary</h2>\n<table>")?;
+        for (label, value) in self.summary_rows() {
+            writeln!(out, "<tr><th>{label}</th><td>{value}</td></tr>")?;
+        }
+        writeln!(out, "</table>")?;
+
+        writeln!(out, "<h2>Lengths</h2>\n<table>")?;
+        writeln!(
+            out,
+            "<tr><th></th><th>min</th><th>max</th><th>mean</th><th>N50</th></tr>"
+        )?;
+        for (label, s) in
+            [("Nodes", &self.node_lengths), ("Paths", &self.path_lengths)]
+        {
+            writeln!(
+                out,
+                "<tr><th>{label}</th><td>{}</td><td>{}</td>\
+                 <td>{:.1}</td><td>{}</td></tr>",
+                s.min, s.max, s.mean, s.n50
+            )?;
+        }
+        writeln!(out, "</table>")?;
+
+        if !self.reference_coverage.is_empty() {
+            writeln!(out, "<h2>Reference coverage</h2>\n<table>")?;
+            writeln!(out, "<tr><th>Path</th><th>bp</th><th>%</th></tr>")?;
+            for c in &self.reference_coverage {
+                writeln!(
+                    out,
+                    "<tr><th>{}</th><td>{}</td><td>{:.2}</td></tr>",
+                    html_escape(&c.name),
+                    c.covered.0,
+                    c.fraction * 100.0
+                )?;
+            }
+            writeln!(out, "</table>")?;
+        }
+
+        writeln!(out, "<h2>Components</h2>\n<table>")?;
+        writeln!(out, "<tr><th>#</th><th>Nodes</th><th>bp</th></tr>")?;
+        for (ix, c) in self.components.iter().take(100).enumerate() {
+            writeln!(
+                out,
+                "<tr><th>{}</th><td>{}</td><td>{}</td></tr>",
+                ix + 1,
+                c.nodes,
+                c.length.0
+            )?;
+        }
+        writeln!(out, "</table>")?;
+        if self.components.len() > 100 {
+            writeln!(
+                out,
+                "<p>{} smaller components not shown</p>",
+                self.components.len() - 100
+            )?;
+        }
+
+        self.write_html_duplicates(&mut out)?;
+
+        writeln!(out, "</body></html>")?;
+
+        Ok(())
+    }
+
+    fn write_html_duplicates(
+        &self,
+        mut out: impl Write,
+    ) -> std::io::Result<()> {
+        if !self.duplicate_segment_names.is_empty() {
+            writeln!(out, "<h2>Duplicate segment names</h2>\n<ul>")?;
+            for name in &self.duplicate_segment_names {
+                writeln!(out, "<li>{}</li>", html_escape(name))?;
+            }
+            writeln!(out, "</ul>")?;
+        }
+        Ok(())
+    }
+
+    /// Label and value of each scalar in the summary, for display.
+    pub fn summary_rows(&self) -> Vec<(&'static str, String)> {
+        if !self.indexed {
+            return vec![(
+                "Duplicate segment names",
+                self.duplicate_segment_names.len().to_string(),
+            )];
+        }
+
+        vec![
+            ("Nodes", self.node_count.to_string()),
+            ("Edges", self.edge_count.to_string()),
+            ("Paths", self.path_count.to_string()),
+            ("Total length (bp)", self.total_length.0.to_string()),
+            ("Components", self.components.len().to_string()),
+            ("Dead ends", self.dead_ends.to_string()),
+            ("Isolated nodes", self.isolated_nodes.to_string()),
+            (
+                "Nodes not on any path",
+                self.nodes_without_paths.to_string(),
+            ),
+            (
+                "Duplicate segment names",
+                self.duplicate_segment_names.len().to_string(),
+            ),
+        ]
+    }
+}
+
+/// Scans the segment lines of a GFA for names that occur more than
+/// once. Done separately from parsing the graph, which assumes that
+/// segment names are unique.
+pub fn duplicate_segment_names(
+    gfa_path: impl AsRef<std::path::Path>,
+) -> std::io::Result<Vec<String>> {
+    // GBZ node IDs are integers, so they can't be duplicated
+    if super::gbz::is_gbz_file(&gfa_path) {
+        return Ok(Vec::new());
+    }
+
+    let gfa = std::fs::File::open(gfa_path)?;
+    duplicate_segment_names_from_reader(BufReader::new(gfa))
+}
+
+/// Like `duplicate_segment_names`, for a GFA in `gfa_reader`.
+pub fn duplicate_segment_names_from_reader(
+    mut gfa_reader: impl BufRead,
+) -> std::io::Result<Vec<String>> {
+    let mut line_buf = Vec::new();
+    let mut counts: HashMap<Vec<u8>, usize> = HashMap::new();
+
+    loop {
+        line_buf.clear();
+
+        let len = gfa_reader.read_until(b'\n', &mut line_buf)?;
+        if len == 0 {
+            break;
+        }
+
+        if !matches!(line_buf.first(), Some(b'S')) {
+            continue;
+        }
+
+        if let Some(name) = line_buf.split(|&c| c == b'\t').nth(1) {
+            *counts.entry(name.to_vec()).or_default() += 1;
+        }
+    }
+
+    let mut duplicates = counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(name, _)| String::from_utf8_lossy(&name).into_owned())
+        .collect::<Vec<_>>();
+    duplicates.sort();
+
+    Ok(duplicates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn length_stats_n50() {
+        let stats = LengthStats::from_lengths([2, 3, 4, 5, 6, 10]);
+        assert_eq!(stats.min, 2);
+        assert_eq!(stats.max, 10);
+        assert_eq!(stats.n50, 6);
+        assert!((stats.mean - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn graph_qc_summary() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tC
+S\t3\tG
+S\t4\tTT
+S\t5\tAAAA
+L\t1\t+\t2\t+\t0M
+L\t1\t+\t3\t+\t0M
+L\t2\t+\t4\t+\t0M
+L\t3\t+\t4\t+\t0M
+P\tref\t1+,2+,4+\t*
+P\talt\t1+,3+,4+\t*
+";
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let reference = graph.path_names.get_by_right("ref").copied().unwrap();
+        let qc = GraphQc::compute(&graph, &[reference]);
+
+        assert_eq!(qc.node_count, 5);
+        assert_eq!(qc.edge_count, 4);
+        assert_eq!(qc.path_count, 2);
+        assert_eq!(qc.components.len(), 2);
+        assert_eq!(qc.components[0].nodes, 4);
+        // node 1 start, node 4 end, and both ends of node 5
+        assert_eq!(qc.dead_ends, 4);
+        assert_eq!(qc.isolated_nodes, 1);
+        assert_eq!(qc.nodes_without_paths, 1);
+        assert_eq!(qc.reference_coverage[0].covered, Bp(7));
+
+        let mut json = Vec::new();
+        qc.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains("\"dead_ends\": 4,"));
+        assert!(json.contains("\"name\": \"ref\""));
+    }
+
+    #[test]
+    fn duplicate_segments_are_reported() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tC
+S\t2\tC
+S\t3\tG
+P\tref\t1+,2+,3+\t*
+";
+        let duplicates =
+            duplicate_segment_names_from_reader(gfa.as_bytes()).unwrap();
+        assert_eq!(duplicates, vec!["2".to_string()]);
+
+        let err =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let qc = GraphQc::unindexed(duplicates);
+
+        let mut json = Vec::new();
+        qc.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains("\"indexed\": false,"));
+        assert!(json.contains("\"duplicate_segment_names\": [\"2\"]"));
+        assert!(!json.contains("node_count"));
+
+        let mut html = Vec::new();
+        qc.write_html(&mut html).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains("<li>2</li>"));
+        assert!(!html.contains("Components"));
+    }
+}
@@ -0,0 +1,199 @@
+//! Guessing which path is the reference, for the default coordinate
+//! system: the rank 0 sequences of an rGFA, a well-known reference
+//! sample in PanSN names, or else the longest path.
+
+use std::collections::BTreeSet;
+use std::io::prelude::*;
+
+use super::{PathId, PathIndex};
+
+/// PanSN sample names of common human references, in order of
+/// preference; matched ignoring case.
+pub const KNOWN_REFERENCE_SAMPLES: [&str; 4] =
+    ["grch38", "hg38", "chm13", "t2t-chm13"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceReason {
+    /// The path was given by the user, e.g. on the command line
+    Named,
+    /// The path is a rank 0 sequence of the rGFA
+    RgfaRank0,
+    /// The path belongs to a known reference sample
+    KnownSample(String),
+    /// No better guess; the path is the longest in the graph
+    Longest,
+}
+
+impl std::fmt::Display for ReferenceReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Named => write!(f, "given by the user"),
+            Self::RgfaRank0 => write!(f, "rank 0 sequence of the rGFA"),
+            Self::KnownSample(sample) => {
+                write!(f, "path of the reference sample `{sample}`")
+            }
+            Self::Longest => write!(f, "longest path"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceGuess {
+    pub path: PathId,
+    pub reason: ReferenceReason,
+}
+
+/// The `SN` names of the rank 0 (`SR:i:0`) segments of an rGFA. Only
+/// the first segment is checked for an `SR` tag, so files that aren't
+/// rGFAs are not read in full.
+pub fn rgfa_rank0_names(
+    gfa_path: impl AsRef<std::path::Path>,
+) -> std::io::Result<BTreeSet<String>> {
+    let gfa = std::fs::File::open(gfa_path)?;
+    rgfa_rank0_names_from_reader(std::io::BufReader::new(gfa))
+}
+
+/// Like `rgfa_rank0_names`, for a GFA in `reader`.
+pub fn rgfa_rank0_names_from_reader(
+    reader: impl BufRead,
+) -> std::io::Result<BTreeSet<String>> {
+    let mut names = BTreeSet::new();
+    let mut first_segment = true;
+
+    for line in reader.split(b'\n') {
+        let line = line?;
+        if !line.starts_with(b"S\t") {
+            continue;
+        }
+
+        let tags = line.split(|&b| b == b'\t').skip(3);
+
+        let mut name = None;
+        let mut rank = None;
+
+        for tag in tags {
+            if let Some(sn) = tag.strip_prefix(b"SN:Z:") {
+                name = Some(String::from_utf8_lossy(sn).trim_end().to_string());
+            } else if let Some(sr) = tag.strip_prefix(b"SR:i:") {
+                rank = std::str::from_utf8(sr)
+                    .ok()
+                    .and_then(|sr| sr.trim_end().parse::<u64>().ok());
+            }
+        }
+
+        if first_segment && rank.is_none() {
+            break;
+        }
+        first_segment = false;
+
+        if let (Some(name), Some(0)) = (name, rank) {
+            names.insert(name);
+        }
+    }
+
+    Ok(names)
+}
+
+/// Guesses the reference path: the longest path named after one of
+/// the rank 0 sequences in `rank0_names` (exactly, or as the contig
+/// part of a PanSN name), else the longest path of the first sample
+/// in `KNOWN_REFERENCE_SAMPLES` with paths in the graph, else the
+/// longest path. `None` if the graph has no paths.
+pub fn guess_reference(
+    graph: &PathIndex,
+    rank0_names: &BTreeSet<String>,
+) -> Option<ReferenceGuess> {
+    let longest = |paths: &mut dyn Iterator<Item = PathId>| {
+        paths
+            .max_by_key(|&path| (graph.path_len(path), std::cmp::Reverse(path)))
+    };
+
+    let rank0 =
+        longest(&mut graph.path_names.iter().filter_map(|(path, name)| {
+            let contig = name.rsplit('#').next().unwrap_or(name);
+            (rank0_names.contains(name) || rank0_names.contains(contig))
+                .then_some(*path)
+        }));
+
+    if let Some(path) = rank0 {
+        return Some(ReferenceGuess {
+            path,
+            reason: ReferenceReason::RgfaRank0,
+        });
+    }
+
+    for sample in KNOWN_REFERENCE_SAMPLES {
+        let path =
+            longest(&mut graph.path_names.iter().filter_map(|(path, name)| {
+                let name_sample = name.split('#').next().unwrap_or(name);
+                name_sample.eq_ignore_ascii_case(sample).then_some(*path)
+            }));
+
+        if let Some(path) = path {
+            return Some(ReferenceGuess {
+                path,
+                reason: ReferenceReason::KnownSample(sample.to_string()),
+            });
+        }
+    }
+
+    let path = longest(&mut graph.path_names.left_values().copied())?;
+
+    Some(ReferenceGuess {
+        path,
+        reason: ReferenceReason::Longest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph_with_paths(paths: &[(&str, &str)]) -> PathIndex {
+        let mut gfa = String::from(
+            "S\t1\tAAAA\tSN:Z:chr1\tSO:i:0\tSR:i:0\n\
+             S\t2\tAA\tSN:Z:alt1\tSO:i:0\tSR:i:1\n\
+             S\t3\tAAAAAA\tSN:Z:chr1\tSO:i:4\tSR:i:0\n",
+        );
+        for (path, steps) in paths {
+            gfa.push_str(&format!("P\t{path}\t{steps}\t*\n"));
+        }
+
+        let names = rgfa_rank0_names_from_reader(gfa.as_bytes()).unwrap();
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+        assert_eq!(names, BTreeSet::from(["chr1".to_string()]));
+
+        graph
+    }
+
+    fn guess(graph: &PathIndex, rank0: &[&str]) -> (String, ReferenceReason) {
+        let rank0 = rank0.iter().map(|s| s.to_string()).collect();
+        let guess = guess_reference(graph, &rank0).unwrap();
+        let name = graph.path_names.get_by_left(&guess.path).unwrap();
+        (name.clone(), guess.reason)
+    }
+
+    #[test]
+    fn reference_heuristics() {
+        let graph = graph_with_paths(&[
+            ("HG002#1#chr1", "1+,2+,3+"),
+            ("GRCh38#0#chr1", "1+,3+"),
+            ("sample#0#chr1_alt", "2+"),
+        ]);
+
+        // the rGFA rank 0 contig
+        let (name, reason) = guess(&graph, &["chr1"]);
+        assert_eq!(reason, ReferenceReason::RgfaRank0);
+        // both paths match the contig; the longest is picked
+        assert_eq!(name, "HG002#1#chr1");
+
+        let (name, reason) = guess(&graph, &[]);
+        assert_eq!(name, "GRCh38#0#chr1");
+        assert_eq!(reason, ReferenceReason::KnownSample("grch38".into()));
+
+        let graph =
+            graph_with_paths(&[("a", "2+"), ("b", "1+,3+"), ("c", "1+")]);
+        assert_eq!(guess(&graph, &[]), ("b".into(), ReferenceReason::Longest));
+    }
+}
@@ -6,12 +6,96 @@ pub trait PathData<T> {
     fn get_path(&self, path_id: PathId) -> &[T];
 }
 
+/// How the node values in a bin are combined into the bin's value.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinAggregate {
+    /// Weighted by the length of each node in the bin
+    #[default]
+    Mean,
+    /// Keeps narrow peaks visible when zoomed out
+    Max,
+    Min,
+    /// Values summed over the bases in the bin
+    Sum,
+}
+
+impl BinAggregate {
+    pub const ALL: [BinAggregate; 4] = [
+        BinAggregate::Mean,
+        BinAggregate::Max,
+        BinAggregate::Min,
+        BinAggregate::Sum,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BinAggregate::Mean => "Mean",
+            BinAggregate::Max => "Max",
+            BinAggregate::Min => "Min",
+            BinAggregate::Sum => "Sum",
+        }
+    }
+
+    /// Combines the (node length, value) pairs of a bin; `None` if
+    /// the bin is empty.
+    pub fn aggregate(
+        &self,
+        values: impl IntoIterator<Item = (u64, f32)>,
+    ) -> Option<f32> {
+        let mut sum_len = 0;
+        let mut sum_val = 0.0;
+        let mut min = f32::INFINITY;
+        let mut max = f32::NEG_INFINITY;
+
+        for (len, val) in values {
+            if len == 0 {
+                continue;
+            }
+            sum_len += len;
+            sum_val += val * len as f32;
+            min = min.min(val);
+            max = max.max(val);
+        }
+
+        if sum_len == 0 {
+            return None;
+        }
+
+        Some(match self {
+            BinAggregate::Mean => sum_val / sum_len as f32,
+            BinAggregate::Max => max,
+            BinAggregate::Min => min,
+            BinAggregate::Sum => sum_val,
+        })
+    }
+}
+
 pub fn sample_data_into_buffer(
     index: &PathIndex,
     path_id: PathId,
     path_data: &[f32],
     view_range: std::ops::Range<u64>,
     bins: &mut [f32],
+) {
+    sample_data_into_buffer_with(
+        index,
+        path_id,
+        path_data,
+        view_range,
+        bins,
+        BinAggregate::Mean,
+    )
+}
+
+/// Like `sample_data_into_buffer`, combining the values in each bin
+/// with `aggregate` rather than the mean.
+pub fn sample_data_into_buffer_with(
+    index: &PathIndex,
+    path_id: PathId,
+    path_data: &[f32],
+    view_range: std::ops::Range<u64>,
+    bins: &mut [f32],
+    aggregate: BinAggregate,
 ) {
     let bin_count = bins.len();
 
@@ -35,16 +119,10 @@ pub fn sample_data_into_buffer(
         let range = bin_range(bin_ix);
         let iter = index.path_data_pan_range_iter(range, path_id, path_data);
 
-        let mut sum_len = 0;
-        let mut sum_val = 0.0;
+        let values = iter.map(|((_node, len), val)| (len.0, *val));
 
-        for ((_node, len), val) in iter {
-            sum_len += len.0;
-            sum_val += *val * len.0 as f32;
-        }
-
-        if sum_len > 0 {
-            *buf_val = sum_val / sum_len as f32;
+        if let Some(val) = aggregate.aggregate(values) {
+            *buf_val = val;
         }
     }
 }
@@ -159,3 +237,21 @@ impl PathDepthData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bin_aggregates() {
+        let bin = [(4, 1.0), (1, 9.0), (0, 100.0), (5, 2.0)];
+        let agg = |a: BinAggregate| a.aggregate(bin.iter().copied());
+
+        assert_eq!(agg(BinAggregate::Mean), Some(2.3));
+        assert_eq!(agg(BinAggregate::Max), Some(9.0));
+        assert_eq!(agg(BinAggregate::Min), Some(1.0));
+        assert_eq!(agg(BinAggregate::Sum), Some(23.0));
+
+        assert_eq!(BinAggregate::Max.aggregate([]), None);
+    }
+}
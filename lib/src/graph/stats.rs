@@ -0,0 +1,268 @@
+//! Graph statistics for display: counts, and the distributions of node
+//! lengths and degrees as histograms, whose bins can be mapped back to
+//! the nodes they count.
+
+use super::{Bp, Node, PathIndex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistogramBin {
+    pub min: u64,
+    /// Inclusive
+    pub max: u64,
+    pub count: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    pub bins: Vec<HistogramBin>,
+    // the bins are either doubling in width, or one per value up to
+    // the last bin, which holds the rest
+    log2: bool,
+}
+
+impl Histogram {
+    /// Bins that double in width: 1, 2-3, 4-7, and so on, from the
+    /// bin of the smallest value to the bin of the largest.
+    pub fn log2(values: impl IntoIterator<Item = u64>) -> Self {
+        let mut counts = vec![0usize; 65];
+        for v in values {
+            counts[Self::log2_bin(v)] += 1;
+        }
+
+        let first = counts.iter().position(|&c| c > 0).unwrap_or(0);
+        let last = counts.iter().rposition(|&c| c > 0).unwrap_or(0);
+
+        let bins = (first..=last)
+            .map(|k| {
+                let (min, max) = if k == 0 {
+                    (0, 0)
+                } else {
+                    (1 << (k - 1), (1u128 << k) as u64 - 1)
+                };
+                HistogramBin {
+                    min,
+                    max: max.max(min),
+                    count: counts[k],
+                }
+            })
+            .collect();
+
+        Self { bins, log2: true }
+    }
+
+    /// One bin per value from 0 to `max_value`, and one for the larger
+    /// values, if there are any.
+    pub fn linear(
+        values: impl IntoIterator<Item = u64>,
+        max_value: u64,
+    ) -> Self {
+        let mut counts = vec![0usize; max_value as usize + 1];
+        let mut rest = 0;
+        let mut largest = 0;
+
+        for v in values {
+            largest = largest.max(v);
+            if v <= max_value {
+                counts[v as usize] += 1;
+            } else {
+                rest += 1;
+            }
+        }
+
+        let mut bins = counts
+            .into_iter()
+            .enumerate()
+            .map(|(v, count)| HistogramBin {
+                min: v as u64,
+                max: v as u64,
+                count,
+            })
+            .collect::<Vec<_>>();
+
+        if rest > 0 {
+            bins.push(HistogramBin {
+                min: max_value + 1,
+                max: largest,
+                count: rest,
+            });
+        } else {
+            // trim the empty bins past the largest value
+            bins.truncate(largest as usize + 1);
+        }
+
+        Self { bins, log2: false }
+    }
+
+    fn log2_bin(value: u64) -> usize {
+        (u64::BITS - value.leading_zeros()) as usize
+    }
+
+    /// The index of the bin containing `value`.
+    pub fn bin_index(&self, value: u64) -> Option<usize> {
+        if self.log2 {
+            let first = Self::log2_bin(self.bins.first()?.min);
+            Self::log2_bin(value)
+                .checked_sub(first)
+                .filter(|&ix| ix < self.bins.len())
+        } else {
+            let last = self.bins.len().checked_sub(1)?;
+            let ix = (value as usize).min(last);
+            let bin = &self.bins[ix];
+            (bin.min..=bin.max).contains(&value).then_some(ix)
+        }
+    }
+
+    pub fn max_count(&self) -> usize {
+        self.bins.iter().map(|b| b.count).max().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeMeasure {
+    Length,
+    /// Number of edges on either side of the node
+    Degree,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub path_count: usize,
+    pub total_length: Bp,
+
+    pub node_lengths: Histogram,
+    pub degrees: Histogram,
+
+    node_degrees: Vec<u32>,
+}
+
+impl GraphStats {
+    /// Degrees above this share the last bin of the histogram
+    pub const MAX_DEGREE_BIN: u64 = 16;
+
+    pub fn compute(graph: &PathIndex) -> Self {
+        let node_count = graph.node_count;
+
+        let mut node_degrees = vec![0u32; node_count];
+        let mut edge_count = 0;
+
+        for edge in graph.edges_iter() {
+            let (a, b) = edge.endpoints();
+            node_degrees[a.node().ix()] += 1;
+            node_degrees[b.node().ix()] += 1;
+            edge_count += 1;
+        }
+
+        let node_lengths = Histogram::log2(
+            (0..node_count).map(|ix| graph.node_length(Node::from(ix)).0),
+        );
+        let degrees = Histogram::linear(
+            node_degrees.iter().map(|&d| d as u64),
+            Self::MAX_DEGREE_BIN,
+        );
+
+        Self {
+            node_count,
+            edge_count,
+            path_count: graph.path_names.len(),
+            total_length: graph.pangenome_len(),
+
+            node_lengths,
+            degrees,
+
+            node_degrees,
+        }
+    }
+
+    pub fn histogram(&self, measure: NodeMeasure) -> &Histogram {
+        match measure {
+            NodeMeasure::Length => &self.node_lengths,
+            NodeMeasure::Degree => &self.degrees,
+        }
+    }
+
+    pub fn node_degree(&self, node: Node) -> u32 {
+        self.node_degrees[node.ix()]
+    }
+
+    /// The nodes counted in the given bin of the histogram of
+    /// `measure`.
+    pub fn nodes_in_bin(
+        &self,
+        graph: &PathIndex,
+        measure: NodeMeasure,
+        bin_ix: usize,
+    ) -> Vec<Node> {
+        let hist = self.histogram(measure);
+
+        (0..self.node_count)
+            .map(Node::from)
+            .filter(|&node| {
+                let value = match measure {
+                    NodeMeasure::Length => graph.node_length(node).0,
+                    NodeMeasure::Degree => self.node_degree(node) as u64,
+                };
+                hist.bin_index(value) == Some(bin_ix)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_bins() {
+        let hist = Histogram::log2([1, 2, 3, 9, 12, 1]);
+        let bins = hist
+            .bins
+            .iter()
+            .map(|b| (b.min, b.max, b.count))
+            .collect::<Vec<_>>();
+        assert_eq!(bins, vec![(1, 1, 2), (2, 3, 2), (4, 7, 0), (8, 15, 2)]);
+        assert_eq!(hist.bin_index(5), Some(2));
+        assert_eq!(hist.bin_index(16), None);
+        assert_eq!(hist.max_count(), 2);
+
+        let hist = Histogram::linear([0, 2, 2, 3, 40, 17], 16);
+        assert_eq!(hist.bins.len(), 18);
+        assert_eq!(hist.bins[2].count, 2);
+        assert_eq!(
+            hist.bins[17],
+            HistogramBin {
+                min: 17,
+                max: 40,
+                count: 2,
+            }
+        );
+        assert_eq!(hist.bin_index(25), Some(17));
+        assert_eq!(hist.bin_index(3), Some(3));
+
+        let hist = Histogram::linear([1, 3], 16);
+        assert_eq!(hist.bins.len(), 4);
+        assert_eq!(hist.bin_index(5), None);
+    }
+
+    #[test]
+    fn graph_stats() {
+        let graph = PathIndex::from_gfa(super::super::tests::GFA_PATH).unwrap();
+        let stats = GraphStats::compute(&graph);
+
+        let hist_total =
+            |hist: &Histogram| hist.bins.iter().map(|b| b.count).sum::<usize>();
+        assert_eq!(hist_total(&stats.node_lengths), graph.node_count);
+        assert_eq!(hist_total(&stats.degrees), graph.node_count);
+
+        let degree_sum: u64 = (0..graph.node_count)
+            .map(|ix| stats.node_degree(Node::from(ix)) as u64)
+            .sum();
+        assert_eq!(degree_sum, 2 * stats.edge_count as u64);
+
+        for (ix, bin) in stats.node_lengths.bins.iter().enumerate() {
+            let nodes = stats.nodes_in_bin(&graph, NodeMeasure::Length, ix);
+            assert_eq!(nodes.len(), bin.count);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Extracting part of a graph, e.g. a region of interest, as a
+//! standalone GFA that can be loaded by other tools such as odgi and
+//! vg.
+
+use std::io::Write;
+use std::ops::Range;
+
+use roaring::RoaringBitmap;
+
+use super::{Bp, Node, PathId, PathIndex};
+
+/// A stretch of consecutive steps of a path that are all in the
+/// subgraph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubPath {
+    pub path: PathId,
+    pub steps: Range<usize>,
+    /// Range in the path's own coordinates
+    pub range: Range<Bp>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subgraph {
+    pub nodes: RoaringBitmap,
+    pub paths: Vec<SubPath>,
+}
+
+impl Subgraph {
+    /// The subgraph induced by the nodes: the edges between them, and
+    /// the parts of the paths that only visit them.
+    pub fn from_nodes(
+        graph: &PathIndex,
+        nodes: impl IntoIterator<Item = Node>,
+    ) -> Self {
+        let nodes = nodes
+            .into_iter()
+            .filter(|n| n.ix() < graph.node_count)
+            .map(|n| n.ix() as u32)
+            .collect::<RoaringBitmap>();
+
+        let mut paths = Vec::new();
+
+        for (path_ix, steps) in graph.path_steps.iter().enumerate() {
+            let path = PathId::from(path_ix as u32);
+
+            if graph.path_node_sets[path_ix].is_disjoint(&nodes) {
+                continue;
+            }
+
+            let offsets = &graph.path_step_offsets[path_ix];
+
+            let mut push_run = |run: Range<usize>| {
+                let start =
+                    offsets.select(run.start as u64).unwrap_or_default();
+                let last = steps[run.end - 1].node();
+                let end =
+                    offsets.select(run.end as u64 - 1).unwrap_or_default()
+                        + graph.node_length(last).0;
+
+                paths.push(SubPath {
+                    path,
+                    steps: run,
+                    range: Bp(start)..Bp(end),
+                });
+            };
+
+            let mut run_start = None;
+
+            for (ix, step) in steps.iter().enumerate() {
+                let inside = nodes.contains(step.node().ix() as u32);
+
+                match (inside, run_start) {
+                    (true, None) => run_start = Some(ix),
+                    (false, Some(start)) => {
+                        push_run(start..ix);
+                        run_start = None;
+                    }
+                    _ => (),
+                }
+            }
+
+            if let Some(start) = run_start {
+                push_run(start..steps.len());
+            }
+        }
+
+        Self { nodes, paths }
+    }
+
+    /// The subgraph of the nodes overlapping the pangenome range.
+    /// Nodes are kept whole, so the path intervals can extend past
+    /// the range.
+    pub fn from_pangenome_range(graph: &PathIndex, range: Range<Bp>) -> Self {
+        if range.is_empty() || range.start >= graph.pangenome_len() {
+            return Self::from_nodes(graph, []);
+        }
+
+        // the last base in the range
+        let last = range.end.0.min(graph.pangenome_len().0) - 1;
+        let nodes = graph.pos_range_nodes(range.start.0..last);
+
+        let nodes = nodes.start().ix()..=nodes.end().ix();
+        Self::from_nodes(graph, nodes.map(Node::from))
+    }
+
+    pub fn node_count(&self) -> u64 {
+        self.nodes.len()
+    }
+
+    /// Writes the subgraph as GFA 1, with the original segment IDs.
+    /// Each subpath is named `path:start-end`, with a 0-based,
+    /// half-open range in the path's coordinates. Overlaps aren't
+    /// kept, so links are written with `0M`.
+    pub fn write_gfa(
+        &self,
+        graph: &PathIndex,
+        mut out: impl Write,
+    ) -> std::io::Result<()> {
+        writeln!(out, "H\tVN:Z:1.0")?;
+
+        for ix in self.nodes.iter() {
+            let node = Node::from(ix);
+            let seq = graph.node_sequence(node);
+            write!(out, "S\t{}\t", graph.segment_id(node))?;
+            out.write_all(seq)?;
+            writeln!(out)?;
+        }
+
+        let orient = |reverse: bool| if reverse { '-' } else { '+' };
+
+        for edge in graph.edges_iter() {
+            let (from, to) = (edge.from, edge.to);
+            if !self.nodes.contains(from.node().ix() as u32)
+                || !self.nodes.contains(to.node().ix() as u32)
+            {
+                continue;
+            }
+
+            writeln!(
+                out,
+                "L\t{}\t{}\t{}\t{}\t0M",
+                graph.segment_id(from.node()),
+                orient(from.is_reverse()),
+                graph.segment_id(to.node()),
+                orient(to.is_reverse()),
+            )?;
+        }
+
+        for sub in self.paths.iter() {
+            let name = graph
+                .path_names
+                .get_by_left(&sub.path)
+                .map(|n| n.as_str())
+                .unwrap_or("path");
+
+            let steps = graph.path_steps[sub.path.ix()][sub.steps.clone()]
+                .iter()
+                .map(|step| {
+                    format!(
+                        "{}{}",
+                        graph.segment_id(step.node()),
+                        orient(step.is_reverse())
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+
+            writeln!(
+                out,
+                "P\t{name}:{}-{}\t{steps}\t*",
+                sub.range.start.0, sub.range.end.0
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subgraph_of_range() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tC
+S\t3\tG
+S\t4\tTT
+S\t5\tAAAA
+L\t1\t+\t2\t+\t0M
+L\t1\t+\t3\t+\t0M
+L\t2\t+\t4\t+\t0M
+L\t3\t+\t4\t+\t0M
+L\t4\t+\t5\t+\t0M
+P\tref\t1+,2+,4+,5+\t*
+P\talt\t1+,3+,4+,5+\t*
+";
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        // covers the ends of node 1 and 3, and so all of nodes 1-3
+        let sub = Subgraph::from_pangenome_range(&graph, Bp(3)..Bp(6));
+        assert_eq!(sub.node_count(), 3);
+
+        let ref_path = *graph.path_names.get_by_right("ref").unwrap();
+        let ref_sub = sub.paths.iter().find(|p| p.path == ref_path).unwrap();
+        assert_eq!(ref_sub.steps, 0..2);
+        assert_eq!(ref_sub.range, Bp(0)..Bp(5));
+
+        let mut out = Vec::new();
+        sub.write_gfa(&graph, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("S\t3\tG\n"));
+        assert!(out.contains("L\t1\t+\t3\t+\t0M\n"));
+        assert!(!out.contains("S\t4\t"));
+        assert!(out.contains("P\tref:0-5\t1+,2+\t*\n"));
+        assert!(out.contains("P\talt:0-5\t1+,3+\t*\n"));
+    }
+}
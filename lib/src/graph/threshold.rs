@@ -0,0 +1,114 @@
+//! Threshold-crossing events: the parts of a path where per-node data
+//! stays above a threshold, such as runs of high depth.
+
+use std::io::prelude::*;
+use std::ops::Range;
+
+use super::{Bp, PathId, PathIndex};
+
+/// The ranges of `path`, in path coordinates, where the value of each
+/// step is above `threshold`, merged across consecutive steps, and
+/// kept if at least `min_length` long.
+///
+/// `data` holds one value per node on the path, in node order, as
+/// the path data sources produce.
+pub fn threshold_intervals(
+    graph: &PathIndex,
+    path: PathId,
+    data: &[f32],
+    threshold: f32,
+    min_length: Bp,
+) -> Vec<Range<Bp>> {
+    let Some(steps) = graph.path_steps.get(path.ix()) else {
+        return Vec::new();
+    };
+
+    let mut intervals = Vec::new();
+    let mut current: Option<Range<u64>> = None;
+    let mut offset = 0u64;
+
+    for step in steps {
+        let node = step.node();
+        let len = graph.node_length(node).0;
+
+        let above = graph
+            .path_node_value(path, node, data)
+            .map(|&v| v > threshold)
+            .unwrap_or(false);
+
+        if above {
+            let range = current.get_or_insert(offset..offset);
+            range.end = offset + len;
+        } else if let Some(range) = current.take() {
+            intervals.push(range);
+        }
+
+        offset += len;
+    }
+
+    intervals.extend(current);
+
+    intervals
+        .into_iter()
+        .filter(|r| r.end - r.start >= min_length.0)
+        .map(|r| Bp(r.start)..Bp(r.end))
+        .collect()
+}
+
+/// Writes the intervals as BED4, with the path names as the
+/// sequence names and `name` in the name column.
+pub fn write_bed(
+    graph: &PathIndex,
+    intervals: &[(PathId, Range<Bp>)],
+    name: &str,
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    for (path, range) in intervals {
+        let Some(path_name) = graph.path_names.get_by_left(path) else {
+            continue;
+        };
+        writeln!(
+            out,
+            "{path_name}\t{}\t{}\t{name}",
+            range.start.0, range.end.0
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals_above_threshold() {
+        let gfa = "\
+S\t1\tAAAA
+S\t2\tAA
+S\t3\tAAAAAA
+S\t4\tA
+S\t5\tAAA
+P\tp\t1+,2+,3+,4+,5+\t*
+";
+        let graph =
+            PathIndex::from_gfa_reader(std::io::Cursor::new(gfa)).unwrap();
+
+        let path = *graph.path_names.get_by_right("p").unwrap();
+
+        // path positions: 1: 0-4, 2: 4-6, 3: 6-12, 4: 12-13, 5: 13-16
+        let data = [5.0, 1.0, 3.0, 4.0, 2.5];
+
+        let ivs = threshold_intervals(&graph, path, &data, 2.0, Bp(0));
+        assert_eq!(ivs, vec![Bp(0)..Bp(4), Bp(6)..Bp(16)]);
+
+        let ivs = threshold_intervals(&graph, path, &data, 2.0, Bp(5));
+        assert_eq!(ivs, vec![Bp(6)..Bp(16)]);
+
+        let ivs = threshold_intervals(&graph, path, &data, 4.0, Bp(0));
+        assert_eq!(ivs, vec![Bp(0)..Bp(4)]);
+
+        let mut bed = Vec::new();
+        write_bed(&graph, &[(path, Bp(6)..Bp(16))], "high", &mut bed).unwrap();
+        assert_eq!(String::from_utf8(bed).unwrap(), "p\t6\t16\thigh\n");
+    }
+}
@@ -1 +1,2 @@
-pub mod graph;
\ No newline at end of file
+pub mod export;
+pub mod graph;